@@ -0,0 +1,16 @@
+#version 450
+layout(location = 0) in vec2 v_tex_coords;
+layout(location = 1) in vec4 v_color;
+
+layout(location = 0) out vec4 f_color;
+
+// Glyph coverage in red channel
+layout(set = 0, binding = 0) uniform sampler2D font_atlas;
+
+void main() {
+    float coverage = texture(font_atlas, v_tex_coords).r;
+    if (coverage == 0.0) {
+        discard;
+    }
+    f_color = vec4(v_color.rgb, coverage * v_color.a);
+}
\ No newline at end of file
@@ -0,0 +1,89 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    collections::VecDeque,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static NUM_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// System allocator that counts heap allocations. Set it as `#[global_allocator]` in your app
+/// to see allocation counts when profiling
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        NUM_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        NUM_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        NUM_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Number of allocations (incl. reallocations) since start. Always 0 if `CountingAllocator`
+/// isn't the global allocator
+pub fn allocation_count() -> usize {
+    NUM_ALLOCATIONS.load(Ordering::Relaxed)
+}
+
+const NUM_ALLOCATION_SAMPLES: usize = 150;
+
+/// Like `PerformanceTimer`, but records allocation counts
+pub struct AllocationTracker {
+    count_at_start: usize,
+    data: VecDeque<usize>,
+}
+
+impl AllocationTracker {
+    pub fn new() -> Self {
+        Self {
+            count_at_start: allocation_count(),
+            data: VecDeque::new(),
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.count_at_start = allocation_count();
+    }
+
+    /// Allocations since `start`
+    pub fn end(&self) -> usize {
+        allocation_count() - self.count_at_start
+    }
+
+    pub fn record(&mut self) {
+        self.push(self.end());
+    }
+
+    pub fn push(&mut self, count: usize) {
+        self.data.push_back(count);
+        if self.data.len() >= NUM_ALLOCATION_SAMPLES {
+            self.data.pop_front();
+        }
+    }
+
+    pub fn average(&self) -> f64 {
+        self.data.iter().sum::<usize>() as f64 / self.data.len().max(1) as f64
+    }
+
+    pub fn latest(&self) -> usize {
+        self.data.back().copied().unwrap_or(0)
+    }
+}
+
+impl Default for AllocationTracker {
+    fn default() -> Self {
+        AllocationTracker::new()
+    }
+}
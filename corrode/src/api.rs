@@ -10,7 +10,7 @@ use rayon::{ThreadPool, ThreadPoolBuilder};
 use crate::{
     input_system::{InputButton, InputSystem},
     physics::PhysicsWorld,
-    renderer::{Camera2D, Renderer},
+    renderer::{Camera2D, PopOutWindows, Renderer},
     time::TimeTracker,
 };
 
@@ -20,6 +20,8 @@ pub struct EngineApi<I: Hash + Eq + Copy + 'static> {
     pub physics_world: PhysicsWorld,
     pub gui: Gui,
     pub renderer: Renderer,
+    /// Extra windows with only gui on them, see `PopOutWindows`
+    pub pop_outs: PopOutWindows,
     pub inputs: Vec<InputSystem<I>>,
     pub main_camera: Camera2D,
     pub time: TimeTracker,
@@ -48,6 +50,7 @@ impl<I: Hash + Eq + Copy + 'static> EngineApi<I> {
             physics_world: PhysicsWorld::new(),
             gui,
             renderer,
+            pop_outs: PopOutWindows::new(),
             inputs: input_systems,
             main_camera,
             time: public_time,
@@ -55,6 +58,15 @@ impl<I: Hash + Eq + Copy + 'static> EngineApi<I> {
         })
     }
 
+    /// Recreate renderer's device & gui after the device was lost. Gui textures are lost too, and
+    /// pop-out windows are closed
+    pub fn recreate_gpu(&mut self) -> Result<()> {
+        self.pop_outs.clear();
+        self.renderer.recreate_device()?;
+        self.gui = Gui::new(self.renderer.surface(), self.renderer.graphics_queue(), true);
+        Ok(())
+    }
+
     pub fn reset_world(&mut self) -> Result<()> {
         self.ecs_world = World::new();
         self.physics_world = PhysicsWorld::new();
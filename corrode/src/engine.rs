@@ -10,7 +10,12 @@ use winit::{
     platform::run_return::EventLoopExtRunReturn,
 };
 
-use crate::{api::EngineApi, input_system::InputButton, renderer::Renderer, time::TimeTracker};
+use crate::{
+    api::EngineApi,
+    input_system::InputButton,
+    renderer::{is_device_lost_error, Renderer},
+    time::TimeTracker,
+};
 
 #[derive(Debug, Copy, Clone)]
 pub struct DeviceOptions {
@@ -118,6 +123,10 @@ impl Corrode {
             let mut event_err = None;
             event_loop.run_return(|event, _, control_flow| {
                 *control_flow = ControlFlow::Wait;
+                // Pop-out window events only go to their own gui
+                if api.pop_outs.handle_event(&event) {
+                    return;
+                }
                 // Update gui
                 api.gui.update(&event);
 
@@ -176,15 +185,20 @@ impl Corrode {
             if !is_running {
                 break;
             }
-            application.update(api)?;
-            // Update fixed 60fps
-            if internal_time.dt_sum_fixed() >= 1000.0 / opts.fixed_update_fps {
-                application.fixed_update(api)?;
-                internal_time.reset_fixed();
-                api.time.reset_fixed();
+            let result = Self::update_and_render(&mut application, api, &opts, &mut internal_time);
+            api.pop_outs.apply_requests(&event_loop, &api.renderer);
+            if let Err(e) = result {
+                // Lost device is recreated below instead of quitting
+                if !is_device_lost_error(&e) {
+                    return Err(e);
+                }
+                error!("Gpu device lost: {:#}", e);
+                api.renderer.mark_device_lost();
+            }
+            if api.renderer.is_device_lost() {
+                api.recreate_gpu()?;
+                application.device_recreated(api)?;
             }
-            // Render
-            Corrode::render(&mut application, api, opts.render_options)?;
             // Reset inputs state after frame
             api.inputs.iter_mut().for_each(|i| i.reset());
 
@@ -197,6 +211,23 @@ impl Corrode {
         Ok(())
     }
 
+    fn update_and_render<S: Engine<I> + 'static, I: Hash + Eq + Copy + 'static>(
+        application: &mut S,
+        api: &mut EngineApi<I>,
+        opts: &EngineOptions,
+        internal_time: &mut TimeTracker,
+    ) -> Result<()> {
+        application.update(api)?;
+        // Update fixed 60fps
+        if internal_time.dt_sum_fixed() >= 1000.0 / opts.fixed_update_fps {
+            application.fixed_update(api)?;
+            internal_time.reset_fixed();
+            api.time.reset_fixed();
+        }
+        // Render
+        Corrode::render(application, api, opts.render_options)
+    }
+
     /// Render using `draw_passes_fn` for world rendering (on camera views)
     /// and `gui_pass_fn` for gui render on window
     fn render<S: Engine<I> + 'static, I: Hash + Eq + Copy + 'static>(
@@ -215,6 +246,7 @@ impl Corrode {
             let after_pipeline_future = app.render(before_pipeline_future, api)?;
             if opts.is_gui {
                 api.gui.begin_frame();
+                api.pop_outs.begin_frame();
                 app.gui_content(api)?;
                 api.gui
                     .draw_on_image(after_pipeline_future, api.renderer.final_image())
@@ -224,6 +256,9 @@ impl Corrode {
         };
         // Finish
         api.renderer.finish_frame(after_future);
+        if opts.is_gui {
+            api.pop_outs.draw(api.renderer.device())?;
+        }
         Ok(())
     }
 
@@ -270,7 +305,8 @@ pub trait Engine<I: Hash + Eq + Copy + 'static> {
         Ok(())
     }
     /// Fill your render pipeline here. This must return the Vulkano future representing the point
-    /// when your rendering finishes. `before_future` represents the end of last frame.
+    /// when your rendering finishes. `before_future` represents the end of last frame & compute
+    /// submitted with `Renderer::submit_compute` or `Renderer::join_frame_future` during update.
     fn render<F>(
         &mut self,
         before_future: F,
@@ -281,6 +317,11 @@ pub trait Engine<I: Hash + Eq + Copy + 'static> {
     {
         Ok(before_future.boxed())
     }
+    /// Run after the gpu device was lost (e.g. driver reset) & recreated. Recreate everything
+    /// created from the old device here, e.g. buffers, pipelines & gui textures
+    fn device_recreated(&mut self, _api: &mut EngineApi<I>) -> Result<()> {
+        Ok(())
+    }
     /// Run each frame after everyting else
     fn end_of_frame(&mut self, _api: &mut EngineApi<I>) -> Result<()> {
         Ok(())
@@ -8,6 +8,7 @@
 #[macro_use]
 extern crate log;
 
+pub mod alloc_counter;
 pub mod api;
 pub mod engine;
 pub mod input_system;
@@ -2,6 +2,32 @@ use cgmath::Vector2;
 use rapier2d::prelude::*;
 use rayon::ThreadPool;
 
+/// Collider user data flag for one-way platforms. Contacts with them are kept only when the other
+/// collider comes from the platform's local up side, so objects can jump through from below
+pub const ONE_WAY_PLATFORM: u128 = 1;
+/// Max angle between contact normal & platform's up for the contact to be kept
+const ONE_WAY_PLATFORM_ALLOWED_ANGLE: f32 = 0.1;
+
+/// Physics hooks for colliders flagged with `ONE_WAY_PLATFORM`. Such colliders need
+/// `ActiveHooks::MODIFY_SOLVER_CONTACTS` set
+struct OneWayPlatformHooks;
+
+impl PhysicsHooks for OneWayPlatformHooks {
+    fn modify_solver_contacts(&self, context: &mut ContactModificationContext) {
+        let is_platform = |handle: ColliderHandle| {
+            context.colliders[handle].user_data & ONE_WAY_PLATFORM != 0
+        };
+        let allowed_local_n1 = if is_platform(context.collider1) {
+            Vector::y()
+        } else if is_platform(context.collider2) {
+            -Vector::y()
+        } else {
+            return;
+        };
+        context.update_as_oneway_platform(&allowed_local_n1, ONE_WAY_PLATFORM_ALLOWED_ANGLE);
+    }
+}
+
 pub struct Physics {
     pub bodies: RigidBodySet,
     pub colliders: ColliderSet,
@@ -103,7 +129,7 @@ impl PhysicsWorld {
             joints,
             multibody_joints,
             ccd_solver,
-            &(),
+            &OneWayPlatformHooks,
             event_handler,
         );
         // });
@@ -134,6 +160,28 @@ impl PhysicsWorld {
         );
     }
 
+    /// Join two bodies with a joint, e.g. a revolute joint acting as a hinge between them
+    pub fn insert_joint(
+        &mut self,
+        rb1: RigidBodyHandle,
+        rb2: RigidBodyHandle,
+        joint: impl Into<GenericJoint>,
+    ) -> ImpulseJointHandle {
+        self.physics.joints.insert(rb1, rb2, joint)
+    }
+
+    /// Remove a joint, waking up the bodies it joined. Joints of removed bodies are removed with
+    /// them, see `remove_physics`
+    pub fn remove_joint(&mut self, joint: ImpulseJointHandle) {
+        let Physics {
+            bodies,
+            island_manager,
+            joints,
+            ..
+        } = &mut self.physics;
+        joints.remove(joint, island_manager, bodies, true);
+    }
+
     pub fn rigid_body_at_pos(&self, world_pos: Vector2<f32>) -> Option<&RigidBody> {
         let Physics {
             colliders,
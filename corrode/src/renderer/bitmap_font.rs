@@ -0,0 +1,171 @@
+use cgmath::Vector2;
+
+use crate::renderer::TextVertex;
+
+pub const GLYPH_WIDTH: u32 = 5;
+pub const GLYPH_HEIGHT: u32 = 7;
+/// Empty atlas column after each glyph, so nearest sampling at glyph edges doesn't bleed
+const GLYPH_PADDING: u32 = 1;
+/// Empty glyph rows between lines of text
+const LINE_GAP: u32 = 2;
+
+/// 5x7 glyph rows from top to bottom, bit 4 being the leftmost column. Lowercase letters are drawn
+/// as uppercase
+const GLYPHS: [(char, [u8; 7]); 62] = [
+    ('A', [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('B', [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E]),
+    ('C', [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E]),
+    ('D', [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C]),
+    ('E', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F]),
+    ('F', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10]),
+    ('G', [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F]),
+    ('H', [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('I', [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C]),
+    ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+    ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F]),
+    ('M', [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('P', [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10]),
+    ('Q', [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D]),
+    ('R', [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11]),
+    ('S', [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E]),
+    ('T', [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A]),
+    ('X', [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11]),
+    ('Y', [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04]),
+    ('Z', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F]),
+    ('0', [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E]),
+    ('1', [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('2', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F]),
+    ('3', [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E]),
+    ('4', [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02]),
+    ('5', [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E]),
+    ('6', [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E]),
+    ('7', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E]),
+    ('9', [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C]),
+    ('.', [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C]),
+    (',', [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08]),
+    (':', [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00]),
+    (';', [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x04, 0x08]),
+    ('!', [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04]),
+    ('?', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04]),
+    ('\'', [0x0C, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00]),
+    ('"', [0x0A, 0x0A, 0x0A, 0x00, 0x00, 0x00, 0x00]),
+    ('-', [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00]),
+    ('+', [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00]),
+    ('=', [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00]),
+    ('/', [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00]),
+    ('(', [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02]),
+    (')', [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08]),
+    ('[', [0x0E, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0E]),
+    (']', [0x0E, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0E]),
+    ('<', [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02]),
+    ('>', [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08]),
+    ('_', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F]),
+    ('#', [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A]),
+    ('%', [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03]),
+    ('*', [0x00, 0x04, 0x15, 0x0E, 0x15, 0x04, 0x00]),
+    ('&', [0x0C, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0D]),
+    ('@', [0x0E, 0x11, 0x17, 0x15, 0x17, 0x10, 0x0E]),
+    ('$', [0x04, 0x0F, 0x14, 0x0E, 0x05, 0x1E, 0x04]),
+    // Drawn for characters the font lacks
+    ('\u{FFFD}', [0x1F, 0x11, 0x11, 0x11, 0x11, 0x11, 0x1F]),
+];
+
+/// Glyph's index in `GLYPHS` & the font atlas, None for whitespace
+fn glyph_index(c: char) -> Option<usize> {
+    if c.is_whitespace() {
+        return None;
+    }
+    let c = c.to_ascii_uppercase();
+    Some(
+        GLYPHS
+            .iter()
+            .position(|(glyph, _)| *glyph == c)
+            .unwrap_or(GLYPHS.len() - 1),
+    )
+}
+
+/// Single row atlas of all glyphs, one coverage byte per pixel (R8 format). Returns its size & data
+pub fn font_atlas() -> ([u32; 2], Vec<u8>) {
+    let cell_width = GLYPH_WIDTH + GLYPH_PADDING;
+    let width = GLYPHS.len() as u32 * cell_width;
+    let mut data = vec![0; (width * GLYPH_HEIGHT) as usize];
+    for (i, (_, rows)) in GLYPHS.iter().enumerate() {
+        for (y, row) in rows.iter().enumerate() {
+            for x in 0..GLYPH_WIDTH {
+                if row & (1 << (GLYPH_WIDTH - 1 - x)) != 0 {
+                    let atlas_x = i as u32 * cell_width + x;
+                    data[y * width as usize + atlas_x as usize] = 255;
+                }
+            }
+        }
+    }
+    ([width, GLYPH_HEIGHT], data)
+}
+
+/// World size of `text` drawn with glyphs `height` tall
+pub fn text_size(text: &str, height: f32) -> Vector2<f32> {
+    let scale = height / GLYPH_HEIGHT as f32;
+    let columns = text
+        .lines()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0) as f32;
+    let rows = text.lines().count().max(1) as f32;
+    Vector2::new(
+        (columns * (GLYPH_WIDTH + GLYPH_PADDING) as f32 - GLYPH_PADDING as f32).max(0.0) * scale,
+        (rows * (GLYPH_HEIGHT + LINE_GAP) as f32 - LINE_GAP as f32) * scale,
+    )
+}
+
+/// Quads of `text` textured from `font_atlas`, with `pos` at the top left of the first line. Lines
+/// continue downwards (world y is up)
+pub fn text_vertices(
+    text: &str,
+    pos: Vector2<f32>,
+    height: f32,
+    color: [f32; 4],
+) -> (Vec<TextVertex>, Vec<u32>) {
+    let scale = height / GLYPH_HEIGHT as f32;
+    let width = GLYPH_WIDTH as f32 * scale;
+    let advance = (GLYPH_WIDTH + GLYPH_PADDING) as f32 * scale;
+    let line_advance = (GLYPH_HEIGHT + LINE_GAP) as f32 * scale;
+    let atlas_cell = 1.0 / GLYPHS.len() as f32;
+    let atlas_glyph = atlas_cell * GLYPH_WIDTH as f32 / (GLYPH_WIDTH + GLYPH_PADDING) as f32;
+    let mut vertices = vec![];
+    let mut indices = vec![];
+    for (row, line) in text.lines().enumerate() {
+        let top = pos.y - row as f32 * line_advance;
+        for (column, c) in line.chars().enumerate() {
+            let index = match glyph_index(c) {
+                Some(index) => index,
+                None => continue,
+            };
+            let left = pos.x + column as f32 * advance;
+            let u = index as f32 * atlas_cell;
+            let first = vertices.len() as u32;
+            // Bottom left, bottom right, top right, top left. Atlas rows go from top to bottom
+            for (x, y, tex_coords) in [
+                (left, top - height, [u, 1.0]),
+                (left + width, top - height, [u + atlas_glyph, 1.0]),
+                (left + width, top, [u + atlas_glyph, 0.0]),
+                (left, top, [u, 0.0]),
+            ] {
+                vertices.push(TextVertex {
+                    position: [x, y],
+                    normal: [0.0, 0.0],
+                    tex_coords,
+                    color,
+                });
+            }
+            indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+        }
+    }
+    (vertices, indices)
+}
@@ -1,4 +1,4 @@
-use cgmath::{Matrix4, Point3, SquareMatrix, Transform, Vector2, Vector3};
+use cgmath::{Matrix2, Matrix4, Point3, Rad, SquareMatrix, Transform, Vector2, Vector3};
 
 // c1r1: y flipped for vulkan
 #[rustfmt::skip]
@@ -16,6 +16,8 @@ pub struct Camera2D {
     near: f32,
     far: f32,
     zoom: f32,
+    /// Counter clockwise rotation of view in radians
+    rotation: f32,
 }
 
 impl Camera2D {
@@ -26,6 +28,7 @@ impl Camera2D {
             near: 0.001,
             far: 10000.0,
             zoom,
+            rotation: 0.0,
         }
     }
 
@@ -85,6 +88,25 @@ impl Camera2D {
         self.zoom
     }
 
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    /// Sets view rotation in radians, wrapped to [0.0, 2.0 * PI)
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.rotation = rotation.rem_euclid(std::f32::consts::TAU);
+    }
+
+    /// Rotates view counter clockwise by angle in radians
+    pub fn rotate(&mut self, angle: f32) {
+        self.set_rotation(self.rotation + angle);
+    }
+
+    /// Converts a direction on screen (y up) to world direction, e.g. for panning a rotated view
+    pub fn screen_dir_to_world(&self, dir: Vector2<f32>) -> Vector2<f32> {
+        Matrix2::from_angle(Rad(self.rotation)) * dir
+    }
+
     /// Updates camera position
     pub fn set_pos(&mut self, world_pos: Vector2<f32>) {
         self.pos = world_pos;
@@ -100,7 +122,7 @@ impl Camera2D {
         Matrix4::look_to_rh(
             Point3::new(self.pos.x, self.pos.y, 1.0),
             Vector3::new(0.0, 0.0, -1.0),
-            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(-self.rotation.sin(), self.rotation.cos(), 0.0),
         )
     }
 
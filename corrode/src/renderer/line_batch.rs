@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use anyhow::*;
+use vulkano::{buffer::CpuAccessibleBuffer, device::Device};
+
+use crate::renderer::{line_vertices, textured_vertex_cpu_buffers_with_indices, Line, TextVertex};
+
+pub type LineBuffers = (
+    Arc<CpuAccessibleBuffer<[TextVertex]>>,
+    Arc<CpuAccessibleBuffer<[u32]>>,
+);
+
+/// Lines that are uploaded once and redrawn every frame until their source version changes.
+/// Useful for large amounts of mostly static debug lines such as boundary outlines
+#[derive(Default)]
+pub struct LineBatch {
+    version: Option<u64>,
+    buffers: Option<LineBuffers>,
+}
+
+impl LineBatch {
+    pub fn new() -> LineBatch {
+        LineBatch::default()
+    }
+
+    /// Whether uploaded lines were created from given version of their source
+    pub fn is_current(&self, version: u64) -> bool {
+        self.version == Some(version)
+    }
+
+    pub fn invalidate(&mut self) {
+        self.version = None;
+    }
+
+    /// Replace batch's lines, marking them as created from `version`
+    pub fn upload(&mut self, device: &Arc<Device>, lines: &[Line], version: u64) -> Result<()> {
+        self.buffers = if lines.is_empty() {
+            None
+        } else {
+            let (vertices, indices) = line_vertices(lines);
+            Some(textured_vertex_cpu_buffers_with_indices(device, vertices, indices, false)?)
+        };
+        self.version = Some(version);
+        Ok(())
+    }
+
+    pub fn buffers(&self) -> Option<&LineBuffers> {
+        self.buffers.as_ref()
+    }
+}
@@ -1,13 +1,19 @@
+pub use bitmap_font::*;
 pub use camera::*;
 pub use cpu_buffers::*;
+pub use line_batch::*;
 pub use mesh::*;
+pub use pop_out_window::*;
 pub use renderer::*;
 pub use vertices::*;
 
+mod bitmap_font;
 mod camera;
 mod cpu_buffers;
+mod line_batch;
 mod mesh;
 pub mod pipelines;
+mod pop_out_window;
 pub mod render_pass;
 mod renderer;
 mod vertices;
@@ -17,7 +17,7 @@ use vulkano::{
         GraphicsPipeline, Pipeline, PipelineBindPoint,
     },
     render_pass::Subpass,
-    sampler::SamplerAddressMode,
+    sampler::{Filter, SamplerAddressMode},
 };
 
 use crate::renderer::{
@@ -89,6 +89,7 @@ impl FullFrameImagePipeline {
     fn create_descriptor_set(
         &self,
         image: Arc<dyn ImageViewAbstract + 'static>,
+        filter: Filter,
     ) -> Result<Arc<PersistentDescriptorSet>> {
         let layout = self
             .pipeline
@@ -101,6 +102,7 @@ impl FullFrameImagePipeline {
             layout,
             image,
             SamplerAddressMode::Repeat,
+            filter,
         )
     }
 
@@ -110,6 +112,7 @@ impl FullFrameImagePipeline {
         image: Arc<dyn ImageViewAbstract + 'static>,
         is_alpha: bool,
         invert_y: bool,
+        filter: Filter,
     ) -> Result<SecondaryAutoCommandBuffer> {
         let pipeline = if is_alpha {
             self.pipeline_alpha.clone()
@@ -118,7 +121,7 @@ impl FullFrameImagePipeline {
         };
         let mut builder =
             command_buffer_builder(self.gfx_queue.clone(), pipeline.subpass().clone())?;
-        let desc_set = self.create_descriptor_set(image)?;
+        let desc_set = self.create_descriptor_set(image, filter)?;
         let index_count = self.indices.len() as u32;
         let push_constants = vs::ty::PushConstants {
             invert_y: invert_y as i32,
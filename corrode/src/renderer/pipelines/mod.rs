@@ -5,6 +5,7 @@ pub use basic_draw_pipeline::*;
 pub use circle_draw_pipeline::*;
 pub use full_frame_image_draw_pipeline::*;
 pub use line_draw_pipeline::*;
+pub use text_draw_pipeline::*;
 pub use texture_draw_pipeline::*;
 use vulkano::{
     command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, SecondaryAutoCommandBuffer},
@@ -12,7 +13,7 @@ use vulkano::{
     device::Queue,
     image::ImageViewAbstract,
     render_pass::Subpass,
-    sampler::{Filter, Sampler, SamplerAddressMode, SamplerMipmapMode},
+    sampler::{Filter, Sampler, SamplerAddressMode, SamplerMipmapMode, LOD_CLAMP_NONE},
 };
 pub use wireframe_draw_pipeline::*;
 
@@ -20,6 +21,7 @@ mod basic_draw_pipeline;
 mod circle_draw_pipeline;
 mod full_frame_image_draw_pipeline;
 mod line_draw_pipeline;
+mod text_draw_pipeline;
 mod texture_draw_pipeline;
 mod wireframe_draw_pipeline;
 
@@ -36,20 +38,22 @@ pub fn command_buffer_builder(
     Ok(builder)
 }
 
-/// Creates a descriptor set for images with nearest mipmap mode (pixel perfect)
+/// Creates a descriptor set for images. Nearest filter is pixel perfect. Images with mipmaps (see
+/// `create_mipmapped_image`) blend between levels when drawn smaller, others only have level 0
 #[allow(unused)]
 pub fn sampled_image_desc_set(
     gfx_queue: Arc<Queue>,
     layout: &Arc<DescriptorSetLayout>,
     image: Arc<dyn ImageViewAbstract + 'static>,
     sampler_mode: SamplerAddressMode,
+    filter: Filter,
 ) -> Result<Arc<PersistentDescriptorSet>> {
     let sampler_builder = Sampler::start(gfx_queue.device().clone())
-        .filter(Filter::Nearest)
+        .filter(filter)
         .address_mode(sampler_mode)
-        .mipmap_mode(SamplerMipmapMode::Nearest)
+        .mipmap_mode(SamplerMipmapMode::Linear)
         .mip_lod_bias(0.0)
-        .lod(0.0..=0.0);
+        .lod(0.0..=LOD_CLAMP_NONE);
     let sampler = sampler_builder.build()?;
     Ok(PersistentDescriptorSet::new(layout.clone(), [
         WriteDescriptorSet::image_view_sampler(0, image.clone(), sampler),
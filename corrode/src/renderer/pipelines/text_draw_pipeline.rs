@@ -0,0 +1,154 @@
+use std::sync::Arc;
+
+use anyhow::*;
+use cgmath::Vector2;
+use vulkano::{
+    buffer::TypedBufferAccess,
+    command_buffer::SecondaryAutoCommandBuffer,
+    descriptor_set::PersistentDescriptorSet,
+    device::Queue,
+    format::Format,
+    image::{view::ImageView, ImageDimensions, ImmutableImage, MipmapsCount},
+    pipeline::{
+        graphics::{
+            color_blend::ColorBlendState,
+            input_assembly::InputAssemblyState,
+            vertex_input::BuffersDefinition,
+            viewport::{Viewport, ViewportState},
+        },
+        GraphicsPipeline, Pipeline, PipelineBindPoint,
+    },
+    render_pass::Subpass,
+    sampler::{Filter, SamplerAddressMode},
+    sync::GpuFuture,
+};
+
+use crate::renderer::{
+    font_atlas,
+    pipelines::{command_buffer_builder, sampled_image_desc_set},
+    text_vertices, textured_vertex_cpu_buffers_with_indices, TextVertex,
+};
+
+/// Draws text in world space with the built in bitmap font, see `text_vertices`
+pub struct TextDrawPipeline {
+    gfx_queue: Arc<Queue>,
+    pipeline: Arc<GraphicsPipeline>,
+    font_desc_set: Arc<PersistentDescriptorSet>,
+}
+
+impl TextDrawPipeline {
+    pub fn new(gfx_queue: Arc<Queue>, subpass: Subpass) -> Result<TextDrawPipeline> {
+        let pipeline = {
+            let vs =
+                vs::load(gfx_queue.device().clone()).context("failed to create shader module")?;
+            let fs =
+                fs::load(gfx_queue.device().clone()).context("failed to create shader module")?;
+
+            GraphicsPipeline::start()
+                .vertex_input_state(BuffersDefinition::new().vertex::<TextVertex>())
+                .vertex_shader(vs.entry_point("main").unwrap(), ())
+                .fragment_shader(fs.entry_point("main").unwrap(), ())
+                .input_assembly_state(InputAssemblyState::new())
+                .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+                .color_blend_state(ColorBlendState::new(1).blend_alpha())
+                .render_pass(subpass)
+                .build(gfx_queue.device().clone())?
+        };
+        // Atlas is tiny, so it's uploaded once here. No mipmaps, they'd blur glyphs into each other
+        let ([width, height], data) = font_atlas();
+        let (image, future) = ImmutableImage::from_iter(
+            data,
+            ImageDimensions::Dim2d {
+                width,
+                height,
+                array_layers: 1,
+            },
+            MipmapsCount::One,
+            Format::R8_UNORM,
+            gfx_queue.clone(),
+        )?;
+        future.then_signal_fence_and_flush()?.wait(None)?;
+        let layout = pipeline.layout().descriptor_set_layouts().get(0).unwrap();
+        let font_desc_set = sampled_image_desc_set(
+            gfx_queue.clone(),
+            layout,
+            ImageView::new(image)?,
+            SamplerAddressMode::ClampToEdge,
+            Filter::Nearest,
+        )?;
+        Ok(TextDrawPipeline {
+            gfx_queue,
+            pipeline,
+            font_desc_set,
+        })
+    }
+
+    /// Draw texts in one draw call. Each is (text, pos at the top left of its first line, glyph
+    /// height, color)
+    pub fn draw(
+        &mut self,
+        viewport_dimensions: [u32; 2],
+        world_to_screen: cgmath::Matrix4<f32>,
+        texts: &[(&str, Vector2<f32>, f32, [f32; 4])],
+    ) -> Result<SecondaryAutoCommandBuffer> {
+        let mut vertices = vec![];
+        let mut indices = vec![];
+        for &(text, pos, height, color) in texts {
+            let (glyph_vertices, glyph_indices) = text_vertices(text, pos, height, color);
+            let first = vertices.len() as u32;
+            indices.extend(glyph_indices.into_iter().map(|index| first + index));
+            vertices.extend(glyph_vertices);
+        }
+        let push_constants = vs::ty::PushConstants {
+            world_to_screen: world_to_screen.into(),
+        };
+        let mut builder =
+            command_buffer_builder(self.gfx_queue.clone(), self.pipeline.subpass().clone())?;
+        // Whitespace only texts have nothing to draw, but an empty buffer can't be created
+        if !indices.is_empty() {
+            let (vertices, indices) = textured_vertex_cpu_buffers_with_indices(
+                self.gfx_queue.device(),
+                vertices,
+                indices,
+                false,
+            )?;
+            let index_count = indices.len() as u32;
+            builder
+                .bind_pipeline_graphics(self.pipeline.clone())
+                .set_viewport(0, vec![Viewport {
+                    origin: [0.0, 0.0],
+                    dimensions: [viewport_dimensions[0] as f32, viewport_dimensions[1] as f32],
+                    depth_range: 0.0..1.0,
+                }])
+                .bind_descriptor_sets(
+                    PipelineBindPoint::Graphics,
+                    self.pipeline.layout().clone(),
+                    0,
+                    self.font_desc_set.clone(),
+                )
+                .bind_vertex_buffers(0, vertices)
+                .bind_index_buffer(indices)
+                .push_constants(self.pipeline.layout().clone(), 0, push_constants)
+                .draw_indexed(index_count, 1, 0, 0, 0)
+                .unwrap();
+        }
+        let command_buffer = builder.build()?;
+        Ok(command_buffer)
+    }
+}
+
+#[allow(deprecated)]
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "shaders/text_vert.glsl"
+    }
+}
+
+#[allow(deprecated)]
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "shaders/text_frag.glsl"
+    }
+}
@@ -18,7 +18,7 @@ use vulkano::{
         GraphicsPipeline, Pipeline, PipelineBindPoint,
     },
     render_pass::Subpass,
-    sampler::SamplerAddressMode,
+    sampler::{Filter, SamplerAddressMode},
 };
 
 use crate::renderer::{
@@ -88,6 +88,7 @@ impl TextureDrawPipeline {
     fn create_descriptor_set(
         &self,
         image: Arc<dyn ImageViewAbstract + 'static>,
+        filter: Filter,
     ) -> Result<Arc<PersistentDescriptorSet>> {
         let layout = self
             .pipeline
@@ -100,6 +101,7 @@ impl TextureDrawPipeline {
             layout,
             image,
             SamplerAddressMode::ClampToEdge,
+            filter,
         )
     }
 
@@ -114,6 +116,7 @@ impl TextureDrawPipeline {
         image: Arc<dyn ImageViewAbstract + 'static>,
         invert_y: bool,
         alpha: bool,
+        filter: Filter,
     ) -> Result<SecondaryAutoCommandBuffer> {
         let push_constants = vs::ty::PushConstants {
             world_to_screen: world_to_screen.into(),
@@ -124,7 +127,7 @@ impl TextureDrawPipeline {
         };
         let mut builder =
             command_buffer_builder(self.gfx_queue.clone(), self.pipeline.subpass().clone())?;
-        let desc_set = self.create_descriptor_set(image)?;
+        let desc_set = self.create_descriptor_set(image, filter)?;
         let pipeline = if alpha {
             self.pipeline_alpha.clone()
         } else {
@@ -0,0 +1,266 @@
+use core::result::Result::Ok;
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::*;
+use egui_winit_vulkano::Gui;
+use vulkano::{
+    device::{Device, Queue},
+    image::view::ImageView,
+    swapchain,
+    swapchain::{AcquireError, Swapchain, SwapchainCreationError},
+    sync,
+    sync::{FlushError, GpuFuture},
+};
+use vulkano_win::create_vk_surface;
+use winit::{
+    dpi::LogicalSize,
+    event::{Event, WindowEvent},
+    event_loop::EventLoopWindowTarget,
+    window::{Window, WindowBuilder, WindowId},
+};
+
+use crate::renderer::{FinalImageView, Renderer};
+
+/// Extra OS window with its own swapchain & gui, e.g. for tool panels on a second monitor. Only
+/// gui is drawn on it. Gui textures are per window, so register them to `gui` as well
+pub struct PopOutWindow {
+    queue: Arc<Queue>,
+    swap_chain: Arc<Swapchain<Window>>,
+    final_views: Vec<FinalImageView>,
+    recreate_swapchain: bool,
+    previous_frame_end: Option<Box<dyn GpuFuture>>,
+    pub gui: Gui,
+}
+
+impl PopOutWindow {
+    fn new<E>(
+        event_loop: &EventLoopWindowTarget<E>,
+        renderer: &Renderer,
+        title: &str,
+        size: [u32; 2],
+    ) -> Result<PopOutWindow> {
+        let window = WindowBuilder::new()
+            .with_inner_size(LogicalSize::new(size[0], size[1]))
+            .with_title(title)
+            .build(event_loop)?;
+        let surface = create_vk_surface(window, renderer.device().instance().clone())?;
+        if !surface.is_supported(renderer.graphics_queue().family())? {
+            bail!("Graphics queue can't present to a new window");
+        }
+        let (swap_chain, final_views) = Renderer::create_swap_chain(
+            surface.clone(),
+            renderer.physical_device(),
+            renderer.device(),
+            renderer.graphics_queue(),
+            renderer.present_mode(),
+        )?;
+        // Nothing else is drawn on the window, thus gui clears it
+        let gui = Gui::new(surface, renderer.graphics_queue(), false);
+        Ok(PopOutWindow {
+            queue: renderer.graphics_queue(),
+            swap_chain,
+            final_views,
+            recreate_swapchain: false,
+            previous_frame_end: Some(sync::now(renderer.device()).boxed()),
+            gui,
+        })
+    }
+
+    pub fn window(&self) -> &Window {
+        self.swap_chain.surface().window()
+    }
+
+    /// Draw gui of this frame & present it
+    fn draw(&mut self, device: Arc<Device>) -> Result<()> {
+        if let Some(future) = self.previous_frame_end.as_mut() {
+            future.cleanup_finished();
+        }
+        if self.recreate_swapchain {
+            self.recreate_swapchain()?;
+        }
+        let (image_num, suboptimal, acquire_future) =
+            match swapchain::acquire_next_image(self.swap_chain.clone(), None) {
+                Ok(r) => r,
+                Err(AcquireError::OutOfDate) => {
+                    self.recreate_swapchain = true;
+                    return Ok(());
+                }
+                Err(e) => return Err(anyhow!(e)),
+            };
+        if suboptimal {
+            self.recreate_swapchain = true;
+        }
+        let before_future = self
+            .previous_frame_end
+            .take()
+            .unwrap_or_else(|| sync::now(device.clone()).boxed())
+            .join(acquire_future);
+        let after_future = self
+            .gui
+            .draw_on_image(before_future, self.final_views[image_num].clone());
+        let future = after_future
+            .then_swapchain_present(self.queue.clone(), self.swap_chain.clone(), image_num)
+            .then_signal_fence_and_flush();
+        match future {
+            Ok(future) => self.previous_frame_end = Some(future.boxed()),
+            Err(FlushError::OutOfDate) => {
+                self.recreate_swapchain = true;
+                self.previous_frame_end = Some(sync::now(device).boxed());
+            }
+            Err(e) => {
+                self.previous_frame_end = Some(sync::now(device).boxed());
+                return Err(anyhow!(e));
+            }
+        }
+        Ok(())
+    }
+
+    fn recreate_swapchain(&mut self) -> Result<()> {
+        let dimensions: [u32; 2] = self.window().inner_size().into();
+        let (new_swapchain, new_images) =
+            match self.swap_chain.recreate().dimensions(dimensions).build() {
+                Ok(r) => r,
+                // E.g. minimized
+                Err(SwapchainCreationError::UnsupportedDimensions) => return Ok(()),
+                Err(e) => return Err(anyhow!(e)),
+            };
+        self.swap_chain = new_swapchain;
+        self.final_views = new_images
+            .into_iter()
+            .map(|image| ImageView::new(image).unwrap())
+            .collect::<Vec<_>>();
+        self.recreate_swapchain = false;
+        Ok(())
+    }
+}
+
+/// Pop-out windows by key. Windows are opened & closed after the frame, because creating a
+/// window needs the event loop. A window closed by its user is gone from here after the frame
+pub struct PopOutWindows {
+    windows: HashMap<String, PopOutWindow>,
+    /// Key, title & logical size of windows to open
+    to_open: Vec<(String, String, [u32; 2])>,
+    to_close: Vec<String>,
+}
+
+impl PopOutWindows {
+    pub fn new() -> PopOutWindows {
+        PopOutWindows {
+            windows: HashMap::new(),
+            to_open: vec![],
+            to_close: vec![],
+        }
+    }
+
+    /// Open a window after this frame, unless one with `key` is open already
+    pub fn open(&mut self, key: &str, title: &str, size: [u32; 2]) {
+        if !self.is_open(key) {
+            self.to_open
+                .push((key.to_string(), title.to_string(), size));
+        }
+    }
+
+    /// Close window after this frame
+    pub fn close(&mut self, key: &str) {
+        self.to_open.retain(|(k, ..)| k != key);
+        self.to_close.push(key.to_string());
+    }
+
+    /// Whether window is open or opening after this frame
+    pub fn is_open(&self, key: &str) -> bool {
+        (self.windows.contains_key(key) || self.to_open.iter().any(|(k, ..)| k == key))
+            && !self.to_close.iter().any(|k| k == key)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut PopOutWindow> {
+        self.windows.get_mut(key)
+    }
+
+    /// Give event to the gui of its pop-out window. Returns whether the event was a pop-out window
+    /// event, which the main window should ignore
+    pub(crate) fn handle_event<E>(&mut self, event: &Event<E>) -> bool {
+        let window_id = match event {
+            Event::WindowEvent {
+                window_id, ..
+            } => *window_id,
+            _ => return false,
+        };
+        let key = match self.key_of(window_id) {
+            Some(key) => key,
+            None => return false,
+        };
+        let window = self.windows.get_mut(&key).unwrap();
+        window.gui.update(event);
+        if let Event::WindowEvent {
+            event, ..
+        } = event
+        {
+            match event {
+                WindowEvent::CloseRequested => self.to_close.push(key),
+                WindowEvent::Resized(..)
+                | WindowEvent::ScaleFactorChanged {
+                    ..
+                } => window.recreate_swapchain = true,
+                _ => (),
+            }
+        }
+        true
+    }
+
+    fn key_of(&self, window_id: WindowId) -> Option<String> {
+        self.windows
+            .iter()
+            .find(|(_, window)| window.window().id() == window_id)
+            .map(|(key, _)| key.clone())
+    }
+
+    pub(crate) fn begin_frame(&mut self) {
+        for window in self.windows.values_mut() {
+            window.gui.begin_frame();
+        }
+    }
+
+    pub(crate) fn draw(&mut self, device: Arc<Device>) -> Result<()> {
+        for window in self.windows.values_mut() {
+            // Nothing to draw on minimized windows
+            let size = window.window().inner_size();
+            if size.width == 0 || size.height == 0 {
+                continue;
+            }
+            window.draw(device.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Open & close requested windows. Windows that fail to open are logged & skipped
+    pub(crate) fn apply_requests<E>(
+        &mut self,
+        event_loop: &EventLoopWindowTarget<E>,
+        renderer: &Renderer,
+    ) {
+        for key in self.to_close.drain(..) {
+            self.windows.remove(&key);
+        }
+        for (key, title, size) in std::mem::take(&mut self.to_open) {
+            match PopOutWindow::new(event_loop, renderer, &title, size) {
+                Ok(window) => {
+                    self.windows.insert(key, window);
+                }
+                Err(e) => error!("Failed to open window {}: {:#}", title, e),
+            }
+        }
+    }
+
+    /// Close all windows, e.g. when their device was lost
+    pub(crate) fn clear(&mut self) {
+        self.windows.clear();
+        self.to_open.clear();
+        self.to_close.clear();
+    }
+}
+
+impl Default for PopOutWindows {
+    fn default() -> Self {
+        PopOutWindows::new()
+    }
+}
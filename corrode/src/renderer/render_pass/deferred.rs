@@ -13,16 +13,17 @@ use vulkano::{
     image::{ImageAccess, ImageViewAbstract},
     pipeline::graphics::input_assembly::Index,
     render_pass::{Framebuffer, RenderPass, Subpass},
+    sampler::Filter,
     sync::GpuFuture,
 };
 
 use crate::renderer::{
     line_vertices,
     pipelines::{
-        BasicDrawPipeline, CircleDrawPipeline, LineDrawPipeline, TextureDrawPipeline,
-        WireframeDrawPipeline,
+        BasicDrawPipeline, CircleDrawPipeline, LineDrawPipeline, TextDrawPipeline,
+        TextureDrawPipeline, WireframeDrawPipeline,
     },
-    textured_vertex_cpu_buffers_with_indices, Camera2D, Line, Mesh,
+    textured_vertex_cpu_buffers_with_indices, Camera2D, Line, LineBatch, Mesh,
 };
 
 pub struct Pipelines {
@@ -32,6 +33,7 @@ pub struct Pipelines {
     wireframe: WireframeDrawPipeline,
     basic: BasicDrawPipeline,
     circle: CircleDrawPipeline,
+    text: TextDrawPipeline,
 }
 
 /// System that contains the necessary facilities for rendering a single frame.
@@ -69,7 +71,8 @@ impl RenderPassDeferred {
             texture: TextureDrawPipeline::new(gfx_queue.clone(), deferred_subpass.clone())?,
             wireframe: WireframeDrawPipeline::new(gfx_queue.clone(), deferred_subpass.clone())?,
             basic: BasicDrawPipeline::new(gfx_queue.clone(), deferred_subpass.clone())?,
-            circle: CircleDrawPipeline::new(gfx_queue.clone(), deferred_subpass)?,
+            circle: CircleDrawPipeline::new(gfx_queue.clone(), deferred_subpass.clone())?,
+            text: TextDrawPipeline::new(gfx_queue.clone(), deferred_subpass)?,
         };
 
         Ok(RenderPassDeferred {
@@ -248,6 +251,28 @@ impl<'f, 's: 'f> DrawPass<'f, 's> {
         self.execute(cb)
     }
 
+    /// Draw `text` in world space with `pos` at the top left of its first line, see `text_size`
+    pub fn draw_text(
+        &mut self,
+        text: &str,
+        pos: Vector2<f32>,
+        height: f32,
+        color: [f32; 4],
+    ) -> Result<()> {
+        self.draw_texts(&[(text, pos, height, color)])
+    }
+
+    /// Draw texts in one go, each is (text, pos, height, color) like in `draw_text`
+    pub fn draw_texts(&mut self, texts: &[(&str, Vector2<f32>, f32, [f32; 4])]) -> Result<()> {
+        let dims = self.frame.framebuffer.dimensions();
+        let cb = self.frame.system.pipelines.text.draw(
+            [dims[0], dims[1]],
+            self.camera().world_to_screen(),
+            texts,
+        )?;
+        self.execute(cb)
+    }
+
     pub fn draw_line(&mut self, line: Line) -> Result<()> {
         self.draw_lines(&[line])
     }
@@ -266,6 +291,14 @@ impl<'f, 's: 'f> DrawPass<'f, 's> {
         self.execute(cb)
     }
 
+    /// Draw a retained line batch without re-uploading its lines
+    pub fn draw_line_batch(&mut self, batch: &LineBatch) -> Result<()> {
+        if let Some((vertices, indices)) = batch.buffers() {
+            self.draw_lines_from_buffers_indexed(vertices.clone(), indices.clone())?;
+        }
+        Ok(())
+    }
+
     pub fn draw_lines_from_buffers_indexed<
         V,
         Vb: BufferAccess + TypedBufferAccess<Content = [V]> + Send + Sync + 'static,
@@ -311,6 +344,7 @@ impl<'f, 's: 'f> DrawPass<'f, 's> {
         texture: Arc<dyn ImageViewAbstract + 'static>,
         invert_y: bool,
         is_alpha: bool,
+        filter: Filter,
     ) -> Result<()> {
         let dims = self.frame.framebuffer.dimensions();
         let cb = self.frame.system.pipelines.texture.draw_texture_on_quad(
@@ -323,6 +357,7 @@ impl<'f, 's: 'f> DrawPass<'f, 's> {
             texture,
             invert_y,
             is_alpha,
+            filter,
         )?;
         self.execute(cb)
     }
@@ -7,6 +7,7 @@ use vulkano::{
     format::Format,
     image::ImageAccess,
     render_pass::{Framebuffer, RenderPass, Subpass},
+    sampler::Filter,
     sync::GpuFuture,
 };
 
@@ -46,6 +47,7 @@ impl RenderPassPlaceOverFrame {
 
     /// Place view exactly over swapchain image target.
     /// Texture draw pipeline uses a quad onto which it places the view.
+    /// Filter is used when view is scaled to target size.
     pub fn render<F>(
         &mut self,
         before_future: F,
@@ -53,6 +55,7 @@ impl RenderPassPlaceOverFrame {
         target: FinalImageView,
         is_alpha: bool,
         invert_y: bool,
+        filter: Filter,
     ) -> Result<Box<dyn GpuFuture>>
     where
         F: GpuFuture + 'static,
@@ -78,7 +81,7 @@ impl RenderPassPlaceOverFrame {
         // Create secondary command buffer from texture pipeline & send draw commands
         let cb = self
             .full_frame_image_pipeline
-            .draw(img_dims, view, is_alpha, invert_y)?;
+            .draw(img_dims, view, is_alpha, invert_y, filter)?;
         // Execute above commands (subpass)
         command_buffer_builder.execute_commands(cb)?;
         // End render pass
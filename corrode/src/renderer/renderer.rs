@@ -13,6 +13,7 @@ use egui_winit_vulkano::texture_from_file;
 #[cfg(target_os = "macos")]
 use vulkano::instance::InstanceCreationError;
 use vulkano::{
+    command_buffer::PrimaryCommandBuffer,
     device::{
         physical::{PhysicalDevice, PhysicalDeviceType},
         Device, DeviceExtensions, Features, Queue,
@@ -20,7 +21,7 @@ use vulkano::{
     format::Format,
     image::{
         view::ImageView, ImageAccess, ImageCreateFlags, ImageDimensions, ImageUsage,
-        ImageViewAbstract, StorageImage, SwapchainImage,
+        ImageViewAbstract, ImmutableImage, MipmapsCount, StorageImage, SwapchainImage,
     },
     instance::{
         debug::{DebugCallback, MessageSeverity, MessageType},
@@ -38,6 +39,7 @@ use vulkano::{
 use vulkano_win::create_vk_surface;
 use winit::{
     event_loop::EventLoop,
+    monitor::VideoMode,
     window::{Fullscreen, Window, WindowBuilder},
 };
 
@@ -149,16 +151,30 @@ pub struct DefaultRenderPasses {
 pub type FinalImageView = Arc<ImageView<SwapchainImage<Window>>>;
 /// Multipurpose image view
 pub type DeviceImageView = Arc<ImageView<StorageImage>>;
+/// Sampled image with its full mip chain, see `create_mipmapped_image`
+pub type MipmappedImageView = Arc<ImageView<ImmutableImage>>;
+
+/// Whether error was caused by losing the gpu device, e.g. on a driver reset or gpu crash
+pub fn is_device_lost_error(error: &Error) -> bool {
+    error.chain().any(|cause| {
+        matches!(cause.downcast_ref::<FlushError>(), Some(FlushError::DeviceLost))
+            || matches!(cause.downcast_ref::<AcquireError>(), Some(AcquireError::DeviceLost))
+    })
+}
 
 /// Renderer that handles all gpu side rendering
 pub struct Renderer {
-    _instance: Arc<Instance>,
+    instance: Arc<Instance>,
     _debug_callback: DebugCallback,
+    physical_device_index: usize,
     device: Arc<Device>,
     surface: Arc<Surface<Window>>,
     graphics_queue: Arc<Queue>,
     compute_queue: Arc<Queue>,
-    swap_chain: Arc<Swapchain<Window>>,
+    transfer_queue: Arc<Queue>,
+    /// None only while device is being recreated, as a surface can only have one swapchain
+    swap_chain: Option<Arc<Swapchain<Window>>>,
+    present_mode: PresentMode,
     image_index: usize,
     final_views: Vec<FinalImageView>,
     /// Image view that is to be rendered with our pipeline.
@@ -168,12 +184,18 @@ pub struct Renderer {
     image_textures: HashMap<ImageTextureId, Arc<dyn ImageViewAbstract + 'static>>,
     recreate_swapchain: bool,
     previous_frame_end: Option<Box<dyn GpuFuture>>,
+    /// Gpu work submitted by the app that the next frame waits for, see `join_frame_future`
+    frame_compute: Option<Box<dyn GpuFuture>>,
     pub render_passes: DefaultRenderPasses,
     _clear_color: [f32; 4],
     is_fullscreen: bool,
+    /// Video mode of exclusive fullscreen. Borderless fullscreen at desktop resolution if None
+    fullscreen_video_mode: Option<VideoMode>,
     device_name: String,
     device_type: PhysicalDeviceType,
     max_mem_gb: f32,
+    /// Device was lost & must be recreated before rendering, see `recreate_device`
+    device_lost: bool,
 }
 
 impl Renderer {
@@ -214,19 +236,20 @@ impl Renderer {
         let surface = create_vk_surface(window, instance.clone()).unwrap();
 
         // Create device
-        let (device, graphics_queue, compute_queue) =
+        let (device, graphics_queue, compute_queue, transfer_queue) =
             Self::create_device(physical_device, surface.clone())?;
         // Create swap chain & frame(s) to which we'll render
+        let present_mode = if opts.v_sync {
+            PresentMode::Fifo
+        } else {
+            PresentMode::Immediate
+        };
         let (swap_chain, final_images) = Self::create_swap_chain(
             surface.clone(),
             physical_device,
             device.clone(),
             graphics_queue.clone(),
-            if opts.v_sync {
-                PresentMode::Fifo
-            } else {
-                PresentMode::Immediate
-            },
+            present_mode,
         )?;
         let previous_frame_end = Some(sync::now(device.clone()).boxed());
         let is_fullscreen = swap_chain.surface().window().fullscreen().is_some();
@@ -238,25 +261,31 @@ impl Renderer {
         };
 
         Ok(Self {
-            _instance: instance,
+            instance,
             _debug_callback: debug_callback,
+            physical_device_index: physical_device.index(),
             device,
             surface,
             graphics_queue,
             compute_queue,
-            swap_chain,
+            transfer_queue,
+            swap_chain: Some(swap_chain),
+            present_mode,
             image_index: 0,
             final_views: final_images,
             interim_image_views: HashMap::new(),
             image_textures: HashMap::new(),
             previous_frame_end,
+            frame_compute: None,
             recreate_swapchain: false,
             render_passes,
             _clear_color: [0.0; 4],
             is_fullscreen,
+            fullscreen_video_mode: None,
             device_name,
             device_type,
             max_mem_gb,
+            device_lost: false,
         })
     }
 
@@ -264,11 +293,13 @@ impl Renderer {
     STATIC FUNCTIONS
     =================*/
 
-    /// Creates vulkan device with required queue families and required extensions
+    /// Creates vulkan device with required queue families and required extensions.
+    /// Compute & transfer queues are from their own families if the device has them, otherwise
+    /// they fall back to graphics (and compute) queue
     fn create_device(
         physical: PhysicalDevice,
         surface: Arc<Surface<Window>>,
-    ) -> Result<(Arc<Device>, Arc<Queue>, Arc<Queue>)> {
+    ) -> Result<(Arc<Device>, Arc<Queue>, Arc<Queue>, Arc<Queue>)> {
         let (gfx_index, queue_family_graphics) = physical
             .queue_families()
             .enumerate()
@@ -278,6 +309,20 @@ impl Renderer {
             .queue_families()
             .enumerate()
             .find(|&(i, q)| i != gfx_index && q.supports_compute());
+        let compute_index = compute_family_data.map(|(i, _q)| i);
+        // Dedicated transfer family (e.g. dma engine) supports only transfers
+        let transfer_family_data = physical.queue_families().enumerate().find(|&(i, q)| {
+            i != gfx_index
+                && Some(i) != compute_index
+                && q.explicitly_supports_transfers()
+                && !q.supports_graphics()
+                && !q.supports_compute()
+        });
+        info!(
+            "Compute queue family: {}, transfer queue family: {}",
+            if compute_family_data.is_some() { "dedicated" } else { "graphics" },
+            if transfer_family_data.is_some() { "dedicated" } else { "compute" }
+        );
 
         // Add device extensions based on needs,
         let device_extensions = DeviceExtensions {
@@ -291,41 +336,38 @@ impl Renderer {
             ..Features::none()
         };
 
-        Ok(
-            if let Some((_compute_index, queue_family_compute)) = compute_family_data {
-                let (device, mut queues) = {
-                    Device::new(
-                        physical,
-                        &features,
-                        &physical.required_extensions().union(&device_extensions),
-                        [(queue_family_graphics, 1.0), (queue_family_compute, 0.5)]
-                            .iter()
-                            .cloned(),
-                    )
-                    .context("failed to create device")?
-                };
-                let gfx_queue = queues.next().unwrap();
-                let compute_queue = queues.next().unwrap();
-                (device, gfx_queue, compute_queue)
-            } else {
-                let (device, mut queues) = {
-                    Device::new(
-                        physical,
-                        &features,
-                        &physical.required_extensions().union(&device_extensions),
-                        [(queue_family_graphics, 1.0)].iter().cloned(),
-                    )
-                    .context("failed to create device")?
-                };
-                let gfx_queue = queues.next().unwrap();
-                let compute_queue = gfx_queue.clone();
-                (device, gfx_queue, compute_queue)
-            },
-        )
+        let mut queue_families = vec![(queue_family_graphics, 1.0)];
+        if let Some((_compute_index, queue_family_compute)) = compute_family_data {
+            queue_families.push((queue_family_compute, 0.5));
+        }
+        if let Some((_transfer_index, queue_family_transfer)) = transfer_family_data {
+            queue_families.push((queue_family_transfer, 0.5));
+        }
+        let (device, mut queues) = {
+            Device::new(
+                physical,
+                &features,
+                &physical.required_extensions().union(&device_extensions),
+                queue_families.iter().cloned(),
+            )
+            .context("failed to create device")?
+        };
+        let gfx_queue = queues.next().unwrap();
+        let compute_queue = if compute_family_data.is_some() {
+            queues.next().unwrap()
+        } else {
+            gfx_queue.clone()
+        };
+        let transfer_queue = if transfer_family_data.is_some() {
+            queues.next().unwrap()
+        } else {
+            compute_queue.clone()
+        };
+        Ok((device, gfx_queue, compute_queue, transfer_queue))
     }
 
     /// Creates swapchain and swapchain images
-    fn create_swap_chain(
+    pub(crate) fn create_swap_chain(
         surface: Arc<Surface<Window>>,
         physical: PhysicalDevice,
         device: Arc<Device>,
@@ -445,6 +487,18 @@ impl Renderer {
         self.image_index
     }
 
+    pub(crate) fn physical_device(&self) -> PhysicalDevice {
+        PhysicalDevice::from_index(&self.instance, self.physical_device_index).unwrap()
+    }
+
+    pub(crate) fn present_mode(&self) -> PresentMode {
+        self.present_mode
+    }
+
+    fn swap_chain(&self) -> Arc<Swapchain<Window>> {
+        self.swap_chain.clone().unwrap()
+    }
+
     /// Access device
     pub fn device(&self) -> Arc<Device> {
         self.device.clone()
@@ -460,6 +514,11 @@ impl Renderer {
         self.compute_queue.clone()
     }
 
+    /// Access transfer queue (for uploads that shouldn't stall compute)
+    pub fn transfer_queue(&self) -> Arc<Queue> {
+        self.transfer_queue.clone()
+    }
+
     /// Render target surface
     pub fn surface(&self) -> Arc<Surface<Window>> {
         self.surface.clone()
@@ -551,11 +610,52 @@ impl Renderer {
 
     pub fn toggle_fullscreen(&mut self) {
         self.is_fullscreen = !self.is_fullscreen;
-        self.window().set_fullscreen(if self.is_fullscreen {
-            Some(Fullscreen::Borderless(self.window().current_monitor()))
-        } else {
-            None
+        self.apply_fullscreen();
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.is_fullscreen
+    }
+
+    /// Video modes of the monitor the window is on, largest & fastest first
+    pub fn video_modes(&self) -> Vec<VideoMode> {
+        let mut modes = self
+            .window()
+            .current_monitor()
+            .map(|monitor| monitor.video_modes().collect::<Vec<VideoMode>>())
+            .unwrap_or_default();
+        modes.sort_by_key(|mode| {
+            let size = mode.size();
+            std::cmp::Reverse((size.width, size.height, mode.refresh_rate(), mode.bit_depth()))
         });
+        modes
+    }
+
+    pub fn fullscreen_video_mode(&self) -> Option<&VideoMode> {
+        self.fullscreen_video_mode.as_ref()
+    }
+
+    /// Use exclusive fullscreen with given video mode, or borderless fullscreen if None. Applied
+    /// right away when in fullscreen
+    pub fn set_fullscreen_video_mode(&mut self, video_mode: Option<VideoMode>) {
+        self.fullscreen_video_mode = video_mode;
+        if self.is_fullscreen {
+            self.apply_fullscreen();
+        }
+    }
+
+    fn apply_fullscreen(&mut self) {
+        let fullscreen = if !self.is_fullscreen {
+            None
+        } else if let Some(video_mode) = &self.fullscreen_video_mode {
+            Some(Fullscreen::Exclusive(video_mode.clone()))
+        } else {
+            Some(Fullscreen::Borderless(self.window().current_monitor()))
+        };
+        self.window().set_fullscreen(fullscreen);
+        // Mode changes don't always resize the window (e.g. only refresh rate changes), thus
+        // recreate swapchain regardless of resize events
+        self.recreate_swapchain = true;
     }
 
     /// Resize swapchain and camera view images
@@ -563,6 +663,61 @@ impl Renderer {
         self.recreate_swapchain = true;
     }
 
+    /*================
+    DEVICE LOSS
+    =================*/
+
+    /// Whether device has been lost & must be recreated with `recreate_device`
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost
+    }
+
+    /// Mark device lost, e.g. when a compute submission failed with `FlushError::DeviceLost`
+    pub fn mark_device_lost(&mut self) {
+        self.device_lost = true;
+    }
+
+    /// Recreate device, queues, swapchain & render passes on the same gpu after the device was
+    /// lost. Image targets & textures are dropped, and everything created from the old device
+    /// must be recreated by the user
+    pub fn recreate_device(&mut self) -> Result<()> {
+        // Old swapchain must be gone before a new one is created for the surface
+        self.previous_frame_end = None;
+        self.frame_compute = None;
+        self.final_views.clear();
+        self.interim_image_views.clear();
+        self.image_textures.clear();
+        self.swap_chain = None;
+        let physical_device = PhysicalDevice::from_index(&self.instance, self.physical_device_index)
+            .context("Gpu is no longer available")?;
+        let (device, graphics_queue, compute_queue, transfer_queue) =
+            Self::create_device(physical_device, self.surface.clone())?;
+        let (swap_chain, final_images) = Self::create_swap_chain(
+            self.surface.clone(),
+            physical_device,
+            device.clone(),
+            graphics_queue.clone(),
+            self.present_mode,
+        )?;
+        let image_format = final_images.first().unwrap().format();
+        self.render_passes = DefaultRenderPasses {
+            deferred: RenderPassDeferred::new(graphics_queue.clone(), image_format)?,
+            place_over_frame: RenderPassPlaceOverFrame::new(graphics_queue.clone(), image_format)?,
+        };
+        self.previous_frame_end = Some(sync::now(device.clone()).boxed());
+        self.device = device;
+        self.graphics_queue = graphics_queue;
+        self.compute_queue = compute_queue;
+        self.transfer_queue = transfer_queue;
+        self.swap_chain = Some(swap_chain);
+        self.final_views = final_images;
+        self.image_index = 0;
+        self.recreate_swapchain = false;
+        self.device_lost = false;
+        info!("Recreated lost device {}", self.device_name);
+        Ok(())
+    }
+
     /*================
     RENDERING
     =================*/
@@ -581,12 +736,16 @@ impl Renderer {
 
         // Acquire next image in the swapchain
         let (image_num, suboptimal, acquire_future) =
-            match swapchain::acquire_next_image(self.swap_chain.clone(), None) {
+            match swapchain::acquire_next_image(self.swap_chain(), None) {
                 Ok(r) => r,
                 Err(AcquireError::OutOfDate) => {
                     self.recreate_swapchain = true;
                     return Err(anyhow!(AcquireError::OutOfDate));
                 }
+                Err(AcquireError::DeviceLost) => {
+                    self.device_lost = true;
+                    return Err(anyhow!(AcquireError::DeviceLost));
+                }
                 Err(e) => panic!("Failed to acquire next image: {:?}", e),
             };
         if suboptimal {
@@ -596,8 +755,34 @@ impl Renderer {
         self.image_index = image_num;
 
         let future = self.previous_frame_end.take().unwrap().join(acquire_future);
+        let future = match self.frame_compute.take() {
+            Some(frame_compute) => future.join(frame_compute).boxed(),
+            None => future.boxed(),
+        };
 
-        Ok(future.boxed())
+        Ok(future)
+    }
+
+    /// Execute `command_buffer` on compute queue now. The next frame's `before_future` waits for
+    /// it to finish, so its results can be drawn in `Engine::render`
+    pub fn submit_compute<C>(&mut self, command_buffer: C) -> Result<()>
+    where
+        C: PrimaryCommandBuffer + 'static,
+    {
+        let future = sync::now(self.device.clone())
+            .then_execute(self.compute_queue.clone(), command_buffer)?
+            .then_signal_fence_and_flush()?;
+        self.join_frame_future(Arc::new(future));
+        Ok(())
+    }
+
+    /// Make the next frame's `before_future` wait for `future`, e.g. compute the app submitted
+    /// itself during `update`. Futures joined during a frame are joined together
+    pub fn join_frame_future(&mut self, future: impl GpuFuture + 'static) {
+        self.frame_compute = Some(match self.frame_compute.take() {
+            Some(frame_compute) => frame_compute.join(future).boxed(),
+            None => future.boxed(),
+        });
     }
 
     /// Finishes render by presenting the swapchain
@@ -605,7 +790,7 @@ impl Renderer {
         let future = after_future
             .then_swapchain_present(
                 self.graphics_queue.clone(),
-                self.swap_chain.clone(),
+                self.swap_chain(),
                 self.image_index,
             )
             .then_signal_fence_and_flush();
@@ -615,6 +800,7 @@ impl Renderer {
                 // https://github.com/vulkano-rs/vulkano/issues/627
                 match future.wait(None) {
                     Ok(x) => x,
+                    Err(FlushError::DeviceLost) => self.device_lost = true,
                     Err(err) => error!("{:?}", err),
                 }
                 self.previous_frame_end = Some(future.boxed());
@@ -623,6 +809,10 @@ impl Renderer {
                 self.recreate_swapchain = true;
                 self.previous_frame_end = Some(sync::now(self.device.clone()).boxed());
             }
+            Err(FlushError::DeviceLost) => {
+                self.device_lost = true;
+                self.previous_frame_end = Some(sync::now(self.device.clone()).boxed());
+            }
             Err(e) => {
                 error!("Failed to flush future: {:?}", e);
                 self.previous_frame_end = Some(sync::now(self.device.clone()).boxed());
@@ -635,7 +825,7 @@ impl Renderer {
     fn recreate_swapchain_and_views(&mut self) -> Result<()> {
         let dimensions: [u32; 2] = self.window().inner_size().into();
         let (new_swapchain, new_images) =
-            match self.swap_chain.recreate().dimensions(dimensions).build() {
+            match self.swap_chain().recreate().dimensions(dimensions).build() {
                 Ok(r) => r,
                 Err(SwapchainCreationError::UnsupportedDimensions) => {
                     error!(
@@ -647,7 +837,7 @@ impl Renderer {
                 Err(e) => panic!("Failed to recreate swapchain: {:?}", e),
             };
 
-        self.swap_chain = new_swapchain;
+        self.swap_chain = Some(new_swapchain);
         let new_images = new_images
             .into_iter()
             .map(|image| ImageView::new(image).unwrap())
@@ -721,3 +911,47 @@ pub fn create_device_image_with_usage(
         Some(queue.family()),
     )?)?)
 }
+
+/// Like `create_device_image_with_usage`, but shared concurrently by all queue families of
+/// `device`, so it can be written on one queue (e.g. transfer) & used on others without
+/// ownership transfers
+#[allow(unused)]
+pub fn create_shared_device_image(
+    device: Arc<Device>,
+    size: [u32; 2],
+    format: Format,
+    usage: ImageUsage,
+) -> Result<DeviceImageView> {
+    let dims = ImageDimensions::Dim2d {
+        width: size[0],
+        height: size[1],
+        array_layers: 1,
+    };
+    let flags = ImageCreateFlags::none();
+    Ok(ImageView::new(StorageImage::with_usage(
+        device.clone(),
+        dims,
+        format,
+        usage,
+        flags,
+        device.active_queue_families(),
+    )?)?)
+}
+
+/// Create an image of rgba `data` with mipmaps generated on `queue`, so it doesn't shimmer when
+/// drawn much smaller than it is, e.g. zoomed out. Returned future must finish before the image
+/// is used
+pub fn create_mipmapped_image(
+    queue: Arc<Queue>,
+    size: [u32; 2],
+    format: Format,
+    data: Vec<u8>,
+) -> Result<(MipmappedImageView, Box<dyn GpuFuture>)> {
+    let dims = ImageDimensions::Dim2d {
+        width: size[0],
+        height: size[1],
+        array_layers: 1,
+    };
+    let (image, future) = ImmutableImage::from_iter(data, dims, MipmapsCount::Log2, format, queue)?;
+    Ok((ImageView::new(image)?, future.boxed()))
+}
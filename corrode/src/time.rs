@@ -18,6 +18,14 @@ pub struct TimeTracker {
     /// Delta time sum to handle fixed frame times
     dt_sum_fixed: f64,
     prev_time: Instant,
+    /// Simulated time doesn't advance while paused, see `sim_dt`
+    paused: bool,
+    /// Multiplier of delta time for simulated time
+    time_scale: f64,
+    /// Simulated time since start in milliseconds
+    sim_time: f64,
+    /// Single steps requested while paused, taken with `take_step`
+    requested_steps: u32,
 }
 
 impl TimeTracker {
@@ -32,6 +40,10 @@ impl TimeTracker {
             dt_sum: 0.0,
             dt_sum_fixed: 0.0,
             prev_time: Instant::now(),
+            paused: false,
+            time_scale: 1.0,
+            sim_time: 0.0,
+            requested_steps: 0,
         }
     }
 
@@ -61,6 +73,63 @@ impl TimeTracker {
         self.start.elapsed().as_secs_f64()
     }
 
+    /// Deltatime in milliseconds scaled by time scale, regardless of pause. Use to pace manual
+    /// steps
+    pub fn scaled_dt(&self) -> f64 {
+        self.dt * self.time_scale
+    }
+
+    /// Simulated deltatime in milliseconds, 0.0 while paused. Physics, simulation steps &
+    /// animations should advance by this
+    pub fn sim_dt(&self) -> f64 {
+        if self.paused {
+            0.0
+        } else {
+            self.scaled_dt()
+        }
+    }
+
+    /// Simulated time since start, see `sim_dt`
+    pub fn sim_time_secs(&self) -> f64 {
+        self.sim_time / 1000.0
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn toggle_paused(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    /// Scale of simulated time to real time, e.g. 0.5 for slow motion
+    pub fn set_time_scale(&mut self, time_scale: f64) {
+        self.time_scale = time_scale.max(0.0);
+    }
+
+    /// Request a single step, e.g. while paused
+    pub fn request_step(&mut self) {
+        self.requested_steps += 1;
+    }
+
+    /// Take a requested step, if any
+    pub fn take_step(&mut self) -> bool {
+        if self.requested_steps > 0 {
+            self.requested_steps -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
     /// Reset delta time sum for fixed after update, set dt_fixed
     pub fn reset_fixed(&mut self) {
         self.dt_fixed = self.dt_sum_fixed;
@@ -81,6 +150,7 @@ impl TimeTracker {
         self.prev_time = now;
         self.frame_counter += 1;
         self.dt_sum_fixed += self.dt;
+        self.sim_time += self.sim_dt();
     }
 }
 
@@ -133,6 +203,11 @@ impl PerformanceTimer {
     pub fn time_average_ms(&self) -> f64 {
         self.data.iter().sum::<f64>() / self.data.len() as f64
     }
+
+    /// Last recorded time in milliseconds
+    pub fn latest_ms(&self) -> f64 {
+        self.data.back().copied().unwrap_or(0.0)
+    }
 }
 
 impl Default for PerformanceTimer {
@@ -140,3 +215,101 @@ impl Default for PerformanceTimer {
         PerformanceTimer::new()
     }
 }
+
+/// Number of frame times kept for percentile lows, enough for a meaningful 0.1% low
+const NUM_FRAME_SAMPLES: usize = 5000;
+const NUM_SPIKES: usize = 20;
+/// Frame counts as a spike if it takes this many times longer than the average frame
+const SPIKE_FACTOR: f64 = 2.5;
+/// Don't log spikes shorter than this (ms), e.g. when running uncapped at high fps
+const MIN_SPIKE_MS: f64 = 8.0;
+
+/// A frame that took much longer than average, and the subsystem that was slowest on that frame
+#[derive(Debug, Clone)]
+pub struct FrameSpike {
+    pub time_secs: f64,
+    pub dt: f64,
+    pub avg_dt: f64,
+    pub slowest: &'static str,
+    pub slowest_ms: f64,
+}
+
+/// Per frame timing ring buffer for percentile lows & spike log
+#[derive(Debug)]
+pub struct FrameStats {
+    frame_times: VecDeque<f64>,
+    frame_time_sum: f64,
+    spikes: VecDeque<FrameSpike>,
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        Self {
+            frame_times: VecDeque::with_capacity(NUM_FRAME_SAMPLES),
+            frame_time_sum: 0.0,
+            spikes: VecDeque::with_capacity(NUM_SPIKES),
+        }
+    }
+
+    /// Record frame time (ms) and the time each subsystem took on that frame
+    pub fn push_frame(&mut self, time_secs: f64, dt: f64, subsystems: &[(&'static str, f64)]) {
+        let avg_dt = self.average_ms();
+        if self.frame_times.len() > 60 && dt > avg_dt * SPIKE_FACTOR && dt > MIN_SPIKE_MS {
+            let (slowest, slowest_ms) = subsystems
+                .iter()
+                .copied()
+                .fold(("Unknown", 0.0), |acc, s| if s.1 > acc.1 { s } else { acc });
+            if self.spikes.len() >= NUM_SPIKES {
+                self.spikes.pop_front();
+            }
+            self.spikes.push_back(FrameSpike {
+                time_secs,
+                dt,
+                avg_dt,
+                slowest,
+                slowest_ms,
+            });
+        }
+        if self.frame_times.len() >= NUM_FRAME_SAMPLES {
+            self.frame_time_sum -= self.frame_times.pop_front().unwrap();
+        }
+        self.frame_times.push_back(dt);
+        self.frame_time_sum += dt;
+    }
+
+    pub fn average_ms(&self) -> f64 {
+        if self.frame_times.is_empty() {
+            return 0.0;
+        }
+        self.frame_time_sum / self.frame_times.len() as f64
+    }
+
+    /// Average fps of the slowest `percent` of frames, e.g. 1.0 for 1% lows
+    pub fn percentile_low_fps(&self, percent: f64) -> f64 {
+        if self.frame_times.is_empty() {
+            return 0.0;
+        }
+        let mut sorted = self.frame_times.iter().copied().collect::<Vec<f64>>();
+        sorted.sort_by(|a, b| b.partial_cmp(a).unwrap());
+        let count = ((sorted.len() as f64 * percent / 100.0).ceil() as usize).max(1);
+        let avg_dt = sorted[..count].iter().sum::<f64>() / count as f64;
+        1000.0 / avg_dt
+    }
+
+    /// Logged spikes, latest last
+    pub fn spikes(&self) -> &VecDeque<FrameSpike> {
+        &self.spikes
+    }
+
+    pub fn clear(&mut self) {
+        self.frame_times.clear();
+        self.frame_time_sum = 0.0;
+        self.spikes.clear();
+    }
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        FrameStats::new()
+    }
+}
@@ -0,0 +1,99 @@
+#version 450
+
+#include "includes.glsl"
+
+#define BUG_WALK_PROBABILITY 0.5
+#define BUG_EAT_PROBABILITY 0.1
+#define BUG_TURN_PROBABILITY 0.01
+
+const vec4 BUG_COLOR = vec4(0.15, 0.08, 0.02, 1.0);
+
+// Bugs walk & fall through these, liquids included so they sink to the bottom
+bool is_passable(Matter matter) {
+    return !is_object(matter) &&
+    (is_empty(matter) || is_gas(matter) || is_energy(matter) || is_liquid(matter));
+}
+
+bool is_edible(Matter matter) {
+    return !is_object(matter) && (matter.characteristics & CHARACTERISTIC_EDIBLE) != 0 &&
+    (matter.characteristics & MATTER_PROTECTED) == 0;
+}
+
+bool is_deadly(Matter matter) {
+    uint deadly = CHARACTERISTIC_CORROSIVE | CHARACTERISTIC_MELTING | CHARACTERISTIC_BURNING |
+    CHARACTERISTIC_EXPLODING | CHARACTERISTIC_ERASER;
+    return (matter.characteristics & deadly) != 0;
+}
+
+// Bugs don't leave the simulated area, its edges are walls to them
+bool is_passable_at(ivec2 pos) {
+    return is_inside_sim_canvas(pos) && is_passable(read_matter(pos));
+}
+
+void update_bug(uint index) {
+    uint bug_index = index * BUG_STRIDE;
+    uint state = bugs[bug_index + 2];
+    if (state == BUG_DEAD) {
+        return;
+    }
+    ivec2 pos = ivec2(int(bugs[bug_index]), int(bugs[bug_index + 1]));
+    // Bugs outside simulated area are frozen until the camera moves back to them
+    if (!is_inside_sim_canvas(pos)) {
+        return;
+    }
+    Matter current = read_matter(pos);
+    if (is_deadly(current)) {
+        bugs[bug_index + 2] = BUG_DEAD;
+        return;
+    }
+    ivec2 up = ivec2(0, 1);
+    ivec2 down = ivec2(0, -1);
+    ivec2 ahead = pos + ivec2(state == BUG_RIGHT ? 1 : -1, 0);
+    float seed = push_constants.chunk_seeds[get_chunk_index(pos)];
+    float p = rand(vec2(pos) + vec2(index, 0), seed);
+    if (!is_passable(current)) {
+        // Buried, eat or climb out
+        if (is_edible(current)) {
+            write_matter_both(pos, new_matter(empty));
+        } else if (is_passable_at(pos + up)) {
+            pos += up;
+        }
+    } else if (is_passable_at(pos + down)) {
+        pos += down;
+    } else if (p < BUG_WALK_PROBABILITY) {
+        float p_eat = rand(vec2(pos) + vec2(index, 0), seed + 1.0);
+        bool turn = p < BUG_WALK_PROBABILITY * BUG_TURN_PROBABILITY;
+        if (!is_inside_sim_canvas(ahead)) {
+            turn = true;
+        } else {
+            Matter ahead_matter = read_matter(ahead);
+            if (is_edible(ahead_matter)) {
+                if (p_eat < BUG_EAT_PROBABILITY) {
+                    write_matter_both(ahead, new_matter(empty));
+                }
+            } else if (is_passable(ahead_matter)) {
+                pos = ahead;
+            } else if (is_passable_at(pos + up) && is_passable_at(ahead + up)) {
+                // Climb a step
+                pos = ahead + up;
+            } else {
+                turn = true;
+            }
+        }
+        if (turn) {
+            state = state == BUG_RIGHT ? BUG_LEFT : BUG_RIGHT;
+        }
+    }
+    bugs[bug_index] = uint(pos.x);
+    bugs[bug_index + 1] = uint(pos.y);
+    bugs[bug_index + 2] = state;
+    write_image_color(pos, BUG_COLOR);
+}
+
+void main() {
+    uint index = gl_WorkGroupID.x * gl_WorkGroupSize.x * gl_WorkGroupSize.y +
+    gl_LocalInvocationIndex;
+    if (index * BUG_STRIDE < uint(bugs.length())) {
+        update_bug(index);
+    }
+}
@@ -0,0 +1,216 @@
+// Agents (bugs) live in a compact buffer instead of the grid, and are updated one agent per
+// invocation. Own buffer inputs, because simulation inputs are at macos molten vk limit of 30
+
+// Specialization constants
+layout(constant_id = 0) const uint empty = 1;
+layout(constant_id = 1) const int sim_canvas_width = 1;
+layout(constant_id = 2) const int bitmap_ratio = 1;
+layout(constant_id = 3) const uint state_empty = 1;
+layout(constant_id = 4) const uint state_powder = 1;
+layout(constant_id = 5) const uint state_liquid = 1;
+layout(constant_id = 6) const uint state_solid = 1;
+layout(constant_id = 7) const uint state_solid_gravity = 1;
+layout(constant_id = 8) const uint state_gas = 1;
+layout(constant_id = 9) const uint state_energy = 1;
+layout(constant_id = 10) const uint state_object = 1;
+layout(constant_id = 13) const int sim_canvas_height = 1;
+
+// X & Y input as specialization constant
+layout(local_size_x_id = 11, local_size_y_id = 12, local_size_z = 1) in;
+
+layout(set = 0, binding = 0) restrict buffer MatterStateBuffer {
+    uint matter_state[];
+};
+layout(set = 0, binding = 1) restrict buffer MatterCharacteristicsBuffer {
+    uint matter_characteristics[];
+};
+
+/*
+Matter data chunks
+*/
+layout(set = 0, binding = 2) restrict buffer MatterInBuffer0 { uint matter_in0[]; };
+layout(set = 0, binding = 3) restrict buffer MatterOutBuffer0 { uint matter_out0[]; };
+layout(set = 0, binding = 4) restrict buffer ObjectsMatter0 { uint objects_matter0[]; };
+layout(set = 0, binding = 5, rgba8) restrict uniform writeonly image2D canvas_img0;
+
+layout(set = 0, binding = 6) restrict buffer MatterInBuffer1 { uint matter_in1[]; };
+layout(set = 0, binding = 7) restrict buffer MatterOutBuffer1 { uint matter_out1[]; };
+layout(set = 0, binding = 8) restrict buffer ObjectsMatter1 { uint objects_matter1[]; };
+layout(set = 0, binding = 9, rgba8) restrict uniform writeonly image2D canvas_img1;
+
+layout(set = 0, binding = 10) restrict buffer MatterInBuffer2 { uint matter_in2[]; };
+layout(set = 0, binding = 11) restrict buffer MatterOutBuffer2 { uint matter_out2[]; };
+layout(set = 0, binding = 12) restrict buffer ObjectsMatter2 { uint objects_matter2[]; };
+layout(set = 0, binding = 13, rgba8) restrict uniform writeonly image2D canvas_img2;
+
+layout(set = 0, binding = 14) restrict buffer MatterInBuffer3 { uint matter_in3[]; };
+layout(set = 0, binding = 15) restrict buffer MatterOutBuffer3 { uint matter_out3[]; };
+layout(set = 0, binding = 16) restrict buffer ObjectsMatter3 { uint objects_matter3[]; };
+layout(set = 0, binding = 17, rgba8) restrict uniform writeonly image2D canvas_img3;
+
+// Bugs as (x, y, state) in canvas coordinates, see BUG_STRIDE
+layout(set = 0, binding = 18) restrict buffer BugsBuffer { uint bugs[]; };
+
+layout(push_constant) uniform PushConstants {
+    ivec2 sim_pos_offset;
+    ivec2 sim_chunk_start_offset;
+    // Seed per compute chunk (in get_chunk_index order), see chunk_step_seed in ca_simulator.rs
+    vec4 chunk_seeds;
+} push_constants;
+
+const ivec2 SIM_CANVAS_SIZE = ivec2(sim_canvas_width, sim_canvas_height);
+const ivec2 HALF_CANVAS = SIM_CANVAS_SIZE / 2;
+
+// Must match BUG_STRIDE & BugState in ca_simulator.rs
+#define BUG_STRIDE 3
+#define BUG_DEAD 0
+#define BUG_RIGHT 1
+#define BUG_LEFT 2
+
+// Must match MatterCharacteristic
+#define CHARACTERISTIC_CORROSIVE (uint(1) << 0)
+#define CHARACTERISTIC_MELTING (uint(1) << 2)
+#define CHARACTERISTIC_BURNING (uint(1) << 4)
+#define CHARACTERISTIC_EXPLODING (uint(1) << 8)
+#define CHARACTERISTIC_ERASER (uint(1) << 17)
+#define CHARACTERISTIC_EDIBLE (uint(1) << 26)
+// Must match MATTER_PROTECTED_BIT in ca_simulator.rs
+#define MATTER_PROTECTED (uint(1) << 31)
+
+struct Matter {
+    uint matter;
+    uint state;
+    uint characteristics;
+};
+
+Matter new_matter(uint matter) {
+    Matter m;
+    m.matter = matter;
+    m.state = matter_state[m.matter];
+    m.characteristics = matter_characteristics[m.matter];
+    return m;
+}
+
+// https://stackoverflow.com/questions/4200224/random-noise-functions-for-glsl
+float PHI = 1.61803398874989484820459; // Golden ratio
+float rand(in vec2 xy, in float seed){
+    vec2 pos = vec2(xy.x + 0.5, xy.y + 0.5);
+    return fract(tan(distance(pos * PHI, pos) * seed) * pos.x);
+}
+
+ivec2 get_local_pos(ivec2 pos) {
+    return pos + HALF_CANVAS - push_constants.sim_pos_offset;
+}
+
+int get_index(ivec2 pos) {
+    return pos.y * sim_canvas_width + pos.x;
+}
+
+ivec2 get_pos_inside_chunk(ivec2 pos) {
+    ivec2 diff = pos - push_constants.sim_chunk_start_offset;
+    return diff % SIM_CANVAS_SIZE;
+}
+
+int get_chunk_index(ivec2 pos) {
+    ivec2 pos_on_4_chunks = (pos - push_constants.sim_chunk_start_offset) / SIM_CANVAS_SIZE;
+    return pos_on_4_chunks.y * 2 + pos_on_4_chunks.x;
+}
+
+bool is_inside_sim_canvas(ivec2 pos) {
+    ivec2 local_pos = get_local_pos(pos);
+    return local_pos.x >= 0 && local_pos.x < sim_canvas_width &&
+    local_pos.y >= 0 && local_pos.y < sim_canvas_height;
+}
+
+uint get_matter_in(ivec2 pos) {
+    int index = get_index(get_pos_inside_chunk(pos));
+    int chunk_index = get_chunk_index(pos);
+    if (chunk_index == 0) {
+        return matter_in0[index];
+    } else if (chunk_index == 1) {
+        return matter_in1[index];
+    } else if (chunk_index == 2) {
+        return matter_in2[index];
+    } else if (chunk_index == 3) {
+        return matter_in3[index];
+    }
+    return matter_in0[index];
+}
+
+uint get_objects_matter(ivec2 pos) {
+    int index = get_index(get_pos_inside_chunk(pos));
+    int chunk_index = get_chunk_index(pos);
+    if (chunk_index == 0) {
+        return objects_matter0[index];
+    } else if (chunk_index == 1) {
+        return objects_matter1[index];
+    } else if (chunk_index == 2) {
+        return objects_matter2[index];
+    } else if (chunk_index == 3) {
+        return objects_matter3[index];
+    }
+    return objects_matter0[index];
+}
+
+Matter read_matter(ivec2 pos) {
+    uint obj_matter = get_objects_matter(pos);
+    if (obj_matter != empty) {
+        Matter matter = new_matter(obj_matter);
+        matter.state = state_object;
+        return matter;
+    } else {
+        return new_matter(get_matter_in(pos));
+    }
+}
+
+void write_matter_both(ivec2 pos, Matter matter) {
+    int index = get_index(get_pos_inside_chunk(pos));
+    int chunk_index = get_chunk_index(pos);
+    if (chunk_index == 0) {
+        matter_in0[index] = matter.matter;
+        matter_out0[index] = matter.matter;
+    } else if (chunk_index == 1) {
+        matter_in1[index] = matter.matter;
+        matter_out1[index] = matter.matter;
+    } else if (chunk_index == 2) {
+        matter_in2[index] = matter.matter;
+        matter_out2[index] = matter.matter;
+    } else if (chunk_index == 3) {
+        matter_in3[index] = matter.matter;
+        matter_out3[index] = matter.matter;
+    }
+}
+
+void write_image_color(ivec2 pos, vec4 color) {
+    ivec2 img_pos = get_pos_inside_chunk(pos);
+    int chunk_index = get_chunk_index(pos);
+    if (chunk_index == 0) {
+        imageStore(canvas_img0, img_pos, color);
+    } else if (chunk_index == 1) {
+        imageStore(canvas_img1, img_pos, color);
+    } else if (chunk_index == 2) {
+        imageStore(canvas_img2, img_pos, color);
+    } else if (chunk_index == 3) {
+        imageStore(canvas_img3, img_pos, color);
+    }
+}
+
+bool is_object(Matter matter) {
+    return matter.state == state_object;
+}
+
+bool is_gas(Matter matter) {
+    return matter.state == state_gas;
+}
+
+bool is_empty(Matter matter) {
+    return matter.matter == empty;
+}
+
+bool is_liquid(Matter matter) {
+    return matter.state == state_liquid;
+}
+
+bool is_energy(Matter matter) {
+    return matter.state == state_energy;
+}
@@ -0,0 +1,42 @@
+#version 450
+
+#include "includes.glsl"
+
+// Direction (-1 or 1) supported liquid at pos flows to empty next to it by chance of its
+// velocity, 0 if it doesn't. Unsupported liquid falls instead
+int flow_dir(ivec2 pos) {
+    if (!is_liquid_at(pos)) {
+        return 0;
+    }
+    float v = velocity[field_index(pos)];
+    int dir = v > 0.0 ? 1 : -1;
+    if (is_empty_at(pos + DOWN) || !is_empty_at(pos + ivec2(dir, 0)) ||
+    rand_at(pos, 11.0) >= abs(v)) {
+        return 0;
+    }
+    return dir;
+}
+
+// Liquid moves horizontally to empty towards lower pressure. When liquid on both sides flows to
+// the same cell, the left one moves
+void main() {
+    ivec2 pos = get_current_sim_pos();
+    if (keeps_frozen(pos)) {
+        return;
+    }
+    Matter current = read_matter(pos);
+    Matter m = current;
+    if (is_empty(current)) {
+        if (flow_dir(pos + LEFT) == 1) {
+            m = read_matter(pos + LEFT);
+        } else if (flow_dir(pos + RIGHT) == -1) {
+            m = read_matter(pos + RIGHT);
+        }
+    } else {
+        int dir = flow_dir(pos);
+        if (dir == 1 || (dir == -1 && flow_dir(pos + 2 * LEFT) != 1)) {
+            m = read_matter(pos + ivec2(dir, 0));
+        }
+    }
+    write_matter(pos, m);
+}
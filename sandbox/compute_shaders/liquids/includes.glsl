@@ -0,0 +1,245 @@
+// Pressure liquid model (LiquidModel::Pressure). Liquids get a pressure & horizontal velocity
+// field over sim canvas, which push them up through connected tunnels & make them flow towards
+// lower pressure. Own buffer inputs, because simulation inputs are at macos molten vk limit of 30
+
+// Specialization constants
+layout(constant_id = 0) const uint empty = 1;
+layout(constant_id = 1) const int sim_canvas_width = 1;
+layout(constant_id = 2) const int bitmap_ratio = 1;
+layout(constant_id = 3) const uint state_empty = 1;
+layout(constant_id = 4) const uint state_powder = 1;
+layout(constant_id = 5) const uint state_liquid = 1;
+layout(constant_id = 6) const uint state_solid = 1;
+layout(constant_id = 7) const uint state_solid_gravity = 1;
+layout(constant_id = 8) const uint state_gas = 1;
+layout(constant_id = 9) const uint state_energy = 1;
+layout(constant_id = 10) const uint state_object = 1;
+layout(constant_id = 13) const int sim_canvas_height = 1;
+
+// X & Y input as specialization constant
+layout(local_size_x_id = 11, local_size_y_id = 12, local_size_z = 1) in;
+
+layout(set = 0, binding = 0) restrict buffer MatterStateBuffer {
+    uint matter_state[];
+};
+
+/*
+Matter data chunks
+*/
+layout(set = 0, binding = 1) restrict buffer MatterInBuffer0 { uint matter_in0[]; };
+layout(set = 0, binding = 2) restrict writeonly buffer MatterOutBuffer0 { uint matter_out0[]; };
+layout(set = 0, binding = 3) restrict buffer ObjectsMatter0 { uint objects_matter0[]; };
+
+layout(set = 0, binding = 4) restrict buffer MatterInBuffer1 { uint matter_in1[]; };
+layout(set = 0, binding = 5) restrict writeonly buffer MatterOutBuffer1 { uint matter_out1[]; };
+layout(set = 0, binding = 6) restrict buffer ObjectsMatter1 { uint objects_matter1[]; };
+
+layout(set = 0, binding = 7) restrict buffer MatterInBuffer2 { uint matter_in2[]; };
+layout(set = 0, binding = 8) restrict writeonly buffer MatterOutBuffer2 { uint matter_out2[]; };
+layout(set = 0, binding = 9) restrict buffer ObjectsMatter2 { uint objects_matter2[]; };
+
+layout(set = 0, binding = 10) restrict buffer MatterInBuffer3 { uint matter_in3[]; };
+layout(set = 0, binding = 11) restrict writeonly buffer MatterOutBuffer3 { uint matter_out3[]; };
+layout(set = 0, binding = 12) restrict buffer ObjectsMatter3 { uint objects_matter3[]; };
+
+// Region of interest flags per tile, see simulation/includes.glsl
+layout(set = 0, binding = 13) restrict buffer TileActivityBuffer {
+    uint tile_activity[];
+};
+// Pressure of liquid cells in cells of liquid head, 0 elsewhere. See field_index
+layout(set = 0, binding = 14) restrict buffer PressureBuffer {
+    float pressure[];
+};
+// Horizontal velocity of liquid cells in cells per step, positive is right. See field_index
+layout(set = 0, binding = 15) restrict buffer VelocityBuffer {
+    float velocity[];
+};
+
+layout(push_constant) uniform PushConstants {
+    // Seed per compute chunk (in get_chunk_index order), see chunk_step_seed in ca_simulator.rs
+    vec4 chunk_seeds;
+    ivec2 sim_pos_offset;
+    ivec2 sim_chunk_start_offset;
+    // Canvas pos of the corner of region of interest tile 0
+    ivec2 roi_origin;
+} push_constants;
+
+// Must match region_of_interest.rs
+#define ROI_TILE_SIZE 32
+#define TILE_ACTIVE 1
+#define TILE_CHANGED 2
+
+const ivec2 SIM_CANVAS_SIZE = ivec2(sim_canvas_width, sim_canvas_height);
+const ivec2 HALF_CANVAS = SIM_CANVAS_SIZE / 2;
+
+struct Matter {
+    uint matter;
+    uint state;
+};
+
+Matter new_matter(uint matter) {
+    Matter m;
+    m.matter = matter;
+    m.state = matter_state[m.matter];
+    return m;
+}
+
+// https://stackoverflow.com/questions/4200224/random-noise-functions-for-glsl
+float PHI = 1.61803398874989484820459; // Golden ratio
+float rand(in vec2 xy, in float seed){
+    vec2 pos = vec2(xy.x + 0.5, xy.y + 0.5);
+    return fract(tan(distance(pos * PHI, pos) * seed) * pos.x);
+}
+
+ivec2 get_current_sim_pos() {
+    return ivec2(gl_GlobalInvocationID.xy) - HALF_CANVAS + push_constants.sim_pos_offset;
+}
+
+ivec2 get_local_pos(ivec2 pos) {
+    return pos + HALF_CANVAS - push_constants.sim_pos_offset;
+}
+
+int get_index(ivec2 pos) {
+    return pos.y * sim_canvas_width + pos.x;
+}
+
+ivec2 get_pos_inside_chunk(ivec2 pos) {
+    ivec2 diff = pos - push_constants.sim_chunk_start_offset;
+    return diff % SIM_CANVAS_SIZE;
+}
+
+int get_chunk_index(ivec2 pos) {
+    ivec2 pos_on_4_chunks = (pos - push_constants.sim_chunk_start_offset) / SIM_CANVAS_SIZE;
+    return pos_on_4_chunks.y * 2 + pos_on_4_chunks.x;
+}
+
+float rand_at(ivec2 pos, float seed_offset) {
+    return rand(pos, push_constants.chunk_seeds[get_chunk_index(pos)] + seed_offset);
+}
+
+// Fields wrap around canvas pos, so a cell keeps its values when sim area moves. Cells entering
+// sim area start with stale values, which the next pressure iterations overwrite
+int field_index(ivec2 pos) {
+    ivec2 wrapped = pos - SIM_CANVAS_SIZE * ivec2(floor(vec2(pos) / vec2(SIM_CANVAS_SIZE)));
+    return get_index(wrapped);
+}
+
+bool is_inside_sim_canvas(ivec2 pos) {
+    ivec2 local_pos = get_local_pos(pos);
+    return local_pos.x >= 0 && local_pos.x < sim_canvas_width &&
+    local_pos.y >= 0 && local_pos.y < sim_canvas_height;
+}
+
+uint get_matter_in(ivec2 pos) {
+    int index = get_index(get_pos_inside_chunk(pos));
+    int chunk_index = get_chunk_index(pos);
+    if (chunk_index == 0) {
+        return matter_in0[index];
+    } else if (chunk_index == 1) {
+        return matter_in1[index];
+    } else if (chunk_index == 2) {
+        return matter_in2[index];
+    } else if (chunk_index == 3) {
+        return matter_in3[index];
+    }
+    return matter_in0[index];
+}
+
+uint get_objects_matter(ivec2 pos) {
+    int index = get_index(get_pos_inside_chunk(pos));
+    int chunk_index = get_chunk_index(pos);
+    if (chunk_index == 0) {
+        return objects_matter0[index];
+    } else if (chunk_index == 1) {
+        return objects_matter1[index];
+    } else if (chunk_index == 2) {
+        return objects_matter2[index];
+    } else if (chunk_index == 3) {
+        return objects_matter3[index];
+    }
+    return objects_matter0[index];
+}
+
+int get_tile_index(ivec2 pos) {
+    ivec2 tile = (pos - push_constants.roi_origin) / ROI_TILE_SIZE;
+    return tile.y * (sim_canvas_width / ROI_TILE_SIZE + 1) + tile.x;
+}
+
+bool is_frozen(ivec2 pos) {
+    return (tile_activity[get_tile_index(pos)] & TILE_ACTIVE) == 0;
+}
+
+Matter read_matter(ivec2 pos) {
+    uint obj_matter = get_objects_matter(pos);
+    if (obj_matter != empty) {
+        Matter matter = new_matter(obj_matter);
+        matter.state = state_object;
+        return matter;
+    } else {
+        return new_matter(get_matter_in(pos));
+    }
+}
+
+// Cells outside sim canvas & frozen cells act like objects, nothing moves in or out of them
+Matter read_neighbor(ivec2 pos) {
+    if (!is_inside_sim_canvas(pos)) {
+        Matter matter = new_matter(empty);
+        matter.state = state_object;
+        return matter;
+    }
+    Matter matter = read_matter(pos);
+    if (is_frozen(pos)) {
+        matter.state = state_object;
+    }
+    return matter;
+}
+
+void write_matter(ivec2 pos, Matter matter) {
+    int index = get_index(get_pos_inside_chunk(pos));
+    int chunk_index = get_chunk_index(pos);
+    if (chunk_index == 0) {
+        matter_out0[index] = matter.matter;
+    } else if (chunk_index == 1) {
+        matter_out1[index] = matter.matter;
+    } else if (chunk_index == 2) {
+        matter_out2[index] = matter.matter;
+    } else if (chunk_index == 3) {
+        matter_out3[index] = matter.matter;
+    }
+    // Changing cells keep their tile simulated
+    if (matter.matter != get_matter_in(pos) && get_objects_matter(pos) == empty) {
+        tile_activity[get_tile_index(pos)] = TILE_ACTIVE | TILE_CHANGED;
+    }
+}
+
+// Frozen cells only carry their matter over to output. Returns whether pos is frozen
+bool keeps_frozen(ivec2 pos) {
+    if (!is_frozen(pos)) {
+        return false;
+    }
+    Matter matter;
+    matter.matter = get_matter_in(pos);
+    write_matter(pos, matter);
+    return true;
+}
+
+bool is_empty(Matter matter) {
+    return matter.matter == empty && matter.state != state_object;
+}
+
+bool is_liquid(Matter matter) {
+    return matter.state == state_liquid;
+}
+
+bool is_liquid_at(ivec2 pos) {
+    return is_liquid(read_neighbor(pos));
+}
+
+bool is_empty_at(ivec2 pos) {
+    return is_empty(read_neighbor(pos));
+}
+
+const ivec2 UP = ivec2(0, 1);
+const ivec2 DOWN = ivec2(0, -1);
+const ivec2 LEFT = ivec2(-1, 0);
+const ivec2 RIGHT = ivec2(1, 0);
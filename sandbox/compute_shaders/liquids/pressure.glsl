@@ -0,0 +1,28 @@
+#version 450
+
+#include "includes.glsl"
+
+// Pressure lost per cell it's carried sideways or upwards, so stale pressure fades & long tunnels
+// have some friction
+#define PRESSURE_LOSS 0.05
+#define MAX_PRESSURE 1024.0
+
+float pressure_at(ivec2 pos) {
+    return is_liquid_at(pos) ? pressure[field_index(pos)] : 0.0;
+}
+
+// Liquid head at pos: one cell for itself plus head carried from connected liquid. Iterated in
+// place a few times per step, so pressure spreads a few cells per step
+void main() {
+    ivec2 pos = get_current_sim_pos();
+    int index = field_index(pos);
+    if (!is_liquid_at(pos)) {
+        pressure[index] = 0.0;
+        return;
+    }
+    float p = pressure_at(pos + UP) + 1.0;
+    p = max(p, pressure_at(pos + LEFT) - PRESSURE_LOSS);
+    p = max(p, pressure_at(pos + RIGHT) - PRESSURE_LOSS);
+    p = max(p, pressure_at(pos + DOWN) - 1.0 - PRESSURE_LOSS);
+    pressure[index] = min(p, MAX_PRESSURE);
+}
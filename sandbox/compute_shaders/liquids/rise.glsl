@@ -0,0 +1,116 @@
+#version 450
+
+#include "includes.glsl"
+
+// Cells scanned up & down a liquid column, taller columns don't rise
+#define RISE_REACH 32
+// Surplus pressure over a surface cell's own head beyond which its column starts to rise
+#define RISE_THRESHOLD 1.0
+
+// Surface liquid at top rises to empty above it by chance of its surplus pressure
+bool top_rises(ivec2 top) {
+    float surplus = pressure[field_index(top)] - 1.0;
+    return is_empty_at(top + UP) && rand_at(top, 13.0) < surplus - RISE_THRESHOLD;
+}
+
+// Liquid column at pos has a rising surface within reach
+bool wants_rise(ivec2 pos) {
+    for (int i = 0; i < RISE_REACH; i++) {
+        ivec2 above = pos + UP * (i + 1);
+        if (!is_liquid_at(above)) {
+            return top_rises(above + DOWN);
+        }
+    }
+    return false;
+}
+
+// Bottom of liquid column within reach, or pos itself if none
+ivec2 column_bottom(ivec2 pos) {
+    for (int i = 0; i < RISE_REACH; i++) {
+        ivec2 below = pos + DOWN * (i + 1);
+        if (!is_liquid_at(below)) {
+            return below + UP;
+        }
+    }
+    return pos;
+}
+
+bool is_column_bottom(ivec2 pos) {
+    return is_liquid_at(pos) && !is_liquid_at(pos + DOWN);
+}
+
+// Liquid whose column doesn't rise can be pulled by a neighboring column
+bool is_pullable(ivec2 pos) {
+    if (!is_liquid_at(pos)) {
+        return false;
+    }
+    ivec2 bottom = column_bottom(pos);
+    return !is_column_bottom(bottom) || !wants_rise(bottom);
+}
+
+// Side (-1 or 1) a rising column's bottom pulls liquid from, the one with more pressure. 0 if
+// there's nothing to pull
+int candidate_pull(ivec2 bottom) {
+    if (!is_column_bottom(bottom) || !wants_rise(bottom)) {
+        return 0;
+    }
+    int side = 0;
+    float side_pressure = 0.0;
+    for (int dir = -1; dir <= 1; dir += 2) {
+        ivec2 source = bottom + ivec2(dir, 0);
+        if (is_pullable(source) && pressure[field_index(source)] > side_pressure) {
+            side = dir;
+            side_pressure = pressure[field_index(source)];
+        }
+    }
+    return side;
+}
+
+// When two bottoms pull the same liquid, the left one gets it
+int granted_pull(ivec2 bottom) {
+    int side = candidate_pull(bottom);
+    if (side == -1 && candidate_pull(bottom + 2 * LEFT) == 1) {
+        return 0;
+    }
+    return side;
+}
+
+// Decided at column's bottom, so all cells of a column agree
+bool column_rises(ivec2 pos) {
+    if (!is_liquid_at(pos)) {
+        return false;
+    }
+    ivec2 bottom = column_bottom(pos);
+    return is_column_bottom(bottom) && granted_pull(bottom) != 0;
+}
+
+bool is_pulled(ivec2 pos) {
+    return (is_column_bottom(pos + LEFT) && granted_pull(pos + LEFT) == 1) ||
+    (is_column_bottom(pos + RIGHT) && granted_pull(pos + RIGHT) == -1);
+}
+
+// Liquid columns with surplus pressure rise one cell as a whole, their bottom pulling liquid from
+// the side pressure comes from. The gap left there is filled from above by falling liquid, so
+// the higher side sinks as the lower rises, e.g. in U-shaped tunnels
+void main() {
+    ivec2 pos = get_current_sim_pos();
+    if (keeps_frozen(pos)) {
+        return;
+    }
+    Matter current = read_matter(pos);
+    Matter m = current;
+    if (is_empty(current)) {
+        if (column_rises(pos + DOWN)) {
+            m = read_matter(pos + DOWN);
+        }
+    } else if (column_rises(pos)) {
+        if (is_liquid_at(pos + DOWN)) {
+            m = read_matter(pos + DOWN);
+        } else {
+            m = read_matter(pos + ivec2(granted_pull(pos), 0));
+        }
+    } else if (is_liquid(current) && is_pulled(pos)) {
+        m = new_matter(empty);
+    }
+    write_matter(pos, m);
+}
@@ -0,0 +1,32 @@
+#version 450
+
+#include "includes.glsl"
+
+#define VELOCITY_DAMPING 0.8
+#define PRESSURE_ACCELERATION 0.1
+
+// Pressure pushing liquid from a side. Empty pushes nothing & anything else blocks flow
+float side_pressure(ivec2 side, float own) {
+    Matter matter = read_neighbor(side);
+    if (is_liquid(matter)) {
+        return pressure[field_index(side)];
+    } else if (is_empty(matter)) {
+        return 0.0;
+    }
+    return own;
+}
+
+// Horizontal velocity accelerates down pressure slope & is damped, like momentum
+void main() {
+    ivec2 pos = get_current_sim_pos();
+    int index = field_index(pos);
+    if (!is_liquid_at(pos)) {
+        velocity[index] = 0.0;
+        return;
+    }
+    float own = pressure[index];
+    float slope = side_pressure(pos + LEFT, own) - side_pressure(pos + RIGHT, own);
+    velocity[index] = clamp(
+        velocity[index] * VELOCITY_DAMPING + slope * PRESSURE_ACCELERATION, -1.0, 1.0
+    );
+}
@@ -0,0 +1,146 @@
+#version 450
+
+// Brush strokes queued on cpu (see PaintStroke in ca_simulator.rs) painted at the start of a
+// step. Each cell applies the strokes covering it in order, writing matter in place
+
+// Specialization constants
+layout(constant_id = 0) const uint empty = 1;
+layout(constant_id = 1) const int sim_canvas_width = 1;
+layout(constant_id = 2) const int bitmap_ratio = 1;
+layout(constant_id = 3) const uint state_empty = 1;
+layout(constant_id = 4) const uint state_powder = 1;
+layout(constant_id = 5) const uint state_liquid = 1;
+layout(constant_id = 6) const uint state_solid = 1;
+layout(constant_id = 7) const uint state_solid_gravity = 1;
+layout(constant_id = 8) const uint state_gas = 1;
+layout(constant_id = 9) const uint state_energy = 1;
+layout(constant_id = 10) const uint state_object = 1;
+layout(constant_id = 13) const int sim_canvas_height = 1;
+
+// X & Y input as specialization constant
+layout(local_size_x_id = 11, local_size_y_id = 12, local_size_z = 1) in;
+
+layout(set = 0, binding = 0) restrict buffer MatterInBuffer0 { uint matter_in0[]; };
+layout(set = 0, binding = 1) restrict buffer MatterInBuffer1 { uint matter_in1[]; };
+layout(set = 0, binding = 2) restrict buffer MatterInBuffer2 { uint matter_in2[]; };
+layout(set = 0, binding = 3) restrict buffer MatterInBuffer3 { uint matter_in3[]; };
+
+// Region of interest flags per tile, see simulation/includes.glsl
+layout(set = 0, binding = 4) restrict buffer TileActivityBuffer {
+    uint tile_activity[];
+};
+// STROKE_STRIDE values per stroke: center x & y (as int bits), radius (as float bits), matter
+// & shape
+layout(set = 0, binding = 5) restrict readonly buffer StrokeBuffer {
+    uint strokes[];
+};
+layout(set = 0, binding = 6) restrict readonly buffer MatterCharacteristicsBuffer {
+    uint matter_characteristics[];
+};
+
+layout(push_constant) uniform PushConstants {
+    ivec2 sim_pos_offset;
+    ivec2 sim_chunk_start_offset;
+    // Canvas pos of the corner of region of interest tile 0
+    ivec2 roi_origin;
+    uint num_strokes;
+} push_constants;
+
+// Must match ca_simulator.rs
+#define STROKE_STRIDE 5
+#define SHAPE_ROUND 0
+#define SHAPE_SQUARE 1
+#define MATTER_PROTECTED (uint(1) << 31)
+// Must match region_of_interest.rs
+#define ROI_TILE_SIZE 32
+#define TILE_ACTIVE 1
+#define TILE_CHANGED 2
+
+const ivec2 SIM_CANVAS_SIZE = ivec2(sim_canvas_width, sim_canvas_height);
+const ivec2 HALF_CANVAS = SIM_CANVAS_SIZE / 2;
+
+ivec2 get_current_sim_pos() {
+    return ivec2(gl_GlobalInvocationID.xy) - HALF_CANVAS + push_constants.sim_pos_offset;
+}
+
+int get_index(ivec2 pos) {
+    return pos.y * sim_canvas_width + pos.x;
+}
+
+ivec2 get_pos_inside_chunk(ivec2 pos) {
+    ivec2 diff = pos - push_constants.sim_chunk_start_offset;
+    return diff % SIM_CANVAS_SIZE;
+}
+
+int get_chunk_index(ivec2 pos) {
+    ivec2 pos_on_4_chunks = (pos - push_constants.sim_chunk_start_offset) / SIM_CANVAS_SIZE;
+    return pos_on_4_chunks.y * 2 + pos_on_4_chunks.x;
+}
+
+int get_tile_index(ivec2 pos) {
+    ivec2 tile = (pos - push_constants.roi_origin) / ROI_TILE_SIZE;
+    return tile.y * (sim_canvas_width / ROI_TILE_SIZE + 1) + tile.x;
+}
+
+uint get_matter_in(ivec2 pos) {
+    int index = get_index(get_pos_inside_chunk(pos));
+    int chunk_index = get_chunk_index(pos);
+    if (chunk_index == 0) {
+        return matter_in0[index];
+    } else if (chunk_index == 1) {
+        return matter_in1[index];
+    } else if (chunk_index == 2) {
+        return matter_in2[index];
+    } else if (chunk_index == 3) {
+        return matter_in3[index];
+    }
+    return matter_in0[index];
+}
+
+void set_matter_in(ivec2 pos, uint matter) {
+    int index = get_index(get_pos_inside_chunk(pos));
+    int chunk_index = get_chunk_index(pos);
+    if (chunk_index == 0) {
+        matter_in0[index] = matter;
+    } else if (chunk_index == 1) {
+        matter_in1[index] = matter;
+    } else if (chunk_index == 2) {
+        matter_in2[index] = matter;
+    } else if (chunk_index == 3) {
+        matter_in3[index] = matter;
+    }
+}
+
+// Same cells as Simulation::paint_round & paint_square
+bool covers(ivec2 pos, ivec2 center, float radius, uint shape) {
+    if (shape == SHAPE_SQUARE) {
+        int half_size = int(radius * 2.0) / 2;
+        ivec2 offset = pos - center;
+        return offset.x >= -half_size && offset.x < half_size &&
+        offset.y >= -half_size && offset.y < half_size;
+    }
+    return round(distance(vec2(pos), vec2(center))) <= radius;
+}
+
+void main() {
+    ivec2 pos = get_current_sim_pos();
+    uint previous = get_matter_in(pos);
+    uint current = previous;
+    for (uint i = 0; i < push_constants.num_strokes; i++) {
+        uint base = i * STROKE_STRIDE;
+        ivec2 center = ivec2(int(strokes[base]), int(strokes[base + 1]));
+        float radius = uintBitsToFloat(strokes[base + 2]);
+        uint matter = strokes[base + 3];
+        // Like cpu painting, matter only goes to empty cells & empty erases unprotected cells
+        bool is_protected = (matter_characteristics[current] & MATTER_PROTECTED) != 0;
+        if (covers(pos, center, radius, strokes[base + 4]) &&
+        (current == empty || (matter == empty && !is_protected))) {
+            current = matter;
+        }
+    }
+    if (current != previous) {
+        set_matter_in(pos, current);
+        // Painted tiles are simulated even if they were frozen
+        tile_activity[get_tile_index(pos)] = TILE_ACTIVE | TILE_CHANGED;
+    }
+}
@@ -0,0 +1,53 @@
+#version 450
+
+// Synthesizes 2 x 2 sub-cells per cell of a chunk color image, only for rendering. Corners
+// between matters are smoothed (Scale2x) & sub-cells get a little noise seeded by cell color, so
+// each matter gets its own grain
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0, rgba8) restrict uniform readonly image2D canvas_img;
+layout(set = 0, binding = 1, rgba8) restrict uniform writeonly image2D detail_img;
+
+#define NOISE_AMOUNT 0.08
+
+// https://stackoverflow.com/questions/4200224/random-noise-functions-for-glsl
+float PHI = 1.61803398874989484820459; // Golden ratio
+float rand(in vec2 xy, in float seed){
+    vec2 pos = vec2(xy.x + 0.5, xy.y + 0.5);
+    return fract(tan(distance(pos * PHI, pos) * seed) * pos.x);
+}
+
+vec4 load_color(ivec2 pos) {
+    return imageLoad(canvas_img, clamp(pos, ivec2(0), imageSize(canvas_img) - 1));
+}
+
+// Cell colors of a matter vary in brightness (see vary_color_rgb), so colors are compared by
+// their tint & roughly by brightness
+bool same_matter(vec4 a, vec4 b) {
+    float brightness_a = (a.r + a.g + a.b) / 3.0;
+    float brightness_b = (b.r + b.g + b.b) / 3.0;
+    return distance(a.rgb - brightness_a, b.rgb - brightness_b) < 0.08 &&
+    abs(brightness_a - brightness_b) < 0.22 && abs(a.a - b.a) < 0.5;
+}
+
+void main() {
+    ivec2 detail_pos = ivec2(gl_GlobalInvocationID.xy);
+    ivec2 pos = detail_pos / 2;
+    ivec2 sub = detail_pos % 2;
+    vec4 color = load_color(pos);
+    // Neighbors on the side of the sub-cell & opposite to it
+    vec4 horizontal = load_color(pos + ivec2(sub.x == 0 ? -1 : 1, 0));
+    vec4 vertical = load_color(pos + ivec2(0, sub.y == 0 ? -1 : 1));
+    vec4 opposite_horizontal = load_color(pos + ivec2(sub.x == 0 ? 1 : -1, 0));
+    vec4 opposite_vertical = load_color(pos + ivec2(0, sub.y == 0 ? 1 : -1));
+    if (same_matter(horizontal, vertical) && !same_matter(horizontal, opposite_vertical) &&
+    !same_matter(vertical, opposite_horizontal)) {
+        color = vertical;
+    }
+    if (color.a > 0.0) {
+        float seed = 1.0 + dot(floor(color.rgb * 8.0), vec3(0.11, 0.23, 0.37));
+        color.rgb += (rand(vec2(detail_pos), seed) - 0.5) * NOISE_AMOUNT;
+    }
+    imageStore(detail_img, detail_pos, color);
+}
@@ -0,0 +1,126 @@
+#version 450
+
+// Particles of reactions emitted by the react kernel, only for rendering. Embers rise from matter
+// catching fire, sparks burst from energy turning into other matter & splashes fly from liquid
+// boiling away. Clear stage clears the image per pixel, update stage spawns, moves & draws one
+// particle slot per invocation
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+// Per particle: canvas x & y, velocity x & y, life left in frames (floats as bits), kind & color
+layout(set = 0, binding = 0) restrict buffer ParticlesBuffer { uint particles[]; };
+// Per spawn: canvas x & y, kind & color
+layout(set = 0, binding = 1) restrict readonly buffer SpawnsBuffer { uint spawns[]; };
+layout(set = 0, binding = 2, rgba8) restrict uniform writeonly image2D particles_img;
+
+layout(push_constant) uniform PushConstants {
+    // Canvas pos of image's corner
+    ivec2 image_origin;
+    // Slot of the first spawned particle, spawns fill slots from there
+    uint cursor;
+    uint num_spawns;
+    uint stage;
+    uint seed;
+} push_constants;
+
+// Must match particles.rs
+#define MAX_PARTICLES 8192
+#define PARTICLE_STRIDE 7
+#define SPAWN_STRIDE 4
+#define STAGE_CLEAR 0
+#define KIND_EMBER 0
+#define KIND_SPARK 1
+#define KIND_SPLASH 2
+
+// Cells per frame squared
+#define GRAVITY 0.04
+// Particles fade out over their last frames
+#define FADE_FRAMES 8.0
+
+// https://stackoverflow.com/questions/4200224/random-noise-functions-for-glsl
+float PHI = 1.61803398874989484820459; // Golden ratio
+float rand(in vec2 xy, in float seed){
+    vec2 pos = vec2(xy.x + 0.5, xy.y + 0.5);
+    return fract(tan(distance(pos * PHI, pos) * seed) * pos.x);
+}
+
+vec2 read_vec2(uint index) {
+    return vec2(uintBitsToFloat(particles[index]), uintBitsToFloat(particles[index + 1]));
+}
+
+void write_vec2(uint index, vec2 value) {
+    particles[index] = floatBitsToUint(value.x);
+    particles[index + 1] = floatBitsToUint(value.y);
+}
+
+void spawn(uint slot, uint spawn_index) {
+    uint offset = spawn_index * SPAWN_STRIDE;
+    vec2 pos = vec2(int(spawns[offset]), int(spawns[offset + 1])) + 0.5;
+    uint kind = spawns[offset + 2];
+    vec2 rand_pos = vec2(float(slot), float(push_constants.seed % 4096));
+    float r0 = rand(rand_pos, 1.0);
+    float r1 = rand(rand_pos, 2.0);
+    vec2 velocity;
+    float life;
+    if (kind == KIND_EMBER) {
+        velocity = vec2((r0 - 0.5) * 0.3, 0.2 + r1 * 0.4);
+        life = 30.0 + r1 * 30.0;
+    } else if (kind == KIND_SPARK) {
+        float angle = r0 * 6.2831853;
+        velocity = vec2(cos(angle), sin(angle)) * (0.5 + r1);
+        life = 10.0 + r1 * 15.0;
+    } else {
+        velocity = vec2((r0 - 0.5), 0.6 + r1 * 0.6);
+        life = 20.0 + r1 * 20.0;
+    }
+    uint index = slot * PARTICLE_STRIDE;
+    write_vec2(index, pos);
+    write_vec2(index + 2, velocity);
+    particles[index + 4] = floatBitsToUint(life);
+    particles[index + 5] = kind;
+    particles[index + 6] = spawns[offset + 3];
+}
+
+void update(uint slot) {
+    uint index = slot * PARTICLE_STRIDE;
+    float life = uintBitsToFloat(particles[index + 4]);
+    if (life <= 0.0) {
+        return;
+    }
+    vec2 pos = read_vec2(index);
+    vec2 velocity = read_vec2(index + 2);
+    if (particles[index + 5] == KIND_EMBER) {
+        // Embers drift up slowing down sideways
+        velocity.x *= 0.95;
+        velocity.y += GRAVITY * 0.25;
+    } else {
+        velocity.y -= GRAVITY;
+    }
+    pos += velocity;
+    life -= 1.0;
+    write_vec2(index, pos);
+    write_vec2(index + 2, velocity);
+    particles[index + 4] = floatBitsToUint(life);
+
+    ivec2 img_pos = ivec2(floor(pos)) - push_constants.image_origin;
+    if (life > 0.0 && all(greaterThanEqual(img_pos, ivec2(0))) &&
+    all(lessThan(img_pos, imageSize(particles_img)))) {
+        vec4 color = unpackUnorm4x8(particles[index + 6]);
+        color.a *= clamp(life / FADE_FRAMES, 0.0, 1.0);
+        imageStore(particles_img, img_pos, color);
+    }
+}
+
+void main() {
+    if (push_constants.stage == STAGE_CLEAR) {
+        imageStore(particles_img, ivec2(gl_GlobalInvocationID.xy), vec4(0.0));
+        return;
+    }
+    uint slot = gl_WorkGroupID.x * 64 + gl_LocalInvocationIndex;
+    // Spawns replace oldest particles, which are the slots after cursor
+    uint spawn_index = (slot + MAX_PARTICLES - push_constants.cursor) % MAX_PARTICLES;
+    if (spawn_index < push_constants.num_spawns) {
+        spawn(slot, spawn_index);
+    }
+    update(slot);
+}
@@ -2,6 +2,39 @@
 
 #include "includes.glsl"
 
+// Must match MatterCharacteristic::MELTING & BURNING
+#define CHARACTERISTIC_MELTING (uint(1) << 2)
+#define CHARACTERISTIC_BURNING (uint(1) << 4)
+// Cells around a solid that heat it
+#define HEAT_GLOW_REACH 2
+
+// Cells don't have a temperature, so solids are as hot as how many melting or burning cells
+// there are near them, closer ones counting more. 0.0 - 1.0
+float get_heat(ivec2 pos) {
+    float heat = 0.0;
+    for (int y = -HEAT_GLOW_REACH; y <= HEAT_GLOW_REACH; y++) {
+        for (int x = -HEAT_GLOW_REACH; x <= HEAT_GLOW_REACH; x++) {
+            ivec2 neighbor = pos + ivec2(x, y);
+            if (!is_inside_sim_canvas(neighbor)) {
+                continue;
+            }
+            uint characteristics = matter_characteristics[get_matter_in(neighbor)];
+            if ((characteristics & (CHARACTERISTIC_MELTING | CHARACTERISTIC_BURNING)) != 0) {
+                heat += 1.0 - length(vec2(x, y)) / float(HEAT_GLOW_REACH + 1);
+            }
+        }
+    }
+    return clamp(heat / 4.0, 0.0, 1.0);
+}
+
+// Black-body like ramp: dull red, orange, then white hot
+vec3 heat_glow(float heat) {
+    vec3 red = vec3(0.5, 0.05, 0.0);
+    vec3 orange = vec3(1.0, 0.45, 0.05);
+    vec3 white = vec3(1.0, 0.95, 0.8);
+    return heat < 0.5 ? mix(red, orange, heat * 2.0) : mix(orange, white, heat * 2.0 - 1.0);
+}
+
 void write_color_to_image(ivec2 pos) {
     int index = get_index(pos);
     Matter matter = read_matter(pos);
@@ -10,10 +43,16 @@ void write_color_to_image(ivec2 pos) {
         color = color_i32_to_vec4(int(get_objects_color(pos)));
     } else {
         color = vary_color_rgb(color_i32_to_vec4(int(matter_colors[matter.matter])), pos);
+        if (matter.state == state_solid || matter.state == state_solid_gravity) {
+            float heat = get_heat(pos);
+            if (heat > 0.0) {
+                color.rgb = mix(color.rgb, heat_glow(heat), 0.25 + heat * 0.6);
+            }
+        }
     }
     write_image_color(pos, color);
 }
 
 void main() {
     write_color_to_image(get_current_sim_pos());
-}
\ No newline at end of file
+}
@@ -0,0 +1,67 @@
+#version 450
+
+#include "includes.glsl"
+
+float decay_probability(uint matter) {
+    return uintBitsToFloat(matter_decay[matter * DECAY_STRIDE]);
+}
+
+uint decay_becomes(uint matter) {
+    return matter_decay[matter * DECAY_STRIDE + 1];
+}
+
+uint decay_emits(uint matter) {
+    return matter_decay[matter * DECAY_STRIDE + 2];
+}
+
+// Same random for the decaying cell & its neighbors, so they agree on what happens
+bool decays(ivec2 pos, Matter matter) {
+    return !is_object(matter) && !matter.is_protected &&
+    rand_at(pos, 5.0) < decay_probability(matter.matter);
+}
+
+int emission_dir(ivec2 pos) {
+    return int(rand_at(pos, 6.0) * 8.0) % 8;
+}
+
+// Cells can only write to themselves, thus an empty cell takes the emission of a neighbor that
+// decays towards it (instead of the decaying cell writing to its neighbor)
+bool receives_emission(ivec2 pos, out uint emitted, out uint emitter) {
+    for (int dir = 0; dir < 8; dir++) {
+        ivec2 neighbor_pos = get_pos_at_dir(pos, dir);
+        if (neighbor_pos == pos || !is_inside_sim_canvas(neighbor_pos) || is_frozen(neighbor_pos)) {
+            continue;
+        }
+        Matter neighbor = read_matter(neighbor_pos);
+        uint emits = decay_emits(neighbor.matter);
+        if (emits != empty && decays(neighbor_pos, neighbor) &&
+        get_pos_at_dir(neighbor_pos, emission_dir(neighbor_pos)) == pos) {
+            emitted = emits;
+            emitter = neighbor.matter;
+            return true;
+        }
+    }
+    return false;
+}
+
+void cellular_automata_decay(ivec2 pos) {
+    Matter current = read_matter(pos);
+    Matter m = current;
+    uint emitted;
+    uint emitter;
+    if (decays(pos, current)) {
+        m = new_matter(decay_becomes(current.matter));
+        count_reaction(current.matter, DECAY_COUNT_INDEX);
+    } else if (is_empty(current) && receives_emission(pos, emitted, emitter)) {
+        m = new_matter(emitted);
+        count_reaction(emitter, EMISSION_COUNT_INDEX);
+    }
+    write_matter(pos, m);
+}
+
+void main() {
+    ivec2 pos = get_current_sim_pos();
+    if (!keeps_frozen(pos)) {
+        cellular_automata_decay(pos);
+    }
+}
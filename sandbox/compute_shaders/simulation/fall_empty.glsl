@@ -7,14 +7,20 @@ void cellular_automata_fall_empty(ivec2 pos) {
     Matter up = get_neighbor(pos, UP);
     Matter down = get_neighbor(pos, DOWN);
     Matter m = current;
-    if (!is_at_border_top() && falls_on_empty(up, current)) {
+    // Powders held up by a fan don't fall
+    if (!is_at_border_top() && falls_on_empty(up, current, get_pos_at_dir(pos, UP)) &&
+    !(is_powder(up) && is_blown_by_fan(get_pos_at_dir(pos, UP)))) {
         m = up;
-    } else if (!is_at_border_bottom() && falls_on_empty(current, down)) {
+    } else if (!is_at_border_bottom() && falls_on_empty(current, down, pos) &&
+    !(is_powder(current) && is_blown_by_fan(pos))) {
         m = down;
     }
     write_matter(pos, m);
 }
 
 void main() {
-    cellular_automata_fall_empty(get_current_sim_pos());
+    ivec2 pos = get_current_sim_pos();
+    if (!keeps_frozen(pos)) {
+        cellular_automata_fall_empty(pos);
+    }
 }
\ No newline at end of file
@@ -8,14 +8,17 @@ void cellular_automata_fall_swap(ivec2 pos) {
     Matter up = get_neighbor(pos, UP);
     Matter down = get_neighbor(pos, DOWN);
     Matter m = current;
-    if (!is_at_border_top() && falls_on_swap(up, current)) {
+    if (!is_at_border_top() && falls_on_swap(up, current, get_pos_at_dir(pos, UP))) {
         m = up;
-    } else if (!is_at_border_bottom() && falls_on_swap(current, down)) {
+    } else if (!is_at_border_bottom() && falls_on_swap(current, down, pos)) {
         m = down;
     }
     write_matter(pos, m);
 }
 
 void main() {
-    cellular_automata_fall_swap(get_current_sim_pos());
+    ivec2 pos = get_current_sim_pos();
+    if (!keeps_frozen(pos)) {
+        cellular_automata_fall_swap(pos);
+    }
 }
\ No newline at end of file
@@ -16,9 +16,9 @@ void cellular_automata_move_left_empty(ivec2 pos) {
     } else if (!is_at_border_left() && moves_on_empty_certainly(current, left, right, down)) {
         m = left;
     } else if (!is_at_border_right() && moves_on_empty_maybe(right, current, right_right, down_right,
-            rand(get_pos_at_dir(pos, RIGHT), push_constants.seed))) {
+            rand_at(get_pos_at_dir(pos, RIGHT), 0.0))) {
         m = right;
-    } else if (!is_at_border_left() && moves_on_empty_maybe(current, left, right, down, rand(pos, push_constants.seed))) {
+    } else if (!is_at_border_left() && moves_on_empty_maybe(current, left, right, down, rand_at(pos, 0.0))) {
         m = left;
     }
     write_matter(pos, m);
@@ -38,9 +38,9 @@ void cellular_automata_move_right_empty(ivec2 pos) {
     } else if (!is_at_border_right() && moves_on_empty_certainly(current, right, left, down)) {
         m = right;
     } else if (!is_at_border_left() && moves_on_empty_maybe(left, current, left_left, down_left,
-            rand(get_pos_at_dir(pos, LEFT), push_constants.seed))) {
+            rand_at(get_pos_at_dir(pos, LEFT), 0.0))) {
         m = left;
-    } else if (!is_at_border_right() && moves_on_empty_maybe(current, right, left, down, rand(pos, push_constants.seed))) {
+    } else if (!is_at_border_right() && moves_on_empty_maybe(current, right, left, down, rand_at(pos, 0.0))) {
         m = right;
     }
     write_matter(pos, m);
@@ -55,5 +55,8 @@ void cellular_automata_move_horizontal_empty(ivec2 pos) {
 }
 
 void main() {
-    cellular_automata_move_horizontal_empty(get_current_sim_pos());
+    ivec2 pos = get_current_sim_pos();
+    if (!keeps_frozen(pos)) {
+        cellular_automata_move_horizontal_empty(pos);
+    }
 }
\ No newline at end of file
@@ -14,9 +14,9 @@ void cellular_automata_move_left_swap(ivec2 pos) {
     } else if (!is_at_border_left() && moves_on_swap_certainly(current, left, right)) {
         m = left;
     } else if (!is_at_border_right() && moves_on_swap_maybe(right, current, right_right,
-                rand(get_pos_at_dir(pos, RIGHT), push_constants.seed))) {
+                rand_at(get_pos_at_dir(pos, RIGHT), 0.0))) {
         m = right;
-    } else if (!is_at_border_left() && moves_on_swap_maybe(current, left, right, rand(pos, push_constants.seed))) {
+    } else if (!is_at_border_left() && moves_on_swap_maybe(current, left, right, rand_at(pos, 0.0))) {
         m = left;
     }
     write_matter(pos, m);
@@ -34,9 +34,9 @@ void cellular_automata_move_right_swap(ivec2 pos) {
     } else if (!is_at_border_right() && moves_on_swap_certainly(current, right, left)) {
         m = right;
     } else if (!is_at_border_left() && moves_on_swap_maybe(left, current, left_left,
-                rand(get_pos_at_dir(pos, LEFT), push_constants.seed))) {
+                rand_at(get_pos_at_dir(pos, LEFT), 0.0))) {
         m = left;
-    } else if (!is_at_border_right() && moves_on_swap_maybe(current, right, left, rand(pos, push_constants.seed))) {
+    } else if (!is_at_border_right() && moves_on_swap_maybe(current, right, left, rand_at(pos, 0.0))) {
         m = right;
     }
     write_matter(pos, m);
@@ -51,5 +51,8 @@ void cellular_automata_move_horizontal_swap(ivec2 pos) {
 }
 
 void main() {
-    cellular_automata_move_horizontal_swap(get_current_sim_pos());
+    ivec2 pos = get_current_sim_pos();
+    if (!keeps_frozen(pos)) {
+        cellular_automata_move_horizontal_swap(pos);
+    }
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 // Specialization constants
 layout(constant_id = 0) const uint empty = 1;
-layout(constant_id = 1) const int sim_canvas_size = 1;
+layout(constant_id = 1) const int sim_canvas_width = 1;
 layout(constant_id = 2) const int bitmap_ratio = 1;
 layout(constant_id = 3) const uint state_empty = 1;
 layout(constant_id = 4) const uint state_powder = 1;
@@ -10,6 +10,7 @@ layout(constant_id = 7) const uint state_solid_gravity = 1;
 layout(constant_id = 8) const uint state_gas = 1;
 layout(constant_id = 9) const uint state_energy = 1;
 layout(constant_id = 10) const uint state_object = 1;
+layout(constant_id = 13) const int sim_canvas_height = 1;
 
 // X & Y input as specialization constant
 layout(local_size_x_id = 11, local_size_y_id = 12, local_size_z = 1) in;
@@ -69,28 +70,88 @@ layout(set = 0, binding = 26) restrict buffer ObjectsMatter3 { uint objects_matt
 layout(set = 0, binding = 27) restrict buffer ObjectsColor3 { uint objects_color3[]; };
 layout(set = 0, binding = 28, rgba8) restrict uniform writeonly image2D canvas_img3;
 
+// Decay per matter as (probability bits, becomes, emits). Packed in one buffer to stay within
+// buffer binding limits (macos)
+layout(set = 0, binding = 29) restrict buffer MatterDecayBuffer {
+    uint matter_decay[];
+};
+layout(set = 0, binding = 30) restrict buffer MatterGravityBuffer {
+    float matter_gravity[];
+};
+// Times each reaction, decay & decay emission of each matter occurred, read & reset on cpu for
+// reaction stats
+layout(set = 0, binding = 31) restrict buffer ReactionCountsBuffer {
+    uint reaction_counts[];
+};
+// Region of interest flags per tile (TILE_ACTIVE & TILE_CHANGED), written on cpu each step, see
+// region_of_interest.rs
+layout(set = 0, binding = 32) restrict buffer TileActivityBuffer {
+    uint tile_activity[];
+};
+// Reactions emitted for particle effects: count, then (canvas x, canvas y, matter before, matter
+// after) per emission. Read & reset on cpu, see render/particles.rs
+layout(set = 0, binding = 33) restrict buffer ParticleEmissionsBuffer {
+    uint particle_emission_count;
+    uint particle_emissions[];
+};
+
+// Must match MAX_PORTALS in portals.rs
+#define MAX_PORTALS 2
+
 layout(push_constant) uniform PushConstants {
-    float seed;
+    // Seed per compute chunk (in get_chunk_index order), see chunk_step_seed in ca_simulator.rs
+    vec4 chunk_seeds;
     uint sim_step;
     uint move_step;
     uint dispersion_step;
     uint dispersion_dir;
     ivec2 sim_pos_offset;
     ivec2 sim_chunk_start_offset;
+    // Portal pairs as (a.x, a.y, b.x, b.y), width 0 means the pair is unused
+    ivec4 portals[MAX_PORTALS];
+    ivec2 portal_widths;
+    // Canvas pos of the corner of region of interest tile 0
+    ivec2 roi_origin;
+    // Whether reactions are counted into reaction_counts, only while reaction stats are shown
+    uint count_reactions;
 } push_constants;
 
 #include "dirs.glsl"
 
 #define MAX_TRANSITIONS 5
-
-const ivec2 HALF_CANVAS = ivec2(sim_canvas_size / 2);
+// Must match REACTION_TICK_DIVIDER_SHIFT in ca_simulator.rs
+#define REACTION_TICK_DIVIDER_SHIFT 16
+// Must match MATTER_DECAY_STRIDE in ca_simulator.rs
+#define DECAY_STRIDE 3
+// Must match REACTION_COUNT_STRIDE in reaction_stats.rs: reactions, then decay & emission
+#define REACTION_COUNT_STRIDE (MAX_TRANSITIONS + 2)
+#define DECAY_COUNT_INDEX MAX_TRANSITIONS
+#define EMISSION_COUNT_INDEX (MAX_TRANSITIONS + 1)
+// Must match MatterCharacteristic::FAN & FAN_REACH
+#define CHARACTERISTIC_FAN (uint(1) << 20)
+#define FAN_REACH 16
+// Must match MATTER_PROTECTED_BIT in ca_simulator.rs
+#define MATTER_PROTECTED (uint(1) << 31)
+// Must match region_of_interest.rs
+#define ROI_TILE_SIZE 32
+#define TILE_ACTIVE 1
+#define TILE_CHANGED 2
+// Must match MAX_PARTICLE_EMISSIONS & PARTICLE_EMISSION_STRIDE in particles.rs
+#define MAX_PARTICLE_EMISSIONS 1024
+#define PARTICLE_EMISSION_STRIDE 4
+
+const ivec2 SIM_CANVAS_SIZE = ivec2(sim_canvas_width, sim_canvas_height);
+const ivec2 HALF_CANVAS = SIM_CANVAS_SIZE / 2;
 
 struct Matter {
     uint matter;
     uint state;
     uint dispersion;
     float weight;
+    float gravity;
     uint characteristics;
+    // Protected in editor, reactions don't change it
+    bool is_protected;
     uint[MAX_TRANSITIONS] reacts;
     uint[MAX_TRANSITIONS] reacts_direction;
     float[MAX_TRANSITIONS] reaction_probability;
@@ -102,8 +163,11 @@ Matter new_matter(uint matter) {
     m.matter = matter;
     m.state = matter_state[m.matter];
     m.weight = matter_weights[m.matter];
+    m.gravity = matter_gravity[m.matter];
     m.dispersion = matter_dispersion[m.matter];
-    m.characteristics = matter_characteristics[m.matter];
+    // Protection isn't a characteristic others react to
+    m.characteristics = matter_characteristics[m.matter] & ~MATTER_PROTECTED;
+    m.is_protected = (matter_characteristics[m.matter] & MATTER_PROTECTED) != 0;
     uint table_index = m.matter * MAX_TRANSITIONS;
     m.reacts[0] = matter_reaction_with[table_index + 0];
     m.reacts[1] = matter_reaction_with[table_index + 1];
@@ -131,6 +195,13 @@ Matter new_matter(uint matter) {
     return m;
 }
 
+void count_reaction(uint matter, uint index) {
+    if (push_constants.count_reactions == 0) {
+        return;
+    }
+    atomicAdd(reaction_counts[matter * REACTION_COUNT_STRIDE + index], 1);
+}
+
 // https://stackoverflow.com/questions/4200224/random-noise-functions-for-glsl
 float PHI = 1.61803398874989484820459; // Golden ratio
 float rand(in vec2 xy, in float seed){
@@ -147,22 +218,28 @@ ivec2 get_local_pos(ivec2 pos) {
 }
 
 int get_index(ivec2 pos) {
-    return pos.y * sim_canvas_size + pos.x;
+    return pos.y * sim_canvas_width + pos.x;
 }
 
 ivec2 get_pos_inside_chunk(ivec2 pos) {
     ivec2 diff = pos - push_constants.sim_chunk_start_offset;
-    return ivec2(diff.x % sim_canvas_size, diff.y % sim_canvas_size);
+    return diff % SIM_CANVAS_SIZE;
 }
 
 int get_chunk_index(ivec2 pos) {
-    ivec2 pos_on_4_chunks = (pos - push_constants.sim_chunk_start_offset) / sim_canvas_size;
+    ivec2 pos_on_4_chunks = (pos - push_constants.sim_chunk_start_offset) / SIM_CANVAS_SIZE;
     return pos_on_4_chunks.y * 2 + pos_on_4_chunks.x;
 }
 
+// Random value at pos, seeded by the chunk pos is in so it's the same whichever compute chunks
+// the chunk is simulated with
+float rand_at(ivec2 pos, float seed_offset) {
+    return rand(pos, push_constants.chunk_seeds[get_chunk_index(pos)] + seed_offset);
+}
+
 bool is_at_border_top() {
     ivec2 local_pos = ivec2(gl_GlobalInvocationID.xy);
-    return local_pos.y == sim_canvas_size - 1;
+    return local_pos.y == sim_canvas_height - 1;
 }
 
 bool is_at_border_bottom() {
@@ -172,7 +249,7 @@ bool is_at_border_bottom() {
 
 bool is_at_border_right() {
     ivec2 local_pos = ivec2(gl_GlobalInvocationID.xy);
-    return local_pos.x == sim_canvas_size - 1;
+    return local_pos.x == sim_canvas_width - 1;
 }
 
 bool is_at_border_left() {
@@ -227,8 +304,19 @@ uint get_objects_color(ivec2 pos) {
 
 bool is_inside_sim_canvas(ivec2 pos) {
     ivec2 local_pos = get_local_pos(pos);
-    return local_pos.x >= 0 && local_pos.x < sim_canvas_size &&
-        local_pos.y >= 0 && local_pos.y < sim_canvas_size;
+    return local_pos.x >= 0 && local_pos.x < sim_canvas_width &&
+        local_pos.y >= 0 && local_pos.y < sim_canvas_height;
+}
+
+int get_tile_index(ivec2 pos) {
+    ivec2 tile = (pos - push_constants.roi_origin) / ROI_TILE_SIZE;
+    return tile.y * (sim_canvas_width / ROI_TILE_SIZE + 1) + tile.x;
+}
+
+// Cells outside region of interest are frozen. They keep their matter & act like objects to
+// simulated neighbors, so nothing moves in or out of them
+bool is_frozen(ivec2 pos) {
+    return (tile_activity[get_tile_index(pos)] & TILE_ACTIVE) == 0;
 }
 
 Matter read_matter(ivec2 pos) {
@@ -254,6 +342,21 @@ void write_matter(ivec2 pos, Matter matter) {
     } else if (chunk_index == 3) {
         matter_out3[index] = matter.matter;
     }
+    // Changing cells keep their tile simulated
+    if (matter.matter != get_matter_in(pos) && get_objects_matter(pos) == empty) {
+        tile_activity[get_tile_index(pos)] = TILE_ACTIVE | TILE_CHANGED;
+    }
+}
+
+// Frozen cells only carry their matter over to output. Returns whether pos is frozen
+bool keeps_frozen(ivec2 pos) {
+    if (!is_frozen(pos)) {
+        return false;
+    }
+    Matter matter;
+    matter.matter = get_matter_in(pos);
+    write_matter(pos, matter);
+    return true;
 }
 
 void write_matter_both(ivec2 pos, Matter matter) {
@@ -302,8 +405,52 @@ void write_image_color(ivec2 pos, vec4 color) {
     }
 }
 
+bool is_in_portal_range(int x, int start, int width) {
+    return x >= start && x < start + width;
+}
+
+// Portals glue horizontal seams together: row a.y continues downwards to row b.y and
+// row b.y + 1 continues downwards to row a.y - 1 (and vice versa upwards).
+// Diagonal moves over a seam are blocked by returning the position itself.
 ivec2 get_pos_at_dir(ivec2 pos, int dir) {
-    return pos + OFFSETS[dir];
+    ivec2 offset = OFFSETS[dir];
+    if (offset.y == 0) {
+        return pos + offset;
+    }
+    for (int i = 0; i < MAX_PORTALS; i++) {
+        int width = push_constants.portal_widths[i];
+        if (width <= 0) {
+            continue;
+        }
+        ivec2 a = push_constants.portals[i].xy;
+        ivec2 b = push_constants.portals[i].zw;
+        ivec2 from;
+        ivec2 to;
+        if (offset.y < 0 && pos.y == a.y) {
+            from = a;
+            to = b;
+        } else if (offset.y < 0 && pos.y == b.y + 1) {
+            from = ivec2(b.x, b.y + 1);
+            to = ivec2(a.x, a.y - 1);
+        } else if (offset.y > 0 && pos.y == b.y) {
+            from = b;
+            to = a;
+        } else if (offset.y > 0 && pos.y == a.y - 1) {
+            from = ivec2(a.x, a.y - 1);
+            to = ivec2(b.x, b.y + 1);
+        } else {
+            continue;
+        }
+        bool source_in = is_in_portal_range(pos.x, from.x, width);
+        bool target_in = is_in_portal_range(pos.x + offset.x, from.x, width);
+        if (offset.x == 0 && source_in) {
+            return ivec2(to.x + pos.x - from.x, to.y);
+        }
+        if (offset.x != 0 && (source_in || target_in)) {
+            return pos;
+        }
+    }
+    return pos + offset;
 }
 
 // | 0 1 2 |
@@ -312,7 +459,11 @@ ivec2 get_pos_at_dir(ivec2 pos, int dir) {
 Matter get_neighbor(ivec2 pos, int dir) {
     ivec2 neighbor_pos = get_pos_at_dir(pos, dir);
     if (is_inside_sim_canvas(neighbor_pos)) {
-        return read_matter(neighbor_pos);
+        Matter neighbor = read_matter(neighbor_pos);
+        if (is_frozen(neighbor_pos)) {
+            neighbor.state = state_object;
+        }
+        return neighbor;
     } else {
         return new_matter(empty);
     }
@@ -339,8 +490,9 @@ bool is_gas(Matter matter) {
     return matter.state == state_gas;
 }
 
+// Frozen empty cells read as objects, see is_frozen
 bool is_empty(Matter matter) {
-    return matter.matter == state_empty;
+    return matter.matter == state_empty && matter.state != state_object;
 }
 
 bool is_powder(Matter matter) {
@@ -359,25 +511,66 @@ bool is_energy(Matter matter) {
     return matter.state == state_energy;
 }
 
+bool is_fan(Matter matter) {
+    return (matter.characteristics & CHARACTERISTIC_FAN) != 0;
+}
+
+// Fan blows powders upwards within its column until something solid blocks it
+bool is_blown_by_fan(ivec2 pos) {
+    for (int i = 1; i <= FAN_REACH; i++) {
+        ivec2 below = pos - ivec2(0, i);
+        if (!is_inside_sim_canvas(below)) {
+            return false;
+        }
+        Matter m = read_matter(below);
+        if (is_fan(m)) {
+            return true;
+        }
+        if (is_solid(m) || is_object(m)) {
+            return false;
+        }
+    }
+    return false;
+}
+
 bool is_gravity(Matter matter) {
     return is_powder(matter) || is_liquid(matter) || is_solid_gravity(matter);
 }
 
+// Negative gravity scale reverses direction: gravity matters rise & gases sink
+bool is_falling(Matter matter) {
+    return (is_gravity(matter) && matter.gravity > 0.0) || (is_gas(matter) && matter.gravity < 0.0);
+}
+
+bool is_rising(Matter matter) {
+    return (is_gas(matter) && matter.gravity > 0.0) || (is_gravity(matter) && matter.gravity < 0.0);
+}
+
+// Matter moves vertically on share of moves by its gravity scale. Random is taken at the moving
+// cell, so it & the cell it moves to agree
+bool moves_by_gravity(Matter matter, ivec2 pos) {
+    float scale = abs(matter.gravity);
+    return scale >= 1.0 || rand_at(pos, 7.0 + float(push_constants.move_step)) < scale;
+}
+
 // For anything that falls (liquid or powder)
-bool falls_on_empty(Matter from, Matter to) {
-    return is_gravity(from) && is_empty(to);
+bool falls_on_empty(Matter from, Matter to, ivec2 from_pos) {
+    return is_falling(from) && is_empty(to) && moves_by_gravity(from, from_pos);
 }
 
-bool falls_on_swap(Matter from, Matter to) {
-    return is_gravity(from) && (is_liquid(to) || is_gas(to) || is_energy(to)) && to.weight < from.weight;
+bool falls_on_swap(Matter from, Matter to, ivec2 from_pos) {
+    return is_falling(from) && (is_liquid(to) || is_gas(to) || is_energy(to)) && to.weight < from.weight &&
+    moves_by_gravity(from, from_pos);
 }
 
-bool rises_on_empty(Matter from, Matter to) {
-    return is_gas(from) && is_empty(to);
+bool rises_on_empty(Matter from, Matter to, ivec2 from_pos) {
+    return is_empty(to) && ((is_rising(from) && moves_by_gravity(from, from_pos)) ||
+    (is_powder(from) && is_blown_by_fan(from_pos)));
 }
 
-bool rises_on_swap(Matter from, Matter to) {
-    return is_gas(from) && (is_liquid(to) || is_powder(to) || is_energy(to)) && to.weight > from.weight;
+bool rises_on_swap(Matter from, Matter to, ivec2 from_pos) {
+    return is_rising(from) && (is_liquid(to) || is_powder(to) || is_energy(to)) && to.weight > from.weight &&
+    moves_by_gravity(from, from_pos);
 }
 
 /*
@@ -387,11 +580,11 @@ For powders
     f->t where x is space under f
 */
 bool slides_on_empty(Matter from_diagonal, Matter to_diagonal, Matter from_down) {
-    return is_powder(from_diagonal) && !is_empty(from_down) && !is_liquid(from_down) && is_empty(to_diagonal);
+    return is_powder(from_diagonal) && from_diagonal.gravity > 0.0 && !is_empty(from_down) && !is_liquid(from_down) && is_empty(to_diagonal);
 }
 
 bool slides_on_swap(Matter from_diagonal, Matter to_diagonal, Matter from_down) {
-    return is_powder(from_diagonal) && !is_empty(from_down) && !is_liquid(from_down) &&
+    return is_powder(from_diagonal) && from_diagonal.gravity > 0.0 && !is_empty(from_down) && !is_liquid(from_down) &&
     is_liquid(to_diagonal) && to_diagonal.weight < from_diagonal.weight;
 }
 
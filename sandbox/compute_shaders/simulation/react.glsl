@@ -50,10 +50,16 @@ Matter up_left, Matter up_right, Matter down_left, Matter down_right) {
     return reacts_up_left || reacts_up || reacts_up_right || reacts_right || reacts_down_right || reacts_down || reacts_down_left ||  reacts_left;
 }
 
+// Reactions with a tick divider are tried only every n sim steps. It's packed above directions
+bool reaction_ticks(uint reacts_direction) {
+    uint tick_divider = max(reacts_direction >> REACTION_TICK_DIVIDER_SHIFT, 1);
+    return push_constants.sim_step % tick_divider == 0;
+}
+
 bool transition_occurs(uint reacts, uint reacts_direction, float p, float transition_probability,
 Matter up, Matter down, Matter left, Matter right,
 Matter up_left, Matter up_right, Matter down_left, Matter down_right) {
-    return p < transition_probability &&
+    return p < transition_probability && reaction_ticks(reacts_direction) &&
     interacts_with_reactive(reacts, reacts_direction, up, down, left, right, up_left, up_right, down_left, down_right);
 }
 
@@ -71,7 +77,7 @@ Matter transition_into(Matter current, ivec2 pos) {
 
     Matter m = current;
 
-    float p = rand(pos, push_constants.seed);
+    float p = rand_at(pos, 0.0);
     uint reacts = current.reacts[0];
     uint reacts_direction = current.reacts_direction[0];
     float reaction_probability = current.reaction_probability[0];
@@ -79,10 +85,11 @@ Matter transition_into(Matter current, ivec2 pos) {
     if (transition_occurs(reacts, reacts_direction, p,
     reaction_probability, up, down, left, right, up_left, up_right, down_left, down_right)) {
         m = new_matter(reaction_transition);
+        count_reaction(current.matter, 0);
         return m;
     }
 
-    p = rand(pos, push_constants.seed + 1.0);
+    p = rand_at(pos, 1.0);
     reacts = current.reacts[1];
     reaction_probability = current.reaction_probability[1];
     reacts_direction = current.reacts_direction[1];
@@ -90,10 +97,11 @@ Matter transition_into(Matter current, ivec2 pos) {
     if (transition_occurs(reacts, reacts_direction, p,
     reaction_probability, up, down, left, right, up_left, up_right, down_left, down_right)) {
         m = new_matter(reaction_transition);
+        count_reaction(current.matter, 1);
         return m;
     }
 
-    p = rand(pos, push_constants.seed + 2.0);
+    p = rand_at(pos, 2.0);
     reacts = current.reacts[2];
     reaction_probability = current.reaction_probability[2];
     reacts_direction = current.reacts_direction[2];
@@ -101,10 +109,11 @@ Matter transition_into(Matter current, ivec2 pos) {
     if (transition_occurs(reacts, reacts_direction, p,
     reaction_probability, up, down, left, right, up_left, up_right, down_left, down_right)) {
         m = new_matter(reaction_transition);
+        count_reaction(current.matter, 2);
         return m;
     }
 
-    p = rand(pos, push_constants.seed + 3.0);
+    p = rand_at(pos, 3.0);
     reacts = current.reacts[3];
     reaction_probability = current.reaction_probability[3];
     reacts_direction = current.reacts_direction[3];
@@ -112,10 +121,11 @@ Matter transition_into(Matter current, ivec2 pos) {
     if (transition_occurs(reacts, reacts_direction, p,
     reaction_probability, up, down, left, right, up_left, up_right, down_left, down_right)) {
         m = new_matter(reaction_transition);
+        count_reaction(current.matter, 3);
         return m;
     }
 
-    p = rand(pos, push_constants.seed + 4.0);
+    p = rand_at(pos, 4.0);
     reacts = current.reacts[4];
     reaction_probability = current.reaction_probability[4];
     reacts_direction = current.reacts_direction[4];
@@ -123,21 +133,54 @@ Matter transition_into(Matter current, ivec2 pos) {
     if (transition_occurs(reacts, reacts_direction, p,
     reaction_probability, up, down, left, right, up_left, up_right, down_left, down_right)) {
         m = new_matter(reaction_transition);
+        count_reaction(current.matter, 4);
         return m;
     }
     return m;
 }
 
+// Share of energy reactions (e.g. wood catching fire) & liquids turning to gas (e.g. water on
+// lava) emitted for particle effects
+#define PARTICLE_EMISSION_CHANCE 0.05
+
+void emit_particles(ivec2 pos, Matter from, Matter to) {
+    bool is_energy = from.state == state_energy || to.state == state_energy;
+    bool is_boiling = from.state == state_liquid && to.state == state_gas;
+    if (!(is_energy || is_boiling) || rand_at(pos, 5.0) >= PARTICLE_EMISSION_CHANCE) {
+        return;
+    }
+    uint index = atomicAdd(particle_emission_count, 1);
+    // Count keeps growing past capacity, cpu reads only the first ones
+    if (index >= MAX_PARTICLE_EMISSIONS) {
+        return;
+    }
+    uint offset = index * PARTICLE_EMISSION_STRIDE;
+    particle_emissions[offset] = uint(pos.x);
+    particle_emissions[offset + 1] = uint(pos.y);
+    particle_emissions[offset + 2] = from.matter;
+    particle_emissions[offset + 3] = to.matter;
+}
+
 void cellular_automata_react(ivec2 pos) {
     Matter current = read_matter(pos);
+    if (current.is_protected) {
+        write_matter(pos, current);
+        return;
+    }
     Matter m = transition_into(current, pos);
     // If object e.g. caught fire, its pixel should no longer exist in the object grid...
     if (m.matter != current.matter && is_object(current)) {
         write_objects_matter(pos, empty);
     }
+    if (m.matter != current.matter) {
+        emit_particles(pos, current, m);
+    }
     write_matter(pos, m);
 }
 
 void main() {
-    cellular_automata_react(get_current_sim_pos());
+    ivec2 pos = get_current_sim_pos();
+    if (!keeps_frozen(pos)) {
+        cellular_automata_react(pos);
+    }
 }
\ No newline at end of file
@@ -8,14 +8,17 @@ void cellular_automata_rise_swap(ivec2 pos) {
     Matter up = get_neighbor(pos, UP);
     Matter down = get_neighbor(pos, DOWN);
     Matter m = current;
-    if (!is_at_border_bottom() && rises_on_swap(down, current)) {
+    if (!is_at_border_bottom() && rises_on_swap(down, current, get_pos_at_dir(pos, DOWN))) {
         m = down;
-    } else if (!is_at_border_top() && rises_on_swap(current, up)) {
+    } else if (!is_at_border_top() && rises_on_swap(current, up, pos)) {
         m = up;
     }
     write_matter(pos, m);
 }
 
 void main() {
-    cellular_automata_rise_swap(get_current_sim_pos());
+    ivec2 pos = get_current_sim_pos();
+    if (!keeps_frozen(pos)) {
+        cellular_automata_rise_swap(pos);
+    }
 }
\ No newline at end of file
@@ -45,5 +45,8 @@ void cellular_automata_slide_down_empty(ivec2 pos) {
 }
 
 void main() {
-    cellular_automata_slide_down_empty(get_current_sim_pos());
+    ivec2 pos = get_current_sim_pos();
+    if (!keeps_frozen(pos)) {
+        cellular_automata_slide_down_empty(pos);
+    }
 }
\ No newline at end of file
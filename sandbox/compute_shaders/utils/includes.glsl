@@ -3,7 +3,7 @@
 
 // Specialization constants
 layout(constant_id = 0) const uint empty = 1;
-layout(constant_id = 1) const int sim_canvas_size = 1;
+layout(constant_id = 1) const int sim_canvas_width = 1;
 layout(constant_id = 2) const int bitmap_ratio = 1;
 layout(constant_id = 3) const uint state_empty = 1;
 layout(constant_id = 4) const uint state_powder = 1;
@@ -13,6 +13,7 @@ layout(constant_id = 7) const uint state_solid_gravity = 1;
 layout(constant_id = 8) const uint state_gas = 1;
 layout(constant_id = 9) const uint state_energy = 1;
 layout(constant_id = 10) const uint state_object = 1;
+layout(constant_id = 13) const int sim_canvas_height = 1;
 
 // X & Y input as specialization constant
 layout(local_size_x_id = 11, local_size_y_id = 12, local_size_z = 1) in;
@@ -46,6 +47,12 @@ layout(set = 0, binding = 14) restrict buffer ObjectsMatter3 { uint objects_matt
 
 layout(set = 0, binding = 15) restrict buffer TmpMatter { uint tmp_matter[]; };
 
+// Exploding matter per bitmap block (empty if none), read on cpu to form shockwaves
+layout(set = 0, binding = 16) restrict buffer ExplosionsBuffer { uint explosions[]; };
+layout(set = 0, binding = 17) restrict buffer MatterCharacteristicsBuffer {
+    uint matter_characteristics[];
+};
+
 layout(push_constant) uniform PushConstants {
     ivec2 sim_pos_offset;
     ivec2 sim_chunk_start_offset;
@@ -53,17 +60,23 @@ layout(push_constant) uniform PushConstants {
 
 #include "../simulation/dirs.glsl"
 
-const ivec2 HALF_CANVAS = ivec2(sim_canvas_size / 2);
+const ivec2 SIM_CANVAS_SIZE = ivec2(sim_canvas_width, sim_canvas_height);
+const ivec2 HALF_CANVAS = SIM_CANVAS_SIZE / 2;
+
+// Must match MatterCharacteristic::EXPLODING
+#define CHARACTERISTIC_EXPLODING (uint(1) << 8)
 
 struct Matter {
     uint matter;
     uint state;
+    uint characteristics;
 };
 
 Matter new_matter(uint matter) {
     Matter m;
     m.matter = matter;
     m.state = matter_state[m.matter];
+    m.characteristics = matter_characteristics[m.matter];
     return m;
 }
 
@@ -76,16 +89,16 @@ ivec2 get_local_pos(ivec2 pos) {
 }
 
 int get_index(ivec2 pos) {
-    return pos.y * sim_canvas_size + pos.x;
+    return pos.y * sim_canvas_width + pos.x;
 }
 
 ivec2 get_pos_inside_chunk(ivec2 pos) {
     ivec2 diff = pos - push_constants.sim_chunk_start_offset;
-    return ivec2(diff.x % sim_canvas_size, diff.y % sim_canvas_size);
+    return diff % SIM_CANVAS_SIZE;
 }
 
 int get_chunk_index(ivec2 pos) {
-    ivec2 pos_on_4_chunks = (pos - push_constants.sim_chunk_start_offset) / sim_canvas_size;
+    ivec2 pos_on_4_chunks = (pos - push_constants.sim_chunk_start_offset) / SIM_CANVAS_SIZE;
     return pos_on_4_chunks.y * 2 + pos_on_4_chunks.x;
 }
 
@@ -150,8 +163,8 @@ void write_matter_both(ivec2 pos, Matter matter) {
 
 bool is_inside_sim_canvas(ivec2 pos) {
     ivec2 local_pos = get_local_pos(pos);
-    return local_pos.x >= 0 && local_pos.x < sim_canvas_size &&
-    local_pos.y >= 0 && local_pos.y < sim_canvas_size;
+    return local_pos.x >= 0 && local_pos.x < sim_canvas_width &&
+    local_pos.y >= 0 && local_pos.y < sim_canvas_height;
 }
 
 ivec2 get_pos_at_dir(ivec2 pos, int dir) {
@@ -191,6 +204,10 @@ bool is_liquid(Matter matter) {
     return matter.state == state_liquid;
 }
 
+bool is_exploding(Matter matter) {
+    return (matter.characteristics & CHARACTERISTIC_EXPLODING) != 0;
+}
+
 bool current_same_as_neighbors_ignore_objects(ivec2 pos, Matter current) {
     Matter up = get_neighbor_ignore_objects(pos, UP);
     Matter down = get_neighbor_ignore_objects(pos, DOWN);
@@ -4,9 +4,10 @@
 
 void reset_bitmap(ivec2 pos) {
     ivec2 bitmap_pos = ivec2(gl_GlobalInvocationID.xy) / bitmap_ratio;
-    int bitmap_size = sim_canvas_size / bitmap_ratio;
-    int bitmap_index = bitmap_pos.y * bitmap_size + bitmap_pos.x;
+    int bitmap_width = sim_canvas_width / bitmap_ratio;
+    int bitmap_index = bitmap_pos.y * bitmap_width + bitmap_pos.x;
     bitmap[bitmap_index] = 0;
+    explosions[bitmap_index] = empty;
 }
 
 void save_object_matter_to_tmp(ivec2 pos) {
@@ -6,9 +6,9 @@
 void update_bitmap(ivec2 pos, Matter matter) {
     if (!is_empty(matter) && current_same_as_neighbors_ignore_objects(pos, matter) &&
     (is_solid(matter) || is_powder(matter) || is_liquid(matter))) {
-        int bitmap_size = sim_canvas_size / bitmap_ratio;
+        int bitmap_width = sim_canvas_width / bitmap_ratio;
         ivec2 bitmap_pos = ivec2(gl_GlobalInvocationID.xy) / bitmap_ratio;
-        int bitmap_index = bitmap_pos.y * bitmap_size + bitmap_pos.x;
+        int bitmap_index = bitmap_pos.y * bitmap_width + bitmap_pos.x;
         int solid = int(is_solid(matter));
         int powder = int(is_powder(matter));
         int liquid = int(is_liquid(matter));
@@ -16,8 +16,17 @@ void update_bitmap(ivec2 pos, Matter matter) {
     }
 }
 
+void update_explosions(Matter matter) {
+    if (is_exploding(matter)) {
+        int bitmap_width = sim_canvas_width / bitmap_ratio;
+        ivec2 bitmap_pos = ivec2(gl_GlobalInvocationID.xy) / bitmap_ratio;
+        explosions[bitmap_pos.y * bitmap_width + bitmap_pos.x] = matter.matter;
+    }
+}
+
 void main() {
     ivec2 pos = get_current_sim_pos();
     Matter matter = new_matter(get_matter_in(pos));
     update_bitmap(pos, matter);
+    update_explosions(matter);
 }
\ No newline at end of file
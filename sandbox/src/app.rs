@@ -1,26 +1,51 @@
+use std::sync::Arc;
+
 use anyhow::*;
+use cgmath::Vector2;
 use corrode::{
+    alloc_counter::AllocationTracker,
     api::EngineApi,
     engine::Engine,
     renderer::{render_pass::Pass, Line},
-    time::PerformanceTimer,
+    time::{FrameStats, PerformanceTimer},
+};
+use serde::{Deserialize, Serialize};
+use vulkano::{
+    image::{ImageAccess, ImageViewAbstract},
+    sampler::Filter,
+    sync::GpuFuture,
 };
-use vulkano::sync::GpuFuture;
-use winit::event_loop::EventLoop;
+use winit::{event::VirtualKeyCode, event_loop::EventLoop};
 
 use crate::{
+    asset_audit::AssetAudit,
+    benchmark::{read_benchmark_result, run_benchmark},
+    breakpoints::{Breakpoint, Breakpoints},
+    camera_path::CameraPath,
     gui_state::GuiState,
     interact::{Editor, EditorMode},
     matter::{default_matter_definitions, validate_matter_definitions},
+    mods::Mods,
     object::{Angle, Position},
-    render::{draw_canvas, draw_chunk_debug_info, draw_contours, draw_debug_bounds, draw_grid},
+    render::{
+        canvas_filter, draw_annotations, draw_beam, draw_camera_path, draw_canvas,
+        draw_canvas_rect, draw_chunk_debug_info, draw_contours, draw_debug_bounds, draw_grid,
+        draw_object_joints, draw_particles, draw_portals, draw_trigger_zones,
+        scaled_render_target,
+    },
     settings::AppSettings,
-    sim::{log_world_performance, Simulation},
-    utils::{read_matter_definitions_file, u32_rgba_to_f32_rgba, CanvasMouseState},
-    GRAVITY_SCALE, SIM_CANVAS_SIZE, WORLD_UNIT_SIZE,
+    settings_profiles::SettingsProfiles,
+    sim::{canvas_pos_to_world_pos, log_world_performance, ReactionStats, Simulation},
+    stats_log::StatsLog,
+    step_control::StepControl,
+    stress_test::StressTest,
+    utils::{read_matter_definitions_file, CanvasMouseState, Color32},
+    version_check::VersionCheck,
+    watches::Watches,
+    CELL_UNIT_SIZE, GRAVITY_SCALE, SIM_CANVAS_SIZE, WORLD_UNIT_SIZE,
 };
 
-#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, Serialize, Deserialize)]
 pub enum InputAction {
     Pause,
     Step,
@@ -29,6 +54,45 @@ pub enum InputAction {
     DragMode,
     ObjectPaintMode,
     ToggleFullScreen,
+    ToggleCompactHud,
+    SpawnBugs,
+    /// Move camera back to canvas center & initial zoom
+    CenterCamera,
+    /// Rotate view by 90 degrees, simulation stays gravity down
+    RotateCamera,
+    /// Pin object or cell at mouse to watch window
+    PinWatch,
+    /// Select quick bar slot
+    QuickSelect(usize),
+    /// Undo & redo editor paint & place operations, with Ctrl held
+    Undo,
+    Redo,
+}
+
+/// Keys of input actions, unless a settings profile rebinds them
+pub fn default_key_bindings() -> Vec<(InputAction, VirtualKeyCode)> {
+    vec![
+        (InputAction::Pause, VirtualKeyCode::Space),
+        (InputAction::Step, VirtualKeyCode::Return),
+        (InputAction::PaintMode, VirtualKeyCode::Key1),
+        (InputAction::PlaceMode, VirtualKeyCode::Key2),
+        (InputAction::ObjectPaintMode, VirtualKeyCode::Key3),
+        (InputAction::DragMode, VirtualKeyCode::Key4),
+        (InputAction::QuickSelect(0), VirtualKeyCode::Key5),
+        (InputAction::QuickSelect(1), VirtualKeyCode::Key6),
+        (InputAction::QuickSelect(2), VirtualKeyCode::Key7),
+        (InputAction::QuickSelect(3), VirtualKeyCode::Key8),
+        (InputAction::QuickSelect(4), VirtualKeyCode::Key9),
+        (InputAction::QuickSelect(5), VirtualKeyCode::Key0),
+        (InputAction::ToggleFullScreen, VirtualKeyCode::F),
+        (InputAction::ToggleCompactHud, VirtualKeyCode::Tab),
+        (InputAction::SpawnBugs, VirtualKeyCode::B),
+        (InputAction::CenterCamera, VirtualKeyCode::Home),
+        (InputAction::RotateCamera, VirtualKeyCode::R),
+        (InputAction::PinWatch, VirtualKeyCode::P),
+        (InputAction::Undo, VirtualKeyCode::Z),
+        (InputAction::Redo, VirtualKeyCode::Y),
+    ]
 }
 
 pub struct SandboxApp {
@@ -37,33 +101,69 @@ pub struct SandboxApp {
     editor: Editor,
     gui_state: GuiState,
     settings: AppSettings,
+    settings_profiles: SettingsProfiles,
+    version_check: VersionCheck,
+    stats_log: StatsLog,
+    step_control: StepControl,
+    breakpoints: Breakpoints,
+    watches: Watches,
+    reaction_stats: ReactionStats,
+    mods: Mods,
+    camera_path: CameraPath,
+    stress_test: StressTest,
     // Bools
-    is_running_simulation: bool,
-    is_step: bool,
     is_debug: bool,
     time_since_last_step: f64,
     time_since_last_perf: f64,
+    finish_step_time: f64,
     // Performance metrics
     simulation_timer: PerformanceTimer,
     render_timer: PerformanceTimer,
     frame_timer: PerformanceTimer,
+    frame_stats: FrameStats,
+    stepped_this_frame: bool,
+    frame_allocations: AllocationTracker,
+    sim_allocations: AllocationTracker,
+    finish_step_allocations: usize,
 }
 
 impl SandboxApp {
     pub fn new() -> Result<SandboxApp> {
+        // Audit before anything reads assets, so missing directories & defaults exist
+        let audit = AssetAudit::run()?;
+        let mut editor = Editor::new()?;
+        audit.report(&mut editor);
+        let mut mods = Mods::scan()?;
+        for e in mods.add_images(&mut editor) {
+            editor.toasts.error(e);
+        }
         Ok(SandboxApp {
             simulation: None,
-            editor: Editor::new()?,
+            editor,
             gui_state: GuiState::new(),
             settings: AppSettings::new(),
-            is_running_simulation: true,
-            is_step: false,
+            settings_profiles: SettingsProfiles::new()?,
+            version_check: VersionCheck::new(),
+            stats_log: StatsLog::new(),
+            step_control: StepControl::new(),
+            breakpoints: Breakpoints::new(),
+            watches: Watches::new(),
+            reaction_stats: ReactionStats::new(),
+            mods,
+            camera_path: CameraPath::new(),
+            stress_test: StressTest::new(),
             is_debug: false,
             time_since_last_step: 0.0,
             time_since_last_perf: 0.0,
+            finish_step_time: 0.0,
             simulation_timer: PerformanceTimer::new(),
             render_timer: PerformanceTimer::new(),
             frame_timer: PerformanceTimer::new(),
+            frame_stats: FrameStats::new(),
+            stepped_this_frame: false,
+            frame_allocations: AllocationTracker::new(),
+            sim_allocations: AllocationTracker::new(),
+            finish_step_allocations: 0,
         })
     }
 
@@ -84,19 +184,195 @@ impl SandboxApp {
             self.render_timer.time_average_ms(),
             self.simulation_timer.time_average_ms(),
         );
+        println!(
+            "  1% low: {:.3}, 0.1% low: {:.3}, spikes: {}",
+            self.frame_stats.percentile_low_fps(1.0),
+            self.frame_stats.percentile_low_fps(0.1),
+            self.frame_stats.spikes().len()
+        );
+        println!(
+            "  Allocations / frame: {:.0}, / sim step: {:.0}",
+            self.frame_allocations.average(),
+            self.sim_allocations.average(),
+        );
         log_world_performance(self.simulation.as_ref().unwrap());
     }
 
+    /// Start or stop logging stats to csv when the setting is toggled
+    fn update_stats_log(&mut self) {
+        if self.settings.log_stats == self.stats_log.is_logging() {
+            return;
+        }
+        let result = if self.settings.log_stats {
+            self.stats_log
+                .start(self.simulation.as_ref().unwrap())
+                .map(|path| format!("Logging stats to {:?}", path))
+        } else {
+            self.stats_log.stop().map(|_| "Stopped logging stats".to_string())
+        };
+        match result {
+            std::result::Result::Ok(message) => self.editor.toasts.info(message),
+            Err(e) => {
+                self.settings.log_stats = false;
+                self.editor
+                    .error_dialog
+                    .push_error("Failed to log stats", &e);
+            }
+        }
+    }
+
+    /// Log stats of the previous step, its results have been read back by now
+    fn log_stats(&mut self, api: &EngineApi<InputAction>) {
+        let result = self.stats_log.log_step(
+            self.simulation.as_ref().unwrap(),
+            api.time.time_secs(),
+            api.ecs_world.len(),
+            self.simulation_timer.latest_ms(),
+        );
+        match result {
+            std::result::Result::Ok(Some(path)) => self
+                .editor
+                .toasts
+                .info(format!("Matters changed, logging stats to {:?}", path)),
+            std::result::Result::Ok(None) => (),
+            Err(e) => {
+                self.settings.log_stats = false;
+                let _ = self.stats_log.stop();
+                self.editor
+                    .error_dialog
+                    .push_error("Failed to log stats", &e);
+            }
+        }
+    }
+
+    /// Stop running until a condition once the step just read back meets it
+    fn check_step_stop(&mut self) {
+        let simulation = self.simulation.as_ref().unwrap();
+        match self.step_control.check_stop(simulation) {
+            std::result::Result::Ok(Some(condition)) => self.editor.toasts.info(format!(
+                "{} at step {}",
+                condition.name(),
+                simulation.step_count
+            )),
+            std::result::Result::Ok(None) => (),
+            Err(e) => {
+                self.step_control.stop();
+                self.editor
+                    .error_dialog
+                    .push_error("Failed to check stop condition", &e);
+            }
+        }
+    }
+
+    /// Pause simulation once a replay has finished & tell whether it reproduced the recording
+    fn check_replay(&mut self, api: &mut EngineApi<InputAction>) {
+        match self.simulation.as_mut().unwrap().check_replay() {
+            std::result::Result::Ok(Some(matched)) => {
+                api.time.set_paused(true);
+                if matched {
+                    self.editor.toasts.info("Replay finished, matter matches the recording");
+                } else {
+                    self.editor.toasts.error(
+                        "Replay finished, but matter differs from the recording. Was the map \
+                         saved or edited since?",
+                    );
+                }
+            }
+            std::result::Result::Ok(None) => (),
+            Err(e) => self
+                .editor
+                .error_dialog
+                .push_error("Failed to check replay", &e),
+        }
+    }
+
+    /// Pause simulation when a breakpoint is hit by the step just read back
+    fn check_breakpoints(&mut self, api: &mut EngineApi<InputAction>) {
+        let simulation = self.simulation.as_ref().unwrap();
+        match self.breakpoints.check(api, simulation) {
+            std::result::Result::Ok(Some(hit)) => {
+                api.time.set_paused(true);
+                self.step_control.stop();
+                self.editor
+                    .toasts
+                    .info(format!("Breakpoint at step {}: {}", simulation.step_count, hit));
+            }
+            std::result::Result::Ok(None) => (),
+            Err(e) => {
+                self.breakpoints.breakpoints.clear();
+                self.editor
+                    .error_dialog
+                    .push_error("Failed to check breakpoints", &e);
+            }
+        }
+    }
+
+    /// Pin watches at mouse & read their values. Previous step has finished, so reading cells
+    /// doesn't wait for gpu
+    fn update_watches(&mut self, api: &EngineApi<InputAction>) {
+        let hotkeys = self.editor.key_gate.is_open(
+            api.inputs[0].modifiers,
+            api.gui.context().wants_keyboard_input(),
+        );
+        if hotkeys && api.inputs[0].is_action_activated(InputAction::PinWatch) {
+            let canvas_mouse_state = CanvasMouseState::new(&api.main_camera, &api.inputs[0]);
+            self.watches.pin_at(api, canvas_mouse_state.mouse_on_canvas);
+            self.gui_state.show_watch_view = true;
+        }
+        if let Err(e) = self.watches.update(api, self.simulation.as_ref().unwrap()) {
+            self.watches.watches.clear();
+            self.editor
+                .error_dialog
+                .push_error("Failed to read watches", &e);
+        }
+    }
+
+    /// Accumulate reaction counts while they're shown, counting resumes from when the window opens
+    fn update_reaction_stats(&mut self) {
+        self.simulation
+            .as_mut()
+            .unwrap()
+            .set_count_reactions(self.gui_state.show_reactions_view);
+        if !self.gui_state.show_reactions_view {
+            return;
+        }
+        if let Err(e) = self.reaction_stats.update(self.simulation.as_ref().unwrap()) {
+            self.gui_state.show_reactions_view = false;
+            self.editor
+                .error_dialog
+                .push_error("Failed to read reaction counts", &e);
+        }
+    }
+
     /// Step the simulation
     pub fn step(&mut self, api: &mut EngineApi<InputAction>) -> Result<()> {
+        if self.stats_log.is_logging() {
+            self.log_stats(api);
+        }
+        self.simulation_timer.start();
+        self.sim_allocations.start();
+        self.simulation.as_mut().unwrap().step(api, self.settings)?;
+        // Cpu time of a step includes reading back its results next frame
+        self.simulation_timer
+            .push_dt_ms(self.simulation_timer.end() + self.finish_step_time);
+        self.sim_allocations
+            .push(self.sim_allocations.end() + self.finish_step_allocations);
+        self.stepped_this_frame = true;
+        self.time_since_last_step = 0.0;
+        Ok(())
+    }
+
+    /// Read back results of the step submitted last frame
+    pub fn finish_step(&mut self, api: &mut EngineApi<InputAction>) -> Result<()> {
         self.simulation_timer.start();
+        self.sim_allocations.start();
         let canvas_mouse_state = CanvasMouseState::new(&api.main_camera, &api.inputs[0]);
         self.simulation
             .as_mut()
             .unwrap()
-            .step(api, self.settings, &canvas_mouse_state)?;
-        self.simulation_timer.time_it();
-        self.time_since_last_step = 0.0;
+            .finish_step(api, self.settings, &canvas_mouse_state)?;
+        self.finish_step_time = self.simulation_timer.end();
+        self.finish_step_allocations = self.sim_allocations.end();
         Ok(())
     }
 }
@@ -110,24 +386,69 @@ impl Engine<InputAction> for SandboxApp {
         // Zoom to desired level
         api.main_camera.zoom_to_fit_canvas(WORLD_UNIT_SIZE);
         // Read matter definitions
-        let matter_definitions = if let Some(defs) = read_matter_definitions_file() {
-            defs
-        } else {
-            default_matter_definitions()
+        let mut matter_definitions = match read_matter_definitions_file() {
+            std::result::Result::Ok(Some(defs)) => defs,
+            std::result::Result::Ok(None) => default_matter_definitions(),
+            Err(e) => {
+                self.editor.error_dialog.push_error(
+                    "Failed to read matter definitions, using defaults",
+                    &e,
+                );
+                default_matter_definitions()
+            }
         };
-        validate_matter_definitions(&matter_definitions);
+        for e in self.mods.add_matters(&mut matter_definitions) {
+            self.editor.toasts.error(e);
+        }
+        if !self.mods.conflicts.is_empty() {
+            self.editor.toasts.error(format!(
+                "{} mod conflicts, see Mods window",
+                self.mods.conflicts.len()
+            ));
+        }
+        validate_matter_definitions(&mut matter_definitions);
         // Create simulator
         self.simulation = Some(Simulation::new(
             api.renderer.compute_queue(),
+            api.renderer.transfer_queue(),
             matter_definitions,
             api.renderer.image_format(),
         )?);
+        self.simulation.as_mut().unwrap().mod_matters = self.mods.matter_names();
         // Register gui images (for editor windows in gui)
         self.editor
             .register_gui_images(api, self.simulation.as_ref().unwrap());
         // Update settings based on read information from renderer
         self.settings
             .update_based_on_device_info_and_env(&api.renderer);
+        // Benchmark on first run, its picks replace device based settings
+        match read_benchmark_result() {
+            Some(result) => result.apply(&mut self.settings),
+            None => match run_benchmark(&api.renderer) {
+                std::result::Result::Ok(result) => {
+                    result.apply(&mut self.settings);
+                    self.editor.toasts.info(result.notice());
+                }
+                Err(e) => self
+                    .editor
+                    .error_dialog
+                    .push_error("Benchmark failed, using device based settings", &e),
+            },
+        }
+        // Last used profile overrides device based settings
+        if let Some(name) = self.settings_profiles.current.clone() {
+            let result = self
+                .settings_profiles
+                .load(&name, &mut self.settings, &mut api.inputs[0]);
+            self.editor
+                .toasts
+                .report(result, format!("Loaded settings profile {}", name));
+        }
+        let world_seed = self.settings.simulation_seed.world_seed();
+        self.simulation
+            .as_mut()
+            .unwrap()
+            .set_world_seed(world_seed)?;
         // Toggle fullscreen
         api.renderer.toggle_fullscreen();
         // Adjust gravity
@@ -136,27 +457,63 @@ impl Engine<InputAction> for SandboxApp {
     }
 
     fn update(&mut self, api: &mut EngineApi<InputAction>) -> Result<()> {
+        self.editor.key_gate.modifier = self.settings.hotkey_modifier;
+        // Finish previous step before editor, so edits see the simulation as it was before
+        self.finish_step(api)?;
+        self.check_step_stop();
+        self.check_replay(api);
+        self.check_breakpoints(api);
+        self.update_watches(api);
+        self.update_reaction_stats();
+        self.editor
+            .saver
+            .toast_finished_saves(&mut self.editor.toasts);
+        if self.settings.check_for_updates && !self.version_check.is_started() {
+            self.version_check.start();
+        }
+        self.update_stats_log();
+        if let Some(release) = self.version_check.poll() {
+            self.editor.toasts.link(
+                format!("Sandbox {} is available", release.version),
+                release.url,
+            );
+        }
         // Update editor & handle inputs there
-        self.editor.update(
-            api,
-            self.simulation.as_mut().unwrap(),
-            &mut self.is_running_simulation,
-            &mut self.is_step,
-        )?;
+        self.editor.update(api, self.simulation.as_mut().unwrap())?;
+        if let Err(e) = self.stress_test.update(api, self.simulation.as_mut().unwrap()) {
+            self.stress_test.is_painting = false;
+            self.editor
+                .error_dialog
+                .push_error("Failed to paint stress test cells", &e);
+        }
+        // Camera path playback overrides camera moved by editor
+        self.camera_path
+            .update(&mut api.main_camera, (api.time.dt() / 1000.0) as f32);
+        if api.time.take_step() {
+            self.step_control.step_n();
+        }
+        // Running normally takes over stepping
+        if !api.time.is_paused() {
+            self.step_control.stop();
+        }
+        let ctx = api.gui.context();
+        let is_gui_focused = ctx.wants_pointer_input() || ctx.wants_keyboard_input();
+        let is_running =
+            !api.time.is_paused() && !(self.settings.pause_while_gui_focused && is_gui_focused);
         // Step if desired
-        if self.should_step() {
-            if self.is_running_simulation {
-                self.step(api)?;
-            } else if self.is_step {
-                self.step(api)?;
-                self.is_step = false;
-            }
+        if self.should_step() && (is_running || self.step_control.take_step()) {
+            self.step(api)?;
+        }
+        // Chunk images are written by the simulation step, which may still be running on gpu
+        if let Some(compute_future) = self.simulation.as_ref().unwrap().compute_future() {
+            api.renderer.join_frame_future(compute_future);
         }
         if self.should_print_perf() {
             self.log_performance(api);
             self.time_since_last_perf = 0.0;
         }
-        self.time_since_last_step += api.time.dt();
+        // Manual steps are paced too, so they follow time scale regardless of pause
+        self.time_since_last_step += api.time.scaled_dt();
         self.time_since_last_perf += api.time.dt();
         Ok(())
     }
@@ -177,19 +534,52 @@ impl Engine<InputAction> for SandboxApp {
             renderer,
             ..
         } = api;
-        let simulation = self.simulation.as_ref().unwrap();
+        let simulation = self.simulation.as_mut().unwrap();
         let canvas_mouse_state = CanvasMouseState::new(main_camera, &api.inputs[0]);
-        let image_target = renderer.final_image();
+        let final_image = renderer.final_image();
+        // Canvas is rendered at lower resolution & upscaled when render scale is below 1.0
+        let scaled_target = if self.settings.render_scale < 1.0 {
+            Some(scaled_render_target(renderer, self.settings.render_scale)?)
+        } else {
+            None
+        };
+        let image_target: Arc<dyn ImageViewAbstract> = match &scaled_target {
+            Some(target) => target.clone(),
+            None => final_image.clone(),
+        };
+        let canvas_filter = canvas_filter(
+            main_camera,
+            image_target.image().dimensions().height(),
+            self.settings.smooth_canvas_below,
+        );
         let image_format = renderer.image_format();
         let render_pass = &mut renderer.render_passes.deferred;
         let bg_color = [0.0; 4];
+        let before_future = before_future.boxed();
+        let before_future = if self.settings.detail_upscale {
+            let chunks = simulation.chunk_manager.get_chunks_for_render();
+            simulation.detail_upscaler.update(&chunks, before_future)?
+        } else {
+            simulation.detail_upscaler.clear();
+            before_future
+        };
+        let before_future = if self.settings.particles {
+            let chunk_start = simulation.chunk_manager.sim_chunk_start();
+            simulation.particles.update(chunk_start, before_future)?
+        } else {
+            simulation.particles.clear();
+            before_future
+        };
         let mut frame = render_pass.frame(bg_color, before_future, image_target, *main_camera)?;
         let mut after_future = None;
         while let Some(pass) = frame.next_pass()? {
             after_future = match pass {
                 Pass::Deferred(mut dp) => {
                     // Render canvas first
-                    draw_canvas(simulation, &mut dp)?;
+                    draw_canvas(simulation, &mut dp, canvas_filter, self.settings.detail_upscale)?;
+                    if self.settings.particles {
+                        draw_particles(simulation, &mut dp)?;
+                    }
                     // Debug renders
                     if self.is_debug {
                         draw_contours(ecs_world, physics_world, simulation, &mut dp)?;
@@ -201,6 +591,64 @@ impl Engine<InputAction> for SandboxApp {
                             ])?;
                         }
                     }
+                    // Trigger zones are shown while editing them
+                    if self.is_debug || self.gui_state.show_triggers_view {
+                        draw_trigger_zones(simulation, &mut dp, [1.0, 0.0, 1.0, 1.0], [
+                            1.0, 0.5, 0.0, 1.0,
+                        ])?;
+                    }
+                    // Camera path & its keyframes are shown while editing it
+                    if self.gui_state.show_camera_path_view && !self.camera_path.is_playing() {
+                        draw_camera_path(&self.camera_path, &mut dp, [1.0, 1.0, 0.0, 1.0])?;
+                    }
+                    // Watched deformation areas are shown while editing breakpoints
+                    if self.gui_state.show_breakpoints_view {
+                        for breakpoint in self.breakpoints.breakpoints.iter() {
+                            if let Breakpoint::Deformation {
+                                min,
+                                max,
+                            } = *breakpoint
+                            {
+                                draw_canvas_rect(&mut dp, min, max, [1.0, 0.0, 0.0, 1.0])?;
+                            }
+                        }
+                    }
+                    if self.is_debug || self.gui_state.show_portals_view {
+                        draw_portals(simulation, &mut dp, [0.0, 0.5, 1.0, 1.0], [
+                            1.0, 0.5, 0.0, 1.0,
+                        ])?;
+                    }
+                    draw_annotations(simulation, &mut dp)?;
+                    // Object joints & the first picked object's point while joining
+                    if self.is_debug || self.editor.mode == EditorMode::Joint {
+                        draw_object_joints(physics_world, simulation, &mut dp, [
+                            1.0, 0.8, 0.2, 1.0,
+                        ])?;
+                    }
+                    if let Some((_, first_pos)) = self.editor.joiner.first {
+                        dp.draw_circle(first_pos, *CELL_UNIT_SIZE, [1.0, 1.0, 1.0, 1.0])?;
+                    }
+                    // Selected object at mouse, mipmapped so it doesn't shimmer when zoomed out
+                    if self.editor.mode == EditorMode::Place {
+                        let preview =
+                            self.editor.placer.preview_texture(dp.queue().clone(), image_format)?;
+                        if let Some((texture, image)) = preview {
+                            dp.draw_texture(
+                                canvas_mouse_state.mouse_world_pos,
+                                *CELL_UNIT_SIZE * image.width as f32 * 0.5,
+                                *CELL_UNIT_SIZE * image.height as f32 * 0.5,
+                                0.0,
+                                texture,
+                                false,
+                                true,
+                                Filter::Nearest,
+                            )?;
+                        }
+                    }
+                    // Blueprint sketch over the canvas
+                    if self.editor.blueprint.visible {
+                        self.editor.blueprint.draw_preview(&mut dp, image_format)?;
+                    }
                     // Render line from dragged object
                     if let Some((obj_id, _)) = self.editor.dragger.dragged_object {
                         ecs_world
@@ -218,26 +666,88 @@ impl Engine<InputAction> for SandboxApp {
                     }
 
                     // Render circle when painting
-                    if self.editor.mode == EditorMode::Paint
-                        || self.editor.mode == EditorMode::ObjectPaint
-                    {
+                    if matches!(
+                        self.editor.mode,
+                        EditorMode::Paint | EditorMode::ObjectPaint | EditorMode::Blueprint
+                    ) {
                         let pos = canvas_mouse_state.mouse_world_pos;
                         let radius = 0.5 * self.editor.painter.radius * WORLD_UNIT_SIZE
-                            / *SIM_CANVAS_SIZE as f32;
+                            / SIM_CANVAS_SIZE.x as f32;
                         let matter_definitions = &simulation.matter_definitions.definitions;
-                        let mut color_f32 = if self.editor.mode == EditorMode::Paint {
-                            u32_rgba_to_f32_rgba(
-                                matter_definitions[self.editor.painter.matter as usize].color,
-                            )
+                        let matter = if self.editor.mode != EditorMode::ObjectPaint {
+                            self.editor.painter.matter
                         } else {
-                            u32_rgba_to_f32_rgba(
-                                matter_definitions[self.editor.placer.object_matter as usize].color,
-                            )
+                            self.editor.placer.object_matter
                         };
+                        let mut color_f32 =
+                            Color32::from_u32_rgba(matter_definitions[matter as usize].color)
+                                .to_f32_rgba();
                         color_f32[3] = 0.5;
                         dp.draw_circle(pos, radius, color_f32)?;
                     }
 
+                    // Outline stamp copy selection, or placed stamp at mouse
+                    if self.editor.mode == EditorMode::Stamp {
+                        let stamper = &self.editor.stamper;
+                        let stamp =
+                            stamper.stamp.as_ref().and_then(|s| stamper.stamp_assets.get(s));
+                        if let Some((min, max)) = stamper.selection_bounds() {
+                            draw_canvas_rect(&mut dp, min, max, [1.0, 1.0, 1.0, 1.0])?;
+                        } else if let Some(stamp) = stamp {
+                            // Stamps are pasted centered at mouse, see `paste_matter_image`
+                            let (width, height) = (stamp.width as i32, stamp.height as i32);
+                            let pos = canvas_mouse_state.mouse_on_canvas;
+                            let max = pos + Vector2::new(width - width / 2 - 1, height / 2);
+                            let min = max - Vector2::new(width - 1, height - 1);
+                            draw_canvas_rect(&mut dp, min, max, [1.0, 1.0, 1.0, 0.5])?;
+                        }
+                    }
+
+                    // Outline region selection, or preview clipboard paste at mouse
+                    if self.editor.mode == EditorMode::Select {
+                        let selector = &mut self.editor.selector;
+                        if let Some((min, max)) = selector.selection_bounds() {
+                            draw_canvas_rect(&mut dp, min, max, [1.0, 1.0, 1.0, 1.0])?;
+                        } else if let Some(clipboard) = selector.clipboard() {
+                            let min = clipboard.min_at(canvas_mouse_state.mouse_on_canvas);
+                            let (width, height) = (clipboard.width as i32, clipboard.height as i32);
+                            let max = min + Vector2::new(width - 1, height - 1);
+                            let center =
+                                (canvas_pos_to_world_pos(min) + canvas_pos_to_world_pos(max)) / 2.0;
+                            selector.draw_preview(&mut dp, center, image_format)?;
+                            draw_canvas_rect(&mut dp, min, max, [1.0, 1.0, 1.0, 0.5])?;
+                        }
+                    }
+
+                    // Outline filled rectangle, or clicked polygon up to mouse
+                    if self.editor.mode == EditorMode::Fill {
+                        let filler = &self.editor.filler;
+                        if let Some((min, max)) = filler.rect_bounds() {
+                            draw_canvas_rect(&mut dp, min, max, [1.0, 1.0, 1.0, 1.0])?;
+                        }
+                        let mouse_pos = canvas_mouse_state.mouse_world_pos;
+                        let vertices = filler
+                            .polygon
+                            .iter()
+                            .map(|v| canvas_pos_to_world_pos(*v))
+                            .chain(filler.polygon.first().map(|_| mouse_pos))
+                            .collect::<Vec<Vector2<f32>>>();
+                        for edge in vertices.windows(2) {
+                            dp.draw_line(Line(edge[0], edge[1], [1.0, 1.0, 1.0, 1.0]))?;
+                        }
+                    }
+
+                    // Beam emitter & the beam while firing
+                    if self.editor.mode == EditorMode::Beam {
+                        if let Some(emitter) = self.editor.beam.emitter {
+                            let radius = 2.0 * WORLD_UNIT_SIZE / SIM_CANVAS_SIZE.x as f32;
+                            dp.draw_circle(emitter, radius, [1.0, 0.4, 0.1, 1.0])?;
+                        }
+                        if let Some((start, end)) = self.editor.beam.fired {
+                            draw_beam(&mut dp, start, end)?;
+                        }
+                    }
+
                     // Draw painted object image
                     if self.editor.mode == EditorMode::ObjectPaint
                         && self.editor.draw_state.started()
@@ -251,7 +761,17 @@ impl Engine<InputAction> for SandboxApp {
                 Pass::Finished(af) => Some(af),
             };
         }
-        let after_drawing = after_future.unwrap().then_signal_fence_and_flush()?.boxed();
+        let after_drawing = match scaled_target {
+            Some(target) => renderer.render_passes.place_over_frame.render(
+                after_future.unwrap(),
+                target,
+                final_image,
+                false,
+                true,
+                self.settings.upscale_filter,
+            )?,
+            None => after_future.unwrap().then_signal_fence_and_flush()?.boxed(),
+        };
         Ok(after_drawing)
     }
 
@@ -259,10 +779,20 @@ impl Engine<InputAction> for SandboxApp {
         let SandboxApp {
             simulation: simulator,
             gui_state,
-            is_running_simulation,
             is_debug,
             editor,
             settings,
+            settings_profiles,
+            step_control,
+            breakpoints,
+            watches,
+            reaction_stats,
+            mods,
+            camera_path,
+            stress_test,
+            frame_stats,
+            frame_allocations,
+            sim_allocations,
             ..
         } = self;
         gui_state.layout(
@@ -270,21 +800,67 @@ impl Engine<InputAction> for SandboxApp {
             simulator.as_mut().unwrap(),
             editor,
             settings,
-            *is_running_simulation,
+            settings_profiles,
+            step_control,
+            breakpoints,
+            watches,
+            reaction_stats,
+            mods,
+            camera_path,
+            stress_test,
             is_debug,
             self.frame_timer.time_average_ms(),
             self.render_timer.time_average_ms(),
             self.simulation_timer.time_average_ms(),
+            frame_stats,
+            (frame_allocations, sim_allocations),
         );
 
         Ok(())
     }
 
+    fn device_recreated(&mut self, api: &mut EngineApi<InputAction>) -> Result<()> {
+        let simulation = self.simulation.as_mut().unwrap();
+        simulation.recreate_gpu(
+            api.renderer.compute_queue(),
+            api.renderer.transfer_queue(),
+            api.renderer.image_format(),
+        )?;
+        // Textures of the old device can't be drawn
+        self.editor.placer.preview_textures.clear();
+        // Gui was recreated without our textures
+        self.editor.forget_gui_images();
+        self.gui_state.minimap.forget_gui_image();
+        self.editor.register_gui_images(api, simulation);
+        self.editor.toasts.error(
+            "Gpu was lost (driver reset?) & has been recovered. Recent changes near the camera \
+             may have been lost",
+        );
+        Ok(())
+    }
+
     fn end_of_frame(&mut self, api: &mut EngineApi<InputAction>) -> Result<()> {
         // Render timer was started in the beginning of render function, there's basically nothing between
         // end of frame and render...
         self.render_timer.time_it();
         self.frame_timer.push_dt_ms(api.time.dt());
+        // Record what each subsystem took this frame so spikes can be attributed
+        let mut subsystems = vec![("Render", self.render_timer.latest_ms())];
+        if self.stepped_this_frame {
+            subsystems.extend(self.simulation.as_ref().unwrap().latest_timings());
+        }
+        self.frame_stats
+            .push_frame(api.time.time_secs(), api.time.dt(), &subsystems);
+        self.stepped_this_frame = false;
+        self.frame_allocations.record();
+        self.frame_allocations.start();
+        Ok(())
+    }
+
+    fn shutdown(&mut self, _api: &mut EngineApi<InputAction>) -> Result<()> {
+        // Don't lose a map save that is still being written
+        self.editor.saver.wait_writes();
+        self.stats_log.stop()?;
         Ok(())
     }
 }
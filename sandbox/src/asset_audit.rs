@@ -0,0 +1,223 @@
+use std::{fs, path::Path};
+
+use anyhow::*;
+
+use crate::{
+    data_dirs::{assets_dir, DataDirs, DATA_DIRS},
+    interact::Editor,
+    matter::default_matter_definitions,
+    utils::matter_definitions_path,
+};
+
+/// Directories the app reads & writes, by their root in `DataDirs`
+const ASSET_SUB_DIRS: [&str; 2] = ["object_images", "mods"];
+const DATA_SUB_DIRS: [&str; 4] = ["maps", "stamps", "brushes", "replays"];
+const CONFIG_SUB_DIRS: [&str; 1] = ["settings_profiles"];
+/// Config files kept beside assets before platform directories, besides `CONFIG_SUB_DIRS`
+const LEGACY_CONFIG_FILES: [&str; 1] = ["matter_definitions.json"];
+
+/// Startup check of data directories. Missing directories & matter definitions are created, so
+/// later loads & saves don't fail on them. Problems that can't be fixed here are reported
+pub struct AssetAudit {
+    /// Assets created by the audit
+    pub created: Vec<String>,
+    /// Files & directories copied from their location before platform directories
+    pub migrated: Vec<String>,
+    /// Problems found, shown to the user
+    pub problems: Vec<String>,
+}
+
+impl AssetAudit {
+    /// Audit directories of this launch
+    pub fn run() -> Result<AssetAudit> {
+        // Copied before the audit, so it doesn't create defaults in place of legacy files
+        let (migrated, migration_problems) = if DATA_DIRS.portable {
+            (vec![], vec![])
+        } else {
+            migrate_legacy(&assets_dir(), &DATA_DIRS)
+        };
+        let mut audit = AssetAudit::run_in(&DATA_DIRS, &matter_definitions_path());
+        audit.migrated = migrated;
+        audit.problems.extend(migration_problems);
+        if audit.created.contains(&display(&DATA_DIRS.assets)) {
+            audit.problems.push(format!(
+                "No assets directory in {:?}, created an empty one. Run sandbox from its own \
+                 directory to use the bundled assets",
+                DATA_DIRS.assets
+            ));
+        }
+        Ok(audit)
+    }
+
+    fn run_in(dirs: &DataDirs, matter_definitions_path: &Path) -> AssetAudit {
+        let mut audit = AssetAudit {
+            created: vec![],
+            migrated: vec![],
+            problems: vec![],
+        };
+        for (root, sub_dirs) in [
+            (&dirs.assets, &ASSET_SUB_DIRS[..]),
+            (&dirs.data, &DATA_SUB_DIRS[..]),
+            (&dirs.config, &CONFIG_SUB_DIRS[..]),
+        ] {
+            audit.ensure_dir(root);
+            for dir in sub_dirs {
+                audit.ensure_dir(&root.join(dir));
+            }
+        }
+        if !matter_definitions_path.exists() {
+            match fs::write(matter_definitions_path, default_matter_definitions().serialize()) {
+                std::result::Result::Ok(()) => audit.created.push(display(matter_definitions_path)),
+                Err(e) => audit.problems.push(format!(
+                    "Failed to write default matter definitions to {:?}: {}",
+                    matter_definitions_path, e
+                )),
+            }
+        }
+        let object_images = dirs.assets.join("object_images");
+        if count_files(&object_images) == 0 {
+            audit.problems.push(format!(
+                "No images in {:?}, placing objects is unavailable",
+                object_images
+            ));
+        }
+        audit
+    }
+
+    fn ensure_dir(&mut self, path: &Path) {
+        if path.is_dir() {
+            return;
+        }
+        match fs::create_dir_all(path) {
+            std::result::Result::Ok(()) => self.created.push(display(path)),
+            Err(e) => self
+                .problems
+                .push(format!("Failed to create {:?}: {}", path, e)),
+        }
+    }
+
+    /// Show problems in error dialog & created assets as a toast
+    pub fn report(&self, editor: &mut Editor) {
+        if !self.created.is_empty() {
+            info!("Created missing assets: {}", self.created.join(", "));
+            editor
+                .toasts
+                .info(format!("Created missing assets: {}", self.created.join(", ")));
+        }
+        if !self.migrated.is_empty() {
+            info!("Copied data from assets: {}", self.migrated.join(", "));
+            editor
+                .toasts
+                .info(format!("Copied data from assets: {}", self.migrated.join(", ")));
+        }
+        if !self.problems.is_empty() {
+            editor
+                .error_dialog
+                .push("Startup asset check found problems", self.problems.clone());
+        }
+    }
+}
+
+/// Copy user data & config from `legacy_assets`, where they were kept before platform
+/// directories, to `dirs`. Only done for what doesn't exist in `dirs` yet, i.e. on first run.
+/// Originals are kept. Returns copied destinations & problems
+fn migrate_legacy(legacy_assets: &Path, dirs: &DataDirs) -> (Vec<String>, Vec<String>) {
+    let (mut migrated, mut problems) = (vec![], vec![]);
+    let entries = DATA_SUB_DIRS
+        .iter()
+        .map(|name| (name, &dirs.data))
+        .chain(CONFIG_SUB_DIRS.iter().map(|name| (name, &dirs.config)))
+        .chain(LEGACY_CONFIG_FILES.iter().map(|name| (name, &dirs.config)));
+    for (name, root) in entries {
+        let (src, dst) = (legacy_assets.join(name), root.join(name));
+        if !src.exists() || dst.exists() {
+            continue;
+        }
+        match copy_recursive(&src, &dst) {
+            std::result::Result::Ok(()) => migrated.push(display(&dst)),
+            Err(e) => problems.push(format!("Failed to copy {:?} to {:?}: {}", src, dst, e)),
+        }
+    }
+    (migrated, problems)
+}
+
+fn copy_recursive(src: &Path, dst: &Path) -> std::io::Result<()> {
+    if src.is_dir() {
+        fs::create_dir_all(dst)?;
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &dst.join(entry.file_name()))?;
+        }
+    } else {
+        if let Some(parent) = dst.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(src, dst)?;
+    }
+    std::result::Result::Ok(())
+}
+
+fn display(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+fn count_files(dir: &Path) -> usize {
+    fs::read_dir(dir).map_or(0, |entries| {
+        entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file())
+            .count()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_creates_missing_assets() {
+        let root = std::env::temp_dir().join(format!("asset_audit_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let dirs = DataDirs {
+            assets: root.join("assets"),
+            data: root.join("data"),
+            config: root.join("config"),
+            portable: false,
+        };
+        let matter_definitions_path = dirs.config.join("matter_definitions.json");
+        let audit = AssetAudit::run_in(&dirs, &matter_definitions_path);
+        assert!(DATA_SUB_DIRS
+            .iter()
+            .all(|dir| dirs.data.join(dir).is_dir()));
+        assert!(matter_definitions_path.is_file());
+        // Only the missing object images remain a problem
+        assert_eq!(audit.problems.len(), 1);
+        let audit = AssetAudit::run_in(&dirs, &matter_definitions_path);
+        assert!(audit.created.is_empty());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_migrates_legacy_data_once() {
+        let root = std::env::temp_dir().join(format!("asset_migration_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let legacy_assets = root.join("assets");
+        fs::create_dir_all(legacy_assets.join("maps/cave")).unwrap();
+        fs::write(legacy_assets.join("maps/cave/chunk_0_0.png"), "chunk").unwrap();
+        fs::write(legacy_assets.join("matter_definitions.json"), "{}").unwrap();
+        let dirs = DataDirs {
+            assets: legacy_assets.clone(),
+            data: root.join("data"),
+            config: root.join("config"),
+            portable: false,
+        };
+        let (migrated, problems) = migrate_legacy(&legacy_assets, &dirs);
+        assert_eq!(migrated.len(), 2);
+        assert!(problems.is_empty());
+        assert!(dirs.data.join("maps/cave/chunk_0_0.png").is_file());
+        assert!(dirs.config.join("matter_definitions.json").is_file());
+        // Already copied data isn't overwritten
+        assert!(migrate_legacy(&legacy_assets, &dirs).0.is_empty());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,134 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::*;
+use cgmath::Vector2;
+use corrode::renderer::Renderer;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data_dirs::DATA_DIRS,
+    matter::{default_matter_definitions, MATTER_SAND, MATTER_WATER},
+    settings::AppSettings,
+    sim::Simulation,
+    HALF_CANVAS, SIM_CANVAS_SIZE,
+};
+
+/// Steps run before timing, loading chunks & warming up gpu
+const WARMUP_STEPS: u32 = 20;
+const BENCHMARK_STEPS: u32 = 300;
+/// Cells of the large canvas (`LARGE=1`)
+const LARGE_CANVAS_CELLS: f64 = 1024.0 * 1024.0;
+
+/// Settings picked by a benchmark on first run (per canvas size) & used as defaults after that
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct BenchmarkResult {
+    /// Milliseconds per CA step with max dispersion & movement steps
+    pub ms_per_step: f64,
+    pub dispersion_steps: u32,
+    pub movement_steps: u32,
+    pub sim_fps: f32,
+    /// Applied from next start, window has been created by the time benchmark runs
+    pub v_sync: bool,
+    /// Gpu is fast enough for the large canvas. Only suggested, as each canvas size has its own
+    /// maps
+    pub large_canvas: bool,
+}
+
+impl BenchmarkResult {
+    pub fn from_ms_per_step(ms_per_step: f64) -> BenchmarkResult {
+        let (dispersion_steps, movement_steps, sim_fps) = if ms_per_step < 2.0 {
+            (10, 3, 60.0)
+        } else if ms_per_step < 4.0 {
+            (6, 2, 60.0)
+        } else if ms_per_step < 8.0 {
+            (4, 1, 60.0)
+        } else {
+            (3, 1, 30.0)
+        };
+        // Step cost grows with cell count
+        let cells = (SIM_CANVAS_SIZE.x * SIM_CANVAS_SIZE.y) as f64;
+        BenchmarkResult {
+            ms_per_step,
+            dispersion_steps,
+            movement_steps,
+            sim_fps,
+            v_sync: ms_per_step < 4.0,
+            large_canvas: cells < LARGE_CANVAS_CELLS
+                && ms_per_step * LARGE_CANVAS_CELLS / cells < 4.0,
+        }
+    }
+
+    pub fn apply(&self, settings: &mut AppSettings) {
+        settings.dispersion_steps = self.dispersion_steps;
+        settings.movement_steps = self.movement_steps;
+        settings.sim_fps = self.sim_fps;
+    }
+
+    pub fn notice(&self) -> String {
+        let mut notice = format!(
+            "Picked settings by a quick benchmark ({:.1} ms per step): {} dispersion & {} \
+             movement steps at {} fps",
+            self.ms_per_step, self.dispersion_steps, self.movement_steps, self.sim_fps
+        );
+        if self.v_sync {
+            notice.push_str(", vsync from next start");
+        }
+        if self.large_canvas {
+            notice.push_str(". Your gpu can also run a larger canvas with LARGE=1");
+        }
+        notice
+    }
+}
+
+/// Benchmark result of current canvas size, None if benchmark hasn't been run
+pub fn read_benchmark_result() -> Option<BenchmarkResult> {
+    let data = fs::read_to_string(benchmark_path().ok()?).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+/// Time CA steps of a hidden simulation of sand falling into water, pick settings by it & save
+/// them
+pub fn run_benchmark(renderer: &Renderer) -> Result<BenchmarkResult> {
+    let mut simulation = Simulation::new(
+        renderer.compute_queue(),
+        renderer.transfer_queue(),
+        default_matter_definitions(),
+        renderer.image_format(),
+    )?;
+    let mut settings = AppSettings::new();
+    settings.dispersion_steps = 10;
+    settings.movement_steps = 3;
+    simulation.time_ca_steps(settings, WARMUP_STEPS)?;
+    let size = SIM_CANVAS_SIZE.x.max(SIM_CANVAS_SIZE.y) as i32;
+    simulation.queue_paint_square(&[Vector2::new(0, -HALF_CANVAS.y)], MATTER_WATER, size);
+    let sand_pos = Vector2::new(0, HALF_CANVAS.y / 2);
+    simulation.queue_paint_square(&[sand_pos], MATTER_SAND, HALF_CANVAS.x);
+    let ms_per_step = simulation.time_ca_steps(settings, BENCHMARK_STEPS)?;
+    let result = BenchmarkResult::from_ms_per_step(ms_per_step);
+    fs::write(benchmark_path()?, serde_json::to_string_pretty(&result)?)?;
+    info!("Benchmark: {:.2} ms per step", ms_per_step);
+    Ok(result)
+}
+
+fn benchmark_path() -> Result<PathBuf> {
+    fs::create_dir_all(&DATA_DIRS.config)?;
+    Ok(DATA_DIRS.config.join(format!(
+        "benchmark_{}x{}.json",
+        SIM_CANVAS_SIZE.x, SIM_CANVAS_SIZE.y
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_benchmark_picks() {
+        let fast = BenchmarkResult::from_ms_per_step(0.5);
+        assert_eq!((fast.dispersion_steps, fast.movement_steps, fast.sim_fps), (10, 3, 60.0));
+        assert!(fast.v_sync);
+        let slow = BenchmarkResult::from_ms_per_step(12.0);
+        assert_eq!((slow.dispersion_steps, slow.movement_steps, slow.sim_fps), (3, 1, 30.0));
+        assert!(!slow.v_sync && !slow.large_canvas);
+    }
+}
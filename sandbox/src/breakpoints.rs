@@ -0,0 +1,104 @@
+use anyhow::*;
+use cgmath::Vector2;
+use corrode::api::EngineApi;
+
+use crate::{
+    app::InputAction,
+    sim::{world_pos_to_canvas_pos, Simulation},
+};
+
+/// Condition on simulation state pausing the simulation when hit, e.g. to catch rare physics
+/// blowups
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Breakpoint {
+    /// Cells of matter in simulated area exceed count
+    MatterCount {
+        matter: u32,
+        count: u64,
+    },
+    /// Any rigid body moves faster than this (world units per second)
+    Velocity { speed: f32 },
+    /// An object deforms inside canvas area, min & max inclusive
+    Deformation {
+        min: Vector2<i32>,
+        max: Vector2<i32>,
+    },
+}
+
+/// Breakpoints checked after each simulation step
+pub struct Breakpoints {
+    pub breakpoints: Vec<Breakpoint>,
+    /// Simulation step count when breakpoints were last checked
+    checked_step: u64,
+}
+
+impl Breakpoints {
+    pub fn new() -> Breakpoints {
+        Breakpoints {
+            breakpoints: vec![],
+            checked_step: 0,
+        }
+    }
+
+    /// Check breakpoints once results of a new step have been read back. Returns description of
+    /// the first breakpoint hit
+    pub fn check(
+        &mut self,
+        api: &EngineApi<InputAction>,
+        simulation: &Simulation,
+    ) -> Result<Option<String>> {
+        if self.breakpoints.is_empty() || simulation.step_count == self.checked_step {
+            return Ok(None);
+        }
+        self.checked_step = simulation.step_count;
+        // Matter counts are read from gpu buffers, so only when needed
+        let mut matter_counts = None;
+        for breakpoint in self.breakpoints.iter() {
+            let hit = match *breakpoint {
+                Breakpoint::MatterCount {
+                    matter,
+                    count,
+                } => {
+                    if matter_counts.is_none() {
+                        matter_counts = Some(simulation.matter_counts()?);
+                    }
+                    let counts = matter_counts.as_ref().unwrap();
+                    let cells = counts.get(matter as usize).copied().unwrap_or(0);
+                    let name = simulation
+                        .matter_definitions
+                        .definitions
+                        .get(matter as usize)
+                        .map_or("Unknown", |m| m.name.as_str());
+                    (cells > count).then(|| format!("{} cells of {} exceed {}", cells, name, count))
+                }
+                Breakpoint::Velocity {
+                    speed,
+                } => api
+                    .physics_world
+                    .physics
+                    .bodies
+                    .iter()
+                    .map(|(_, body)| body.linvel().norm())
+                    // NaN speed is the blowup this is meant to catch, yet never compares greater
+                    .find(|body_speed| !body_speed.is_finite() || *body_speed > speed)
+                    .map(|body_speed| format!("Body speed {:.2} exceeds {:.2}", body_speed, speed)),
+                Breakpoint::Deformation {
+                    min,
+                    max,
+                } => simulation.deformed_object_positions.iter().find_map(|pos| {
+                    if !pos.x.is_finite() || !pos.y.is_finite() {
+                        return Some(format!("Object deformed at ({}, {})", pos.x, pos.y));
+                    }
+                    // Positions beyond i32 range can't be inside the area
+                    let pos = world_pos_to_canvas_pos(*pos).cast::<i32>()?;
+                    (pos.x >= min.x && pos.x <= max.x && pos.y >= min.y && pos.y <= max.y)
+                        .then(|| format!("Object deformed at ({}, {})", pos.x, pos.y))
+                }),
+            };
+            if hit.is_some() {
+                return Ok(hit);
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,217 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::*;
+use cgmath::Vector2;
+use corrode::renderer::Camera2D;
+use serde::{Deserialize, Serialize};
+
+use crate::{data_dirs::DATA_DIRS, utils::data_file_path};
+
+/// Camera position & zoom at a time of a camera path
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct CameraKeyframe {
+    /// Seconds from the start of the path
+    pub time: f32,
+    pub pos: [f32; 2],
+    pub zoom: f32,
+}
+
+/// Keyframes of camera motion played back smoothly, e.g. for showcase videos recorded with
+/// compact HUD. Saved in camera_paths of data directory
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CameraPath {
+    /// Sorted by time
+    pub keyframes: Vec<CameraKeyframe>,
+    /// Start over when the end is reached
+    #[serde(default)]
+    pub looped: bool,
+    /// Seconds played, None when not playing
+    #[serde(skip)]
+    playhead: Option<f32>,
+}
+
+impl CameraPath {
+    pub fn new() -> CameraPath {
+        CameraPath::default()
+    }
+
+    /// Time of the last keyframe
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+    }
+
+    /// Add camera's current view at `time`, replacing a keyframe at the same time
+    pub fn add_keyframe(&mut self, camera: &Camera2D, time: f32) {
+        let keyframe = CameraKeyframe {
+            time,
+            pos: camera.pos().into(),
+            zoom: camera.zoom_level(),
+        };
+        self.keyframes.retain(|k| k.time != time);
+        let index = self.keyframes.partition_point(|k| k.time < time);
+        self.keyframes.insert(index, keyframe);
+    }
+
+    /// Keep keyframes sorted after their times were edited
+    pub fn sort(&mut self) {
+        self.keyframes
+            .sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap_or(std::cmp::Ordering::Equal));
+    }
+
+    /// Camera position & zoom at `time`. Positions follow a Catmull-Rom spline through keyframes &
+    /// zoom is interpolated geometrically, so zooming in & out feel equally fast
+    pub fn sample(&self, time: f32) -> Option<(Vector2<f32>, f32)> {
+        let first = self.keyframes.first()?;
+        let last = self.keyframes.last()?;
+        if time <= first.time || self.keyframes.len() == 1 {
+            return Some((first.pos.into(), first.zoom));
+        }
+        if time >= last.time {
+            return Some((last.pos.into(), last.zoom));
+        }
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        let keyframe = |index: isize| {
+            let index = index.clamp(0, self.keyframes.len() as isize - 1) as usize;
+            self.keyframes[index]
+        };
+        let (k0, k1, k2, k3) = (
+            keyframe(next as isize - 2),
+            keyframe(next as isize - 1),
+            keyframe(next as isize),
+            keyframe(next as isize + 1),
+        );
+        let span = k2.time - k1.time;
+        let t = if span > 0.0 {
+            (time - k1.time) / span
+        } else {
+            1.0
+        };
+        let pos = catmull_rom(k0.pos.into(), k1.pos.into(), k2.pos.into(), k3.pos.into(), t);
+        let zoom = (k1.zoom.ln() + (k2.zoom.ln() - k1.zoom.ln()) * t).exp();
+        Some((pos, zoom))
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playhead.is_some()
+    }
+
+    /// Seconds played, None when not playing
+    pub fn playhead(&self) -> Option<f32> {
+        self.playhead
+    }
+
+    pub fn play(&mut self) {
+        if !self.keyframes.is_empty() {
+            self.playhead = Some(0.0);
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.playhead = None;
+    }
+
+    /// Advance playback by `dt_secs` & move camera along the path. Stops at the end unless looped
+    pub fn update(&mut self, camera: &mut Camera2D, dt_secs: f32) {
+        let playhead = match self.playhead {
+            Some(playhead) => playhead + dt_secs,
+            None => return,
+        };
+        let duration = self.duration();
+        self.playhead = if playhead <= duration {
+            Some(playhead)
+        } else if self.looped && duration > 0.0 {
+            Some(playhead % duration)
+        } else {
+            None
+        };
+        if let Some((pos, zoom)) = self.sample(self.playhead.unwrap_or(duration)) {
+            camera.set_pos(pos);
+            camera.reset_zoom();
+            camera.zoom(zoom);
+        }
+    }
+
+    pub fn load(name: &str) -> Result<CameraPath> {
+        let data = fs::read_to_string(camera_path_file(name)?)?;
+        serde_json::from_str(&data).with_context(|| format!("Invalid camera path {}", name))
+    }
+
+    pub fn save(&self, name: &str) -> Result<()> {
+        fs::write(camera_path_file(name)?, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+fn catmull_rom(
+    p0: Vector2<f32>,
+    p1: Vector2<f32>,
+    p2: Vector2<f32>,
+    p3: Vector2<f32>,
+    t: f32,
+) -> Vector2<f32> {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+        * 0.5
+}
+
+/// Names of saved camera paths, sorted
+pub fn camera_path_names() -> Result<Vec<String>> {
+    let mut names = vec![];
+    for entry in fs::read_dir(camera_paths_dir()?)? {
+        let path = entry?.path();
+        if path.extension().map_or(false, |ext| ext == "json") {
+            if let Some(name) = path.file_stem() {
+                names.push(name.to_string_lossy().to_string());
+            }
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+fn camera_paths_dir() -> Result<PathBuf> {
+    let dir_path = DATA_DIRS.data.join("camera_paths");
+    fs::create_dir_all(&dir_path)?;
+    Ok(dir_path)
+}
+
+/// Camera path file by name, which can't lead outside camera paths directory
+fn camera_path_file(name: &str) -> Result<PathBuf> {
+    data_file_path(&camera_paths_dir()?, name, "json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_camera_path_passes_keyframes() {
+        let mut path = CameraPath::new();
+        let mut camera = Camera2D::new(Vector2::new(0.0, 0.0), 1.0, 1.0);
+        path.add_keyframe(&camera, 0.0);
+        camera.set_pos(Vector2::new(2.0, 0.0));
+        camera.zoom(4.0);
+        path.add_keyframe(&camera, 2.0);
+        camera.set_pos(Vector2::new(2.0, 2.0));
+        path.add_keyframe(&camera, 1.0);
+        assert_eq!(path.keyframes[1].time, 1.0);
+        // Keyframes are passed exactly, zoom halfway is the geometric mean
+        let (pos, _) = path.sample(1.0).unwrap();
+        assert!((pos.x - 2.0).abs() < 1e-5 && (pos.y - 2.0).abs() < 1e-5);
+        let (_, zoom) = path.sample(0.5).unwrap();
+        assert!((zoom - 2.0).abs() < 1e-4);
+        // Playback stops at the end & leaves camera at the last keyframe
+        path.play();
+        path.update(&mut camera, 3.0);
+        assert!(!path.is_playing());
+        assert_eq!(camera.pos(), Vector2::new(2.0, 0.0));
+        path.looped = true;
+        path.play();
+        path.update(&mut camera, 2.5);
+        assert_eq!(path.playhead(), Some(0.5));
+    }
+}
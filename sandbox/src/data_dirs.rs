@@ -0,0 +1,71 @@
+use std::{env::current_dir, path::PathBuf};
+
+use directories::ProjectDirs;
+
+/// Launch flag keeping all data in `assets` of working directory, e.g. for running from a usb stick
+pub const PORTABLE_FLAG: &str = "--portable";
+
+lazy_static! {
+    /// Directories of this launch, see `DataDirs`
+    pub static ref DATA_DIRS: DataDirs = DataDirs::from_args();
+}
+
+/// Where assets, user data & settings are read from and written to. By default user data &
+/// settings go to platform data & config directories, while bundled assets are read from
+/// `assets` in working directory. In portable mode all of them are that `assets` directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataDirs {
+    /// Bundled assets: object images & mods
+    pub assets: PathBuf,
+    /// User data: maps, stamps, brushes, replays & stats
+    pub data: PathBuf,
+    /// Settings profiles, benchmark results & matter definitions
+    pub config: PathBuf,
+    pub portable: bool,
+}
+
+impl DataDirs {
+    fn from_args() -> DataDirs {
+        let portable = std::env::args().any(|arg| arg == PORTABLE_FLAG);
+        let dirs = if portable {
+            DataDirs::portable()
+        } else {
+            DataDirs::platform()
+        };
+        info!("Data directories: {:?}", dirs);
+        dirs
+    }
+
+    fn portable() -> DataDirs {
+        let assets = assets_dir();
+        DataDirs {
+            assets: assets.clone(),
+            data: assets.clone(),
+            config: assets,
+            portable: true,
+        }
+    }
+
+    /// Platform directories, falling back to portable if there are none (e.g. no home directory)
+    fn platform() -> DataDirs {
+        match ProjectDirs::from("", "", "Sandbox") {
+            Some(project_dirs) => DataDirs {
+                assets: assets_dir(),
+                data: project_dirs.data_dir().to_path_buf(),
+                config: project_dirs.config_dir().to_path_buf(),
+                portable: false,
+            },
+            None => {
+                warn!("No platform data directories found, using portable mode");
+                DataDirs::portable()
+            }
+        }
+    }
+}
+
+/// Bundled assets in working directory, where data was also kept before platform directories
+pub fn assets_dir() -> PathBuf {
+    current_dir()
+        .unwrap_or_else(|_| PathBuf::from("."))
+        .join("assets")
+}
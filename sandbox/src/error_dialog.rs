@@ -0,0 +1,71 @@
+use anyhow::*;
+use egui::{Align2, CtxRef};
+
+struct DialogError {
+    title: String,
+    details: Vec<String>,
+}
+
+/// Window listing errors of user triggered actions (e.g. failed map loads) one at a time, so
+/// they don't go unnoticed in the log
+pub struct ErrorDialog {
+    errors: Vec<DialogError>,
+}
+
+impl ErrorDialog {
+    pub fn new() -> ErrorDialog {
+        ErrorDialog {
+            errors: vec![],
+        }
+    }
+
+    pub fn push(&mut self, title: impl Into<String>, details: Vec<String>) {
+        let title = title.into();
+        error!("{}", title);
+        for detail in details.iter() {
+            error!("    {}", detail);
+        }
+        self.errors.push(DialogError {
+            title,
+            details,
+        });
+    }
+
+    /// Show error with its chain of causes as details
+    pub fn push_error(&mut self, title: impl Into<String>, error: &Error) {
+        self.push(title, error.chain().map(|cause| cause.to_string()).collect());
+    }
+
+    pub fn show(&mut self, ctx: &CtxRef) {
+        let mut close = false;
+        if let Some(error) = self.errors.first() {
+            egui::Window::new("Error")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.heading(&error.title);
+                    ui.separator();
+                    egui::ScrollArea::vertical()
+                        .max_height(200.0)
+                        .show(ui, |ui| {
+                            for detail in error.details.iter() {
+                                ui.label(detail);
+                            }
+                        });
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.button("OK").clicked().then(|| {
+                            close = true;
+                        });
+                        if self.errors.len() > 1 {
+                            ui.label(format!("{} more", self.errors.len() - 1));
+                        }
+                    });
+                });
+        }
+        if close {
+            self.errors.remove(0);
+        }
+    }
+}
@@ -1,23 +1,62 @@
-use std::ops::BitAnd;
+use std::{
+    collections::{BTreeSet, HashMap},
+    ops::BitAnd,
+};
 
+use anyhow::*;
 use cgmath::Vector2;
-use corrode::api::{physics_entity_at_pos, EngineApi};
-use egui::{Grid, ImageButton, Ui, Vec2};
+use corrode::{
+    alloc_counter::AllocationTracker,
+    api::{physics_entity_at_pos, EngineApi},
+    input_system::InputButton,
+    time::{FrameStats, TimeTracker},
+};
+use egui::{Align2, Grid, ImageButton, Ui, Vec2};
+use vulkano::sampler::Filter;
+use winit::monitor::VideoMode;
 
 use crate::{
-    app::InputAction,
-    interact::{Editor, EditorMode, EditorPlacer},
+    app::{default_key_bindings, InputAction},
+    breakpoints::{Breakpoint, Breakpoints},
+    camera_path::{camera_path_names, CameraPath},
+    data_dirs::{DATA_DIRS, PORTABLE_FLAG},
+    interact::{
+        BrushFill, BrushImages, BrushPattern, BrushShape, EditAction, Editor, EditorMode,
+        EditorPainter, EditorPlacer, EditorStamper, FillTool, HotkeyModifier, QuickBarItem,
+        ALL_BRUSH_SHAPES,
+    },
+    map_path,
     matter::{
-        Direction, MatterCharacteristic, MatterDefinition, MatterDefinitions, MatterState,
-        ALL_CHARACTERISTICS, ALL_DIRECTIONS, MATTER_EMPTY,
+        matter_pack_names, Direction, DryRun, MatterCharacteristic, MatterDefinition,
+        MatterDefinitions, MatterState, ALL_CHARACTERISTICS, ALL_DIRECTIONS, ALL_IMPACT_SOUNDS,
+        DECAY_INTERVAL, MATTER_EMPTY, MAX_REACTION_TICK_DIVIDER,
+    },
+    mods::Mods,
+    object::{Angle, CollisionPreset, ObjectCollision, Position, NUM_OBJECT_LAYERS},
+    pop_outs::{Panel, PanelTextures, PopOuts},
+    render::{Minimap, MAX_MINIMAP_RADIUS},
+    settings::{AppSettings, SimulationSeed},
+    settings_profiles::{
+        bound_key, current_key_bindings, key_binding_problem, key_name, rebind_key,
+        SettingsProfiles, BINDABLE_KEYS,
     },
-    object::{Angle, Position},
-    settings::AppSettings,
-    sim::{canvas_pos_to_world_pos, Simulation},
-    utils::{u32_rgba_to_u8_rgba, u8_rgba_to_u32_rgba, CanvasMouseState},
-    SIM_CANVAS_SIZE,
+    sim::{
+        canvas_pos_to_chunk_pos, canvas_pos_to_world_pos, chunk_disk_bytes, replay_names,
+        world_pos_to_canvas_pos, Annotation, ChunkLoadState, ChunkStreaming, DebrisPolicy,
+        LiquidModel, ObjectJointKind, OreVeins, PortalPair, ReactionKind, ReactionStats, Replay,
+        Shape, Simulation, TriggerAction, TriggerZone, MAX_PORTALS,
+    },
+    step_control::{StepControl, ALL_STOP_CONDITIONS},
+    stress_test::StressTest,
+    utils::{CanvasMouseState, Color32},
+    version_check::BUILD_VERSION,
+    watches::{Watch, WatchValue, Watches},
+    CELL_UNIT_SIZE, SIM_CANVAS_SIZE,
 };
 
+/// Screen points the world map's chunk grid aims to span
+const WORLD_MAP_SIZE: f32 = 240.0;
+
 fn get_selected_characteristics(
     current_characteristics: MatterCharacteristic,
 ) -> Vec<(MatterCharacteristic, &'static str, &'static str, bool)> {
@@ -40,6 +79,20 @@ fn get_selected_directions(current_directions: Direction) -> Vec<(Direction, &'s
         .collect()
 }
 
+/// Matters picked for matter pack export. Picked by name, so removing matters keeps the picks
+struct MatterPackForm {
+    name: String,
+    picked: BTreeSet<String>,
+}
+
+/// Neighbors of the matter being edited in the reaction sandbox, ordered as `ALL_DIRECTIONS`,
+/// & result of the latest dry run
+struct ReactionSandbox {
+    neighbors: [u32; 8],
+    steps: u32,
+    dry_run: Option<DryRun>,
+}
+
 pub struct GuiState {
     pub show_guide_view: bool,
     pub show_info_view: bool,
@@ -47,7 +100,30 @@ pub struct GuiState {
     pub show_load_view: bool,
     pub show_settings_view: bool,
     pub show_new_matter_view: bool,
+    pub show_triggers_view: bool,
+    pub show_portals_view: bool,
+    pub show_annotations_view: bool,
+    pub show_breakpoints_view: bool,
+    pub show_watch_view: bool,
+    pub show_reactions_view: bool,
+    pub show_mods_view: bool,
+    pub show_minimap_view: bool,
+    pub show_world_map_view: bool,
+    pub show_camera_path_view: bool,
+    pub show_stress_test_view: bool,
+    /// Hide windows, showing only a minimal HUD (e.g. for recording videos)
+    pub compact_hud: bool,
+    pub pop_outs: PopOuts,
+    pub minimap: Minimap,
+    /// Chunk file sizes of the map directory, by map name. Rescanned when the map changes
+    map_disk_bytes: Option<(String, HashMap<Vector2<i32>, u64>)>,
     add_matter: MatterDefinition,
+    matter_pack: MatterPackForm,
+    reaction_sandbox: ReactionSandbox,
+    /// Name camera path is saved as
+    camera_path_name: String,
+    /// Seconds from the last keyframe to a new one
+    keyframe_gap: f32,
 }
 
 impl GuiState {
@@ -59,7 +135,33 @@ impl GuiState {
             show_load_view: false,
             show_new_matter_view: false,
             show_settings_view: false,
+            show_triggers_view: false,
+            show_portals_view: false,
+            show_annotations_view: false,
+            show_breakpoints_view: false,
+            show_watch_view: false,
+            show_reactions_view: false,
+            show_mods_view: false,
+            show_minimap_view: false,
+            show_world_map_view: false,
+            show_camera_path_view: false,
+            show_stress_test_view: false,
+            compact_hud: false,
+            pop_outs: PopOuts::new(),
+            minimap: Minimap::new(),
+            map_disk_bytes: None,
             add_matter: MatterDefinition::zero(),
+            matter_pack: MatterPackForm {
+                name: "my_matters".to_string(),
+                picked: BTreeSet::new(),
+            },
+            reaction_sandbox: ReactionSandbox {
+                neighbors: [MATTER_EMPTY; 8],
+                steps: 1000,
+                dry_run: None,
+            },
+            camera_path_name: "showcase".to_string(),
+            keyframe_gap: 3.0,
         }
     }
 
@@ -69,12 +171,34 @@ impl GuiState {
         simulation: &mut Simulation,
         editor: &mut Editor,
         settings: &mut AppSettings,
-        is_running_simulation: bool,
+        settings_profiles: &mut SettingsProfiles,
+        step_control: &mut StepControl,
+        breakpoints: &mut Breakpoints,
+        watches: &mut Watches,
+        reaction_stats: &mut ReactionStats,
+        mods: &mut Mods,
+        camera_path: &mut CameraPath,
+        stress_test: &mut StressTest,
         is_debug: &mut bool,
         frame_time: f64,
         render_time: f64,
         sim_time: f64,
+        frame_stats: &mut FrameStats,
+        allocations: (&AllocationTracker, &AllocationTracker),
     ) {
+        let hotkeys = editor.key_gate.is_open(
+            api.inputs[0].modifiers,
+            api.gui.context().wants_keyboard_input(),
+        );
+        if hotkeys && api.inputs[0].is_action_activated(InputAction::ToggleCompactHud) {
+            self.compact_hud = !self.compact_hud;
+        }
+        self.pop_outs.update(api, editor, simulation);
+        if self.compact_hud {
+            self.add_compact_hud(api, simulation, editor);
+            editor.error_dialog.show(&api.gui.context());
+            return;
+        }
         egui::TopBottomPanel::top("Test").show(&api.gui.context(), |ui| {
             ui.horizontal(|ui| {
                 ui.selectable_label(self.show_edit_view, "Editor")
@@ -97,6 +221,69 @@ impl GuiState {
                     .then(|| {
                         self.show_load_view = !self.show_load_view;
                     });
+                ui.selectable_label(self.show_triggers_view, "Triggers")
+                    .clicked()
+                    .then(|| {
+                        self.show_triggers_view = !self.show_triggers_view;
+                    });
+                ui.selectable_label(self.show_portals_view, "Portals")
+                    .clicked()
+                    .then(|| {
+                        self.show_portals_view = !self.show_portals_view;
+                    });
+                ui.selectable_label(self.show_annotations_view, "Annotations")
+                    .clicked()
+                    .then(|| {
+                        self.show_annotations_view = !self.show_annotations_view;
+                    });
+                ui.selectable_label(self.show_minimap_view, "Minimap")
+                    .clicked()
+                    .then(|| {
+                        self.show_minimap_view = !self.show_minimap_view;
+                    });
+                ui.selectable_label(self.show_world_map_view, "World Map")
+                    .clicked()
+                    .then(|| {
+                        self.show_world_map_view = !self.show_world_map_view;
+                    });
+                ui.selectable_label(self.show_camera_path_view, "Camera Path")
+                    .clicked()
+                    .then(|| {
+                        self.show_camera_path_view = !self.show_camera_path_view;
+                    });
+                ui.selectable_label(self.show_stress_test_view, "Stress Test")
+                    .clicked()
+                    .then(|| {
+                        self.show_stress_test_view = !self.show_stress_test_view;
+                    });
+                ui.selectable_label(self.show_breakpoints_view, "Breakpoints")
+                    .clicked()
+                    .then(|| {
+                        self.show_breakpoints_view = !self.show_breakpoints_view;
+                    });
+                ui.selectable_label(self.show_watch_view, "Watch")
+                    .clicked()
+                    .then(|| {
+                        self.show_watch_view = !self.show_watch_view;
+                    });
+                ui.selectable_label(self.show_reactions_view, "Reactions")
+                    .clicked()
+                    .then(|| {
+                        self.show_reactions_view = !self.show_reactions_view;
+                        // Counts from while the window was closed aren't shown
+                        if self.show_reactions_view {
+                            if let Err(e) = reaction_stats.reset(simulation) {
+                                editor
+                                    .error_dialog
+                                    .push_error("Failed to reset reaction counts", &e);
+                            }
+                        }
+                    });
+                ui.selectable_label(self.show_mods_view, "Mods")
+                    .clicked()
+                    .then(|| {
+                        self.show_mods_view = !self.show_mods_view;
+                    });
                 ui.selectable_label(self.show_guide_view, "Guide")
                     .clicked()
                     .then(|| {
@@ -109,19 +296,41 @@ impl GuiState {
                     });
             })
         });
-        self.add_settings_window(api, simulation, settings, is_debug);
+        self.add_settings_window(api, simulation, editor, settings, settings_profiles, is_debug);
         self.add_editor_window(api, simulation, editor);
         self.add_info_window(
             api,
             simulation,
-            is_running_simulation,
+            step_control,
             frame_time,
             render_time,
             sim_time,
+            frame_stats,
+            allocations,
         );
         self.add_load_save_window(api, simulation, editor, settings);
         self.add_new_matter_window(api, simulation, editor);
+        self.add_triggers_window(api, simulation);
+        self.add_portals_window(api, simulation);
+        self.add_annotations_window(api, simulation);
+        self.add_minimap_window(api, simulation);
+        self.add_world_map_window(api, simulation, editor);
+        self.add_camera_path_window(api, editor, camera_path);
+        self.add_stress_test_window(
+            api,
+            simulation,
+            editor,
+            stress_test,
+            (frame_time, render_time, sim_time),
+        );
+        self.add_breakpoints_window(api, simulation, breakpoints);
+        self.add_watch_window(api, simulation, watches);
+        self.add_reactions_window(api, simulation, editor, reaction_stats);
+        self.add_mods_window(api, editor, mods);
         self.add_guide_view(api);
+        self.add_quick_bar(api, simulation, editor);
+        editor.toasts.show(&api.gui.context(), api.time.time_secs());
+        editor.error_dialog.show(&api.gui.context());
         if *is_debug {
             self.add_query_tooltip(api, simulation);
         }
@@ -135,8 +344,12 @@ impl GuiState {
     ) {
         let GuiState {
             show_new_matter_view,
+            pop_outs,
             ..
         } = self;
+        if !*show_new_matter_view && pop_outs.is_popped(Panel::Matters) {
+            pop_outs.dock(api, Panel::Matters);
+        }
         if let Some(def) = simulation
             .matter_definitions
             .definitions
@@ -148,181 +361,296 @@ impl GuiState {
             self.add_matter.id = simulation.matter_definitions.definitions.len() as u32;
         }
         self.add_matter.id = simulation.matter_definitions.definitions.len() as u32;
-        let rgba = u32_rgba_to_u8_rgba(self.add_matter.color);
+        let rgba = Color32::from_u32_rgba(self.add_matter.color).to_rgba();
         let mut color = [rgba[0], rgba[1], rgba[2]];
         let color_before = color;
         let selected_characteristics =
             get_selected_characteristics(self.add_matter.characteristics);
         let reactions = self.add_matter.reactions;
         let ctx = api.gui.context();
-        egui::Window::new("Edit Matters")
-            .open(show_new_matter_view)
-            .default_width(200.0)
-            .default_height(600.0)
-            .vscroll(true)
-            .show(&ctx, |ui| {
-                ui.group(|ui| {
-                    ui.label("Name");
-                    ui.text_edit_singleline(&mut self.add_matter.name);
-                    ui.label("Color");
-                    ui.color_edit_button_srgb(&mut color);
-                    ui.label("Weight")
-                        .on_hover_text("Weight affects fall order in liquids");
-                    ui.add(egui::Slider::new(&mut self.add_matter.weight, 0.0..=5.0));
-                    egui::ComboBox::from_label("Matter State")
-                        .selected_text(format!("{:?}", self.add_matter.state.to_string()))
-                        .show_ui(ui, |ui| {
-                            ui.selectable_value(
-                                &mut self.add_matter.state,
-                                MatterState::Powder,
-                                "Powder",
-                            );
-                            ui.selectable_value(
-                                &mut self.add_matter.state,
-                                MatterState::Liquid,
-                                "Liquid",
-                            );
-                            ui.selectable_value(
-                                &mut self.add_matter.state,
-                                MatterState::Solid,
-                                "Solid",
-                            );
-                            ui.selectable_value(
-                                &mut self.add_matter.state,
-                                MatterState::SolidGravity,
-                                "Solid Gravity",
-                            );
-                            ui.selectable_value(
-                                &mut self.add_matter.state,
-                                MatterState::Gas,
-                                "Gas",
-                            );
-                            ui.selectable_value(
-                                &mut self.add_matter.state,
-                                MatterState::Energy,
-                                "Energy",
-                            );
-                        });
-                    ui.label("Dispersion");
-                    ui.add(egui::Slider::new(&mut self.add_matter.dispersion, 0..=10))
-                        .on_hover_text("Spreading speed for liquids or gases");
-                    ui.collapsing("Characteristics", |ui| {
-                        for (val, text, guide, is_selected) in selected_characteristics.iter() {
-                            ui.selectable_label(*is_selected, *text)
-                                .on_hover_text(*guide)
-                                .clicked()
-                                .then(|| {
-                                    if *is_selected {
-                                        self.add_matter.characteristics.remove(*val);
-                                    } else {
-                                        self.add_matter.characteristics.insert(*val);
-                                    }
-                                });
-                        }
+        let is_popped = pop_outs.is_popped(Panel::Matters);
+        let window_gui = pop_outs.window_gui(api, Panel::Matters);
+        let textures = window_gui
+            .as_ref()
+            .map_or(PanelTextures::MAIN, |(_, textures)| *textures);
+        let mut toggle_pop_out = false;
+        let contents = |ui: &mut Ui| {
+            add_pop_out_button(ui, is_popped, &mut toggle_pop_out);
+            ui.group(|ui| {
+                ui.label("Name");
+                ui.text_edit_singleline(&mut self.add_matter.name);
+                ui.label("Color");
+                ui.color_edit_button_srgb(&mut color);
+                ui.label("Weight")
+                    .on_hover_text("Weight affects fall order in liquids");
+                ui.add(egui::Slider::new(&mut self.add_matter.weight, 0.0..=5.0));
+                ui.label("Gravity").on_hover_text(
+                    "Below 1.0 falls (or rises for gases) slower, negative reverses direction",
+                );
+                ui.add(egui::Slider::new(&mut self.add_matter.gravity, -1.0..=1.0));
+                ui.label("Hardness")
+                    .on_hover_text("Brush strength needed to erase matter in one pass");
+                ui.add(egui::Slider::new(&mut self.add_matter.hardness, 0.0..=1.0));
+                egui::ComboBox::from_label("Matter State")
+                    .selected_text(format!("{:?}", self.add_matter.state.to_string()))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(
+                            &mut self.add_matter.state,
+                            MatterState::Powder,
+                            "Powder",
+                        );
+                        ui.selectable_value(
+                            &mut self.add_matter.state,
+                            MatterState::Liquid,
+                            "Liquid",
+                        );
+                        ui.selectable_value(
+                            &mut self.add_matter.state,
+                            MatterState::Solid,
+                            "Solid",
+                        );
+                        ui.selectable_value(
+                            &mut self.add_matter.state,
+                            MatterState::SolidGravity,
+                            "Solid Gravity",
+                        );
+                        ui.selectable_value(
+                            &mut self.add_matter.state,
+                            MatterState::Gas,
+                            "Gas",
+                        );
+                        ui.selectable_value(
+                            &mut self.add_matter.state,
+                            MatterState::Energy,
+                            "Energy",
+                        );
                     });
-                    ui.collapsing("Reactions", |ui| {
-                        for (index, reaction) in reactions.iter().enumerate() {
-                            ui.collapsing(format!("{}: Reacts with", index), |ui| {
-                                for (val, text, guide, is_selected) in
-                                    get_selected_characteristics(reaction.reacts).iter()
-                                {
-                                    ui.selectable_label(*is_selected, *text)
-                                        .on_hover_text(*guide)
-                                        .clicked()
-                                        .then(|| {
-                                            if *is_selected {
-                                                self.add_matter.reactions[index]
-                                                    .reacts
-                                                    .remove(*val);
-                                            } else {
-                                                self.add_matter.reactions[index]
-                                                    .reacts
-                                                    .insert(*val);
-                                            }
-                                        });
+                ui.label("Dispersion");
+                ui.add(egui::Slider::new(&mut self.add_matter.dispersion, 0..=10))
+                    .on_hover_text("Spreading speed for liquids or gases");
+                ui.collapsing("Characteristics", |ui| {
+                    for (val, text, guide, is_selected) in selected_characteristics.iter() {
+                        ui.selectable_label(*is_selected, *text)
+                            .on_hover_text(*guide)
+                            .clicked()
+                            .then(|| {
+                                if *is_selected {
+                                    self.add_matter.characteristics.remove(*val);
+                                } else {
+                                    self.add_matter.characteristics.insert(*val);
                                 }
                             });
-                            ui.collapsing(format!("{}: Reacts direction", index), |ui| {
-                                for (val, text, is_selected) in
-                                    get_selected_directions(reaction.direction).iter()
-                                {
-                                    ui.selectable_label(*is_selected, *text).clicked().then(|| {
+                    }
+                });
+                ui.collapsing("Reactions", |ui| {
+                    for (index, reaction) in reactions.iter().enumerate() {
+                        ui.collapsing(format!("{}: Reacts with", index), |ui| {
+                            for (val, text, guide, is_selected) in
+                                get_selected_characteristics(reaction.reacts).iter()
+                            {
+                                ui.selectable_label(*is_selected, *text)
+                                    .on_hover_text(*guide)
+                                    .clicked()
+                                    .then(|| {
                                         if *is_selected {
-                                            self.add_matter.reactions[index].direction.remove(*val);
+                                            self.add_matter.reactions[index]
+                                                .reacts
+                                                .remove(*val);
                                         } else {
-                                            self.add_matter.reactions[index].direction.insert(*val);
+                                            self.add_matter.reactions[index]
+                                                .reacts
+                                                .insert(*val);
                                         }
                                     });
-                                }
-                            });
-                            ui.add(egui::Slider::new(
-                                &mut self.add_matter.reactions[index].probability,
-                                0.0..=1.0,
-                            ))
-                            .on_hover_text("Probability");
-                            egui::ComboBox::from_label(format!("{}: Becomes", index))
-                                .selected_text(format!(
-                                    "{:?}",
-                                    simulation.matter_definitions.definitions
-                                        [self.add_matter.reactions[index].becomes as usize]
-                                        .name
-                                ))
-                                .show_ui(ui, |ui| {
-                                    for (id, definition) in
-                                        simulation.matter_definitions.definitions.iter().enumerate()
-                                    {
-                                        ui.selectable_value(
-                                            &mut self.add_matter.reactions[index].becomes,
-                                            id as u32,
-                                            &definition.name,
-                                        );
+                            }
+                        });
+                        ui.collapsing(format!("{}: Reacts direction", index), |ui| {
+                            for (val, text, is_selected) in
+                                get_selected_directions(reaction.direction).iter()
+                            {
+                                ui.selectable_label(*is_selected, *text).clicked().then(|| {
+                                    if *is_selected {
+                                        self.add_matter.reactions[index].direction.remove(*val);
+                                    } else {
+                                        self.add_matter.reactions[index].direction.insert(*val);
                                     }
                                 });
-                            ui.separator();
-                        }
-                    });
-                    ui.separator();
-                    if let Some(def) = simulation
-                        .matter_definitions
-                        .definitions
-                        .iter()
-                        .find(|d| d.name == self.add_matter.name)
-                    {
-                        self.add_matter.id = def.id;
-                        ui.button(format!("Update {}", self.add_matter.name))
-                            .clicked()
-                            .then(|| {
-                                simulation
-                                    .add_matter_to_definitions(self.add_matter.clone())
-                                    .unwrap();
-                                editor.update_matter_gui_textures(api, simulation);
-                            });
-                    } else {
-                        ui.button("Add").clicked().then(|| {
-                            simulation
-                                .add_matter_to_definitions(self.add_matter.clone())
-                                .unwrap();
-                            editor.update_matter_gui_textures(api, simulation);
+                            }
                         });
+                        ui.add(egui::Slider::new(
+                            &mut self.add_matter.reactions[index].probability,
+                            0.0..=1.0,
+                        ))
+                        .on_hover_text("Probability");
+                        ui.add(
+                            egui::Slider::new(
+                                &mut self.add_matter.reactions[index].tick_divider,
+                                1..=MAX_REACTION_TICK_DIVIDER,
+                            )
+                            .logarithmic(true),
+                        )
+                        .on_hover_text("Reaction is tried every this many simulation steps");
+                        egui::ComboBox::from_label(format!("{}: Becomes", index))
+                            .selected_text(format!(
+                                "{:?}",
+                                simulation.matter_definitions.definitions
+                                    [self.add_matter.reactions[index].becomes as usize]
+                                    .name
+                            ))
+                            .show_ui(ui, |ui| {
+                                for (id, definition) in
+                                    simulation.matter_definitions.definitions.iter().enumerate()
+                                {
+                                    ui.selectable_value(
+                                        &mut self.add_matter.reactions[index].becomes,
+                                        id as u32,
+                                        &definition.name,
+                                    );
+                                }
+                            });
+                        ui.separator();
                     }
                 });
-                ui.group(|ui| {
-                    add_matter_edit_palette(ui, api, simulation, editor, &mut self.add_matter);
+                ui.collapsing("Decay", |ui| {
+                    let matter_defs = &simulation.matter_definitions.definitions;
+                    let decay = &mut self.add_matter.decay;
+                    ui.add(
+                        egui::Slider::new(&mut decay.probability, 0.0..=0.1).logarithmic(true),
+                    )
+                    .on_hover_text(format!(
+                        "Probability to decay, checked every {} simulation steps",
+                        DECAY_INTERVAL
+                    ));
+                    egui::ComboBox::from_label("Decays into")
+                        .selected_text(&matter_defs[decay.becomes as usize].name)
+                        .show_ui(ui, |ui| {
+                            for (id, definition) in matter_defs.iter().enumerate() {
+                                ui.selectable_value(
+                                    &mut decay.becomes,
+                                    id as u32,
+                                    &definition.name,
+                                );
+                            }
+                        });
+                    egui::ComboBox::from_label("Emits")
+                        .selected_text(&matter_defs[decay.emits as usize].name)
+                        .show_ui(ui, |ui| {
+                            for (id, definition) in matter_defs.iter().enumerate() {
+                                ui.selectable_value(
+                                    &mut decay.emits,
+                                    id as u32,
+                                    &definition.name,
+                                );
+                            }
+                        });
+                });
+                egui::ComboBox::from_label("Impact Sound")
+                    .selected_text(match self.add_matter.impact_sound {
+                        Some(sound) => sound.name(),
+                        None => "By state",
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(
+                            &mut self.add_matter.impact_sound,
+                            None,
+                            "By state",
+                        );
+                        for sound in ALL_IMPACT_SOUNDS {
+                            ui.selectable_value(
+                                &mut self.add_matter.impact_sound,
+                                Some(sound),
+                                sound.name(),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text("Sound of objects hitting this matter");
+                ui.separator();
+                if let Some(def) = simulation
+                    .matter_definitions
+                    .definitions
+                    .iter()
+                    .find(|d| d.name == self.add_matter.name)
+                {
+                    self.add_matter.id = def.id;
+                    ui.button(format!("Update {}", self.add_matter.name))
+                        .clicked()
+                        .then(|| {
+                            let result =
+                                simulation.add_matter_to_definitions(self.add_matter.clone());
+                            editor.toasts.report(
+                                result,
+                                format!("Updated matter {}", self.add_matter.name),
+                            );
+                            editor.update_matter_gui_textures(api, simulation);
+                        });
+                } else {
+                    ui.button("Add").clicked().then(|| {
+                        let result =
+                            simulation.add_matter_to_definitions(self.add_matter.clone());
+                        editor
+                            .toasts
+                            .report(result, format!("Added matter {}", self.add_matter.name));
+                        editor.update_matter_gui_textures(api, simulation);
+                    });
+                }
+            });
+            ui.group(|ui| {
+                ui.collapsing("Reaction Sandbox", |ui| {
+                    add_reaction_sandbox(
+                        ui,
+                        simulation,
+                        &self.add_matter,
+                        &mut self.reaction_sandbox,
+                    );
                 });
             });
+            ui.group(|ui| {
+                add_matter_edit_palette(
+                    ui,
+                    api,
+                    simulation,
+                    editor,
+                    &mut self.add_matter,
+                    &mut self.matter_pack,
+                    textures,
+                );
+            });
+        };
+        match window_gui {
+            Some((window_ctx, _)) => {
+                egui::CentralPanel::default().show(&window_ctx, |ui| {
+                    egui::ScrollArea::vertical().show(ui, contents);
+                });
+            }
+            None => {
+                egui::Window::new("Edit Matters")
+                    .open(show_new_matter_view)
+                    .default_width(200.0)
+                    .default_height(600.0)
+                    .vscroll(true)
+                    .show(&ctx, contents);
+            }
+        }
+        if toggle_pop_out {
+            pop_outs.toggle(api, Panel::Matters);
+        }
         if color_before != color {
-            self.add_matter.color = u8_rgba_to_u32_rgba(color[0], color[1], color[2], 255);
+            self.add_matter.color =
+                Color32::from_rgba(color[0], color[1], color[2], 255).to_u32_rgba();
         }
     }
 
     pub fn add_info_window(
         &mut self,
-        api: &EngineApi<InputAction>,
+        api: &mut EngineApi<InputAction>,
         simulation: &Simulation,
-        is_running_simulation: bool,
+        step_control: &mut StepControl,
         frame_time_average: f64,
         render_time_average: f64,
         sim_time_average: f64,
+        frame_stats: &mut FrameStats,
+        (frame_allocations, sim_allocations): (&AllocationTracker, &AllocationTracker),
     ) {
         let GuiState {
             show_info_view, ..
@@ -332,6 +660,12 @@ impl GuiState {
             .open(show_info_view)
             .default_width(200.0)
             .show(&ctx, |ui| {
+                ui.label(format!("Version: {}", BUILD_VERSION));
+                ui.separator();
+                ui.label("Stepping:");
+                ui.separator();
+                add_step_controls(ui, simulation, &mut api.time, step_control);
+                ui.separator();
                 ui.label("Macro level time averages:");
                 ui.separator();
                 ui.label(format!("FPS: {:.3}", api.time.avg_fps()));
@@ -339,8 +673,48 @@ impl GuiState {
                 ui.label(format!("Render: {:.3}", render_time_average));
                 ui.label(format!("Simulation: {:.3}", sim_time_average));
                 ui.separator();
+                ui.label("Frame lows:");
+                ui.separator();
+                ui.label(format!("1% low FPS: {:.3}", frame_stats.percentile_low_fps(1.0)));
+                ui.label(format!(
+                    "0.1% low FPS: {:.3}",
+                    frame_stats.percentile_low_fps(0.1)
+                ));
+                ui.collapsing(format!("Spikes ({})", frame_stats.spikes().len()), |ui| {
+                    for spike in frame_stats.spikes().iter().rev() {
+                        ui.label(format!(
+                            "{:.1}s: {:.1} ms (avg {:.1}), {}: {:.1} ms",
+                            spike.time_secs,
+                            spike.dt,
+                            spike.avg_dt,
+                            spike.slowest,
+                            spike.slowest_ms
+                        ));
+                    }
+                });
+                ui.button("Reset frame stats").clicked().then(|| {
+                    frame_stats.clear();
+                });
+                ui.separator();
+                ui.label("Allocations:");
+                ui.separator();
+                ui.label(format!(
+                    "Per frame: {:.0} (last {})",
+                    frame_allocations.average(),
+                    frame_allocations.latest()
+                ));
+                ui.label(format!(
+                    "Per sim step: {:.0} (last {})",
+                    sim_allocations.average(),
+                    sim_allocations.latest()
+                ));
+                ui.separator();
                 ui.label("Sim breakdown:");
                 ui.separator();
+                ui.label(format!(
+                    "Chunk loading: {:.3}",
+                    simulation.chunk_timer.time_average_ms()
+                ));
                 ui.label(format!(
                     "Obj write to grid: {:.3}",
                     simulation.obj_write_timer.time_average_ms()
@@ -349,6 +723,10 @@ impl GuiState {
                     "CA simulation: {:.3}",
                     simulation.ca_timer.time_average_ms()
                 ));
+                ui.label(format!(
+                    "CA gpu wait: {:.3}",
+                    simulation.ca_wait_timer.time_average_ms()
+                ));
                 ui.label(format!(
                     "Obj deformation: {:.3}",
                     simulation.obj_read_timer.time_average_ms()
@@ -362,7 +740,23 @@ impl GuiState {
                     simulation.physics_timer.time_average_ms()
                 ));
                 ui.separator();
-                ui.label(format!("Running: {}", is_running_simulation));
+                ui.label("CA gpu passes:");
+                ui.separator();
+                match simulation.gpu_timings() {
+                    Some(gpu_timings) => {
+                        for (pass, ms) in gpu_timings.averages_ms() {
+                            ui.label(format!("{}: {:.3}", pass.name(), ms));
+                        }
+                        ui.label(format!("Total: {:.3}", gpu_timings.total_average_ms()));
+                    }
+                    None => {
+                        ui.label("Gpu timestamps not supported");
+                    }
+                }
+                ui.separator();
+                ui.label(format!("Running: {}", !api.time.is_paused()));
+                ui.label(format!("Sim time: {:.1}s", api.time.sim_time_secs()));
+                ui.label(format!("Step: {}", simulation.step_count));
                 ui.label(format!("Num entities : {}", api.ecs_world.len()));
             });
     }
@@ -387,78 +781,1265 @@ impl GuiState {
                 add_loadable_maps(ui, editor, api, simulation);
                 ui.label("New map");
                 ui.separator();
-                ui.button("New")
+                ui.button("New").clicked().then(|| {
+                    editor.undo.clear();
+                    let result = editor.saver.new_map(api, simulation, settings.simulation_seed);
+                    editor.toasts.report(result, "New empty map");
+                });
+                ui.button("New with ore veins")
+                    .on_hover_text("New map, ore is generated once its chunks have loaded")
+                    .clicked()
+                    .then(|| {
+                        editor.undo.clear();
+                        let result =
+                            editor.saver.new_map(api, simulation, settings.simulation_seed);
+                        simulation.pending_ore_veins = Some(editor.ore_veins);
+                        editor.toasts.report(result, "New map with ore veins");
+                    });
+                ui.label("Ore veins");
+                ui.separator();
+                add_ore_vein_options(ui, &mut editor.ore_veins, &simulation.matter_definitions);
+                ui.button("Generate ore veins")
+                    .on_hover_text("Generate ore in simulated area of current map")
+                    .clicked()
+                    .then(|| {
+                        match simulation.generate_ore_veins(&editor.ore_veins) {
+                            std::result::Result::Ok(num_ore) => editor
+                                .toasts
+                                .info(format!("Generated {} cells of ore", num_ore)),
+                            Err(e) => editor
+                                .error_dialog
+                                .push_error("Failed to generate ore veins", &e),
+                        }
+                    });
+                let chunk_pos = canvas_pos_to_chunk_pos(simulation.camera_canvas_pos);
+                ui.label(format!("Chunk {}, {} at camera", chunk_pos.x, chunk_pos.y));
+                ui.separator();
+                ui.button("Reload chunk from disk")
+                    .on_hover_text("Discard changes to the chunk since the map was saved")
                     .clicked()
-                    .then(|| editor.saver.new_map(api, simulation));
+                    .then(|| {
+                        let map_name = editor.saver.map_name.clone();
+                        let result = simulation.reload_chunk_from_disk(&map_name, chunk_pos);
+                        let reloaded = format!("Reloaded chunk {}, {}", chunk_pos.x, chunk_pos.y);
+                        // Undo history of the chunk no longer matches its matter
+                        if editor.toasts.report(result, reloaded).is_some() {
+                            editor.undo.clear();
+                        }
+                    });
+                ui.button("Regenerate chunk")
+                    .on_hover_text("Discard changes to the chunk & generate ore veins in it anew")
+                    .clicked()
+                    .then(|| {
+                        let result = simulation.regenerate_chunk(chunk_pos, &editor.ore_veins);
+                        let regenerated =
+                            format!("Regenerated chunk {}, {}", chunk_pos.x, chunk_pos.y);
+                        if editor.toasts.report(result, regenerated).is_some() {
+                            editor.undo.clear();
+                        }
+                    });
                 ui.label("Save map");
                 ui.separator();
                 ui.text_edit_singleline(&mut editor.saver.map_name);
-                ui.button("Save")
+                ui.button("Save").clicked().then(|| {
+                    let result =
+                        editor.saver.save_map(api, simulation, settings, &editor.painter);
+                    let map_name = editor.saver.map_name.clone();
+                    editor.report(
+                        result,
+                        format!("Saving map {}...", map_name),
+                        format!("Failed to save map {}", map_name),
+                    );
+                });
+                ui.button("Export images")
+                    .on_hover_text("Write png images of the map's chunks to its export directory")
                     .clicked()
-                    .then(|| editor.saver.save_map(api, simulation, settings));
+                    .then(|| {
+                        let result = editor.saver.export_map_images(simulation, settings);
+                        let map_name = editor.saver.map_name.clone();
+                        editor.report(
+                            result,
+                            format!("Exporting images of map {}...", map_name),
+                            format!("Failed to export images of map {}", map_name),
+                        );
+                    });
+                // Chunks are read back & written in the background
+                for (name, written, total) in editor.saver.save_progress() {
+                    ui.add(
+                        egui::ProgressBar::new(written as f32 / total as f32)
+                            .text(format!("Writing {}: {} / {} files", name, written, total)),
+                    );
+                }
+                ui.label("Replays");
+                ui.separator();
+                add_replays(ui, editor, api, simulation);
             });
     }
 
-    pub fn add_guide_view(&mut self, api: &mut EngineApi<InputAction>) {
+    pub fn add_triggers_window(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        simulation: &mut Simulation,
+    ) {
         let GuiState {
-            show_guide_view, ..
+            show_triggers_view,
+            ..
         } = self;
         let ctx = api.gui.context();
-        egui::Window::new("Guide")
-            .open(show_guide_view)
-            .default_width(200.0)
+        let matter_names = simulation
+            .matter_definitions
+            .definitions
+            .iter()
+            .map(|d| d.name.clone())
+            .collect::<Vec<String>>();
+        let mut respawn = vec![];
+        let mut remove = None;
+        egui::Window::new("Trigger Zones")
+            .open(show_triggers_view)
+            .default_width(250.0)
+            .vscroll(true)
             .show(&ctx, |ui| {
-                ui.label("Keys:");
-                ui.separator();
-                ui.label("Key 1: Paint matter mode");
-                ui.label("Key 2: Place object mode");
-                ui.label("Key 3: Paint object mode");
-                ui.label("Key 4: Drag object mode");
-                ui.label("Key F: Toggle Fullscreen");
-                ui.label("Key Space: Pause Simulation");
-                ui.label("Key Enter: Step Simulation");
-                ui.separator();
-                ui.label("Mouse:");
-                ui.separator();
-                ui.label("Mouse Left: Paint / Place / Drag object");
-                ui.label("Mouse Right: Remove object (in place / paint object mode)");
-                ui.label("Mouse Middle: Move camera)");
-                ui.label("Mouse Scroll: Zoom)");
-                ui.separator();
-                ui.label("Matters");
-                ui.separator();
-                ui.label(
-                    "Use Edit matter window to update matters. Saving will save them to \
-                     assets/matter_definitions.json which is read by default",
-                );
+                for (index, zone) in simulation.trigger_zones.zones.iter_mut().enumerate() {
+                    let pos_before = zone.pos;
+                    let half_extents_before = zone.half_extents;
+                    ui.collapsing(format!("{}: {}", index, zone.name), |ui| {
+                        ui.text_edit_singleline(&mut zone.name);
+                        ui.horizontal(|ui| {
+                            ui.label("Pos");
+                            ui.add(egui::DragValue::new(&mut zone.pos.x).speed(0.05));
+                            ui.add(egui::DragValue::new(&mut zone.pos.y).speed(0.05));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Half size");
+                            ui.add(
+                                egui::DragValue::new(&mut zone.half_extents.x)
+                                    .speed(0.05)
+                                    .clamp_range(0.05..=5.0),
+                            );
+                            ui.add(
+                                egui::DragValue::new(&mut zone.half_extents.y)
+                                    .speed(0.05)
+                                    .clamp_range(0.05..=5.0),
+                            );
+                        });
+                        egui::ComboBox::from_label(format!("{}: Emitter matter", index))
+                            .selected_text(&matter_names[zone.emitter_matter as usize])
+                            .show_ui(ui, |ui| {
+                                for (id, name) in matter_names.iter().enumerate() {
+                                    ui.selectable_value(
+                                        &mut zone.emitter_matter,
+                                        id as u32,
+                                        name,
+                                    );
+                                }
+                            });
+                        ui.checkbox(&mut zone.is_emitting, "Emitting");
+                        ui.label("On enter");
+                        add_trigger_actions(
+                            ui,
+                            &format!("{} enter", index),
+                            &mut zone.on_enter,
+                            &matter_names,
+                        );
+                        ui.label("On exit");
+                        add_trigger_actions(
+                            ui,
+                            &format!("{} exit", index),
+                            &mut zone.on_exit,
+                            &matter_names,
+                        );
+                        ui.separator();
+                        ui.button("Remove zone").clicked().then(|| {
+                            remove = Some(index);
+                        });
+                    });
+                    if pos_before != zone.pos || half_extents_before != zone.half_extents {
+                        respawn.push(index);
+                    }
+                }
                 ui.separator();
-                ui.label("Launch app with LARGE=1 to test 1024 sized grid (experimental & slow)");
+                ui.button("Add zone at camera").clicked().then(|| {
+                    let name = format!("Zone {}", simulation.trigger_zones.zones.len());
+                    let zone = TriggerZone::new(&name, api.main_camera.pos(), MATTER_EMPTY);
+                    simulation
+                        .trigger_zones
+                        .add_zone(&mut api.ecs_world, &mut api.physics_world, zone)
+                        .unwrap();
+                });
             });
+        for index in respawn {
+            simulation
+                .trigger_zones
+                .respawn_zone(&mut api.ecs_world, &mut api.physics_world, index)
+                .unwrap();
+        }
+        if let Some(index) = remove {
+            simulation
+                .trigger_zones
+                .remove_zone(&mut api.ecs_world, &mut api.physics_world, index)
+                .unwrap();
+        }
     }
 
-    pub fn add_settings_window(
+    pub fn add_portals_window(
         &mut self,
         api: &mut EngineApi<InputAction>,
         simulation: &mut Simulation,
-        settings: &mut AppSettings,
-        is_debug: &mut bool,
     ) {
         let GuiState {
-            show_settings_view,
-            ..
+            show_portals_view, ..
         } = self;
         let ctx = api.gui.context();
-        egui::Window::new("Settings")
-            .open(show_settings_view)
+        let mut remove = None;
+        egui::Window::new("Portals")
+            .open(show_portals_view)
             .default_width(250.0)
             .show(&ctx, |ui| {
-                ui.checkbox(is_debug, "Debug")
-                    .on_hover_text("Render debug information like physics colliders & grid");
+                ui.label(format!(
+                    "Matter passes only through the first {} portal pairs inside the simulated \
+                     area",
+                    MAX_PORTALS
+                ));
                 ui.separator();
-                ui.label("Performance Settings");
-                ui.group(|ui| {
-                    ui.label(&format!("Sim size: {}", *SIM_CANVAS_SIZE));
-                    ui.label("Device");
+                Grid::new("Portal pairs").show(ui, |ui| {
+                    ui.label("A (x, y)");
+                    ui.label("B (x, y)");
+                    ui.label("Width");
+                    ui.end_row();
+                    for (index, pair) in simulation.portals.pairs.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut pair.a.x));
+                            ui.add(egui::DragValue::new(&mut pair.a.y));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut pair.b.x));
+                            ui.add(egui::DragValue::new(&mut pair.b.y));
+                        });
+                        ui.add(egui::DragValue::new(&mut pair.width).clamp_range(0..=256));
+                        ui.button("❌").clicked().then(|| {
+                            remove = Some(index);
+                        });
+                        ui.end_row();
+                    }
+                });
+                ui.separator();
+                ui.button("Add pair at camera").clicked().then(|| {
+                    let camera_canvas_pos = world_pos_to_canvas_pos(api.main_camera.pos())
+                        .cast::<i32>()
+                        .unwrap();
+                    simulation.portals.pairs.push(PortalPair {
+                        a: camera_canvas_pos + Vector2::new(-48, 0),
+                        b: camera_canvas_pos + Vector2::new(32, 64),
+                        width: 16,
+                    });
+                });
+            });
+        if let Some(index) = remove {
+            simulation.portals.pairs.remove(index);
+        }
+    }
+
+    /// Text labels & arrows pinned to world positions, saved with the map
+    pub fn add_annotations_window(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        simulation: &mut Simulation,
+    ) {
+        let GuiState {
+            show_annotations_view, ..
+        } = self;
+        let ctx = api.gui.context();
+        let mut remove = None;
+        let cell = *CELL_UNIT_SIZE;
+        egui::Window::new("Annotations")
+            .open(show_annotations_view)
+            .default_width(300.0)
+            .show(&ctx, |ui| {
+                ui.label("Positions are the label's top left corner in world units");
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(400.0)
+                    .show(ui, |ui| {
+                        for (index, annotation) in
+                            simulation.annotations.annotations.iter_mut().enumerate()
+                        {
+                            ui.group(|ui| {
+                                ui.text_edit_multiline(&mut annotation.text);
+                                ui.horizontal(|ui| {
+                                    ui.label("Pos");
+                                    ui.add(egui::DragValue::new(&mut annotation.pos.x).speed(cell));
+                                    ui.add(egui::DragValue::new(&mut annotation.pos.y).speed(cell));
+                                    ui.label("Height");
+                                    ui.add(
+                                        egui::DragValue::new(&mut annotation.height)
+                                            .speed(cell)
+                                            .clamp_range(cell..=cell * 64.0),
+                                    );
+                                    ui.color_edit_button_rgba_unmultiplied(&mut annotation.color);
+                                });
+                                ui.horizontal(|ui| {
+                                    let mut has_arrow = annotation.arrow_to.is_some();
+                                    ui.checkbox(&mut has_arrow, "Arrow to");
+                                    if has_arrow != annotation.arrow_to.is_some() {
+                                        // New arrows point below the label
+                                        let below = annotation.pos
+                                            - Vector2::new(0.0, annotation.height * 4.0);
+                                        annotation.arrow_to = has_arrow.then(|| below);
+                                    }
+                                    if let Some(arrow_to) = annotation.arrow_to.as_mut() {
+                                        ui.add(egui::DragValue::new(&mut arrow_to.x).speed(cell));
+                                        ui.add(egui::DragValue::new(&mut arrow_to.y).speed(cell));
+                                    }
+                                    ui.button("❌").clicked().then(|| {
+                                        remove = Some(index);
+                                    });
+                                });
+                            });
+                        }
+                    });
+                ui.separator();
+                ui.button("Add at camera").clicked().then(|| {
+                    simulation.annotations.annotations.push(Annotation {
+                        text: "Label".to_string(),
+                        pos: api.main_camera.pos(),
+                        arrow_to: None,
+                        height: cell * 8.0,
+                        color: [1.0; 4],
+                    });
+                });
+            });
+        if let Some(index) = remove {
+            simulation.annotations.annotations.remove(index);
+        }
+    }
+
+    /// Chunks around the camera, loaded ones bright & outlined. Clicking moves the camera
+    pub fn add_minimap_window(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        simulation: &Simulation,
+    ) {
+        let GuiState {
+            show_minimap_view,
+            minimap,
+            ..
+        } = self;
+        if !*show_minimap_view {
+            return;
+        }
+        let ctx = api.gui.context();
+        let camera_canvas_pos = world_pos_to_canvas_pos(api.main_camera.pos())
+            .cast::<i32>()
+            .unwrap();
+        let image = minimap.update(
+            api,
+            &simulation.chunk_manager,
+            &simulation.matter_definitions,
+            camera_canvas_pos,
+        );
+        let chunk_manager = &simulation.chunk_manager;
+        let mut teleport = None;
+        egui::Window::new("Minimap")
+            .open(show_minimap_view)
+            .resizable(false)
+            .show(&ctx, |ui| {
+                ui.add(
+                    egui::Slider::new(&mut minimap.radius, 1..=MAX_MINIMAP_RADIUS)
+                        .text("Radius (chunks)"),
+                );
+                let (texture, size) = match image {
+                    Some(image) => image,
+                    None => return,
+                };
+                let response = ui.image(texture, size).interact(egui::Sense::click());
+                let rect = response.rect;
+                let to_screen = |uv: Vector2<f32>| {
+                    rect.min + Vec2::new(uv.x * rect.width(), uv.y * rect.height())
+                };
+                let to_uv = |pos: egui::Pos2| {
+                    Vector2::new(
+                        (pos.x - rect.min.x) / rect.width(),
+                        (pos.y - rect.min.y) / rect.height(),
+                    )
+                };
+                let painter = ui.painter();
+                for chunk_pos in chunk_manager.chunks_in_use.iter() {
+                    if let Some((top_left, bottom_right)) = minimap.chunk_uv_rect(*chunk_pos) {
+                        let chunk_rect =
+                            egui::Rect::from_two_pos(to_screen(top_left), to_screen(bottom_right));
+                        painter.rect_stroke(chunk_rect, 0.0, (1.0, egui::Color32::GRAY));
+                    }
+                }
+                // Simulated area around the camera
+                let sim_start = chunk_manager.sim_chunk_start();
+                let sim_end = sim_start + SIM_CANVAS_SIZE.cast::<i32>().unwrap() * 2;
+                let sim_rect = egui::Rect::from_two_pos(
+                    to_screen(minimap.uv_at(sim_start)),
+                    to_screen(minimap.uv_at(sim_end)),
+                );
+                painter.rect_stroke(sim_rect, 0.0, (1.0, egui::Color32::YELLOW));
+                let camera_pos = to_screen(minimap.uv_at(camera_canvas_pos));
+                painter.circle_filled(camera_pos, 3.0, egui::Color32::RED);
+                if response.clicked() {
+                    teleport = response
+                        .interact_pointer_pos()
+                        .map(|pos| minimap.canvas_pos_at(to_uv(pos)));
+                }
+                if let Some(pos) = response.hover_pos() {
+                    let chunk_pos = minimap.chunk_at(to_uv(pos));
+                    response.on_hover_text(format!(
+                        "Chunk ({}, {}), click to move camera",
+                        chunk_pos.x, chunk_pos.y
+                    ));
+                }
+            });
+        if let Some(canvas_pos) = teleport {
+            api.main_camera.set_pos(canvas_pos_to_world_pos(canvas_pos));
+        }
+    }
+
+    /// Known world chunks colored by where their matter is, with memory used by chunks on disk,
+    /// cpu & gpu. Clicking a chunk queues it to load or unload
+    pub fn add_world_map_window(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        simulation: &mut Simulation,
+        editor: &mut Editor,
+    ) {
+        let GuiState {
+            show_world_map_view,
+            map_disk_bytes,
+            ..
+        } = self;
+        if !*show_world_map_view {
+            return;
+        }
+        let map_name = &editor.saver.map_name;
+        if map_disk_bytes.as_ref().map_or(true, |(name, _)| name != map_name) {
+            // Maps that haven't been saved have no directory
+            let disk_bytes = chunk_disk_bytes(&map_path().join(map_name)).unwrap_or_default();
+            *map_disk_bytes = Some((map_name.clone(), disk_bytes));
+        }
+        let disk_bytes = &map_disk_bytes.as_ref().unwrap().1;
+        let ctx = api.gui.context();
+        let chunk_manager = &simulation.chunk_manager;
+        let states = chunk_manager
+            .chunk_load_states()
+            .into_iter()
+            .collect::<HashMap<Vector2<i32>, ChunkLoadState>>();
+        let camera_chunk = canvas_pos_to_chunk_pos(
+            world_pos_to_canvas_pos(api.main_camera.pos())
+                .cast::<i32>()
+                .unwrap(),
+        );
+        let (min, max) = states
+            .keys()
+            .chain(disk_bytes.keys())
+            .fold((camera_chunk, camera_chunk), |(min, max), pos| {
+                (
+                    Vector2::new(min.x.min(pos.x), min.y.min(pos.y)),
+                    Vector2::new(max.x.max(pos.x), max.y.max(pos.y)),
+                )
+            });
+        let span = (max.x - min.x).max(max.y - min.y) + 1;
+        let cell_size = (WORLD_MAP_SIZE / span as f32).clamp(4.0, 24.0);
+        let (gpu_used, gpu_allocated) = chunk_manager.gpu_memory_bytes();
+        let cpu_bytes = chunk_manager.cpu_memory_bytes();
+        let mut clicked = None;
+        let mut rescan = false;
+        egui::Window::new("World Map")
+            .open(show_world_map_view)
+            .resizable(false)
+            .show(&ctx, |ui| {
+                ui.label(format!(
+                    "Chunks known {}, on disk {}",
+                    states.len(),
+                    disk_bytes.len()
+                ));
+                ui.label(format!(
+                    "Disk {}, cpu {}, gpu {} / {}",
+                    format_mib(disk_bytes.values().sum::<u64>() as usize),
+                    format_mib(cpu_bytes),
+                    format_mib(gpu_used),
+                    format_mib(gpu_allocated)
+                ));
+                ui.horizontal_wrapped(|ui| {
+                    for state in [
+                        ChunkLoadState::Simulated,
+                        ChunkLoadState::Gpu,
+                        ChunkLoadState::Loading,
+                        ChunkLoadState::Unloading,
+                        ChunkLoadState::Cpu,
+                    ] {
+                        ui.colored_label(chunk_state_color(Some(state)), state.name());
+                    }
+                    ui.colored_label(chunk_state_color(None), "Disk only");
+                });
+                let width = (max.x - min.x + 1) as f32 * cell_size;
+                let height = (max.y - min.y + 1) as f32 * cell_size;
+                let (rect, response) =
+                    ui.allocate_exact_size(Vec2::new(width, height), egui::Sense::click());
+                // Top row is the highest chunk
+                let chunk_rect = |chunk_pos: Vector2<i32>| {
+                    let top_left = rect.min
+                        + Vec2::new(
+                            (chunk_pos.x - min.x) as f32 * cell_size,
+                            (max.y - chunk_pos.y) as f32 * cell_size,
+                        );
+                    egui::Rect::from_min_size(top_left, Vec2::splat(cell_size))
+                };
+                let painter = ui.painter();
+                for y in min.y..=max.y {
+                    for x in min.x..=max.x {
+                        let chunk_pos = Vector2::new(x, y);
+                        let state = states.get(&chunk_pos).copied();
+                        if state.is_none() && !disk_bytes.contains_key(&chunk_pos) {
+                            continue;
+                        }
+                        let chunk_rect = chunk_rect(chunk_pos).shrink(0.5);
+                        painter.rect_filled(chunk_rect, 0.0, chunk_state_color(state));
+                    }
+                }
+                painter.rect_stroke(chunk_rect(camera_chunk), 0.0, (1.0, egui::Color32::RED));
+                let hovered = response.hover_pos().map(|pos| {
+                    let offset = (pos - rect.min) / cell_size;
+                    Vector2::new(
+                        min.x + offset.x.floor() as i32,
+                        max.y - offset.y.floor() as i32,
+                    )
+                });
+                if response.clicked() {
+                    clicked = hovered;
+                }
+                if let Some(chunk_pos) = hovered {
+                    let state = states.get(&chunk_pos).copied();
+                    response.on_hover_text(format!(
+                        "Chunk ({}, {}): {}, disk {}\nClick to load or unload",
+                        chunk_pos.x,
+                        chunk_pos.y,
+                        state.map_or("Not loaded", |state| state.name()),
+                        disk_bytes
+                            .get(&chunk_pos)
+                            .map_or("-".to_string(), |bytes| format_mib(*bytes as usize))
+                    ));
+                }
+                ui.label("Chunks around the camera stay loaded");
+                rescan = ui
+                    .button("Rescan disk")
+                    .on_hover_text("Disk usage is read when the map changes, rescan after saving")
+                    .clicked();
+            });
+        if rescan {
+            *map_disk_bytes = None;
+        }
+        if let Some(chunk_pos) = clicked {
+            let chunk_manager = &mut simulation.chunk_manager;
+            let result = match states.get(&chunk_pos) {
+                Some(ChunkLoadState::Cpu) => chunk_manager.queue_chunk_load(chunk_pos),
+                Some(_) => chunk_manager.queue_chunk_unload(chunk_pos),
+                None => Err(anyhow!("Chunk {:?} isn't in the world", chunk_pos)),
+            };
+            if let Err(e) = result {
+                editor.toasts.error(format!("{:#}", e));
+            }
+        }
+    }
+
+    pub fn add_camera_path_window(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        editor: &mut Editor,
+        camera_path: &mut CameraPath,
+    ) {
+        let GuiState {
+            show_camera_path_view,
+            camera_path_name,
+            keyframe_gap,
+            ..
+        } = self;
+        let ctx = api.gui.context();
+        let mut remove = None;
+        egui::Window::new("Camera Path")
+            .open(show_camera_path_view)
+            .default_width(250.0)
+            .show(&ctx, |ui| {
+                ui.label("Keyframes of camera view, played back smoothly e.g. for videos");
+                ui.separator();
+                let mut times_changed = false;
+                for (index, keyframe) in camera_path.keyframes.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        times_changed |= ui
+                            .add(
+                                egui::DragValue::new(&mut keyframe.time)
+                                    .speed(0.1)
+                                    .clamp_range(0.0..=3600.0)
+                                    .suffix(" s"),
+                            )
+                            .changed();
+                        ui.label(format!(
+                            "({:.2}, {:.2}) zoom {:.2}",
+                            keyframe.pos[0], keyframe.pos[1], keyframe.zoom
+                        ));
+                        ui.button("Go")
+                            .on_hover_text("Move camera to keyframe")
+                            .clicked()
+                            .then(|| {
+                                api.main_camera.set_pos(keyframe.pos.into());
+                                api.main_camera.reset_zoom();
+                                api.main_camera.zoom(keyframe.zoom);
+                            });
+                        ui.button("❌").clicked().then(|| {
+                            remove = Some(index);
+                        });
+                    });
+                }
+                if times_changed {
+                    camera_path.sort();
+                }
+                ui.horizontal(|ui| {
+                    let time = if camera_path.keyframes.is_empty() {
+                        0.0
+                    } else {
+                        camera_path.duration() + *keyframe_gap
+                    };
+                    ui.button("Add keyframe")
+                        .on_hover_text("Current camera view, after the last keyframe")
+                        .clicked()
+                        .then(|| camera_path.add_keyframe(&api.main_camera, time));
+                    ui.add(
+                        egui::DragValue::new(keyframe_gap)
+                            .speed(0.1)
+                            .clamp_range(0.1..=60.0)
+                            .suffix(" s later"),
+                    );
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    match camera_path.playhead() {
+                        Some(playhead) => {
+                            ui.button("Stop").clicked().then(|| camera_path.stop());
+                            ui.label(format!(
+                                "{:.1} / {:.1} s",
+                                playhead,
+                                camera_path.duration()
+                            ));
+                        }
+                        None => {
+                            ui.add_enabled(
+                                !camera_path.keyframes.is_empty(),
+                                egui::Button::new("Play"),
+                            )
+                            .on_hover_text("Hide windows with compact HUD to record the view")
+                            .clicked()
+                            .then(|| camera_path.play());
+                        }
+                    }
+                    ui.checkbox(&mut camera_path.looped, "Loop");
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(camera_path_name);
+                    ui.button("Save").clicked().then(|| {
+                        let result = camera_path.save(camera_path_name);
+                        editor.report(
+                            result,
+                            format!("Saved camera path {}", camera_path_name),
+                            "Failed to save camera path",
+                        );
+                    });
+                });
+                let names = match camera_path_names() {
+                    std::result::Result::Ok(names) => names,
+                    Err(e) => {
+                        ui.label(format!("Failed to list camera paths: {}", e));
+                        return;
+                    }
+                };
+                for name in names.iter() {
+                    ui.button(name).clicked().then(|| match CameraPath::load(name) {
+                        std::result::Result::Ok(loaded) => {
+                            *camera_path = loaded;
+                            *camera_path_name = name.clone();
+                        }
+                        Err(e) => editor.error_dialog.push_error("Failed to load camera path", &e),
+                    });
+                }
+            });
+        if let Some(index) = remove {
+            camera_path.keyframes.remove(index);
+        }
+    }
+
+    /// Spawn objects & paint cells in bulk while watching performance, e.g. to report how much
+    /// a gpu handles
+    pub fn add_stress_test_window(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        simulation: &mut Simulation,
+        editor: &mut Editor,
+        stress_test: &mut StressTest,
+        (frame_time, render_time, sim_time): (f64, f64, f64),
+    ) {
+        let GuiState {
+            show_stress_test_view,
+            ..
+        } = self;
+        let ctx = api.gui.context();
+        let matter_names = simulation
+            .matter_definitions
+            .definitions
+            .iter()
+            .map(|m| m.name.clone())
+            .collect::<Vec<String>>();
+        // Matters may have been removed since they were picked
+        stress_test.cell_matter = stress_test.cell_matter.min(matter_names.len() as u32 - 1);
+        stress_test.object_matter = stress_test.object_matter.min(matter_names.len() as u32 - 1);
+        egui::Window::new("Stress Test")
+            .open(show_stress_test_view)
+            .default_width(250.0)
+            .show(&ctx, |ui| {
+                ui.label(format!("FPS: {:.1}", api.time.avg_fps()));
+                ui.label(format!(
+                    "Frame {:.2} ms, render {:.2} ms, simulation {:.2} ms",
+                    frame_time, render_time, sim_time
+                ));
+                ui.label(format!(
+                    "Bodies: {}, stress test objects: {} / {}",
+                    api.physics_world.physics.bodies.len(),
+                    stress_test.num_objects(),
+                    stress_test.max_objects
+                ));
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Objects");
+                    ui.add(egui::DragValue::new(&mut stress_test.objects_per_spawn).clamp_range(
+                        1..=1000,
+                    ));
+                    add_matter_combo(
+                        ui,
+                        "Stress test object matter",
+                        &mut stress_test.object_matter,
+                        &matter_names,
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Population cap");
+                    ui.add(egui::DragValue::new(&mut stress_test.max_objects).clamp_range(
+                        1..=100_000,
+                    ));
+                });
+                ui.horizontal(|ui| {
+                    ui.button("Spawn")
+                        .on_hover_text("Random objects at random positions of simulated area")
+                        .clicked()
+                        .then(|| {
+                            let images = &editor.placer.obj_image_assets;
+                            let result = stress_test.spawn_objects(api, simulation, images);
+                            editor.toasts.report_error(result);
+                        });
+                    ui.button("Remove all")
+                        .clicked()
+                        .then(|| stress_test.remove_objects(api));
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Cells per frame");
+                    ui.add(egui::DragValue::new(&mut stress_test.cells_per_frame).clamp_range(
+                        1..=1_000_000,
+                    ));
+                    add_matter_combo(
+                        ui,
+                        "Stress test cell matter",
+                        &mut stress_test.cell_matter,
+                        &matter_names,
+                    );
+                });
+                ui.checkbox(&mut stress_test.is_painting, "Paint random cells every frame");
+            });
+    }
+
+    pub fn add_breakpoints_window(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        simulation: &Simulation,
+        breakpoints: &mut Breakpoints,
+    ) {
+        let GuiState {
+            show_breakpoints_view,
+            ..
+        } = self;
+        let ctx = api.gui.context();
+        let mut remove = None;
+        egui::Window::new("Breakpoints")
+            .open(show_breakpoints_view)
+            .default_width(250.0)
+            .show(&ctx, |ui| {
+                ui.label("Simulation pauses when any of these is hit after a step");
+                ui.separator();
+                let matter_defs = &simulation.matter_definitions.definitions;
+                for (index, breakpoint) in breakpoints.breakpoints.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        match breakpoint {
+                            Breakpoint::MatterCount {
+                                matter,
+                                count,
+                            } => {
+                                let name = matter_defs
+                                    .get(*matter as usize)
+                                    .map_or("Unknown", |m| m.name.as_str());
+                                egui::ComboBox::from_id_source(("Breakpoint matter", index))
+                                    .selected_text(name)
+                                    .show_ui(ui, |ui| {
+                                        for definition in matter_defs.iter() {
+                                            ui.selectable_value(
+                                                matter,
+                                                definition.id,
+                                                &definition.name,
+                                            );
+                                        }
+                                    });
+                                ui.label("cells exceed");
+                                ui.add(egui::DragValue::new(count).speed(100.0));
+                            }
+                            Breakpoint::Velocity {
+                                speed,
+                            } => {
+                                ui.label("Body speed exceeds");
+                                ui.add(
+                                    egui::DragValue::new(speed)
+                                        .speed(0.1)
+                                        .clamp_range(0.0..=1000.0),
+                                );
+                            }
+                            Breakpoint::Deformation {
+                                min,
+                                max,
+                            } => {
+                                ui.label("Deformation in");
+                                ui.add(egui::DragValue::new(&mut min.x));
+                                ui.add(egui::DragValue::new(&mut min.y));
+                                ui.label("to");
+                                ui.add(egui::DragValue::new(&mut max.x));
+                                ui.add(egui::DragValue::new(&mut max.y));
+                            }
+                        }
+                        ui.button("❌").clicked().then(|| {
+                            remove = Some(index);
+                        });
+                    });
+                }
+                ui.separator();
+                ui.horizontal_wrapped(|ui| {
+                    ui.button("Add matter count").clicked().then(|| {
+                        breakpoints.breakpoints.push(Breakpoint::MatterCount {
+                            matter: simulation.matter_definitions.empty,
+                            count: 100_000,
+                        });
+                    });
+                    ui.button("Add velocity").clicked().then(|| {
+                        breakpoints.breakpoints.push(Breakpoint::Velocity {
+                            speed: 20.0,
+                        });
+                    });
+                    ui.button("Add deformation at camera")
+                        .on_hover_text("Watched area is outlined in red while this window is open")
+                        .clicked()
+                        .then(|| {
+                            let camera_canvas_pos = world_pos_to_canvas_pos(api.main_camera.pos())
+                                .cast::<i32>()
+                                .unwrap();
+                            breakpoints.breakpoints.push(Breakpoint::Deformation {
+                                min: camera_canvas_pos - Vector2::new(32, 32),
+                                max: camera_canvas_pos + Vector2::new(32, 32),
+                            });
+                        });
+                });
+            });
+        if let Some(index) = remove {
+            breakpoints.breakpoints.remove(index);
+        }
+    }
+
+    pub fn add_watch_window(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        simulation: &Simulation,
+        watches: &mut Watches,
+    ) {
+        let GuiState {
+            show_watch_view, ..
+        } = self;
+        let ctx = api.gui.context();
+        let matter_data = &simulation.matter_definitions.definitions;
+        let mut remove = None;
+        egui::Window::new("Watch")
+            .open(show_watch_view)
+            .default_width(250.0)
+            .show(&ctx, |ui| {
+                ui.label("Key P pins object or cell at mouse");
+                ui.separator();
+                for (index, (watch, value)) in
+                    watches.watches.iter().zip(watches.values.iter()).enumerate()
+                {
+                    ui.horizontal(|ui| {
+                        ui.button("❌").clicked().then(|| {
+                            remove = Some(index);
+                        });
+                        match (watch, value) {
+                            (Watch::Cell(pos), WatchValue::Cell(matter)) => {
+                                let matter = matter.and_then(|m| matter_data.get(m as usize));
+                                if let Some(matter) = matter {
+                                    let [r, g, b, a] =
+                                        Color32::from_u32_rgba(matter.color).to_rgba();
+                                    let color = egui::Color32::from_rgba_unmultiplied(r, g, b, a);
+                                    ui.colored_label(color, "⏹");
+                                }
+                                ui.label(format!(
+                                    "Cell ({}, {}): {}",
+                                    pos.x,
+                                    pos.y,
+                                    matter.map_or("Outside simulation", |m| m.name.as_str())
+                                ));
+                            }
+                            (
+                                Watch::Entity(entity),
+                                WatchValue::Entity {
+                                    pos,
+                                    vel,
+                                    angle,
+                                    ang_vel,
+                                },
+                            ) => {
+                                ui.label(format!(
+                                    "Entity {:?}\n Pos: ({:.3}, {:.3})\n Vel: ({:.3}, {:.3})\n \
+                                     Angle: {:.3} rad\n Ang vel: {:.3} rad/s",
+                                    entity, pos.x, pos.y, vel.x, vel.y, angle, ang_vel
+                                ));
+                            }
+                            (watch, _) => {
+                                ui.label(format!("{:?}: Removed", watch));
+                            }
+                        }
+                    });
+                }
+            });
+        if let Some(index) = remove {
+            watches.remove(index);
+        }
+    }
+
+    /// Table of cells created & destroyed per matter and of reactions, most frequent first, for
+    /// balancing custom matters
+    pub fn add_reactions_window(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        simulation: &Simulation,
+        editor: &mut Editor,
+        reaction_stats: &mut ReactionStats,
+    ) {
+        let GuiState {
+            show_reactions_view,
+            ..
+        } = self;
+        let ctx = api.gui.context();
+        let matter_definitions = &simulation.matter_definitions;
+        let name = |matter: u32| matter_definitions.definitions[matter as usize].name.as_str();
+        // Rates are shown per 100 steps
+        let steps = reaction_stats.steps;
+        let rate = |count: u64| count as f64 * 100.0 / steps.max(1) as f64;
+        egui::Window::new("Reactions")
+            .open(show_reactions_view)
+            .vscroll(true)
+            .default_width(300.0)
+            .default_height(500.0)
+            .show(&ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Counted over {} steps", steps));
+                    ui.button("Reset").clicked().then(|| {
+                        let result = reaction_stats.reset(simulation);
+                        editor.report(result, "Reset reaction counts", "Failed to reset counts");
+                    });
+                });
+                ui.label("Cells per 100 steps");
+                ui.separator();
+                ui.collapsing("Matters", |ui| {
+                    Grid::new("Matter balances").striped(true).show(ui, |ui| {
+                        ui.label("Matter");
+                        ui.label("Created");
+                        ui.label("Destroyed");
+                        ui.label("Net");
+                        ui.end_row();
+                        for balance in reaction_stats.balances(matter_definitions) {
+                            ui.label(name(balance.matter));
+                            ui.label(format!("{:.1}", rate(balance.created)));
+                            ui.label(format!("{:.1}", rate(balance.destroyed)));
+                            ui.label(format!(
+                                "{:+.1}",
+                                rate(balance.created) - rate(balance.destroyed)
+                            ));
+                            ui.end_row();
+                        }
+                    });
+                });
+                ui.collapsing("Reactions", |ui| {
+                    let reactions = reaction_stats.reactions(matter_definitions);
+                    let total = reactions.iter().map(|r| r.count).sum::<u64>().max(1);
+                    Grid::new("Reaction counts").striped(true).show(ui, |ui| {
+                        ui.label("Reaction");
+                        ui.label("Rate");
+                        ui.label("Share");
+                        ui.end_row();
+                        for reaction in reactions.iter() {
+                            let kind = match reaction.kind {
+                                ReactionKind::Reaction(index) => format!("reaction {}", index),
+                                ReactionKind::Decay => "decay".to_string(),
+                                ReactionKind::Emission => "emission".to_string(),
+                            };
+                            ui.label(format!(
+                                "{} → {} ({})",
+                                name(reaction.matter),
+                                name(reaction.becomes),
+                                kind
+                            ));
+                            ui.label(format!("{:.1}", rate(reaction.count)));
+                            ui.label(format!(
+                                "{:.1}%",
+                                reaction.count as f64 * 100.0 / total as f64
+                            ));
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+    }
+
+    /// Mods with enable toggles, the content they added & name conflicts
+    pub fn add_mods_window(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        editor: &mut Editor,
+        mods: &mut Mods,
+    ) {
+        let GuiState {
+            show_mods_view,
+            ..
+        } = self;
+        let ctx = api.gui.context();
+        egui::Window::new("Mods")
+            .open(show_mods_view)
+            .vscroll(true)
+            .default_width(300.0)
+            .show(&ctx, |ui| {
+                ui.label(
+                    "Mods are directories in assets/mods with any of matter_definitions.json, \
+                     object_images & stamps",
+                );
+                ui.separator();
+                if mods.mods.is_empty() {
+                    ui.label("No mods found");
+                }
+                let mut toggled = None;
+                for (index, m) in mods.mods.iter().enumerate() {
+                    let mut enabled = m.enabled;
+                    ui.horizontal(|ui| {
+                        if ui.checkbox(&mut enabled, m.name.as_str()).changed() {
+                            toggled = Some((index, enabled));
+                        }
+                        if m.has_scripts {
+                            ui.label("(scripts aren't supported yet)");
+                        }
+                    });
+                    ui.indent(&m.name, |ui| {
+                        for (kind, names) in [
+                            ("Matters", &m.matters),
+                            ("Object images", &m.object_images),
+                            ("Stamps", &m.stamps),
+                        ] {
+                            if !names.is_empty() {
+                                ui.label(format!("{}: {}", kind, names.join(", ")));
+                            }
+                        }
+                    });
+                }
+                if let Some((index, enabled)) = toggled {
+                    let result = mods.set_enabled(index, enabled);
+                    let name = &mods.mods[index].name;
+                    editor.report(
+                        result,
+                        format!("{} mod {}", if enabled { "Enabled" } else { "Disabled" }, name),
+                        "Failed to save enabled mods",
+                    );
+                }
+                if mods.changed {
+                    ui.label("Restart to apply changes");
+                }
+                if !mods.conflicts.is_empty() {
+                    ui.separator();
+                    ui.label("Conflicts (base content, then first mod by name wins):");
+                    for conflict in mods.conflicts.iter() {
+                        ui.label(conflict);
+                    }
+                }
+            });
+    }
+
+    pub fn add_guide_view(&mut self, api: &mut EngineApi<InputAction>) {
+        let GuiState {
+            show_guide_view, ..
+        } = self;
+        let ctx = api.gui.context();
+        egui::Window::new("Guide")
+            .open(show_guide_view)
+            .default_width(200.0)
+            .show(&ctx, |ui| {
+                ui.label("Keys (defaults, rebind them in Settings):");
+                ui.separator();
+                ui.label("Key 1: Paint matter mode");
+                ui.label("Key 2: Place object mode");
+                ui.label("Key 3: Paint object mode");
+                ui.label("Key 4: Drag object mode");
+                ui.label("Keys 5-0: Select quick bar slot");
+                ui.label("Key F: Toggle Fullscreen");
+                ui.label("Key Tab: Toggle compact HUD (hides windows)");
+                ui.label("Key B: Spawn bugs at mouse (they walk, fall & eat wood)");
+                ui.label("Key Space: Pause Simulation");
+                ui.label("Key Enter: Step Simulation (step count in Info window)");
+                ui.label("Keys Arrows / WASD: Move camera");
+                ui.label("Key Home: Recenter camera");
+                ui.label("Key R: Rotate camera by 90 degrees");
+                ui.label("Key P: Pin object or cell at mouse to watch window");
+                ui.separator();
+                ui.label("Mouse:");
+                ui.separator();
+                ui.label("Mouse Left: Paint / Place / Drag object");
+                ui.label("Mouse Right: Remove object (in place / paint object mode)");
+                ui.label("Mouse Right drag: Copy matter for a new stamp (in stamp mode)");
+                ui.label("Mouse Right on palette / quick bar: Pin to quick bar");
+                ui.label("Mouse Middle: Move camera)");
+                ui.label("Mouse Scroll: Zoom)");
+                ui.separator();
+                ui.label("Matters");
+                ui.separator();
+                ui.label(
+                    "Use Edit matter window to update matters. Saving will save them to \
+                     matter_definitions.json of config directory which is read by default",
+                );
+                ui.separator();
+                ui.label(
+                    "Default settings are picked by a benchmark on first run. Delete \
+                     benchmark_*.json of config directory to run it again",
+                );
+                ui.label("Launch app with LARGE=1 to test 1024 sized grid (experimental & slow)");
+                ui.label(
+                    "Region of interest in settings keeps large grids interactive by simulating \
+                     only around camera, cursor & moving objects",
+                );
+                ui.label(
+                    "Set SIM_WIDTH & SIM_HEIGHT (e.g. 1024 & 512) for a non square grid. Each \
+                     grid size has its own maps",
+                );
+            });
+    }
+
+    pub fn add_settings_window(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        simulation: &mut Simulation,
+        editor: &mut Editor,
+        settings: &mut AppSettings,
+        settings_profiles: &mut SettingsProfiles,
+        is_debug: &mut bool,
+    ) {
+        let GuiState {
+            show_settings_view,
+            ..
+        } = self;
+        let ctx = api.gui.context();
+        egui::Window::new("Settings")
+            .open(show_settings_view)
+            .default_width(250.0)
+            .show(&ctx, |ui| {
+                ui.checkbox(is_debug, "Debug")
+                    .on_hover_text("Render debug information like physics colliders & grid");
+                ui.checkbox(&mut settings.pause_while_gui_focused, "Pause while using gui")
+                    .on_hover_text(
+                        "Simulation doesn't run while mouse is over a window or a text field is \
+                         focused. Steps still run",
+                    );
+                egui::ComboBox::from_label("Hotkeys while typing")
+                    .selected_text(
+                        settings
+                            .hotkey_modifier
+                            .map_or("Off", |modifier| modifier.name()),
+                    )
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut settings.hotkey_modifier, None, "Off");
+                        for modifier in HotkeyModifier::ALL {
+                            ui.selectable_value(
+                                &mut settings.hotkey_modifier,
+                                Some(modifier),
+                                modifier.name(),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Hotkeys don't reach tools while typing in gui, unless this modifier is \
+                         held, e.g. Alt + 1 for paint mode",
+                    );
+                ui.separator();
+                ui.label("Profiles");
+                ui.group(|ui| {
+                    add_settings_profiles(ui, api, editor, settings, settings_profiles);
+                });
+                ui.separator();
+                ui.label("Display");
+                ui.group(|ui| {
+                    add_fullscreen_options(ui, api);
+                    ui.separator();
+                    ui.add(
+                        egui::Slider::new(&mut settings.render_scale, 0.25..=1.0)
+                            .text("Render scale"),
+                    )
+                    .on_hover_text(
+                        "Resolution canvas is rendered at relative to window. Lower is lighter \
+                         for the gpu, simulation size stays the same",
+                    );
+                    ui.horizontal(|ui| {
+                        ui.label("Upscale");
+                        ui.selectable_value(
+                            &mut settings.upscale_filter,
+                            Filter::Nearest,
+                            "Nearest",
+                        )
+                        .on_hover_text("Sharp pixels");
+                        ui.selectable_value(&mut settings.upscale_filter, Filter::Linear, "Linear")
+                            .on_hover_text("Smooth pixels");
+                    });
+                    ui.add(
+                        egui::Slider::new(&mut settings.smooth_canvas_below, 0.0..=4.0)
+                            .text("Smooth below cell px"),
+                    )
+                    .on_hover_text(
+                        "Canvas is smoothed when zoomed out so that cells are smaller than this \
+                         many pixels, reducing shimmer. 0 keeps canvas sharp at all zooms",
+                    );
+                    ui.checkbox(&mut settings.detail_upscale, "2x detail")
+                        .on_hover_text(
+                            "Draw canvas at double resolution with dithered detail per matter, \
+                             for less blocky screenshots. Simulation stays at base resolution",
+                        );
+                    ui.checkbox(&mut settings.particles, "Particles")
+                        .on_hover_text(
+                            "Draw sparks, embers & splashes where energy reacts & liquid boils. \
+                             Visual only",
+                        );
+                });
+                ui.separator();
+                ui.label("Performance Settings");
+                ui.group(|ui| {
+                    ui.label(&format!("Sim size: {}x{}", SIM_CANVAS_SIZE.x, SIM_CANVAS_SIZE.y));
+                    ui.label("Device");
                     ui.label(&format!("Name: {:?}", api.renderer.device_name()));
                     ui.label(&format!("Type: {:?}", api.renderer.device_type()));
                     ui.label(&format!("Mem: {:.2} gb", api.renderer.max_mem_gb()));
@@ -482,11 +2063,102 @@ impl GuiState {
                             "How many movement steps is taken for falling, rising & sliding \
                              cellular automata",
                         );
+                    ui.label("Liquids");
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(
+                            &mut settings.liquid_model,
+                            LiquidModel::Dispersion,
+                            "Dispersion",
+                        )
+                        .on_hover_text("Liquids spread sideways by dispersion steps");
+                        ui.selectable_value(
+                            &mut settings.liquid_model,
+                            LiquidModel::Pressure,
+                            "Pressure",
+                        )
+                        .on_hover_text(
+                            "Liquids also get pressure, which pushes them up through connected \
+                             tunnels towards their level. Slower",
+                        );
+                    });
+                    ui.separator();
+                    ui.checkbox(&mut settings.region_of_interest, "Region of interest")
+                        .on_hover_text(
+                            "Simulate only around camera, cursor & moving objects, and where \
+                             matter keeps changing. Far regions stay frozen until you get near",
+                        );
+                    if settings.region_of_interest {
+                        ui.add(
+                            egui::Slider::new(&mut settings.region_of_interest_radius, 32..=512)
+                                .text("Radius"),
+                        )
+                        .on_hover_text("Cells simulated around camera & cursor");
+                    }
+                    ui.separator();
+                    ui.label("Object budgets");
+                    ui.add(egui::Slider::new(&mut settings.max_dynamic_objects, 10..=1000))
+                        .on_hover_text("Max dynamic objects before debris is removed");
+                    ui.add(egui::Slider::new(&mut settings.max_object_colliders, 100..=10000))
+                        .on_hover_text("Max colliders of dynamic objects before debris is removed");
+                    ui.selectable_value(
+                        &mut settings.debris_policy,
+                        DebrisPolicy::MergeIntoGrid,
+                        "Merge debris into grid",
+                    )
+                    .on_hover_text("Turn oldest resting debris back into matter");
+                    ui.selectable_value(
+                        &mut settings.debris_policy,
+                        DebrisPolicy::DespawnOldest,
+                        "Despawn oldest debris",
+                    )
+                    .on_hover_text("Remove oldest debris wherever it is");
+                    ui.checkbox(&mut settings.freeze_resting_objects, "Freeze resting objects")
+                        .on_hover_text(
+                            "Turn dynamic objects that have slept on solid terrain for a while \
+                             into matter",
+                        );
+                    if settings.freeze_resting_objects {
+                        ui.add(
+                            egui::Slider::new(&mut settings.freeze_after_secs, 1.0..=120.0)
+                                .text("Asleep for (s)"),
+                        );
+                    }
                     ui.separator();
                     ui.checkbox(&mut settings.print_performance, "Print performance")
                         .on_hover_text("Whether performance is printed in terminal");
+                    ui.checkbox(&mut settings.background_boundaries, "Background boundaries")
+                        .on_hover_text(
+                            "Form colliders of solid, powder & liquid areas on a background \
+                             thread. Smoother frames, but boundaries lag one frame behind",
+                        );
+                    ui.label("Boundaries collide with layers");
+                    add_layer_mask_checkboxes(ui, &mut settings.boundary_collides_with);
+                    ui.checkbox(&mut settings.log_stats, "Log stats to CSV").on_hover_text(
+                        "Write matter counts, entity counts & timers of each simulation step to \
+                         a csv file in stats directory",
+                    );
                 });
                 ui.separator();
+                ui.label("Seed of new maps");
+                ui.horizontal(|ui| {
+                    let is_fixed = matches!(settings.simulation_seed, SimulationSeed::Fixed(_));
+                    ui.selectable_label(!is_fixed, "Random")
+                        .clicked()
+                        .then(|| settings.simulation_seed = SimulationSeed::Random);
+                    if ui
+                        .selectable_label(is_fixed, "Fixed")
+                        .on_hover_text("Same seed for every new map, so runs can be reproduced")
+                        .clicked()
+                        && !is_fixed
+                    {
+                        settings.simulation_seed = SimulationSeed::Fixed(0);
+                    }
+                    if let SimulationSeed::Fixed(seed) = &mut settings.simulation_seed {
+                        ui.add(egui::DragValue::new(seed));
+                    }
+                });
+                ui.label(format!("Current seed: {}", simulation.world_seed()));
+                ui.separator();
                 let is_chunked = settings.chunked_simulation;
                 ui.checkbox(&mut settings.chunked_simulation, "Chunked Sim Movement")
                     .on_hover_text(
@@ -498,82 +2170,426 @@ impl GuiState {
                 if is_chunked != settings.chunked_simulation && !settings.chunked_simulation {
                     simulation.camera_pos = Vector2::new(0.0, 0.0);
                 }
+                if settings.chunked_simulation {
+                    ui.label("Chunk streaming");
+                    ui.selectable_value(
+                        &mut settings.chunk_streaming,
+                        ChunkStreaming::Radial,
+                        "Radial",
+                    )
+                    .on_hover_text("Keep 3 x 3 chunks around camera loaded");
+                    ui.selectable_value(
+                        &mut settings.chunk_streaming,
+                        ChunkStreaming::VerticalSlice,
+                        "Vertical slice",
+                    )
+                    .on_hover_text(
+                        "Keep a 3 x 5 chunk tall slice around camera loaded & prefetch chunks \
+                         below when falling fast. Smoother for deep digging",
+                    );
+                }
+                ui.separator();
+                ui.checkbox(&mut settings.check_for_updates, "Check for updates")
+                    .on_hover_text(
+                        "Check once whether a newer release exists. Only a static version file \
+                         is downloaded from the releases page",
+                    );
+                ui.separator();
+                add_data_dirs(ui);
             });
     }
 
     pub fn add_editor_window(
         &mut self,
         api: &mut EngineApi<InputAction>,
-        simulation: &Simulation,
+        simulation: &mut Simulation,
         editor: &mut Editor,
     ) {
         let GuiState {
-            show_edit_view, ..
+            show_edit_view,
+            pop_outs,
+            ..
         } = self;
+        if !*show_edit_view && pop_outs.is_popped(Panel::Editor) {
+            pop_outs.dock(api, Panel::Editor);
+        }
         let ctx = api.gui.context();
-        egui::Window::new("Editor")
-            .open(show_edit_view)
-            .vscroll(true)
-            .default_width(200.0)
-            .default_height(800.0)
-            .show(&ctx, |ui| {
-                ui.label(format!("Mode {:?}", editor.mode));
-                ui.selectable_value(&mut editor.mode, EditorMode::Paint, "Paint Matter (1)")
-                    .on_hover_text("Paint matter with mouse");
-                ui.selectable_value(&mut editor.mode, EditorMode::Place, "Place Object (2)")
-                    .on_hover_text("Place objects at mouse position");
-                ui.selectable_value(
-                    &mut editor.mode,
-                    EditorMode::ObjectPaint,
-                    "Paint Object (3)",
-                )
-                .on_hover_text("Paint custom objects at mouse position");
-                ui.selectable_value(&mut editor.mode, EditorMode::Drag, "Drag Object (4)")
-                    .on_hover_text("Drag existing objects at mouse position");
-                if editor.mode == EditorMode::Paint {
-                    ui.label("Brush Radius");
-                    ui.add(egui::Slider::new(&mut editor.painter.radius, 0.5..=30.0));
-                    ui.checkbox(&mut editor.painter.is_square, "Square brush");
-                    ui.separator();
-                    ui.label(format!(
-                        "Matter ({})",
-                        &simulation.matter_definitions.definitions[editor.painter.matter as usize]
-                            .name
-                    ));
-                    ui.separator();
-                    add_matter_palette(ui, simulation, editor);
-                } else if editor.mode == EditorMode::Place {
-                    ui.separator();
-                    if let Some(object) = &editor.placer.place_object {
-                        ui.label(format!("Object ({})", object));
-                        add_object_palette(ui, editor);
-                    } else {
-                        ui.label("Object (None)");
-                        ui.label("Add .png images to assets/object_images");
-                    }
-                    ui.separator();
-                    ui.label(format!(
-                        "Object Matter ({})",
-                        &simulation.matter_definitions.definitions
-                            [editor.placer.object_matter as usize]
-                            .name
-                    ));
-                    ui.separator();
-                    add_object_matter_palette(ui, editor, &simulation.matter_definitions);
-                } else if editor.mode == EditorMode::ObjectPaint {
-                    ui.label("Brush Radius");
-                    ui.add(egui::Slider::new(&mut editor.painter.radius, 0.5..=10.0));
-                    ui.checkbox(&mut editor.painter.is_square, "Is square");
-                    ui.label(format!(
-                        "Object Matter ({})",
-                        &simulation.matter_definitions.definitions
-                            [editor.placer.object_matter as usize]
-                            .name
-                    ));
-                    add_object_matter_palette(ui, editor, &simulation.matter_definitions);
+        let is_popped = pop_outs.is_popped(Panel::Editor);
+        let window_gui = pop_outs.window_gui(api, Panel::Editor);
+        let textures = window_gui
+            .as_ref()
+            .map_or(PanelTextures::MAIN, |(_, textures)| *textures);
+        let mut toggle_pop_out = false;
+        let contents = |ui: &mut Ui| {
+            add_pop_out_button(ui, is_popped, &mut toggle_pop_out);
+            add_undo_buttons(ui, editor, api, simulation);
+            add_edit_history(ui, editor, api, simulation, textures);
+            ui.label(format!("Mode {:?}", editor.mode));
+            ui.selectable_value(&mut editor.mode, EditorMode::Paint, "Paint Matter (1)")
+                .on_hover_text("Paint matter with mouse");
+            ui.selectable_value(&mut editor.mode, EditorMode::Place, "Place Object (2)")
+                .on_hover_text("Place objects at mouse position");
+            ui.selectable_value(
+                &mut editor.mode,
+                EditorMode::ObjectPaint,
+                "Paint Object (3)",
+            )
+            .on_hover_text("Paint custom objects at mouse position");
+            ui.selectable_value(&mut editor.mode, EditorMode::Drag, "Drag Object (4)")
+                .on_hover_text("Drag existing objects at mouse position");
+            ui.selectable_value(&mut editor.mode, EditorMode::Stamp, "Stamp")
+                .on_hover_text("Place saved stamps of matter at mouse position");
+            ui.selectable_value(&mut editor.mode, EditorMode::Select, "Select")
+                .on_hover_text("Copy matter regions & paste them elsewhere");
+            ui.selectable_value(&mut editor.mode, EditorMode::Beam, "Beam")
+                .on_hover_text("Fire a heating beam that pushes objects it hits");
+            ui.selectable_value(&mut editor.mode, EditorMode::Blueprint, "Blueprint")
+                .on_hover_text("Sketch plans over the canvas without simulating them");
+            ui.selectable_value(&mut editor.mode, EditorMode::Joint, "Joint")
+                .on_hover_text("Connect objects with hinges & ropes");
+            ui.selectable_value(&mut editor.mode, EditorMode::Fill, "Fill")
+                .on_hover_text("Fill rectangles, polygons & connected regions with matter");
+            if editor.mode == EditorMode::Paint {
+                ui.label("Brush Radius");
+                ui.add(egui::Slider::new(&mut editor.painter.radius, 0.5..=30.0));
+                add_brush_options(ui, &mut editor.painter, &mut editor.brush_images);
+                add_brush_fill_options(ui, &mut editor.painter.fill);
+                ui.checkbox(&mut editor.draw_state.smoothing, "Smooth strokes")
+                    .on_hover_text("Curve strokes through mouse samples instead of straight lines");
+                ui.separator();
+                ui.label(format!(
+                    "Matter ({})",
+                    &simulation.matter_definitions.definitions[editor.painter.matter as usize]
+                        .name
+                ));
+                ui.separator();
+                ui.label("Right click to pin to quick bar");
+                add_matter_palette(ui, simulation, editor, textures);
+            } else if editor.mode == EditorMode::Place {
+                ui.separator();
+                if let Some(object) = &editor.placer.place_object {
+                    ui.label(format!("Object ({})", object));
+                    add_object_palette(ui, editor, textures);
+                } else {
+                    ui.label("Object (None)");
+                    ui.label("Add .png images to assets/object_images");
+                }
+                ui.separator();
+                ui.label(format!(
+                    "Object Matter ({})",
+                    &simulation.matter_definitions.definitions
+                        [editor.placer.object_matter as usize]
+                        .name
+                ));
+                ui.separator();
+                add_object_matter_palette(
+                    ui,
+                    editor,
+                    &simulation.matter_definitions,
+                    textures,
+                );
+                ui.separator();
+                ui.checkbox(&mut editor.placer.place_fixed, "Static object")
+                    .on_hover_text("Placed objects stay in place, but still deform");
+                ui.separator();
+                add_object_collision_options(ui, &mut editor.placer.collision);
+            } else if editor.mode == EditorMode::ObjectPaint {
+                ui.label("Brush Radius");
+                ui.add(egui::Slider::new(&mut editor.painter.radius, 0.5..=10.0));
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut editor.painter.shape, BrushShape::Round, "Round");
+                    ui.selectable_value(&mut editor.painter.shape, BrushShape::Square, "Square");
+                });
+                ui.checkbox(&mut editor.placer.place_fixed, "Static object")
+                    .on_hover_text("Painted objects stay in place, but still deform");
+                add_object_collision_options(ui, &mut editor.placer.collision);
+                ui.label(format!(
+                    "Object Matter ({})",
+                    &simulation.matter_definitions.definitions
+                        [editor.placer.object_matter as usize]
+                        .name
+                ));
+                add_object_matter_palette(
+                    ui,
+                    editor,
+                    &simulation.matter_definitions,
+                    textures,
+                );
+            } else if editor.mode == EditorMode::Stamp {
+                ui.separator();
+                if let Some(stamp) = &editor.stamper.stamp {
+                    ui.label(format!("Stamp ({})", stamp));
+                    add_stamp_palette(ui, editor, textures);
                 } else {
-                    ui.label("Move object by dragging");
+                    ui.label("Stamp (None)");
+                }
+                ui.separator();
+                ui.label("Right drag to copy matter for a new stamp");
+                if let Some(copied) = &editor.stamper.copied {
+                    ui.label(format!("Copied {}x{}", copied.width, copied.height));
+                    ui.text_edit_singleline(&mut editor.stamper.stamp_name);
+                    ui.button("Save stamp").clicked().then(|| {
+                        let result = editor.stamper.save_copied(api);
+                        editor.report(result, "Saved stamp", "Failed to save stamp");
+                    });
+                }
+            } else if editor.mode == EditorMode::Select {
+                ui.separator();
+                ui.label("Left drag to copy matter, right click to paste");
+                if let Some(clipboard) = editor.selector.clipboard().cloned() {
+                    ui.label(format!("Clipboard {}x{}", clipboard.width, clipboard.height));
+                    let mut transformed = None;
+                    ui.horizontal(|ui| {
+                        if ui.button("Rotate").clicked() {
+                            transformed = Some(clipboard.rotated());
+                        }
+                        if ui.button("Flip horizontally").clicked() {
+                            transformed = Some(clipboard.flipped_horizontally());
+                        }
+                        if ui.button("Flip vertically").clicked() {
+                            transformed = Some(clipboard.flipped_vertically());
+                        }
+                    });
+                    if let Some(transformed) = transformed {
+                        editor
+                            .selector
+                            .set_clipboard(Some(transformed), &simulation.matter_definitions);
+                    }
+                    ui.checkbox(&mut editor.selector.paste_empty, "Paste empty cells")
+                        .on_hover_text("Clear matter under empty cells of clipboard");
+                    if ui.button("Clear").clicked() {
+                        editor
+                            .selector
+                            .set_clipboard(None, &simulation.matter_definitions);
+                    }
+                }
+            } else if editor.mode == EditorMode::Blueprint {
+                ui.separator();
+                ui.label("Left drag to sketch, sketching empty erases");
+                ui.checkbox(&mut editor.blueprint.visible, "Show blueprint");
+                ui.label(format!("Sketched cells ({})", editor.blueprint.num_cells()));
+                ui.button("Build from blueprint")
+                    .on_hover_text("Paint sketched matter to empty cells of simulated area")
+                    .clicked()
+                    .then(|| match editor.blueprint.build(simulation) {
+                        std::result::Result::Ok(cells) => {
+                            editor.toasts.info(format!("Built {} cells", cells.len()));
+                            if !cells.is_empty() {
+                                editor.undo.push(EditAction::Paint(cells));
+                            }
+                        }
+                        Err(e) => editor
+                            .error_dialog
+                            .push_error("Failed to build from blueprint", &e),
+                    });
+                if ui.button("Clear blueprint").clicked() {
+                    editor.blueprint.clear();
                 }
+                ui.label("Brush Radius");
+                ui.add(egui::Slider::new(&mut editor.painter.radius, 0.5..=30.0));
+                ui.separator();
+                ui.label(format!(
+                    "Matter ({})",
+                    &simulation.matter_definitions.definitions[editor.painter.matter as usize]
+                        .name
+                ));
+                add_matter_palette(ui, simulation, editor, textures);
+            } else if editor.mode == EditorMode::Joint {
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut editor.joiner.kind, ObjectJointKind::Revolute, "Hinge")
+                        .on_hover_text("Objects turn freely around the second clicked point");
+                    ui.selectable_value(&mut editor.joiner.kind, ObjectJointKind::Rope, "Rope")
+                        .on_hover_text("Clicked points can't get further apart than they are");
+                });
+                ui.label("Left click two objects to join them, right click to free an object");
+                if editor.joiner.first.is_some() {
+                    ui.label("Click second object, or the first again to cancel");
+                }
+                ui.label(format!("Joints ({})", simulation.object_joints.len()));
+            } else if editor.mode == EditorMode::Fill {
+                ui.separator();
+                ui.horizontal(|ui| {
+                    for tool in FillTool::ALL {
+                        ui.selectable_value(&mut editor.filler.tool, tool, tool.name());
+                    }
+                });
+                match editor.filler.tool {
+                    FillTool::Rect => {
+                        ui.label("Left drag to fill a rectangle");
+                    }
+                    FillTool::Polygon => {
+                        ui.label("Left click vertices, click the first or right click to fill");
+                        ui.label(format!("Vertices ({})", editor.filler.polygon.len()));
+                    }
+                    FillTool::Bucket => {
+                        ui.label("Left click to replace the connected region of that matter");
+                    }
+                }
+                if editor.filler.tool != FillTool::Bucket {
+                    ui.checkbox(&mut editor.filler.replace, "Replace matter")
+                        .on_hover_text("Fill over matter too, instead of only empty cells");
+                }
+                ui.separator();
+                ui.label(format!(
+                    "Matter ({})",
+                    &simulation.matter_definitions.definitions[editor.painter.matter as usize]
+                        .name
+                ));
+                add_matter_palette(ui, simulation, editor, textures);
+            } else if editor.mode == EditorMode::Beam {
+                ui.separator();
+                ui.label("Right click to place emitter, hold left to fire towards mouse");
+                ui.label("Beams pass liquids & gases, and stop at solids & powders");
+                ui.add(egui::Slider::new(&mut editor.beam.length, 8.0..=512.0).text("Length"));
+                ui.add(
+                    egui::Slider::new(&mut editor.beam.impulse, 0.0..=0.5).text("Object impulse"),
+                );
+                ui.label("Heat matter")
+                    .on_hover_text("Spawned next to hit cells, reacting with them");
+                let matter_names = simulation
+                    .matter_definitions
+                    .definitions
+                    .iter()
+                    .map(|d| d.name.clone())
+                    .collect::<Vec<String>>();
+                add_matter_combo(
+                    ui,
+                    "Beam heat matter",
+                    &mut editor.beam.heat_matter,
+                    &matter_names,
+                );
+                if editor.beam.emitter.is_some() && ui.button("Remove emitter").clicked() {
+                    editor.beam.emitter = None;
+                }
+            } else {
+                ui.label("Move object by dragging");
+                ui.label("Right click to anchor object to nearby solid terrain, again to free");
+                ui.label(format!("Anchored objects ({})", simulation.anchors.len()));
+            }
+            ui.separator();
+            ui.add(
+                egui::Slider::new(&mut editor.camera_pan_speed, 0.1..=3.0).text("Pan speed"),
+            )
+            .on_hover_text("Screens per second moved with arrows & WASD. Home recenters");
+            let mut rotation = api.main_camera.rotation().to_degrees();
+            ui.add(egui::Slider::new(&mut rotation, 0.0..=359.0).text("Camera rotation"))
+                .on_hover_text("Rotates only the view, gravity stays down. R rotates by 90°")
+                .changed()
+                .then(|| api.main_camera.set_rotation(rotation.to_radians()));
+        };
+        match window_gui {
+            Some((window_ctx, _)) => {
+                egui::CentralPanel::default().show(&window_ctx, |ui| {
+                    egui::ScrollArea::vertical().show(ui, contents);
+                });
+            }
+            None => {
+                egui::Window::new("Editor")
+                    .open(show_edit_view)
+                    .vscroll(true)
+                    .default_width(200.0)
+                    .default_height(800.0)
+                    .show(&ctx, contents);
+            }
+        }
+        if toggle_pop_out {
+            pop_outs.toggle(api, Panel::Editor);
+        }
+    }
+
+    /// Minimal overlay shown instead of windows in compact HUD mode
+    pub fn add_compact_hud(
+        &mut self,
+        api: &EngineApi<InputAction>,
+        simulation: &Simulation,
+        editor: &Editor,
+    ) {
+        let matter = match editor.mode {
+            EditorMode::Paint | EditorMode::Blueprint | EditorMode::Fill => {
+                Some(editor.painter.matter)
+            }
+            EditorMode::Place | EditorMode::ObjectPaint => Some(editor.placer.object_matter),
+            EditorMode::Beam => Some(editor.beam.heat_matter),
+            EditorMode::Drag | EditorMode::Stamp | EditorMode::Select | EditorMode::Joint => None,
+        };
+        let ctx = api.gui.context();
+        egui::Area::new("Compact HUD")
+            .anchor(Align2::LEFT_TOP, [10.0, 10.0])
+            .show(&ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if let Some(matter) = matter {
+                        if let Some(texture_id) = editor.matter_texture_ids.get(&matter) {
+                            ui.add(egui::Image::new(*texture_id, Vec2::new(16.0, 16.0)));
+                        }
+                        ui.label(&simulation.matter_definitions.definitions[matter as usize].name);
+                    }
+                    if editor.mode == EditorMode::Paint || editor.mode == EditorMode::ObjectPaint {
+                        ui.label(format!("Brush: {:.1}", editor.painter.radius));
+                    }
+                    ui.label(format!("FPS: {:.0}", api.time.avg_fps()));
+                });
+            });
+    }
+
+    /// Always visible bar of pinned and recently used matters & objects
+    pub fn add_quick_bar(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        simulation: &Simulation,
+        editor: &mut Editor,
+    ) {
+        let button_size = Vec2::new(32.0, 32.0);
+        let matter_data = &simulation.matter_definitions.definitions;
+        let items = editor.quick_bar.items();
+        if items.is_empty() {
+            return;
+        }
+        let ctx = api.gui.context();
+        egui::Area::new("Quick Bar")
+            .anchor(Align2::CENTER_BOTTOM, [0.0, -10.0])
+            .show(&ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        for (slot, item) in items.iter().enumerate() {
+                            let image = match item {
+                                QuickBarItem::Matter(matter) => editor
+                                    .matter_texture_ids
+                                    .get(matter)
+                                    .zip(matter_data.get(*matter as usize).map(|m| &m.name)),
+                                QuickBarItem::Object(object) => editor
+                                    .placer
+                                    .object_image_texture_ids
+                                    .get(object)
+                                    .map(|texture_id| (texture_id, object)),
+                            };
+                            let (texture_id, name) = match image {
+                                Some((texture_id, name)) => (*texture_id, name.clone()),
+                                None => continue,
+                            };
+                            ui.vertical(|ui| {
+                                let is_pinned = editor.quick_bar.is_pinned(item);
+                                // Slots are bound to keys 5, 6, ..., 0
+                                let key = (slot + 5) % 10;
+                                let label = if is_pinned {
+                                    format!("{} 📌", key)
+                                } else {
+                                    key.to_string()
+                                };
+                                ui.label(label);
+                                let response = ui
+                                    .add(ImageButton::new(texture_id, button_size))
+                                    .on_hover_text(format!("{} (right click to pin)", name));
+                                if response.clicked() {
+                                    editor.select_quick_bar_item(item);
+                                }
+                                if response.secondary_clicked() {
+                                    editor.quick_bar.toggle_pin(item);
+                                }
+                            });
+                        }
+                    });
+                });
             });
     }
 
@@ -585,6 +2601,25 @@ impl GuiState {
             .query_matter(canvas_mouse_state.mouse_on_canvas)
             .unwrap()
         {
+            // Distance down to matter under empty cells, e.g. for measuring drops
+            let mouse_pos = canvas_mouse_state.mouse_on_canvas;
+            let below = if matter == simulation.matter_definitions.empty {
+                simulation
+                    .raycast_matter(
+                        Vector2::new(mouse_pos.x as f32, mouse_pos.y as f32),
+                        Vector2::new(0.0, -1.0),
+                        f32::INFINITY,
+                        |_| true,
+                    )
+                    .unwrap()
+                    .map_or(String::new(), |hit| {
+                        let name = &matter_data[hit.matter as usize].name;
+                        let (y, distance) = (hit.canvas_pos.y, hit.distance);
+                        format!("\nBelow: {} at y {}, {:.1} cells down", name, y, distance)
+                    })
+            } else {
+                String::new()
+            };
             let matter = &matter_data[matter as usize];
             let obj = physics_entity_at_pos(
                 &api.physics_world,
@@ -622,8 +2657,8 @@ impl GuiState {
                     egui::Id::new("Hover tooltip"),
                     |ui| {
                         ui.label(format!(
-                            "Matter: ({}, {})\n{}",
-                            matter.name, matter.state, canvas_mouse_state,
+                            "Matter: ({}, {}){}\n{}",
+                            matter.name, matter.state, below, canvas_mouse_state,
                         ));
                     },
                 );
@@ -632,7 +2667,28 @@ impl GuiState {
     }
 }
 
-fn add_matter_palette(ui: &mut Ui, simulation: &Simulation, editor: &mut Editor) {
+/// World map color of chunks in `state`, None for chunks only on disk
+fn chunk_state_color(state: Option<ChunkLoadState>) -> egui::Color32 {
+    match state {
+        Some(ChunkLoadState::Simulated) => egui::Color32::YELLOW,
+        Some(ChunkLoadState::Gpu) => egui::Color32::from_rgb(60, 180, 75),
+        Some(ChunkLoadState::Loading) => egui::Color32::LIGHT_BLUE,
+        Some(ChunkLoadState::Unloading) => egui::Color32::from_rgb(245, 130, 48),
+        Some(ChunkLoadState::Cpu) => egui::Color32::GRAY,
+        None => egui::Color32::from_rgb(70, 60, 110),
+    }
+}
+
+fn format_mib(bytes: usize) -> String {
+    format!("{:.2} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+fn add_matter_palette(
+    ui: &mut Ui,
+    simulation: &Simulation,
+    editor: &mut Editor,
+    textures: PanelTextures,
+) {
     let button_size = Vec2::new(24.0, 24.0);
     let grouped_matters = get_grouped_matters(&simulation.matter_definitions.definitions);
     let num_cols = 4;
@@ -647,11 +2703,15 @@ fn add_matter_palette(ui: &mut Ui, simulation: &Simulation, editor: &mut Editor)
                     .matter_texture_ids
                     .get(&m.id)
                     .expect("Material texture id not found");
-                let btn = ImageButton::new(*texture_id, button_size);
+                let btn = ImageButton::new(textures.get(*texture_id), button_size);
                 ui.horizontal(|ui| {
-                    if ui.add(btn).on_hover_text(&m.name).clicked() {
+                    let response = ui.add(btn).on_hover_text(&m.name);
+                    if response.clicked() {
                         editor.painter.matter = m.id;
                     }
+                    if response.secondary_clicked() {
+                        editor.quick_bar.toggle_pin(&QuickBarItem::Matter(m.id));
+                    }
                     ui.label(&m.name);
                 });
                 cols += 1;
@@ -670,9 +2730,25 @@ fn add_matter_edit_palette(
     simulation: &mut Simulation,
     editor: &mut Editor,
     add_matter: &mut MatterDefinition,
+    matter_pack: &mut MatterPackForm,
+    textures: PanelTextures,
 ) {
     let img_size = Vec2::new(24.0, 24.0);
     let matters: Vec<MatterDefinition> = simulation.matter_definitions.definitions.clone();
+    // Matter sharing a color can't be told apart on canvas or in exported chunk images
+    for (a, b) in simulation.matter_definitions.color_collisions() {
+        let (a, b) = (&matters[a as usize].name, &matters[b as usize].name);
+        ui.colored_label(
+            egui::Color32::from_rgb(255, 180, 0),
+            format!("⚠ {} & {} share a color", a, b),
+        );
+    }
+    for issue in simulation.matter_definitions.validate() {
+        ui.colored_label(
+            egui::Color32::from_rgb(255, 180, 0),
+            format!("⚠ {}", issue.describe(&simulation.matter_definitions)),
+        );
+    }
     ui.horizontal(|ui| {
         Grid::new("Edit matter palette").show(ui, |ui| {
             for m in matters.iter() {
@@ -680,15 +2756,38 @@ fn add_matter_edit_palette(
                     .matter_texture_ids
                     .get(&m.id)
                     .expect("Material texture id not found");
-                let img = egui::Image::new(*texture_id, img_size);
+                let img = egui::Image::new(textures.get(*texture_id), img_size);
                 ui.add(img);
                 ui.label(&m.name);
                 ui.button("🖊").clicked().then(|| {
                     *add_matter = m.clone();
                 });
+                let protected = simulation.is_matter_protected(m.id);
+                ui.selectable_label(protected, "🔒")
+                    .on_hover_text("Protect cells from brush & reactions, e.g. blueprint outlines")
+                    .clicked()
+                    .then(|| {
+                        let result = simulation.set_matter_protected(m.id, !protected);
+                        if let Err(e) = result {
+                            editor.error_dialog.push_error("Failed to protect matter", &e);
+                        }
+                    });
                 if m.id != MATTER_EMPTY {
+                    let mut picked = matter_pack.picked.contains(&m.name);
+                    ui.checkbox(&mut picked, "📦")
+                        .on_hover_text("Include in exported matter pack")
+                        .changed()
+                        .then(|| {
+                            if picked {
+                                matter_pack.picked.insert(m.name.clone());
+                            } else {
+                                matter_pack.picked.remove(&m.name);
+                            }
+                        });
                     ui.button("❌").clicked().then(|| {
-                        simulation.remove_matter_definition(m.id).unwrap();
+                        let result = simulation.remove_matter_definition(m.id);
+                        editor.quick_bar.matter_removed(m.id);
+                        editor.toasts.report(result, format!("Removed matter {}", m.name));
                         editor.update_matter_gui_textures(api, simulation);
                     });
                 }
@@ -699,26 +2798,337 @@ fn add_matter_edit_palette(
 
     ui.separator();
     ui.button("Save Matters").clicked().then(|| {
-        simulation.save_matter_definitions();
+        let result = simulation.save_matter_definitions();
+        let saved = result.as_ref().map_or(String::new(), |path| {
+            format!("Saved matter definitions to {:?}", path)
+        });
+        editor.report(result, saved, "Failed to save matter definitions");
+    });
+    ui.button("Export Reference")
+        .on_hover_text("Markdown of matters & their reactions to share with collaborators")
+        .clicked()
+        .then(|| {
+            let result = simulation.export_matter_reference();
+            let exported = result.as_ref().map_or(String::new(), |path| {
+                format!("Exported matter reference to {:?}", path)
+            });
+            editor.report(result, exported, "Failed to export matter reference");
+        });
+    ui.collapsing("Matter Packs", |ui| {
+        add_matter_packs(ui, api, simulation, editor, matter_pack);
+    });
+}
+
+/// Step the matter being edited among chosen neighbors on cpu, to test its reactions without
+/// painting on canvas
+fn add_reaction_sandbox(
+    ui: &mut Ui,
+    simulation: &Simulation,
+    matter: &MatterDefinition,
+    sandbox: &mut ReactionSandbox,
+) {
+    let matter_definitions = &simulation.matter_definitions;
+    let matter_names = matter_definitions
+        .definitions
+        .iter()
+        .map(|m| m.name.clone())
+        .collect::<Vec<String>>();
+    // Neighbors by grid row & column, center being the tested matter
+    let layout = [[0, 1, 2], [7, 8, 3], [6, 5, 4]];
+    Grid::new("Reaction sandbox").show(ui, |ui| {
+        for row in layout {
+            for index in row {
+                match sandbox.neighbors.get_mut(index) {
+                    Some(neighbor) => {
+                        // Matters may have been removed since
+                        *neighbor = (*neighbor).min(matter_names.len() as u32 - 1);
+                        add_matter_combo(ui, ("Sandbox neighbor", index), neighbor, &matter_names);
+                    }
+                    None => {
+                        ui.label(&matter.name);
+                    }
+                }
+            }
+            ui.end_row();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut sandbox.steps).clamp_range(1..=100_000));
+        ui.button("Run steps")
+            .on_hover_text("Count reactions over steps, restoring the cell after each reaction")
+            .clicked()
+            .then(|| {
+                sandbox.dry_run = Some(DryRun::run(
+                    matter_definitions,
+                    matter,
+                    &sandbox.neighbors,
+                    sandbox.steps,
+                    &mut rand::thread_rng(),
+                ));
+            });
+    });
+    if let Some(dry_run) = &sandbox.dry_run {
+        for (index, &count) in dry_run.reactions.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let becomes = matter.reactions[index].becomes;
+            ui.label(format!(
+                "Reaction {} into {}: {:.1}% of steps",
+                index + 1,
+                matter_names.get(becomes as usize).map_or("Unknown", |n| n.as_str()),
+                count as f32 / dry_run.steps as f32 * 100.0
+            ));
+        }
+        ui.label(format!("Unchanged: {:.1}% of steps", dry_run.unchanged() * 100.0));
+    }
+}
+
+/// Export picked matters as a pack & import packs from data directory, e.g. shared by others
+fn add_matter_packs(
+    ui: &mut Ui,
+    api: &mut EngineApi<InputAction>,
+    simulation: &mut Simulation,
+    editor: &mut Editor,
+    matter_pack: &mut MatterPackForm,
+) {
+    ui.horizontal(|ui| {
+        ui.label("Name");
+        ui.text_edit_singleline(&mut matter_pack.name);
+    });
+    let ids = simulation
+        .matter_definitions
+        .definitions
+        .iter()
+        .filter(|m| matter_pack.picked.contains(&m.name))
+        .map(|m| m.id)
+        .collect::<Vec<u32>>();
+    let can_export = !ids.is_empty() && !matter_pack.name.trim().is_empty();
+    ui.add_enabled(can_export, egui::Button::new("Export Pack"))
+        .on_hover_text("Matters picked with 📦, along with matters they react into")
+        .clicked()
+        .then(|| {
+            let result = simulation.export_matter_pack(matter_pack.name.trim(), &ids);
+            let exported = result.as_ref().map_or(String::new(), |path| {
+                format!("Exported matter pack to {:?}", path)
+            });
+            editor.report(result, exported, "Failed to export matter pack");
+        });
+    ui.separator();
+    let names = match matter_pack_names() {
+        std::result::Result::Ok(names) => names,
+        Err(e) => {
+            ui.label(format!("Failed to list matter packs: {}", e));
+            return;
+        }
+    };
+    if names.is_empty() {
+        ui.label(format!(
+            "Add packs from others to {:?}",
+            DATA_DIRS.data.join("matter_packs")
+        ));
+    }
+    for name in names.iter() {
+        ui.horizontal(|ui| {
+            ui.label(name);
+            ui.button("Import")
+                .on_hover_text("Add matters of pack that don't exist yet")
+                .clicked()
+                .then(|| {
+                    match simulation.import_matter_pack(name) {
+                        std::result::Result::Ok(import) => {
+                            let mut message = format!(
+                                "Imported {} matters of pack {}",
+                                import.added.len(),
+                                name
+                            );
+                            if !import.skipped.is_empty() {
+                                message += &format!(", kept existing {:?}", import.skipped);
+                            }
+                            if !import.unresolved.is_empty() {
+                                message +=
+                                    &format!(", missing {:?} became empty", import.unresolved);
+                            }
+                            editor.toasts.info(message);
+                        }
+                        Err(e) => editor
+                            .error_dialog
+                            .push_error("Failed to import matter pack", &e),
+                    }
+                    editor.update_matter_gui_textures(api, simulation);
+                });
+        });
+    }
+}
+
+fn add_trigger_actions(
+    ui: &mut Ui,
+    id_source: &str,
+    actions: &mut Vec<TriggerAction>,
+    matter_names: &[String],
+) {
+    let mut remove = None;
+    for (index, action) in actions.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.label(action.label());
+            match action {
+                TriggerAction::SpawnMatter {
+                    matter,
+                    radius,
+                } => {
+                    add_matter_combo(ui, (id_source, index), matter, matter_names);
+                    ui.add(egui::Slider::new(radius, 0.5..=30.0));
+                }
+                TriggerAction::SpawnShape {
+                    matter,
+                    shape,
+                } => {
+                    add_matter_combo(ui, (id_source, index), matter, matter_names);
+                    add_shape_combo(ui, (id_source, index, "shape"), shape);
+                    match shape {
+                        Shape::Circle {
+                            radius,
+                        } => {
+                            ui.add(egui::Slider::new(radius, 0.5..=30.0));
+                        }
+                        Shape::Rect {
+                            width,
+                            height,
+                        } => {
+                            ui.add(egui::DragValue::new(width).clamp_range(1..=256));
+                            ui.add(egui::DragValue::new(height).clamp_range(1..=256));
+                        }
+                        // Polygons are edited in map's triggers.json
+                        Shape::Polygon(vertices) => {
+                            ui.label(format!("{} vertices", vertices.len()));
+                        }
+                    }
+                }
+                TriggerAction::PlaySound(sound) => {
+                    ui.text_edit_singleline(sound);
+                }
+                _ => (),
+            }
+            ui.button("❌").clicked().then(|| {
+                remove = Some(index);
+            });
+        });
+    }
+    if let Some(index) = remove {
+        actions.remove(index);
+    }
+    ui.horizontal(|ui| {
+        for new_action in [
+            TriggerAction::SpawnMatter {
+                matter: MATTER_EMPTY,
+                radius: 4.0,
+            },
+            TriggerAction::SpawnShape {
+                matter: MATTER_EMPTY,
+                shape: Shape::Rect {
+                    width: 8,
+                    height: 8,
+                },
+            },
+            TriggerAction::DeleteObjects,
+            TriggerAction::PlaySound("".to_string()),
+            TriggerAction::ToggleEmitter,
+        ] {
+            ui.small_button(format!("+ {}", new_action.label()))
+                .clicked()
+                .then(|| actions.push(new_action));
+        }
     });
 }
 
-fn add_object_palette(ui: &mut Ui, editor: &mut Editor) {
+/// Picks between circle & rect, polygons can't be picked as their vertices are edited in json
+fn add_shape_combo(ui: &mut Ui, id_source: impl std::hash::Hash, shape: &mut Shape) {
+    egui::ComboBox::from_id_source(id_source)
+        .selected_text(shape.label())
+        .show_ui(ui, |ui| {
+            for option in [
+                Shape::Circle {
+                    radius: 4.0,
+                },
+                Shape::Rect {
+                    width: 8,
+                    height: 8,
+                },
+            ] {
+                let selected = std::mem::discriminant(shape) == std::mem::discriminant(&option);
+                if ui.selectable_label(selected, option.label()).clicked() && !selected {
+                    *shape = option;
+                }
+            }
+        });
+}
+
+fn add_matter_combo(
+    ui: &mut Ui,
+    id_source: impl std::hash::Hash,
+    matter: &mut u32,
+    matter_names: &[String],
+) {
+    egui::ComboBox::from_id_source(id_source)
+        .selected_text(&matter_names[*matter as usize])
+        .show_ui(ui, |ui| {
+            for (id, name) in matter_names.iter().enumerate() {
+                ui.selectable_value(matter, id as u32, name);
+            }
+        });
+}
+
+fn add_object_palette(ui: &mut Ui, editor: &mut Editor, textures: PanelTextures) {
     let EditorPlacer {
         place_object: object,
         object_image_texture_ids,
         ..
     } = &mut editor.placer;
+    let quick_bar = &mut editor.quick_bar;
     let button_size = Vec2::new(48.0, 48.0);
     let num_cols = 2;
     Grid::new("Objects").show(ui, |ui| {
         let mut cols = 0;
         for (key, val) in object_image_texture_ids.iter() {
-            let btn = ImageButton::new(*val, button_size);
+            let btn = ImageButton::new(textures.get(*val), button_size);
             ui.horizontal(|ui| {
-                if ui.add(btn).on_hover_text(key).clicked() {
+                let response = ui.add(btn).on_hover_text(key);
+                if response.clicked() {
                     *object = Some(key.clone());
                 }
+                if response.secondary_clicked() {
+                    quick_bar.toggle_pin(&QuickBarItem::Object(key.clone()));
+                }
+                ui.label(key);
+            });
+            cols += 1;
+            if cols == num_cols {
+                ui.end_row();
+                cols = 0;
+            }
+        }
+    });
+}
+
+fn add_stamp_palette(ui: &mut Ui, editor: &mut Editor, textures: PanelTextures) {
+    let EditorStamper {
+        stamp,
+        stamp_texture_ids,
+        ..
+    } = &mut editor.stamper;
+    let button_size = Vec2::new(48.0, 48.0);
+    let num_cols = 2;
+    Grid::new("Stamps").show(ui, |ui| {
+        let mut cols = 0;
+        for (key, val) in stamp_texture_ids.iter() {
+            ui.horizontal(|ui| {
+                if ui
+                    .add(ImageButton::new(textures.get(*val), button_size))
+                    .on_hover_text(key)
+                    .clicked()
+                {
+                    *stamp = Some(key.clone());
+                }
                 ui.label(key);
             });
             cols += 1;
@@ -737,21 +3147,100 @@ fn add_loadable_maps(
     simulation: &mut Simulation,
 ) {
     let file_names = editor.saver.map_file_names.clone();
+    let thumbnail_size = Vec2::new(32.0, 32.0);
     for map in file_names.iter() {
         ui.horizontal(|ui| {
+            if let Some(thumbnail) = editor.saver.map_thumbnail(api, map) {
+                ui.image(thumbnail, thumbnail_size);
+            }
             ui.button(map).clicked().then(|| {
-                editor.saver.load_map(api, simulation, map).unwrap();
-                api.main_camera.translate(-api.main_camera.pos());
+                editor.undo.clear();
+                let result = editor.saver.load_map(api, simulation, map, &mut editor.painter);
+                let failure = format!("Failed to load map {}", map);
+                if let Some(skipped) = editor.report(result, format!("Loaded map {}", map), failure)
+                {
+                    if !skipped.is_empty() {
+                        editor
+                            .error_dialog
+                            .push(format!("Map {} was loaded partially", map), skipped);
+                    }
+                }
+            });
+            ui.button("❌").clicked().then(|| {
+                let result = editor.saver.delete_map(api, map);
+                editor.report(
+                    result,
+                    format!("Removed map {}", map),
+                    format!("Failed to remove map {}", map),
+                );
             });
-            ui.button("❌")
-                .clicked()
-                .then(|| editor.saver.delete_map(map));
         });
         ui.end_row();
     }
 }
 
-fn add_object_matter_palette(ui: &mut Ui, editor: &mut Editor, matter_data: &MatterDefinitions) {
+fn add_replays(
+    ui: &mut Ui,
+    editor: &mut Editor,
+    api: &mut EngineApi<InputAction>,
+    simulation: &mut Simulation,
+) {
+    ui.button("Save replay")
+        .on_hover_text(
+            "Save seeds of steps since map was loaded. Replaying loads the map & runs the same \
+             steps, which reproduce the session if nothing was edited",
+        )
+        .clicked()
+        .then(|| {
+            match simulation
+                .record_replay(&editor.saver.map_name)
+                .and_then(|replay| replay.save())
+            {
+                std::result::Result::Ok(name) => {
+                    editor.toasts.info(format!("Saved replay {}", name))
+                }
+                Err(e) => editor.error_dialog.push_error("Failed to save replay", &e),
+            }
+        });
+    if let Some(replay) = &simulation.replay {
+        let (step, steps) = replay.progress();
+        ui.label(format!("Replaying step {} / {}", step, steps));
+        ui.button("Stop replay").clicked().then(|| simulation.replay = None);
+        return;
+    }
+    let names = match replay_names() {
+        std::result::Result::Ok(names) => names,
+        Err(e) => {
+            ui.label(format!("Failed to list replays: {}", e));
+            return;
+        }
+    };
+    for name in names.iter() {
+        ui.button(name).clicked().then(|| {
+            editor.undo.clear();
+            let result = Replay::load(name).and_then(|replay| {
+                editor
+                    .saver
+                    .load_map(api, simulation, &replay.map_name, &mut editor.painter)?;
+                simulation.set_world_seed(replay.world_seed)?;
+                simulation.replay = Some(replay);
+                Ok(())
+            });
+            editor.report(
+                result,
+                format!("Replaying {}, run simulation to play it", name),
+                format!("Failed to start replay {}", name),
+            );
+        });
+    }
+}
+
+fn add_object_matter_palette(
+    ui: &mut Ui,
+    editor: &mut Editor,
+    matter_data: &MatterDefinitions,
+    textures: PanelTextures,
+) {
     let button_size = Vec2::new(24.0, 24.0);
     let matters: Vec<MatterDefinition> = matter_data
         .definitions
@@ -773,7 +3262,7 @@ fn add_object_matter_palette(ui: &mut Ui, editor: &mut Editor, matter_data: &Mat
                     .matter_texture_ids
                     .get(&m.id)
                     .expect("Material texture id not found");
-                let btn = ImageButton::new(*texture_id, button_size);
+                let btn = ImageButton::new(textures.get(*texture_id), button_size);
                 ui.horizontal(|ui| {
                     if ui.add(btn).on_hover_text(&m.name).clicked() {
                         editor.placer.object_matter = m.id;
@@ -790,6 +3279,389 @@ fn add_object_matter_palette(ui: &mut Ui, editor: &mut Editor, matter_data: &Mat
     }
 }
 
+/// Checkbox per object layer, toggling its bit in the mask
+fn add_layer_mask_checkboxes(ui: &mut Ui, mask: &mut u32) {
+    ui.horizontal_wrapped(|ui| {
+        for layer in 0..NUM_OBJECT_LAYERS {
+            let mut is_set = *mask & (1 << layer) != 0;
+            if ui.checkbox(&mut is_set, layer.to_string()).changed() {
+                *mask ^= 1 << layer;
+            }
+        }
+    });
+}
+
+fn add_object_collision_options(ui: &mut Ui, collision: &mut ObjectCollision) {
+    ui.label("Collision");
+    ui.selectable_value(&mut collision.preset, CollisionPreset::Solid, "Solid")
+        .on_hover_text("Collides with boundaries & objects on chosen layers");
+    ui.selectable_value(
+        &mut collision.preset,
+        CollisionPreset::OneWayPlatform,
+        "One-way platform",
+    )
+    .on_hover_text("Objects pass through from below & land on top");
+    ui.selectable_value(&mut collision.preset, CollisionPreset::Ghost, "Ghost")
+        .on_hover_text("Collides only with boundaries");
+    ui.label("Layer");
+    ui.add(egui::Slider::new(&mut collision.layer, 0..=NUM_OBJECT_LAYERS - 1));
+    if collision.preset != CollisionPreset::Ghost {
+        ui.label("Collides with layers");
+        add_layer_mask_checkboxes(ui, &mut collision.collides_with);
+    }
+}
+
+fn add_brush_options(ui: &mut Ui, painter: &mut EditorPainter, brush_images: &mut BrushImages) {
+    ui.label("Brush shape");
+    ui.horizontal_wrapped(|ui| {
+        for shape in ALL_BRUSH_SHAPES {
+            ui.selectable_value(&mut painter.shape, shape, format!("{:?}", shape));
+        }
+    });
+    match painter.shape {
+        BrushShape::Line | BrushShape::Triangle => {
+            ui.add(egui::Slider::new(&mut painter.angle, 0.0..=359.0).text("Angle"));
+        }
+        BrushShape::Spray => {
+            ui.add(egui::Slider::new(&mut painter.spray_density, 0.0..=1.0).text("Spray density"))
+                .on_hover_text("Chance of painting a cell each step");
+        }
+        BrushShape::Image => {
+            let BrushImages {
+                selected,
+                images,
+            } = brush_images;
+            if images.is_empty() {
+                ui.label(format!("Add .png images to {:?}", DATA_DIRS.data.join("brushes")));
+            }
+            for name in images.keys() {
+                ui.selectable_value(selected, Some(name.clone()), name);
+            }
+        }
+        BrushShape::Round | BrushShape::Square => {
+            ui.add(egui::Slider::new(&mut painter.stamp_spacing, 0.05..=1.0).text("Spacing"))
+                .on_hover_text(
+                    "Distance between brush stamps along strokes, relative to radius. Lower is \
+                     smoother but slower",
+                );
+        }
+    }
+    ui.add(egui::Slider::new(&mut painter.falloff, 0.0..=1.0).text("Falloff"))
+        .on_hover_text("Fewer cells are painted towards brush edge");
+    ui.add(egui::Slider::new(&mut painter.strength, 0.0..=1.0).text("Strength"))
+        .on_hover_text("Erasing matter harder than strength takes more passes");
+}
+
+fn add_brush_fill_options(ui: &mut Ui, fill: &mut BrushFill) {
+    ui.label("Fill pattern");
+    ui.horizontal_wrapped(|ui| {
+        for (pattern, name) in [
+            (BrushPattern::Solid, "Solid"),
+            (BrushPattern::Noise, "Noise"),
+            (BrushPattern::Checker, "Checker"),
+            (BrushPattern::Stripes, "Stripes"),
+            (BrushPattern::Veins, "Veins"),
+        ] {
+            ui.selectable_value(&mut fill.pattern, pattern, name);
+        }
+    });
+    if fill.pattern == BrushPattern::Noise || fill.pattern == BrushPattern::Veins {
+        ui.add(egui::Slider::new(&mut fill.density, 0.0..=1.0).text("Density"))
+            .on_hover_text("How much of the brush area is filled");
+    }
+    if fill.pattern != BrushPattern::Solid && fill.pattern != BrushPattern::Noise {
+        ui.add(egui::Slider::new(&mut fill.scale, 1.0..=32.0).text("Scale"))
+            .on_hover_text("Size of pattern features in cells");
+    }
+}
+
+/// Button moving a panel between main window & a window of its own
+fn add_pop_out_button(ui: &mut Ui, is_popped: bool, toggle: &mut bool) {
+    let (text, hover) = if is_popped {
+        ("Dock", "Move back to main window")
+    } else {
+        ("Pop out", "Move to a window of its own, e.g. to a second monitor")
+    };
+    ui.button(text)
+        .on_hover_text(hover)
+        .clicked()
+        .then(|| *toggle = true);
+}
+
+fn add_undo_buttons(
+    ui: &mut Ui,
+    editor: &mut Editor,
+    api: &mut EngineApi<InputAction>,
+    simulation: &mut Simulation,
+) {
+    ui.horizontal(|ui| {
+        let undo = ui
+            .add_enabled(editor.undo.can_undo(), egui::Button::new("Undo"))
+            .on_hover_text("Undo last paint or object placement (Ctrl+Z)");
+        if undo.clicked() {
+            let result = editor.undo.undo(api, simulation);
+            editor.toasts.report_error(result);
+        }
+        let redo = ui
+            .add_enabled(editor.undo.can_redo(), egui::Button::new("Redo"))
+            .on_hover_text("Redo last undone edit (Ctrl+Y)");
+        if redo.clicked() {
+            let result = editor.undo.redo(api, simulation);
+            editor.toasts.report_error(result);
+        }
+    });
+}
+
+/// Strip of edit thumbnails, oldest first. Clicking one undoes or redoes to the state after it
+fn add_edit_history(
+    ui: &mut Ui,
+    editor: &mut Editor,
+    api: &mut EngineApi<InputAction>,
+    simulation: &mut Simulation,
+    textures: PanelTextures,
+) {
+    let history = editor.undo.history();
+    if history.is_empty() {
+        return;
+    }
+    let applied = editor.undo.applied();
+    let button_size = Vec2::new(40.0, 40.0);
+    let mut jump = None;
+    egui::ScrollArea::horizontal()
+        .id_source("Edit history")
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                let start = ui
+                    .selectable_label(applied == 0, "Start")
+                    .on_hover_text("State before the oldest edit");
+                if start.clicked() {
+                    jump = Some(0);
+                }
+                for (i, thumbnail) in history.iter().enumerate() {
+                    let hover = if i < applied {
+                        format!("Edit {}", i + 1)
+                    } else {
+                        format!("Edit {} (undone)", i + 1)
+                    };
+                    let response = match thumbnail {
+                        Some(texture) => ui.add(
+                            ImageButton::new(textures.get(*texture), button_size)
+                                .selected(i + 1 == applied),
+                        ),
+                        None => ui.selectable_label(i + 1 == applied, format!("{}", i + 1)),
+                    };
+                    if response.on_hover_text(hover).clicked() {
+                        jump = Some(i + 1);
+                    }
+                }
+            });
+        });
+    if let Some(target) = jump {
+        let result = editor.undo.jump_to(api, simulation, target);
+        editor.toasts.report_error(result);
+    }
+}
+
+fn add_step_controls(
+    ui: &mut Ui,
+    simulation: &Simulation,
+    time: &mut TimeTracker,
+    step_control: &mut StepControl,
+) {
+    let mut time_scale = time.time_scale();
+    ui.add(egui::Slider::new(&mut time_scale, 0.1..=4.0).text("Time scale"))
+        .on_hover_text("Speed of simulated time. Steps are capped at one per frame");
+    time.set_time_scale(time_scale);
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut step_control.steps_per_input).clamp_range(1..=1000));
+        ui.button("Step")
+            .on_hover_text("Take this many steps, also with Enter")
+            .clicked()
+            .then(|| {
+                time.set_paused(true);
+                step_control.step_n();
+            });
+    });
+    egui::ComboBox::from_label("Stop condition")
+        .selected_text(step_control.stop_condition.name())
+        .show_ui(ui, |ui| {
+            for condition in ALL_STOP_CONDITIONS {
+                ui.selectable_value(&mut step_control.stop_condition, condition, condition.name());
+            }
+        });
+    if let Some(condition) = step_control.running_until() {
+        ui.label(format!("Running until: {}", condition.name()));
+        ui.button("Stop").clicked().then(|| step_control.stop());
+    } else if ui.button("Run until").clicked() {
+        time.set_paused(true);
+        if let Err(e) = step_control.run_until(simulation) {
+            error!("Failed to start running until {}: {}", step_control.stop_condition.name(), e);
+        }
+    }
+    if step_control.steps_left() > 0 {
+        ui.label(format!("Steps left: {}", step_control.steps_left()));
+    }
+}
+
+/// Saved settings profiles & key bindings saved with them
+fn add_data_dirs(ui: &mut Ui) {
+    ui.collapsing("Directories", |ui| {
+        if DATA_DIRS.portable {
+            ui.label("Portable mode, all data is kept in assets");
+        } else {
+            ui.label(format!("Launch with {} to keep all data in assets", PORTABLE_FLAG));
+        }
+        Grid::new("Data directories").show(ui, |ui| {
+            for (name, dir) in [
+                ("Assets", &DATA_DIRS.assets),
+                ("Data", &DATA_DIRS.data),
+                ("Config", &DATA_DIRS.config),
+            ] {
+                ui.label(name);
+                ui.label(dir.to_string_lossy());
+                ui.end_row();
+            }
+        });
+    });
+}
+
+fn add_settings_profiles(
+    ui: &mut Ui,
+    api: &mut EngineApi<InputAction>,
+    editor: &mut Editor,
+    settings: &mut AppSettings,
+    profiles: &mut SettingsProfiles,
+) {
+    let mut load = None;
+    let mut delete = None;
+    Grid::new("Settings profiles").show(ui, |ui| {
+        for name in profiles.names.iter() {
+            let is_current = profiles.current.as_ref() == Some(name);
+            ui.selectable_label(is_current, name)
+                .on_hover_text("Load profile")
+                .clicked()
+                .then(|| load = Some(name.clone()));
+            ui.button("❌").clicked().then(|| delete = Some(name.clone()));
+            ui.end_row();
+        }
+    });
+    if let Some(name) = load {
+        let result = profiles.load(&name, settings, &mut api.inputs[0]);
+        editor.report(
+            result,
+            format!("Loaded settings profile {}", name),
+            "Failed to load settings profile",
+        );
+    }
+    if let Some(name) = delete {
+        let result = profiles.delete(&name);
+        editor.toasts.report(result, format!("Deleted settings profile {}", name));
+    }
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(&mut profiles.profile_name);
+        ui.button("Save")
+            .on_hover_text("Save settings & key bindings, replacing a profile of the same name")
+            .clicked()
+            .then(|| {
+                let result = profiles.save(settings, &api.inputs[0]);
+                if let Some(name) = editor.toasts.report_error(result) {
+                    editor.toasts.info(format!("Saved settings profile {}", name));
+                }
+            });
+    });
+    ui.collapsing("Key bindings", |ui| {
+        Grid::new("Key bindings").show(ui, |ui| {
+            for (action, _) in default_key_bindings() {
+                let key = bound_key(&api.inputs[0], action);
+                ui.label(format!("{:?}", action));
+                egui::ComboBox::from_id_source(action)
+                    .selected_text(key.map_or_else(|| "None".to_string(), key_name))
+                    .show_ui(ui, |ui| {
+                        for new_key in BINDABLE_KEYS.iter().copied() {
+                            if !ui
+                                .selectable_label(key == Some(new_key), key_name(new_key))
+                                .clicked()
+                            {
+                                continue;
+                            }
+                            let key_bindings = current_key_bindings(&api.inputs[0]);
+                            let rebound = rebind_key(&key_bindings, action, new_key);
+                            match key_binding_problem(&rebound) {
+                                Some(problem) => editor.toasts.error(problem),
+                                None => api.inputs[0]
+                                    .mapper_mut()
+                                    .add_action(action, InputButton::Key(new_key)),
+                            }
+                        }
+                    });
+                ui.end_row();
+            }
+        });
+        ui.button("Reset keys").clicked().then(|| {
+            let key_bindings = default_key_bindings()
+                .into_iter()
+                .map(|(action, key)| (action, InputButton::Key(key)))
+                .collect::<Vec<(InputAction, InputButton)>>();
+            api.inputs[0].mapper_mut().add_actions(&key_bindings);
+        });
+    });
+}
+
+fn add_fullscreen_options(ui: &mut Ui, api: &mut EngineApi<InputAction>) {
+    if ui.selectable_label(api.renderer.is_fullscreen(), "Fullscreen (F)").clicked() {
+        api.renderer.toggle_fullscreen();
+    }
+    let mut video_mode = api.renderer.fullscreen_video_mode().cloned();
+    let selected_text = video_mode
+        .as_ref()
+        .map_or_else(|| "Borderless".to_string(), video_mode_label);
+    egui::ComboBox::from_label("Fullscreen mode")
+        .selected_text(selected_text)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut video_mode, None, "Borderless")
+                .on_hover_text("Fullscreen window at desktop resolution");
+            for mode in api.renderer.video_modes() {
+                let label = video_mode_label(&mode);
+                ui.selectable_value(&mut video_mode, Some(mode), label)
+                    .on_hover_text("Exclusive fullscreen, resolution & refresh rate of monitor");
+            }
+        });
+    if video_mode.as_ref() != api.renderer.fullscreen_video_mode() {
+        api.renderer.set_fullscreen_video_mode(video_mode);
+    }
+}
+
+fn video_mode_label(mode: &VideoMode) -> String {
+    let size = mode.size();
+    format!("{}x{} @ {} Hz", size.width, size.height, mode.refresh_rate())
+}
+
+fn add_ore_vein_options(ui: &mut Ui, ore_veins: &mut OreVeins, matter_data: &MatterDefinitions) {
+    let matter_defs = &matter_data.definitions;
+    egui::ComboBox::from_label("Host")
+        .selected_text(&matter_defs[ore_veins.host as usize].name)
+        .show_ui(ui, |ui| {
+            for definition in matter_defs.iter() {
+                ui.selectable_value(&mut ore_veins.host, definition.id, &definition.name);
+            }
+        });
+    egui::ComboBox::from_label("Ore")
+        .selected_text(&matter_defs[ore_veins.ore as usize].name)
+        .show_ui(ui, |ui| {
+            for definition in matter_defs.iter() {
+                ui.selectable_value(&mut ore_veins.ore, definition.id, &definition.name);
+            }
+        });
+    ui.add(egui::Slider::new(&mut ore_veins.frequency, 0.0..=1.0).text("Frequency"))
+        .on_hover_text("Share of host area veins appear in");
+    ui.add(egui::Slider::new(&mut ore_veins.vein_length, 2.0..=64.0).text("Vein length"))
+        .on_hover_text("Typical length of veins in cells");
+    ui.add(egui::Slider::new(&mut ore_veins.thickness, 0.0..=1.0).text("Thickness"));
+    ui.add(egui::DragValue::new(&mut ore_veins.seed).prefix("Seed: "));
+    ui.checkbox(&mut ore_veins.fill_empty, "Fill empty with host")
+        .on_hover_text("Turn empty cells to host first, e.g. for a world to dig through");
+}
+
 fn get_grouped_matters(matters: &[MatterDefinition]) -> Vec<Vec<MatterDefinition>> {
     let mut matters: Vec<MatterDefinition> = matters.to_vec();
     matters.sort_unstable_by_key(|m| m.state);
@@ -0,0 +1,71 @@
+use anyhow::*;
+use cgmath::{InnerSpace, Vector2};
+use corrode::{api::physics_entity_mut_at_pos, physics::PhysicsWorld};
+use rapier2d::prelude::*;
+
+use crate::{sim::Simulation, CELL_UNIT_SIZE};
+
+/// Beam fired from an emitter towards the mouse, heating solids it hits & pushing objects
+pub struct EditorBeam {
+    /// World position the beam is fired from, placed with right click
+    pub emitter: Option<Vector2<f32>>,
+    /// Matter spawned next to the hit cell, whose reactions heat or ignite it
+    pub heat_matter: u32,
+    /// Max beam length in cells
+    pub length: f32,
+    /// Impulse given to hit objects each step the beam is fired
+    pub impulse: f32,
+    /// World positions of the latest beam's start & end, for rendering
+    pub fired: Option<(Vector2<f32>, Vector2<f32>)>,
+    /// Simulation step the beam was last fired at, so it's applied once per step regardless of
+    /// frame rate
+    pub fired_step: Option<u64>,
+}
+
+impl EditorBeam {
+    /// Fire beam from emitter towards `target` world position, unless already fired this step
+    pub fn fire(
+        &mut self,
+        simulation: &mut Simulation,
+        physics_world: &mut PhysicsWorld,
+        target: Vector2<f32>,
+    ) -> Result<()> {
+        if self.fired_step == Some(simulation.step_count) {
+            return Ok(());
+        }
+        self.fired_step = Some(simulation.step_count);
+        self.fired = None;
+        let start = match self.emitter {
+            Some(start) => start,
+            None => return Ok(()),
+        };
+        let dir = target - start;
+        if dir.magnitude2() == 0.0 {
+            return Ok(());
+        }
+        let dir = dir.normalize();
+        let hit = simulation.fire_beam(
+            start / *CELL_UNIT_SIZE,
+            dir,
+            self.length,
+            self.heat_matter,
+        )?;
+        let distance = hit.map_or(self.length, |hit| hit.distance);
+        self.fired = Some((start, start + dir * distance * *CELL_UNIT_SIZE));
+        if let Some(hit) = hit {
+            let hit_center = Vector2::new(hit.canvas_pos.x as f32, hit.canvas_pos.y as f32);
+            let hit_pos = hit_center * *CELL_UNIT_SIZE;
+            if let Some((rb, _)) = physics_entity_mut_at_pos(physics_world, hit_pos) {
+                if rb.is_dynamic() {
+                    let impulse = dir * self.impulse;
+                    rb.apply_impulse_at_point(
+                        vector![impulse.x, impulse.y],
+                        point![hit_pos.x, hit_pos.y],
+                        true,
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::*;
+use cgmath::{MetricSpace, Vector2};
+use corrode::renderer::render_pass::DrawPass;
+use vulkano::format::Format;
+
+use crate::{
+    interact::PreviewTexture,
+    matter::MatterDefinitions,
+    sim::{canvas_pos_to_world_pos, Simulation},
+    utils::{BitmapImage, Color32},
+};
+
+/// Alpha of sketched cells drawn over the canvas
+const BLUEPRINT_ALPHA: u8 = 110;
+
+/// Non simulated sketch of matter over the canvas for planning builds. Sketched cells are drawn
+/// translucently & can be built, painting real matter where they are
+pub struct EditorBlueprint {
+    /// Sketched matter by canvas position
+    cells: HashMap<Vector2<i32>, u32>,
+    /// Whether the sketch is drawn over the canvas
+    pub visible: bool,
+    /// Image of sketched cells, top row first, & its min & max canvas positions
+    preview: Option<(BitmapImage, Vector2<i32>, Vector2<i32>)>,
+    preview_texture: PreviewTexture,
+}
+
+impl EditorBlueprint {
+    pub fn new() -> EditorBlueprint {
+        EditorBlueprint {
+            cells: HashMap::new(),
+            visible: true,
+            preview: None,
+            preview_texture: PreviewTexture::new(),
+        }
+    }
+
+    pub fn num_cells(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Sketch round strokes of matter along the line, covering the same cells as
+    /// `Simulation::paint_round`. Empty matter erases the sketch
+    pub fn sketch(
+        &mut self,
+        line: &[Vector2<i32>],
+        matter: u32,
+        radius: f32,
+        matter_definitions: &MatterDefinitions,
+    ) {
+        let reach = radius as i32;
+        for &center in line {
+            for y in center.y - reach..=center.y + reach {
+                for x in center.x - reach..=center.x + reach {
+                    let distance = Vector2::new(x as f32, y as f32)
+                        .distance(Vector2::new(center.x as f32, center.y as f32));
+                    if distance.round() > radius {
+                        continue;
+                    }
+                    let pos = Vector2::new(x, y);
+                    if matter == matter_definitions.empty {
+                        self.cells.remove(&pos);
+                    } else {
+                        self.cells.insert(pos, matter);
+                    }
+                }
+            }
+        }
+        self.update_preview(matter_definitions);
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.preview = None;
+        self.preview_texture.invalidate();
+    }
+
+    /// Paint sketched matter to empty cells of simulated area, like the brush. Returns painted
+    /// cells with their matter before & after, for undo
+    pub fn build(&self, simulation: &mut Simulation) -> Result<Vec<(Vector2<i32>, u32, u32)>> {
+        let num_matters = simulation.matter_definitions.definitions.len() as u32;
+        let mut cells_by_matter = BTreeMap::<u32, Vec<Vector2<i32>>>::new();
+        // Matter removed since sketching is skipped
+        for (&pos, &matter) in self.cells.iter().filter(|(_, &matter)| matter < num_matters) {
+            cells_by_matter.entry(matter).or_default().push(pos);
+        }
+        let mut built = vec![];
+        for (matter, cells) in cells_by_matter {
+            let painted = simulation.paint_round(&cells, matter, 0.0, 1.0, |_, _| true)?;
+            built.extend(painted.into_iter().map(|(pos, previous)| (pos, previous, matter)));
+        }
+        Ok(built)
+    }
+
+    /// Draw preview image over the canvas where the cells are sketched
+    pub fn draw_preview(&mut self, draw_pass: &mut DrawPass, format: Format) -> Result<()> {
+        if let Some((image, min, max)) = &self.preview {
+            let center = (canvas_pos_to_world_pos(*min) + canvas_pos_to_world_pos(*max)) / 2.0;
+            self.preview_texture.draw(draw_pass, image, center, format)?;
+        }
+        Ok(())
+    }
+
+    /// Redraw preview image, e.g. after sketching or matter colors changed
+    pub fn update_preview(&mut self, matter_definitions: &MatterDefinitions) {
+        self.preview_texture.invalidate();
+        let mut positions = self.cells.keys();
+        let first = match positions.next() {
+            Some(&first) => first,
+            None => {
+                self.preview = None;
+                return;
+            }
+        };
+        let (min, max) = positions.fold((first, first), |(min, max), pos| {
+            (
+                Vector2::new(min.x.min(pos.x), min.y.min(pos.y)),
+                Vector2::new(max.x.max(pos.x), max.y.max(pos.y)),
+            )
+        });
+        let width = (max.x - min.x + 1) as u32;
+        let mut image = BitmapImage::empty(width, (max.y - min.y + 1) as u32);
+        for (pos, &matter) in self.cells.iter() {
+            let definition = match matter_definitions.definitions.get(matter as usize) {
+                Some(definition) => definition,
+                None => continue,
+            };
+            let mut rgba = Color32::from_u32_rgba(definition.color).to_rgba();
+            rgba[3] = BLUEPRINT_ALPHA;
+            let (x, y) = ((pos.x - min.x) as u32, (max.y - pos.y) as u32);
+            let index = (y * width + x) as usize * 4;
+            image.data[index..index + 4].copy_from_slice(&rgba);
+        }
+        self.preview = Some((image, min, max));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matter::{default_matter_definitions, MATTER_SAND};
+
+    #[test]
+    fn test_sketch_and_erase() {
+        let definitions = default_matter_definitions();
+        let mut blueprint = EditorBlueprint::new();
+        let line = [Vector2::new(0, 0), Vector2::new(1, 0)];
+        blueprint.sketch(&line, MATTER_SAND, 0.5, &definitions);
+        assert_eq!(blueprint.num_cells(), 2);
+        let (image, min, max) = blueprint.preview.as_ref().unwrap();
+        assert_eq!((*min, *max), (Vector2::new(0, 0), Vector2::new(1, 0)));
+        assert_eq!(image.data[3], BLUEPRINT_ALPHA);
+
+        blueprint.sketch(&line[..1], definitions.empty, 0.5, &definitions);
+        assert_eq!(blueprint.num_cells(), 1);
+        blueprint.sketch(&line[1..], definitions.empty, 0.5, &definitions);
+        assert!(blueprint.preview.is_none());
+    }
+}
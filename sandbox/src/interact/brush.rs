@@ -0,0 +1,160 @@
+use std::{collections::BTreeMap, fs, path::PathBuf, sync::Arc};
+
+use anyhow::*;
+use cgmath::{InnerSpace, Vector2};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data_dirs::DATA_DIRS,
+    sim::is_inside_polygon,
+    utils::{load_bitmap_image_from_path, BitmapImage},
+};
+
+/// Footprint of the painter's brush
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BrushShape {
+    Round,
+    Square,
+    /// One cell wide line of brush diameter, rotated by brush angle
+    Line,
+    /// Triangle within brush radius, rotated by brush angle
+    Triangle,
+    /// Round brush painting random cells each step, `spray_density` of them
+    Spray,
+    /// Opaque pixels of a brush image, scaled to brush diameter
+    Image,
+}
+
+pub const ALL_BRUSH_SHAPES: [BrushShape; 6] = [
+    BrushShape::Round,
+    BrushShape::Square,
+    BrushShape::Line,
+    BrushShape::Triangle,
+    BrushShape::Spray,
+    BrushShape::Image,
+];
+
+impl Default for BrushShape {
+    fn default() -> Self {
+        BrushShape::Round
+    }
+}
+
+impl BrushShape {
+    /// Whether brush covers cell at `offset` from its center. Square & image brushes are painted
+    /// as squares of brush diameter & others within brush radius, so only cells there are asked
+    pub fn covers(
+        &self,
+        offset: Vector2<i32>,
+        radius: f32,
+        angle: f32,
+        image: Option<&BitmapImage>,
+    ) -> bool {
+        let offset_f32 = Vector2::new(offset.x as f32, offset.y as f32);
+        let dir = Vector2::new(angle.cos(), angle.sin());
+        match self {
+            BrushShape::Round | BrushShape::Square | BrushShape::Spray => true,
+            BrushShape::Line => {
+                let along = offset_f32.dot(dir).clamp(-radius, radius);
+                (offset_f32 - dir * along).magnitude() <= 0.5
+            }
+            BrushShape::Triangle => {
+                // Pointing up without rotation
+                let corners = [90.0f32, 210.0, 330.0]
+                    .iter()
+                    .map(|a| {
+                        let a = a.to_radians() + angle;
+                        Vector2::new(a.cos(), a.sin()) * radius
+                    })
+                    .collect::<Vec<_>>();
+                is_inside_polygon(&corners, offset_f32)
+            }
+            BrushShape::Image => {
+                let image = match image {
+                    Some(image) => image,
+                    None => return false,
+                };
+                // Top row of image is at the top of the brush
+                let diameter = radius * 2.0;
+                let u = (offset_f32.x + radius) / diameter * image.width as f32;
+                let v = (radius - 1.0 - offset_f32.y) / diameter * image.height as f32;
+                let x = (u.max(0.0) as u32).min(image.width - 1);
+                let y = (v.max(0.0) as u32).min(image.height - 1);
+                image.data[((y * image.width + x) * 4 + 3) as usize] > 0
+            }
+        }
+    }
+}
+
+/// Chance a cell at `distance` from brush center is painted. Without falloff every cell is, with
+/// full falloff chance fades to zero at brush edge
+pub fn falloff_chance(distance: f32, radius: f32, falloff: f32) -> f32 {
+    (1.0 - falloff * distance / radius.max(0.5)).clamp(0.0, 1.0)
+}
+
+/// Images for image brushes, by file name
+pub struct BrushImages {
+    pub selected: Option<String>,
+    pub images: BTreeMap<String, Arc<BitmapImage>>,
+}
+
+impl BrushImages {
+    pub fn selected_image(&self) -> Option<&BitmapImage> {
+        self.selected
+            .as_ref()
+            .and_then(|name| self.images.get(name))
+            .map(|image| image.as_ref())
+    }
+}
+
+fn brushes_dir() -> Result<PathBuf> {
+    let dir_path = DATA_DIRS.data.join("brushes");
+    fs::create_dir_all(&dir_path)?;
+    Ok(dir_path)
+}
+
+/// Brush images by file name, and errors of files that could not be loaded
+pub fn get_brush_image_files() -> Result<(BTreeMap<String, Arc<BitmapImage>>, Vec<String>)> {
+    let mut images = BTreeMap::new();
+    let mut errors = vec![];
+    let dir_path = brushes_dir()?;
+    for file in fs::read_dir(&dir_path)? {
+        let file = file?.file_name();
+        let file_name = file.to_string_lossy().to_string();
+        match load_bitmap_image_from_path(dir_path.join(&file_name)) {
+            std::result::Result::Ok(image) => {
+                images.insert(file_name, Arc::new(image));
+            }
+            Err(e) => errors.push(format!("Failed to load brush {}: {:#}", file_name, e)),
+        }
+    }
+    Ok((images, errors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_brush_shapes() {
+        let covered = |shape: BrushShape, angle: f32| {
+            (-4..=4)
+                .flat_map(|y| (-4..=4).map(move |x| Vector2::new(x, y)))
+                .filter(|offset| shape.covers(*offset, 4.0, angle, None))
+                .collect::<Vec<_>>()
+        };
+        let line = covered(BrushShape::Line, 0.0);
+        assert_eq!(line.len(), 9);
+        assert!(line.iter().all(|offset| offset.y == 0));
+        let vertical = covered(BrushShape::Line, std::f32::consts::FRAC_PI_2);
+        assert!(vertical.iter().all(|offset| offset.x == 0));
+        let triangle = covered(BrushShape::Triangle, 0.0);
+        assert!(triangle.contains(&Vector2::new(0, 3)));
+        assert!(!triangle.contains(&Vector2::new(3, 3)));
+        assert!(covered(BrushShape::Image, 0.0).is_empty());
+
+        assert_eq!(falloff_chance(4.0, 4.0, 0.0), 1.0);
+        assert_eq!(falloff_chance(4.0, 4.0, 1.0), 0.0);
+        assert_eq!(falloff_chance(2.0, 4.0, 1.0), 0.5);
+    }
+}
@@ -0,0 +1,74 @@
+use cgmath::Vector2;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{cell_hash, value_noise};
+
+/// Pattern of cells a brush fills, so large painted areas don't look flat
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BrushPattern {
+    Solid,
+    /// Random cells, `density` of them
+    Noise,
+    Checker,
+    /// Diagonal stripes
+    Stripes,
+    /// Winding lines, wider with `density`
+    Veins,
+}
+
+/// Which cells painting fills. Depends only on the cell, so painting over the same area again
+/// doesn't fill it up
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BrushFill {
+    pub pattern: BrushPattern,
+    /// 0.0 - 1.0
+    pub density: f32,
+    /// Size of pattern features in cells
+    pub scale: f32,
+}
+
+impl BrushFill {
+    pub fn fills(&self, pos: Vector2<i32>) -> bool {
+        let scale = self.scale.max(1.0);
+        let cell = |v: i32| (v as f32 / scale).floor() as i32;
+        match self.pattern {
+            BrushPattern::Solid => true,
+            BrushPattern::Noise => cell_hash(pos.x, pos.y, 0) < self.density,
+            BrushPattern::Checker => (cell(pos.x) + cell(pos.y)).rem_euclid(2) == 0,
+            BrushPattern::Stripes => cell(pos.x + pos.y).rem_euclid(2) == 0,
+            BrushPattern::Veins => {
+                let noise = value_noise(pos.x as f32 / scale, pos.y as f32 / scale, 0);
+                (noise - 0.5).abs() < 0.15 * self.density
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_brush_fill_patterns() {
+        let fill = |pattern, density| BrushFill {
+            pattern,
+            density,
+            scale: 4.0,
+        };
+        let count = |fill: BrushFill| {
+            (0..64)
+                .flat_map(|y| (0..64).map(move |x| Vector2::new(x, y)))
+                .filter(|pos| fill.fills(*pos))
+                .count()
+        };
+        assert_eq!(count(fill(BrushPattern::Solid, 0.0)), 64 * 64);
+        assert_eq!(count(fill(BrushPattern::Checker, 1.0)), 64 * 64 / 2);
+        assert_eq!(count(fill(BrushPattern::Noise, 0.0)), 0);
+        let noise = count(fill(BrushPattern::Noise, 0.5));
+        assert!(noise > 64 * 64 * 2 / 5 && noise < 64 * 64 * 3 / 5);
+        // Same cells are filled every time
+        let veins = fill(BrushPattern::Veins, 0.5);
+        assert_eq!(count(veins), count(veins));
+        assert!(count(veins) < count(fill(BrushPattern::Veins, 1.0)));
+    }
+}
@@ -8,9 +8,15 @@ use crate::sim::canvas_pos_to_world_pos;
 pub struct CanvasDrawState {
     pub current: Option<Vector2<i32>>,
     pub prev: Option<Vector2<i32>>,
+    /// Sample before `prev`, bending smoothed strokes
+    before_prev: Option<Vector2<i32>>,
+    /// Continuous cells covered by the latest transition
+    line: Vec<Vector2<i32>>,
     pub pixels: HashSet<Vector2<i32>>,
     pub min: Option<Vector2<i32>>,
     pub max: Option<Vector2<i32>>,
+    /// Whether strokes curve through mouse samples instead of joining them with straight lines
+    pub smoothing: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -25,9 +31,12 @@ impl CanvasDrawState {
         CanvasDrawState {
             current: None,
             prev: None,
+            before_prev: None,
+            line: vec![],
             pixels: HashSet::new(),
             min: None,
             max: None,
+            smoothing: false,
         }
     }
 
@@ -39,37 +48,39 @@ impl CanvasDrawState {
         match draw_event {
             DrawTransition::Start(v, size) => {
                 self.pixels.clear();
+                self.prev = None;
+                self.before_prev = None;
                 self.current = Some(v);
-                if !is_square {
-                    self.add_to_pixels_by_radius(v, size);
-                } else {
-                    self.add_to_pixels_by_square(v, size);
-                }
+                self.line = vec![v];
+                self.add_line_to_pixels(size, is_square);
                 None
             }
             DrawTransition::Draw(v, size) => {
-                self.prev = self.current;
-                self.current = Some(v);
-                let line = self.get_line();
-                for pos in line {
-                    if !is_square {
-                        self.add_to_pixels_by_radius(pos, size);
-                    } else {
-                        self.add_to_pixels_by_square(pos, size);
-                    }
+                // Mouse resting in place doesn't bend the stroke
+                if self.current == Some(v) {
+                    self.line = vec![v];
+                    return None;
                 }
+                self.advance(v);
+                self.line = self.segment(false);
+                self.add_line_to_pixels(size, is_square);
                 None
             }
             DrawTransition::End(v, size) => {
-                self.prev = self.current;
-                self.current = Some(v);
-                if !is_square {
-                    self.add_to_pixels_by_radius(v, size);
+                self.line = if self.current != Some(v) {
+                    self.advance(v);
+                    self.segment(true)
                 } else {
-                    self.add_to_pixels_by_square(v, size);
-                }
+                    // Smoothed stroke still lags behind from the latest moving sample
+                    match self.prev {
+                        Some(prev) if self.smoothing => bresenham_line(midpoint(prev, v), v),
+                        _ => vec![v],
+                    }
+                };
+                self.add_line_to_pixels(size, is_square);
                 let result = self.clone();
                 self.prev = None;
+                self.before_prev = None;
                 self.current = None;
                 self.min = None;
                 self.max = None;
@@ -78,6 +89,45 @@ impl CanvasDrawState {
         }
     }
 
+    fn advance(&mut self, v: Vector2<i32>) {
+        self.before_prev = self.prev;
+        self.prev = self.current;
+        self.current = Some(v);
+    }
+
+    /// Cells from the previous sample to current one, Bresenham joined so fast mouse movement
+    /// leaves no gaps. Smoothed strokes curve from midpoint to midpoint of samples, bending at
+    /// samples, so they lag half a segment behind until `is_end`
+    fn segment(&self, is_end: bool) -> Vec<Vector2<i32>> {
+        let current = self.current.unwrap();
+        let prev = match self.prev {
+            Some(prev) => prev,
+            None => return vec![current],
+        };
+        if !self.smoothing {
+            return bresenham_line(prev, current);
+        }
+        let mid = midpoint(prev, current);
+        let mut line = match self.before_prev {
+            Some(before_prev) => quadratic_bezier_line(midpoint(before_prev, prev), prev, mid),
+            None => bresenham_line(prev, mid),
+        };
+        if is_end {
+            line.extend(bresenham_line(mid, current).into_iter().skip(1));
+        }
+        line
+    }
+
+    fn add_line_to_pixels(&mut self, size: f32, is_square: bool) {
+        for pos in self.line.clone() {
+            if !is_square {
+                self.add_to_pixels_by_radius(pos, size);
+            } else {
+                self.add_to_pixels_by_square(pos, size);
+            }
+        }
+    }
+
     fn update_bounds(&mut self, pos: Vector2<i32>) {
         if self.min.is_none() {
             self.min = Some(pos)
@@ -133,15 +183,9 @@ impl CanvasDrawState {
         }
     }
 
+    /// Continuous cells stroked by the latest transition, to be painted
     pub fn get_line(&self) -> Vec<Vector2<i32>> {
-        let current = self.current.unwrap();
-        if let Some(prev_pos) = self.prev {
-            line_drawing::Bresenham::new((prev_pos.x, prev_pos.y), (current.x, current.y))
-                .map(|pos| Vector2::new(pos.0, pos.1))
-                .collect()
-        } else {
-            vec![current]
-        }
+        self.line.clone()
     }
 
     #[allow(unused)]
@@ -160,3 +204,73 @@ impl CanvasDrawState {
         canvas_pos_to_world_pos(self.current.unwrap() - (local_current - half_way))
     }
 }
+
+fn midpoint(a: Vector2<i32>, b: Vector2<i32>) -> Vector2<i32> {
+    Vector2::new((a.x + b.x).div_euclid(2), (a.y + b.y).div_euclid(2))
+}
+
+fn bresenham_line(start: Vector2<i32>, end: Vector2<i32>) -> Vec<Vector2<i32>> {
+    line_drawing::Bresenham::new((start.x, start.y), (end.x, end.y))
+        .map(|pos| Vector2::new(pos.0, pos.1))
+        .collect()
+}
+
+/// Cells along quadratic bezier from `start` to `end` bent towards `control`. Curve is sampled
+/// about once per cell & samples joined with Bresenham, so the line has no gaps
+fn quadratic_bezier_line(
+    start: Vector2<i32>,
+    control: Vector2<i32>,
+    end: Vector2<i32>,
+) -> Vec<Vector2<i32>> {
+    let [p0, p1, p2] = [start, control, end].map(|p| p.cast::<f32>().unwrap());
+    let steps = (p0.distance(p1) + p1.distance(p2)).ceil().max(1.0) as i32;
+    let mut line = vec![start];
+    let mut last = start;
+    for step in 1..=steps {
+        let t = step as f32 / steps as f32;
+        let point = p0 * (1.0 - t) * (1.0 - t) + p1 * 2.0 * (1.0 - t) * t + p2 * t * t;
+        let cell = point.map(|v| v.round() as i32);
+        if cell != last {
+            line.extend(bresenham_line(last, cell).into_iter().skip(1));
+            last = cell;
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_continuous(line: &[Vector2<i32>]) -> bool {
+        line.windows(2)
+            .all(|pair| (pair[1].x - pair[0].x).abs() <= 1 && (pair[1].y - pair[0].y).abs() <= 1)
+    }
+
+    #[test]
+    fn test_fast_strokes_are_continuous() {
+        for smoothing in [false, true] {
+            let mut state = CanvasDrawState::new();
+            state.smoothing = smoothing;
+            let samples = [Vector2::new(0, 0), Vector2::new(40, 5), Vector2::new(10, 60)];
+            state.transition(DrawTransition::Start(samples[0], 1.0), false);
+            let mut stroke = state.get_line();
+            for &sample in &samples[1..] {
+                state.transition(DrawTransition::Draw(sample, 1.0), false);
+                let line = state.get_line();
+                assert!(is_continuous(&line));
+                assert_eq!(line.first(), stroke.last());
+                stroke.extend(line.into_iter().skip(1));
+            }
+            let end = Vector2::new(-20, 30);
+            let end_state = state
+                .transition(DrawTransition::End(end, 1.0), false)
+                .unwrap();
+            let line = end_state.get_line();
+            assert_eq!(line.first(), stroke.last());
+            assert_eq!(line.last(), Some(&end));
+            assert!(is_continuous(&line));
+            assert!(end_state.pixels.contains(&end));
+        }
+    }
+}
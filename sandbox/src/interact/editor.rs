@@ -5,10 +5,13 @@ use cgmath::Vector2;
 use corrode::{
     api::{physics_entity_at_pos, remove_physics_entity, EngineApi},
     input_system::{
-        InputButton::{MouseLeft, MouseMiddle, MouseRight},
+        InputButton::{Key, MouseLeft, MouseMiddle, MouseRight},
         State::{Activated, Deactivated, Held},
     },
-    renderer::{create_device_image_with_usage, render_pass::DrawPass},
+    renderer::{
+        create_device_image_with_usage, create_mipmapped_image, render_pass::DrawPass,
+        MipmappedImageView,
+    },
 };
 use egui::TextureId;
 use rand::Rng;
@@ -17,26 +20,58 @@ use vulkano::{
     command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, PrimaryCommandBuffer},
     format::Format,
     image::ImageUsage,
+    sampler::Filter,
     sync::GpuFuture,
 };
+use winit::event::VirtualKeyCode;
 
 use crate::{
     app::InputAction,
+    error_dialog::ErrorDialog,
     interact::{
+        beam::EditorBeam,
+        blueprint::EditorBlueprint,
+        brush::{get_brush_image_files, BrushImages, BrushShape},
+        brush_fill::{BrushFill, BrushPattern},
         dragger::EditorDragger,
+        filler::{EditorFiller, FillTool},
+        joiner::EditorJoiner,
+        key_gate::KeyGate,
         painter::EditorPainter,
         placer::{get_object_image_files, EditorPlacer},
+        pointer_gate::PointerGate,
+        quick_bar::{QuickBar, QuickBarItem, QUICK_BAR_SIZE},
         saver::EditorSaveLoader,
+        selector::EditorSelector,
+        stamper::{get_stamp_files, EditorStamper},
+        undo::{EditAction, ObjectSnapshot, UndoHistory},
         CanvasDrawState, DrawTransition,
     },
-    matter::{MatterDefinition, MATTER_SAND, MATTER_WOOD},
-    sim::{world_pos_to_canvas_pos, Simulation},
-    utils::get_map_directory_names,
-    CELL_UNIT_SIZE,
+    matter::{
+        MatterDefinition, MATTER_FIRE, MATTER_LEAD, MATTER_ROCK, MATTER_SAND, MATTER_WOOD,
+    },
+    object::{FixedObject, ObjectCollision},
+    sim::{world_pos_to_canvas_pos, ObjectJointKind, OreVeins, Simulation, MAX_BUGS},
+    toasts::Toasts,
+    utils::{get_map_directory_names, BitmapImage, Color32, ImageWriter},
+    CELL_UNIT_SIZE, WORLD_UNIT_SIZE,
 };
 
 /// Radius of the brush. 0.5 for one pixel
 const BRUSH_RADIUS: f32 = 4.0;
+/// Bugs spawned per key press, within brush radius
+const BUGS_PER_SPAWN: usize = 10;
+/// Keys panning the camera & their directions, for those without a middle mouse button
+pub const CAMERA_PAN_KEYS: [(VirtualKeyCode, Vector2<f32>); 8] = [
+    (VirtualKeyCode::Left, Vector2::new(-1.0, 0.0)),
+    (VirtualKeyCode::Right, Vector2::new(1.0, 0.0)),
+    (VirtualKeyCode::Up, Vector2::new(0.0, 1.0)),
+    (VirtualKeyCode::Down, Vector2::new(0.0, -1.0)),
+    (VirtualKeyCode::A, Vector2::new(-1.0, 0.0)),
+    (VirtualKeyCode::D, Vector2::new(1.0, 0.0)),
+    (VirtualKeyCode::W, Vector2::new(0.0, 1.0)),
+    (VirtualKeyCode::S, Vector2::new(0.0, -1.0)),
+];
 
 #[derive(Debug, Ord, PartialOrd, Eq, PartialEq)]
 pub enum EditorMode {
@@ -44,6 +79,12 @@ pub enum EditorMode {
     Place,
     ObjectPaint,
     Drag,
+    Stamp,
+    Select,
+    Beam,
+    Blueprint,
+    Joint,
+    Fill,
 }
 
 pub struct Editor {
@@ -51,27 +92,77 @@ pub struct Editor {
     pub draw_state: CanvasDrawState,
 
     pub matter_texture_ids: BTreeMap<u32, TextureId>,
+    /// Colors matter textures were generated from, so unchanged ones can be reused
+    matter_texture_colors: BTreeMap<u32, u32>,
+    pub toasts: Toasts,
+    pub error_dialog: ErrorDialog,
+    pub quick_bar: QuickBar,
+    pointer_gate: PointerGate,
+    /// Tool hotkeys pass only while gui doesn't take keyboard input, or with its modifier
+    pub key_gate: KeyGate,
 
     pub painter: EditorPainter,
+    pub brush_images: BrushImages,
     pub dragger: EditorDragger,
     pub placer: EditorPlacer,
     pub saver: EditorSaveLoader,
+    pub stamper: EditorStamper,
+    pub selector: EditorSelector,
+    pub beam: EditorBeam,
+    pub blueprint: EditorBlueprint,
+    pub joiner: EditorJoiner,
+    pub filler: EditorFiller,
+    pub undo: UndoHistory,
+    /// Ore generation settings of new maps & on demand generation
+    pub ore_veins: OreVeins,
+    /// Screen heights per second camera is panned with keys
+    pub camera_pan_speed: f32,
 }
 
 impl Editor {
     pub fn new() -> Result<Editor> {
-        let obj_images = get_object_image_files()?;
+        let (obj_images, obj_image_errors) = get_object_image_files()?;
+        let (stamps, stamp_errors) = get_stamp_files()?;
+        let (brush_images, brush_errors) = get_brush_image_files()?;
         let map_file_names = get_map_directory_names()?;
+        let mut toasts = Toasts::new();
+        for e in obj_image_errors
+            .into_iter()
+            .chain(stamp_errors)
+            .chain(brush_errors)
+        {
+            toasts.error(e);
+        }
         Ok(Editor {
             mode: EditorMode::Paint,
             draw_state: CanvasDrawState::new(),
 
             matter_texture_ids: BTreeMap::new(),
+            matter_texture_colors: BTreeMap::new(),
+            toasts,
+            error_dialog: ErrorDialog::new(),
+            quick_bar: QuickBar::new(),
+            pointer_gate: PointerGate::new(),
+            key_gate: KeyGate::new(),
 
             painter: EditorPainter {
                 matter: MATTER_SAND,
                 radius: BRUSH_RADIUS,
-                is_square: false,
+                shape: BrushShape::Round,
+                angle: 0.0,
+                falloff: 0.0,
+                spray_density: 0.05,
+                strength: 0.5,
+                fill: BrushFill {
+                    pattern: BrushPattern::Solid,
+                    density: 0.5,
+                    scale: 4.0,
+                },
+                stamp_spacing: 0.25,
+            },
+            brush_images: BrushImages {
+                selected: brush_images.keys().next().cloned(),
+                images: brush_images,
             },
             dragger: EditorDragger {
                 dragged_object: None,
@@ -81,26 +172,162 @@ impl Editor {
                 place_object: obj_images.keys().next().cloned(),
                 obj_image_assets: obj_images,
                 object_image_texture_ids: BTreeMap::new(),
+                preview_textures: BTreeMap::new(),
                 bitmap_image: None,
+                place_fixed: false,
+                collision: ObjectCollision::default(),
             },
             saver: EditorSaveLoader {
                 map_name: "New".to_string(),
                 map_file_names,
+                image_writer: ImageWriter::new(),
+                pending_chunks: vec![],
+                pending_saves: vec![],
+                finished_saves: vec![],
+                map_thumbnails: BTreeMap::new(),
+            },
+            stamper: EditorStamper {
+                stamp: stamps.keys().next().cloned(),
+                stamp_assets: stamps,
+                stamp_texture_ids: BTreeMap::new(),
+                copied: None,
+                stamp_name: "Stamp".to_string(),
+                selection: None,
+            },
+            selector: EditorSelector::new(),
+            beam: EditorBeam {
+                emitter: None,
+                heat_matter: MATTER_FIRE,
+                length: 256.0,
+                impulse: 0.05,
+                fired: None,
+                fired_step: None,
+            },
+            blueprint: EditorBlueprint::new(),
+            joiner: EditorJoiner {
+                kind: ObjectJointKind::Revolute,
+                first: None,
             },
+            filler: EditorFiller::new(),
+            undo: UndoHistory::new(),
+            ore_veins: OreVeins {
+                host: MATTER_ROCK,
+                ore: MATTER_LEAD,
+                frequency: 0.5,
+                vein_length: 16.0,
+                thickness: 0.5,
+                fill_empty: false,
+                seed: rand::thread_rng().gen(),
+            },
+            camera_pan_speed: 0.75,
         })
     }
 }
 
 impl Editor {
+    /// Toast `success` if a user triggered action succeeded, otherwise show its error in a dialog
+    pub fn report<T>(
+        &mut self,
+        result: Result<T>,
+        success: impl Into<String>,
+        failure: impl Into<String>,
+    ) -> Option<T> {
+        match result {
+            std::result::Result::Ok(value) => {
+                self.toasts.info(success);
+                Some(value)
+            }
+            Err(e) => {
+                self.error_dialog.push_error(failure, &e);
+                None
+            }
+        }
+    }
+
+    /// Switch to painting or placing the quick bar item
+    pub fn select_quick_bar_item(&mut self, item: &QuickBarItem) {
+        match item {
+            QuickBarItem::Matter(matter) => {
+                self.mode = EditorMode::Paint;
+                self.painter.matter = *matter;
+            }
+            QuickBarItem::Object(object) => {
+                self.mode = EditorMode::Place;
+                self.placer.place_object = Some(object.clone());
+            }
+        }
+    }
+
+    /// Sync matter textures & blueprint preview with definitions. Only new matters or ones whose
+    /// color changed get a new texture, others are reused even if their id shifted due to a
+    /// removal
     pub fn update_matter_gui_textures(
         &mut self,
         api: &mut EngineApi<InputAction>,
         simulation: &Simulation,
     ) {
-        for (_key, texture) in self.matter_texture_ids.iter() {
-            api.gui.unregister_user_image(*texture);
+        let mut unused = std::mem::take(&mut self.matter_texture_ids)
+            .into_iter()
+            .map(|(id, texture)| (self.matter_texture_colors[&id], texture))
+            .collect::<Vec<(u32, TextureId)>>();
+        self.matter_texture_colors.clear();
+        for matter in simulation.matter_definitions.definitions.iter() {
+            let texture_id = match unused.iter().position(|(color, _)| *color == matter.color) {
+                Some(index) => unused.swap_remove(index).1,
+                None => register_matter_gui_image(api, matter),
+            };
+            self.matter_texture_ids.insert(matter.id, texture_id);
+            self.matter_texture_colors.insert(matter.id, matter.color);
+        }
+        for (_color, texture) in unused {
+            api.gui.unregister_user_image(texture);
+        }
+        self.blueprint.update_preview(&simulation.matter_definitions);
+    }
+
+    /// Forget gui textures, e.g. when gui was recreated & they no longer exist
+    pub fn forget_gui_images(&mut self) {
+        self.matter_texture_ids.clear();
+        self.matter_texture_colors.clear();
+        self.placer.object_image_texture_ids.clear();
+        self.stamper.stamp_texture_ids.clear();
+        self.saver.map_thumbnails.clear();
+        self.undo.forget_gui_images();
+    }
+
+    /// Gui textures of palettes: matters, object images & stamps, and edit history thumbnails
+    pub fn gui_textures(&self) -> Vec<TextureId> {
+        self.matter_texture_ids
+            .values()
+            .chain(self.placer.object_image_texture_ids.values())
+            .chain(self.stamper.stamp_texture_ids.values())
+            .copied()
+            .chain(self.undo.thumbnail_textures())
+            .collect()
+    }
+
+    /// Rgba pixels & size of a palette texture, e.g. to register it to a pop-out window's gui
+    pub fn gui_image_data(
+        &self,
+        texture: TextureId,
+        simulation: &Simulation,
+    ) -> Option<(Vec<u8>, (u64, u64))> {
+        if let Some(id) = key_of(&self.matter_texture_ids, texture) {
+            let matter = simulation.matter_definitions.definitions.get(*id as usize)?;
+            return Some((gui_texture_rgba_data(matter, (24, 24)), (24, 24)));
         }
-        self.register_matter_gui_images(api, simulation);
+        let image = key_of(&self.placer.object_image_texture_ids, texture)
+            .and_then(|key| self.placer.obj_image_assets.get(key))
+            .or_else(|| {
+                key_of(&self.stamper.stamp_texture_ids, texture)
+                    .and_then(|key| self.stamper.stamp_assets.get(key))
+            })
+            .map(|image| image.as_ref())
+            .or_else(|| self.undo.thumbnail_image(texture))?;
+        Some((
+            image.data.clone(),
+            (image.width as u64, image.height as u64),
+        ))
     }
 
     pub fn register_gui_images(
@@ -108,7 +335,7 @@ impl Editor {
         api: &mut EngineApi<InputAction>,
         simulation: &Simulation,
     ) {
-        self.register_matter_gui_images(api, simulation);
+        self.update_matter_gui_textures(api, simulation);
         for (key, val) in self.placer.obj_image_assets.iter() {
             let texture_id = api.gui.register_user_image_from_bytes(
                 &val.data,
@@ -119,41 +346,20 @@ impl Editor {
                 .object_image_texture_ids
                 .insert(key.clone(), texture_id);
         }
-    }
-
-    fn register_matter_gui_images(
-        &mut self,
-        api: &mut EngineApi<InputAction>,
-        simulation: &Simulation,
-    ) {
-        let material_texture_dimensions = (24, 24);
-        simulation
-            .matter_definitions
-            .definitions
-            .iter()
-            .for_each(|matter| {
-                let image_byte_data = gui_texture_rgba_data(matter, material_texture_dimensions);
-                let texture_id = api.gui.register_user_image_from_bytes(
-                    &image_byte_data,
-                    (
-                        material_texture_dimensions.0 as u64,
-                        material_texture_dimensions.1 as u64,
-                    ),
-                    api.renderer.image_format(),
-                );
-                self.matter_texture_ids.insert(matter.id, texture_id);
-            });
+        let stamps = self.stamper.stamp_assets.keys().cloned().collect::<Vec<String>>();
+        for stamp in stamps {
+            self.stamper.register_gui_image(api, &stamp);
+        }
     }
 
     pub fn update(
         &mut self,
         api: &mut EngineApi<InputAction>,
         simulation: &mut Simulation,
-        is_running: &mut bool,
-        is_step: &mut bool,
     ) -> Result<()> {
-        self.handle_inputs(api, simulation, is_running, is_step)?;
-        if !*is_running {
+        self.handle_inputs(api, simulation)?;
+        self.undo.update_thumbnails(api, simulation);
+        if api.time.is_paused() {
             return Ok(());
         }
         // Obj dragging...
@@ -167,8 +373,6 @@ impl Editor {
         &mut self,
         api: &mut EngineApi<InputAction>,
         simulation: &mut Simulation,
-        is_running: &mut bool,
-        is_step: &mut bool,
     ) -> Result<()> {
         let EngineApi {
             ecs_world,
@@ -179,17 +383,34 @@ impl Editor {
         } = api;
         let input = &mut inputs[0];
         let camera = main_camera;
+        // Clicks & scrolls on gui windows are left to gui
+        let gui_wants_pointer = api.gui.context().wants_pointer_input();
+        let [left, right, middle] = [MouseLeft, MouseRight, MouseMiddle]
+            .map(|button| self.pointer_gate.state(input, button, gui_wants_pointer));
+        // Keys are left to gui while e.g. typing a name, unless hotkey modifier is held
+        let hotkeys = self
+            .key_gate
+            .is_open(input.modifiers, api.gui.context().wants_keyboard_input());
 
-        if input.is_action_held(InputAction::PaintMode) {
-            self.mode = EditorMode::Paint;
-        } else if input.is_action_held(InputAction::PlaceMode) {
-            self.mode = EditorMode::Place;
-        } else if input.is_action_held(InputAction::DragMode) {
-            self.mode = EditorMode::Drag;
-        } else if input.is_action_held(InputAction::ObjectPaintMode) {
-            self.mode = EditorMode::ObjectPaint;
+        if hotkeys {
+            if input.is_action_held(InputAction::PaintMode) {
+                self.mode = EditorMode::Paint;
+            } else if input.is_action_held(InputAction::PlaceMode) {
+                self.mode = EditorMode::Place;
+            } else if input.is_action_held(InputAction::DragMode) {
+                self.mode = EditorMode::Drag;
+            } else if input.is_action_held(InputAction::ObjectPaintMode) {
+                self.mode = EditorMode::ObjectPaint;
+            }
+        }
+        for slot in 0..QUICK_BAR_SIZE {
+            if hotkeys && input.is_action_activated(InputAction::QuickSelect(slot)) {
+                if let Some(item) = self.quick_bar.item(slot) {
+                    self.select_quick_bar_item(&item);
+                }
+            }
         }
-        if input.is_action_activated(InputAction::ToggleFullScreen) {
+        if hotkeys && input.is_action_activated(InputAction::ToggleFullScreen) {
             api.renderer.toggle_fullscreen();
         }
 
@@ -200,74 +421,233 @@ impl Editor {
 
         let mut draw_end_state = None;
         // Handle draw state
-        if self.mode == EditorMode::Paint || self.mode == EditorMode::ObjectPaint {
-            if input.button_state(MouseLeft) == Some(Activated) {
+        if matches!(
+            self.mode,
+            EditorMode::Paint | EditorMode::ObjectPaint | EditorMode::Blueprint
+        ) {
+            if left == Some(Activated) {
                 draw_end_state = self.draw_state.transition(
                     DrawTransition::Start(mouse_canvas_pos, self.painter.radius),
-                    self.painter.is_square,
+                    self.painter.shape == BrushShape::Square,
                 );
             }
-            if input.button_state(MouseLeft) == Some(Held) {
+            if left == Some(Held) {
                 draw_end_state = self.draw_state.transition(
                     DrawTransition::Draw(mouse_canvas_pos, self.painter.radius),
-                    self.painter.is_square,
+                    self.painter.shape == BrushShape::Square,
                 );
             }
-            if input.button_state(MouseLeft) == Some(Deactivated) {
+            if left == Some(Deactivated) {
                 draw_end_state = self.draw_state.transition(
                     DrawTransition::End(mouse_canvas_pos, self.painter.radius),
-                    self.painter.is_square,
+                    self.painter.shape == BrushShape::Square,
                 );
             }
         }
 
+        // Cells stroked this frame, including the last segment up to where the stroke ended
+        let stroke_line = match &draw_end_state {
+            Some(end_state) => Some(end_state.get_line()),
+            None => self.draw_state.started().then(|| self.draw_state.get_line()),
+        };
+
         // Matter painting
-        if self.mode == EditorMode::Paint && self.draw_state.started() {
-            if self.painter.is_square {
-                self.painter
-                    .paint_square_line(simulation, &self.draw_state.get_line())?;
-            } else {
-                self.painter
-                    .paint_round_line(simulation, &self.draw_state.get_line())?;
+        if self.mode == EditorMode::Paint && left == Some(Activated) {
+            self.quick_bar
+                .use_item(QuickBarItem::Matter(self.painter.matter));
+        }
+        if self.mode == EditorMode::Paint {
+            if let Some(line) = &stroke_line {
+                let painted = self.painter.paint_line(
+                    simulation,
+                    line,
+                    self.brush_images.selected_image(),
+                )?;
+                self.undo.record_paint(&painted, self.painter.matter);
+            }
+        }
+        if !matches!(left, Some(Activated) | Some(Held)) {
+            self.undo.end_stroke();
+        }
+
+        // Blueprint sketching with brush matter & radius
+        if self.mode == EditorMode::Blueprint {
+            if let Some(line) = &stroke_line {
+                self.blueprint.sketch(
+                    line,
+                    self.painter.matter,
+                    self.painter.radius,
+                    &simulation.matter_definitions,
+                );
             }
         }
 
         if self.mode == EditorMode::ObjectPaint {
             if let Some(end_state) = &draw_end_state {
-                self.placer.place_painted_object(
+                let entity = self.placer.place_painted_object(
                     ecs_world,
                     physics_world,
                     simulation,
                     end_state,
                 )?;
+                self.undo.push(EditAction::Spawn(entity));
             } else if self.draw_state.started() {
                 self.placer
                     .update_in_place_paint_object(simulation, &self.draw_state);
             }
         }
 
+        // Bug spawning
+        if hotkeys && input.is_action_activated(InputAction::SpawnBugs) {
+            let spawned =
+                simulation.spawn_bugs(mouse_canvas_pos, self.painter.radius, BUGS_PER_SPAWN)?;
+            // Fewer are spawned also when some positions fall outside simulated area
+            if spawned < BUGS_PER_SPAWN && simulation.num_bugs()? == MAX_BUGS {
+                self.toasts
+                    .info(format!("Bug limit of {} reached", MAX_BUGS));
+            }
+        }
+
         // Object placement
-        if self.mode == EditorMode::Place && input.button_state(MouseLeft) == Some(Activated) {
-            self.placer
-                .place_object(ecs_world, physics_world, simulation, mouse_world_pos)?;
+        if self.mode == EditorMode::Place && left == Some(Activated) {
+            if let Some(object) = &self.placer.place_object {
+                self.quick_bar
+                    .use_item(QuickBarItem::Object(object.clone()));
+            }
+            if let Some(entity) =
+                self.placer
+                    .place_object(ecs_world, physics_world, simulation, mouse_world_pos)?
+            {
+                self.undo.push(EditAction::Spawn(entity));
+            }
         }
 
         // Object removal
         if (self.mode == EditorMode::Place || self.mode == EditorMode::ObjectPaint)
-            && input.button_state(MouseRight) == Some(Activated)
+            && right == Some(Activated)
         {
             if let Some((rb, entity)) = physics_entity_at_pos(physics_world, mouse_world_pos) {
-                if rb.is_dynamic() {
+                if rb.is_dynamic() || ecs_world.get::<FixedObject>(entity).is_ok() {
+                    let snapshot = ObjectSnapshot::new(ecs_world, entity)?;
                     remove_physics_entity(ecs_world, physics_world, entity);
+                    self.undo.push(EditAction::Despawn(snapshot));
+                }
+            }
+        }
+
+        // Stamp placement & copying matter for new stamps
+        if self.mode == EditorMode::Stamp {
+            if left == Some(Activated) {
+                self.stamper.place_stamp(simulation, mouse_canvas_pos)?;
+            }
+            match right {
+                Some(Activated) => {
+                    self.stamper.selection = Some((mouse_canvas_pos, mouse_canvas_pos));
+                }
+                Some(Held) => {
+                    if let Some((_, end)) = &mut self.stamper.selection {
+                        *end = mouse_canvas_pos;
+                    }
+                }
+                Some(Deactivated) => self.stamper.finish_selection(simulation)?,
+                _ => (),
+            }
+        }
+
+        // Region copying & pasting
+        if self.mode == EditorMode::Select {
+            match left {
+                Some(Activated) => {
+                    self.selector.selection = Some((mouse_canvas_pos, mouse_canvas_pos));
+                }
+                Some(Held) => {
+                    if let Some((_, end)) = &mut self.selector.selection {
+                        *end = mouse_canvas_pos;
+                    }
+                }
+                Some(Deactivated) => self.selector.finish_selection(simulation)?,
+                _ => (),
+            }
+            if right == Some(Activated) {
+                let cells = self.selector.paste(simulation, mouse_canvas_pos)?;
+                if !cells.is_empty() {
+                    self.undo.push(EditAction::Paint(cells));
+                }
+            }
+        }
+
+        // Rectangle, polygon & bucket fills with brush matter
+        if self.mode == EditorMode::Fill {
+            let matter = self.painter.matter;
+            let filled = match self.filler.tool {
+                FillTool::Rect => match left {
+                    Some(Activated) => {
+                        self.filler.rect = Some((mouse_canvas_pos, mouse_canvas_pos));
+                        vec![]
+                    }
+                    Some(Held) => {
+                        if let Some((_, end)) = &mut self.filler.rect {
+                            *end = mouse_canvas_pos;
+                        }
+                        vec![]
+                    }
+                    Some(Deactivated) => self.filler.finish_rect(simulation, matter)?,
+                    _ => vec![],
+                },
+                FillTool::Polygon => {
+                    let closed = left == Some(Activated)
+                        && self.filler.add_polygon_point(mouse_canvas_pos);
+                    if closed || right == Some(Activated) {
+                        self.filler.finish_polygon(simulation, matter)?
+                    } else {
+                        vec![]
+                    }
+                }
+                FillTool::Bucket if left == Some(Activated) => {
+                    self.filler.fill_bucket(simulation, mouse_canvas_pos, matter)?
                 }
+                FillTool::Bucket => vec![],
+            };
+            if !filled.is_empty() {
+                self.undo.push(EditAction::Paint(filled));
+            }
+        } else {
+            self.filler.cancel();
+        }
+
+        // Beam emitter placement & firing, while simulation runs
+        if self.mode == EditorMode::Beam {
+            if right == Some(Activated) {
+                self.beam.emitter = Some(mouse_world_pos);
+            }
+            if matches!(left, Some(Activated) | Some(Held)) && !api.time.is_paused() {
+                self.beam.fire(simulation, physics_world, mouse_world_pos)?;
+            } else {
+                self.beam.fired = None;
+            }
+        } else {
+            self.beam.fired = None;
+        }
+
+        // Object anchoring to terrain
+        if self.mode == EditorMode::Drag && right == Some(Activated) {
+            simulation.toggle_anchor_at(mouse_world_pos);
+        }
+
+        // Object joining with hinges & ropes
+        if self.mode == EditorMode::Joint {
+            if left == Some(Activated) {
+                self.joiner.pick(ecs_world, physics_world, simulation, mouse_world_pos)?;
             }
+            if right == Some(Activated) {
+                self.joiner.release(physics_world, simulation, mouse_world_pos);
+            }
+        } else {
+            self.joiner.first = None;
         }
 
         // Object dragging
-        if self.mode == EditorMode::Drag
-            && (input.button_state(MouseLeft) == Some(Activated)
-                || input.button_state(MouseLeft) == Some(Held))
-        {
+        if self.mode == EditorMode::Drag && (left == Some(Activated) || left == Some(Held)) {
             if self.dragger.dragged_object.is_none() {
                 self.dragger
                     .set_dragged_object(ecs_world, physics_world, mouse_world_pos);
@@ -277,25 +657,50 @@ impl Editor {
         }
 
         // Simulation pausing & unpausing
-        if input.is_action_activated(InputAction::Pause) {
-            *is_running = !*is_running;
+        if hotkeys && input.is_action_activated(InputAction::Pause) {
+            api.time.toggle_paused();
         }
-        if input.is_action_activated(InputAction::Step) {
-            *is_step = true;
+        if hotkeys && input.is_action_activated(InputAction::Step) {
+            api.time.request_step();
         }
+        let is_undo =
+            hotkeys && input.modifiers.ctrl() && input.is_action_activated(InputAction::Undo);
+        let is_redo =
+            hotkeys && input.modifiers.ctrl() && input.is_action_activated(InputAction::Redo);
 
         // Editor movement
-        if input.button_state(MouseMiddle) == Some(Activated)
-            || input.button_state(MouseMiddle) == Some(Held)
-        {
+        if middle == Some(Activated) || middle == Some(Held) {
             let delta = input.mouse_delta();
             if delta.x != 0.0 || delta.y != 0.0 {
-                camera.translate(Vector2::new(-delta.x, delta.y) * 50.0 / 2000.0);
+                let translation = camera.screen_dir_to_world(Vector2::new(-delta.x, delta.y));
+                camera.translate(translation * 50.0 / 2000.0);
+            }
+        }
+        // Pan keys are left to gui while e.g. typing a name
+        if !api.gui.context().wants_keyboard_input() {
+            let direction = CAMERA_PAN_KEYS
+                .iter()
+                .filter(|(key, _)| {
+                    matches!(input.button_state(Key(*key)), Some(Activated) | Some(Held))
+                })
+                .fold(Vector2::new(0.0, 0.0), |sum, (_, dir)| sum + *dir);
+            if direction.x != 0.0 || direction.y != 0.0 {
+                // Visible world height is 2.0 / zoom, so panning looks as fast at any zoom
+                let screens = self.camera_pan_speed * api.time.dt() as f32 / 1000.0;
+                let direction = camera.screen_dir_to_world(direction);
+                camera.translate(direction * screens * 2.0 / camera.zoom_level());
             }
         }
+        if hotkeys && input.is_action_activated(InputAction::RotateCamera) {
+            camera.rotate(std::f32::consts::FRAC_PI_2);
+        }
+        if hotkeys && input.is_action_activated(InputAction::CenterCamera) {
+            camera.set_pos(Vector2::new(0.0, 0.0));
+            camera.zoom_to_fit_canvas(WORLD_UNIT_SIZE);
+            camera.set_rotation(0.0);
+        }
 
-        let mouse = input.mouse_position_normalized();
-        if mouse.x > 0.2 && mouse.x < 0.8 && mouse.y > 0.2 && mouse.y < 0.8 {
+        if !gui_wants_pointer {
             // Editor zoom
             let scroll = input.mouse_scroll();
             let zoom = 1.1;
@@ -305,6 +710,13 @@ impl Editor {
                 camera.zoom(1.0 / zoom);
             }
         }
+
+        // Undo & redo
+        if is_undo {
+            self.undo.undo(api, simulation)?;
+        } else if is_redo {
+            self.undo.redo(api, simulation)?;
+        }
         Ok(())
     }
 
@@ -313,56 +725,146 @@ impl Editor {
         draw_pass: &mut DrawPass,
         format: Format,
     ) -> Result<()> {
-        let device = draw_pass.device();
         let bitmap_image = self.placer.bitmap_image.as_ref().unwrap();
-        let color_data = CpuAccessibleBuffer::from_iter(
-            device.clone(),
-            BufferUsage::all(),
-            false,
-            bitmap_image.data.clone(),
-        )?;
-        let image = create_device_image_with_usage(
-            draw_pass.queue().clone(),
-            [bitmap_image.width, bitmap_image.height],
+        draw_bitmap_image(
+            draw_pass,
+            bitmap_image,
+            self.draw_state.pixels_world_pos(),
             format,
-            ImageUsage {
-                sampled: true,
-                storage: true,
-                transfer_destination: true,
-                ..ImageUsage::none()
-            },
-        )?;
-        // Copy data to image
-        let mut builder = AutoCommandBufferBuilder::primary(
-            device.clone(),
-            draw_pass.queue().family(),
-            CommandBufferUsage::OneTimeSubmit,
-        )?;
-
-        builder.copy_buffer_to_image(color_data, image.image().clone())?;
-        let command_buffer = builder.build()?;
-        let finished = command_buffer.execute(draw_pass.queue().clone())?;
-        let _fut = finished.then_signal_fence_and_flush()?;
-
-        //---> render
-        let world_pos = self.draw_state.pixels_world_pos();
-        let world_width = *CELL_UNIT_SIZE * bitmap_image.width as f32 * 0.5;
-        let world_height = *CELL_UNIT_SIZE * bitmap_image.height as f32 * 0.5;
+        )
+    }
+}
+
+/// Draw image centered at `world_pos`, one image pixel per cell
+pub fn draw_bitmap_image(
+    draw_pass: &mut DrawPass,
+    bitmap_image: &BitmapImage,
+    world_pos: Vector2<f32>,
+    format: Format,
+) -> Result<()> {
+    let device = draw_pass.device();
+    let color_data = CpuAccessibleBuffer::from_iter(
+        device.clone(),
+        BufferUsage::all(),
+        false,
+        bitmap_image.data.clone(),
+    )?;
+    let image = create_device_image_with_usage(
+        draw_pass.queue().clone(),
+        [bitmap_image.width, bitmap_image.height],
+        format,
+        ImageUsage {
+            sampled: true,
+            storage: true,
+            transfer_destination: true,
+            ..ImageUsage::none()
+        },
+    )?;
+    // Copy data to image
+    let mut builder = AutoCommandBufferBuilder::primary(
+        device.clone(),
+        draw_pass.queue().family(),
+        CommandBufferUsage::OneTimeSubmit,
+    )?;
+
+    builder.copy_buffer_to_image(color_data, image.image().clone())?;
+    let command_buffer = builder.build()?;
+    let finished = command_buffer.execute(draw_pass.queue().clone())?;
+    let _fut = finished.then_signal_fence_and_flush()?;
+
+    //---> render
+    let world_width = *CELL_UNIT_SIZE * bitmap_image.width as f32 * 0.5;
+    let world_height = *CELL_UNIT_SIZE * bitmap_image.height as f32 * 0.5;
+    draw_pass.draw_texture(
+        world_pos,
+        world_width,
+        world_height,
+        0.0,
+        image,
+        false,
+        true,
+        Filter::Nearest,
+    )
+}
+
+/// Mipmapped texture of a preview image, uploaded on first draw & reused until the image changes
+pub struct PreviewTexture {
+    texture: Option<MipmappedImageView>,
+}
+
+impl PreviewTexture {
+    pub fn new() -> PreviewTexture {
+        PreviewTexture {
+            texture: None,
+        }
+    }
+
+    /// Forget the texture, so it's uploaded from the changed image on next draw
+    pub fn invalidate(&mut self) {
+        self.texture = None;
+    }
+
+    /// Draw `image` centered at `world_pos` like `draw_bitmap_image`
+    pub fn draw(
+        &mut self,
+        draw_pass: &mut DrawPass,
+        image: &BitmapImage,
+        world_pos: Vector2<f32>,
+        format: Format,
+    ) -> Result<()> {
+        let texture = match &self.texture {
+            Some(texture) => texture.clone(),
+            None => {
+                let (texture, future) = create_mipmapped_image(
+                    draw_pass.queue().clone(),
+                    [image.width, image.height],
+                    format,
+                    image.data.clone(),
+                )?;
+                future.then_signal_fence_and_flush()?.wait(None)?;
+                self.texture = Some(texture.clone());
+                texture
+            }
+        };
         draw_pass.draw_texture(
             world_pos,
-            world_width,
-            world_height,
+            *CELL_UNIT_SIZE * image.width as f32 * 0.5,
+            *CELL_UNIT_SIZE * image.height as f32 * 0.5,
             0.0,
-            image,
+            texture,
             false,
             true,
+            Filter::Nearest,
         )
     }
 }
 
+fn key_of<K>(textures: &BTreeMap<K, TextureId>, texture: TextureId) -> Option<&K> {
+    textures
+        .iter()
+        .find(|(_, t)| **t == texture)
+        .map(|(key, _)| key)
+}
+
+fn register_matter_gui_image(
+    api: &mut EngineApi<InputAction>,
+    matter: &MatterDefinition,
+) -> TextureId {
+    let material_texture_dimensions = (24, 24);
+    let image_byte_data = gui_texture_rgba_data(matter, material_texture_dimensions);
+    api.gui.register_user_image_from_bytes(
+        &image_byte_data,
+        (
+            material_texture_dimensions.0 as u64,
+            material_texture_dimensions.1 as u64,
+        ),
+        api.renderer.image_format(),
+    )
+}
+
 pub fn gui_texture_rgba_data(matter: &MatterDefinition, dimensions: (usize, usize)) -> Vec<u8> {
     (0..(dimensions.0 * dimensions.1))
-        .map(|_| variated_color(matter.color.to_be_bytes()))
+        .map(|_| variated_color(Color32::from_u32_rgba(matter.color).to_rgba()))
         .flatten()
         .collect()
 }
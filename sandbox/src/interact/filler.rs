@@ -0,0 +1,184 @@
+use anyhow::*;
+use cgmath::Vector2;
+
+use crate::{
+    interact::selector::selection_rect,
+    sim::{Shape, Simulation},
+};
+
+/// Cells from the first polygon vertex within which a click closes the polygon
+const POLYGON_CLOSE_DISTANCE: i32 = 3;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FillTool {
+    /// Drag to fill a rectangle
+    Rect,
+    /// Click vertices, closing the polygon fills it
+    Polygon,
+    /// Replace the connected region of clicked matter
+    Bucket,
+}
+
+impl FillTool {
+    pub const ALL: [FillTool; 3] = [FillTool::Rect, FillTool::Polygon, FillTool::Bucket];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FillTool::Rect => "Rectangle",
+            FillTool::Polygon => "Polygon",
+            FillTool::Bucket => "Bucket",
+        }
+    }
+}
+
+/// Canvas cells of rectangle `min..=max`
+pub fn rect_cells(min: Vector2<i32>, max: Vector2<i32>) -> Vec<Vector2<i32>> {
+    (min.y..=max.y)
+        .flat_map(|y| (min.x..=max.x).map(move |x| Vector2::new(x, y)))
+        .collect()
+}
+
+/// Canvas cells inside polygon & on its outline, so thin polygons still fill their edges
+pub fn polygon_cells(vertices: &[Vector2<i32>]) -> Vec<Vector2<i32>> {
+    if vertices.len() < 3 {
+        return vec![];
+    }
+    let first = vertices[0];
+    let relative = vertices
+        .iter()
+        .map(|v| (*v - first).cast::<f32>().unwrap())
+        .collect();
+    let mut cells = Shape::Polygon(relative).cells(first);
+    for (i, &start) in vertices.iter().enumerate() {
+        let end = vertices[(i + 1) % vertices.len()];
+        cells.extend(
+            line_drawing::Bresenham::new((start.x, start.y), (end.x, end.y))
+                .map(|(x, y)| Vector2::new(x, y)),
+        );
+    }
+    cells.sort_by_key(|cell| (cell.y, cell.x));
+    cells.dedup();
+    cells
+}
+
+/// Fills areas with matter: dragged rectangles, clicked polygons & connected regions of one
+/// matter
+pub struct EditorFiller {
+    pub tool: FillTool,
+    /// Canvas positions where rectangle drag started and where it is now
+    pub rect: Option<(Vector2<i32>, Vector2<i32>)>,
+    /// Clicked polygon vertices in canvas positions
+    pub polygon: Vec<Vector2<i32>>,
+    /// Whether rectangle & polygon fills replace matter instead of filling only empty cells
+    pub replace: bool,
+}
+
+impl EditorFiller {
+    pub fn new() -> EditorFiller {
+        EditorFiller {
+            tool: FillTool::Rect,
+            rect: None,
+            polygon: vec![],
+            replace: false,
+        }
+    }
+
+    pub fn rect_bounds(&self) -> Option<(Vector2<i32>, Vector2<i32>)> {
+        self.rect.map(|(start, end)| selection_rect(start, end))
+    }
+
+    /// Drop unfinished rectangle & polygon, e.g. when leaving fill mode
+    pub fn cancel(&mut self) {
+        self.rect = None;
+        self.polygon.clear();
+    }
+
+    /// Fill dragged rectangle. Returns filled cells with their matter before & after
+    pub fn finish_rect(
+        &mut self,
+        simulation: &mut Simulation,
+        matter: u32,
+    ) -> Result<Vec<(Vector2<i32>, u32, u32)>> {
+        let cells = match self.rect_bounds() {
+            Some((min, max)) => rect_cells(min, max),
+            None => return Ok(vec![]),
+        };
+        self.rect = None;
+        self.fill(simulation, &cells, matter)
+    }
+
+    /// Add a polygon vertex. Returns true instead if `pos` is near the first vertex of a polygon
+    /// that can be closed
+    pub fn add_polygon_point(&mut self, pos: Vector2<i32>) -> bool {
+        if let Some(first) = self.polygon.first() {
+            let offset = pos - *first;
+            let closes = offset.x.abs().max(offset.y.abs()) <= POLYGON_CLOSE_DISTANCE;
+            if closes && self.polygon.len() >= 3 {
+                return true;
+            }
+        }
+        self.polygon.push(pos);
+        false
+    }
+
+    /// Fill clicked polygon, dropping it if it has less than three vertices. Returns filled cells
+    /// with their matter before & after
+    pub fn finish_polygon(
+        &mut self,
+        simulation: &mut Simulation,
+        matter: u32,
+    ) -> Result<Vec<(Vector2<i32>, u32, u32)>> {
+        let cells = polygon_cells(&std::mem::take(&mut self.polygon));
+        self.fill(simulation, &cells, matter)
+    }
+
+    /// Replace the connected region at `pos` with matter. Returns filled cells with their matter
+    /// before & after
+    pub fn fill_bucket(
+        &self,
+        simulation: &mut Simulation,
+        pos: Vector2<i32>,
+        matter: u32,
+    ) -> Result<Vec<(Vector2<i32>, u32, u32)>> {
+        Ok(simulation
+            .flood_fill(pos, matter)?
+            .into_iter()
+            .map(|(pos, previous)| (pos, previous, matter))
+            .collect())
+    }
+
+    fn fill(
+        &self,
+        simulation: &mut Simulation,
+        cells: &[Vector2<i32>],
+        matter: u32,
+    ) -> Result<Vec<(Vector2<i32>, u32, u32)>> {
+        Ok(simulation
+            .fill_cells(cells, matter, self.replace)?
+            .into_iter()
+            .map(|(pos, previous)| (pos, previous, matter))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polygon_cells_include_outline() {
+        let square = [
+            Vector2::new(0, 0),
+            Vector2::new(3, 0),
+            Vector2::new(3, 3),
+            Vector2::new(0, 3),
+        ];
+        assert_eq!(polygon_cells(&square), rect_cells(square[0], square[2]));
+        // A sliver has no cell centers inside, but its edges are filled
+        let sliver = [Vector2::new(0, 0), Vector2::new(4, 0), Vector2::new(0, 1)];
+        let cells = polygon_cells(&sliver);
+        assert!(cells.contains(&Vector2::new(4, 0)));
+        assert!(cells.contains(&Vector2::new(0, 1)));
+        assert!(polygon_cells(&sliver[..2]).is_empty());
+    }
+}
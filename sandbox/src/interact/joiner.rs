@@ -0,0 +1,75 @@
+use anyhow::*;
+use cgmath::Vector2;
+use corrode::{api::physics_entity_at_pos, physics::PhysicsWorld};
+use hecs::{Entity, World};
+
+use crate::{
+    object::FixedObject,
+    sim::{ObjectJointKind, Simulation},
+};
+
+/// Joins two clicked objects with a hinge or a rope
+pub struct EditorJoiner {
+    pub kind: ObjectJointKind,
+    /// First clicked object & world position, joined to the next clicked object
+    pub first: Option<(Entity, Vector2<f32>)>,
+}
+
+impl EditorJoiner {
+    /// Pick object at `world_pos`, joining it to the previously picked object. Ropes are tied to
+    /// both clicked points, hinges are placed at the second click
+    pub fn pick(
+        &mut self,
+        ecs_world: &World,
+        physics_world: &mut PhysicsWorld,
+        simulation: &mut Simulation,
+        world_pos: Vector2<f32>,
+    ) -> Result<()> {
+        let entity = match physics_entity_at_pos(physics_world, world_pos) {
+            Some((rb, entity))
+                if rb.is_dynamic() || ecs_world.get::<FixedObject>(entity).is_ok() =>
+            {
+                entity
+            }
+            _ => {
+                self.first = None;
+                return Ok(());
+            }
+        };
+        match self.first.take() {
+            // Clicking the first object again cancels
+            Some((first, _)) if first == entity => Ok(()),
+            Some((first, first_pos)) => {
+                let world_anchors = match self.kind {
+                    ObjectJointKind::Revolute => [world_pos, world_pos],
+                    ObjectJointKind::Rope => [first_pos, world_pos],
+                };
+                simulation.join_objects(
+                    ecs_world,
+                    physics_world,
+                    [first, entity],
+                    world_anchors,
+                    self.kind,
+                )
+            }
+            None => {
+                self.first = Some((entity, world_pos));
+                Ok(())
+            }
+        }
+    }
+
+    /// Remove joints of the object at `world_pos`
+    pub fn release(
+        &mut self,
+        physics_world: &mut PhysicsWorld,
+        simulation: &mut Simulation,
+        world_pos: Vector2<f32>,
+    ) {
+        self.first = None;
+        let entity = physics_entity_at_pos(physics_world, world_pos).map(|(_, entity)| entity);
+        if let Some(entity) = entity {
+            simulation.remove_object_joints(physics_world, entity);
+        }
+    }
+}
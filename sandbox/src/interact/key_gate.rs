@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use winit::event::ModifiersState;
+
+/// Modifier that lets tool hotkeys through while gui takes keyboard input, e.g. Alt + 1 switches
+/// to paint mode while a name field has focus
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HotkeyModifier {
+    Alt,
+    Ctrl,
+    Shift,
+}
+
+impl HotkeyModifier {
+    pub const ALL: [HotkeyModifier; 3] =
+        [HotkeyModifier::Alt, HotkeyModifier::Ctrl, HotkeyModifier::Shift];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HotkeyModifier::Alt => "Alt",
+            HotkeyModifier::Ctrl => "Ctrl",
+            HotkeyModifier::Shift => "Shift",
+        }
+    }
+
+    fn is_held(&self, modifiers: ModifiersState) -> bool {
+        match self {
+            HotkeyModifier::Alt => modifiers.alt(),
+            HotkeyModifier::Ctrl => modifiers.ctrl(),
+            HotkeyModifier::Shift => modifiers.shift(),
+        }
+    }
+}
+
+/// Keeps tool hotkeys from firing while typing in gui, e.g. number keys in a map name don't
+/// switch modes. Hotkeys with `modifier` held fire regardless
+pub struct KeyGate {
+    /// None leaves hotkeys to gui whenever it takes keyboard input. Set from
+    /// `AppSettings::hotkey_modifier` each frame
+    pub modifier: Option<HotkeyModifier>,
+}
+
+impl KeyGate {
+    pub fn new() -> KeyGate {
+        KeyGate {
+            modifier: Some(HotkeyModifier::Alt),
+        }
+    }
+
+    /// Whether hotkeys reach tools with `modifiers` held
+    pub fn is_open(&self, modifiers: ModifiersState, gui_wants_keyboard: bool) -> bool {
+        !gui_wants_keyboard
+            || self
+                .modifier
+                .map_or(false, |modifier| modifier.is_held(modifiers))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_gate_lets_modifier_through_while_typing() {
+        let mut gate = KeyGate::new();
+        let none = ModifiersState::empty();
+        assert!(gate.is_open(none, false));
+        assert!(!gate.is_open(none, true));
+        assert!(gate.is_open(ModifiersState::ALT, true));
+        assert!(!gate.is_open(ModifiersState::SHIFT, true));
+        gate.modifier = None;
+        assert!(!gate.is_open(ModifiersState::ALT, true));
+    }
+}
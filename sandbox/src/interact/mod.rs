@@ -1,13 +1,37 @@
+mod beam;
+mod blueprint;
+mod brush;
+mod brush_fill;
 mod dragger;
 mod draw_state;
 mod editor;
+mod filler;
+mod joiner;
+mod key_gate;
 mod painter;
 mod placer;
+mod pointer_gate;
+mod quick_bar;
 mod saver;
+mod selector;
+mod stamper;
+mod undo;
 
+pub use beam::*;
+pub use blueprint::*;
+pub use brush::*;
+pub use brush_fill::*;
 pub use dragger::*;
 pub use draw_state::*;
 pub use editor::*;
+pub use filler::*;
+pub use joiner::*;
+pub use key_gate::*;
 pub use painter::*;
 pub use placer::*;
+pub use pointer_gate::*;
+pub use quick_bar::*;
 pub use saver::*;
+pub use selector::*;
+pub use stamper::*;
+pub use undo::*;
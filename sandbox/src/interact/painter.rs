@@ -1,28 +1,146 @@
 use anyhow::*;
-use cgmath::Vector2;
+use cgmath::{InnerSpace, Vector2};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::sim::Simulation;
+use crate::{
+    interact::{falloff_chance, BrushFill, BrushPattern, BrushShape},
+    sim::Simulation,
+    utils::{cell_hash, BitmapImage},
+};
 
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct EditorPainter {
     pub matter: u32,
     pub radius: f32,
-    pub is_square: bool,
+    #[serde(default)]
+    pub shape: BrushShape,
+    /// Rotation of line & triangle brushes in degrees
+    #[serde(default)]
+    pub angle: f32,
+    /// 0.0 - 1.0, how much fewer cells are painted towards brush edge
+    #[serde(default)]
+    pub falloff: f32,
+    /// 0.0 - 1.0, chance of spray brush painting a cell each step
+    #[serde(default = "default_spray_density")]
+    pub spray_density: f32,
+    /// 0.0 - 1.0, matter harder than this takes more passes to erase
+    #[serde(default = "default_strength")]
+    pub strength: f32,
+    pub fill: BrushFill,
+    /// Distance between stamps of round & square brushes along strokes, relative to radius
+    #[serde(default = "default_stamp_spacing")]
+    pub stamp_spacing: f32,
+}
+
+fn default_spray_density() -> f32 {
+    0.05
+}
+
+fn default_strength() -> f32 {
+    1.0
+}
+
+fn default_stamp_spacing() -> f32 {
+    0.25
+}
+
+/// Cells of `line` to stamp a brush at, about `spacing` cells apart. Line ends are always
+/// stamped, so lines of consecutive frames join however far the mouse moved in between
+pub fn spaced_stamps(line: &[Vector2<i32>], spacing: f32) -> Vec<Vector2<i32>> {
+    let mut stamps = vec![];
+    let mut travelled = 0.0;
+    for (i, &pos) in line.iter().enumerate() {
+        if i > 0 {
+            travelled += (pos - line[i - 1]).cast::<f32>().unwrap().magnitude();
+        }
+        if i == 0 || i == line.len() - 1 || travelled >= spacing {
+            stamps.push(pos);
+            travelled = 0.0;
+        }
+    }
+    stamps
 }
 
 impl EditorPainter {
-    pub fn paint_round_line(
-        &mut self,
+    /// Paint brush along the line. `image` is the brush of image brushes. Returns painted cells &
+    /// their previous matter
+    pub fn paint_line(
+        &self,
         simulation: &mut Simulation,
         line: &[Vector2<i32>],
-    ) -> Result<()> {
-        simulation.paint_round(line, self.matter, self.radius)
+        image: Option<&BitmapImage>,
+    ) -> Result<Vec<(Vector2<i32>, u32)>> {
+        let EditorPainter {
+            matter,
+            radius,
+            shape,
+            angle,
+            falloff,
+            spray_density,
+            strength,
+            fill,
+            stamp_spacing,
+        } = *self;
+        // Falloff & weak erasing pick cells by chance, so each stamp paints more of the stroke
+        let is_deterministic =
+            falloff <= 0.0 && (matter != simulation.matter_definitions.empty || strength >= 1.0);
+        // Deterministic round & square stamps overlap enough to skip cells, others could leave
+        // gaps or thin out
+        let stamps;
+        let line: &[Vector2<i32>] = match shape {
+            BrushShape::Round | BrushShape::Square if is_deterministic => {
+                stamps = spaced_stamps(line, (radius * stamp_spacing).max(1.0));
+                &stamps
+            }
+            _ => line,
+        };
+        // Plain round & square strokes are painted on gpu, where cpu cost doesn't grow with
+        // radius. Falloff, fill patterns & weak erasing need to decide per cell on cpu
+        let is_plain = matches!(shape, BrushShape::Round | BrushShape::Square)
+            && is_deterministic
+            && fill.pattern == BrushPattern::Solid;
+        if is_plain {
+            return simulation.queue_paint_line(line, matter, radius, shape == BrushShape::Square);
+        }
+        let angle = angle.to_radians();
+        let mut rng = rand::thread_rng();
+        let fills = |pos: Vector2<i32>, center: Vector2<i32>| {
+            let offset = pos - center;
+            let distance = Vector2::new(offset.x as f32, offset.y as f32).magnitude();
+            let chance = falloff_chance(distance, radius, falloff);
+            let covers = if shape == BrushShape::Spray {
+                rng.gen::<f32>() < chance * spray_density
+            } else {
+                // Hashed by cell, so falloff edge doesn't flicker while painting in place
+                shape.covers(offset, radius, angle, image) && cell_hash(pos.x, pos.y, 1) < chance
+            };
+            covers && fill.fills(pos)
+        };
+        match shape {
+            BrushShape::Square | BrushShape::Image => {
+                simulation.paint_square(line, matter, (radius * 2.0) as i32, strength, fills)
+            }
+            _ => simulation.paint_round(line, matter, radius, strength, fills),
+        }
     }
+}
 
-    pub fn paint_square_line(
-        &mut self,
-        simulation: &mut Simulation,
-        line: &[Vector2<i32>],
-    ) -> Result<()> {
-        simulation.paint_square(line, self.matter, (self.radius * 2.0) as i32)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spaced_stamps_keep_line_ends() {
+        let line = (0..=10).map(|x| Vector2::new(x, 0)).collect::<Vec<_>>();
+        let stamps = spaced_stamps(&line, 4.0);
+        assert_eq!(stamps, vec![
+            Vector2::new(0, 0),
+            Vector2::new(4, 0),
+            Vector2::new(8, 0),
+            Vector2::new(10, 0),
+        ]);
+        assert_eq!(spaced_stamps(&line, 1.0), line);
+        assert_eq!(spaced_stamps(&line[..1], 4.0), vec![Vector2::new(0, 0)]);
     }
 }
@@ -1,15 +1,21 @@
-use std::{collections::BTreeMap, env::current_dir, fs, sync::Arc};
+use std::{collections::BTreeMap, fs, sync::Arc};
 
 use anyhow::*;
 use cgmath::Vector2;
-use corrode::physics::PhysicsWorld;
+use corrode::{
+    physics::PhysicsWorld,
+    renderer::{create_mipmapped_image, MipmappedImageView},
+};
 use egui::TextureId;
-use hecs::World;
+use hecs::{Entity, World};
+use vulkano::{device::Queue, format::Format, sync::GpuFuture};
 
 use crate::{
+    data_dirs::DATA_DIRS,
     interact::{variated_color, CanvasDrawState},
+    object::{make_pixel_object_fixed, set_pixel_object_collision, ObjectCollision},
     sim::{world_pos_inside_canvas, Simulation},
-    utils::{load_image_from_file_bytes, BitmapImage},
+    utils::{load_bitmap_image_from_path, BitmapImage, Color32},
 };
 
 pub struct EditorPlacer {
@@ -17,22 +23,30 @@ pub struct EditorPlacer {
     pub place_object: Option<String>,
     pub obj_image_assets: BTreeMap<String, Arc<BitmapImage>>,
     pub object_image_texture_ids: BTreeMap<String, TextureId>,
+    /// Mipmapped textures of object images previewed at mouse, by file name. Image is kept to
+    /// tell when it was replaced
+    pub preview_textures: BTreeMap<String, (Arc<BitmapImage>, MipmappedImageView)>,
     pub bitmap_image: Option<BitmapImage>,
+    /// Place objects with a fixed body, e.g. platforms
+    pub place_fixed: bool,
+    /// Collision groups of placed objects
+    pub collision: ObjectCollision,
 }
 
 impl EditorPlacer {
+    /// Place selected object at mouse. Returns the placed object, if any
     pub fn place_object(
         &self,
         ecs_world: &mut World,
         physics_world: &mut PhysicsWorld,
         simulation: &mut Simulation,
         mouse_world_pos: Vector2<f32>,
-    ) -> Result<()> {
+    ) -> Result<Option<Entity>> {
         if self.place_object.is_none() {
-            return Ok(());
+            return Ok(None);
         }
         if world_pos_inside_canvas(mouse_world_pos, simulation.camera_pos) {
-            simulation.add_dynamic_pixel_object(
+            let entity = simulation.add_dynamic_pixel_object(
                 ecs_world,
                 physics_world,
                 self.obj_image_assets
@@ -44,9 +58,52 @@ impl EditorPlacer {
                 0.0,
                 0.0,
             )?;
+            if self.place_fixed {
+                make_pixel_object_fixed(ecs_world, &mut physics_world.physics, entity)?;
+            }
+            set_pixel_object_collision(
+                ecs_world,
+                &mut physics_world.physics,
+                entity,
+                self.collision,
+            )?;
+            return Ok(Some(entity));
         }
 
-        Ok(())
+        Ok(None)
+    }
+
+    /// Texture & image of selected object for previewing it at mouse, created on first use
+    pub fn preview_texture(
+        &mut self,
+        queue: Arc<Queue>,
+        format: Format,
+    ) -> Result<Option<(MipmappedImageView, Arc<BitmapImage>)>> {
+        let (name, image) = match self
+            .place_object
+            .as_ref()
+            .and_then(|name| Some((name, self.obj_image_assets.get(name)?)))
+        {
+            Some((name, image)) => (name.clone(), image.clone()),
+            None => return Ok(None),
+        };
+        match self.preview_textures.get(&name) {
+            Some((texture_image, texture)) if Arc::ptr_eq(texture_image, &image) => {
+                Ok(Some((texture.clone(), image)))
+            }
+            _ => {
+                let (texture, future) = create_mipmapped_image(
+                    queue,
+                    [image.width, image.height],
+                    format,
+                    image.data.clone(),
+                )?;
+                future.then_signal_fence_and_flush()?.wait(None)?;
+                self.preview_textures
+                    .insert(name, (image.clone(), texture.clone()));
+                Ok(Some((texture, image)))
+            }
+        }
     }
 
     pub fn update_in_place_paint_object(
@@ -62,11 +119,9 @@ impl EditorPlacer {
         let mut image = BitmapImage::empty(width as u32, height as u32);
         for pixel in canvas_draw_state.pixels.iter() {
             let img_index = ((height - (pixel.y - min.y) - 1) * width + (pixel.x - min.x)) as usize;
-            let matter_color = simulation.matter_definitions.definitions
-                [self.object_matter as usize]
-                .color
-                .to_be_bytes();
-            let rgba = variated_color(matter_color);
+            let matter_color =
+                simulation.matter_definitions.definitions[self.object_matter as usize].color;
+            let rgba = variated_color(Color32::from_u32_rgba(matter_color).to_rgba());
             image.data[img_index * 4] = rgba[0];
             image.data[img_index * 4 + 1] = rgba[1];
             image.data[img_index * 4 + 2] = rgba[2];
@@ -81,7 +136,7 @@ impl EditorPlacer {
         physics_world: &mut PhysicsWorld,
         simulation: &mut Simulation,
         canvas_draw_state: &CanvasDrawState,
-    ) -> Result<()> {
+    ) -> Result<Entity> {
         let image = Arc::new(self.bitmap_image.take().unwrap());
         let world_pos = canvas_draw_state.pixels_world_pos();
         let entity = simulation.add_dynamic_pixel_object(
@@ -94,22 +149,32 @@ impl EditorPlacer {
             0.0,
             0.0,
         )?;
+        if self.place_fixed {
+            make_pixel_object_fixed(ecs_world, &mut physics_world.physics, entity)?;
+        }
+        set_pixel_object_collision(ecs_world, &mut physics_world.physics, entity, self.collision)?;
         simulation.loaded_obj_images.insert(entity.id(), image);
-        Ok(())
+        Ok(entity)
     }
 }
 
-pub fn get_object_image_files() -> Result<BTreeMap<String, Arc<BitmapImage>>> {
+/// Object images by file name, and errors of files that could not be loaded
+pub fn get_object_image_files() -> Result<(BTreeMap<String, Arc<BitmapImage>>, Vec<String>)> {
     let mut object_images = BTreeMap::new();
-    let dir_path = current_dir()?.join("assets/object_images");
-    fs::create_dir_all(dir_path.clone()).unwrap();
-    for file in fs::read_dir(dir_path.clone()).unwrap() {
+    let mut errors = vec![];
+    let dir_path = DATA_DIRS.assets.join("object_images");
+    fs::create_dir_all(dir_path.clone())?;
+    for file in fs::read_dir(dir_path.clone())? {
         let file = file?.file_name();
-        let file_name = file.to_str().unwrap();
+        let file_name = file.to_string_lossy();
+        let file_name = file_name.as_ref();
         let file_path = dir_path.join(file_name);
-        let contents = fs::read(file_path)?;
-        let image = Arc::new(load_image_from_file_bytes(&contents));
-        object_images.insert(file_name.to_string(), image);
+        match load_bitmap_image_from_path(file_path) {
+            std::result::Result::Ok(image) => {
+                object_images.insert(file_name.to_string(), Arc::new(image));
+            }
+            Err(e) => errors.push(format!("Failed to load object image {}: {:#}", file_name, e)),
+        }
     }
-    Ok(object_images)
+    Ok((object_images, errors))
 }
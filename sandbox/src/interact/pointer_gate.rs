@@ -0,0 +1,41 @@
+use corrode::input_system::{InputButton, InputSystem, State};
+
+use crate::app::InputAction;
+
+/// Keeps mouse presses that started over gui from reaching the canvas, e.g. dragging a slider
+/// off its window doesn't paint
+pub struct PointerGate {
+    /// Buttons pressed while gui wanted pointer input, ignored until released
+    gui_presses: Vec<InputButton>,
+}
+
+impl PointerGate {
+    pub fn new() -> PointerGate {
+        PointerGate {
+            gui_presses: vec![],
+        }
+    }
+
+    /// Button state as seen by canvas. Call once per button per frame
+    pub fn state(
+        &mut self,
+        input: &InputSystem<InputAction>,
+        button: InputButton,
+        gui_wants_pointer: bool,
+    ) -> Option<State> {
+        let state = input.button_state(button);
+        if state == Some(State::Activated) && gui_wants_pointer {
+            if !self.gui_presses.contains(&button) {
+                self.gui_presses.push(button);
+            }
+            return None;
+        }
+        if self.gui_presses.contains(&button) {
+            if !matches!(state, Some(State::Held)) {
+                self.gui_presses.retain(|b| *b != button);
+            }
+            return None;
+        }
+        state
+    }
+}
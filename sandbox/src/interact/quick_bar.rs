@@ -0,0 +1,103 @@
+use std::collections::VecDeque;
+
+/// Number of quick bar slots. Slots are selected with keys 5..0, after the editor mode keys
+pub const QUICK_BAR_SIZE: usize = 6;
+
+/// Matter to paint or object to place from the quick bar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuickBarItem {
+    Matter(u32),
+    Object(String),
+}
+
+/// Recently used matters and objects. Pinned favorites take the first slots, the rest are
+/// filled with most recently used items
+pub struct QuickBar {
+    pinned: Vec<QuickBarItem>,
+    recent: VecDeque<QuickBarItem>,
+}
+
+impl QuickBar {
+    pub fn new() -> QuickBar {
+        QuickBar {
+            pinned: vec![],
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// Mark item as most recently used
+    pub fn use_item(&mut self, item: QuickBarItem) {
+        if self.recent.front() == Some(&item) {
+            return;
+        }
+        self.recent.retain(|i| *i != item);
+        self.recent.push_front(item);
+        self.recent.truncate(QUICK_BAR_SIZE);
+    }
+
+    pub fn is_pinned(&self, item: &QuickBarItem) -> bool {
+        self.pinned.contains(item)
+    }
+
+    /// Pin or unpin item. Pinning fails silently when all slots are pinned
+    pub fn toggle_pin(&mut self, item: &QuickBarItem) {
+        if let Some(index) = self.pinned.iter().position(|i| i == item) {
+            self.pinned.remove(index);
+        } else if self.pinned.len() < QUICK_BAR_SIZE {
+            self.pinned.push(item.clone());
+        }
+    }
+
+    /// Items in slot order, pinned first
+    pub fn items(&self) -> Vec<QuickBarItem> {
+        self.pinned
+            .iter()
+            .chain(self.recent.iter().filter(|i| !self.pinned.contains(i)))
+            .take(QUICK_BAR_SIZE)
+            .cloned()
+            .collect()
+    }
+
+    pub fn item(&self, slot: usize) -> Option<QuickBarItem> {
+        self.items().into_iter().nth(slot)
+    }
+
+    /// Forget removed matter and shift ids after it, matching how matter definitions are
+    /// renumbered on removal
+    pub fn matter_removed(&mut self, id: u32) {
+        let removed = QuickBarItem::Matter(id);
+        self.pinned.retain(|i| *i != removed);
+        self.recent.retain(|i| *i != removed);
+        for item in self.pinned.iter_mut().chain(self.recent.iter_mut()) {
+            if let QuickBarItem::Matter(m) = item {
+                if *m > id {
+                    *m -= 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quick_bar_slot_order() {
+        let mut quick_bar = QuickBar::new();
+        for id in 0..(QUICK_BAR_SIZE as u32 + 2) {
+            quick_bar.use_item(QuickBarItem::Matter(id));
+        }
+        quick_bar.use_item(QuickBarItem::Matter(3));
+        quick_bar.toggle_pin(&QuickBarItem::Object("box".to_string()));
+        let items = quick_bar.items();
+        assert_eq!(items.len(), QUICK_BAR_SIZE);
+        assert_eq!(items[0], QuickBarItem::Object("box".to_string()));
+        assert_eq!(items[1], QuickBarItem::Matter(3));
+        assert_eq!(items[2], QuickBarItem::Matter(QUICK_BAR_SIZE as u32 + 1));
+        assert_eq!(items.iter().filter(|i| **i == QuickBarItem::Matter(3)).count(), 1);
+
+        quick_bar.matter_removed(3);
+        assert_eq!(quick_bar.item(1), Some(QuickBarItem::Matter(QUICK_BAR_SIZE as u32)));
+    }
+}
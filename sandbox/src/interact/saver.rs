@@ -1,24 +1,211 @@
-use std::{collections::BTreeSet, fs};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
 
 use anyhow::*;
 use cgmath::Vector2;
 use corrode::api::EngineApi;
+use egui::TextureId;
+use serde::{Deserialize, Serialize};
+use vulkano::{buffer::CpuAccessibleBuffer, sync::FlushError};
 
 use crate::{
     app::InputAction,
+    interact::EditorPainter,
     map_path,
+    matter::MatterDefinitions,
     object::{
-        Angle, AngularVelocity, LinearVelocity, PixelData, PixelObjectSaveData,
-        PixelObjectSaveDataArray, Position,
+        Angle, AngularVelocity, FixedObject, LinearVelocity, ObjectCollision, PixelData,
+        PixelObjectSaveData, PixelObjectSaveDataArray, Position,
+    },
+    settings::{AppSettings, SimulationSeed},
+    sim::{
+        chunk_file_name, chunk_matter_grid, encode_chunk, matter_grid_to_image, ChunkSnapshot,
+        MapManifest, ObjectJointSaveData, Simulation, TransferFuture, CHUNK_FILE_EXTENSION,
+    },
+    toasts::Toasts,
+    utils::{
+        get_map_directory_names, load_bitmap_image_from_path, write_file_atomic, BitmapImage,
+        ImageWriteResult, ImageWriter,
     },
-    settings::AppSettings,
-    sim::Simulation,
-    utils::get_map_directory_names,
 };
 
+/// Chunk previews are this many times smaller than chunks
+pub const PREVIEW_SCALE: u32 = 8;
+
+const SESSION_FILE: &str = "session.json";
+
+/// Editing session saved with a map, so loading it resumes where it was left off. Matter grid is
+/// saved as chunk images and objects with their velocities in objects.json, this has the rest
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionState {
+    pub camera_pos: Vector2<f32>,
+    pub camera_zoom: f32,
+    pub camera_rotation: f32,
+    pub painter: EditorPainter,
+    /// CA steps since world seed was set, so randomness continues instead of repeating
+    pub sim_steps: usize,
+    /// Raw bug buffer, see `Simulation::read_bugs`
+    pub bugs: Vec<u32>,
+}
+
+impl SessionState {
+    pub fn new(
+        api: &EngineApi<InputAction>,
+        simulation: &Simulation,
+        painter: &EditorPainter,
+    ) -> Result<SessionState> {
+        Ok(SessionState {
+            camera_pos: api.main_camera.pos(),
+            camera_zoom: api.main_camera.zoom_level(),
+            camera_rotation: api.main_camera.rotation(),
+            painter: *painter,
+            sim_steps: simulation.sim_steps(),
+            bugs: simulation.read_bugs()?,
+        })
+    }
+
+    /// Resume session on a loaded map. Painted matter that no longer exists is not restored
+    pub fn restore(
+        &self,
+        api: &mut EngineApi<InputAction>,
+        simulation: &mut Simulation,
+        painter: &mut EditorPainter,
+    ) -> Result<()> {
+        api.main_camera.set_pos(self.camera_pos);
+        api.main_camera.reset_zoom();
+        api.main_camera.zoom(self.camera_zoom);
+        api.main_camera.set_rotation(self.camera_rotation);
+        let matter = painter.matter;
+        *painter = self.painter;
+        if self.painter.matter as usize >= simulation.matter_definitions.definitions.len() {
+            painter.matter = matter;
+        }
+        simulation.resume_sim_steps(self.sim_steps)?;
+        simulation.write_bugs(&self.bugs)?;
+        Ok(())
+    }
+
+    pub fn serialize(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn deserialize(data: &str) -> Result<SessionState> {
+        Ok(serde_json::from_str(data)?)
+    }
+}
+
+/// Matter of a saved chunk, read back from gpu or still encoded
+enum SavedMatter {
+    Grid(Vec<u32>),
+    Encoded(Option<Vec<u8>>),
+}
+
+/// Chunk of a map save, written with its preview on the image writer's thread pool
+struct ChunkSave {
+    job: usize,
+    chunk_path: PathBuf,
+    preview_path: PathBuf,
+    matter_definitions: Arc<MatterDefinitions>,
+}
+
+impl ChunkSave {
+    /// Encode or decode `matter` as needed & write the chunk & its preview in the background
+    fn spawn(self, image_writer: &ImageWriter, matter: Result<SavedMatter>) {
+        let ChunkSave {
+            job,
+            chunk_path,
+            preview_path,
+            matter_definitions,
+        } = self;
+        image_writer.spawn(
+            job,
+            chunk_path,
+            Box::new(move |path| {
+                let (data, matter_grid) = match matter? {
+                    SavedMatter::Grid(matter_grid) => (encode_chunk(&matter_grid), matter_grid),
+                    SavedMatter::Encoded(data) => {
+                        let matter_grid = chunk_matter_grid(data.as_deref(), &matter_definitions)?;
+                        (data.unwrap_or_else(|| encode_chunk(&matter_grid)), matter_grid)
+                    }
+                };
+                write_file_atomic(path, &data)?;
+                // Small chunk previews for thumbnails, so chunks needn't be decoded for them
+                matter_grid_to_image(&matter_definitions, &matter_grid)
+                    .downscaled(PREVIEW_SCALE)
+                    .save_png(&preview_path)
+            }),
+        );
+    }
+}
+
+/// Chunk of a map save whose matter is being copied to staging memory on gpu
+pub struct PendingChunkCopy {
+    save: ChunkSave,
+    staging: Arc<CpuAccessibleBuffer<[u32]>>,
+    copied: TransferFuture,
+}
+
+/// Map save written to a directory of its own, which replaces the map's directory once all of
+/// its files have been written. A failed save is discarded, leaving the previous one loadable
+pub struct PendingMapSave {
+    job: usize,
+    map_name: String,
+}
+
+impl PendingMapSave {
+    /// Replace the map's previous save with the written one. Exported images aren't part of
+    /// saves, so they're kept
+    fn replace_previous(&self) -> Result<()> {
+        let dir_path = map_path().join(&self.map_name);
+        let save_dir_path = map_save_dir_path(&self.map_name, "saving");
+        let old_dir_path = map_save_dir_path(&self.map_name, "old");
+        if dir_path.exists() {
+            let export_dir_path = dir_path.join("export");
+            if export_dir_path.exists() {
+                fs::rename(export_dir_path, save_dir_path.join("export"))?;
+            }
+            if old_dir_path.exists() {
+                fs::remove_dir_all(&old_dir_path)?;
+            }
+            fs::rename(&dir_path, &old_dir_path)?;
+        }
+        fs::rename(&save_dir_path, &dir_path)?;
+        if old_dir_path.exists() {
+            fs::remove_dir_all(&old_dir_path)?;
+        }
+        Ok(())
+    }
+
+    fn discard(&self) -> Result<()> {
+        let save_dir_path = map_save_dir_path(&self.map_name, "saving");
+        Ok(fs::remove_dir_all(save_dir_path)?)
+    }
+}
+
+/// Hidden directory next to a map's, e.g. `.name.saving` for a save being written. Hidden
+/// directories aren't listed as maps
+fn map_save_dir_path(map_name: &str, suffix: &str) -> PathBuf {
+    map_path().join(format!(".{}.{}", map_name, suffix))
+}
+
 pub struct EditorSaveLoader {
     pub map_name: String,
     pub map_file_names: BTreeSet<String>,
+    /// Encodes map images in the background
+    pub image_writer: ImageWriter,
+    /// Chunks of map saves waiting for their gpu copies before they're written
+    pub pending_chunks: Vec<PendingChunkCopy>,
+    /// Map saves being written, see `PendingMapSave`
+    pub pending_saves: Vec<PendingMapSave>,
+    /// Map saves that have finished writing but haven't been toasted yet
+    pub finished_saves: Vec<ImageWriteResult>,
+    /// Map thumbnails by map name, None if a map has no preview
+    pub map_thumbnails: BTreeMap<String, Option<TextureId>>,
 }
 
 impl EditorSaveLoader {
@@ -27,79 +214,304 @@ impl EditorSaveLoader {
         api: &mut EngineApi<InputAction>,
         simulation: &mut Simulation,
         settings: &AppSettings,
+        painter: &EditorPainter,
     ) -> Result<()> {
+        // Thumbnail is reloaded from the new previews
+        let map_name = self.map_name.clone();
+        self.remove_map_thumbnail(api, &map_name);
+        let session = SessionState::new(api, simulation, painter)?;
         let EngineApi {
             ecs_world, ..
         } = api;
-        let dir_path = map_path().join(&self.map_name);
-        fs::create_dir_all(dir_path.clone()).unwrap();
-        simulation.save_map_to_disk(dir_path.clone(), settings)?;
+        // Previous save may still be writing to the same directory
+        self.wait_writes();
+        // Whole save is written aside & replaces the previous one once all of it is on disk, so
+        // a failed save leaves the previous one loadable
+        let dir_path = map_save_dir_path(&self.map_name, "saving");
+        // Left over from a save that was interrupted
+        if dir_path.exists() {
+            fs::remove_dir_all(&dir_path)?;
+        }
+        fs::create_dir_all(dir_path.clone())?;
+        // Chunks on gpu are copied aside, so they're read back once copied instead of now
+        let chunks = simulation.snapshot_map_chunks(settings)?;
+        let preview_dir_path = dir_path.join("previews");
+        fs::create_dir_all(&preview_dir_path)?;
+        let mut images = vec![];
+        MapManifest {
+            world_seed: simulation.world_seed(),
+            ..MapManifest::new(&simulation.matter_definitions, settings.chunked_simulation)
+        }
+        .write(&dir_path)?;
 
         // Save objects
         let obj_dir_path = dir_path.join("objects");
-        if obj_dir_path.exists() {
-            fs::remove_dir_all(obj_dir_path.clone()).unwrap();
-        }
-        fs::create_dir_all(obj_dir_path.clone()).unwrap();
+        fs::create_dir_all(obj_dir_path.clone())?;
         let mut obj_save_data = PixelObjectSaveDataArray {
             objects: vec![],
+            joints: vec![],
         };
-        for (id, (pixel_data, pos, lin_vel, angle, ang_vel)) in &mut ecs_world.query::<(
-            &PixelData,
-            &Position,
-            &LinearVelocity,
-            &Angle,
-            &AngularVelocity,
-        )>() {
-            let pixel_image = pixel_data.to_image();
+        // Object indices by entity, for joints
+        let mut object_indices = BTreeMap::new();
+        for (id, (pixel_data, pos, lin_vel, angle, ang_vel, fixed, collision)) in
+            &mut ecs_world.query::<(
+                &PixelData,
+                &Position,
+                &LinearVelocity,
+                &Angle,
+                &AngularVelocity,
+                Option<&FixedObject>,
+                Option<&ObjectCollision>,
+            )>()
+        {
+            let pixel_image = BitmapImage::from_rgba_image(pixel_data.to_image());
             let obj_data = PixelObjectSaveData::from_dynamic_pixel_object(
                 id,
                 (pixel_data.clone(), *pos, *lin_vel, *angle, *ang_vel),
+                fixed.is_some(),
+                collision.copied().unwrap_or_default(),
             );
             let img_path = obj_dir_path.join(&format!("{}.png", obj_data.id));
-            pixel_image.save(img_path)?;
+            images.push((img_path, pixel_image));
+            object_indices.insert(id, obj_save_data.objects.len());
             obj_save_data.objects.push(obj_data);
         }
+        for joint in simulation.object_joints.iter() {
+            let [a, b] = joint.entities;
+            if let (Some(&a), Some(&b)) = (object_indices.get(&a), object_indices.get(&b)) {
+                obj_save_data.joints.push(ObjectJointSaveData {
+                    objects: [a, b],
+                    kind: joint.kind,
+                    local_anchors: joint.local_anchors,
+                    length: joint.length,
+                });
+            }
+        }
 
         let obj_data_path = obj_dir_path.join("objects.json");
-        fs::write(obj_data_path, obj_save_data.serialize()).unwrap();
+        fs::write(obj_data_path, obj_save_data.serialize())?;
 
-        self.map_file_names = get_map_directory_names()?;
-        info!("Saved map {}", self.map_name);
+        // Save trigger zones
+        let triggers_path = dir_path.join("triggers.json");
+        fs::write(triggers_path, simulation.trigger_zones.serialize())?;
+        let portals_path = dir_path.join("portals.json");
+        fs::write(portals_path, simulation.portals.serialize())?;
+        let annotations_path = dir_path.join("annotations.json");
+        fs::write(annotations_path, simulation.annotations.serialize())?;
+        fs::write(dir_path.join(SESSION_FILE), session.serialize())?;
+
+        // Encode images & write chunks off the main thread, see `toast_finished_saves`
+        let job = self
+            .image_writer
+            .start(&self.map_name, images.len() + chunks.len());
+        for (path, image) in images {
+            self.image_writer
+                .spawn(job, path, Box::new(move |path| image.save_png(path)));
+        }
+        let matter_definitions = Arc::new(simulation.matter_definitions.clone());
+        for (chunk_pos, snapshot) in chunks {
+            let save = ChunkSave {
+                job,
+                chunk_path: dir_path.join(chunk_file_name(chunk_pos, CHUNK_FILE_EXTENSION)),
+                preview_path: preview_dir_path.join(chunk_file_name(chunk_pos, "png")),
+                matter_definitions: matter_definitions.clone(),
+            };
+            match snapshot {
+                ChunkSnapshot::Gpu {
+                    staging,
+                    copied,
+                } => self.pending_chunks.push(PendingChunkCopy {
+                    save,
+                    staging,
+                    copied,
+                }),
+                ChunkSnapshot::Cpu(data) => {
+                    save.spawn(&self.image_writer, Ok(SavedMatter::Encoded(data)))
+                }
+            }
+        }
+        self.pending_saves.push(PendingMapSave {
+            job,
+            map_name: self.map_name.clone(),
+        });
+        Ok(())
+    }
+
+    /// Write full size png images of the map's chunks, matter drawn in its color, to `export`
+    /// directory of the map. Exported images aren't loaded with the map
+    pub fn export_map_images(
+        &mut self,
+        simulation: &mut Simulation,
+        settings: &AppSettings,
+    ) -> Result<()> {
+        self.wait_writes();
+        let export_dir_path = map_path().join(&self.map_name).join("export");
+        fs::create_dir_all(&export_dir_path)?;
+        let images = simulation.map_chunk_images(export_dir_path, settings)?;
+        self.image_writer
+            .write(&format!("{} images", self.map_name), images, vec![]);
         Ok(())
     }
 
+    /// Hand chunks whose gpu copies have finished to the image writer, blocking until all have
+    /// if `wait`
+    fn write_copied_chunks(&mut self, wait: bool) {
+        let timeout = if wait { None } else { Some(Duration::ZERO) };
+        for pending in std::mem::take(&mut self.pending_chunks) {
+            let matter = match pending.copied.wait(timeout) {
+                Err(FlushError::Timeout) => {
+                    self.pending_chunks.push(pending);
+                    continue;
+                }
+                Err(e) => Err(Error::from(e)),
+                std::result::Result::Ok(()) => pending
+                    .staging
+                    .read()
+                    .map(|matter_grid| SavedMatter::Grid(matter_grid.to_vec()))
+                    .map_err(Error::from),
+            };
+            pending.save.spawn(&self.image_writer, matter);
+        }
+    }
+
+    /// Block until map saves & exports are on disk, e.g. before loading a map
+    pub fn wait_writes(&mut self) {
+        self.write_copied_chunks(true);
+        self.image_writer.wait();
+        self.collect_finished_saves();
+    }
+
+    /// Replace previous saves with those that have finished without errors, discard the rest &
+    /// keep their results for `toast_finished_saves`
+    fn collect_finished_saves(&mut self) {
+        for mut result in self.image_writer.finished_jobs() {
+            if let Some(index) = self.pending_saves.iter().position(|s| s.job == result.job) {
+                let save = self.pending_saves.remove(index);
+                let replaced = if result.errors.is_empty() {
+                    save.replace_previous()
+                } else {
+                    save.discard()
+                };
+                if let Err(e) = replaced {
+                    result.errors.push(format!("Map directory: {}", e));
+                }
+                match get_map_directory_names() {
+                    std::result::Result::Ok(names) => self.map_file_names = names,
+                    Err(e) => result.errors.push(format!("Map list: {}", e)),
+                }
+            }
+            self.finished_saves.push(result);
+        }
+    }
+
+    pub fn is_writing(&self) -> bool {
+        !self.pending_chunks.is_empty() || self.image_writer.is_writing()
+    }
+
+    /// Name, written & total files of saves still writing
+    pub fn save_progress(&self) -> Vec<(String, usize, usize)> {
+        self.image_writer.progress()
+    }
+
+    /// Write chunks of map saves that are ready & toast map saves whose images have been
+    /// written since last call
+    pub fn toast_finished_saves(&mut self, toasts: &mut Toasts) {
+        self.write_copied_chunks(false);
+        self.collect_finished_saves();
+        for result in std::mem::take(&mut self.finished_saves) {
+            if result.errors.is_empty() {
+                toasts.info(format!("Saved map {}", result.name));
+            } else {
+                for e in result.errors.iter() {
+                    error!("Failed to save map image {}", e);
+                }
+                toasts.error(format!(
+                    "Failed to save {} images of map {}",
+                    result.errors.len(),
+                    result.name
+                ));
+            }
+        }
+    }
+
     pub fn new_map(
         &mut self,
         api: &mut EngineApi<InputAction>,
         simulation: &mut Simulation,
+        seed: SimulationSeed,
     ) -> Result<()> {
         simulation.reset(api.renderer.image_format())?;
+        simulation.set_world_seed(seed.world_seed())?;
         api.reset_world()?;
         self.map_name = "New".to_string();
-        info!("New empty map");
         Ok(())
     }
 
+    /// Load map & resume its saved session, returning descriptions of broken parts that were
+    /// skipped. Maps saved without a session start at origin
     pub fn load_map(
         &mut self,
         api: &mut EngineApi<InputAction>,
         simulation: &mut Simulation,
         map_name: &str,
-    ) -> Result<()> {
+        painter: &mut EditorPainter,
+    ) -> Result<Vec<String>> {
+        self.wait_writes();
         simulation.reset(api.renderer.image_format())?;
         api.reset_world()?;
-        simulation.load_map_from_disk(api, map_name, Vector2::new(0, 0))?;
+        let mut skipped = simulation.load_map_from_disk(api, map_name, Vector2::new(0, 0))?;
         self.map_name = map_name.to_string();
-        info!("Loaded map {}", map_name);
-        Ok(())
+        api.main_camera.translate(-api.main_camera.pos());
+        let session_path = map_path().join(map_name).join(SESSION_FILE);
+        if session_path.exists() {
+            let result = fs::read_to_string(session_path)
+                .map_err(Error::from)
+                .and_then(|data| SessionState::deserialize(&data))
+                .and_then(|session| session.restore(api, simulation, painter));
+            if let Err(e) = result {
+                skipped.push(format!("Session: {:#}", e));
+            }
+        }
+        Ok(skipped)
+    }
+
+    /// Thumbnail of map from the preview of its chunk (0, 0), loaded on first use
+    pub fn map_thumbnail(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        map: &str,
+    ) -> Option<TextureId> {
+        if let Some(thumbnail) = self.map_thumbnails.get(map) {
+            return *thumbnail;
+        }
+        // Previews of a map being saved aren't written yet
+        if self.is_writing() {
+            return None;
+        }
+        let path = map_path().join(map).join("previews/chunk_0_0.png");
+        let thumbnail = load_bitmap_image_from_path(path).ok().map(|image| {
+            api.gui.register_user_image_from_bytes(
+                &image.data,
+                (image.width as u64, image.height as u64),
+                api.renderer.image_format(),
+            )
+        });
+        self.map_thumbnails.insert(map.to_string(), thumbnail);
+        thumbnail
+    }
+
+    fn remove_map_thumbnail(&mut self, api: &mut EngineApi<InputAction>, map: &str) {
+        if let Some(Some(texture_id)) = self.map_thumbnails.remove(map) {
+            api.gui.unregister_user_image(texture_id);
+        }
     }
 
-    pub fn delete_map(&mut self, map: &str) -> Result<()> {
+    pub fn delete_map(&mut self, api: &mut EngineApi<InputAction>, map: &str) -> Result<()> {
+        self.wait_writes();
+        self.remove_map_thumbnail(api, map);
         let dir_path = map_path().join(map);
-        fs::remove_dir_all(dir_path).unwrap();
+        fs::remove_dir_all(dir_path)?;
         self.map_file_names = get_map_directory_names()?;
-        info!("Removed map {}", map);
         Ok(())
     }
 }
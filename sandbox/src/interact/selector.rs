@@ -0,0 +1,218 @@
+use anyhow::*;
+use cgmath::Vector2;
+use corrode::renderer::render_pass::DrawPass;
+use vulkano::format::Format;
+
+use crate::{
+    interact::PreviewTexture,
+    matter::MatterDefinitions,
+    sim::Simulation,
+    utils::{BitmapImage, Color32},
+};
+
+/// Alpha of clipboard preview at mouse
+const PREVIEW_ALPHA: u8 = 160;
+
+/// Min & max (inclusive) canvas positions of a selection from `start` to `end`
+pub fn selection_rect(start: Vector2<i32>, end: Vector2<i32>) -> (Vector2<i32>, Vector2<i32>) {
+    (
+        Vector2::new(start.x.min(end.x), start.y.min(end.y)),
+        Vector2::new(start.x.max(end.x), start.y.max(end.y)),
+    )
+}
+
+/// Matter of a canvas rectangle, bottom row first
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatterRegion {
+    pub width: u32,
+    pub height: u32,
+    pub cells: Vec<u32>,
+}
+
+impl MatterRegion {
+    fn get(&self, x: u32, y: u32) -> u32 {
+        self.cells[(y * self.width + x) as usize]
+    }
+
+    fn from_fn(width: u32, height: u32, matter: impl Fn(u32, u32) -> u32) -> MatterRegion {
+        MatterRegion {
+            width,
+            height,
+            cells: (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| matter(x, y))
+                .collect(),
+        }
+    }
+
+    /// Rotated 90° clockwise
+    pub fn rotated(&self) -> MatterRegion {
+        MatterRegion::from_fn(self.height, self.width, |x, y| {
+            self.get(self.width - 1 - y, x)
+        })
+    }
+
+    pub fn flipped_horizontally(&self) -> MatterRegion {
+        MatterRegion::from_fn(self.width, self.height, |x, y| {
+            self.get(self.width - 1 - x, y)
+        })
+    }
+
+    pub fn flipped_vertically(&self) -> MatterRegion {
+        MatterRegion::from_fn(self.width, self.height, |x, y| {
+            self.get(x, self.height - 1 - y)
+        })
+    }
+
+    /// Min canvas position when centered at `canvas_pos`, like stamps
+    pub fn min_at(&self, canvas_pos: Vector2<i32>) -> Vector2<i32> {
+        let (width, height) = (self.width as i32, self.height as i32);
+        canvas_pos - Vector2::new(width / 2, height - 1 - height / 2)
+    }
+
+    /// Cells & their matter with bottom left cell at `min`
+    pub fn cells_at(&self, min: Vector2<i32>) -> impl Iterator<Item = (Vector2<i32>, u32)> + '_ {
+        self.cells.iter().enumerate().map(move |(index, &matter)| {
+            let (x, y) = (index as u32 % self.width, index as u32 / self.width);
+            (min + Vector2::new(x as i32, y as i32), matter)
+        })
+    }
+
+    /// Translucent image of matter colors, top row first. Empty cells are transparent
+    fn preview_image(&self, matter_definitions: &MatterDefinitions) -> BitmapImage {
+        let mut image = BitmapImage::empty(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let matter = self.get(x, self.height - 1 - y);
+                let definition = match matter_definitions.definitions.get(matter as usize) {
+                    Some(definition) if matter != matter_definitions.empty => definition,
+                    _ => continue,
+                };
+                let mut rgba = Color32::from_u32_rgba(definition.color).to_rgba();
+                rgba[3] = PREVIEW_ALPHA;
+                let index = (y * self.width + x) as usize * 4;
+                image.data[index..index + 4].copy_from_slice(&rgba);
+            }
+        }
+        image
+    }
+}
+
+/// Rectangular selection copying matter to a clipboard, which is pasted elsewhere, rotated or
+/// flipped if wanted
+pub struct EditorSelector {
+    /// Canvas positions where selection started and where it is now
+    pub selection: Option<(Vector2<i32>, Vector2<i32>)>,
+    clipboard: Option<MatterRegion>,
+    /// Clipboard image drawn at mouse
+    preview: Option<BitmapImage>,
+    preview_texture: PreviewTexture,
+    /// Whether empty cells of clipboard are pasted too, clearing matter under them
+    pub paste_empty: bool,
+}
+
+impl EditorSelector {
+    pub fn new() -> EditorSelector {
+        EditorSelector {
+            selection: None,
+            clipboard: None,
+            preview: None,
+            preview_texture: PreviewTexture::new(),
+            paste_empty: false,
+        }
+    }
+
+    pub fn selection_bounds(&self) -> Option<(Vector2<i32>, Vector2<i32>)> {
+        self.selection.map(|(start, end)| selection_rect(start, end))
+    }
+
+    pub fn clipboard(&self) -> Option<&MatterRegion> {
+        self.clipboard.as_ref()
+    }
+
+    /// Draw clipboard image centered at `world_pos`
+    pub fn draw_preview(
+        &mut self,
+        draw_pass: &mut DrawPass,
+        world_pos: Vector2<f32>,
+        format: Format,
+    ) -> Result<()> {
+        if let Some(image) = &self.preview {
+            self.preview_texture.draw(draw_pass, image, world_pos, format)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_clipboard(
+        &mut self,
+        clipboard: Option<MatterRegion>,
+        matter_definitions: &MatterDefinitions,
+    ) {
+        self.preview_texture.invalidate();
+        self.preview = clipboard
+            .as_ref()
+            .map(|clipboard| clipboard.preview_image(matter_definitions));
+        self.clipboard = clipboard;
+    }
+
+    /// Copy matter under selection to clipboard
+    pub fn finish_selection(&mut self, simulation: &Simulation) -> Result<()> {
+        if let Some((min, max)) = self.selection_bounds() {
+            let clipboard = MatterRegion {
+                width: (max.x - min.x + 1) as u32,
+                height: (max.y - min.y + 1) as u32,
+                cells: simulation.read_matter_rect(min, max)?,
+            };
+            self.set_clipboard(Some(clipboard), &simulation.matter_definitions);
+        }
+        self.selection = None;
+        Ok(())
+    }
+
+    /// Paste clipboard centered at `canvas_pos`. Returns pasted cells with their matter before &
+    /// after, for undo
+    pub fn paste(
+        &self,
+        simulation: &mut Simulation,
+        canvas_pos: Vector2<i32>,
+    ) -> Result<Vec<(Vector2<i32>, u32, u32)>> {
+        let clipboard = match &self.clipboard {
+            Some(clipboard) => clipboard,
+            None => return Ok(vec![]),
+        };
+        let empty = simulation.matter_definitions.empty;
+        let num_matters = simulation.matter_definitions.definitions.len() as u32;
+        // Matter removed since copying is skipped
+        let cells = clipboard
+            .cells_at(clipboard.min_at(canvas_pos))
+            .filter(|&(_, matter)| matter < num_matters && (self.paste_empty || matter != empty))
+            .collect::<Vec<_>>();
+        simulation.replace_matter_cells(&cells)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate_and_flip() {
+        // 1 2 3
+        // 4 5 6 <- bottom row
+        let region = MatterRegion {
+            width: 3,
+            height: 2,
+            cells: vec![4, 5, 6, 1, 2, 3],
+        };
+        // 4 1
+        // 5 2
+        // 6 3
+        assert_eq!(region.rotated().cells, vec![6, 3, 5, 2, 4, 1]);
+        assert_eq!(region.rotated().rotated().rotated().rotated(), region);
+        assert_eq!(region.flipped_horizontally().cells, vec![6, 5, 4, 3, 2, 1]);
+        assert_eq!(region.flipped_vertically().cells, vec![1, 2, 3, 4, 5, 6]);
+        let min = region.min_at(Vector2::new(10, 10));
+        assert_eq!(min, Vector2::new(9, 10));
+        assert_eq!(region.cells_at(min).last(), Some((Vector2::new(11, 11), 3)));
+    }
+}
@@ -0,0 +1,104 @@
+use std::{collections::BTreeMap, fs, path::PathBuf, sync::Arc};
+
+use anyhow::*;
+use cgmath::Vector2;
+use corrode::api::EngineApi;
+use egui::TextureId;
+
+use crate::{
+    app::InputAction,
+    data_dirs::DATA_DIRS,
+    interact::selection_rect,
+    sim::Simulation,
+    utils::{data_file_path, load_bitmap_image_from_path, BitmapImage},
+};
+
+/// Stamps are images of matter colors (like map chunks) placed into the matter grid
+pub struct EditorStamper {
+    pub stamp: Option<String>,
+    pub stamp_assets: BTreeMap<String, Arc<BitmapImage>>,
+    pub stamp_texture_ids: BTreeMap<String, TextureId>,
+    /// Matter copied with a selection, saved as a stamp by name
+    pub copied: Option<BitmapImage>,
+    pub stamp_name: String,
+    /// Canvas positions where selection started and where it is now
+    pub selection: Option<(Vector2<i32>, Vector2<i32>)>,
+}
+
+impl EditorStamper {
+    /// Min & max (inclusive) canvas positions of current selection
+    pub fn selection_bounds(&self) -> Option<(Vector2<i32>, Vector2<i32>)> {
+        self.selection.map(|(start, end)| selection_rect(start, end))
+    }
+
+    /// Copy matter under selection, to be saved as a stamp
+    pub fn finish_selection(&mut self, simulation: &Simulation) -> Result<()> {
+        if let Some((min, max)) = self.selection_bounds() {
+            self.copied = Some(simulation.copy_matter_image(min, max)?);
+        }
+        self.selection = None;
+        Ok(())
+    }
+
+    pub fn place_stamp(&self, simulation: &mut Simulation, canvas_pos: Vector2<i32>) -> Result<()> {
+        if let Some(stamp) = self.stamp.as_ref().and_then(|s| self.stamp_assets.get(s)) {
+            simulation.paste_matter_image(stamp, canvas_pos)?;
+        }
+        Ok(())
+    }
+
+    /// Save copied matter to assets/stamps by `stamp_name` and select it. A stamp of the same
+    /// name is replaced
+    pub fn save_copied(&mut self, api: &mut EngineApi<InputAction>) -> Result<String> {
+        let name = self.stamp_name.trim().to_string();
+        let path = data_file_path(&stamps_dir()?, &name, "png")?;
+        let image = self.copied.take().context("Nothing copied")?;
+        let file_name = format!("{}.png", name);
+        let result = image.save_png(&path);
+        if let Err(e) = result {
+            self.copied = Some(image);
+            return Err(e);
+        }
+        self.stamp_assets.insert(file_name.clone(), Arc::new(image));
+        self.register_gui_image(api, &file_name);
+        self.stamp = Some(file_name.clone());
+        Ok(file_name)
+    }
+
+    /// Register (or replace) the thumbnail of a stamp
+    pub fn register_gui_image(&mut self, api: &mut EngineApi<InputAction>, stamp: &str) {
+        let image = &self.stamp_assets[stamp];
+        let texture_id = api.gui.register_user_image_from_bytes(
+            &image.data,
+            (image.width as u64, image.height as u64),
+            api.renderer.image_format(),
+        );
+        if let Some(old) = self.stamp_texture_ids.insert(stamp.to_string(), texture_id) {
+            api.gui.unregister_user_image(old);
+        }
+    }
+}
+
+fn stamps_dir() -> Result<PathBuf> {
+    let dir_path = DATA_DIRS.data.join("stamps");
+    fs::create_dir_all(&dir_path)?;
+    Ok(dir_path)
+}
+
+/// Stamp images by file name, and errors of files that could not be loaded
+pub fn get_stamp_files() -> Result<(BTreeMap<String, Arc<BitmapImage>>, Vec<String>)> {
+    let mut stamps = BTreeMap::new();
+    let mut errors = vec![];
+    let dir_path = stamps_dir()?;
+    for file in fs::read_dir(&dir_path)? {
+        let file = file?.file_name();
+        let file_name = file.to_string_lossy().to_string();
+        match load_bitmap_image_from_path(dir_path.join(&file_name)) {
+            std::result::Result::Ok(image) => {
+                stamps.insert(file_name, Arc::new(image));
+            }
+            Err(e) => errors.push(format!("Failed to load stamp {}: {:#}", file_name, e)),
+        }
+    }
+    Ok((stamps, errors))
+}
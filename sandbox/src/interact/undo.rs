@@ -0,0 +1,390 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::*;
+use cgmath::Vector2;
+use corrode::api::{remove_physics_entity, EngineApi};
+use egui::TextureId;
+use hecs::{Entity, World};
+
+use crate::{
+    app::InputAction,
+    object::{
+        Angle, AngularVelocity, FixedObject, LinearVelocity, ObjectCollision, PixelData,
+        PixelObjectSaveData, Position,
+    },
+    sim::Simulation,
+    utils::BitmapImage,
+};
+
+/// Edits kept for undo, oldest are dropped first
+const MAX_UNDO_STEPS: usize = 100;
+/// History thumbnails are this many times smaller than the simulated area
+const HISTORY_THUMBNAIL_FACTOR: u32 = 8;
+
+/// Object removed by an edit, enough to spawn it again
+pub struct ObjectSnapshot {
+    data: PixelObjectSaveData,
+    image: Arc<BitmapImage>,
+}
+
+impl ObjectSnapshot {
+    pub fn new(ecs_world: &World, entity: Entity) -> Result<ObjectSnapshot> {
+        let mut query = ecs_world.query_one::<(
+            &PixelData,
+            &Position,
+            &LinearVelocity,
+            &Angle,
+            &AngularVelocity,
+            Option<&FixedObject>,
+            Option<&ObjectCollision>,
+        )>(entity)?;
+        let (pixel_data, pos, lin_vel, angle, ang_vel, fixed, collision) =
+            query.get().context("Object has no pixel data")?;
+        Ok(ObjectSnapshot {
+            data: PixelObjectSaveData::from_dynamic_pixel_object(
+                entity,
+                (pixel_data.clone(), *pos, *lin_vel, *angle, *ang_vel),
+                fixed.is_some(),
+                collision.copied().unwrap_or_default(),
+            ),
+            image: Arc::new(BitmapImage::from_rgba_image(pixel_data.to_image())),
+        })
+    }
+}
+
+/// An undoable edit. Undoing an edit returns the edit redoing it & vice versa
+pub enum EditAction {
+    /// Painted cells with their matter before & after
+    Paint(Vec<(Vector2<i32>, u32, u32)>),
+    /// Object placed, removed on undo
+    Spawn(Entity),
+    /// Object removed, placed again on undo
+    Despawn(ObjectSnapshot),
+}
+
+/// Simulated area after an edit, registered as gui texture for the history strip
+struct Thumbnail {
+    image: BitmapImage,
+    texture: Option<TextureId>,
+}
+
+/// Edit in history. Thumbnail shows the state after the edit is applied, so it moves along
+/// when the edit is undone & redone
+struct HistoryEntry {
+    action: EditAction,
+    thumbnail: Option<Thumbnail>,
+}
+
+impl HistoryEntry {
+    fn new(action: EditAction) -> HistoryEntry {
+        HistoryEntry {
+            action,
+            thumbnail: None,
+        }
+    }
+}
+
+/// Undo & redo stacks of editor paint & place operations. Cells outside simulated area at undo
+/// time are left as they are, and objects destroyed meanwhile are skipped
+pub struct UndoHistory {
+    undo: Vec<HistoryEntry>,
+    redo: Vec<HistoryEntry>,
+    /// Cells painted by ongoing stroke: matter before stroke & after
+    stroke: HashMap<Vector2<i32>, (u32, u32)>,
+    /// Textures of dropped entries, unregistered on next update
+    dropped_textures: Vec<TextureId>,
+}
+
+impl UndoHistory {
+    pub fn new() -> UndoHistory {
+        UndoHistory {
+            undo: vec![],
+            redo: vec![],
+            stroke: HashMap::new(),
+            dropped_textures: vec![],
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Forget edits, e.g. when a map is loaded
+    pub fn clear(&mut self) {
+        let entries = self.undo.drain(..).chain(self.redo.drain(..)).collect::<Vec<_>>();
+        self.drop_entries(entries);
+        self.stroke.clear();
+    }
+
+    /// Number of edits applied, i.e. position in `history`
+    pub fn applied(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// Thumbnail textures of edits from oldest to newest, undone edits last. None until the
+    /// thumbnail has been drawn
+    pub fn history(&self) -> Vec<Option<TextureId>> {
+        self.undo
+            .iter()
+            .chain(self.redo.iter().rev())
+            .map(|entry| entry.thumbnail.as_ref().and_then(|thumbnail| thumbnail.texture))
+            .collect()
+    }
+
+    /// Undo or redo until `applied` edits are applied, e.g. when a history thumbnail is clicked
+    pub fn jump_to(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        simulation: &mut Simulation,
+        applied: usize,
+    ) -> Result<()> {
+        self.end_stroke();
+        while self.undo.len() > applied {
+            self.undo(api, simulation)?;
+        }
+        while self.undo.len() < applied && self.can_redo() {
+            self.redo(api, simulation)?;
+        }
+        Ok(())
+    }
+
+    /// Draw thumbnail of the latest edit & register thumbnails to gui. Call after compute has
+    /// finished, so the edit is visible in the simulated area
+    pub fn update_thumbnails(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        simulation: &Simulation,
+    ) {
+        for texture in self.dropped_textures.drain(..) {
+            api.gui.unregister_user_image(texture);
+        }
+        if self.stroke.is_empty() {
+            if let Some(latest) = self.undo.last_mut().filter(|e| e.thumbnail.is_none()) {
+                // Grid may be in use, in which case try again next frame
+                if let std::result::Result::Ok(image) =
+                    simulation.sim_area_thumbnail(HISTORY_THUMBNAIL_FACTOR)
+                {
+                    latest.thumbnail = Some(Thumbnail {
+                        image,
+                        texture: None,
+                    });
+                }
+            }
+        }
+        let format = api.renderer.image_format();
+        for entry in self.undo.iter_mut().chain(self.redo.iter_mut()) {
+            if let Some(thumbnail) = entry.thumbnail.as_mut().filter(|t| t.texture.is_none()) {
+                let image = &thumbnail.image;
+                thumbnail.texture = Some(api.gui.register_user_image_from_bytes(
+                    &image.data,
+                    (image.width as u64, image.height as u64),
+                    format,
+                ));
+            }
+        }
+    }
+
+    /// Forget thumbnail textures, e.g. when gui was recreated. They're registered again on update
+    pub fn forget_gui_images(&mut self) {
+        for entry in self.undo.iter_mut().chain(self.redo.iter_mut()) {
+            if let Some(thumbnail) = entry.thumbnail.as_mut() {
+                thumbnail.texture = None;
+            }
+        }
+        self.dropped_textures.clear();
+    }
+
+    /// Thumbnail image of history texture, e.g. to register it to a pop-out window's gui
+    pub fn thumbnail_image(&self, texture: TextureId) -> Option<&BitmapImage> {
+        self.undo
+            .iter()
+            .chain(self.redo.iter())
+            .filter_map(|entry| entry.thumbnail.as_ref())
+            .find(|thumbnail| thumbnail.texture == Some(texture))
+            .map(|thumbnail| &thumbnail.image)
+    }
+
+    /// Registered thumbnail textures of all edits
+    pub fn thumbnail_textures(&self) -> impl Iterator<Item = TextureId> + '_ {
+        self.undo
+            .iter()
+            .chain(self.redo.iter())
+            .filter_map(|entry| entry.thumbnail.as_ref()?.texture)
+    }
+
+    pub fn push(&mut self, action: EditAction) {
+        self.end_stroke();
+        self.push_undo(action);
+    }
+
+    /// Add cells painted with `matter` to the ongoing stroke, by their previous matter
+    pub fn record_paint(&mut self, painted: &[(Vector2<i32>, u32)], matter: u32) {
+        for &(pos, before) in painted {
+            self.stroke.entry(pos).or_insert((before, matter)).1 = matter;
+        }
+    }
+
+    /// A brush stroke is undone as one edit
+    pub fn end_stroke(&mut self) {
+        if !self.stroke.is_empty() {
+            let cells = self
+                .stroke
+                .drain()
+                .map(|(pos, (before, after))| (pos, before, after))
+                .collect();
+            self.push_undo(EditAction::Paint(cells));
+        }
+    }
+
+    pub fn undo(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        simulation: &mut Simulation,
+    ) -> Result<()> {
+        self.end_stroke();
+        if let Some(entry) = self.undo.pop() {
+            match reverse(api, simulation, entry.action)? {
+                Some(action) => self.redo.push(HistoryEntry {
+                    action,
+                    thumbnail: entry.thumbnail,
+                }),
+                None => self.drop_thumbnail(entry.thumbnail),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn redo(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        simulation: &mut Simulation,
+    ) -> Result<()> {
+        self.end_stroke();
+        if let Some(entry) = self.redo.pop() {
+            match reverse(api, simulation, entry.action)? {
+                Some(action) => self.undo.push(HistoryEntry {
+                    action,
+                    thumbnail: entry.thumbnail,
+                }),
+                None => self.drop_thumbnail(entry.thumbnail),
+            }
+        }
+        Ok(())
+    }
+
+    fn push_undo(&mut self, action: EditAction) {
+        if self.undo.len() == MAX_UNDO_STEPS {
+            let oldest = self.undo.remove(0);
+            self.drop_entries(vec![oldest]);
+        }
+        self.undo.push(HistoryEntry::new(action));
+        let redo = self.redo.drain(..).collect();
+        self.drop_entries(redo);
+    }
+
+    fn drop_entries(&mut self, entries: Vec<HistoryEntry>) {
+        for entry in entries {
+            self.drop_thumbnail(entry.thumbnail);
+        }
+    }
+
+    fn drop_thumbnail(&mut self, thumbnail: Option<Thumbnail>) {
+        if let Some(texture) = thumbnail.and_then(|thumbnail| thumbnail.texture) {
+            self.dropped_textures.push(texture);
+        }
+    }
+}
+
+/// Revert `action`, returning the action that reverts it back. None if there was nothing to
+/// revert, e.g. the object has been destroyed
+fn reverse(
+    api: &mut EngineApi<InputAction>,
+    simulation: &mut Simulation,
+    action: EditAction,
+) -> Result<Option<EditAction>> {
+    Ok(match action {
+        EditAction::Paint(cells) => {
+            // Cells changed since the edit, e.g. by simulation, keep their current matter
+            let reverted = cells
+                .into_iter()
+                .map(|(pos, before, after)| (pos, after, before))
+                .collect::<Vec<_>>();
+            let restored = simulation.swap_matter_cells(&reverted)?;
+            if restored.is_empty() {
+                return Ok(None);
+            }
+            Some(EditAction::Paint(
+                restored
+                    .into_iter()
+                    .map(|(pos, after, before)| (pos, before, after))
+                    .collect(),
+            ))
+        }
+        EditAction::Spawn(entity) => {
+            if !api.ecs_world.contains(entity) {
+                return Ok(None);
+            }
+            let snapshot = ObjectSnapshot::new(&api.ecs_world, entity)?;
+            remove_physics_entity(&mut api.ecs_world, &mut api.physics_world, entity);
+            Some(EditAction::Despawn(snapshot))
+        }
+        EditAction::Despawn(ObjectSnapshot {
+            data,
+            image,
+        }) => {
+            let entity = data.add_dynamic_pixel_object(
+                &mut api.ecs_world,
+                &mut api.physics_world,
+                simulation,
+                &image,
+            )?;
+            simulation.loaded_obj_images.insert(entity.id(), image);
+            Some(EditAction::Spawn(entity))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stroke_is_one_edit() {
+        let mut history = UndoHistory::new();
+        let pos = Vector2::new(1, 2);
+        history.record_paint(&[(pos, 0)], 3);
+        history.record_paint(&[(pos, 3), (Vector2::new(2, 2), 0)], 4);
+        history.end_stroke();
+        assert!(history.can_undo() && !history.can_redo());
+        match &history.undo[0].action {
+            EditAction::Paint(cells) => {
+                assert_eq!(cells.len(), 2);
+                assert!(cells.contains(&(pos, 0, 4)));
+            }
+            _ => panic!("Expected paint"),
+        }
+        history.end_stroke();
+        assert_eq!(history.undo.len(), 1);
+    }
+
+    #[test]
+    fn test_new_edit_drops_undone_thumbnails() {
+        let mut history = UndoHistory::new();
+        history.redo.push(HistoryEntry {
+            action: EditAction::Paint(vec![]),
+            thumbnail: Some(Thumbnail {
+                image: BitmapImage::empty(1, 1),
+                texture: Some(TextureId::User(7)),
+            }),
+        });
+        assert_eq!(history.history(), vec![Some(TextureId::User(7))]);
+        history.push(EditAction::Paint(vec![]));
+        assert_eq!(history.applied(), 1);
+        assert_eq!(history.history(), vec![None]);
+        assert_eq!(history.dropped_textures, vec![TextureId::User(7)]);
+    }
+}
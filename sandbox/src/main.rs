@@ -12,28 +12,50 @@ extern crate log;
 extern crate lazy_static;
 
 mod app;
+mod asset_audit;
+mod benchmark;
+mod breakpoints;
+mod camera_path;
+mod data_dirs;
+mod error_dialog;
 mod gui_state;
 mod interact;
 mod matter;
+mod mods;
 mod object;
+mod pop_outs;
 mod render;
 mod settings;
+mod settings_profiles;
 mod sim;
+mod stats_log;
+mod step_control;
+mod stress_test;
+mod toasts;
 mod utils;
+mod version_check;
+mod watches;
 
-use std::{env::current_dir, path::PathBuf};
+use std::path::PathBuf;
 
 use anyhow::*;
 use cgmath::Vector2;
 use corrode::{
+    alloc_counter::CountingAllocator,
     engine::{Corrode, EngineOptions, RenderOptions},
     input_system::InputButton::Key,
     logger::initialize_logger,
 };
 use simplelog::LevelFilter;
-use winit::event::VirtualKeyCode;
+use crate::{
+    app::{default_key_bindings, SandboxApp},
+    benchmark::read_benchmark_result,
+    data_dirs::DATA_DIRS,
+};
 
-use crate::app::{InputAction, SandboxApp};
+/// Counts allocations for the performance info
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
 
 /// This is an example for using doc comment attributes
 /// Canvas plane scale (1.0 means our world is between -1.0 and 1.0)
@@ -62,29 +84,69 @@ pub const CELL_OFFSETS_NINE: [Vector2<i32>; 9] = [
 /// This affects the shape of objects that have lots of transparency in them.
 /// This being larger than 0 but not too much for example ensures the donut.png image's shape is reasonably good
 pub const DEFORMATION_ALPHA_TRESHOLD: u8 = 20;
+/// How many cells above a fan matter gets blown upwards. If you touch this, also change shaders
+pub const FAN_REACH: i32 = 16;
+/// Force per object pixel touching a conveyor
+pub const CONVEYOR_FORCE: f32 = 0.02;
+/// Force per object pixel above a fan
+pub const FAN_FORCE: f32 = 0.01;
+/// Char level object pixels gain each step they're next to burning matter, out of 255
+pub const CHAR_PER_STEP: u8 = 6;
+/// Radius (cells) of the shockwave around each exploding bitmap block
+pub const EXPLOSION_RADIUS: f32 = 8.0;
+/// Impulse per exploding bitmap block on objects inside its shockwave
+pub const EXPLOSION_IMPULSE: f32 = 0.05;
+/// Objects with larger pixel area than this don't fit through portals
+pub const PORTAL_MAX_OBJECT_PIXELS: usize = 32 * 32;
 
 lazy_static! {
-    /// Number of cells in simulated canvas area
-    pub static ref  SIM_CANVAS_SIZE: u32 = if std::env::var("LARGE").is_ok() { 1024 } else { 512 };
+    /// Number of cells in simulated canvas area (width, height). Defaults to a square canvas,
+    /// override with SIM_WIDTH & SIM_HEIGHT env vars, e.g. 1024 x 512 for wide worlds
+    pub static ref SIM_CANVAS_SIZE: Vector2<u32> = {
+        let default = if std::env::var("LARGE").is_ok() { 1024 } else { 512 };
+        Vector2::new(
+            sim_canvas_dimension("SIM_WIDTH", default),
+            sim_canvas_dimension("SIM_HEIGHT", default),
+        )
+    };
     pub static ref HALF_CANVAS: Vector2<i32> =
-        Vector2::new((*SIM_CANVAS_SIZE / 2) as i32, (*SIM_CANVAS_SIZE / 2) as i32);
+        Vector2::new((SIM_CANVAS_SIZE.x / 2) as i32, (SIM_CANVAS_SIZE.y / 2) as i32);
     /// Size of canvas chunk
-    pub static ref  CANVAS_CHUNK_SIZE: u32 = *SIM_CANVAS_SIZE;
-    /// Size of one cell in world units
-    pub static ref  CELL_UNIT_SIZE: f32 = WORLD_UNIT_SIZE / *SIM_CANVAS_SIZE as f32;
+    pub static ref  CANVAS_CHUNK_SIZE: Vector2<u32> = *SIM_CANVAS_SIZE;
+    /// Size of one cell in world units. Cells are square, so canvas width spans `WORLD_UNIT_SIZE`
+    pub static ref  CELL_UNIT_SIZE: f32 = WORLD_UNIT_SIZE / SIM_CANVAS_SIZE.x as f32;
+    /// Size of canvas (& canvas chunk) in world units
+    pub static ref WORLD_CANVAS_SIZE: Vector2<f32> =
+        SIM_CANVAS_SIZE.cast::<f32>().unwrap() * *CELL_UNIT_SIZE;
     pub static ref HALF_CELL: Vector2<f32> = Vector2::new(*CELL_UNIT_SIZE * 0.5, *CELL_UNIT_SIZE * 0.5);
     /// Ratio of bitmap to canvas. If this is 4, bitmap size is (512 / 4) * (512 / 4)
     pub static ref  BITMAP_RATIO: u32 = if std::env::var("LARGE").is_ok() { 8 } else { 4 };
     /// Ratio with which we must adjust the vertices of solid utils to correctly position them
     pub static ref  BITMAP_PIXEL_TO_CANVAS_RATIO: f64 =
-        WORLD_UNIT_SIZE as f64 / (*SIM_CANVAS_SIZE / *BITMAP_RATIO) as f64;
+        WORLD_UNIT_SIZE as f64 / (SIM_CANVAS_SIZE.x / *BITMAP_RATIO) as f64;
+}
+
+/// Canvas width or height from env var. Must be divisible by kernel size and bitmap ratio
+fn sim_canvas_dimension(var: &str, default: u32) -> u32 {
+    let size = match std::env::var(var).ok().and_then(|v| v.parse::<u32>().ok()) {
+        Some(size) => size,
+        None => return default,
+    };
+    let multiple = KERNEL_SIZE * *BITMAP_RATIO;
+    if size == 0 || size % multiple != 0 {
+        warn!("{} must be a positive multiple of {}, using {}", var, multiple, default);
+        return default;
+    }
+    size
 }
 
 pub fn map_path() -> PathBuf {
-    if *SIM_CANVAS_SIZE == 1024 {
-        current_dir().unwrap().join("assets/maps/large")
-    } else {
-        current_dir().unwrap().join("assets/maps/small")
+    let maps = DATA_DIRS.data.join("maps");
+    // Maps are saved in canvas sized chunks, so each canvas size has its own maps
+    match (SIM_CANVAS_SIZE.x, SIM_CANVAS_SIZE.y) {
+        (512, 512) => maps.join("small"),
+        (1024, 1024) => maps.join("large"),
+        (width, height) => maps.join(format!("{}x{}", width, height)),
     }
 }
 
@@ -98,20 +160,15 @@ fn main() -> Result<()> {
         SandboxApp::new()?,
         EngineOptions {
             render_options: RenderOptions {
-                v_sync: false,
+                v_sync: read_benchmark_result().map_or(false, |result| result.v_sync),
                 title: "Sandbox",
                 ..RenderOptions::default()
             },
             ..EngineOptions::default()
         },
-        vec![vec![
-            (InputAction::Pause, Key(VirtualKeyCode::Space)),
-            (InputAction::Step, Key(VirtualKeyCode::Return)),
-            (InputAction::PaintMode, Key(VirtualKeyCode::Key1)),
-            (InputAction::PlaceMode, Key(VirtualKeyCode::Key2)),
-            (InputAction::ObjectPaintMode, Key(VirtualKeyCode::Key3)),
-            (InputAction::DragMode, Key(VirtualKeyCode::Key4)),
-            (InputAction::ToggleFullScreen, Key(VirtualKeyCode::F)),
-        ]],
+        vec![default_key_bindings()
+            .into_iter()
+            .map(|(action, key)| (action, Key(key)))
+            .collect()],
     )
 }
@@ -1,6 +1,6 @@
 use crate::matter::{
-    Direction, MatterCharacteristic, MatterDefinition, MatterDefinitions, MatterReaction,
-    MatterState,
+    Direction, ImpactSound, MatterCharacteristic, MatterDecay, MatterDefinition,
+    MatterDefinitions, MatterReaction, MatterState,
 };
 
 pub const MATTER_EMPTY: u32 = 0;
@@ -17,6 +17,22 @@ pub const MATTER_GAS: u32 = 10;
 pub const MATTER_FIRE: u32 = 11;
 pub const MATTER_ACID: u32 = 12;
 pub const MATTER_ERASE: u32 = 13;
+pub const MATTER_CONVEYOR_LEFT: u32 = 14;
+pub const MATTER_CONVEYOR_RIGHT: u32 = 15;
+pub const MATTER_FAN: u32 = 16;
+pub const MATTER_RADIATION: u32 = 17;
+pub const MATTER_URANIUM: u32 = 18;
+pub const MATTER_RADIUM: u32 = 19;
+pub const MATTER_LEAD: u32 = 20;
+pub const MATTER_GUNPOWDER: u32 = 21;
+pub const MATTER_EXPLOSION: u32 = 22;
+pub const MATTER_SOIL: u32 = 23;
+pub const MATTER_WET_SOIL: u32 = 24;
+pub const MATTER_SEED: u32 = 25;
+pub const MATTER_SPROUT: u32 = 26;
+pub const MATTER_STEM: u32 = 27;
+pub const MATTER_FLOWER: u32 = 28;
+pub const MATTER_FLOAT_DUST: u32 = 29;
 
 pub fn default_matter_definitions() -> MatterDefinitions {
     MatterDefinitions {
@@ -30,13 +46,30 @@ pub fn default_matter_definitions() -> MatterDefinitions {
                 state: MatterState::Empty,
                 dispersion: 0,
                 characteristics: MatterCharacteristic::empty(),
+                // Plants grow & drop seeds into empty space, a cell can only change itself
                 reactions: [
-                    MatterReaction::zero(),
-                    MatterReaction::zero(),
+                    MatterReaction {
+                        reacts: MatterCharacteristic::GROWING,
+                        direction: Direction::DOWN,
+                        probability: 0.05,
+                        becomes: MATTER_SPROUT,
+                        tick_divider: 1,
+                    },
+                    MatterReaction {
+                        reacts: MatterCharacteristic::SEEDING,
+                        direction: (Direction::UP_LEFT | Direction::UP_RIGHT),
+                        probability: 0.002,
+                        becomes: MATTER_SEED,
+                        tick_divider: 1,
+                    },
                     MatterReaction::zero(),
                     MatterReaction::zero(),
                     MatterReaction::zero(),
                 ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.0,
             },
             MatterDefinition {
                 id: MATTER_SAND,
@@ -52,12 +85,14 @@ pub fn default_matter_definitions() -> MatterDefinitions {
                         direction: Direction::ALL,
                         probability: 0.6,
                         becomes: MATTER_GLASS,
+                        tick_divider: 1,
                     },
                     MatterReaction {
                         reacts: MatterCharacteristic::CORROSIVE,
                         direction: Direction::ALL,
                         probability: 0.05,
                         becomes: MATTER_EMPTY,
+                        tick_divider: 1,
                     },
                     MatterReaction::becomes_on_touch(
                         1.0,
@@ -67,6 +102,10 @@ pub fn default_matter_definitions() -> MatterDefinitions {
                     MatterReaction::zero(),
                     MatterReaction::zero(),
                 ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.0,
             },
             MatterDefinition {
                 id: MATTER_WATER,
@@ -76,6 +115,7 @@ pub fn default_matter_definitions() -> MatterDefinitions {
                 state: MatterState::Liquid,
                 dispersion: 10,
                 characteristics: (MatterCharacteristic::RUSTING
+                    | MatterCharacteristic::WETTING
                     | MatterCharacteristic::COOLING
                     | MatterCharacteristic::FREEZES
                     | MatterCharacteristic::VAPORIZES),
@@ -87,12 +127,14 @@ pub fn default_matter_definitions() -> MatterDefinitions {
                         direction: Direction::ALL,
                         probability: 0.6,
                         becomes: MATTER_STEAM,
+                        tick_divider: 1,
                     },
                     MatterReaction {
                         reacts: (MatterCharacteristic::FREEZING),
                         direction: Direction::ALL,
                         probability: 0.005,
                         becomes: MATTER_ICE,
+                        tick_divider: 1,
                     },
                     MatterReaction::becomes_on_touch(
                         1.0,
@@ -102,6 +144,10 @@ pub fn default_matter_definitions() -> MatterDefinitions {
                     MatterReaction::zero(),
                     MatterReaction::zero(),
                 ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.0,
             },
             MatterDefinition {
                 id: MATTER_LAVA,
@@ -120,6 +166,7 @@ pub fn default_matter_definitions() -> MatterDefinitions {
                         direction: Direction::ALL,
                         probability: 0.5,
                         becomes: MATTER_ROCK,
+                        tick_divider: 1,
                     },
                     // After melting or burning, some lava disappears.
                     MatterReaction {
@@ -127,6 +174,7 @@ pub fn default_matter_definitions() -> MatterDefinitions {
                         direction: Direction::ALL,
                         probability: 0.6,
                         becomes: MATTER_EMPTY,
+                        tick_divider: 1,
                     },
                     MatterReaction::becomes_on_touch(
                         1.0,
@@ -136,6 +184,10 @@ pub fn default_matter_definitions() -> MatterDefinitions {
                     MatterReaction::zero(),
                     MatterReaction::zero(),
                 ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.0,
             },
             MatterDefinition {
                 id: MATTER_ROCK,
@@ -151,6 +203,7 @@ pub fn default_matter_definitions() -> MatterDefinitions {
                         direction: Direction::ALL,
                         probability: 0.05,
                         becomes: MATTER_EMPTY,
+                        tick_divider: 1,
                     },
                     MatterReaction::becomes_on_touch(
                         1.0,
@@ -161,6 +214,10 @@ pub fn default_matter_definitions() -> MatterDefinitions {
                     MatterReaction::zero(),
                     MatterReaction::zero(),
                 ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.6,
             },
             MatterDefinition {
                 id: MATTER_ICE,
@@ -179,6 +236,7 @@ pub fn default_matter_definitions() -> MatterDefinitions {
                         direction: Direction::ALL,
                         probability: 0.4,
                         becomes: MATTER_WATER,
+                        tick_divider: 1,
                     },
                     MatterReaction::becomes_on_touch(
                         1.0,
@@ -189,6 +247,10 @@ pub fn default_matter_definitions() -> MatterDefinitions {
                     MatterReaction::zero(),
                     MatterReaction::zero(),
                 ],
+                decay: MatterDecay::none(),
+                impact_sound: Some(ImpactSound::Clink),
+                gravity: 1.0,
+                hardness: 0.3,
             },
             MatterDefinition {
                 id: MATTER_GLASS,
@@ -204,6 +266,7 @@ pub fn default_matter_definitions() -> MatterDefinitions {
                         direction: Direction::ALL,
                         probability: 0.05,
                         becomes: MATTER_EMPTY,
+                        tick_divider: 1,
                     },
                     MatterReaction::becomes_on_touch(
                         1.0,
@@ -214,6 +277,10 @@ pub fn default_matter_definitions() -> MatterDefinitions {
                     MatterReaction::zero(),
                     MatterReaction::zero(),
                 ],
+                decay: MatterDecay::none(),
+                impact_sound: Some(ImpactSound::Clink),
+                gravity: 1.0,
+                hardness: 0.4,
             },
             MatterDefinition {
                 id: MATTER_WOOD,
@@ -222,7 +289,9 @@ pub fn default_matter_definitions() -> MatterDefinitions {
                 weight: 0.4,
                 state: MatterState::Solid,
                 dispersion: 0,
-                characteristics: (MatterCharacteristic::BURNS | MatterCharacteristic::CORRODES),
+                characteristics: (MatterCharacteristic::BURNS
+                    | MatterCharacteristic::CORRODES
+                    | MatterCharacteristic::EDIBLE),
                 reactions: [
                     MatterReaction::becomes_on_touch_below(
                         0.4,
@@ -250,6 +319,10 @@ pub fn default_matter_definitions() -> MatterDefinitions {
                         MATTER_FIRE,
                     ),
                 ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.3,
             },
             MatterDefinition {
                 id: MATTER_STEAM,
@@ -335,6 +408,10 @@ pub fn default_matter_definitions() -> MatterDefinitions {
                     MatterReaction::zero(),
                     MatterReaction::zero(),
                 ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.0,
             },
             MatterDefinition {
                 id: MATTER_ACID,
@@ -351,12 +428,14 @@ pub fn default_matter_definitions() -> MatterDefinitions {
                         direction: Direction::ALL,
                         probability: 0.2,
                         becomes: MATTER_EMPTY,
+                        tick_divider: 1,
                     },
                     MatterReaction {
                         reacts: (MatterCharacteristic::BURNING),
                         direction: Direction::ALL,
                         probability: 0.4,
                         becomes: MATTER_FIRE,
+                        tick_divider: 1,
                     }, // Acid also disappears over time... like gases
                     MatterReaction::dies(0.005, MATTER_EMPTY),
                     MatterReaction::becomes_on_touch(
@@ -366,6 +445,10 @@ pub fn default_matter_definitions() -> MatterDefinitions {
                     ),
                     MatterReaction::zero(),
                 ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.0,
             },
             MatterDefinition {
                 id: MATTER_ERASE,
@@ -383,6 +466,518 @@ pub fn default_matter_definitions() -> MatterDefinitions {
                     MatterReaction::zero(),
                     MatterReaction::zero(),
                 ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.0,
+            },
+            MatterDefinition {
+                id: MATTER_CONVEYOR_LEFT,
+                name: "Conveyor Left".to_string(),
+                color: 0x4a4e69ff,
+                weight: 2.5,
+                state: MatterState::Solid,
+                dispersion: 0,
+                characteristics: (MatterCharacteristic::CONVEYOR_LEFT),
+                reactions: [
+                    MatterReaction {
+                        reacts: (MatterCharacteristic::CORROSIVE),
+                        direction: Direction::ALL,
+                        probability: 0.05,
+                        becomes: MATTER_EMPTY,
+                        tick_divider: 1,
+                    },
+                    MatterReaction::becomes_on_touch(
+                        1.0,
+                        MatterCharacteristic::ERASER,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::zero(),
+                    MatterReaction::zero(),
+                    MatterReaction::zero(),
+                ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.5,
+            },
+            MatterDefinition {
+                id: MATTER_CONVEYOR_RIGHT,
+                name: "Conveyor Right".to_string(),
+                color: 0x4a5e69ff,
+                weight: 2.5,
+                state: MatterState::Solid,
+                dispersion: 0,
+                characteristics: (MatterCharacteristic::CONVEYOR_RIGHT),
+                reactions: [
+                    MatterReaction {
+                        reacts: (MatterCharacteristic::CORROSIVE),
+                        direction: Direction::ALL,
+                        probability: 0.05,
+                        becomes: MATTER_EMPTY,
+                        tick_divider: 1,
+                    },
+                    MatterReaction::becomes_on_touch(
+                        1.0,
+                        MatterCharacteristic::ERASER,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::zero(),
+                    MatterReaction::zero(),
+                    MatterReaction::zero(),
+                ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.5,
+            },
+            MatterDefinition {
+                id: MATTER_FAN,
+                name: "Fan".to_string(),
+                color: 0xb8c0ffff,
+                weight: 2.5,
+                state: MatterState::Solid,
+                dispersion: 0,
+                characteristics: (MatterCharacteristic::FAN),
+                reactions: [
+                    MatterReaction {
+                        reacts: (MatterCharacteristic::CORROSIVE),
+                        direction: Direction::ALL,
+                        probability: 0.05,
+                        becomes: MATTER_EMPTY,
+                        tick_divider: 1,
+                    },
+                    MatterReaction::becomes_on_touch(
+                        1.0,
+                        MatterCharacteristic::ERASER,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::zero(),
+                    MatterReaction::zero(),
+                    MatterReaction::zero(),
+                ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.5,
+            },
+            MatterDefinition {
+                id: MATTER_RADIATION,
+                name: "Radiation".to_string(),
+                color: 0x7fff00ff,
+                weight: 0.0,
+                state: MatterState::Energy,
+                dispersion: 0,
+                characteristics: (MatterCharacteristic::BURNING),
+                reactions: [
+                    MatterReaction::dies(0.5, MATTER_EMPTY),
+                    MatterReaction::becomes_on_touch(
+                        1.0,
+                        MatterCharacteristic::ERASER,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::zero(),
+                    MatterReaction::zero(),
+                    MatterReaction::zero(),
+                ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.0,
+            },
+            MatterDefinition {
+                id: MATTER_URANIUM,
+                name: "Uranium".to_string(),
+                color: 0x5a7d2aff,
+                weight: 3.0,
+                state: MatterState::Powder,
+                dispersion: 0,
+                characteristics: MatterCharacteristic::empty(),
+                reactions: [
+                    MatterReaction::becomes_on_touch(
+                        0.05,
+                        MatterCharacteristic::CORROSIVE,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::becomes_on_touch(
+                        1.0,
+                        MatterCharacteristic::ERASER,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::zero(),
+                    MatterReaction::zero(),
+                    MatterReaction::zero(),
+                ],
+                // Slow, so uranium lasts minutes
+                decay: MatterDecay {
+                    probability: 0.002,
+                    becomes: MATTER_RADIUM,
+                    emits: MATTER_RADIATION,
+                },
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.7,
+            },
+            MatterDefinition {
+                id: MATTER_RADIUM,
+                name: "Radium".to_string(),
+                color: 0x9fd36bff,
+                weight: 3.0,
+                state: MatterState::Powder,
+                dispersion: 0,
+                characteristics: MatterCharacteristic::empty(),
+                reactions: [
+                    MatterReaction::becomes_on_touch(
+                        0.05,
+                        MatterCharacteristic::CORROSIVE,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::becomes_on_touch(
+                        1.0,
+                        MatterCharacteristic::ERASER,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::zero(),
+                    MatterReaction::zero(),
+                    MatterReaction::zero(),
+                ],
+                decay: MatterDecay {
+                    probability: 0.02,
+                    becomes: MATTER_LEAD,
+                    emits: MATTER_RADIATION,
+                },
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.7,
+            },
+            MatterDefinition {
+                id: MATTER_LEAD,
+                name: "Lead".to_string(),
+                color: 0x5b5e6bff,
+                weight: 4.0,
+                state: MatterState::Powder,
+                dispersion: 0,
+                characteristics: MatterCharacteristic::empty(),
+                reactions: [
+                    MatterReaction::becomes_on_touch(
+                        0.02,
+                        MatterCharacteristic::CORROSIVE,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::becomes_on_touch(
+                        1.0,
+                        MatterCharacteristic::ERASER,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::zero(),
+                    MatterReaction::zero(),
+                    MatterReaction::zero(),
+                ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.8,
+            },
+            MatterDefinition {
+                id: MATTER_GUNPOWDER,
+                name: "Gunpowder".to_string(),
+                color: 0x3c3c3cff,
+                weight: 1.0,
+                state: MatterState::Powder,
+                dispersion: 0,
+                characteristics: (MatterCharacteristic::BURNS | MatterCharacteristic::EXPLODES),
+                reactions: [
+                    MatterReaction::becomes_on_touch(
+                        1.0,
+                        MatterCharacteristic::BURNING | MatterCharacteristic::EXPLODING,
+                        MATTER_EXPLOSION,
+                    ),
+                    MatterReaction::becomes_on_touch(
+                        1.0,
+                        MatterCharacteristic::CORROSIVE,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::becomes_on_touch(
+                        1.0,
+                        MatterCharacteristic::ERASER,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::zero(),
+                    MatterReaction::zero(),
+                ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.0,
+            },
+            // Lives a step or two, its bitmap blocks form shockwaves meanwhile (see explosions.rs)
+            MatterDefinition {
+                id: MATTER_EXPLOSION,
+                name: "Explosion".to_string(),
+                color: 0xffb347ff,
+                weight: 0.0,
+                state: MatterState::Energy,
+                dispersion: 0,
+                characteristics: (MatterCharacteristic::EXPLODING | MatterCharacteristic::BURNING),
+                reactions: [
+                    MatterReaction::dies(0.6, MATTER_FIRE),
+                    MatterReaction::dies(1.0, MATTER_SMOKE),
+                    MatterReaction::becomes_on_touch(
+                        1.0,
+                        MatterCharacteristic::ERASER,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::zero(),
+                    MatterReaction::zero(),
+                ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.0,
+            },
+            MatterDefinition {
+                id: MATTER_SOIL,
+                name: "Soil".to_string(),
+                color: 0x8b5a2bff,
+                weight: 1.2,
+                state: MatterState::Powder,
+                dispersion: 0,
+                characteristics: (MatterCharacteristic::WETS | MatterCharacteristic::CORRODES),
+                reactions: [
+                    MatterReaction::becomes_on_touch(
+                        0.05,
+                        MatterCharacteristic::WETTING,
+                        MATTER_WET_SOIL,
+                    ),
+                    MatterReaction::becomes_on_touch(
+                        0.05,
+                        MatterCharacteristic::CORROSIVE,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::becomes_on_touch(
+                        1.0,
+                        MatterCharacteristic::ERASER,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::zero(),
+                    MatterReaction::zero(),
+                ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.0,
+            },
+            MatterDefinition {
+                id: MATTER_WET_SOIL,
+                name: "Wet Soil".to_string(),
+                color: 0x5c3a1eff,
+                weight: 1.4,
+                state: MatterState::Powder,
+                dispersion: 0,
+                characteristics: (MatterCharacteristic::FERTILE | MatterCharacteristic::CORRODES),
+                reactions: [
+                    // Dries out near heat
+                    MatterReaction::becomes_on_touch(
+                        0.05,
+                        MatterCharacteristic::MELTING | MatterCharacteristic::BURNING,
+                        MATTER_SOIL,
+                    ),
+                    MatterReaction::becomes_on_touch(
+                        0.05,
+                        MatterCharacteristic::CORROSIVE,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::becomes_on_touch(
+                        1.0,
+                        MatterCharacteristic::ERASER,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::zero(),
+                    MatterReaction::zero(),
+                ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.0,
+            },
+            MatterDefinition {
+                id: MATTER_SEED,
+                name: "Seed".to_string(),
+                color: 0xd8c37aff,
+                weight: 0.6,
+                state: MatterState::Powder,
+                dispersion: 0,
+                characteristics: (MatterCharacteristic::BURNS
+                    | MatterCharacteristic::CORRODES
+                    | MatterCharacteristic::EDIBLE),
+                reactions: [
+                    MatterReaction::becomes_on_touch_below(
+                        0.01,
+                        MatterCharacteristic::FERTILE,
+                        MATTER_SPROUT,
+                    ),
+                    MatterReaction::becomes_on_touch(
+                        0.5,
+                        MatterCharacteristic::MELTING | MatterCharacteristic::BURNING,
+                        MATTER_FIRE,
+                    ),
+                    MatterReaction::becomes_on_touch(
+                        0.05,
+                        MatterCharacteristic::CORROSIVE,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::becomes_on_touch(
+                        1.0,
+                        MatterCharacteristic::ERASER,
+                        MATTER_EMPTY,
+                    ),
+                    // Seeds that never find soil rot away
+                    MatterReaction::dies(0.0002, MATTER_EMPTY),
+                ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.0,
+            },
+            // Growing tip of a plant. Empty space above it becomes sprout (see Empty), after which
+            // this turns into stem. Tip eventually flowers, which ends the growth
+            MatterDefinition {
+                id: MATTER_SPROUT,
+                name: "Sprout".to_string(),
+                color: 0x9acd32ff,
+                weight: 0.5,
+                state: MatterState::Solid,
+                dispersion: 0,
+                characteristics: (MatterCharacteristic::GROWING
+                    | MatterCharacteristic::BURNS
+                    | MatterCharacteristic::CORRODES),
+                reactions: [
+                    MatterReaction {
+                        reacts: MatterCharacteristic::GROWING,
+                        direction: Direction::UP,
+                        probability: 1.0,
+                        becomes: MATTER_STEM,
+                        tick_divider: 1,
+                    },
+                    MatterReaction::dies(0.003, MATTER_FLOWER),
+                    MatterReaction::becomes_on_touch(
+                        0.5,
+                        MatterCharacteristic::MELTING | MatterCharacteristic::BURNING,
+                        MATTER_FIRE,
+                    ),
+                    MatterReaction::becomes_on_touch(
+                        0.05,
+                        MatterCharacteristic::CORROSIVE,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::becomes_on_touch(
+                        1.0,
+                        MatterCharacteristic::ERASER,
+                        MATTER_EMPTY,
+                    ),
+                ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.0,
+            },
+            MatterDefinition {
+                id: MATTER_STEM,
+                name: "Stem".to_string(),
+                color: 0x4f7942ff,
+                weight: 0.5,
+                state: MatterState::Solid,
+                dispersion: 0,
+                characteristics: (MatterCharacteristic::BURNS
+                    | MatterCharacteristic::CORRODES
+                    | MatterCharacteristic::EDIBLE),
+                reactions: [
+                    MatterReaction::becomes_on_touch(
+                        0.2,
+                        MatterCharacteristic::MELTING | MatterCharacteristic::BURNING,
+                        MATTER_FIRE,
+                    ),
+                    MatterReaction::becomes_on_touch(
+                        0.05,
+                        MatterCharacteristic::CORROSIVE,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::becomes_on_touch(
+                        1.0,
+                        MatterCharacteristic::ERASER,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::zero(),
+                    MatterReaction::zero(),
+                ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.2,
+            },
+            // Drops seeds while it blooms, then wilts into stem
+            MatterDefinition {
+                id: MATTER_FLOWER,
+                name: "Flower".to_string(),
+                color: 0xff69b4ff,
+                weight: 0.5,
+                state: MatterState::Solid,
+                dispersion: 0,
+                characteristics: (MatterCharacteristic::SEEDING
+                    | MatterCharacteristic::BURNS
+                    | MatterCharacteristic::CORRODES),
+                reactions: [
+                    MatterReaction::dies(0.001, MATTER_STEM),
+                    MatterReaction::becomes_on_touch(
+                        0.5,
+                        MatterCharacteristic::MELTING | MatterCharacteristic::BURNING,
+                        MATTER_FIRE,
+                    ),
+                    MatterReaction::becomes_on_touch(
+                        0.05,
+                        MatterCharacteristic::CORROSIVE,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::becomes_on_touch(
+                        1.0,
+                        MatterCharacteristic::ERASER,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::zero(),
+                ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: 1.0,
+                hardness: 0.0,
+            },
+            // Fantasy powder falling upwards, slower than sand falls
+            MatterDefinition {
+                id: MATTER_FLOAT_DUST,
+                name: "Float Dust".to_string(),
+                color: 0xc8a2e8ff,
+                weight: 0.3,
+                state: MatterState::Powder,
+                dispersion: 0,
+                characteristics: MatterCharacteristic::BURNS,
+                reactions: [
+                    MatterReaction::becomes_on_touch(
+                        0.5,
+                        MatterCharacteristic::MELTING | MatterCharacteristic::BURNING,
+                        MATTER_FIRE,
+                    ),
+                    MatterReaction::becomes_on_touch(
+                        1.0,
+                        MatterCharacteristic::ERASER,
+                        MATTER_EMPTY,
+                    ),
+                    MatterReaction::zero(),
+                    MatterReaction::zero(),
+                    MatterReaction::zero(),
+                ],
+                decay: MatterDecay::none(),
+                impact_sound: None,
+                gravity: -0.5,
+                hardness: 0.0,
             },
         ],
     }
@@ -1,9 +1,15 @@
+use anyhow::*;
 use serde::{Deserialize, Serialize};
 
 use crate::matter::{Direction, MatterCharacteristic, MatterState};
 
 /// If you touch this, also change shaders...
 pub const MAX_TRANSITIONS: u32 = 5;
+/// Decay is run every this many simulation steps, so decay probabilities can describe long
+/// timescales
+pub const DECAY_INTERVAL: usize = 30;
+/// Max reaction tick divider, it's packed in upper 16 bits of reaction direction for shaders
+pub const MAX_REACTION_TICK_DIVIDER: u32 = 0xffff;
 
 #[derive(Serialize, Deserialize, Debug, Copy, Clone)]
 pub struct MatterReaction {
@@ -11,15 +17,24 @@ pub struct MatterReaction {
     pub direction: Direction,
     pub probability: f32,
     pub becomes: u32,
+    /// Reaction is tried only every this many simulation steps, so slow processes (e.g. rust)
+    /// don't need tiny probabilities
+    #[serde(default = "MatterReaction::every_step")]
+    pub tick_divider: u32,
 }
 
 impl MatterReaction {
+    fn every_step() -> u32 {
+        1
+    }
+
     pub fn zero() -> Self {
         MatterReaction {
             reacts: MatterCharacteristic::empty(),
             direction: Direction::NONE,
             probability: 0.0,
             becomes: 0,
+            tick_divider: 1,
         }
     }
 
@@ -29,6 +44,7 @@ impl MatterReaction {
             direction: Direction::ALL,
             probability: p,
             becomes: empty_matter,
+            tick_divider: 1,
         }
     }
 
@@ -42,6 +58,7 @@ impl MatterReaction {
             direction: Direction::ALL,
             probability: p,
             becomes: becomes_matter,
+            tick_divider: 1,
         }
     }
 
@@ -60,6 +77,74 @@ impl MatterReaction {
                 | Direction::LEFT),
             probability: p,
             becomes: becomes_matter,
+            tick_divider: 1,
+        }
+    }
+}
+
+/// Spontaneous change of matter regardless of neighbors, e.g. radioactive decay. Chains are
+/// formed by matter decaying into another decaying matter (uranium -> radium -> lead)
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct MatterDecay {
+    /// Probability to decay on each decay pass (see `DECAY_INTERVAL`)
+    pub probability: f32,
+    pub becomes: u32,
+    /// Matter emitted to a random empty neighbor when decaying (e.g. radiation). Empty for none
+    pub emits: u32,
+}
+
+impl MatterDecay {
+    pub fn none() -> Self {
+        MatterDecay {
+            probability: 0.0,
+            becomes: 0,
+            emits: 0,
+        }
+    }
+}
+
+impl Default for MatterDecay {
+    fn default() -> Self {
+        MatterDecay::none()
+    }
+}
+
+/// Sound of an object hitting matter. Names the sound asset to play
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImpactSound {
+    Silent,
+    Thud,
+    Clink,
+    Splash,
+    Crunch,
+}
+
+pub const ALL_IMPACT_SOUNDS: [ImpactSound; 5] = [
+    ImpactSound::Silent,
+    ImpactSound::Thud,
+    ImpactSound::Clink,
+    ImpactSound::Splash,
+    ImpactSound::Crunch,
+];
+
+impl ImpactSound {
+    /// Sound of matters that don't set one
+    pub fn from_state(state: MatterState) -> ImpactSound {
+        match state {
+            MatterState::Solid | MatterState::SolidGravity => ImpactSound::Thud,
+            MatterState::Powder => ImpactSound::Crunch,
+            MatterState::Liquid => ImpactSound::Splash,
+            _ => ImpactSound::Silent,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImpactSound::Silent => "silent",
+            ImpactSound::Thud => "thud",
+            ImpactSound::Clink => "clink",
+            ImpactSound::Splash => "splash",
+            ImpactSound::Crunch => "crunch",
         }
     }
 }
@@ -82,6 +167,23 @@ pub struct MatterDefinition {
     /// - Example: "Acid might become empty on probability x if touches a material it corroded (corroding)".
     /// Probability will affect the speed at which matter changes
     pub reactions: [MatterReaction; MAX_TRANSITIONS as usize],
+    /// Older matter definitions files have no decay
+    #[serde(default)]
+    pub decay: MatterDecay,
+    /// Sound of objects hitting this matter. None picks one by state
+    #[serde(default)]
+    pub impact_sound: Option<ImpactSound>,
+    /// Scale of gravity on matter that moves by it (powders, liquids & gravity solids fall, gases
+    /// rise). Below 1.0 it moves only on that share of steps, negative reverses its direction
+    #[serde(default = "default_gravity")]
+    pub gravity: f32,
+    /// 0.0 - 1.0, brush strength needed to erase matter in one pass. Softer brushes need more
+    #[serde(default)]
+    pub hardness: f32,
+}
+
+fn default_gravity() -> f32 {
+    1.0
 }
 
 impl MatterDefinition {
@@ -101,8 +203,27 @@ impl MatterDefinition {
                 MatterReaction::zero(),
                 MatterReaction::zero(),
             ],
+            decay: MatterDecay::none(),
+            impact_sound: None,
+            gravity: 1.0,
+            hardness: 0.0,
         }
     }
+
+    /// Chance a brush of `strength` erases matter in one pass. Matter no harder than brush
+    /// strength always breaks, harder matter takes more passes
+    pub fn break_chance(&self, strength: f32) -> f32 {
+        if strength >= self.hardness {
+            1.0
+        } else {
+            (strength / self.hardness).powi(3)
+        }
+    }
+
+    pub fn impact_sound(&self) -> ImpactSound {
+        self.impact_sound
+            .unwrap_or_else(|| ImpactSound::from_state(self.state))
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -116,13 +237,32 @@ impl MatterDefinitions {
         serde_json::to_string(self).unwrap()
     }
 
-    pub fn deserialize(data: &str) -> MatterDefinitions {
-        let deserialized: MatterDefinitions = serde_json::from_str(data).unwrap();
-        deserialized
+    pub fn deserialize(data: &str) -> Result<MatterDefinitions> {
+        Ok(serde_json::from_str(data)?)
+    }
+
+    /// Pairs of matter sharing a color. They look alike & can't be told apart in chunk images
+    pub fn color_collisions(&self) -> Vec<(u32, u32)> {
+        let mut collisions = vec![];
+        for (i, a) in self.definitions.iter().enumerate() {
+            for b in self.definitions[i + 1..].iter() {
+                if a.color == b.color {
+                    collisions.push((a.id, b.id));
+                }
+            }
+        }
+        collisions
     }
 }
 
-pub fn validate_matter_definitions(matter_definitions: &MatterDefinitions) {
+/// Panic on definitions the simulation can't use. Reaction tick dividers outside
+/// 1..=`MAX_REACTION_TICK_DIVIDER`, e.g. 0 from a hand edited file, are clamped into it
+pub fn validate_matter_definitions(matter_definitions: &mut MatterDefinitions) {
+    for m in matter_definitions.definitions.iter_mut() {
+        for r in m.reactions.iter_mut() {
+            r.tick_divider = r.tick_divider.clamp(1, MAX_REACTION_TICK_DIVIDER);
+        }
+    }
     for (i, m) in matter_definitions.definitions.iter().enumerate() {
         if m.id != i as u32 {
             panic!(
@@ -142,5 +282,13 @@ pub fn validate_matter_definitions(matter_definitions: &MatterDefinitions) {
                 m.id, m.name
             )
         }
+        let num_matters = matter_definitions.definitions.len() as u32;
+        if m.decay.becomes >= num_matters || m.decay.emits >= num_matters {
+            panic!(
+                "Matter decay invalid for id: {}, name: {}. 'becomes' & 'emits' must not be \
+                 larger than any id",
+                m.id, m.name
+            )
+        }
     }
 }
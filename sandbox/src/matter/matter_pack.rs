@@ -0,0 +1,235 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::PathBuf,
+};
+
+use anyhow::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data_dirs::DATA_DIRS,
+    matter::{MatterDefinition, MatterDefinitions},
+    utils::data_file_path,
+    MAX_NUM_MATTERS,
+};
+
+/// Matter of a pack. Matter it becomes or emits is referenced by name, so packs can be shared
+/// between users whose matters have different ids. None refers to empty matter
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PackedMatter {
+    /// Definition whose id & matter references are ignored on import
+    pub definition: MatterDefinition,
+    /// Matter each reaction becomes
+    pub reactions_become: Vec<Option<String>>,
+    pub decay_becomes: Option<String>,
+    pub decay_emits: Option<String>,
+}
+
+/// Named set of matters with their reactions, exported for others to import into their
+/// definitions
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MatterPack {
+    pub name: String,
+    pub matters: Vec<PackedMatter>,
+}
+
+/// Outcome of importing a matter pack, by matter name
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PackImport {
+    pub added: Vec<String>,
+    /// Matters that already existed, the existing ones are kept
+    pub skipped: Vec<String>,
+    /// Referenced matters found neither in the pack nor definitions, replaced by empty
+    pub unresolved: Vec<String>,
+}
+
+impl MatterPack {
+    pub fn serialize(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn deserialize(data: &str) -> Result<MatterPack> {
+        Ok(serde_json::from_str(data)?)
+    }
+
+    pub fn load(name: &str) -> Result<MatterPack> {
+        let data = fs::read_to_string(matter_pack_path(name)?)?;
+        MatterPack::deserialize(&data).with_context(|| format!("Invalid matter pack {}", name))
+    }
+
+    /// Save to matter packs of data directory, where packs from others are placed too. Returns
+    /// the file path
+    pub fn save(&self) -> Result<PathBuf> {
+        let path = matter_pack_path(&self.name)?;
+        fs::write(&path, self.serialize())?;
+        Ok(path)
+    }
+}
+
+/// Names of matter packs in data directory, sorted
+pub fn matter_pack_names() -> Result<Vec<String>> {
+    let mut names = vec![];
+    for entry in fs::read_dir(matter_packs_dir()?)? {
+        let path = entry?.path();
+        if path.extension().map_or(false, |ext| ext == "json") {
+            if let Some(name) = path.file_stem() {
+                names.push(name.to_string_lossy().to_string());
+            }
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+fn matter_packs_dir() -> Result<PathBuf> {
+    let dir_path = DATA_DIRS.data.join("matter_packs");
+    fs::create_dir_all(&dir_path)?;
+    Ok(dir_path)
+}
+
+/// Pack file by name, which can't lead outside matter packs directory
+fn matter_pack_path(name: &str) -> Result<PathBuf> {
+    data_file_path(&matter_packs_dir()?, name, "json")
+}
+
+impl MatterDefinitions {
+    /// Pack of matters `ids` along with matters their reactions & decay refer to, so the pack
+    /// is complete on its own. Empty matter is never packed
+    pub fn export_pack(&self, name: &str, ids: &[u32]) -> MatterPack {
+        let mut packed = BTreeSet::new();
+        let mut pending = ids.to_vec();
+        while let Some(id) = pending.pop() {
+            let definition = match self.definitions.get(id as usize) {
+                Some(definition) => definition,
+                None => continue,
+            };
+            if id == self.empty || !packed.insert(id) {
+                continue;
+            }
+            pending.extend(definition.reactions.iter().map(|r| r.becomes));
+            pending.push(definition.decay.becomes);
+            pending.push(definition.decay.emits);
+        }
+        let name_of = |id: u32| {
+            (id != self.empty)
+                .then(|| self.definitions.get(id as usize).map(|d| d.name.clone()))
+                .flatten()
+        };
+        MatterPack {
+            name: name.to_string(),
+            matters: packed
+                .into_iter()
+                .map(|id| {
+                    let definition = self.definitions[id as usize].clone();
+                    PackedMatter {
+                        reactions_become: definition
+                            .reactions
+                            .iter()
+                            .map(|r| name_of(r.becomes))
+                            .collect(),
+                        decay_becomes: name_of(definition.decay.becomes),
+                        decay_emits: name_of(definition.decay.emits),
+                        definition,
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Append matters of pack whose names are not taken, giving them the next free ids & resolving
+    /// their matter references by name
+    pub fn import_pack(&mut self, pack: &MatterPack) -> Result<PackImport> {
+        let mut import = PackImport::default();
+        let mut ids = self
+            .definitions
+            .iter()
+            .map(|d| (d.name.clone(), d.id))
+            .collect::<HashMap<String, u32>>();
+        let mut added = vec![];
+        for matter in pack.matters.iter() {
+            let name = &matter.definition.name;
+            if ids.contains_key(name) {
+                import.skipped.push(name.clone());
+                continue;
+            }
+            let id = (self.definitions.len() + added.len()) as u32;
+            ids.insert(name.clone(), id);
+            added.push(matter);
+        }
+        if self.definitions.len() + added.len() > MAX_NUM_MATTERS as usize {
+            bail!("Matters would exceed max {} matters", MAX_NUM_MATTERS);
+        }
+        let empty = self.empty;
+        let mut resolve = |name: &Option<String>| match name {
+            Some(name) => ids.get(name).copied().unwrap_or_else(|| {
+                if !import.unresolved.contains(name) {
+                    import.unresolved.push(name.clone());
+                }
+                empty
+            }),
+            None => empty,
+        };
+        let mut definitions = vec![];
+        for matter in added {
+            let mut definition = matter.definition.clone();
+            definition.id = ids[&definition.name];
+            for (reaction, becomes) in definition
+                .reactions
+                .iter_mut()
+                .zip(matter.reactions_become.iter())
+            {
+                reaction.becomes = resolve(becomes);
+            }
+            definition.decay.becomes = resolve(&matter.decay_becomes);
+            definition.decay.emits = resolve(&matter.decay_emits);
+            definitions.push(definition);
+        }
+        import.added = definitions.iter().map(|d| d.name.clone()).collect();
+        self.definitions.extend(definitions);
+        Ok(import)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matter::{default_matter_definitions, validate_matter_definitions};
+
+    #[test]
+    fn test_matter_pack_remaps_ids_by_name() {
+        let mut source = default_matter_definitions();
+        let mut goo = MatterDefinition::zero();
+        goo.id = source.definitions.len() as u32;
+        goo.name = "Goo".to_string();
+        let mut slime = MatterDefinition::zero();
+        slime.id = goo.id + 1;
+        slime.name = "Slime".to_string();
+        slime.reactions[0].becomes = goo.id;
+        slime.decay.becomes = 1;
+        source.definitions.push(goo);
+        source.definitions.push(slime.clone());
+        let pack = MatterPack::deserialize(&source.export_pack("Goop", &[slime.id]).serialize())
+            .unwrap();
+        // Goo is packed because slime becomes it, default matters already exist on import
+        let names = pack
+            .matters
+            .iter()
+            .map(|m| m.definition.name.as_str())
+            .collect::<Vec<&str>>();
+        assert!(names.contains(&"Goo") && names.contains(&"Slime"));
+
+        let mut target = default_matter_definitions();
+        target.definitions.truncate(3);
+        let import = target.import_pack(&pack).unwrap();
+        validate_matter_definitions(&mut target);
+        let slime = target.definitions.iter().find(|d| d.name == "Slime").unwrap();
+        let goo = target.definitions.iter().find(|d| d.name == "Goo").unwrap();
+        assert_eq!(slime.reactions[0].becomes, goo.id);
+        assert_eq!(import.added.len(), pack.matters.len() - import.skipped.len());
+        assert!(import.unresolved.is_empty());
+        // Importing again adds nothing
+        let again = target.import_pack(&pack).unwrap();
+        assert!(again.added.is_empty());
+    }
+}
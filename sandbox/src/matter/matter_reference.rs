@@ -0,0 +1,126 @@
+use std::fmt::Write;
+
+use crate::matter::{
+    Direction, MatterCharacteristic, MatterDefinition, MatterDefinitions, ALL_CHARACTERISTICS,
+    ALL_DIRECTIONS, DECAY_INTERVAL,
+};
+
+/// Markdown reference of a matter palette (colors, states, characteristics & reactions), for
+/// sharing custom matters with map collaborators
+pub fn matter_reference_markdown(matter_definitions: &MatterDefinitions) -> String {
+    let mut md = String::new();
+    writeln!(md, "# Matter reference\n").unwrap();
+    writeln!(md, "## Characteristics\n").unwrap();
+    writeln!(md, "| Characteristic | Description |").unwrap();
+    writeln!(md, "| --- | --- |").unwrap();
+    for (_, name, guide) in ALL_CHARACTERISTICS.iter() {
+        writeln!(md, "| {} | {} |", name, guide).unwrap();
+    }
+    writeln!(md, "\n## Matters\n").unwrap();
+    for matter in matter_definitions.definitions.iter() {
+        write_matter(&mut md, matter, matter_definitions);
+    }
+    md
+}
+
+fn write_matter(
+    md: &mut String,
+    matter: &MatterDefinition,
+    matter_definitions: &MatterDefinitions,
+) {
+    let name = |id: u32| {
+        matter_definitions
+            .definitions
+            .get(id as usize)
+            .map_or("Unknown", |m| m.name.as_str())
+    };
+    writeln!(md, "### {}: {}\n", matter.id, matter.name).unwrap();
+    writeln!(md, "- Color: `#{:08x}` (rgba)", matter.color).unwrap();
+    writeln!(md, "- State: {}", matter.state).unwrap();
+    writeln!(md, "- Weight: {}", matter.weight).unwrap();
+    writeln!(md, "- Dispersion: {}", matter.dispersion).unwrap();
+    writeln!(md, "- Gravity: {}", matter.gravity).unwrap();
+    writeln!(md, "- Hardness: {}", matter.hardness).unwrap();
+    writeln!(
+        md,
+        "- Characteristics: {}",
+        characteristic_names(matter.characteristics).unwrap_or_else(|| "None".to_string())
+    )
+    .unwrap();
+    writeln!(md, "- Impact sound: {}", matter.impact_sound().name()).unwrap();
+    if matter.decay.probability > 0.0 {
+        write!(
+            md,
+            "- Decays into {} with probability {} every {} steps",
+            name(matter.decay.becomes),
+            matter.decay.probability,
+            DECAY_INTERVAL
+        )
+        .unwrap();
+        if matter.decay.emits != matter_definitions.empty {
+            write!(md, ", emitting {}", name(matter.decay.emits)).unwrap();
+        }
+        writeln!(md).unwrap();
+    }
+    let reactions = matter
+        .reactions
+        .iter()
+        .filter(|r| r.probability > 0.0 && r.direction != Direction::NONE)
+        .collect::<Vec<_>>();
+    if !reactions.is_empty() {
+        writeln!(md, "\n| Reacts to | Direction | Probability | Every n steps | Becomes |").unwrap();
+        writeln!(md, "| --- | --- | --- | --- | --- |").unwrap();
+        for reaction in reactions {
+            writeln!(
+                md,
+                "| {} | {} | {} | {} | {} |",
+                // Reactions without characteristics match neighbors without any, e.g. empty
+                characteristic_names(reaction.reacts)
+                    .unwrap_or_else(|| "No characteristics".to_string()),
+                direction_names(reaction.direction),
+                reaction.probability,
+                reaction.tick_divider,
+                name(reaction.becomes)
+            )
+            .unwrap();
+        }
+    }
+    writeln!(md).unwrap();
+}
+
+fn characteristic_names(characteristics: MatterCharacteristic) -> Option<String> {
+    let names = ALL_CHARACTERISTICS
+        .iter()
+        .filter(|(c, ..)| characteristics.contains(*c))
+        .map(|(_, name, _)| *name)
+        .collect::<Vec<&str>>();
+    (!names.is_empty()).then(|| names.join(", "))
+}
+
+fn direction_names(direction: Direction) -> String {
+    if direction == Direction::ALL {
+        return "Any".to_string();
+    }
+    ALL_DIRECTIONS
+        .iter()
+        .filter(|(d, _)| direction.contains(*d))
+        .map(|(_, name)| *name)
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matter::default_matter_definitions;
+
+    #[test]
+    fn test_matter_reference_markdown() {
+        let md = matter_reference_markdown(&default_matter_definitions());
+        assert!(md.starts_with("# Matter reference"));
+        assert!(md.contains("| Corrosive | Matter is like acid (destroys other matter) |"));
+        assert!(md.contains("### 1: Sand"));
+        assert!(md.contains("- Decays into Radium with probability 0.002 every 30 steps"));
+        assert!(md.contains("| Corrosive | Any | 0.05 | 1 | Empty |"));
+    }
+}
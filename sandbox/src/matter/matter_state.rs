@@ -73,6 +73,26 @@ bitflags! {
         const VAPORIZES = 1 << 16;
         /// Eraser
         const ERASER = 1 << 17;
+
+        /// Moves objects resting on top of it to the left
+        const CONVEYOR_LEFT = 1 << 18;
+        /// Moves objects resting on top of it to the right
+        const CONVEYOR_RIGHT = 1 << 19;
+        /// Blows powders & objects upwards in the column above it (see FAN_REACH)
+        const FAN = 1 << 20;
+
+        /// A material that wets others
+        const WETTING = 1 << 21;
+        /// A material that becomes wet by wetting
+        const WETS = 1 << 22;
+        /// Soil on which seeds germinate
+        const FERTILE = 1 << 23;
+        /// Plant tip, empty space above it grows into the plant
+        const GROWING = 1 << 24;
+        /// Empty space diagonally below it receives seeds
+        const SEEDING = 1 << 25;
+        /// Eaten by bugs
+        const EDIBLE = 1 << 26;
     }
 }
 
@@ -130,7 +150,7 @@ impl<'de> Deserialize<'de> for MatterCharacteristic {
     }
 }
 
-pub const ALL_CHARACTERISTICS: [(MatterCharacteristic, &str, &str); 18] = [
+pub const ALL_CHARACTERISTICS: [(MatterCharacteristic, &str, &str); 27] = [
     (
         MatterCharacteristic::CORROSIVE,
         "Corrosive",
@@ -221,6 +241,51 @@ pub const ALL_CHARACTERISTICS: [(MatterCharacteristic, &str, &str); 18] = [
         "Eraser",
         "Matter erases others",
     ),
+    (
+        MatterCharacteristic::CONVEYOR_LEFT,
+        "Conveyor Left",
+        "Matter pushes objects on top of it to the left",
+    ),
+    (
+        MatterCharacteristic::CONVEYOR_RIGHT,
+        "Conveyor Right",
+        "Matter pushes objects on top of it to the right",
+    ),
+    (
+        MatterCharacteristic::FAN,
+        "Fan",
+        "Matter blows powders & objects upwards above it",
+    ),
+    (
+        MatterCharacteristic::WETTING,
+        "Wetting",
+        "Matter wets others",
+    ),
+    (
+        MatterCharacteristic::WETS,
+        "Wets",
+        "Matter becomes wet by wetting matter",
+    ),
+    (
+        MatterCharacteristic::FERTILE,
+        "Fertile",
+        "Seeds germinate on top of matter",
+    ),
+    (
+        MatterCharacteristic::GROWING,
+        "Growing",
+        "Plant grows into empty space above matter",
+    ),
+    (
+        MatterCharacteristic::SEEDING,
+        "Seeding",
+        "Matter drops seeds diagonally below it",
+    ),
+    (
+        MatterCharacteristic::EDIBLE,
+        "Edible",
+        "Matter is eaten by bugs",
+    ),
 ];
 
 bitflags! {
@@ -0,0 +1,312 @@
+use rand::Rng;
+
+use crate::matter::{
+    Direction, MatterCharacteristic, MatterDefinition, MatterDefinitions, MatterReaction,
+    MAX_TRANSITIONS,
+};
+
+/// Problem in matter definitions, found by `MatterDefinitions::validate`. Reactions are indexed
+/// in matter's reactions, unused reactions (zero probability) are not checked
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatterIssue {
+    /// Reaction becomes matter that doesn't exist, None being decay
+    DanglingBecomes {
+        matter: u32,
+        reaction: Option<usize>,
+        becomes: u32,
+    },
+    /// Reaction can never happen
+    UnreachableReaction {
+        matter: u32,
+        reaction: usize,
+        reason: &'static str,
+    },
+    /// Probability outside 0.0 - 1.0
+    InvalidProbability {
+        matter: u32,
+        reaction: usize,
+        probability: f32,
+    },
+    /// Reactions are tried in order, so with a sum above 1.0 later reactions happen less often
+    /// than their probability
+    ProbabilitySum {
+        matter: u32,
+        sum: f32,
+    },
+}
+
+impl MatterIssue {
+    pub fn describe(&self, matter_definitions: &MatterDefinitions) -> String {
+        let name = |id: u32| {
+            matter_definitions
+                .definitions
+                .get(id as usize)
+                .map_or("Unknown", |m| m.name.as_str())
+        };
+        match *self {
+            MatterIssue::DanglingBecomes {
+                matter,
+                reaction,
+                becomes,
+            } => match reaction {
+                Some(reaction) => format!(
+                    "{} reaction {} becomes missing matter {}",
+                    name(matter),
+                    reaction + 1,
+                    becomes
+                ),
+                None => format!("{} decays into missing matter {}", name(matter), becomes),
+            },
+            MatterIssue::UnreachableReaction {
+                matter,
+                reaction,
+                reason,
+            } => format!(
+                "{} reaction {} never happens: {}",
+                name(matter),
+                reaction + 1,
+                reason
+            ),
+            MatterIssue::InvalidProbability {
+                matter,
+                reaction,
+                probability,
+            } => format!(
+                "{} reaction {} probability {} is not within 0 - 1",
+                name(matter),
+                reaction + 1,
+                probability
+            ),
+            MatterIssue::ProbabilitySum {
+                matter,
+                sum,
+            } => format!(
+                "{} reaction probabilities sum to {:.2}, later reactions happen less often",
+                name(matter),
+                sum
+            ),
+        }
+    }
+}
+
+impl MatterDefinitions {
+    /// Find dangling matter references, reactions that can't happen & probabilities that don't
+    /// work as written. Unlike `validate_matter_definitions`, issues are returned for showing
+    pub fn validate(&self) -> Vec<MatterIssue> {
+        let mut issues = vec![];
+        let num_matters = self.definitions.len() as u32;
+        for matter in self.definitions.iter() {
+            let mut sum = 0.0;
+            for (index, reaction) in matter.reactions.iter().enumerate() {
+                if reaction.probability == 0.0 {
+                    continue;
+                }
+                sum += reaction.probability;
+                if reaction.becomes >= num_matters {
+                    issues.push(MatterIssue::DanglingBecomes {
+                        matter: matter.id,
+                        reaction: Some(index),
+                        becomes: reaction.becomes,
+                    });
+                }
+                if !(0.0..=1.0).contains(&reaction.probability) {
+                    issues.push(MatterIssue::InvalidProbability {
+                        matter: matter.id,
+                        reaction: index,
+                        probability: reaction.probability,
+                    });
+                }
+                if let Some(reason) = self.unreachable_reason(matter, index) {
+                    issues.push(MatterIssue::UnreachableReaction {
+                        matter: matter.id,
+                        reaction: index,
+                        reason,
+                    });
+                }
+            }
+            if sum > 1.0 {
+                issues.push(MatterIssue::ProbabilitySum {
+                    matter: matter.id,
+                    sum,
+                });
+            }
+            if matter.decay.probability > 0.0 {
+                for becomes in [matter.decay.becomes, matter.decay.emits] {
+                    if becomes >= num_matters {
+                        issues.push(MatterIssue::DanglingBecomes {
+                            matter: matter.id,
+                            reaction: None,
+                            becomes,
+                        });
+                    }
+                }
+            }
+        }
+        issues
+    }
+
+    fn unreachable_reason(&self, matter: &MatterDefinition, index: usize) -> Option<&'static str> {
+        let reaction = &matter.reactions[index];
+        if reaction.direction == Direction::NONE {
+            return Some("no direction");
+        }
+        if !self
+            .definitions
+            .iter()
+            .any(|m| reacts_to(reaction.reacts, m.characteristics))
+        {
+            return Some("no matter has the characteristic it reacts to");
+        }
+        // Earlier reaction that always happens in the same situations
+        let is_shadowed = matter.reactions[..index].iter().any(|earlier| {
+            earlier.probability >= 1.0
+                && earlier.tick_divider <= 1
+                && earlier.reacts == reaction.reacts
+                && earlier.direction.contains(reaction.direction)
+        });
+        if is_shadowed {
+            return Some("an earlier reaction always happens first");
+        }
+        None
+    }
+}
+
+/// Whether a reaction to `reacts` happens next to matter of `characteristics`. Reacting to no
+/// characteristics matches matter without characteristics, e.g. empty
+fn reacts_to(reacts: MatterCharacteristic, characteristics: MatterCharacteristic) -> bool {
+    reacts.intersects(characteristics) || reacts == characteristics
+}
+
+/// Index of the first reaction of `matter` that happens at `sim_step` next to `neighbors`, like
+/// the react shader does. Neighbors are ordered as `ALL_DIRECTIONS` & `rolls` are the random
+/// numbers reactions' probabilities are compared to
+pub fn reaction_at(
+    matter_definitions: &MatterDefinitions,
+    matter: &MatterDefinition,
+    neighbors: &[u32; 8],
+    sim_step: u32,
+    rolls: [f32; MAX_TRANSITIONS as usize],
+) -> Option<usize> {
+    let touches = |reaction: &MatterReaction| {
+        neighbors.iter().enumerate().any(|(bit, &neighbor)| {
+            let characteristics = matter_definitions
+                .definitions
+                .get(neighbor as usize)
+                .map_or(MatterCharacteristic::empty(), |m| m.characteristics);
+            reaction.direction.bits() & (1 << bit) != 0
+                && reacts_to(reaction.reacts, characteristics)
+        })
+    };
+    matter.reactions.iter().zip(rolls).position(|(reaction, roll)| {
+        roll < reaction.probability
+            && sim_step % reaction.tick_divider.max(1) == 0
+            && touches(reaction)
+    })
+}
+
+/// Reactions counted over steps of a cell whose neighbors stay the same
+#[derive(Debug, Clone, PartialEq)]
+pub struct DryRun {
+    pub steps: u32,
+    /// Times each reaction happened
+    pub reactions: [u32; MAX_TRANSITIONS as usize],
+}
+
+impl DryRun {
+    /// Step a cell of `matter` among `neighbors` on cpu. The cell is restored after each reaction,
+    /// so counts estimate how often each reaction happens per step
+    pub fn run(
+        matter_definitions: &MatterDefinitions,
+        matter: &MatterDefinition,
+        neighbors: &[u32; 8],
+        steps: u32,
+        rng: &mut impl Rng,
+    ) -> DryRun {
+        let mut reactions = [0; MAX_TRANSITIONS as usize];
+        for sim_step in 0..steps {
+            let rolls = rng.gen::<[f32; MAX_TRANSITIONS as usize]>();
+            if let Some(index) =
+                reaction_at(matter_definitions, matter, neighbors, sim_step, rolls)
+            {
+                reactions[index] += 1;
+            }
+        }
+        DryRun {
+            steps,
+            reactions,
+        }
+    }
+
+    /// Share of steps on which nothing happened
+    pub fn unchanged(&self) -> f32 {
+        let reacted = self.reactions.iter().sum::<u32>();
+        (self.steps - reacted) as f32 / self.steps.max(1) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::matter::{default_matter_definitions, MATTER_EMPTY, MATTER_SAND};
+
+    #[test]
+    fn test_validate_finds_issues() {
+        let mut matter_definitions = default_matter_definitions();
+        let mut goo = MatterDefinition::zero();
+        goo.id = matter_definitions.definitions.len() as u32;
+        goo.name = "Goo".to_string();
+        goo.reactions[0] = MatterReaction::dies(1.0, MATTER_EMPTY);
+        // Same as the first, which always happens
+        goo.reactions[1] = MatterReaction::dies(0.5, 999);
+        goo.reactions[2].probability = 0.1;
+        let goo_id = goo.id;
+        matter_definitions.definitions.push(goo);
+        let issues = matter_definitions
+            .validate()
+            .into_iter()
+            .filter(|issue| issue.describe(&matter_definitions).starts_with("Goo"))
+            .collect::<Vec<MatterIssue>>();
+        assert!(issues.contains(&MatterIssue::DanglingBecomes {
+            matter: goo_id,
+            reaction: Some(1),
+            becomes: 999,
+        }));
+        assert!(issues.iter().any(|issue| matches!(
+            issue,
+            MatterIssue::UnreachableReaction {
+                reaction: 1,
+                ..
+            }
+        )));
+        assert!(issues.iter().any(|issue| matches!(
+            issue,
+            MatterIssue::UnreachableReaction {
+                reaction: 2,
+                reason,
+                ..
+            } if *reason == "no direction"
+        )));
+        assert!(issues.iter().any(|issue| matches!(issue, MatterIssue::ProbabilitySum { .. })));
+    }
+
+    #[test]
+    fn test_dry_run_reacts_only_to_neighbors() {
+        let matter_definitions = default_matter_definitions();
+        let mut goo = MatterDefinition::zero();
+        goo.reactions[0] = MatterReaction::dies(0.5, MATTER_EMPTY);
+        goo.reactions[0].direction = Direction::UP;
+        let mut rng = StdRng::seed_from_u64(3);
+        // Reacts to empty above
+        let mut neighbors = [MATTER_SAND; 8];
+        neighbors[1] = MATTER_EMPTY;
+        let run = DryRun::run(&matter_definitions, &goo, &neighbors, 1000, &mut rng);
+        assert!((run.reactions[0] as f32 / 1000.0 - 0.5).abs() < 0.1);
+        // Not to empty below
+        let mut neighbors = [MATTER_SAND; 8];
+        neighbors[5] = MATTER_EMPTY;
+        let run = DryRun::run(&matter_definitions, &goo, &neighbors, 1000, &mut rng);
+        assert_eq!(run.unchanged(), 1.0);
+    }
+}
@@ -1,7 +1,13 @@
 mod example_matter_definitions;
 mod matter_definition;
+mod matter_pack;
+mod matter_reference;
 mod matter_state;
+mod matter_validation;
 
 pub use example_matter_definitions::*;
 pub use matter_definition::*;
+pub use matter_pack::*;
+pub use matter_reference::*;
 pub use matter_state::*;
+pub use matter_validation::*;
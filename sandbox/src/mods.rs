@@ -0,0 +1,359 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::*;
+
+use crate::{
+    data_dirs::DATA_DIRS,
+    interact::Editor,
+    matter::MatterDefinitions,
+    utils::{load_bitmap_image_from_path, BitmapImage},
+    MAX_NUM_MATTERS,
+};
+
+/// Contents of a mod directory, all optional
+const MOD_MATTERS_FILE: &str = "matter_definitions.json";
+const MOD_OBJECT_IMAGES_DIR: &str = "object_images";
+const MOD_STAMPS_DIR: &str = "stamps";
+/// Reserved for scripts, which aren't run yet
+const MOD_SCRIPTS_DIR: &str = "scripts";
+/// Names of disabled mods, in mods directory
+const DISABLED_MODS_FILE: &str = "disabled.json";
+
+/// Content pack in a directory of assets/mods
+pub struct Mod {
+    pub name: String,
+    pub enabled: bool,
+    /// Names of content the mod added (conflicting content isn't added)
+    pub matters: Vec<String>,
+    pub object_images: Vec<String>,
+    pub stamps: Vec<String>,
+    pub has_scripts: bool,
+}
+
+/// Mods scanned at startup. Base content wins name conflicts, then mods in alphabetical order
+pub struct Mods {
+    pub mods: Vec<Mod>,
+    /// Mod content skipped because its name was taken
+    pub conflicts: Vec<String>,
+    /// Mods were enabled or disabled, which applies on restart
+    pub changed: bool,
+    dir_path: PathBuf,
+}
+
+impl Mods {
+    /// Find mods in assets/mods. Their content is added with `add_images` & `add_matters`
+    pub fn scan() -> Result<Mods> {
+        let dir_path = DATA_DIRS.assets.join("mods");
+        fs::create_dir_all(&dir_path)?;
+        let disabled = match fs::read_to_string(dir_path.join(DISABLED_MODS_FILE)) {
+            std::result::Result::Ok(data) => {
+                serde_json::from_str::<Vec<String>>(&data).unwrap_or_else(|e| {
+                    warn!("Invalid {}, enabling all mods: {}", DISABLED_MODS_FILE, e);
+                    vec![]
+                })
+            }
+            Err(_) => vec![],
+        };
+        let mut names = vec![];
+        for entry in fs::read_dir(&dir_path)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                names.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        names.sort();
+        let mods = names
+            .into_iter()
+            .map(|name| Mod {
+                enabled: !disabled.contains(&name),
+                has_scripts: dir_path.join(&name).join(MOD_SCRIPTS_DIR).is_dir(),
+                name,
+                matters: vec![],
+                object_images: vec![],
+                stamps: vec![],
+            })
+            .collect();
+        Ok(Mods {
+            mods,
+            conflicts: vec![],
+            changed: false,
+            dir_path,
+        })
+    }
+
+    /// Add object images & stamps of enabled mods to editor. Returns errors of files that could
+    /// not be loaded
+    pub fn add_images(&mut self, editor: &mut Editor) -> Vec<String> {
+        let Mods {
+            mods,
+            conflicts,
+            dir_path,
+            ..
+        } = self;
+        let mut errors = vec![];
+        for m in mods.iter_mut().filter(|m| m.enabled) {
+            let mod_path = dir_path.join(&m.name);
+            m.object_images = add_mod_images(
+                &mod_path.join(MOD_OBJECT_IMAGES_DIR),
+                &m.name,
+                "object image",
+                &mut editor.placer.obj_image_assets,
+                conflicts,
+                &mut errors,
+            );
+            m.stamps = add_mod_images(
+                &mod_path.join(MOD_STAMPS_DIR),
+                &m.name,
+                "stamp",
+                &mut editor.stamper.stamp_assets,
+                conflicts,
+                &mut errors,
+            );
+        }
+        // Mods may provide the only images
+        if editor.placer.place_object.is_none() {
+            editor.placer.place_object = editor.placer.obj_image_assets.keys().next().cloned();
+        }
+        if editor.stamper.stamp.is_none() {
+            editor.stamper.stamp = editor.stamper.stamp_assets.keys().next().cloned();
+        }
+        errors
+    }
+
+    /// Append matters of enabled mods to definitions. Returns errors of mods whose matters could
+    /// not be added
+    pub fn add_matters(&mut self, matter_definitions: &mut MatterDefinitions) -> Vec<String> {
+        let Mods {
+            mods,
+            conflicts,
+            dir_path,
+            ..
+        } = self;
+        let mut sources = matter_definitions
+            .definitions
+            .iter()
+            .map(|m| (m.name.clone(), "base matters".to_string()))
+            .collect::<HashMap<String, String>>();
+        let mut errors = vec![];
+        for m in mods.iter_mut().filter(|m| m.enabled) {
+            let data = match fs::read_to_string(dir_path.join(&m.name).join(MOD_MATTERS_FILE)) {
+                std::result::Result::Ok(data) => data,
+                Err(_) => continue,
+            };
+            let result = MatterDefinitions::deserialize(&data).and_then(|palette| {
+                merge_mod_matters(matter_definitions, &m.name, &palette, &mut sources, conflicts)
+            });
+            match result {
+                std::result::Result::Ok(added) => m.matters = added,
+                Err(e) => errors.push(format!("Failed to add matters of mod {}: {:#}", m.name, e)),
+            }
+        }
+        errors
+    }
+
+    /// Names of matters enabled mods added
+    pub fn matter_names(&self) -> BTreeSet<String> {
+        self.mods
+            .iter()
+            .flat_map(|m| m.matters.iter().cloned())
+            .collect()
+    }
+
+    /// Enable or disable a mod & save the choice
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) -> Result<()> {
+        self.mods[index].enabled = enabled;
+        self.changed = true;
+        let disabled = self
+            .mods
+            .iter()
+            .filter(|m| !m.enabled)
+            .map(|m| m.name.clone())
+            .collect::<Vec<String>>();
+        fs::write(
+            self.dir_path.join(DISABLED_MODS_FILE),
+            serde_json::to_string(&disabled)?,
+        )?;
+        Ok(())
+    }
+}
+
+/// Load images of a mod directory whose file names are not taken. Returns names of added images
+fn add_mod_images(
+    dir_path: &Path,
+    mod_name: &str,
+    kind: &str,
+    images: &mut BTreeMap<String, Arc<BitmapImage>>,
+    conflicts: &mut Vec<String>,
+    errors: &mut Vec<String>,
+) -> Vec<String> {
+    let mut added = vec![];
+    let entries = match fs::read_dir(dir_path) {
+        std::result::Result::Ok(entries) => entries,
+        Err(_) => return added,
+    };
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if images.contains_key(&file_name) {
+            conflicts.push(format!(
+                "Mod {} {} {} conflicts with an existing one",
+                mod_name, kind, file_name
+            ));
+            continue;
+        }
+        match load_bitmap_image_from_path(entry.path()) {
+            std::result::Result::Ok(image) => {
+                images.insert(file_name.clone(), Arc::new(image));
+                added.push(file_name);
+            }
+            Err(e) => errors.push(format!(
+                "Failed to load {} {} of mod {}: {:#}",
+                kind, file_name, mod_name, e
+            )),
+        }
+    }
+    added.sort();
+    added
+}
+
+/// Append matters of a mod palette with new names, remapping the ids their reactions & decay
+/// refer to. Matters whose names are taken are not added, references to them refer to the
+/// existing matter. Returns names of added matters
+fn merge_mod_matters(
+    matter_definitions: &mut MatterDefinitions,
+    mod_name: &str,
+    palette: &MatterDefinitions,
+    sources: &mut HashMap<String, String>,
+    conflicts: &mut Vec<String>,
+) -> Result<Vec<String>> {
+    let empty = matter_definitions.empty;
+    let mut ids = vec![empty; palette.definitions.len()];
+    let mut added = vec![];
+    let mut next_id = matter_definitions.definitions.len() as u32;
+    for (index, matter) in palette.definitions.iter().enumerate() {
+        if index as u32 == palette.empty {
+            continue;
+        }
+        match matter_definitions
+            .definitions
+            .iter()
+            .find(|m| m.name == matter.name)
+        {
+            Some(existing) => {
+                ids[index] = existing.id;
+                conflicts.push(format!(
+                    "Mod {} matter {} conflicts with {}",
+                    mod_name,
+                    matter.name,
+                    sources.get(&matter.name).map_or("another mod", |s| s.as_str())
+                ));
+            }
+            None => {
+                ids[index] = next_id;
+                next_id += 1;
+                added.push(index);
+            }
+        }
+    }
+    if next_id > MAX_NUM_MATTERS {
+        bail!("Matters would exceed max {} matters", MAX_NUM_MATTERS);
+    }
+    let id = |old: u32| ids.get(old as usize).copied().unwrap_or(empty);
+    let mut names = vec![];
+    for index in added {
+        let mut matter = palette.definitions[index].clone();
+        matter.id = ids[index];
+        for reaction in matter.reactions.iter_mut() {
+            reaction.becomes = id(reaction.becomes);
+        }
+        matter.decay.becomes = id(matter.decay.becomes);
+        matter.decay.emits = id(matter.decay.emits);
+        sources.insert(matter.name.clone(), format!("mod {}", mod_name));
+        names.push(matter.name.clone());
+        matter_definitions.definitions.push(matter);
+    }
+    Ok(names)
+}
+
+/// Definitions without matters named in `mod_matters`, e.g. to save only base matters. Remaining
+/// matters get consecutive ids & references to removed matters become empty
+pub fn without_mod_matters(
+    matter_definitions: &MatterDefinitions,
+    mod_matters: &BTreeSet<String>,
+) -> MatterDefinitions {
+    let mut ids = vec![None; matter_definitions.definitions.len()];
+    let mut kept = vec![];
+    for (index, matter) in matter_definitions.definitions.iter().enumerate() {
+        if index as u32 == matter_definitions.empty || !mod_matters.contains(&matter.name) {
+            ids[index] = Some(kept.len() as u32);
+            kept.push(matter.clone());
+        }
+    }
+    let empty = ids[matter_definitions.empty as usize].unwrap();
+    let id = |old: u32| ids.get(old as usize).copied().flatten().unwrap_or(empty);
+    for (new_id, matter) in kept.iter_mut().enumerate() {
+        matter.id = new_id as u32;
+        for reaction in matter.reactions.iter_mut() {
+            reaction.becomes = id(reaction.becomes);
+        }
+        matter.decay.becomes = id(matter.decay.becomes);
+        matter.decay.emits = id(matter.decay.emits);
+    }
+    MatterDefinitions {
+        definitions: kept,
+        empty,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matter::{
+        default_matter_definitions, validate_matter_definitions, MatterDefinition, MATTER_SAND,
+    };
+
+    #[test]
+    fn test_merge_mod_matters() {
+        let mut matter_definitions = default_matter_definitions();
+        let num_base = matter_definitions.definitions.len() as u32;
+        let mut sand = MatterDefinition::zero();
+        sand.id = 1;
+        sand.name = "Sand".to_string();
+        let mut goo = MatterDefinition::zero();
+        goo.id = 2;
+        goo.name = "Goo".to_string();
+        goo.reactions[0].becomes = 1;
+        goo.decay.becomes = 2;
+        let palette = MatterDefinitions {
+            definitions: vec![MatterDefinition::zero(), sand, goo],
+            empty: 0,
+        };
+        let mut sources = HashMap::new();
+        sources.insert("Sand".to_string(), "base matters".to_string());
+        let mut conflicts = vec![];
+        let added = merge_mod_matters(
+            &mut matter_definitions,
+            "Slime",
+            &palette,
+            &mut sources,
+            &mut conflicts,
+        )
+        .unwrap();
+        assert_eq!(added, vec!["Goo".to_string()]);
+        assert_eq!(conflicts, vec!["Mod Slime matter Sand conflicts with base matters"]);
+        let goo = &matter_definitions.definitions[num_base as usize];
+        assert_eq!(goo.id, num_base);
+        assert_eq!(goo.reactions[0].becomes, MATTER_SAND);
+        assert_eq!(goo.decay.becomes, num_base);
+        validate_matter_definitions(&mut matter_definitions);
+        // Saved base matters don't keep the mod's
+        let mod_matters = added.into_iter().collect();
+        let base = without_mod_matters(&matter_definitions, &mod_matters);
+        assert_eq!(base.definitions.len() as u32, num_base);
+        assert!(base.definitions.iter().all(|m| m.name != "Goo"));
+    }
+}
@@ -0,0 +1,112 @@
+use corrode::physics::{Physics, ONE_WAY_PLATFORM};
+use rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Number of collision layers objects can be put on
+pub const NUM_OBJECT_LAYERS: u32 = 8;
+/// Mask of all object layers
+pub const ALL_OBJECT_LAYERS: u32 = (1 << NUM_OBJECT_LAYERS) - 1;
+/// Collision group of boundary colliders (solids, powders & liquids of the grid)
+pub const BOUNDARY_GROUP: u32 = 1 << NUM_OBJECT_LAYERS;
+
+/// How a pixel object collides with others
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CollisionPreset {
+    /// Collides with boundaries & objects of the layers it collides with
+    Solid,
+    /// Solid from above, objects pass through from below & land on top
+    OneWayPlatform,
+    /// Collides only with boundaries, objects pass through it
+    Ghost,
+}
+
+/// Collision groups of a pixel object, kept on the entity so pieces of deformed objects get the
+/// same groups
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ObjectCollision {
+    pub preset: CollisionPreset,
+    /// Layer the object is on, `0..NUM_OBJECT_LAYERS`
+    pub layer: u32,
+    /// Mask of object layers the object collides with
+    pub collides_with: u32,
+}
+
+impl Default for ObjectCollision {
+    fn default() -> Self {
+        ObjectCollision {
+            preset: CollisionPreset::Solid,
+            layer: 0,
+            collides_with: ALL_OBJECT_LAYERS,
+        }
+    }
+}
+
+impl ObjectCollision {
+    pub fn interaction_groups(&self) -> InteractionGroups {
+        let filter = match self.preset {
+            CollisionPreset::Ghost => BOUNDARY_GROUP,
+            _ => self.collides_with | BOUNDARY_GROUP,
+        };
+        InteractionGroups::new(1 << self.layer, filter)
+    }
+
+    /// Set collision groups (and one-way platform hooks) of all colliders of the body
+    pub fn apply(&self, physics: &mut Physics, rb: RigidBodyHandle) {
+        let groups = self.interaction_groups();
+        let one_way = self.preset == CollisionPreset::OneWayPlatform;
+        for handle in physics.bodies[rb].colliders() {
+            set_collider_groups(&mut physics.colliders[*handle], groups, one_way);
+        }
+    }
+}
+
+/// Collision groups of boundary colliders colliding with given object layers. Colliders outside
+/// object layers (e.g. trigger zones) keep colliding with boundaries
+pub fn boundary_interaction_groups(collides_with: u32) -> InteractionGroups {
+    InteractionGroups::new(BOUNDARY_GROUP, collides_with | !ALL_OBJECT_LAYERS)
+}
+
+pub fn set_collider_groups(collider: &mut Collider, groups: InteractionGroups, one_way: bool) {
+    collider.set_collision_groups(groups);
+    if one_way {
+        collider.user_data |= ONE_WAY_PLATFORM;
+        collider.set_active_hooks(ActiveHooks::MODIFY_SOLVER_CONTACTS);
+    } else {
+        collider.user_data &= !ONE_WAY_PLATFORM;
+        collider.set_active_hooks(ActiveHooks::empty());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interacts(a: InteractionGroups, b: InteractionGroups) -> bool {
+        a.memberships & b.filter != 0 && b.memberships & a.filter != 0
+    }
+
+    #[test]
+    fn test_object_collision_groups() {
+        let boundaries = boundary_interaction_groups(ALL_OBJECT_LAYERS);
+        let solid = ObjectCollision::default().interaction_groups();
+        let layer_1 = ObjectCollision {
+            layer: 1,
+            collides_with: 1 << 1,
+            ..ObjectCollision::default()
+        }
+        .interaction_groups();
+        let ghost = ObjectCollision {
+            preset: CollisionPreset::Ghost,
+            ..ObjectCollision::default()
+        }
+        .interaction_groups();
+        assert!(interacts(solid, boundaries));
+        assert!(interacts(layer_1, boundaries));
+        assert!(interacts(ghost, boundaries));
+        // Layer 1 object collides only with its own layer
+        assert!(!interacts(solid, layer_1));
+        assert!(!interacts(ghost, solid));
+        // Boundaries can be set to let objects of a layer through
+        assert!(!interacts(layer_1, boundary_interaction_groups(1)));
+    }
+}
@@ -31,6 +31,7 @@ pub fn form_pixel_data_with_contours_from_image(
                     matter: empty_matter,
                     color_index: index,
                     is_alive: false,
+                    char_level: 0,
                 };
                 bitmap[flipped_y_index] = 0.0;
             } else {
@@ -38,6 +39,7 @@ pub fn form_pixel_data_with_contours_from_image(
                     matter,
                     color_index: index,
                     is_alive: true,
+                    char_level: 0,
                 };
             }
         }
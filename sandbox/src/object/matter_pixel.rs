@@ -5,14 +5,25 @@ pub struct MatterPixel {
     pub matter: u32,
     pub color_index: usize,
     pub is_alive: bool,
+    /// How scorched the pixel is by fire next to it, 0 - 255
+    pub char_level: u8,
 }
 
+/// How much of its color a fully charred pixel loses
+const MAX_CHAR_DARKENING: f32 = 0.8;
+
 impl MatterPixel {
     pub fn zero(empty_matter: u32) -> MatterPixel {
         MatterPixel {
             matter: empty_matter,
             color_index: 0,
             is_alive: false,
+            char_level: 0,
         }
     }
+
+    /// Multiplier of pixel's color channels, lower the more charred the pixel is
+    pub fn char_brightness(&self) -> f32 {
+        1.0 - self.char_level as f32 / u8::MAX as f32 * MAX_CHAR_DARKENING
+    }
 }
@@ -1,3 +1,4 @@
+mod collision_groups;
 mod contour_formation;
 mod deformation_utils;
 mod matter_pixel;
@@ -5,6 +6,7 @@ mod objects;
 mod physics_components;
 mod pixels;
 
+pub use collision_groups::*;
 pub use contour_formation::*;
 pub use deformation_utils::*;
 pub use matter_pixel::*;
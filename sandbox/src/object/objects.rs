@@ -9,10 +9,10 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     object::{
-        Angle, AngularVelocity, DynamicRigidbody, LinearVelocity, MatterPixel, PixelData, Position,
-        SensorRigidbody, StaticRigidbody, TempPixel,
+        Angle, AngularVelocity, DynamicRigidbody, FixedObject, LinearVelocity, MatterPixel,
+        ObjectCollision, PixelData, Position, SensorRigidbody, StaticRigidbody, TempPixel,
     },
-    sim::Simulation,
+    sim::{ObjectJointSaveData, Simulation},
     utils::BitmapImage,
 };
 
@@ -145,9 +145,41 @@ pub(crate) fn invisible_sensor_object(
     (rb, Position(pos), Angle(angle))
 }
 
+/// Turn a pixel object into a static one. Its body gets fixed in place, but it's still written to
+/// the grid & deformed like dynamic pixel objects
+pub fn make_pixel_object_fixed(
+    ecs_world: &mut World,
+    physics: &mut Physics,
+    id: Entity,
+) -> Result<()> {
+    let rb = *ecs_world.get::<RigidBodyHandle>(id)?;
+    if let Some(body) = physics.bodies.get_mut(rb) {
+        body.set_body_type(RigidBodyType::Fixed);
+        body.set_linvel(Vector::zeros(), false);
+        body.set_angvel(0.0, false);
+    }
+    ecs_world.insert_one(id, FixedObject)?;
+    Ok(())
+}
+
+/// Set collision groups of a pixel object's colliders & remember them on the entity
+pub fn set_pixel_object_collision(
+    ecs_world: &mut World,
+    physics: &mut Physics,
+    id: Entity,
+    collision: ObjectCollision,
+) -> Result<()> {
+    let rb = *ecs_world.get::<RigidBodyHandle>(id)?;
+    collision.apply(physics, rb);
+    ecs_world.insert_one(id, collision)?;
+    Ok(())
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct PixelObjectSaveDataArray {
     pub objects: Vec<PixelObjectSaveData>,
+    #[serde(default)]
+    pub joints: Vec<ObjectJointSaveData>,
 }
 
 impl PixelObjectSaveDataArray {
@@ -155,9 +187,8 @@ impl PixelObjectSaveDataArray {
         serde_json::to_string(self).unwrap()
     }
 
-    pub fn deserialize(data: &str) -> PixelObjectSaveDataArray {
-        let deserialized: PixelObjectSaveDataArray = serde_json::from_str(data).unwrap();
-        deserialized
+    pub fn deserialize(data: &str) -> Result<PixelObjectSaveDataArray> {
+        Ok(serde_json::from_str(data)?)
     }
 }
 
@@ -169,6 +200,10 @@ pub struct PixelObjectSaveData {
     pub lin_vel: Vector2<f32>,
     pub ang_vel: f32,
     pub matter: u32,
+    #[serde(default)]
+    pub fixed: bool,
+    #[serde(default)]
+    pub collision: ObjectCollision,
 }
 
 impl PixelObjectSaveData {
@@ -180,7 +215,7 @@ impl PixelObjectSaveData {
         simulation: &mut Simulation,
         image: &Arc<BitmapImage>,
     ) -> Result<Entity> {
-        simulation.add_dynamic_pixel_object(
+        let id = simulation.add_dynamic_pixel_object(
             ecs_world,
             physics_world,
             image,
@@ -189,12 +224,19 @@ impl PixelObjectSaveData {
             self.lin_vel,
             self.angle,
             self.ang_vel,
-        )
+        )?;
+        if self.fixed {
+            make_pixel_object_fixed(ecs_world, &mut physics_world.physics, id)?;
+        }
+        set_pixel_object_collision(ecs_world, &mut physics_world.physics, id, self.collision)?;
+        Ok(id)
     }
 
     pub fn from_dynamic_pixel_object(
         id: Entity,
         object_data: (PixelData, Position, LinearVelocity, Angle, AngularVelocity),
+        fixed: bool,
+        collision: ObjectCollision,
     ) -> PixelObjectSaveData {
         let (pixel_data, pos, lin_vel, angle, ang_vel) = object_data;
         let lin_vel = lin_vel.0;
@@ -208,6 +250,7 @@ impl PixelObjectSaveData {
                 matter: 0,
                 color_index: 0,
                 is_alive: false,
+                char_level: 0,
             })
             .matter;
 
@@ -218,6 +261,8 @@ impl PixelObjectSaveData {
             angle: angle.0,
             lin_vel,
             ang_vel,
+            fixed,
+            collision,
         }
     }
 
@@ -54,7 +54,15 @@ pub fn collider_sensor_from_polylines(vertices: &[Vector2<f64>]) -> Collider {
         .collect();
     ColliderBuilder::polyline(verts, None)
         .sensor(true)
-        .active_events(ActiveEvents::empty())
+        // Objects entering liquids splash
+        .active_events(ActiveEvents::COLLISION_EVENTS)
+        .build()
+}
+
+pub fn collider_sensor_from_cuboid(half_extents: Vector2<f32>) -> Collider {
+    ColliderBuilder::cuboid(half_extents.x, half_extents.y)
+        .sensor(true)
+        .active_events(ActiveEvents::COLLISION_EVENTS)
         .build()
 }
 
@@ -148,3 +156,8 @@ pub struct AngularVelocity(pub f32);
 
 #[derive(Debug, Copy, Clone)]
 pub struct Angle(pub f32);
+
+/// Marks a pixel object placed as static. Its body stays fixed while it still deforms & couples
+/// with the matter grid
+#[derive(Debug, Copy, Clone)]
+pub struct FixedObject;
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use corrode::api::EngineApi;
+use egui::{CtxRef, TextureId};
+
+use crate::{app::InputAction, interact::Editor, sim::Simulation};
+
+/// Gui panels that can be popped out of the main window into their own OS windows
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Panel {
+    Editor,
+    Matters,
+}
+
+impl Panel {
+    fn key(&self) -> &'static str {
+        match self {
+            Panel::Editor => "editor",
+            Panel::Matters => "matters",
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            Panel::Editor => "Sandbox Editor",
+            Panel::Matters => "Sandbox Edit Matters",
+        }
+    }
+
+    fn size(&self) -> [u32; 2] {
+        match self {
+            Panel::Editor => [320, 900],
+            Panel::Matters => [420, 800],
+        }
+    }
+}
+
+/// Panels shown in pop-out windows. Each window has a gui of its own, thus editor textures are
+/// registered to each window too, see `PanelTextures`. Closing a pop-out window docks its panel
+/// back to the main window
+pub struct PopOuts {
+    /// Pop-out window texture of each main gui texture, by popped panel
+    textures: HashMap<Panel, HashMap<TextureId, TextureId>>,
+}
+
+impl PopOuts {
+    pub fn new() -> PopOuts {
+        PopOuts {
+            textures: HashMap::new(),
+        }
+    }
+
+    pub fn is_popped(&self, panel: Panel) -> bool {
+        self.textures.contains_key(&panel)
+    }
+
+    pub fn pop_out(&mut self, api: &mut EngineApi<InputAction>, panel: Panel) {
+        api.pop_outs.open(panel.key(), panel.title(), panel.size());
+        self.textures.insert(panel, HashMap::new());
+    }
+
+    pub fn dock(&mut self, api: &mut EngineApi<InputAction>, panel: Panel) {
+        api.pop_outs.close(panel.key());
+        self.textures.remove(&panel);
+    }
+
+    pub fn toggle(&mut self, api: &mut EngineApi<InputAction>, panel: Panel) {
+        if self.is_popped(panel) {
+            self.dock(api, panel);
+        } else {
+            self.pop_out(api, panel);
+        }
+    }
+
+    /// Dock panels whose window was closed & register new editor textures to pop-out windows.
+    /// Call before gui layout
+    pub fn update(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        editor: &Editor,
+        simulation: &Simulation,
+    ) {
+        self.textures
+            .retain(|panel, _| api.pop_outs.is_open(panel.key()));
+        let format = api.renderer.image_format();
+        for (panel, textures) in self.textures.iter_mut() {
+            let window = match api.pop_outs.get_mut(panel.key()) {
+                Some(window) => window,
+                None => continue,
+            };
+            let main_textures = editor.gui_textures();
+            textures.retain(|main, texture| {
+                let keep = main_textures.contains(main);
+                if !keep {
+                    window.gui.unregister_user_image(*texture);
+                }
+                keep
+            });
+            for main in main_textures {
+                if textures.contains_key(&main) {
+                    continue;
+                }
+                if let Some((data, size)) = editor.gui_image_data(main, simulation) {
+                    let texture = window.gui.register_user_image_from_bytes(&data, size, format);
+                    textures.insert(main, texture);
+                }
+            }
+        }
+    }
+
+    /// Gui context & textures of the panel's pop-out window. None if panel is drawn on main
+    /// window, also while its window is still opening
+    pub fn window_gui(
+        &self,
+        api: &mut EngineApi<InputAction>,
+        panel: Panel,
+    ) -> Option<(CtxRef, PanelTextures)> {
+        let textures = self.textures.get(&panel)?;
+        let window = api.pop_outs.get_mut(panel.key())?;
+        Some((window.gui.context(), PanelTextures(Some(textures))))
+    }
+}
+
+/// Maps editor's main gui textures to textures of the gui a panel is drawn on
+#[derive(Copy, Clone)]
+pub struct PanelTextures<'a>(Option<&'a HashMap<TextureId, TextureId>>);
+
+impl<'a> PanelTextures<'a> {
+    /// Textures of panels on main window
+    pub const MAIN: PanelTextures<'static> = PanelTextures(None);
+
+    pub fn get(&self, texture: TextureId) -> TextureId {
+        match self.0 {
+            None => texture,
+            // Textures registered during this frame are mirrored next frame
+            Some(textures) => textures.get(&texture).copied().unwrap_or(TextureId::Egui),
+        }
+    }
+}
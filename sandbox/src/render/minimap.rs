@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+
+use cgmath::Vector2;
+use corrode::api::EngineApi;
+use egui::TextureId;
+
+use crate::{
+    app::InputAction,
+    interact::PREVIEW_SCALE,
+    matter::MatterDefinitions,
+    sim::{
+        canvas_pos_to_chunk_pos, chunk_data_hash, chunk_matter_grid, matter_grid_to_image,
+        ChunkGrid, GridRead, SimulationChunkManager,
+    },
+    utils::{load_bitmap_image_from_path, BitmapImage},
+    CANVAS_CHUNK_SIZE, HALF_CANVAS,
+};
+
+/// Chunks are drawn this many times smaller than their cells on the minimap
+const MINIMAP_THUMBNAIL_FACTOR: u32 = 16;
+/// Largest number of chunks shown from the center chunk to the minimap's edge
+pub const MAX_MINIMAP_RADIUS: i32 = 8;
+/// Milliseconds between redraws of the minimap. Chunks on gpu are read back on each redraw
+const MINIMAP_REFRESH_MS: f64 = 1000.0;
+
+/// Thumbnail of a chunk that isn't on gpu, along with hash of the chunk data it was drawn from
+struct CachedThumbnail {
+    data_hash: u64,
+    image: BitmapImage,
+}
+
+/// Map of world chunks around the camera, composed from downscaled chunk thumbnails. Simulated
+/// chunks are drawn as they are now, others from their saved data, which only changes on unload.
+/// Chunks unchanged since the map was loaded are drawn from the previews saved with it
+pub struct Minimap {
+    /// Chunks shown from the center chunk to the edge, zooming out when larger
+    pub radius: i32,
+    /// Chunk at the center of the latest minimap image
+    center: Vector2<i32>,
+    texture: Option<TextureId>,
+    thumbnails: HashMap<Vector2<i32>, CachedThumbnail>,
+    time_since_refresh: f64,
+    drawn_radius: i32,
+}
+
+impl Minimap {
+    pub fn new() -> Minimap {
+        Minimap {
+            radius: 2,
+            center: Vector2::new(0, 0),
+            texture: None,
+            thumbnails: HashMap::new(),
+            time_since_refresh: MINIMAP_REFRESH_MS,
+            drawn_radius: 0,
+        }
+    }
+
+    /// Chunk at the center of the minimap
+    pub fn center(&self) -> Vector2<i32> {
+        self.center
+    }
+
+    /// Minimap image's size in pixels & its texture, redrawn around camera's chunk when it moved,
+    /// zoom changed or it's time for a refresh
+    pub fn update(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        chunk_manager: &SimulationChunkManager,
+        matter_definitions: &MatterDefinitions,
+        camera_canvas_pos: Vector2<i32>,
+    ) -> Option<(TextureId, [f32; 2])> {
+        let center = canvas_pos_to_chunk_pos(camera_canvas_pos);
+        self.radius = self.radius.clamp(1, MAX_MINIMAP_RADIUS);
+        self.time_since_refresh += api.time.dt();
+        let is_stale = self.time_since_refresh >= MINIMAP_REFRESH_MS
+            || center != self.center
+            || self.radius != self.drawn_radius;
+        if is_stale || self.texture.is_none() {
+            let image = self.compose(chunk_manager, matter_definitions, camera_canvas_pos);
+            if let Some(old) = self.texture.take() {
+                api.gui.unregister_user_image(old);
+            }
+            self.texture = Some(api.gui.register_user_image_from_bytes(
+                &image.data,
+                (image.width as u64, image.height as u64),
+                api.renderer.image_format(),
+            ));
+            self.center = center;
+            self.drawn_radius = self.radius;
+            self.time_since_refresh = 0.0;
+        }
+        let size = thumbnail_size() * self.chunks_across();
+        self.texture
+            .map(|texture| (texture, [size.x as f32, size.y as f32]))
+    }
+
+    /// Forget the gui texture, e.g. when gui was recreated & it no longer exists
+    pub fn forget_gui_image(&mut self) {
+        self.texture = None;
+    }
+
+    /// Chunk drawn at `uv` of the minimap image, uv (0, 0) being its top left corner
+    pub fn chunk_at(&self, uv: Vector2<f32>) -> Vector2<i32> {
+        let across = self.chunks_across() as f32;
+        Vector2::new(
+            self.center.x - self.drawn_radius + (uv.x * across).floor() as i32,
+            self.center.y + self.drawn_radius - (uv.y * across).floor() as i32,
+        )
+    }
+
+    /// Canvas position at `uv` of the minimap image, see `chunk_at`
+    pub fn canvas_pos_at(&self, uv: Vector2<f32>) -> Vector2<i32> {
+        let across = self.chunks_across() as f32;
+        let chunk_size = CANVAS_CHUNK_SIZE.cast::<f32>().unwrap();
+        let from_corner = Vector2::new(
+            uv.x * across * chunk_size.x,
+            (1.0 - uv.y) * across * chunk_size.y,
+        );
+        let corner_chunk = self.center - Vector2::new(self.drawn_radius, self.drawn_radius);
+        let corner = Vector2::new(
+            corner_chunk.x * CANVAS_CHUNK_SIZE.x as i32,
+            corner_chunk.y * CANVAS_CHUNK_SIZE.y as i32,
+        ) - *HALF_CANVAS;
+        corner + from_corner.cast::<i32>().unwrap()
+    }
+
+    /// Uv of the minimap image at `canvas_pos`, see `canvas_pos_at`
+    pub fn uv_at(&self, canvas_pos: Vector2<i32>) -> Vector2<f32> {
+        let across = self.chunks_across() as f32;
+        let chunk_size = CANVAS_CHUNK_SIZE.cast::<f32>().unwrap();
+        let corner = self.canvas_pos_at(Vector2::new(0.0, 1.0));
+        let from_corner = (canvas_pos - corner).cast::<f32>().unwrap();
+        Vector2::new(
+            from_corner.x / (across * chunk_size.x),
+            1.0 - from_corner.y / (across * chunk_size.y),
+        )
+    }
+
+    /// Uv of top left & bottom right corners of chunk on the minimap image, None if it's not shown
+    pub fn chunk_uv_rect(&self, chunk_pos: Vector2<i32>) -> Option<(Vector2<f32>, Vector2<f32>)> {
+        let offset = chunk_pos - self.center;
+        if offset.x.abs() > self.drawn_radius || offset.y.abs() > self.drawn_radius {
+            return None;
+        }
+        let across = self.chunks_across() as f32;
+        let top_left = Vector2::new(
+            (offset.x + self.drawn_radius) as f32 / across,
+            (self.drawn_radius - offset.y) as f32 / across,
+        );
+        Some((top_left, top_left + Vector2::new(1.0 / across, 1.0 / across)))
+    }
+
+    fn chunks_across(&self) -> u32 {
+        (self.drawn_radius * 2 + 1) as u32
+    }
+
+    /// Draw thumbnails of chunks around camera's chunk into one image. Chunks that have never
+    /// existed are left transparent, & chunks that aren't on gpu are dimmed
+    fn compose(
+        &mut self,
+        chunk_manager: &SimulationChunkManager,
+        matter_definitions: &MatterDefinitions,
+        camera_canvas_pos: Vector2<i32>,
+    ) -> BitmapImage {
+        let center = canvas_pos_to_chunk_pos(camera_canvas_pos);
+        // Simulated chunks can't be read while compute is using them, they keep their latest
+        // thumbnails then
+        let sim_chunks = chunk_manager.sim_chunks(camera_canvas_pos);
+        let sim_matter = sim_chunks.read(ChunkGrid::Matter).ok();
+        let across = (self.radius * 2 + 1) as u32;
+        let size = thumbnail_size();
+        let mut image = BitmapImage::empty(across * size.x, across * size.y);
+        for row in 0..across {
+            for column in 0..across {
+                let chunk_pos = center
+                    + Vector2::new(column as i32 - self.radius, self.radius - row as i32);
+                let is_loaded = chunk_manager.chunks_in_use.contains(&chunk_pos);
+                let thumbnail = self.thumbnail(
+                    chunk_manager,
+                    matter_definitions,
+                    sim_matter.as_ref(),
+                    chunk_pos,
+                );
+                let thumbnail = match thumbnail {
+                    Some(thumbnail) => thumbnail,
+                    None => continue,
+                };
+                for y in 0..thumbnail.height.min(size.y) {
+                    let from = (y * thumbnail.width * 4) as usize;
+                    let to = (((row * size.y + y) * image.width + column * size.x) * 4) as usize;
+                    let width = (thumbnail.width.min(size.x) * 4) as usize;
+                    let pixels = &mut image.data[to..to + width];
+                    pixels.copy_from_slice(&thumbnail.data[from..from + width]);
+                    if !is_loaded {
+                        pixels.chunks_exact_mut(4).for_each(|pixel| pixel[3] /= 2);
+                    }
+                }
+            }
+        }
+        image
+    }
+
+    /// Thumbnail of chunk, None if the chunk doesn't exist. Simulated chunks are read from
+    /// `sim_matter`, falling back to their latest thumbnail when it couldn't be read
+    fn thumbnail(
+        &mut self,
+        chunk_manager: &SimulationChunkManager,
+        matter_definitions: &MatterDefinitions,
+        sim_matter: Option<&GridRead>,
+        chunk_pos: Vector2<i32>,
+    ) -> Option<&BitmapImage> {
+        let world_chunk = chunk_manager.world_chunk(chunk_pos)?;
+        let sim_grid = chunk_manager
+            .interaction_chunks
+            .iter()
+            .position(|pos| *pos == chunk_pos)
+            .and_then(|index| Some(sim_matter?.chunk(index).to_vec()));
+        let thumbnail = match sim_grid {
+            // Simulated matter changes all the time, so its thumbnail is never reused
+            Some(matter_grid) => Some((0, matter_grid_image(matter_definitions, &matter_grid))),
+            None => {
+                let data_hash = chunk_data_hash(world_chunk.data.as_deref());
+                let cached = self.thumbnails.get(&chunk_pos);
+                // Chunk on gpu that couldn't be read keeps its latest thumbnail
+                match cached {
+                    Some(cached)
+                        if world_chunk.gpu_chunk.is_some() || cached.data_hash == data_hash =>
+                    {
+                        None
+                    }
+                    // Saved preview spares decoding chunks unchanged since the map was loaded
+                    _ => chunk_manager
+                        .saved_preview(chunk_pos, data_hash)
+                        .and_then(|path| load_bitmap_image_from_path(path.to_path_buf()).ok())
+                        .map(|preview| preview.downscaled(MINIMAP_THUMBNAIL_FACTOR / PREVIEW_SCALE))
+                        .or_else(|| {
+                            let data = world_chunk.data.as_deref();
+                            let matter_grid = chunk_matter_grid(data, matter_definitions).ok()?;
+                            Some(matter_grid_image(matter_definitions, &matter_grid))
+                        })
+                        .map(|image| (data_hash, image)),
+                }
+            }
+        };
+        if let Some((data_hash, image)) = thumbnail {
+            self.thumbnails
+                .insert(chunk_pos, CachedThumbnail { data_hash, image });
+        }
+        self.thumbnails.get(&chunk_pos).map(|cached| &cached.image)
+    }
+}
+
+/// Thumbnail of a chunk's matter grid
+fn matter_grid_image(matter_definitions: &MatterDefinitions, matter_grid: &[u32]) -> BitmapImage {
+    matter_grid_to_image(matter_definitions, matter_grid).downscaled(MINIMAP_THUMBNAIL_FACTOR)
+}
+
+/// Size of a chunk's thumbnail in pixels
+fn thumbnail_size() -> Vector2<u32> {
+    Vector2::new(
+        (CANVAS_CHUNK_SIZE.x / MINIMAP_THUMBNAIL_FACTOR).max(1),
+        (CANVAS_CHUNK_SIZE.y / MINIMAP_THUMBNAIL_FACTOR).max(1),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minimap_positions() {
+        let mut minimap = Minimap::new();
+        minimap.center = Vector2::new(3, -1);
+        minimap.drawn_radius = 1;
+        // Top left chunk is above & left of center
+        assert_eq!(minimap.chunk_at(Vector2::new(0.0, 0.0)), Vector2::new(2, 0));
+        assert_eq!(minimap.chunk_at(Vector2::new(0.5, 0.5)), Vector2::new(3, -1));
+        assert_eq!(minimap.chunk_at(Vector2::new(0.99, 0.99)), Vector2::new(4, -2));
+        // Minimap's center is the center chunk's center, which is its canvas pos
+        let center_canvas_pos = Vector2::new(
+            3 * CANVAS_CHUNK_SIZE.x as i32,
+            -(CANVAS_CHUNK_SIZE.y as i32),
+        );
+        assert_eq!(minimap.canvas_pos_at(Vector2::new(0.5, 0.5)), center_canvas_pos);
+        assert_eq!(minimap.uv_at(center_canvas_pos), Vector2::new(0.5, 0.5));
+        let (top_left, _) = minimap.chunk_uv_rect(Vector2::new(2, 0)).unwrap();
+        assert_eq!(top_left, Vector2::new(0.0, 0.0));
+        assert!(minimap.chunk_uv_rect(Vector2::new(5, -1)).is_none());
+    }
+}
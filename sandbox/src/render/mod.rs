@@ -0,0 +1,427 @@
+mod minimap;
+mod particles;
+
+pub use minimap::*;
+pub use particles::*;
+
+use anyhow::*;
+use cgmath::{InnerSpace, Vector2};
+use corrode::{
+    physics::PhysicsWorld,
+    renderer::{render_pass::DrawPass, Camera2D, DeviceImageView, Line, Renderer},
+};
+use hecs::{Entity, World};
+use rapier2d::prelude::*;
+use vulkano::{image::ImageAccess, sampler::Filter};
+
+use crate::{
+    camera_path::CameraPath,
+    object::PixelData,
+    sim::{chunk_lines, object_world_anchor, push_collider_lines, ObjectJointKind, Simulation},
+    CELL_UNIT_SIZE, HALF_CELL, SIM_CANVAS_SIZE, WORLD_CANVAS_SIZE,
+};
+
+/// Key of the image target canvas is rendered to when render scale is below 1.0
+const SCALED_RENDER_TARGET: usize = 1;
+
+/// Image target at `render_scale` of the window resolution, to be upscaled over the frame.
+/// Recreated whenever window size or scale changes
+pub fn scaled_render_target(renderer: &mut Renderer, render_scale: f32) -> Result<DeviceImageView> {
+    let [width, height] = renderer.final_image_size();
+    let size = [
+        ((width as f32 * render_scale) as u32).max(1),
+        ((height as f32 * render_scale) as u32).max(1),
+    ];
+    let is_current = renderer.has_image_target(SCALED_RENDER_TARGET)
+        && renderer
+            .get_image_target(SCALED_RENDER_TARGET)
+            .image()
+            .dimensions()
+            .width_height()
+            == size;
+    if !is_current {
+        let format = renderer.swapchain_format();
+        renderer.add_image_target(SCALED_RENDER_TARGET, Some(size), format)?;
+    }
+    Ok(renderer.get_image_target(SCALED_RENDER_TARGET))
+}
+
+fn push_boundary_contour_lines(
+    ecs_world: &World,
+    physics_world: &PhysicsWorld,
+    boundary_entities: &[Vec<Entity>],
+    color: [f32; 4],
+    lines: &mut Vec<Line>,
+) {
+    for e in boundary_entities.iter().flatten() {
+        let rb = *ecs_world.get::<RigidBodyHandle>(*e).unwrap();
+        let rigid_body = &physics_world.physics.bodies[rb];
+        for c in rigid_body.colliders() {
+            let collider = &physics_world.physics.colliders[*c];
+            if collider.shape().as_polyline().is_some() {
+                push_collider_lines(collider, color, lines);
+            }
+        }
+    }
+}
+
+/// Filter canvas is sampled with at camera zoom. Zoomed far out, cells cover few screen pixels
+/// and shimmer when sampled nearest, so canvas is smoothed when cells are smaller than
+/// `smooth_below` pixels
+pub fn canvas_filter(camera: &Camera2D, target_height: u32, smooth_below: f32) -> Filter {
+    // Camera shows 2.0 / zoom world units vertically
+    let cell_pixels = *CELL_UNIT_SIZE * camera.zoom_level() * target_height as f32 / 2.0;
+    if cell_pixels < smooth_below {
+        Filter::Linear
+    } else {
+        Filter::Nearest
+    }
+}
+
+/// Draw chunk color images, or their 2x detail images when `detail` is on (see
+/// `DetailUpscaler`)
+pub fn draw_canvas(
+    simulation: &Simulation,
+    draw_pass: &mut DrawPass,
+    filter: Filter,
+    detail: bool,
+) -> Result<()> {
+    for chunk in simulation.chunk_manager.get_chunks_for_render() {
+        let chunk_pos = Vector2::new(
+            chunk.0.x as f32 * WORLD_CANVAS_SIZE.x,
+            chunk.0.y as f32 * WORLD_CANVAS_SIZE.y,
+        ) - *HALF_CELL;
+        let detail_image = if detail {
+            simulation.detail_upscaler.image(chunk.0)
+        } else {
+            None
+        };
+        let chunk_image = detail_image.unwrap_or_else(|| chunk.1.image.clone());
+        draw_pass.draw_texture(
+            chunk_pos,
+            WORLD_CANVAS_SIZE.x / 2.0,
+            WORLD_CANVAS_SIZE.y / 2.0,
+            0.0,
+            chunk_image,
+            true,
+            false,
+            filter,
+        )?
+    }
+    Ok(())
+}
+
+/// Draw particles over the canvas, see `ParticleSystem`
+pub fn draw_particles(simulation: &Simulation, draw_pass: &mut DrawPass) -> Result<()> {
+    let (image, image_origin) = simulation.particles.image();
+    // Image covers 2 x 2 chunks
+    let center = image_origin + SIM_CANVAS_SIZE.cast::<i32>().unwrap();
+    draw_pass.draw_texture(
+        center.cast::<f32>().unwrap() * *CELL_UNIT_SIZE - *HALF_CELL,
+        WORLD_CANVAS_SIZE.x,
+        WORLD_CANVAS_SIZE.y,
+        0.0,
+        image,
+        true,
+        true,
+        Filter::Nearest,
+    )
+}
+
+pub fn draw_contours(
+    ecs_world: &World,
+    physics_world: &PhysicsWorld,
+    simulation: &mut Simulation,
+    draw_pass: &mut DrawPass,
+) -> Result<()> {
+    // Reuse line buffer between frames
+    let lines = &mut simulation.scratch.lines;
+    lines.clear();
+    // Pixel Objects
+    for (_id, (rb, ..)) in &mut ecs_world.query::<(&RigidBodyHandle, &PixelData)>() {
+        let rigid_body = &physics_world.physics.bodies[*rb];
+        for c in rigid_body.colliders() {
+            let collider = &physics_world.physics.colliders[*c];
+            if collider.shape().as_compound().is_some() {
+                push_collider_lines(collider, [1.0, 0.0, 0.0, 1.0], lines);
+            }
+        }
+    }
+    if !lines.is_empty() {
+        draw_pass.draw_lines(lines)?;
+    }
+    // Polylines (utils) only change when boundaries are rebuilt, so they're uploaded once
+    let boundaries = &simulation.boundaries;
+    let boundary_lines = &mut simulation.scratch.boundary_lines;
+    if !boundary_lines.is_current(boundaries.version) {
+        lines.clear();
+        push_boundary_contour_lines(
+            ecs_world,
+            physics_world,
+            &boundaries.solid_objects,
+            [0.0, 1.0, 0.0, 1.0],
+            lines,
+        );
+        push_boundary_contour_lines(
+            ecs_world,
+            physics_world,
+            &boundaries.powder_objects,
+            [1.0, 1.0, 0.0, 1.0],
+            lines,
+        );
+        push_boundary_contour_lines(
+            ecs_world,
+            physics_world,
+            &boundaries.liquid_objects,
+            [0.0, 0.0, 1.0, 1.0],
+            lines,
+        );
+        boundary_lines.upload(draw_pass.device(), lines, boundaries.version)?;
+    }
+    draw_pass.draw_line_batch(boundary_lines)?;
+    Ok(())
+}
+
+pub fn draw_grid(
+    simulation: &Simulation,
+    draw_pass: &mut DrawPass,
+    grid_color: [f32; 4],
+) -> Result<()> {
+    let mut lines = vec![];
+    let length = 20;
+    let half_length = length / 2;
+    let cam_chunk = Vector2::new(
+        simulation.camera_canvas_pos.x / SIM_CANVAS_SIZE.x as i32,
+        simulation.camera_canvas_pos.y / SIM_CANVAS_SIZE.y as i32,
+    );
+    for y in -half_length..=half_length {
+        for x in -half_length..=half_length {
+            let chunk = Vector2::new(x, y) + cam_chunk;
+            lines.extend(chunk_lines(chunk, grid_color));
+        }
+    }
+    draw_pass.draw_lines(&lines)?;
+    Ok(())
+}
+
+pub fn draw_debug_bounds(
+    simulation: &Simulation,
+    draw_pass: &mut DrawPass,
+    sim_color: [f32; 4],
+) -> Result<()> {
+    let half = 0.5 * *WORLD_CANVAS_SIZE;
+    let center = simulation.camera_pos - *HALF_CELL;
+    let mut lines = vec![];
+    lines.extend([
+        Line(
+            Vector2::new(-half.x, half.y) + center,
+            Vector2::new(half.x, half.y) + center,
+            sim_color,
+        ),
+        Line(
+            Vector2::new(-half.x, -half.y) + center,
+            Vector2::new(half.x, -half.y) + center,
+            sim_color,
+        ),
+        Line(
+            Vector2::new(-half.x, -half.y) + center,
+            Vector2::new(-half.x, half.y) + center,
+            sim_color,
+        ),
+        Line(
+            Vector2::new(half.x, -half.y) + center,
+            Vector2::new(half.x, half.y) + center,
+            sim_color,
+        ),
+    ]);
+    draw_pass.draw_lines(&lines)?;
+    Ok(())
+}
+
+pub fn draw_chunk_debug_info(
+    simulation: &Simulation,
+    draw_pass: &mut DrawPass,
+    chunk_color: [f32; 4],
+    interaction_color: [f32; 4],
+) -> Result<()> {
+    let mut lines = vec![];
+    for chunk in simulation.chunk_manager.chunks_in_use.iter() {
+        lines.extend(chunk_lines(*chunk, chunk_color));
+    }
+    for chunk in simulation.chunk_manager.interaction_chunks.iter() {
+        lines.extend(chunk_lines(*chunk, interaction_color));
+    }
+    draw_pass.draw_lines(&lines)?;
+    Ok(())
+}
+
+pub fn draw_trigger_zones(
+    simulation: &Simulation,
+    draw_pass: &mut DrawPass,
+    zone_color: [f32; 4],
+    emitting_color: [f32; 4],
+) -> Result<()> {
+    let mut lines = vec![];
+    for zone in simulation.trigger_zones.zones.iter() {
+        let color = if zone.is_emitting {
+            emitting_color
+        } else {
+            zone_color
+        };
+        let min = zone.pos - zone.half_extents;
+        let max = zone.pos + zone.half_extents;
+        lines.extend([
+            Line(Vector2::new(min.x, max.y), max, color),
+            Line(min, Vector2::new(max.x, min.y), color),
+            Line(min, Vector2::new(min.x, max.y), color),
+            Line(Vector2::new(max.x, min.y), max, color),
+        ]);
+    }
+    draw_pass.draw_lines(&lines)?;
+    Ok(())
+}
+
+pub fn draw_portals(
+    simulation: &Simulation,
+    draw_pass: &mut DrawPass,
+    a_color: [f32; 4],
+    b_color: [f32; 4],
+) -> Result<()> {
+    let mut lines = vec![];
+    // Seams are drawn on the bottom edge of the starting cell row, where matter passes through
+    let seam = |start: Vector2<i32>, width: i32, color: [f32; 4]| {
+        let start_pos = Vector2::new(start.x as f32, start.y as f32) * *CELL_UNIT_SIZE;
+        let end_pos = start_pos + Vector2::new(width as f32 * *CELL_UNIT_SIZE, 0.0);
+        Line(start_pos, end_pos, color)
+    };
+    for pair in simulation.portals.pairs.iter() {
+        lines.push(seam(pair.a, pair.width, a_color));
+        lines.push(seam(pair.b + Vector2::new(0, 1), pair.width, b_color));
+    }
+    draw_pass.draw_lines(&lines)?;
+    Ok(())
+}
+
+/// Annotation labels with their arrows
+pub fn draw_annotations(simulation: &Simulation, draw_pass: &mut DrawPass) -> Result<()> {
+    let mut lines = vec![];
+    for annotation in simulation.annotations.annotations.iter() {
+        if let Some(arrow) = annotation.arrow_lines() {
+            lines.extend(
+                arrow
+                    .iter()
+                    .map(|&(start, end)| Line(start, end, annotation.color)),
+            );
+        }
+    }
+    if !lines.is_empty() {
+        draw_pass.draw_lines(&lines)?;
+    }
+    let texts = simulation
+        .annotations
+        .annotations
+        .iter()
+        .map(|annotation| {
+            (
+                annotation.text.as_str(),
+                annotation.pos,
+                annotation.height,
+                annotation.color,
+            )
+        })
+        .collect::<Vec<_>>();
+    if !texts.is_empty() {
+        draw_pass.draw_texts(&texts)?;
+    }
+    Ok(())
+}
+
+/// Camera path as sampled over its duration, with a dot at each keyframe
+pub fn draw_camera_path(
+    camera_path: &CameraPath,
+    draw_pass: &mut DrawPass,
+    color: [f32; 4],
+) -> Result<()> {
+    // Samples per second of the path
+    let samples_per_sec = 20.0;
+    let num_samples = (camera_path.duration() * samples_per_sec).ceil() as usize;
+    let points = (0..=num_samples)
+        .filter_map(|i| camera_path.sample(i as f32 / samples_per_sec))
+        .map(|(pos, _)| pos)
+        .collect::<Vec<Vector2<f32>>>();
+    let lines = points
+        .windows(2)
+        .map(|pair| Line(pair[0], pair[1], color))
+        .collect::<Vec<Line>>();
+    if !lines.is_empty() {
+        draw_pass.draw_lines(&lines)?;
+    }
+    for keyframe in camera_path.keyframes.iter() {
+        draw_pass.draw_circle(keyframe.pos.into(), *CELL_UNIT_SIZE * 4.0, color)?;
+    }
+    Ok(())
+}
+
+/// Outline of canvas cells `min..=max`
+pub fn draw_canvas_rect(
+    draw_pass: &mut DrawPass,
+    min: Vector2<i32>,
+    max: Vector2<i32>,
+    color: [f32; 4],
+) -> Result<()> {
+    let min = Vector2::new(min.x as f32, min.y as f32) * *CELL_UNIT_SIZE;
+    let max = Vector2::new((max.x + 1) as f32, (max.y + 1) as f32) * *CELL_UNIT_SIZE;
+    draw_pass.draw_lines(&[
+        Line(Vector2::new(min.x, max.y), max, color),
+        Line(min, Vector2::new(max.x, min.y), color),
+        Line(min, Vector2::new(min.x, max.y), color),
+        Line(Vector2::new(max.x, min.y), max, color),
+    ])
+}
+
+/// Glowing beam from `start` to `end` world position, faint lines around a bright core
+pub fn draw_beam(draw_pass: &mut DrawPass, start: Vector2<f32>, end: Vector2<f32>) -> Result<()> {
+    let dir = end - start;
+    let normal = if dir.magnitude2() > 0.0 {
+        Vector2::new(-dir.y, dir.x).normalize() * *CELL_UNIT_SIZE * 0.5
+    } else {
+        Vector2::new(0.0, 0.0)
+    };
+    let mut lines = vec![Line(start, end, [1.0, 1.0, 0.8, 1.0])];
+    for (offset, alpha) in [(1.0, 0.6), (2.0, 0.3), (3.0, 0.1)] {
+        let color = [1.0, 0.4, 0.1, alpha];
+        for side in [-1.0, 1.0] {
+            let shift = normal * offset * side;
+            lines.push(Line(start + shift, end + shift, color));
+        }
+    }
+    draw_pass.draw_lines(&lines)?;
+    draw_pass.draw_circle(end, *CELL_UNIT_SIZE * 2.0, [1.0, 0.6, 0.2, 0.5])
+}
+
+/// Joints between objects, hinges as circles & ropes as lines between their tied points
+pub fn draw_object_joints(
+    physics_world: &PhysicsWorld,
+    simulation: &Simulation,
+    draw_pass: &mut DrawPass,
+    color: [f32; 4],
+) -> Result<()> {
+    let bodies = &physics_world.physics.bodies;
+    let mut lines = vec![];
+    for joint in simulation.object_joints.iter() {
+        let (body1, body2) = match (bodies.get(joint.bodies[0]), bodies.get(joint.bodies[1])) {
+            (Some(body1), Some(body2)) => (body1, body2),
+            _ => continue,
+        };
+        let start = object_world_anchor(body1, joint.local_anchors[0]);
+        let end = object_world_anchor(body2, joint.local_anchors[1]);
+        match joint.kind {
+            ObjectJointKind::Revolute => draw_pass.draw_circle(start, *CELL_UNIT_SIZE, color)?,
+            ObjectJointKind::Rope => lines.push(Line(start, end, color)),
+        }
+    }
+    if !lines.is_empty() {
+        draw_pass.draw_lines(&lines)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,260 @@
+use std::sync::Arc;
+
+use anyhow::*;
+use cgmath::Vector2;
+use corrode::renderer::{create_device_image_with_usage, DeviceImageView};
+use vulkano::{
+    buffer::{BufferUsage, CpuAccessibleBuffer},
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage},
+    descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet},
+    device::Queue,
+    format::Format,
+    image::ImageUsage,
+    pipeline::{ComputePipeline, Pipeline, PipelineBindPoint},
+    sync::GpuFuture,
+};
+
+use crate::{
+    matter::{MatterDefinitions, MatterState},
+    sim::empty_u32,
+    utils::Color32,
+    SIM_CANVAS_SIZE,
+};
+
+/// Max reactions emitted for particles per step. Must match
+/// compute_shaders/simulation/includes.glsl
+pub const MAX_PARTICLE_EMISSIONS: usize = 1024;
+/// Emission data after emission count: canvas x & y (as i32 bits), matter before & after. Must
+/// match compute_shaders/simulation/includes.glsl
+pub const PARTICLE_EMISSION_STRIDE: usize = 4;
+/// Max particles alive at once, the oldest are replaced by new ones. Must match
+/// compute_shaders/render/particles.glsl & be divisible by PARTICLE_GROUP_SIZE
+const MAX_PARTICLES: usize = 8192;
+/// Particle data in `particles`: canvas x & y, velocity x & y, life (as f32 bits), kind & color
+const PARTICLE_STRIDE: usize = 7;
+/// Spawn data: canvas x & y (as i32 bits), kind & color
+const SPAWN_STRIDE: usize = 4;
+/// Must match local size of compute_shaders/render/particles.glsl
+const PARTICLE_KERNEL_SIZE: u32 = 8;
+const PARTICLE_GROUP_SIZE: u32 = PARTICLE_KERNEL_SIZE * PARTICLE_KERNEL_SIZE;
+/// Must match compute_shaders/render/particles.glsl
+const STAGE_CLEAR: u32 = 0;
+const STAGE_UPDATE: u32 = 1;
+
+/// Must match compute_shaders/render/particles.glsl
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u32)]
+enum ParticleKind {
+    /// Rises from matter catching fire
+    Ember = 0,
+    /// Bursts from energy turning into other matter
+    Spark = 1,
+    /// Flies from liquid boiling away
+    Splash = 2,
+}
+
+/// Sparks, embers & splashes of reactions emitted by the react kernel, moved & drawn on gpu to an
+/// image over the simulated area. Visual only, particles don't interact with matter
+pub struct ParticleSystem {
+    comp_queue: Arc<Queue>,
+    pipeline: Arc<ComputePipeline>,
+    particles: Arc<CpuAccessibleBuffer<[u32]>>,
+    /// Spawns waiting for next update, `SPAWN_STRIDE` values each
+    spawns: Vec<u32>,
+    /// Slot of next spawned particle
+    cursor: usize,
+    /// Particles of the simulated area (2 x 2 chunks) & canvas pos of its corner
+    image: DeviceImageView,
+    image_origin: Vector2<i32>,
+    /// Kill all particles on next update
+    needs_clear: bool,
+    frame: u32,
+}
+
+impl ParticleSystem {
+    pub fn new(comp_queue: Arc<Queue>, format: Format) -> Result<ParticleSystem> {
+        assert_eq!(MAX_PARTICLES as u32 % PARTICLE_GROUP_SIZE, 0);
+        let pipeline = {
+            let shader = particles_cs::load(comp_queue.device().clone())?;
+            ComputePipeline::new(
+                comp_queue.device().clone(),
+                shader.entry_point("main").unwrap(),
+                &(),
+                None,
+                |_| {},
+            )?
+        };
+        let particles = empty_u32(comp_queue.device().clone(), MAX_PARTICLES * PARTICLE_STRIDE)?;
+        let image = create_device_image_with_usage(
+            comp_queue.clone(),
+            [SIM_CANVAS_SIZE.x * 2, SIM_CANVAS_SIZE.y * 2],
+            format,
+            ImageUsage {
+                sampled: true,
+                storage: true,
+                ..ImageUsage::none()
+            },
+        )?;
+        Ok(ParticleSystem {
+            comp_queue,
+            pipeline,
+            particles,
+            spawns: vec![],
+            cursor: 0,
+            image,
+            image_origin: Vector2::new(0, 0),
+            needs_clear: false,
+            frame: 0,
+        })
+    }
+
+    /// Image particles were drawn to on latest update & canvas pos of its corner
+    pub fn image(&self) -> (DeviceImageView, Vector2<i32>) {
+        (self.image.clone(), self.image_origin)
+    }
+
+    /// Queue particles of emissions read from the react kernel (see
+    /// `CASimulator::take_particle_emissions`) to be spawned on next update
+    pub fn queue_emissions(&mut self, emissions: &[u32], matter_definitions: &MatterDefinitions) {
+        queue_spawns(&mut self.spawns, emissions, matter_definitions);
+    }
+
+    /// Spawn queued particles, move particles & draw them to image over the simulated area
+    /// starting at canvas pos `chunk_start`, after `before_future`
+    pub fn update<F>(
+        &mut self,
+        chunk_start: Vector2<i32>,
+        before_future: F,
+    ) -> Result<Box<dyn GpuFuture>>
+    where
+        F: GpuFuture + 'static,
+    {
+        let num_spawns = self.spawns.len() / SPAWN_STRIDE;
+        // Buffer can't be empty
+        self.spawns.resize(self.spawns.len().max(SPAWN_STRIDE), 0);
+        let spawns = CpuAccessibleBuffer::from_iter(
+            self.comp_queue.device().clone(),
+            BufferUsage::all(),
+            false,
+            self.spawns.drain(..),
+        )?;
+        self.image_origin = chunk_start;
+        let mut builder = AutoCommandBufferBuilder::primary(
+            self.comp_queue.device().clone(),
+            self.comp_queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        if self.needs_clear {
+            builder.fill_buffer(self.particles.clone(), 0)?;
+            self.needs_clear = false;
+        }
+        let pipeline_layout = self.pipeline.layout();
+        let desc_layout = pipeline_layout.descriptor_set_layouts().get(0).unwrap();
+        let set = PersistentDescriptorSet::new(desc_layout.clone(), [
+            WriteDescriptorSet::buffer(0, self.particles.clone()),
+            WriteDescriptorSet::buffer(1, spawns),
+            WriteDescriptorSet::image_view(2, self.image.clone()),
+        ])?;
+        let push_constants = |stage: u32| particles_cs::ty::PushConstants {
+            image_origin: chunk_start.into(),
+            cursor: self.cursor as u32,
+            num_spawns: num_spawns as u32,
+            stage,
+            seed: self.frame,
+        };
+        builder
+            .bind_pipeline_compute(self.pipeline.clone())
+            .bind_descriptor_sets(PipelineBindPoint::Compute, pipeline_layout.clone(), 0, set)
+            .push_constants(pipeline_layout.clone(), 0, push_constants(STAGE_CLEAR))
+            .dispatch([
+                SIM_CANVAS_SIZE.x * 2 / PARTICLE_KERNEL_SIZE,
+                SIM_CANVAS_SIZE.y * 2 / PARTICLE_KERNEL_SIZE,
+                1,
+            ])?
+            .push_constants(pipeline_layout.clone(), 0, push_constants(STAGE_UPDATE))
+            .dispatch([MAX_PARTICLES as u32 / PARTICLE_GROUP_SIZE, 1, 1])?;
+        self.cursor = (self.cursor + num_spawns) % MAX_PARTICLES;
+        self.frame = self.frame.wrapping_add(1);
+        let command_buffer = builder.build()?;
+        Ok(before_future
+            .then_execute(self.comp_queue.clone(), command_buffer)?
+            .boxed())
+    }
+
+    /// Drop queued & alive particles, e.g. when particles are turned off
+    pub fn clear(&mut self) {
+        self.spawns.clear();
+        self.needs_clear = true;
+    }
+}
+
+/// Push spawn data of emissions to `spawns`, up to `MAX_PARTICLES` spawns. Kind & color of the
+/// particle depend on the matter that reacted
+fn queue_spawns(spawns: &mut Vec<u32>, emissions: &[u32], matter_definitions: &MatterDefinitions) {
+    let definitions = &matter_definitions.definitions;
+    for emission in emissions.chunks_exact(PARTICLE_EMISSION_STRIDE) {
+        if spawns.len() >= MAX_PARTICLES * SPAWN_STRIDE {
+            break;
+        }
+        let (from, to) = match (
+            definitions.get(emission[2] as usize),
+            definitions.get(emission[3] as usize),
+        ) {
+            (Some(from), Some(to)) => (from, to),
+            _ => continue,
+        };
+        let (kind, color) = if to.state == MatterState::Energy {
+            (ParticleKind::Ember, to.color)
+        } else if from.state == MatterState::Energy {
+            (ParticleKind::Spark, from.color)
+        } else {
+            (ParticleKind::Splash, from.color)
+        };
+        let color = Color32::from_u32_rgba(color).to_u32_abgr();
+        spawns.extend_from_slice(&[emission[0], emission[1], kind as u32, color]);
+    }
+}
+
+mod particles_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "compute_shaders/render/particles.glsl"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matter::default_matter_definitions;
+
+    #[test]
+    fn test_emission_particle_kinds() {
+        let definitions = default_matter_definitions();
+        let id_of = |state: MatterState| {
+            definitions
+                .definitions
+                .iter()
+                .find(|definition| definition.state == state)
+                .unwrap()
+                .id
+        };
+        let (energy, liquid, gas) = (
+            id_of(MatterState::Energy),
+            id_of(MatterState::Liquid),
+            id_of(MatterState::Gas),
+        );
+        let emissions = [
+            [1, (-2i32) as u32, liquid, energy],
+            [0, 0, energy, gas],
+            [0, 0, liquid, gas],
+            [0, 0, u32::MAX, gas],
+        ];
+        let mut spawns = vec![];
+        queue_spawns(&mut spawns, &emissions.concat(), &definitions);
+        // Unknown matter isn't spawned
+        assert_eq!(spawns.len(), 3 * SPAWN_STRIDE);
+        assert_eq!(spawns[..3], [1, (-2i32) as u32, ParticleKind::Ember as u32]);
+        assert_eq!(spawns[SPAWN_STRIDE + 2], ParticleKind::Spark as u32);
+        assert_eq!(spawns[2 * SPAWN_STRIDE + 2], ParticleKind::Splash as u32);
+    }
+}
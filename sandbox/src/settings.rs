@@ -1,15 +1,89 @@
 use corrode::renderer::Renderer;
-use vulkano::device::physical::PhysicalDeviceType;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use vulkano::{device::physical::PhysicalDeviceType, sampler::Filter};
 
-use crate::{INIT_DISPERSION_STEPS, INIT_MOVEMENT_STEPS, SIM_CANVAS_SIZE};
+use crate::{
+    interact::HotkeyModifier,
+    object::ALL_OBJECT_LAYERS,
+    sim::{ChunkStreaming, DebrisPolicy, LiquidModel},
+    INIT_DISPERSION_STEPS, INIT_MOVEMENT_STEPS, SIM_CANVAS_SIZE,
+};
 
-#[derive(Debug, Clone, Copy)]
+/// Seed of new maps' randomness. Loaded maps keep the seed they were saved with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SimulationSeed {
+    Random,
+    /// Same seed for every new map, so runs can be reproduced
+    Fixed(u64),
+}
+
+impl SimulationSeed {
+    pub fn world_seed(&self) -> u64 {
+        match self {
+            SimulationSeed::Random => rand::thread_rng().gen(),
+            SimulationSeed::Fixed(seed) => *seed,
+        }
+    }
+}
+
+/// Missing fields (e.g. in settings profiles saved by older versions) are defaults
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppSettings {
     pub dispersion_steps: u32,
     pub movement_steps: u32,
+    pub liquid_model: LiquidModel,
     pub sim_fps: f32,
     pub print_performance: bool,
     pub chunked_simulation: bool,
+    /// Which chunks are kept loaded around the camera in chunked simulation
+    pub chunk_streaming: ChunkStreaming,
+    /// Form boundary colliders on a background thread. Boundary physics lag one frame behind
+    pub background_boundaries: bool,
+    /// Mask of object layers boundary colliders collide with
+    pub boundary_collides_with: u32,
+    /// Max dynamic pixel objects before debris is removed
+    pub max_dynamic_objects: u32,
+    /// Max colliders of dynamic pixel objects before debris is removed
+    pub max_object_colliders: u32,
+    pub debris_policy: DebrisPolicy,
+    /// Merge dynamic objects that have been asleep on terrain for `freeze_after_secs` into the
+    /// grid, keeping long sessions from piling up physics objects
+    pub freeze_resting_objects: bool,
+    pub freeze_after_secs: f32,
+    /// Opt-in check for a newer release
+    pub check_for_updates: bool,
+    /// Write per step stats to a csv file in `stats` directory
+    pub log_stats: bool,
+    /// Fraction of window resolution canvas is rendered at, then upscaled to window
+    pub render_scale: f32,
+    #[serde(with = "filter_name")]
+    pub upscale_filter: Filter,
+    /// Canvas is drawn smoothed when zoomed out so that cells are smaller than this many pixels,
+    /// 0.0 keeps it sharp at all zooms
+    pub smooth_canvas_below: f32,
+    /// Canvas is drawn from 2x color images with synthesized sub-cell detail. Visual only
+    pub detail_upscale: bool,
+    /// Sparks, embers & splashes are drawn where energy reacts & liquid boils. Visual only
+    pub particles: bool,
+    /// Simulation doesn't run while pointer is over gui or gui takes keyboard input. Manual steps
+    /// still run
+    pub pause_while_gui_focused: bool,
+    /// Modifier letting tool hotkeys through while typing in gui, see `KeyGate`
+    pub hotkey_modifier: Option<HotkeyModifier>,
+    /// Simulate only around camera, cursor & dynamic objects, and where matter keeps changing.
+    /// Far regions are frozen, which keeps large canvases interactive on slower gpus
+    pub region_of_interest: bool,
+    /// Cells simulated around camera & cursor in region of interest mode
+    pub region_of_interest_radius: u32,
+    pub simulation_seed: SimulationSeed,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        AppSettings::new()
+    }
 }
 
 impl AppSettings {
@@ -20,9 +94,30 @@ impl AppSettings {
         AppSettings {
             dispersion_steps,
             movement_steps,
+            liquid_model: LiquidModel::Dispersion,
             sim_fps,
             print_performance: false,
             chunked_simulation: false,
+            chunk_streaming: ChunkStreaming::Radial,
+            background_boundaries: true,
+            boundary_collides_with: ALL_OBJECT_LAYERS,
+            max_dynamic_objects: 300,
+            max_object_colliders: 3000,
+            debris_policy: DebrisPolicy::MergeIntoGrid,
+            freeze_resting_objects: false,
+            freeze_after_secs: 10.0,
+            check_for_updates: false,
+            log_stats: false,
+            render_scale: 1.0,
+            upscale_filter: Filter::Nearest,
+            smooth_canvas_below: 0.0,
+            detail_upscale: false,
+            particles: true,
+            pause_while_gui_focused: false,
+            hotkey_modifier: Some(HotkeyModifier::Alt),
+            region_of_interest: false,
+            region_of_interest_radius: 128,
+            simulation_seed: SimulationSeed::Random,
         }
     }
 
@@ -43,10 +138,32 @@ impl AppSettings {
             self.dispersion_steps = 3;
             self.movement_steps = 1;
         };
-        if *SIM_CANVAS_SIZE == 1024 {
+        // Larger than default canvases are heavier to simulate
+        if SIM_CANVAS_SIZE.x * SIM_CANVAS_SIZE.y > 512 * 512 {
             self.dispersion_steps = 4;
             self.movement_steps = 1;
             self.sim_fps = 30.0;
         }
     }
 }
+
+/// Vulkano's filter isn't serializable, so it's saved by name
+mod filter_name {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use vulkano::sampler::Filter;
+
+    pub fn serialize<S: Serializer>(filter: &Filter, serializer: S) -> Result<S::Ok, S::Error> {
+        match filter {
+            Filter::Linear => "Linear",
+            _ => "Nearest",
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Filter, D::Error> {
+        Ok(match String::deserialize(deserializer)?.as_str() {
+            "Linear" => Filter::Linear,
+            _ => Filter::Nearest,
+        })
+    }
+}
@@ -0,0 +1,312 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::*;
+use corrode::input_system::{InputButton, InputSystem};
+use serde::{Deserialize, Serialize};
+use winit::event::VirtualKeyCode;
+
+use crate::{
+    app::{default_key_bindings, InputAction},
+    data_dirs::DATA_DIRS,
+    interact::CAMERA_PAN_KEYS,
+    settings::AppSettings,
+    utils::data_file_path,
+};
+
+/// Name of profile loaded on startup, in profiles directory
+const LAST_PROFILE_FILE: &str = "last_profile.txt";
+
+/// Keys input actions can be bound to
+pub const BINDABLE_KEYS: &[VirtualKeyCode] = &[
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+    VirtualKeyCode::Key0,
+    VirtualKeyCode::A,
+    VirtualKeyCode::B,
+    VirtualKeyCode::C,
+    VirtualKeyCode::D,
+    VirtualKeyCode::E,
+    VirtualKeyCode::F,
+    VirtualKeyCode::G,
+    VirtualKeyCode::H,
+    VirtualKeyCode::I,
+    VirtualKeyCode::J,
+    VirtualKeyCode::K,
+    VirtualKeyCode::L,
+    VirtualKeyCode::M,
+    VirtualKeyCode::N,
+    VirtualKeyCode::O,
+    VirtualKeyCode::P,
+    VirtualKeyCode::Q,
+    VirtualKeyCode::R,
+    VirtualKeyCode::S,
+    VirtualKeyCode::T,
+    VirtualKeyCode::U,
+    VirtualKeyCode::V,
+    VirtualKeyCode::W,
+    VirtualKeyCode::X,
+    VirtualKeyCode::Y,
+    VirtualKeyCode::Z,
+    VirtualKeyCode::F1,
+    VirtualKeyCode::F2,
+    VirtualKeyCode::F3,
+    VirtualKeyCode::F4,
+    VirtualKeyCode::F5,
+    VirtualKeyCode::F6,
+    VirtualKeyCode::F7,
+    VirtualKeyCode::F8,
+    VirtualKeyCode::F9,
+    VirtualKeyCode::F10,
+    VirtualKeyCode::F11,
+    VirtualKeyCode::F12,
+    VirtualKeyCode::Space,
+    VirtualKeyCode::Return,
+    VirtualKeyCode::Tab,
+    VirtualKeyCode::Back,
+    VirtualKeyCode::Insert,
+    VirtualKeyCode::Delete,
+    VirtualKeyCode::Home,
+    VirtualKeyCode::End,
+    VirtualKeyCode::PageUp,
+    VirtualKeyCode::PageDown,
+];
+
+pub fn key_name(key: VirtualKeyCode) -> String {
+    format!("{:?}", key)
+}
+
+pub fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+    BINDABLE_KEYS.iter().copied().find(|key| key_name(*key) == name)
+}
+
+/// Key bound to an action, if it's bound to a key
+pub fn bound_key(inputs: &InputSystem<InputAction>, action: InputAction) -> Option<VirtualKeyCode> {
+    match inputs.action_mapped(action) {
+        Some(InputButton::Key(key)) => Some(*key),
+        _ => None,
+    }
+}
+
+/// Keys bound to actions, in `default_key_bindings` order
+pub fn current_key_bindings(
+    inputs: &InputSystem<InputAction>,
+) -> Vec<(InputAction, VirtualKeyCode)> {
+    default_key_bindings()
+        .into_iter()
+        .filter_map(|(action, _)| bound_key(inputs, action).map(|key| (action, key)))
+        .collect()
+}
+
+/// Describes why keys can't be bound like this, e.g. a key bound to two actions. Keys panning
+/// the camera are hard-wired, so they can't be bound either
+pub fn key_binding_problem(key_bindings: &[(InputAction, VirtualKeyCode)]) -> Option<String> {
+    for (i, (action, key)) in key_bindings.iter().enumerate() {
+        if CAMERA_PAN_KEYS.iter().any(|(pan_key, _)| pan_key == key) {
+            return Some(format!(
+                "{} pans camera, it can't be bound to {:?}",
+                key_name(*key),
+                action
+            ));
+        }
+        let bound_before = key_bindings[..i]
+            .iter()
+            .find(|(_, other_key)| other_key == key);
+        if let Some((other, _)) = bound_before {
+            return Some(format!(
+                "{} is bound to both {:?} & {:?}",
+                key_name(*key),
+                other,
+                action
+            ));
+        }
+    }
+    None
+}
+
+/// `key_bindings` with `action` bound to `key` instead of its current key
+pub fn rebind_key(
+    key_bindings: &[(InputAction, VirtualKeyCode)],
+    action: InputAction,
+    key: VirtualKeyCode,
+) -> Vec<(InputAction, VirtualKeyCode)> {
+    let mut rebound = key_bindings
+        .iter()
+        .copied()
+        .filter(|(bound_action, _)| *bound_action != action)
+        .collect::<Vec<_>>();
+    rebound.push((action, key));
+    rebound
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct SettingsProfile {
+    settings: AppSettings,
+    /// Key names by action
+    key_bindings: Vec<(InputAction, String)>,
+}
+
+/// Settings & key bindings saved by name, e.g. lighter settings for a laptop. Saved in
+/// assets/settings_profiles, apart from maps
+pub struct SettingsProfiles {
+    pub names: Vec<String>,
+    /// Profile last loaded or saved, which is loaded on startup
+    pub current: Option<String>,
+    /// Name current settings are saved as
+    pub profile_name: String,
+}
+
+impl SettingsProfiles {
+    pub fn new() -> Result<SettingsProfiles> {
+        let dir_path = profiles_dir()?;
+        let mut names = vec![];
+        for entry in fs::read_dir(&dir_path)? {
+            let path = entry?.path();
+            if path.extension().map_or(false, |ext| ext == "json") {
+                if let Some(name) = path.file_stem() {
+                    names.push(name.to_string_lossy().to_string());
+                }
+            }
+        }
+        names.sort();
+        let current = fs::read_to_string(dir_path.join(LAST_PROFILE_FILE))
+            .ok()
+            .map(|name| name.trim().to_string())
+            .filter(|name| names.contains(name));
+        Ok(SettingsProfiles {
+            profile_name: current.clone().unwrap_or_else(|| "Desktop".to_string()),
+            names,
+            current,
+        })
+    }
+
+    /// Save settings & key bindings by `profile_name`. A profile of the same name is replaced
+    pub fn save(
+        &mut self,
+        settings: &AppSettings,
+        inputs: &InputSystem<InputAction>,
+    ) -> Result<String> {
+        let name = self.profile_name.trim().to_string();
+        let key_bindings = current_key_bindings(inputs)
+            .into_iter()
+            .map(|(action, key)| (action, key_name(key)))
+            .collect();
+        let profile = SettingsProfile {
+            settings: *settings,
+            key_bindings,
+        };
+        fs::write(
+            data_file_path(&profiles_dir()?, &name, "json")?,
+            serde_json::to_string_pretty(&profile)?,
+        )?;
+        if !self.names.contains(&name) {
+            self.names.push(name.clone());
+            self.names.sort();
+        }
+        self.set_current(Some(&name))?;
+        Ok(name)
+    }
+
+    /// Replace settings & key bindings with a profile's. Chunked simulation is kept, as it
+    /// depends on the loaded map. Nothing is replaced if profile's keys conflict
+    pub fn load(
+        &mut self,
+        name: &str,
+        settings: &mut AppSettings,
+        inputs: &mut InputSystem<InputAction>,
+    ) -> Result<()> {
+        let data = fs::read_to_string(profiles_dir()?.join(format!("{}.json", name)))?;
+        let profile = serde_json::from_str::<SettingsProfile>(&data)
+            .with_context(|| format!("Invalid settings profile {}", name))?;
+        let key_bindings = profile
+            .key_bindings
+            .iter()
+            .map(|(action, key)| {
+                key_from_name(key)
+                    .map(|key| (*action, key))
+                    .with_context(|| format!("Unknown key {} for {:?}", key, action))
+            })
+            .collect::<Result<Vec<(InputAction, VirtualKeyCode)>>>()?;
+        // Actions missing from profile keep their current keys, which may conflict as well
+        let merged = key_bindings
+            .iter()
+            .fold(current_key_bindings(inputs), |bindings, (action, key)| {
+                rebind_key(&bindings, *action, *key)
+            });
+        if let Some(problem) = key_binding_problem(&merged) {
+            bail!("Invalid key bindings in profile {}: {}", name, problem);
+        }
+        let key_bindings = key_bindings
+            .into_iter()
+            .map(|(action, key)| (action, InputButton::Key(key)))
+            .collect::<Vec<(InputAction, InputButton)>>();
+        inputs.mapper_mut().add_actions(&key_bindings);
+        let chunked_simulation = settings.chunked_simulation;
+        *settings = profile.settings;
+        settings.chunked_simulation = chunked_simulation;
+        self.profile_name = name.to_string();
+        self.set_current(Some(name))
+    }
+
+    pub fn delete(&mut self, name: &str) -> Result<()> {
+        fs::remove_file(profiles_dir()?.join(format!("{}.json", name)))?;
+        self.names.retain(|n| n != name);
+        if self.current.as_deref() == Some(name) {
+            self.set_current(None)?;
+        }
+        Ok(())
+    }
+
+    fn set_current(&mut self, name: Option<&str>) -> Result<()> {
+        let path = profiles_dir()?.join(LAST_PROFILE_FILE);
+        match name {
+            Some(name) => fs::write(path, name)?,
+            None if path.exists() => fs::remove_file(path)?,
+            None => (),
+        }
+        self.current = name.map(|name| name.to_string());
+        Ok(())
+    }
+}
+
+fn profiles_dir() -> Result<PathBuf> {
+    let dir_path = DATA_DIRS.config.join("settings_profiles");
+    fs::create_dir_all(&dir_path)?;
+    Ok(dir_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_defaults_missing_settings() {
+        let profile = serde_json::from_str::<SettingsProfile>(
+            r#"{"settings": {"sim_fps": 30.0}, "key_bindings": [["Pause", "P"]]}"#,
+        )
+        .unwrap();
+        assert_eq!(profile.settings.sim_fps, 30.0);
+        assert_eq!(profile.settings.movement_steps, AppSettings::new().movement_steps);
+        assert_eq!(profile.key_bindings[0].0, InputAction::Pause);
+        assert_eq!(key_from_name(&profile.key_bindings[0].1), Some(VirtualKeyCode::P));
+        for (_, key) in default_key_bindings() {
+            assert_eq!(key_from_name(&key_name(key)), Some(key));
+        }
+    }
+
+    #[test]
+    fn test_key_binding_problems() {
+        let key_bindings = default_key_bindings();
+        assert_eq!(key_binding_problem(&key_bindings), None);
+        let duplicate = rebind_key(&key_bindings, InputAction::SpawnBugs, VirtualKeyCode::Z);
+        assert!(key_binding_problem(&duplicate).unwrap().contains("Undo"));
+        let pan_key = rebind_key(&key_bindings, InputAction::SpawnBugs, VirtualKeyCode::W);
+        assert!(key_binding_problem(&pan_key).unwrap().contains("pans camera"));
+    }
+}
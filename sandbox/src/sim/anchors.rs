@@ -0,0 +1,37 @@
+use cgmath::Vector2;
+use hecs::Entity;
+use rapier2d::prelude::*;
+
+use crate::matter::{MatterDefinition, MatterState};
+
+/// How far (in cells) from the clicked point solid terrain is searched for an anchor
+pub const ANCHOR_SEARCH_RADIUS: i32 = 6;
+/// Anchor holds while there's solid matter this close (in cells) to its support cell
+pub const ANCHOR_SUPPORT_RADIUS: i32 = 1;
+
+/// Dynamic object nailed to terrain with a fixed joint to an invisible fixed body at the support
+/// cell. Terrain colliders are rebuilt all the time, so the joint can't be attached to them
+#[derive(Debug, Copy, Clone)]
+pub struct Anchor {
+    pub entity: Entity,
+    /// Object's body the joint was made with. Deformed objects get new bodies and need a new joint
+    pub object_body: RigidBodyHandle,
+    pub anchor_body: RigidBodyHandle,
+    pub joint: ImpulseJointHandle,
+    /// Canvas position of the terrain cell holding the object
+    pub support: Vector2<i32>,
+}
+
+/// Matter objects can be anchored to. Gravity solids would fall with the object
+pub fn can_support_anchor(matter: &MatterDefinition) -> bool {
+    matter.state == MatterState::Solid
+}
+
+/// Fixed joint holding `object` where it currently is relative to `anchor`
+pub fn anchor_joint(object: &RigidBody, anchor: &RigidBody) -> FixedJoint {
+    let local_frame1 = object.position().inverse() * anchor.position();
+    FixedJointBuilder::new()
+        .local_frame1(local_frame1)
+        .local_frame2(Isometry::identity())
+        .build()
+}
@@ -0,0 +1,88 @@
+use anyhow::*;
+use cgmath::{InnerSpace, Vector2};
+use serde::{Deserialize, Serialize};
+
+/// Text label pinned to a world position, optionally with an arrow pointing elsewhere. Used to
+/// document maps & tutorials
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub text: String,
+    /// World position of the label's top left corner
+    pub pos: Vector2<f32>,
+    /// World position the arrow from label points to
+    pub arrow_to: Option<Vector2<f32>>,
+    /// World height of glyphs
+    pub height: f32,
+    pub color: [f32; 4],
+}
+
+impl Annotation {
+    /// Arrow shaft & head as line segments, None without an arrow or if it has no length
+    pub fn arrow_lines(&self) -> Option<[(Vector2<f32>, Vector2<f32>); 3]> {
+        let end = self.arrow_to?;
+        let shaft = end - self.pos;
+        if shaft.magnitude2() == 0.0 {
+            return None;
+        }
+        // Head is as long as glyphs are tall, but at most half the shaft
+        let back = -shaft.normalize() * self.height.min(shaft.magnitude() * 0.5);
+        let side = Vector2::new(-back.y, back.x) * 0.5;
+        Some([
+            (self.pos, end),
+            (end, end + back + side),
+            (end, end + back - side),
+        ])
+    }
+}
+
+/// Annotations of a map, saved with it
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Annotations {
+    pub annotations: Vec<Annotation>,
+}
+
+impl Annotations {
+    pub fn new() -> Annotations {
+        Annotations {
+            annotations: vec![],
+        }
+    }
+
+    pub fn serialize(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn deserialize(data: &str) -> Result<Annotations> {
+        Ok(serde_json::from_str(data)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotation_arrow_head_points_back_along_shaft() {
+        let mut annotation = Annotation {
+            text: "Lava".to_string(),
+            pos: Vector2::new(0.0, 0.0),
+            arrow_to: None,
+            height: 1.0,
+            color: [1.0; 4],
+        };
+        assert!(annotation.arrow_lines().is_none());
+        annotation.arrow_to = Some(Vector2::new(10.0, 0.0));
+        let [shaft, left, right] = annotation.arrow_lines().unwrap();
+        assert_eq!(shaft.1, Vector2::new(10.0, 0.0));
+        assert_eq!(left.1, Vector2::new(9.0, -0.5));
+        assert_eq!(right.1, Vector2::new(9.0, 0.5));
+        let loaded = Annotations::deserialize(
+            &Annotations {
+                annotations: vec![annotation.clone()],
+            }
+            .serialize(),
+        )
+        .unwrap();
+        assert_eq!(loaded.annotations, vec![annotation]);
+    }
+}
@@ -1,32 +1,218 @@
+use std::sync::mpsc::Receiver;
+
+use cgmath::Vector2;
 use hecs::Entity;
 
-use crate::{BITMAP_RATIO, SIM_CANVAS_SIZE};
+use crate::{object::ALL_OBJECT_LAYERS, sim::BoundaryObjectData, BITMAP_RATIO, SIM_CANVAS_SIZE};
+
+/// Side length (in bitmap pixels) of a boundary region. Bitmap changes are tracked per region
+pub const BOUNDARY_REGION_SIZE: usize = 32;
+
+/// Bitmap width & height
+pub fn bitmap_size() -> Vector2<usize> {
+    (*SIM_CANVAS_SIZE / *BITMAP_RATIO).cast::<usize>().unwrap()
+}
+
+/// Number of boundary regions on a bitmap row (x) & column (y)
+pub fn boundary_regions() -> Vector2<usize> {
+    bitmap_size().map(|size| (size + BOUNDARY_REGION_SIZE - 1) / BOUNDARY_REGION_SIZE)
+}
 
 pub struct PhysicsBoundaries {
-    pub solids_changed: bool,
-    pub powders_changed: bool,
-    pub liquids_changed: bool,
+    /// Incremented whenever boundary objects are rebuilt
+    pub version: u64,
+    /// Changed flags per boundary region (row major), raised by bitmap diffing & painting. Only
+    /// colliders of changed regions are rebuilt
+    pub solid_regions_changed: Vec<bool>,
+    pub powder_regions_changed: Vec<bool>,
+    pub liquid_regions_changed: Vec<bool>,
     pub solid_bitmap: Vec<f64>,
     pub powder_bitmap: Vec<f64>,
     pub liquid_bitmap: Vec<f64>,
-    pub solid_objects: Vec<Entity>,
-    pub powder_objects: Vec<Entity>,
-    pub liquid_objects: Vec<Entity>,
+    /// Boundary objects per boundary region
+    pub solid_objects: Vec<Vec<Entity>>,
+    pub powder_objects: Vec<Vec<Entity>>,
+    pub liquid_objects: Vec<Vec<Entity>>,
+    /// World position boundary objects were built at. Regions are positioned relative to it, so
+    /// all are rebuilt when it moves
+    pub built_at: Option<Vector2<f32>>,
+    /// Boundary objects being formed in the background, replacing current ones on next update
+    pub pending_objects: Option<Receiver<Vec<BoundaryObjectData>>>,
+    /// Mask of object layers boundary colliders collide with
+    pub collides_with: u32,
 }
 
 impl PhysicsBoundaries {
     pub fn new() -> PhysicsBoundaries {
-        let bitmap_size = (*SIM_CANVAS_SIZE / *BITMAP_RATIO) as usize;
+        let bitmap_size = bitmap_size();
+        let regions = boundary_regions();
+        let num_regions = regions.x * regions.y;
         PhysicsBoundaries {
-            solids_changed: false,
-            powders_changed: false,
-            liquids_changed: false,
-            solid_bitmap: vec![0.0; bitmap_size * bitmap_size],
-            powder_bitmap: vec![0.0; bitmap_size * bitmap_size],
-            liquid_bitmap: vec![0.0; bitmap_size * bitmap_size],
-            solid_objects: vec![],
-            powder_objects: vec![],
-            liquid_objects: vec![],
+            version: 0,
+            solid_regions_changed: vec![false; num_regions],
+            powder_regions_changed: vec![false; num_regions],
+            liquid_regions_changed: vec![false; num_regions],
+            solid_bitmap: vec![0.0; bitmap_size.x * bitmap_size.y],
+            powder_bitmap: vec![0.0; bitmap_size.x * bitmap_size.y],
+            liquid_bitmap: vec![0.0; bitmap_size.x * bitmap_size.y],
+            solid_objects: vec![vec![]; num_regions],
+            powder_objects: vec![vec![]; num_regions],
+            liquid_objects: vec![vec![]; num_regions],
+            built_at: None,
+            pending_objects: None,
+            collides_with: ALL_OBJECT_LAYERS,
         }
     }
+
+    /// Mark boundary regions overlapping bitmap rectangle `min` - `max` (inclusive) changed for
+    /// all states, e.g. after painting cells there
+    pub fn mark_rect_changed(&mut self, min: Vector2<usize>, max: Vector2<usize>) {
+        for region in regions_in_rect(min, max) {
+            self.solid_regions_changed[region] = true;
+            self.powder_regions_changed[region] = true;
+            self.liquid_regions_changed[region] = true;
+        }
+    }
+
+    /// Mark every boundary region changed, e.g. when the bitmap moved with camera
+    pub fn mark_all_changed(&mut self) {
+        self.solid_regions_changed.fill(true);
+        self.powder_regions_changed.fill(true);
+        self.liquid_regions_changed.fill(true);
+    }
+}
+
+/// Indices of boundary regions overlapping bitmap rectangle `min` - `max` (inclusive), clamped to
+/// the bitmap
+pub fn regions_in_rect(min: Vector2<usize>, max: Vector2<usize>) -> Vec<usize> {
+    let regions = boundary_regions();
+    let last = bitmap_size().map(|size| size - 1);
+    let (min, max) = (
+        Vector2::new(min.x.min(last.x), min.y.min(last.y)) / BOUNDARY_REGION_SIZE,
+        Vector2::new(max.x.min(last.x), max.y.min(last.y)) / BOUNDARY_REGION_SIZE,
+    );
+    (min.y..=max.y)
+        .flat_map(|y| (min.x..=max.x).map(move |x| y * regions.x + x))
+        .collect()
+}
+
+/// Indices of regions whose flags are raised, lowering the flags
+pub fn take_changed_regions(regions_changed: &mut [bool]) -> Vec<usize> {
+    let changed = regions_changed
+        .iter()
+        .enumerate()
+        .filter(|(_, changed)| **changed)
+        .map(|(region, _)| region)
+        .collect();
+    regions_changed.fill(false);
+    changed
+}
+
+/// Bitmap pixels of `region` with a pixel of margin taken from neighboring regions, so colliders
+/// of adjacent regions overlap instead of leaving a gap at the seam. Returns the pixels, their
+/// width & height, and offset (in bitmap pixels) of their center from bitmap's center
+pub fn region_bitmap(bitmap: &[f64], region: usize) -> (Vec<f64>, u32, u32, Vector2<f64>) {
+    let size = bitmap_size();
+    let regions = boundary_regions();
+    let region_pos = Vector2::new(region % regions.x, region / regions.x) * BOUNDARY_REGION_SIZE;
+    let min = region_pos.map(|v| v.saturating_sub(1));
+    let max = Vector2::new(
+        (region_pos.x + BOUNDARY_REGION_SIZE + 1).min(size.x),
+        (region_pos.y + BOUNDARY_REGION_SIZE + 1).min(size.y),
+    );
+    let pixels = (min.y..max.y)
+        .flat_map(|y| bitmap[y * size.x + min.x..y * size.x + max.x].iter().copied())
+        .collect();
+    let center_offset =
+        (min + max).cast::<f64>().unwrap() * 0.5 - size.cast::<f64>().unwrap() * 0.5;
+    (pixels, (max.x - min.x) as u32, (max.y - min.y) as u32, center_offset)
+}
+
+#[inline]
+fn write_if_changed(old: &mut f64, new: f64, region_changed: &mut bool) {
+    if *old != new {
+        *old = new;
+        *region_changed = true;
+    }
+}
+
+/// Diff one band of `BOUNDARY_REGION_SIZE` bitmap rows against the gpu bitmap. Values are only
+/// written when they differ, and the band's region flags are raised where they did
+pub(crate) fn diff_bitmap_band(
+    gpu_band: &[u32],
+    bitmap_width: usize,
+    [solid, powder, liquid]: [&mut [f64]; 3],
+    [solid_regions, powder_regions, liquid_regions]: [&mut [bool]; 3],
+) {
+    for (i, &gpu_val) in gpu_band.iter().enumerate() {
+        let region = (i % bitmap_width) / BOUNDARY_REGION_SIZE;
+        write_if_changed(
+            &mut solid[i],
+            (gpu_val & (1 << 0)) as f64,
+            &mut solid_regions[region],
+        );
+        write_if_changed(
+            &mut powder[i],
+            (gpu_val & (1 << 1)) as f64,
+            &mut powder_regions[region],
+        );
+        write_if_changed(
+            &mut liquid[i],
+            (gpu_val & (1 << 2)) as f64,
+            &mut liquid_regions[region],
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_bitmap_band_flags_changed_regions_only() {
+        let bitmap_size = BOUNDARY_REGION_SIZE * 2;
+        let mut gpu = vec![0u32; bitmap_size * BOUNDARY_REGION_SIZE];
+        // Solid in second region of the last row
+        gpu[bitmap_size * (BOUNDARY_REGION_SIZE - 1) + BOUNDARY_REGION_SIZE] = 1;
+        let mut solid = vec![0.0; gpu.len()];
+        let mut powder = vec![0.0; gpu.len()];
+        let mut liquid = vec![0.0; gpu.len()];
+        let mut solid_regions = vec![false; 2];
+        let mut powder_regions = vec![false; 2];
+        let mut liquid_regions = vec![false; 2];
+        diff_bitmap_band(
+            &gpu,
+            bitmap_size,
+            [&mut solid, &mut powder, &mut liquid],
+            [&mut solid_regions, &mut powder_regions, &mut liquid_regions],
+        );
+        assert_eq!(solid_regions, vec![false, true]);
+        assert_eq!(powder_regions, vec![false, false]);
+        assert_eq!(liquid_regions, vec![false, false]);
+        assert_eq!(solid.iter().filter(|v| **v != 0.0).count(), 1);
+    }
+
+    #[test]
+    fn test_region_bitmap_overlaps_neighbors() {
+        let size = bitmap_size();
+        let regions = boundary_regions();
+        let bitmap = (0..size.x * size.y).map(|i| i as f64).collect::<Vec<f64>>();
+        // First region has margin only towards its neighbors
+        let (pixels, width, height, _) = region_bitmap(&bitmap, 0);
+        let margined = BOUNDARY_REGION_SIZE as u32 + 1;
+        assert_eq!((width, height), (margined, margined));
+        assert_eq!(pixels[width as usize], size.x as f64);
+        // Inner region's center is offset from bitmap center by its position
+        let region = regions.x + 1;
+        let (pixels, width, _, center_offset) = region_bitmap(&bitmap, region);
+        assert_eq!(width, BOUNDARY_REGION_SIZE as u32 + 2);
+        let corner = (BOUNDARY_REGION_SIZE - 1) * size.x + BOUNDARY_REGION_SIZE - 1;
+        assert_eq!(pixels[0], corner as f64);
+        let expected = (BOUNDARY_REGION_SIZE as f64 * 1.5) - size.x as f64 * 0.5;
+        assert_eq!(center_offset.x, expected);
+        // Rect across a region corner touches four regions
+        let at = Vector2::new(BOUNDARY_REGION_SIZE, BOUNDARY_REGION_SIZE);
+        let touched = regions_in_rect(at - Vector2::new(1, 1), at);
+        assert_eq!(touched, vec![0, 1, regions.x, regions.x + 1]);
+    }
 }
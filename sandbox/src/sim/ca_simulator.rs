@@ -1,7 +1,10 @@
-use std::{sync::Arc, time::Instant};
+use std::{collections::BTreeSet, sync::Arc};
 
 use anyhow::*;
 use cgmath::Vector2;
+use rand::Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use vulkano::{
     buffer::CpuAccessibleBuffer,
     command_buffer::{
@@ -15,17 +18,79 @@ use vulkano::{
     device::Queue,
     pipeline::{ComputePipeline, Pipeline, PipelineBindPoint, PipelineLayout},
     shader::ShaderStages,
-    sync::GpuFuture,
+    sync::{FenceSignalFuture, GpuFuture},
 };
 
 use crate::{
-    matter::{MatterDefinition, MatterDefinitions, MatterState, MAX_TRANSITIONS},
+    matter::{
+        MatterDefinition, MatterDefinitions, MatterState, DECAY_INTERVAL,
+        MAX_REACTION_TICK_DIVIDER, MAX_TRANSITIONS,
+    },
+    render::{MAX_PARTICLE_EMISSIONS, PARTICLE_EMISSION_STRIDE},
     settings::AppSettings,
-    sim::{empty_f32, empty_u32, GpuChunk, SimulationChunkManager},
-    utils::u32_rgba_to_u32_abgr,
+    sim::{
+        boundaries::{
+            bitmap_size, boundary_regions, diff_bitmap_band, BOUNDARY_REGION_SIZE,
+        },
+        empty_f32, empty_u32, GpuChunk, GpuPass, GpuTimings, Portals, RegionOfInterest,
+        SimulationChunkManager, MAX_PORTALS, REACTION_COUNT_STRIDE, ROI_TILE_SIZE,
+    },
+    utils::Color32,
     BITMAP_RATIO, KERNEL_SIZE, MAX_NUM_MATTERS, SIM_CANVAS_SIZE,
 };
 
+/// Decay data per matter in `matter_decay_input`: probability (as bits), becomes & emits
+const MATTER_DECAY_STRIDE: usize = 3;
+/// Reaction tick divider is packed above directions in `matter_reaction_direction_input`.
+/// Must match compute_shaders/simulation/includes.glsl
+const REACTION_TICK_DIVIDER_SHIFT: u32 = 16;
+/// Set in gpu matter characteristics of matter protected in editor, so brush & reactions leave
+/// its cells be. Must match MATTER_PROTECTED in compute shaders
+pub const MATTER_PROTECTED_BIT: u32 = 1 << 31;
+/// Max number of bugs alive at once. Must be divisible by KERNEL_SIZE * KERNEL_SIZE
+pub const MAX_BUGS: usize = 1024;
+/// Bug data in `bugs`: canvas x & y (as i32 bits) & `BugState`
+const BUG_STRIDE: usize = 3;
+
+/// Pressure solve iterations per step. Pressure spreads one cell per iteration
+const PRESSURE_ITERATIONS: u32 = 8;
+/// Max brush strokes painted per step, the rest wait for the next step
+pub const MAX_PAINT_STROKES: usize = 256;
+/// Stroke data in `paint_strokes`: center x & y (as i32 bits), radius (as f32 bits), matter &
+/// shape. Must match compute_shaders/paint/paint.glsl
+const STROKE_STRIDE: usize = 5;
+
+/// Brush stroke painted on gpu at the start of a step, see `CASimulator::queue_paint`. Covers the
+/// same cells as `Simulation::paint_round` & `paint_square` without a fill
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PaintStroke {
+    pub center: Vector2<i32>,
+    /// Half of the side for squares
+    pub radius: f32,
+    pub matter: u32,
+    pub is_square: bool,
+}
+
+/// How liquids move besides falling
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LiquidModel {
+    /// Liquids spread sideways by dispersion steps only
+    Dispersion,
+    /// Liquids also get pressure & velocity fields: pressure pushes liquid up in connected
+    /// tunnels towards its level and velocity makes it flow away from pressure. See
+    /// compute_shaders/liquids
+    Pressure,
+}
+
+/// Must match compute_shaders/agents/includes.glsl
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u32)]
+pub enum BugState {
+    Dead = 0,
+    Right = 1,
+    Left = 2,
+}
+
 pub struct CASimulator {
     pub comp_queue: Arc<Queue>,
     // Simulation pipelines (Could also be one pipeline with multiple entry points... :D)
@@ -38,7 +103,15 @@ pub struct CASimulator {
     horizontal_empty_pipeline: Arc<ComputePipeline>,
     horizontal_swap_pipeline: Arc<ComputePipeline>,
     react_pipeline: Arc<ComputePipeline>,
+    decay_pipeline: Arc<ComputePipeline>,
     color_pipeline: Arc<ComputePipeline>,
+    bugs_pipeline: Arc<ComputePipeline>,
+    paint_pipeline: Arc<ComputePipeline>,
+    // Pressure liquid pipelines
+    pressure_pipeline: Arc<ComputePipeline>,
+    liquid_velocity_pipeline: Arc<ComputePipeline>,
+    liquid_rise_pipeline: Arc<ComputePipeline>,
+    liquid_flow_pipeline: Arc<ComputePipeline>,
     // Utility pipelines
     init_pipeline: Arc<ComputePipeline>,
     update_bitmap_pipeline: Arc<ComputePipeline>,
@@ -53,21 +126,57 @@ pub struct CASimulator {
     matter_reaction_direction_input: Arc<CpuAccessibleBuffer<[u32]>>,
     matter_reaction_probability_input: Arc<CpuAccessibleBuffer<[f32]>>,
     matter_reaction_transition_input: Arc<CpuAccessibleBuffer<[u32]>>,
+    matter_decay_input: Arc<CpuAccessibleBuffer<[u32]>>,
+    matter_gravity_input: Arc<CpuAccessibleBuffer<[f32]>>,
+    /// Occurrences of each reaction per matter since last read, see `take_reaction_counts`
+    reaction_counts: Arc<CpuAccessibleBuffer<[u32]>>,
+    /// Region of interest flags per tile, see `RegionOfInterest`
+    tile_activity: Arc<CpuAccessibleBuffer<[u32]>>,
+    /// Reactions emitted for particle effects since last read, see `take_particle_emissions`
+    particle_emissions: Arc<CpuAccessibleBuffer<[u32]>>,
+    region_of_interest: RegionOfInterest,
     bitmap: Arc<CpuAccessibleBuffer<[u32]>>,
+    /// Exploding matter per bitmap block
+    explosions: Arc<CpuAccessibleBuffer<[u32]>>,
     tmp_matter: Arc<CpuAccessibleBuffer<[u32]>>,
+    /// Bugs walking on the grid, updated & drawn on gpu
+    bugs: Arc<CpuAccessibleBuffer<[u32]>>,
+    /// Liquid pressure & horizontal velocity per sim canvas cell, see `LiquidModel::Pressure`
+    liquid_pressure: Arc<CpuAccessibleBuffer<[f32]>>,
+    liquid_velocity: Arc<CpuAccessibleBuffer<[f32]>>,
+    /// Strokes of the next step on gpu, `STROKE_STRIDE` values each
+    paint_strokes: Arc<CpuAccessibleBuffer<[u32]>>,
+    /// Strokes waiting for a step
+    queued_strokes: Vec<PaintStroke>,
     //... push constants
     pub sim_steps: usize,
     dispersion_step: u32,
     dispersion_dir: u32,
     move_step: u32,
     sim_pos_offset: Vector2<i32>,
-    seed: f32,
-    start: Instant,
+    portals: [[i32; 4]; MAX_PORTALS],
+    portal_widths: [i32; MAX_PORTALS],
+    /// Seeds of the compute chunks for the current step, see `chunk_step_seed`
+    chunk_seeds: [f32; 4],
+    /// Seeds used by the next step instead of `chunk_step_seed`, e.g. when replaying
+    next_step_seeds: Option<[f32; 4]>,
+    roi_origin: Vector2<i32>,
+    /// Whether gpu counts reactions into `reaction_counts`
+    pub count_reactions: bool,
+    world_seed: u64,
+    /// Latest submitted step. Not waited on submit so gpu compute can overlap cpu work
+    compute_future: Option<Arc<FenceSignalFuture<Box<dyn GpuFuture>>>>,
+    /// Gpu time per pass of steps, None if timestamps aren't supported
+    gpu_timings: Option<GpuTimings>,
 }
 
 impl CASimulator {
     pub fn new(comp_queue: Arc<Queue>, empty: u32) -> Result<CASimulator> {
-        assert_eq!(*SIM_CANVAS_SIZE % KERNEL_SIZE, 0);
+        assert_eq!(SIM_CANVAS_SIZE.x % KERNEL_SIZE, 0);
+        assert_eq!(SIM_CANVAS_SIZE.y % KERNEL_SIZE, 0);
+        assert_eq!(MAX_BUGS as u32 % (KERNEL_SIZE * KERNEL_SIZE), 0);
+        assert_eq!(SIM_CANVAS_SIZE.x % ROI_TILE_SIZE as u32, 0);
+        assert_eq!(SIM_CANVAS_SIZE.y % ROI_TILE_SIZE as u32, 0);
 
         let matter_color_input = empty_u32(comp_queue.device().clone(), MAX_NUM_MATTERS as usize)?;
         let matter_state_input = empty_u32(comp_queue.device().clone(), MAX_NUM_MATTERS as usize)?;
@@ -92,18 +201,48 @@ impl CASimulator {
             comp_queue.device().clone(),
             MAX_NUM_MATTERS as usize * MAX_TRANSITIONS as usize,
         )?;
-
-        let bitmap = empty_u32(
+        let matter_decay_input = empty_u32(
             comp_queue.device().clone(),
-            ((*SIM_CANVAS_SIZE / *BITMAP_RATIO) * (*SIM_CANVAS_SIZE / *BITMAP_RATIO)) as usize,
+            MAX_NUM_MATTERS as usize * MATTER_DECAY_STRIDE,
         )?;
+        let matter_gravity_input =
+            empty_f32(comp_queue.device().clone(), MAX_NUM_MATTERS as usize)?;
+        let reaction_counts = empty_u32(
+            comp_queue.device().clone(),
+            MAX_NUM_MATTERS as usize * REACTION_COUNT_STRIDE,
+        )?;
+        let roi_tiles = RegionOfInterest::tiles();
+        let tile_activity = empty_u32(
+            comp_queue.device().clone(),
+            (roi_tiles.x * roi_tiles.y) as usize,
+        )?;
+        let mut region_of_interest = RegionOfInterest::new();
+        region_of_interest.clear(&mut tile_activity.write()?);
+        let particle_emissions = empty_u32(
+            comp_queue.device().clone(),
+            1 + MAX_PARTICLE_EMISSIONS * PARTICLE_EMISSION_STRIDE,
+        )?;
+
+        let bitmap = empty_u32(comp_queue.device().clone(), bitmap_size().x * bitmap_size().y)?;
+        let explosions = empty_u32(comp_queue.device().clone(), bitmap_size().x * bitmap_size().y)?;
         let tmp_matter = empty_u32(
             comp_queue.device().clone(),
-            (*SIM_CANVAS_SIZE * *SIM_CANVAS_SIZE) as usize,
+            (SIM_CANVAS_SIZE.x * SIM_CANVAS_SIZE.y) as usize,
         )?;
+        let bugs = empty_u32(comp_queue.device().clone(), MAX_BUGS * BUG_STRIDE)?;
+        let liquid_pressure = empty_f32(
+            comp_queue.device().clone(),
+            (SIM_CANVAS_SIZE.x * SIM_CANVAS_SIZE.y) as usize,
+        )?;
+        let liquid_velocity = empty_f32(
+            comp_queue.device().clone(),
+            (SIM_CANVAS_SIZE.x * SIM_CANVAS_SIZE.y) as usize,
+        )?;
+        let paint_strokes =
+            empty_u32(comp_queue.device().clone(), MAX_PAINT_STROKES * STROKE_STRIDE)?;
         let spec_const = init_cs::SpecializationConstants {
             empty,
-            sim_canvas_size: *SIM_CANVAS_SIZE as i32,
+            sim_canvas_width: SIM_CANVAS_SIZE.x as i32,
             bitmap_ratio: *BITMAP_RATIO as i32,
             state_empty: MatterState::Empty as u32,
             state_powder: MatterState::Powder as u32,
@@ -115,28 +254,9 @@ impl CASimulator {
             state_object: MatterState::Object as u32,
             constant_11: KERNEL_SIZE,
             constant_12: KERNEL_SIZE,
+            sim_canvas_height: SIM_CANVAS_SIZE.y as i32,
         };
 
-        fn storage_buffer_desc() -> DescriptorDesc {
-            DescriptorDesc {
-                ty: DescriptorType::StorageBuffer,
-                descriptor_count: 1,
-                variable_count: false,
-                stages: ShaderStages::all(),
-                immutable_samplers: Vec::new(),
-            }
-        }
-
-        fn image_desc_set() -> DescriptorDesc {
-            DescriptorDesc {
-                ty: DescriptorType::StorageImage,
-                descriptor_count: 1,
-                variable_count: false,
-                stages: ShaderStages::all(),
-                immutable_samplers: Vec::new(),
-            }
-        }
-
         let sim_pc_requirements = {
             let shader = fall_empty_cs::load(comp_queue.device().clone())?;
             shader
@@ -145,38 +265,8 @@ impl CASimulator {
                 .push_constant_requirements()
                 .cloned()
         };
-        // See compute_shaders/simulation/includes.glsl for layout
-        let sim_set_layout = DescriptorSetLayout::new(comp_queue.device().clone(), [
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(image_desc_set()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(image_desc_set()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(image_desc_set()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(image_desc_set()),
-        ])?;
+        let sim_set_layout =
+            DescriptorSetLayout::new(comp_queue.device().clone(), sim_set_descs())?;
         let sim_pipeline_layout = PipelineLayout::new(
             comp_queue.device().clone(),
             [sim_set_layout],
@@ -192,25 +282,8 @@ impl CASimulator {
                 .cloned()
         };
 
-        // See compute_shaders/utils/includes.glsl for layout
-        let utils_set_layout = DescriptorSetLayout::new(comp_queue.device().clone(), [
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-            Some(storage_buffer_desc()),
-        ])?;
+        let utils_set_layout =
+            DescriptorSetLayout::new(comp_queue.device().clone(), utils_set_descs())?;
 
         let utils_pipeline_layout = PipelineLayout::new(
             comp_queue.device().clone(),
@@ -218,6 +291,50 @@ impl CASimulator {
             utils_pc_requirements,
         )?;
 
+        let bugs_shader = bugs_cs::load(comp_queue.device().clone())?;
+        let bugs_pc_requirements = bugs_shader
+            .entry_point("main")
+            .unwrap()
+            .push_constant_requirements()
+            .cloned();
+        let bugs_set_layout =
+            DescriptorSetLayout::new(comp_queue.device().clone(), bugs_set_descs())?;
+        let bugs_pipeline_layout = PipelineLayout::new(
+            comp_queue.device().clone(),
+            [bugs_set_layout],
+            bugs_pc_requirements,
+        )?;
+
+        let liquid_pc_requirements = {
+            let shader = pressure_cs::load(comp_queue.device().clone())?;
+            shader
+                .entry_point("main")
+                .unwrap()
+                .push_constant_requirements()
+                .cloned()
+        };
+        let liquid_set_layout =
+            DescriptorSetLayout::new(comp_queue.device().clone(), liquid_set_descs())?;
+        let liquid_pipeline_layout = PipelineLayout::new(
+            comp_queue.device().clone(),
+            [liquid_set_layout],
+            liquid_pc_requirements,
+        )?;
+
+        let paint_shader = paint_cs::load(comp_queue.device().clone())?;
+        let paint_pc_requirements = paint_shader
+            .entry_point("main")
+            .unwrap()
+            .push_constant_requirements()
+            .cloned();
+        let paint_set_layout =
+            DescriptorSetLayout::new(comp_queue.device().clone(), paint_set_descs())?;
+        let paint_pipeline_layout = PipelineLayout::new(
+            comp_queue.device().clone(),
+            [paint_set_layout],
+            paint_pc_requirements,
+        )?;
+
         let fall_empty_pipeline = {
             let shader = fall_empty_cs::load(comp_queue.device().clone())?;
             ComputePipeline::with_pipeline_layout(
@@ -308,6 +425,16 @@ impl CASimulator {
                 None,
             )?
         };
+        let decay_pipeline = {
+            let shader = decay_cs::load(comp_queue.device().clone())?;
+            ComputePipeline::with_pipeline_layout(
+                comp_queue.device().clone(),
+                shader.entry_point("main").unwrap(),
+                &spec_const,
+                sim_pipeline_layout.clone(),
+                None,
+            )?
+        };
         let color_pipeline = {
             let shader = color_cs::load(comp_queue.device().clone())?;
             ComputePipeline::with_pipeline_layout(
@@ -318,6 +445,60 @@ impl CASimulator {
                 None,
             )?
         };
+        let bugs_pipeline = ComputePipeline::with_pipeline_layout(
+            comp_queue.device().clone(),
+            bugs_shader.entry_point("main").unwrap(),
+            &spec_const,
+            bugs_pipeline_layout,
+            None,
+        )?;
+        let paint_pipeline = ComputePipeline::with_pipeline_layout(
+            comp_queue.device().clone(),
+            paint_shader.entry_point("main").unwrap(),
+            &spec_const,
+            paint_pipeline_layout,
+            None,
+        )?;
+        let pressure_pipeline = {
+            let shader = pressure_cs::load(comp_queue.device().clone())?;
+            ComputePipeline::with_pipeline_layout(
+                comp_queue.device().clone(),
+                shader.entry_point("main").unwrap(),
+                &spec_const,
+                liquid_pipeline_layout.clone(),
+                None,
+            )?
+        };
+        let liquid_velocity_pipeline = {
+            let shader = liquid_velocity_cs::load(comp_queue.device().clone())?;
+            ComputePipeline::with_pipeline_layout(
+                comp_queue.device().clone(),
+                shader.entry_point("main").unwrap(),
+                &spec_const,
+                liquid_pipeline_layout.clone(),
+                None,
+            )?
+        };
+        let liquid_rise_pipeline = {
+            let shader = liquid_rise_cs::load(comp_queue.device().clone())?;
+            ComputePipeline::with_pipeline_layout(
+                comp_queue.device().clone(),
+                shader.entry_point("main").unwrap(),
+                &spec_const,
+                liquid_pipeline_layout.clone(),
+                None,
+            )?
+        };
+        let liquid_flow_pipeline = {
+            let shader = liquid_flow_cs::load(comp_queue.device().clone())?;
+            ComputePipeline::with_pipeline_layout(
+                comp_queue.device().clone(),
+                shader.entry_point("main").unwrap(),
+                &spec_const,
+                liquid_pipeline_layout,
+                None,
+            )?
+        };
         let init_pipeline = {
             let shader = init_cs::load(comp_queue.device().clone())?;
             ComputePipeline::with_pipeline_layout(
@@ -349,6 +530,7 @@ impl CASimulator {
             )?
         };
 
+        let gpu_timings = GpuTimings::new(&comp_queue)?;
         Ok(CASimulator {
             comp_queue,
             fall_empty_pipeline,
@@ -360,7 +542,14 @@ impl CASimulator {
             horizontal_empty_pipeline,
             horizontal_swap_pipeline,
             react_pipeline,
+            decay_pipeline,
             color_pipeline,
+            bugs_pipeline,
+            paint_pipeline,
+            pressure_pipeline,
+            liquid_velocity_pipeline,
+            liquid_rise_pipeline,
+            liquid_flow_pipeline,
 
             init_pipeline,
             update_bitmap_pipeline,
@@ -375,24 +564,45 @@ impl CASimulator {
             matter_reaction_direction_input,
             matter_reaction_probability_input,
             matter_reaction_transition_input,
+            matter_decay_input,
+            matter_gravity_input,
+            reaction_counts,
+            tile_activity,
+            particle_emissions,
+            region_of_interest,
 
             bitmap,
+            explosions,
 
             tmp_matter,
+            bugs,
+            liquid_pressure,
+            liquid_velocity,
+            paint_strokes,
+            queued_strokes: vec![],
             sim_steps: 0,
             dispersion_step: 0,
             dispersion_dir: 0,
             move_step: 0,
             sim_pos_offset: Vector2::new(0, 0),
-            seed: 0.0,
-            start: Instant::now(),
+            portals: [[0; 4]; MAX_PORTALS],
+            portal_widths: [0; MAX_PORTALS],
+            chunk_seeds: [0.0; 4],
+            next_step_seeds: None,
+            roi_origin: Vector2::new(0, 0),
+            count_reactions: false,
+            world_seed: rand::thread_rng().gen(),
+            compute_future: None,
+            gpu_timings,
         })
     }
 
     pub(crate) fn update_matter_data(
         &mut self,
         matter_definitions: &MatterDefinitions,
+        protected_matters: &BTreeSet<u32>,
     ) -> Result<()> {
+        self.wait_compute()?;
         let mut write_matter_color_input = self.matter_color_input.write()?;
         let mut write_matter_state_input = self.matter_state_input.write()?;
         let mut write_matter_weight_input = self.matter_weight_input.write()?;
@@ -405,6 +615,8 @@ impl CASimulator {
             self.matter_reaction_probability_input.write()?;
         let mut write_matter_reaction_transition_input =
             self.matter_reaction_transition_input.write()?;
+        let mut write_matter_decay_input = self.matter_decay_input.write()?;
+        let mut write_matter_gravity_input = self.matter_gravity_input.write()?;
         let zero = MatterDefinition::zero();
         for i in 0..MAX_NUM_MATTERS as usize {
             let matter = if i < matter_definitions.definitions.len() {
@@ -412,71 +624,274 @@ impl CASimulator {
             } else {
                 &zero
             };
-            write_matter_color_input[i] = u32_rgba_to_u32_abgr(matter.color);
+            write_matter_color_input[i] = Color32::from_u32_rgba(matter.color).to_u32_abgr();
             write_matter_state_input[i] = matter.state as u32;
             write_matter_weight_input[i] = matter.weight;
             write_matter_dispersion_input[i] = matter.dispersion;
-            write_matter_characteristics_input[i] = matter.characteristics.bits();
+            write_matter_characteristics_input[i] = matter.characteristics.bits()
+                | if protected_matters.contains(&(i as u32)) {
+                    MATTER_PROTECTED_BIT
+                } else {
+                    0
+                };
             let table_index = i * MAX_TRANSITIONS as usize;
             for j in 0..(MAX_TRANSITIONS as usize) {
                 write_matter_reaction_with_input[table_index + j] =
                     matter.reactions[j].reacts.bits();
-                write_matter_reaction_direction_input[table_index + j] =
-                    matter.reactions[j].direction.bits();
+                let tick_divider =
+                    matter.reactions[j].tick_divider.clamp(1, MAX_REACTION_TICK_DIVIDER);
+                write_matter_reaction_direction_input[table_index + j] = matter.reactions[j]
+                    .direction
+                    .bits()
+                    | (tick_divider << REACTION_TICK_DIVIDER_SHIFT);
                 write_matter_reaction_probability_input[table_index + j] =
                     matter.reactions[j].probability;
                 write_matter_reaction_transition_input[table_index + j] =
                     matter.reactions[j].becomes;
             }
+            let decay_index = i * MATTER_DECAY_STRIDE;
+            write_matter_decay_input[decay_index] = matter.decay.probability.to_bits();
+            write_matter_decay_input[decay_index + 1] = matter.decay.becomes;
+            write_matter_decay_input[decay_index + 2] = matter.decay.emits;
+            write_matter_gravity_input[i] = matter.gravity;
         }
         Ok(())
     }
 
+    /// Diff gpu bitmap against boundary bitmaps in parallel over bands of boundary regions,
+    /// raising changed flags for each region that differs
     pub fn update_bitmaps(
         &self,
         solid_bitmap: &mut [f64],
         powder_bitmap: &mut [f64],
         liquid_bitmap: &mut [f64],
-        solids_changed: &mut bool,
-        powders_changed: &mut bool,
-        liquids_changed: &mut bool,
+        solid_regions_changed: &mut [bool],
+        powder_regions_changed: &mut [bool],
+        liquid_regions_changed: &mut [bool],
     ) -> Result<()> {
+        self.wait_compute()?;
         let gpu_bitmap = self.bitmap.read()?;
-        for i in 0..gpu_bitmap.len() {
-            let gpu_val = gpu_bitmap[i];
-            let old_solid = solid_bitmap[i];
-            let old_powder = powder_bitmap[i];
-            let old_liquid = liquid_bitmap[i];
-
-            let new_solid = (gpu_val & (1 << 0)) as f64;
-            let new_powder = (gpu_val & (1 << 1)) as f64;
-            let new_liquid = (gpu_val & (1 << 2)) as f64;
-
-            solid_bitmap[i] = new_solid;
-            powder_bitmap[i] = new_powder;
-            liquid_bitmap[i] = new_liquid;
-
-            if !*solids_changed {
-                *solids_changed = old_solid != new_solid;
-            }
-            if !*powders_changed {
-                *powders_changed = old_powder != new_powder;
-            }
-            if !*liquids_changed {
-                *liquids_changed = old_liquid != new_liquid;
-            }
+        let bitmap_width = bitmap_size().x;
+        let regions_per_row = boundary_regions().x;
+        let band_len = BOUNDARY_REGION_SIZE * bitmap_width;
+        gpu_bitmap
+            .par_chunks(band_len)
+            .zip(solid_bitmap.par_chunks_mut(band_len))
+            .zip(powder_bitmap.par_chunks_mut(band_len))
+            .zip(liquid_bitmap.par_chunks_mut(band_len))
+            .zip(solid_regions_changed.par_chunks_mut(regions_per_row))
+            .zip(powder_regions_changed.par_chunks_mut(regions_per_row))
+            .zip(liquid_regions_changed.par_chunks_mut(regions_per_row))
+            .for_each(
+                |((((((gpu_band, solid), powder), liquid), solid_r), powder_r), liquid_r)| {
+                    diff_bitmap_band(gpu_band, bitmap_width, [solid, powder, liquid], [
+                        solid_r, powder_r, liquid_r,
+                    ])
+                },
+            );
+        Ok(())
+    }
+
+    /// Collect bitmap blocks that had exploding matter in the latest step as (bitmap index,
+    /// exploding matter)
+    pub fn read_explosions(&self, empty: u32, explosions: &mut Vec<(usize, u32)>) -> Result<()> {
+        self.wait_compute()?;
+        explosions.clear();
+        explosions.extend(
+            self.explosions
+                .read()?
+                .iter()
+                .enumerate()
+                .filter(|(_, matter)| **matter != empty)
+                .map(|(index, matter)| (index, *matter)),
+        );
+        Ok(())
+    }
+
+    /// Add reaction counts since last call to `counts`, indexed by matter *
+    /// `REACTION_COUNT_STRIDE` + reaction, and reset them on gpu
+    pub fn take_reaction_counts(&self, counts: &mut [u64]) -> Result<()> {
+        self.wait_compute()?;
+        for (count, gpu_count) in counts.iter_mut().zip(self.reaction_counts.write()?.iter_mut()) {
+            *count += *gpu_count as u64;
+            *gpu_count = 0;
         }
+        Ok(())
+    }
 
+    /// Replace `emissions` with reactions emitted for particle effects since last call (see
+    /// `PARTICLE_EMISSION_STRIDE`) and reset them on gpu
+    pub fn take_particle_emissions(&self, emissions: &mut Vec<u32>) -> Result<()> {
+        self.wait_compute()?;
+        let mut buffer = self.particle_emissions.write()?;
+        let count = (buffer[0] as usize).min(MAX_PARTICLE_EMISSIONS);
+        emissions.clear();
+        emissions.extend_from_slice(&buffer[1..1 + count * PARTICLE_EMISSION_STRIDE]);
+        buffer[0] = 0;
         Ok(())
     }
 
+    /// Spawn bugs at canvas positions into free slots. Returns how many fit
+    pub fn spawn_bugs(&mut self, positions: &[Vector2<i32>]) -> Result<usize> {
+        self.wait_compute()?;
+        let mut bugs = self.bugs.write()?;
+        let mut spawned = 0;
+        for (bug, pos) in bugs
+            .chunks_mut(BUG_STRIDE)
+            .filter(|bug| bug[2] == BugState::Dead as u32)
+            .zip(positions.iter())
+        {
+            bug[0] = pos.x as u32;
+            bug[1] = pos.y as u32;
+            bug[2] = if spawned % 2 == 0 {
+                BugState::Right as u32
+            } else {
+                BugState::Left as u32
+            };
+            spawned += 1;
+        }
+        Ok(spawned)
+    }
+
+    pub fn num_bugs(&self) -> Result<usize> {
+        self.wait_compute()?;
+        Ok(self
+            .bugs
+            .read()?
+            .chunks(BUG_STRIDE)
+            .filter(|bug| bug[2] != BugState::Dead as u32)
+            .count())
+    }
+
+    /// Raw bug buffer, `BUG_STRIDE` values per bug
+    pub fn read_bugs(&self) -> Result<Vec<u32>> {
+        self.wait_compute()?;
+        Ok(self.bugs.read()?.to_vec())
+    }
+
+    /// Replace bugs with ones from `read_bugs`
+    pub fn write_bugs(&mut self, bugs: &[u32]) -> Result<()> {
+        self.wait_compute()?;
+        let mut buffer = self.bugs.write()?;
+        ensure!(bugs.len() == buffer.len(), "Expected {} bug values", buffer.len());
+        buffer.copy_from_slice(bugs);
+        Ok(())
+    }
+
+    /// Paint stroke on gpu at the start of next step. Cpu cost doesn't depend on stroke size
+    pub fn queue_paint(&mut self, stroke: PaintStroke) {
+        self.queued_strokes.push(stroke);
+    }
+
+    pub fn clear_bugs(&mut self) -> Result<()> {
+        self.wait_compute()?;
+        self.bugs.write()?.iter_mut().for_each(|b| *b = 0);
+        Ok(())
+    }
+
+    /// Future of the latest submitted step. Join this with gpu work that uses the chunks (e.g.
+    /// rendering) instead of waiting for it on cpu
+    pub fn compute_future(&self) -> Option<Arc<FenceSignalFuture<Box<dyn GpuFuture>>>> {
+        self.compute_future.clone()
+    }
+
+    /// Block until the latest step has finished on gpu. Chunk buffers can't be accessed on cpu
+    /// before that
+    pub fn wait_compute(&self) -> Result<()> {
+        if let Some(future) = &self.compute_future {
+            future.wait(None)?;
+        }
+        Ok(())
+    }
+
+    /// Wait for the latest step and forget it. Returns whether there was a step whose results
+    /// have not yet been read back
+    pub fn finish_compute(&mut self) -> Result<bool> {
+        self.wait_compute()?;
+        self.read_gpu_timings()?;
+        Ok(self.compute_future.take().is_some())
+    }
+
+    /// Gpu time per pass of steps, None if compute queue doesn't support timestamps
+    pub fn gpu_timings(&self) -> Option<&GpuTimings> {
+        self.gpu_timings.as_ref()
+    }
+
+    fn read_gpu_timings(&mut self) -> Result<()> {
+        if let Some(gpu_timings) = &mut self.gpu_timings {
+            gpu_timings.read()?;
+        }
+        Ok(())
+    }
+
+    /// Time passes dispatched since the previous pass ended
+    fn end_gpu_pass(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        pass: GpuPass,
+    ) -> Result<()> {
+        if let Some(gpu_timings) = &mut self.gpu_timings {
+            gpu_timings.end_pass(builder, pass)?;
+        }
+        Ok(())
+    }
+
+    pub fn world_seed(&self) -> u64 {
+        self.world_seed
+    }
+
+    /// Seeds of the compute chunks in the latest step
+    pub fn chunk_seeds(&self) -> [f32; 4] {
+        self.chunk_seeds
+    }
+
+    pub fn set_next_step_seeds(&mut self, seeds: [f32; 4]) {
+        self.next_step_seeds = Some(seeds);
+    }
+
+    /// Seed randomness of the simulation. Steps are counted from here, so a world replays the
+    /// same way from when its seed is set
+    pub fn set_world_seed(&mut self, world_seed: u64) {
+        self.world_seed = world_seed;
+        self.sim_steps = 0;
+    }
+
+    /// Step CA simulation. With `region_of_interest` setting, only tiles overlapping `focus`
+    /// canvas rectangles (min, max) & tiles whose matter keeps changing are simulated
     pub fn step(
         &mut self,
         settings: AppSettings,
         sim_pos_offset: Vector2<i32>,
         chunk_manager: &mut SimulationChunkManager,
+        portals: &Portals,
+        focus: &[(Vector2<i32>, Vector2<i32>)],
     ) -> Result<()> {
-        self.seed = (Instant::now() - self.start).as_secs_f32();
+        self.wait_compute()?;
+        self.read_gpu_timings()?;
+        self.roi_origin = RegionOfInterest::origin(sim_pos_offset);
+        if settings.region_of_interest {
+            self.region_of_interest.update(
+                &mut self.tile_activity.write()?,
+                self.roi_origin,
+                focus,
+            );
+        } else {
+            self.region_of_interest
+                .clear(&mut self.tile_activity.write()?);
+        }
+        for (seed, chunk_pos) in self
+            .chunk_seeds
+            .iter_mut()
+            .zip(chunk_manager.interaction_chunks.iter())
+        {
+            *seed = chunk_step_seed(self.world_seed, *chunk_pos, self.sim_steps);
+        }
+        if let Some(seeds) = self.next_step_seeds.take() {
+            self.chunk_seeds = seeds;
+        }
+        let (portal_data, portal_widths) = portals.shader_data(sim_pos_offset);
+        self.portals = portal_data;
+        self.portal_widths = portal_widths;
         // Get chunks for compute
         let mut world_chunks = chunk_manager.get_chunks_for_compute();
         // Run ca simulation
@@ -487,30 +902,47 @@ impl CASimulator {
             CommandBufferUsage::OneTimeSubmit,
         )?;
 
+        if let Some(gpu_timings) = &mut self.gpu_timings {
+            gpu_timings.begin(&mut builder)?;
+        }
+
+        // Paint queued strokes, before anything reads the grids
+        self.dispatch_paint(&mut builder, &mut world_chunks)?;
+        self.end_gpu_pass(&mut builder, GpuPass::Paint)?;
+
         // Inits
         self.dispatch_utility(&mut builder, self.init_pipeline.clone(), &mut world_chunks)?;
+        self.end_gpu_pass(&mut builder, GpuPass::Init)?;
 
         // Movement
         // ------
         self.move_once(&mut builder, 0, &mut world_chunks)?;
+        self.end_gpu_pass(&mut builder, GpuPass::Move)?;
         self.disperse(
             &mut builder,
             (self.sim_steps % 2 == 0) as u32,
             &mut world_chunks,
             settings.dispersion_steps,
         )?;
+        self.end_gpu_pass(&mut builder, GpuPass::Disperse)?;
         if settings.movement_steps > 1 {
             self.move_once(&mut builder, 1, &mut world_chunks)?;
         }
         if settings.movement_steps > 2 {
             self.move_once(&mut builder, 2, &mut world_chunks)?;
         }
+        self.end_gpu_pass(&mut builder, GpuPass::Move)?;
         self.disperse(
             &mut builder,
             (self.sim_steps % 2 != 0) as u32,
             &mut world_chunks,
             settings.dispersion_steps,
         )?;
+        self.end_gpu_pass(&mut builder, GpuPass::Disperse)?;
+        if settings.liquid_model == LiquidModel::Pressure {
+            self.move_by_pressure(&mut builder, &mut world_chunks)?;
+            self.end_gpu_pass(&mut builder, GpuPass::Pressure)?;
+        }
         // ------
 
         // React
@@ -520,6 +952,17 @@ impl CASimulator {
             &mut world_chunks,
             true,
         )?;
+        self.end_gpu_pass(&mut builder, GpuPass::React)?;
+        // Decay is slow, thus run less often
+        if self.sim_steps % DECAY_INTERVAL == 0 {
+            self.dispatch(
+                &mut builder,
+                self.decay_pipeline.clone(),
+                &mut world_chunks,
+                true,
+            )?;
+            self.end_gpu_pass(&mut builder, GpuPass::Decay)?;
+        }
 
         // Finish
         self.dispatch_utility(
@@ -532,16 +975,21 @@ impl CASimulator {
             self.update_bitmap_pipeline.clone(),
             &mut world_chunks,
         )?;
+        self.end_gpu_pass(&mut builder, GpuPass::Finish)?;
         self.dispatch(
             &mut builder,
             self.color_pipeline.clone(),
             &mut world_chunks,
             false,
         )?;
+        self.end_gpu_pass(&mut builder, GpuPass::Color)?;
+        // Bugs are drawn over matter colors
+        self.dispatch_bugs(&mut builder, &mut world_chunks)?;
+        self.end_gpu_pass(&mut builder, GpuPass::Bugs)?;
 
         let command_buffer = builder.build()?;
         let finished = command_buffer.execute(self.comp_queue.clone())?;
-        let _fut = finished.then_signal_fence_and_flush()?;
+        self.compute_future = Some(Arc::new(finished.boxed().then_signal_fence_and_flush()?));
         self.sim_steps += 1;
 
         // Step flips matter grids, thus update mutated matter grids back to chunk manager after
@@ -614,6 +1062,26 @@ impl CASimulator {
         Ok(())
     }
 
+    /// Solve liquid pressure & velocity, then rise & flow liquids by them
+    fn move_by_pressure(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        world_chunks: &mut (Vector2<i32>, Vec<GpuChunk>),
+    ) -> Result<()> {
+        for _ in 0..PRESSURE_ITERATIONS {
+            self.dispatch_liquid(builder, self.pressure_pipeline.clone(), world_chunks, false)?;
+        }
+        self.dispatch_liquid(
+            builder,
+            self.liquid_velocity_pipeline.clone(),
+            world_chunks,
+            false,
+        )?;
+        self.dispatch_liquid(builder, self.liquid_rise_pipeline.clone(), world_chunks, true)?;
+        self.dispatch_liquid(builder, self.liquid_flow_pipeline.clone(), world_chunks, true)?;
+        Ok(())
+    }
+
     fn dispatch(
         &mut self,
         builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
@@ -655,27 +1123,35 @@ impl CASimulator {
             WriteDescriptorSet::buffer(26, chunks[3].objects_matter.clone()),
             WriteDescriptorSet::buffer(27, chunks[3].objects_color.clone()),
             WriteDescriptorSet::image_view(28, chunks[3].image.clone()),
+            WriteDescriptorSet::buffer(29, self.matter_decay_input.clone()),
+            WriteDescriptorSet::buffer(30, self.matter_gravity_input.clone()),
+            WriteDescriptorSet::buffer(31, self.reaction_counts.clone()),
+            WriteDescriptorSet::buffer(32, self.tile_activity.clone()),
+            WriteDescriptorSet::buffer(33, self.particle_emissions.clone()),
         ])?;
 
         // Note that we make an assumption here that PCs are same for all our simulation kernel (see `shared.glsl`)
         // hence react_cs::...
         let push_constants = react_cs::ty::PushConstants {
-            seed: self.seed,
+            chunk_seeds: self.chunk_seeds,
             sim_step: self.sim_steps as u32,
             move_step: self.move_step,
             dispersion_step: self.dispersion_step,
             dispersion_dir: self.dispersion_dir,
             sim_pos_offset: self.sim_pos_offset.into(),
             sim_chunk_start_offset: (*chunk_start).into(),
-            _dummy0: [0; 4],
+            portals: self.portals,
+            portal_widths: self.portal_widths,
+            roi_origin: self.roi_origin.into(),
+            count_reactions: self.count_reactions as u32,
         };
         builder
             .bind_pipeline_compute(pipeline.clone())
             .bind_descriptor_sets(PipelineBindPoint::Compute, pipeline_layout.clone(), 0, set)
             .push_constants(pipeline_layout.clone(), 0, push_constants)
             .dispatch([
-                *SIM_CANVAS_SIZE / KERNEL_SIZE,
-                *SIM_CANVAS_SIZE / KERNEL_SIZE,
+                SIM_CANVAS_SIZE.x / KERNEL_SIZE,
+                SIM_CANVAS_SIZE.y / KERNEL_SIZE,
                 1,
             ])?;
         if swap {
@@ -718,6 +1194,8 @@ impl CASimulator {
             WriteDescriptorSet::buffer(13, chunks[3].matter_out.clone()),
             WriteDescriptorSet::buffer(14, chunks[3].objects_matter.clone()),
             WriteDescriptorSet::buffer(15, self.tmp_matter.clone()),
+            WriteDescriptorSet::buffer(16, self.explosions.clone()),
+            WriteDescriptorSet::buffer(17, self.matter_characteristics_input.clone()),
         ])?;
 
         // Note that we make an assumption here that PCs are same for all our simulation kernel (see `shared.glsl`)
@@ -730,13 +1208,338 @@ impl CASimulator {
             .bind_descriptor_sets(PipelineBindPoint::Compute, pipeline_layout.clone(), 0, set)
             .push_constants(pipeline_layout.clone(), 0, push_constants)
             .dispatch([
-                *SIM_CANVAS_SIZE / KERNEL_SIZE,
-                *SIM_CANVAS_SIZE / KERNEL_SIZE,
+                SIM_CANVAS_SIZE.x / KERNEL_SIZE,
+                SIM_CANVAS_SIZE.y / KERNEL_SIZE,
                 1,
             ])?;
 
         Ok(())
     }
+
+    /// Pressure liquid kernels need their own field inputs, see `LiquidModel::Pressure`
+    fn dispatch_liquid(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        pipeline: Arc<ComputePipeline>,
+        world_chunks: &mut (Vector2<i32>, Vec<GpuChunk>),
+        swap: bool,
+    ) -> Result<()> {
+        let pipeline_layout = pipeline.layout();
+        let desc_layout = pipeline_layout.descriptor_set_layouts().get(0).unwrap();
+        let (chunk_start, chunks) = world_chunks;
+
+        let set = PersistentDescriptorSet::new(desc_layout.clone(), [
+            WriteDescriptorSet::buffer(0, self.matter_state_input.clone()),
+            WriteDescriptorSet::buffer(1, chunks[0].matter_in.clone()),
+            WriteDescriptorSet::buffer(2, chunks[0].matter_out.clone()),
+            WriteDescriptorSet::buffer(3, chunks[0].objects_matter.clone()),
+            WriteDescriptorSet::buffer(4, chunks[1].matter_in.clone()),
+            WriteDescriptorSet::buffer(5, chunks[1].matter_out.clone()),
+            WriteDescriptorSet::buffer(6, chunks[1].objects_matter.clone()),
+            WriteDescriptorSet::buffer(7, chunks[2].matter_in.clone()),
+            WriteDescriptorSet::buffer(8, chunks[2].matter_out.clone()),
+            WriteDescriptorSet::buffer(9, chunks[2].objects_matter.clone()),
+            WriteDescriptorSet::buffer(10, chunks[3].matter_in.clone()),
+            WriteDescriptorSet::buffer(11, chunks[3].matter_out.clone()),
+            WriteDescriptorSet::buffer(12, chunks[3].objects_matter.clone()),
+            WriteDescriptorSet::buffer(13, self.tile_activity.clone()),
+            WriteDescriptorSet::buffer(14, self.liquid_pressure.clone()),
+            WriteDescriptorSet::buffer(15, self.liquid_velocity.clone()),
+        ])?;
+
+        let push_constants = pressure_cs::ty::PushConstants {
+            chunk_seeds: self.chunk_seeds,
+            sim_pos_offset: self.sim_pos_offset.into(),
+            sim_chunk_start_offset: (*chunk_start).into(),
+            roi_origin: self.roi_origin.into(),
+        };
+        builder
+            .bind_pipeline_compute(pipeline.clone())
+            .bind_descriptor_sets(PipelineBindPoint::Compute, pipeline_layout.clone(), 0, set)
+            .push_constants(pipeline_layout.clone(), 0, push_constants)
+            .dispatch([
+                SIM_CANVAS_SIZE.x / KERNEL_SIZE,
+                SIM_CANVAS_SIZE.y / KERNEL_SIZE,
+                1,
+            ])?;
+        if swap {
+            for chunk in chunks.iter_mut() {
+                let temp = chunk.matter_out.clone();
+                chunk.matter_out = chunk.matter_in.clone();
+                chunk.matter_in = temp;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write queued strokes for the gpu & paint them. Strokes over `MAX_PAINT_STROKES` wait for
+    /// the next step
+    fn dispatch_paint(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        world_chunks: &mut (Vector2<i32>, Vec<GpuChunk>),
+    ) -> Result<()> {
+        if self.queued_strokes.is_empty() {
+            return Ok(());
+        }
+        let num_strokes = self.queued_strokes.len().min(MAX_PAINT_STROKES);
+        {
+            let mut data = self.paint_strokes.write()?;
+            for (stroke, data) in self
+                .queued_strokes
+                .drain(..num_strokes)
+                .zip(data.chunks_mut(STROKE_STRIDE))
+            {
+                data[0] = stroke.center.x as u32;
+                data[1] = stroke.center.y as u32;
+                data[2] = stroke.radius.to_bits();
+                data[3] = stroke.matter;
+                data[4] = stroke.is_square as u32;
+            }
+        }
+        let pipeline = self.paint_pipeline.clone();
+        let pipeline_layout = pipeline.layout();
+        let desc_layout = pipeline_layout.descriptor_set_layouts().get(0).unwrap();
+        let (chunk_start, chunks) = world_chunks;
+
+        let set = PersistentDescriptorSet::new(desc_layout.clone(), [
+            WriteDescriptorSet::buffer(0, chunks[0].matter_in.clone()),
+            WriteDescriptorSet::buffer(1, chunks[1].matter_in.clone()),
+            WriteDescriptorSet::buffer(2, chunks[2].matter_in.clone()),
+            WriteDescriptorSet::buffer(3, chunks[3].matter_in.clone()),
+            WriteDescriptorSet::buffer(4, self.tile_activity.clone()),
+            WriteDescriptorSet::buffer(5, self.paint_strokes.clone()),
+            WriteDescriptorSet::buffer(6, self.matter_characteristics_input.clone()),
+        ])?;
+
+        let push_constants = paint_cs::ty::PushConstants {
+            sim_pos_offset: self.sim_pos_offset.into(),
+            sim_chunk_start_offset: (*chunk_start).into(),
+            roi_origin: self.roi_origin.into(),
+            num_strokes: num_strokes as u32,
+        };
+        builder
+            .bind_pipeline_compute(pipeline.clone())
+            .bind_descriptor_sets(PipelineBindPoint::Compute, pipeline_layout.clone(), 0, set)
+            .push_constants(pipeline_layout.clone(), 0, push_constants)
+            .dispatch([
+                SIM_CANVAS_SIZE.x / KERNEL_SIZE,
+                SIM_CANVAS_SIZE.y / KERNEL_SIZE,
+                1,
+            ])?;
+
+        Ok(())
+    }
+
+    /// Bugs are updated one per invocation rather than per cell, and need their own inputs
+    fn dispatch_bugs(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        world_chunks: &mut (Vector2<i32>, Vec<GpuChunk>),
+    ) -> Result<()> {
+        let pipeline = self.bugs_pipeline.clone();
+        let pipeline_layout = pipeline.layout();
+        let desc_layout = pipeline_layout.descriptor_set_layouts().get(0).unwrap();
+        let (chunk_start, chunks) = world_chunks;
+
+        let set = PersistentDescriptorSet::new(desc_layout.clone(), [
+            WriteDescriptorSet::buffer(0, self.matter_state_input.clone()),
+            WriteDescriptorSet::buffer(1, self.matter_characteristics_input.clone()),
+            WriteDescriptorSet::buffer(2, chunks[0].matter_in.clone()),
+            WriteDescriptorSet::buffer(3, chunks[0].matter_out.clone()),
+            WriteDescriptorSet::buffer(4, chunks[0].objects_matter.clone()),
+            WriteDescriptorSet::image_view(5, chunks[0].image.clone()),
+            WriteDescriptorSet::buffer(6, chunks[1].matter_in.clone()),
+            WriteDescriptorSet::buffer(7, chunks[1].matter_out.clone()),
+            WriteDescriptorSet::buffer(8, chunks[1].objects_matter.clone()),
+            WriteDescriptorSet::image_view(9, chunks[1].image.clone()),
+            WriteDescriptorSet::buffer(10, chunks[2].matter_in.clone()),
+            WriteDescriptorSet::buffer(11, chunks[2].matter_out.clone()),
+            WriteDescriptorSet::buffer(12, chunks[2].objects_matter.clone()),
+            WriteDescriptorSet::image_view(13, chunks[2].image.clone()),
+            WriteDescriptorSet::buffer(14, chunks[3].matter_in.clone()),
+            WriteDescriptorSet::buffer(15, chunks[3].matter_out.clone()),
+            WriteDescriptorSet::buffer(16, chunks[3].objects_matter.clone()),
+            WriteDescriptorSet::image_view(17, chunks[3].image.clone()),
+            WriteDescriptorSet::buffer(18, self.bugs.clone()),
+        ])?;
+
+        let push_constants = bugs_cs::ty::PushConstants {
+            sim_pos_offset: self.sim_pos_offset.into(),
+            sim_chunk_start_offset: (*chunk_start).into(),
+            chunk_seeds: self.chunk_seeds,
+        };
+        builder
+            .bind_pipeline_compute(pipeline.clone())
+            .bind_descriptor_sets(PipelineBindPoint::Compute, pipeline_layout.clone(), 0, set)
+            .push_constants(pipeline_layout.clone(), 0, push_constants)
+            .dispatch([MAX_BUGS as u32 / (KERNEL_SIZE * KERNEL_SIZE), 1, 1])?;
+
+        Ok(())
+    }
+}
+
+fn storage_buffer_desc() -> DescriptorDesc {
+    DescriptorDesc {
+        ty: DescriptorType::StorageBuffer,
+        descriptor_count: 1,
+        variable_count: false,
+        stages: ShaderStages::all(),
+        immutable_samplers: Vec::new(),
+    }
+}
+
+fn image_desc_set() -> DescriptorDesc {
+    DescriptorDesc {
+        ty: DescriptorType::StorageImage,
+        descriptor_count: 1,
+        variable_count: false,
+        stages: ShaderStages::all(),
+        immutable_samplers: Vec::new(),
+    }
+}
+
+/// Descriptors of simulation kernels, see compute_shaders/simulation/includes.glsl for layout
+fn sim_set_descs() -> [Option<DescriptorDesc>; 34] {
+    [
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(image_desc_set()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(image_desc_set()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(image_desc_set()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(image_desc_set()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+    ]
+}
+
+/// Descriptors of utility kernels, see compute_shaders/utils/includes.glsl for layout
+fn utils_set_descs() -> [Option<DescriptorDesc>; 18] {
+    [
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+    ]
+}
+
+/// Descriptors of bug kernels, see compute_shaders/agents/includes.glsl for layout
+fn bugs_set_descs() -> [Option<DescriptorDesc>; 19] {
+    [
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(image_desc_set()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(image_desc_set()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(image_desc_set()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(image_desc_set()),
+        Some(storage_buffer_desc()),
+    ]
+}
+
+/// Descriptors of liquid kernels, see compute_shaders/liquids/includes.glsl for layout
+fn liquid_set_descs() -> [Option<DescriptorDesc>; 16] {
+    [
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+    ]
+}
+
+/// Descriptors of paint kernel, see compute_shaders/paint/paint.glsl for layout
+fn paint_set_descs() -> [Option<DescriptorDesc>; 7] {
+    [
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+        Some(storage_buffer_desc()),
+    ]
+}
+
+/// Shader seed of a chunk for a sim step. Depends only on the world seed, chunk & step, so chunks
+/// get the same randomness whichever compute chunks they are simulated with
+fn chunk_step_seed(world_seed: u64, chunk_pos: Vector2<i32>, step: usize) -> f32 {
+    let mut hash = world_seed;
+    for value in [chunk_pos.x as u32 as u64, chunk_pos.y as u32 as u64, step as u64] {
+        hash = splitmix64(hash ^ value);
+    }
+    // Shader rand needs seeds away from 0. 24 bits fit in f32 exactly
+    1.0 + (hash >> 40) as f32 / (1 << 14) as f32
+}
+
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
 }
 
 #[allow(deprecated)]
@@ -811,6 +1614,14 @@ mod react_cs {
     }
 }
 
+#[allow(deprecated)]
+mod decay_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "compute_shaders/simulation/decay.glsl",
+    }
+}
+
 #[allow(deprecated)]
 mod color_cs {
     vulkano_shaders::shader! {
@@ -842,3 +1653,111 @@ mod finish_cs {
         path: "compute_shaders/utils/finish.glsl",
     }
 }
+
+#[allow(deprecated)]
+mod bugs_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "compute_shaders/agents/bugs.glsl",
+    }
+}
+
+#[allow(deprecated)]
+mod paint_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "compute_shaders/paint/paint.glsl",
+    }
+}
+
+#[allow(deprecated)]
+mod pressure_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "compute_shaders/liquids/pressure.glsl",
+    }
+}
+
+#[allow(deprecated)]
+mod liquid_velocity_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "compute_shaders/liquids/velocity.glsl",
+    }
+}
+
+#[allow(deprecated)]
+mod liquid_rise_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "compute_shaders/liquids/rise.glsl",
+    }
+}
+
+#[allow(deprecated)]
+mod liquid_flow_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "compute_shaders/liquids/flow.glsl",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_step_seed() {
+        let chunk = Vector2::new(-1, 2);
+        assert_eq!(chunk_step_seed(7, chunk, 10), chunk_step_seed(7, chunk, 10));
+        assert_ne!(chunk_step_seed(7, chunk, 10), chunk_step_seed(8, chunk, 10));
+        assert_ne!(chunk_step_seed(7, chunk, 10), chunk_step_seed(7, chunk, 11));
+        assert_ne!(chunk_step_seed(7, chunk, 10), chunk_step_seed(7, Vector2::new(2, -1), 10));
+        assert!(chunk_step_seed(7, chunk, 10) >= 1.0);
+    }
+
+    #[test]
+    fn test_set_layouts_match_shaders() {
+        let layouts = [
+            (
+                sim_set_descs().to_vec(),
+                "compute_shaders/simulation/includes.glsl",
+            ),
+            (
+                utils_set_descs().to_vec(),
+                "compute_shaders/utils/includes.glsl",
+            ),
+            (
+                bugs_set_descs().to_vec(),
+                "compute_shaders/agents/includes.glsl",
+            ),
+            (
+                liquid_set_descs().to_vec(),
+                "compute_shaders/liquids/includes.glsl",
+            ),
+            (
+                paint_set_descs().to_vec(),
+                "compute_shaders/paint/paint.glsl",
+            ),
+        ];
+        for (descs, path) in layouts {
+            let source = std::fs::read_to_string(path).unwrap();
+            let mut num_bindings = 0;
+            for line in source.lines().filter(|line| line.contains("binding = ")) {
+                let binding = line.split("binding = ").nth(1).unwrap();
+                let end = binding.find(|c: char| !c.is_ascii_digit()).unwrap();
+                let binding = binding[..end].parse::<usize>().unwrap();
+                let desc = descs.get(binding).cloned().flatten();
+                let ty = desc.map(|desc| desc.ty);
+                let expected = if line.contains("image2D") {
+                    DescriptorType::StorageImage
+                } else {
+                    DescriptorType::StorageBuffer
+                };
+                assert_eq!(ty, Some(expected), "{} binding {}", path, binding);
+                num_bindings = num_bindings.max(binding + 1);
+            }
+            assert_eq!(descs.len(), num_bindings, "{}", path);
+        }
+    }
+}
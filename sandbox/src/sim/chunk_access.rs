@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use anyhow::*;
+use cgmath::Vector2;
+use vulkano::buffer::{
+    cpu_access::{ReadLock, WriteLock},
+    CpuAccessibleBuffer,
+};
+
+use crate::sim::{is_inside_sim_canvas, sim_chunk_canvas_index, GpuChunk};
+
+/// Grid of gpu chunks accessed on cpu
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChunkGrid {
+    /// Matter of cells, written by the simulation step
+    Matter,
+    /// Matter of object pixels, written before the step
+    ObjectsMatter,
+    /// Colors of object pixels, written before the step
+    ObjectsColor,
+}
+
+impl ChunkGrid {
+    fn buffer(self, chunk: &GpuChunk) -> &Arc<CpuAccessibleBuffer<[u32]>> {
+        match self {
+            ChunkGrid::Matter => &chunk.matter_in,
+            ChunkGrid::ObjectsMatter => &chunk.objects_matter,
+            ChunkGrid::ObjectsColor => &chunk.objects_color,
+        }
+    }
+}
+
+/// The four simulated chunks borrowed once for a phase of cpu access, e.g. reading matter after
+/// the step. Grids are locked through typed read & write passes, which fail with the grid's name
+/// instead of deadlocking when it's in use. Compute must have finished before borrowing
+pub struct SimChunks {
+    chunk_start: Vector2<i32>,
+    camera_canvas_pos: Vector2<i32>,
+    chunks: Vec<GpuChunk>,
+}
+
+impl SimChunks {
+    pub fn new(
+        chunk_start: Vector2<i32>,
+        camera_canvas_pos: Vector2<i32>,
+        chunks: Vec<GpuChunk>,
+    ) -> SimChunks {
+        debug_assert_eq!(chunks.len(), 4);
+        SimChunks {
+            chunk_start,
+            camera_canvas_pos,
+            chunks,
+        }
+    }
+
+    /// Canvas pos of the corner of simulated chunks
+    pub fn chunk_start(&self) -> Vector2<i32> {
+        self.chunk_start
+    }
+
+    /// Lock `grid` of the four chunks for reading
+    pub fn read(&self, grid: ChunkGrid) -> Result<GridRead<'_>> {
+        let lock = |i: usize| {
+            grid.buffer(&self.chunks[i])
+                .read()
+                .with_context(|| format!("{:?} grid of chunk {} is being written", grid, i))
+        };
+        Ok(GridRead {
+            view: self.view(),
+            grids: [lock(0)?, lock(1)?, lock(2)?, lock(3)?],
+        })
+    }
+
+    /// Lock `grid` of the four chunks for writing
+    pub fn write(&self, grid: ChunkGrid) -> Result<GridWrite<'_>> {
+        let lock = |i: usize| {
+            grid.buffer(&self.chunks[i])
+                .write()
+                .with_context(|| format!("{:?} grid of chunk {} is in use", grid, i))
+        };
+        Ok(GridWrite {
+            view: self.view(),
+            grids: [lock(0)?, lock(1)?, lock(2)?, lock(3)?],
+        })
+    }
+
+    fn view(&self) -> GridView {
+        GridView {
+            chunk_start: self.chunk_start,
+            camera_canvas_pos: self.camera_canvas_pos,
+        }
+    }
+}
+
+/// Maps canvas positions to chunk & grid indices of simulated chunks
+#[derive(Debug, Copy, Clone)]
+struct GridView {
+    chunk_start: Vector2<i32>,
+    camera_canvas_pos: Vector2<i32>,
+}
+
+impl GridView {
+    fn index(&self, canvas_pos: Vector2<i32>) -> Option<(usize, usize)> {
+        is_inside_sim_canvas(canvas_pos, self.camera_canvas_pos)
+            .then(|| sim_chunk_canvas_index(canvas_pos, self.chunk_start))
+    }
+}
+
+/// Read pass over a grid of simulated chunks, see `SimChunks::read`
+pub struct GridRead<'a> {
+    view: GridView,
+    grids: [ReadLock<'a, [u32]>; 4],
+}
+
+impl<'a> GridRead<'a> {
+    /// Value at `canvas_pos`, None outside simulated area
+    pub fn get(&self, canvas_pos: Vector2<i32>) -> Option<u32> {
+        self.view
+            .index(canvas_pos)
+            .map(|(chunk_index, grid_index)| self.grids[chunk_index][grid_index])
+    }
+
+    /// Whole grid of chunk at `chunk_index`, chunks ordered (0, 0), (1, 0), (0, 1), (1, 1)
+    pub fn chunk(&self, chunk_index: usize) -> &[u32] {
+        &self.grids[chunk_index]
+    }
+}
+
+/// Write pass over a grid of simulated chunks, see `SimChunks::write`
+pub struct GridWrite<'a> {
+    view: GridView,
+    grids: [WriteLock<'a, [u32]>; 4],
+}
+
+impl<'a> GridWrite<'a> {
+    /// Value at `canvas_pos`, None outside simulated area
+    pub fn get(&self, canvas_pos: Vector2<i32>) -> Option<u32> {
+        self.view
+            .index(canvas_pos)
+            .map(|(chunk_index, grid_index)| self.grids[chunk_index][grid_index])
+    }
+
+    /// Write `value` at `canvas_pos`, returning the previous value. Positions outside simulated
+    /// area are skipped and return None
+    pub fn set(&mut self, canvas_pos: Vector2<i32>, value: u32) -> Option<u32> {
+        let (chunk_index, grid_index) = self.view.index(canvas_pos)?;
+        let cell = &mut self.grids[chunk_index][grid_index];
+        Some(std::mem::replace(cell, value))
+    }
+
+    /// Whole grid of chunk at `chunk_index`, see `GridRead::chunk`
+    pub fn chunk_mut(&mut self, chunk_index: usize) -> &mut [u32] {
+        &mut self.grids[chunk_index]
+    }
+}
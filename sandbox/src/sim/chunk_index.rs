@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use cgmath::Vector2;
+use hecs::Entity;
+
+use crate::{
+    object::PixelData,
+    sim::{canvas_pos_to_chunk_pos, world_pos_to_canvas_pos},
+};
+
+/// Chunk range, min & max inclusive
+pub type ChunkRange = (Vector2<i32>, Vector2<i32>);
+
+/// Pixel objects by the chunks their bounds overlap, so systems can look only at objects near
+/// the simulated area instead of iterating all of them
+#[derive(Debug, Default)]
+pub struct EntityChunkIndex {
+    chunks: HashMap<Vector2<i32>, Vec<Entity>>,
+    entity_chunks: HashMap<Entity, ChunkRange>,
+}
+
+impl EntityChunkIndex {
+    pub fn new() -> EntityChunkIndex {
+        EntityChunkIndex::default()
+    }
+
+    /// Chunks overlapped by an object at `pos`, whatever its rotation
+    pub fn object_chunk_range(pos: Vector2<f32>, pixel_data: &PixelData) -> ChunkRange {
+        let canvas_pos = world_pos_to_canvas_pos(pos).cast::<i32>().unwrap();
+        let (width, height) = (pixel_data.width as f32, pixel_data.height as f32);
+        // One extra cell for rounding of pixel positions
+        let half_diagonal = (width * width + height * height).sqrt() as i32 / 2 + 1;
+        let half_size = Vector2::new(half_diagonal, half_diagonal);
+        (
+            canvas_pos_to_chunk_pos(canvas_pos - half_size),
+            canvas_pos_to_chunk_pos(canvas_pos + half_size),
+        )
+    }
+
+    /// Put entity in chunks of `range`. Nothing is done unless it moved to other chunks
+    pub fn update(&mut self, entity: Entity, range: ChunkRange) {
+        if self.entity_chunks.get(&entity) == Some(&range) {
+            return;
+        }
+        self.remove(entity);
+        for chunk_pos in chunks_in_range(range) {
+            self.chunks.entry(chunk_pos).or_default().push(entity);
+        }
+        self.entity_chunks.insert(entity, range);
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        if let Some(range) = self.entity_chunks.remove(&entity) {
+            for chunk_pos in chunks_in_range(range) {
+                if let Some(entities) = self.chunks.get_mut(&chunk_pos) {
+                    entities.retain(|e| *e != entity);
+                    if entities.is_empty() {
+                        self.chunks.remove(&chunk_pos);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Remove entities that no longer exist
+    pub fn retain(&mut self, mut exists: impl FnMut(Entity) -> bool) {
+        let removed = self
+            .entity_chunks
+            .keys()
+            .filter(|e| !exists(**e))
+            .copied()
+            .collect::<Vec<Entity>>();
+        for entity in removed {
+            self.remove(entity);
+        }
+    }
+
+    /// Entities overlapping any of the chunks, each once
+    pub fn entities_in_chunks(&self, chunk_positions: &[Vector2<i32>]) -> Vec<Entity> {
+        let mut entities = chunk_positions
+            .iter()
+            .filter_map(|chunk_pos| self.chunks.get(chunk_pos))
+            .flatten()
+            .copied()
+            .collect::<Vec<Entity>>();
+        entities.sort_unstable();
+        entities.dedup();
+        entities
+    }
+}
+
+fn chunks_in_range((min, max): ChunkRange) -> impl Iterator<Item = Vector2<i32>> {
+    (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| Vector2::new(x, y)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entity_chunk_index() {
+        let entity = |i: u64| Entity::from_bits(i + (1 << 32)).unwrap();
+        let mut index = EntityChunkIndex::new();
+        index.update(entity(0), (Vector2::new(0, 0), Vector2::new(0, 0)));
+        // Object over a chunk border is in both
+        index.update(entity(1), (Vector2::new(0, 0), Vector2::new(1, 0)));
+        assert_eq!(index.entities_in_chunks(&[Vector2::new(1, 0)]), vec![entity(1)]);
+        let both = index.entities_in_chunks(&[Vector2::new(0, 0), Vector2::new(1, 0)]);
+        assert_eq!(both, vec![entity(0), entity(1)]);
+        // Moved out of chunk (0, 0)
+        index.update(entity(1), (Vector2::new(1, 0), Vector2::new(1, 0)));
+        assert_eq!(index.entities_in_chunks(&[Vector2::new(0, 0)]), vec![entity(0)]);
+        index.retain(|e| e != entity(0));
+        assert!(index.entities_in_chunks(&[Vector2::new(0, 0)]).is_empty());
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    collections::HashSet,
+    sync::mpsc::{channel, Receiver, Sender},
+};
+
+use anyhow::*;
+use cgmath::Vector2;
+
+use crate::{
+    matter::MatterDefinitions,
+    sim::{chunk_matter_grid, encode_chunk},
+};
+
+/// Finished background conversion of a chunk
+pub enum ChunkJobResult {
+    /// Matter grid of a chunk being loaded, ready to upload to gpu
+    Decoded(Vec<u32>),
+    /// Binary data of an unloaded chunk's matter, see `encode_chunk`
+    Encoded(Vec<u8>),
+}
+
+pub type ChunkJobMessage = (Vector2<i32>, Result<ChunkJobResult>);
+
+/// Decodes chunk data to matter grids & encodes them back on rayon's thread pool, so streaming
+/// chunks doesn't stall the main thread. One job per chunk may be pending at a time
+pub struct ChunkJobs {
+    sender: Sender<ChunkJobMessage>,
+    receiver: Receiver<ChunkJobMessage>,
+    pending: HashSet<Vector2<i32>>,
+}
+
+impl ChunkJobs {
+    pub fn new() -> ChunkJobs {
+        let (sender, receiver) = channel();
+        ChunkJobs {
+            sender,
+            receiver,
+            pending: HashSet::new(),
+        }
+    }
+
+    pub fn is_pending(&self, chunk_pos: Vector2<i32>) -> bool {
+        self.pending.contains(&chunk_pos)
+    }
+
+    /// Start decoding chunk data to a matter grid for upload. No data decodes to empty chunk
+    pub fn decode(
+        &mut self,
+        chunk_pos: Vector2<i32>,
+        data: Option<Vec<u8>>,
+        matter_definitions: MatterDefinitions,
+    ) {
+        self.spawn(chunk_pos, move || {
+            chunk_matter_grid(data.as_deref(), &matter_definitions).map(ChunkJobResult::Decoded)
+        });
+    }
+
+    /// Start encoding an unloaded chunk's matter grid back to its data
+    pub fn encode(&mut self, chunk_pos: Vector2<i32>, matter_grid: Vec<u32>) {
+        self.spawn(chunk_pos, move || Ok(ChunkJobResult::Encoded(encode_chunk(&matter_grid))));
+    }
+
+    fn spawn(
+        &mut self,
+        chunk_pos: Vector2<i32>,
+        job: impl FnOnce() -> Result<ChunkJobResult> + Send + 'static,
+    ) {
+        assert!(
+            self.pending.insert(chunk_pos),
+            "Chunk {:?} already has a pending job",
+            chunk_pos
+        );
+        let sender = self.sender.clone();
+        rayon::spawn(move || {
+            // Receiver is gone only if chunk manager was dropped, then the result isn't needed
+            let _ = sender.send((chunk_pos, job()));
+        });
+    }
+
+    /// Results of jobs finished since last poll, without blocking
+    pub fn poll(&mut self) -> Vec<ChunkJobMessage> {
+        let finished = self.receiver.try_iter().collect::<Vec<_>>();
+        for (chunk_pos, _) in finished.iter() {
+            self.pending.remove(chunk_pos);
+        }
+        finished
+    }
+
+    /// Block until job of `chunk_pos` has finished. Returns its result & others received before
+    pub fn wait_for(&mut self, chunk_pos: Vector2<i32>) -> Vec<ChunkJobMessage> {
+        let mut finished = vec![];
+        while self.is_pending(chunk_pos) {
+            let message = self.receiver.recv().expect("Chunk job sender was dropped");
+            self.pending.remove(&message.0);
+            finished.push(message);
+        }
+        finished
+    }
+
+    /// Block until all pending jobs have finished
+    pub fn wait_all(&mut self) -> Vec<ChunkJobMessage> {
+        let mut finished = vec![];
+        while !self.pending.is_empty() {
+            let message = self.receiver.recv().expect("Chunk job sender was dropped");
+            self.pending.remove(&message.0);
+            finished.push(message);
+        }
+        finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        matter::{default_matter_definitions, MATTER_SAND},
+        CANVAS_CHUNK_SIZE,
+    };
+
+    #[test]
+    fn test_chunk_jobs_round_trip() {
+        let definitions = default_matter_definitions();
+        let size = (CANVAS_CHUNK_SIZE.x * CANVAS_CHUNK_SIZE.y) as usize;
+        let mut grid = vec![definitions.empty; size];
+        grid[3] = MATTER_SAND;
+        let chunk_pos = Vector2::new(1, -2);
+        let mut jobs = ChunkJobs::new();
+        jobs.encode(chunk_pos, grid.clone());
+        assert!(jobs.is_pending(chunk_pos));
+        let data = match jobs.wait_for(chunk_pos).pop() {
+            Some((pos, std::result::Result::Ok(ChunkJobResult::Encoded(data)))) => {
+                assert_eq!(pos, chunk_pos);
+                data
+            }
+            _ => panic!("Expected encoded data"),
+        };
+        jobs.decode(chunk_pos, Some(data), definitions);
+        match jobs.wait_all().pop() {
+            Some((_, std::result::Result::Ok(ChunkJobResult::Decoded(decoded)))) => {
+                assert_eq!(decoded, grid)
+            }
+            _ => panic!("Expected decoded grid"),
+        }
+        assert!(!jobs.is_pending(chunk_pos));
+        jobs.decode(chunk_pos, Some(vec![0; 4]), default_matter_definitions());
+        assert!(jobs.wait_all().pop().unwrap().1.is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::*;
+use cgmath::Vector2;
+use corrode::renderer::{create_device_image_with_usage, DeviceImageView};
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage},
+    descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet},
+    device::Queue,
+    format::Format,
+    image::ImageUsage,
+    pipeline::{ComputePipeline, Pipeline, PipelineBindPoint},
+    sync::GpuFuture,
+};
+
+use crate::{sim::GpuChunk, SIM_CANVAS_SIZE};
+
+/// Must match local size of compute_shaders/render/detail.glsl
+const DETAIL_KERNEL_SIZE: u32 = 8;
+
+/// Upscales chunk color images to 2x resolution with synthesized sub-cell detail, so the canvas
+/// looks less blocky. Visual only, simulation stays at base resolution
+pub struct DetailUpscaler {
+    comp_queue: Arc<Queue>,
+    pipeline: Arc<ComputePipeline>,
+    format: Format,
+    /// Detail images by chunk pos, kept for chunks rendered on latest update
+    images: HashMap<Vector2<i32>, DeviceImageView>,
+}
+
+impl DetailUpscaler {
+    pub fn new(comp_queue: Arc<Queue>, format: Format) -> Result<DetailUpscaler> {
+        let pipeline = {
+            let shader = detail_cs::load(comp_queue.device().clone())?;
+            ComputePipeline::new(
+                comp_queue.device().clone(),
+                shader.entry_point("main").unwrap(),
+                &(),
+                None,
+                |_| {},
+            )?
+        };
+        Ok(DetailUpscaler {
+            comp_queue,
+            pipeline,
+            format,
+            images: HashMap::new(),
+        })
+    }
+
+    /// Detail image of chunk at `chunk_pos`, if it was updated
+    pub fn image(&self, chunk_pos: Vector2<i32>) -> Option<DeviceImageView> {
+        self.images.get(&chunk_pos).cloned()
+    }
+
+    /// Upscale color images of `chunks` after `before_future` (which must include the latest
+    /// CA step). Images of chunks no longer rendered are freed
+    pub fn update<F>(
+        &mut self,
+        chunks: &[(Vector2<i32>, GpuChunk)],
+        before_future: F,
+    ) -> Result<Box<dyn GpuFuture>>
+    where
+        F: GpuFuture + 'static,
+    {
+        self.images
+            .retain(|pos, _| chunks.iter().any(|(chunk_pos, _)| chunk_pos == pos));
+        let mut builder = AutoCommandBufferBuilder::primary(
+            self.comp_queue.device().clone(),
+            self.comp_queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        let pipeline_layout = self.pipeline.layout();
+        let desc_layout = pipeline_layout.descriptor_set_layouts().get(0).unwrap();
+        for (chunk_pos, chunk) in chunks {
+            let detail_image = match self.images.get(chunk_pos) {
+                Some(image) => image.clone(),
+                None => {
+                    let image = create_device_image_with_usage(
+                        self.comp_queue.clone(),
+                        [SIM_CANVAS_SIZE.x * 2, SIM_CANVAS_SIZE.y * 2],
+                        self.format,
+                        ImageUsage {
+                            sampled: true,
+                            storage: true,
+                            ..ImageUsage::none()
+                        },
+                    )?;
+                    self.images.insert(*chunk_pos, image.clone());
+                    image
+                }
+            };
+            let set = PersistentDescriptorSet::new(desc_layout.clone(), [
+                WriteDescriptorSet::image_view(0, chunk.image.clone()),
+                WriteDescriptorSet::image_view(1, detail_image),
+            ])?;
+            builder
+                .bind_pipeline_compute(self.pipeline.clone())
+                .bind_descriptor_sets(PipelineBindPoint::Compute, pipeline_layout.clone(), 0, set)
+                .dispatch([
+                    SIM_CANVAS_SIZE.x * 2 / DETAIL_KERNEL_SIZE,
+                    SIM_CANVAS_SIZE.y * 2 / DETAIL_KERNEL_SIZE,
+                    1,
+                ])?;
+        }
+        let command_buffer = builder.build()?;
+        Ok(before_future
+            .then_execute(self.comp_queue.clone(), command_buffer)?
+            .boxed())
+    }
+
+    /// Free detail images, e.g. when detail is turned off
+    pub fn clear(&mut self) {
+        self.images.clear();
+    }
+}
+
+mod detail_cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "compute_shaders/render/detail.glsl"
+    }
+}
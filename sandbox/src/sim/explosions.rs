@@ -0,0 +1,89 @@
+use cgmath::{InnerSpace, Vector2};
+
+use crate::{sim::bitmap_size, BITMAP_RATIO, HALF_CANVAS};
+
+/// Shockwave of a bitmap block that had exploding matter in it
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Explosion {
+    pub canvas_pos: Vector2<i32>,
+    /// Exploding matter, chain reacting matter is turned into this
+    pub matter: u32,
+}
+
+/// Explosions read back from CA step, applied to objects & the grid on the next step
+pub struct Explosions {
+    pub explosions: Vec<Explosion>,
+    /// (bitmap index, matter) read back from gpu
+    blocks: Vec<(usize, u32)>,
+}
+
+impl Explosions {
+    pub fn new() -> Explosions {
+        Explosions {
+            explosions: vec![],
+            blocks: vec![],
+        }
+    }
+
+    pub fn blocks_mut(&mut self) -> &mut Vec<(usize, u32)> {
+        &mut self.blocks
+    }
+
+    /// Convert read back bitmap blocks to explosions at block centers in canvas coordinates
+    pub fn update_from_blocks(&mut self, camera_canvas_pos: Vector2<i32>) {
+        let bitmap_width = bitmap_size().x;
+        let half_block = *BITMAP_RATIO as i32 / 2;
+        self.explosions.clear();
+        self.explosions.extend(self.blocks.iter().map(|&(index, matter)| {
+            let block = Vector2::new((index % bitmap_width) as i32, (index / bitmap_width) as i32);
+            let sim_pos = block * *BITMAP_RATIO as i32 + Vector2::new(half_block, half_block);
+            Explosion {
+                canvas_pos: sim_pos - *HALF_CANVAS + camera_canvas_pos,
+                matter,
+            }
+        }));
+    }
+
+    pub fn clear(&mut self) {
+        self.explosions.clear();
+        self.blocks.clear();
+    }
+}
+
+/// Impulse an explosion at `explosion_pos` gives to a body at `body_pos`. Falls off linearly to
+/// zero at `radius`. None if the body is outside the shockwave
+pub fn explosion_impulse(
+    explosion_pos: Vector2<f32>,
+    body_pos: Vector2<f32>,
+    radius: f32,
+    impulse: f32,
+) -> Option<Vector2<f32>> {
+    let diff = body_pos - explosion_pos;
+    let distance = diff.magnitude();
+    if distance >= radius {
+        return None;
+    }
+    // Body right at the center is pushed upwards
+    let dir = if distance > f32::EPSILON {
+        diff / distance
+    } else {
+        Vector2::new(0.0, 1.0)
+    };
+    Some(dir * impulse * (1.0 - distance / radius))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explosion_impulse_falls_off() {
+        let center = Vector2::new(1.0, 1.0);
+        let near = explosion_impulse(center, Vector2::new(1.5, 1.0), 2.0, 1.0).unwrap();
+        let far = explosion_impulse(center, Vector2::new(2.5, 1.0), 2.0, 1.0).unwrap();
+        assert!(near.x > far.x && far.x > 0.0);
+        assert_eq!(near.y, 0.0);
+        assert!(explosion_impulse(center, Vector2::new(3.0, 1.0), 2.0, 1.0).is_none());
+        assert_eq!(explosion_impulse(center, center, 2.0, 1.0), Some(Vector2::new(0.0, 1.0)));
+    }
+}
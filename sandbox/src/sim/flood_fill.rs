@@ -0,0 +1,80 @@
+use cgmath::Vector2;
+
+/// Replace the region of `start`'s matter connected to it horizontally & vertically with
+/// `matter`, span by span with an explicit stack so large regions don't overflow the call stack.
+/// `get` returns None outside the filled area. Returns filled cells
+pub fn scanline_flood_fill<G>(
+    grid: &mut G,
+    start: Vector2<i32>,
+    matter: u32,
+    get: impl Fn(&G, Vector2<i32>) -> Option<u32>,
+    mut set: impl FnMut(&mut G, Vector2<i32>, u32),
+) -> Vec<Vector2<i32>> {
+    let target = match get(grid, start) {
+        Some(target) if target != matter => target,
+        _ => return vec![],
+    };
+    let is_target = |grid: &G, x: i32, y: i32| get(grid, Vector2::new(x, y)) == Some(target);
+    let mut filled = vec![];
+    let mut seeds = vec![start];
+    while let Some(seed) = seeds.pop() {
+        if !is_target(grid, seed.x, seed.y) {
+            continue;
+        }
+        let y = seed.y;
+        let mut x_start = seed.x;
+        while is_target(grid, x_start - 1, y) {
+            x_start -= 1;
+        }
+        let mut x_end = seed.x;
+        while is_target(grid, x_end + 1, y) {
+            x_end += 1;
+        }
+        for x in x_start..=x_end {
+            set(grid, Vector2::new(x, y), matter);
+            filled.push(Vector2::new(x, y));
+        }
+        // One seed per run of target cells on the rows above & below
+        for next_y in [y - 1, y + 1] {
+            let mut in_run = false;
+            for x in x_start..=x_end {
+                let is_run = is_target(grid, x, next_y);
+                if is_run && !in_run {
+                    seeds.push(Vector2::new(x, next_y));
+                }
+                in_run = is_run;
+            }
+        }
+    }
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scanline_flood_fill() {
+        // 0 is empty, 1 is a wall splitting the grid except for a gap at the top
+        let rows = ["00100", "00100", "00000", "11111", "00000"];
+        let width = 5;
+        let mut grid = rows
+            .iter()
+            .rev()
+            .flat_map(|row| row.chars().map(|c| c.to_digit(10).unwrap()))
+            .collect::<Vec<u32>>();
+        let get = |grid: &Vec<u32>, pos: Vector2<i32>| {
+            ((0..width).contains(&pos.x) && (0..5).contains(&pos.y))
+                .then(|| grid[(pos.y * width + pos.x) as usize])
+        };
+        let set = |grid: &mut Vec<u32>, pos: Vector2<i32>, matter| {
+            grid[(pos.y * width + pos.x) as usize] = matter
+        };
+        let filled = scanline_flood_fill(&mut grid, Vector2::new(0, 4), 2, get, set);
+        // Both sides of the wall through the gap, but not below the floor
+        assert_eq!(filled.len(), 13);
+        assert_eq!(grid[4 * 5 + 4], 2);
+        assert_eq!(grid[0], 0);
+        assert!(scanline_flood_fill(&mut grid, Vector2::new(0, 4), 2, get, set).is_empty());
+    }
+}
@@ -0,0 +1,186 @@
+use std::sync::Arc;
+
+use anyhow::*;
+use corrode::time::PerformanceTimer;
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    device::Queue,
+    query::{QueryPool, QueryResultFlags, QueryType},
+    sync::PipelineStage,
+};
+
+/// Max timestamps written in a step: one at the start & one after each timed pass
+const MAX_GPU_TIMESTAMPS: usize = 32;
+
+/// Kernel groups of a simulation step timed on gpu
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GpuPass {
+    Paint,
+    Init,
+    /// Fall, rise & slide
+    Move,
+    Disperse,
+    /// Liquid pressure, velocity, rise & flow
+    Pressure,
+    React,
+    Decay,
+    /// Finish & bitmap update
+    Finish,
+    Color,
+    Bugs,
+}
+
+impl GpuPass {
+    pub const ALL: [GpuPass; 10] = [
+        GpuPass::Paint,
+        GpuPass::Init,
+        GpuPass::Move,
+        GpuPass::Disperse,
+        GpuPass::Pressure,
+        GpuPass::React,
+        GpuPass::Decay,
+        GpuPass::Finish,
+        GpuPass::Color,
+        GpuPass::Bugs,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GpuPass::Paint => "Paint",
+            GpuPass::Init => "Init",
+            GpuPass::Move => "Fall, rise & slide",
+            GpuPass::Disperse => "Disperse",
+            GpuPass::Pressure => "Liquid pressure",
+            GpuPass::React => "React",
+            GpuPass::Decay => "Decay",
+            GpuPass::Finish => "Finish",
+            GpuPass::Color => "Color",
+            GpuPass::Bugs => "Bugs",
+        }
+    }
+}
+
+/// Per pass gpu time of simulation steps, measured with timestamp queries written between the
+/// passes of a step. Results are read once the step has finished
+pub struct GpuTimings {
+    query_pool: Arc<QueryPool>,
+    /// Nanoseconds per timestamp tick
+    timestamp_period: f64,
+    /// Mask of timestamp bits that are valid on compute queue
+    timestamp_mask: u64,
+    /// Pass ended by each timestamp after the first in the latest step
+    written: Vec<GpuPass>,
+    timers: Vec<PerformanceTimer>,
+    has_results: bool,
+}
+
+impl GpuTimings {
+    /// None if compute queue doesn't support timestamps
+    pub fn new(comp_queue: &Arc<Queue>) -> Result<Option<GpuTimings>> {
+        let valid_bits = match comp_queue.family().timestamp_valid_bits() {
+            Some(valid_bits) => valid_bits,
+            None => return Ok(None),
+        };
+        let device = comp_queue.device();
+        let query_pool = QueryPool::new(
+            device.clone(),
+            QueryType::Timestamp,
+            MAX_GPU_TIMESTAMPS as u32,
+        )?;
+        Ok(Some(GpuTimings {
+            query_pool,
+            timestamp_period: device.physical_device().properties().timestamp_period as f64,
+            timestamp_mask: if valid_bits >= 64 {
+                u64::MAX
+            } else {
+                (1 << valid_bits) - 1
+            },
+            written: vec![],
+            timers: GpuPass::ALL.iter().map(|_| PerformanceTimer::new()).collect(),
+            has_results: false,
+        }))
+    }
+
+    /// Reset queries & write the start timestamp of a step
+    pub fn begin(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) -> Result<()> {
+        self.written.clear();
+        unsafe {
+            builder
+                .reset_query_pool(self.query_pool.clone(), 0..MAX_GPU_TIMESTAMPS as u32)?
+                .write_timestamp(self.query_pool.clone(), 0, PipelineStage::TopOfPipe)?;
+        }
+        Ok(())
+    }
+
+    /// Write a timestamp ending `pass`, timed from the previous timestamp
+    pub fn end_pass(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        pass: GpuPass,
+    ) -> Result<()> {
+        let query = self.written.len() as u32 + 1;
+        if query as usize >= MAX_GPU_TIMESTAMPS {
+            return Ok(());
+        }
+        unsafe {
+            builder.write_timestamp(
+                self.query_pool.clone(),
+                query,
+                PipelineStage::ComputeShader,
+            )?;
+        }
+        self.written.push(pass);
+        Ok(())
+    }
+
+    /// Record pass times of the latest step. Call after the step has finished on gpu
+    pub fn read(&mut self) -> Result<()> {
+        if self.written.is_empty() {
+            return Ok(());
+        }
+        let count = self.written.len() + 1;
+        let mut timestamps = [0u64; MAX_GPU_TIMESTAMPS];
+        let is_ready = self
+            .query_pool
+            .queries_range(0..count as u32)
+            .unwrap()
+            .get_results(&mut timestamps[..count], QueryResultFlags {
+                wait: false,
+                with_availability: false,
+                partial: false,
+            })?;
+        if is_ready {
+            let mut pass_ms = [0.0; GpuPass::ALL.len()];
+            for (pass, ticks) in self.written.iter().zip(timestamps.windows(2)) {
+                let ticks = ticks[1].wrapping_sub(ticks[0]) & self.timestamp_mask;
+                pass_ms[*pass as usize] += ticks as f64 * self.timestamp_period / 1_000_000.0;
+            }
+            for (timer, ms) in self.timers.iter_mut().zip(pass_ms) {
+                timer.push_dt_ms(ms);
+            }
+            self.has_results = true;
+        }
+        self.written.clear();
+        Ok(())
+    }
+
+    /// Average gpu milliseconds of each pass per step, empty before results of a step were read
+    pub fn averages_ms(&self) -> Vec<(GpuPass, f64)> {
+        if !self.has_results {
+            return vec![];
+        }
+        GpuPass::ALL
+            .iter()
+            .zip(self.timers.iter())
+            .map(|(pass, timer)| (*pass, timer.time_average_ms()))
+            .collect()
+    }
+
+    /// Average gpu milliseconds of a whole step
+    pub fn total_average_ms(&self) -> f64 {
+        self.averages_ms().iter().map(|(_, ms)| ms).sum()
+    }
+}
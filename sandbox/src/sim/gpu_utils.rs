@@ -4,6 +4,7 @@ use anyhow::*;
 use vulkano::{
     buffer::{BufferUsage, CpuAccessibleBuffer},
     device::Device,
+    DeviceSize,
 };
 
 #[allow(unused)]
@@ -25,3 +26,22 @@ pub fn empty_u32(device: Arc<Device>, size: usize) -> Result<Arc<CpuAccessibleBu
         vec![0; size].into_iter(),
     )?)
 }
+
+/// Zeroed buffer shared concurrently by all queue families of `device`, for buffers written on
+/// one queue (e.g. transfer) & used on others without ownership transfers
+pub fn shared_empty_u32(
+    device: Arc<Device>,
+    size: usize,
+) -> Result<Arc<CpuAccessibleBuffer<[u32]>>> {
+    let buffer = unsafe {
+        CpuAccessibleBuffer::<[u32]>::raw(
+            device.clone(),
+            (size * std::mem::size_of::<u32>()) as DeviceSize,
+            BufferUsage::all(),
+            false,
+            device.active_queue_families(),
+        )?
+    };
+    buffer.write()?.iter_mut().for_each(|value| *value = 0);
+    Ok(buffer)
+}
@@ -0,0 +1,89 @@
+use cgmath::Vector2;
+
+use crate::matter::ImpactSound;
+
+/// Contact impulse below which objects resting on or sliding against matter are silent
+pub const MIN_IMPACT_IMPULSE: f32 = 0.05;
+/// Speed (world units per second) below which objects entering liquid don't splash
+pub const MIN_SPLASH_SPEED: f32 = 1.0;
+/// Contact points lie on boundary edges, which may be off the matter by a cell or two
+pub const IMPACT_SAMPLE_RADIUS: i32 = 2;
+
+/// Sound produced by a simulation step. There's no audio output yet, these are what one would
+/// play
+#[derive(Debug, Clone, PartialEq)]
+pub enum SoundEvent {
+    Impact(ImpactSoundEvent),
+    /// Named sound of a trigger zone's action, played at the zone
+    Trigger { name: String, world_pos: Vector2<f32> },
+}
+
+/// Object hit matter
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ImpactSoundEvent {
+    pub sound: ImpactSound,
+    pub matter: u32,
+    pub world_pos: Vector2<f32>,
+    /// Contact impulse for solids & powders, speed for liquids. Use for volume
+    pub strength: f32,
+}
+
+/// Nearest matter to `canvas_pos` within `radius` cells that `accept`s it. `matter_at` returns
+/// None for positions outside simulated area
+pub fn nearest_matter(
+    canvas_pos: Vector2<i32>,
+    radius: i32,
+    matter_at: impl Fn(Vector2<i32>) -> Option<u32>,
+    accept: impl Fn(u32) -> bool,
+) -> Option<u32> {
+    nearest_matter_cell(canvas_pos, radius, matter_at, accept).map(|(_, matter)| matter)
+}
+
+/// Like `nearest_matter`, but also returns the canvas position of the matter
+pub fn nearest_matter_cell(
+    canvas_pos: Vector2<i32>,
+    radius: i32,
+    matter_at: impl Fn(Vector2<i32>) -> Option<u32>,
+    accept: impl Fn(u32) -> bool,
+) -> Option<(Vector2<i32>, u32)> {
+    let mut nearest = None;
+    let mut nearest_distance = i32::MAX;
+    for y in -radius..=radius {
+        for x in -radius..=radius {
+            let distance = x * x + y * y;
+            if distance >= nearest_distance || distance > radius * radius {
+                continue;
+            }
+            let pos = canvas_pos + Vector2::new(x, y);
+            if let Some(matter) = matter_at(pos) {
+                if accept(matter) {
+                    nearest = Some((pos, matter));
+                    nearest_distance = distance;
+                }
+            }
+        }
+    }
+    nearest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearest_matter() {
+        // Matter 1 on the row below origin, matter 2 two cells to the right
+        let matter_at = |pos: Vector2<i32>| match (pos.x, pos.y) {
+            (_, -1) => Some(1),
+            (2, 0) => Some(2),
+            (x, _) if x.abs() > 4 => None,
+            _ => Some(0),
+        };
+        let origin = Vector2::new(0, 0);
+        assert_eq!(nearest_matter(origin, 2, matter_at, |m| m != 0), Some(1));
+        assert_eq!(nearest_matter(origin, 2, matter_at, |m| m == 2), Some(2));
+        assert_eq!(nearest_matter(origin, 1, matter_at, |m| m == 2), None);
+        let cell = nearest_matter_cell(origin, 2, matter_at, |m| m == 2);
+        assert_eq!(cell, Some((Vector2::new(2, 0), 2)));
+    }
+}
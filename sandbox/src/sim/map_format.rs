@@ -0,0 +1,289 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    matter::MatterDefinitions,
+    object::PixelObjectSaveDataArray,
+    sim::{encode_chunk, matter_image_to_grid, CHUNK_FILE_EXTENSION},
+    utils::load_bitmap_image_from_path,
+};
+
+/// Version of the map format written by this build. When the format changes, bump this and add
+/// a step from the previous version to `migrate_map`, so existing maps keep loading
+pub const MAP_FORMAT_VERSION: u32 = 2;
+
+pub const MAP_MANIFEST_FILE: &str = "manifest.json";
+
+/// Matter a map was saved with. Manifest's matters are the map's palette: chunks store indices
+/// to it, which are remapped to current matter ids on load, so maps survive matter definitions
+/// changing
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MapMatter {
+    pub name: String,
+    pub color: u32,
+}
+
+/// Describes the format of a saved map directory
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MapManifest {
+    pub version: u32,
+    pub chunked: bool,
+    pub matters: Vec<MapMatter>,
+    /// Seed of the map's simulation randomness. Maps saved without one get 0
+    #[serde(default)]
+    pub world_seed: u64,
+}
+
+impl MapManifest {
+    pub fn new(matter_definitions: &MatterDefinitions, chunked: bool) -> MapManifest {
+        MapManifest {
+            version: MAP_FORMAT_VERSION,
+            chunked,
+            matters: matter_definitions
+                .definitions
+                .iter()
+                .map(|m| MapMatter {
+                    name: m.name.clone(),
+                    color: m.color,
+                })
+                .collect(),
+            world_seed: 0,
+        }
+    }
+
+    /// Current matter id of each palette matter, found by name or else by color. Also returns
+    /// names of palette matters not found, whose cells become empty
+    pub fn matter_remap(&self, matter_definitions: &MatterDefinitions) -> (Vec<u32>, Vec<String>) {
+        let definitions = &matter_definitions.definitions;
+        let mut missing = vec![];
+        let remap = self
+            .matters
+            .iter()
+            .map(|matter| {
+                definitions
+                    .iter()
+                    .find(|d| d.name == matter.name)
+                    .or_else(|| definitions.iter().find(|d| d.color == matter.color))
+                    .map(|d| d.id)
+                    .unwrap_or_else(|| {
+                        missing.push(matter.name.clone());
+                        matter_definitions.empty
+                    })
+            })
+            .collect();
+        (remap, missing)
+    }
+
+    pub fn serialize(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn deserialize(data: &str) -> Result<MapManifest> {
+        Ok(serde_json::from_str(data)?)
+    }
+
+    /// Read map's manifest. Maps saved before versioning have none and are version 0
+    pub fn read(map_dir: &Path) -> Result<Option<MapManifest>> {
+        let path = map_dir.join(MAP_MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(MapManifest::deserialize(&fs::read_to_string(path)?)?))
+    }
+
+    pub fn write(&self, map_dir: &Path) -> Result<()> {
+        fs::write(map_dir.join(MAP_MANIFEST_FILE), self.serialize())?;
+        Ok(())
+    }
+}
+
+/// Bring map at `map_dir` up to `MAP_FORMAT_VERSION`, one version at a time. Each step rewrites
+/// the manifest so a failed migration can be resumed
+pub fn migrate_map(
+    map_dir: &Path,
+    matter_definitions: &MatterDefinitions,
+) -> Result<MapManifest> {
+    let mut manifest = match MapManifest::read(map_dir)? {
+        Some(manifest) => manifest,
+        None => migrate_v0_to_v1(map_dir, matter_definitions)?,
+    };
+    if manifest.version > MAP_FORMAT_VERSION {
+        bail!(
+            "Map {:?} has format version {}, newer than supported {}",
+            map_dir,
+            manifest.version,
+            MAP_FORMAT_VERSION
+        );
+    }
+    if manifest.version == 1 {
+        manifest = migrate_v1_to_v2(map_dir, manifest, matter_definitions)?;
+    }
+    Ok(manifest)
+}
+
+/// Maps before versioning had no manifest and may lack the objects directory. Their matter
+/// is assumed to be the current definitions
+fn migrate_v0_to_v1(
+    map_dir: &Path,
+    matter_definitions: &MatterDefinitions,
+) -> Result<MapManifest> {
+    info!("Migrating map {:?} to format version 1", map_dir);
+    let obj_dir_path = map_dir.join("objects");
+    if !obj_dir_path.exists() {
+        fs::create_dir_all(&obj_dir_path)?;
+    }
+    let obj_data_path = obj_dir_path.join("objects.json");
+    if !obj_data_path.exists() {
+        let empty = PixelObjectSaveDataArray {
+            objects: vec![],
+            joints: vec![],
+        };
+        fs::write(obj_data_path, empty.serialize())?;
+    }
+    let num_chunks = fs::read_dir(map_dir)?
+        .filter_map(|f| f.ok())
+        .filter(|f| {
+            let name = f.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with("chunk") && name.ends_with(".png")
+        })
+        .count();
+    let manifest = MapManifest {
+        version: 1,
+        chunked: num_chunks > 1,
+        ..MapManifest::new(matter_definitions, false)
+    };
+    manifest.write(map_dir)?;
+    Ok(manifest)
+}
+
+/// Chunks were png images storing matter as colors, which loses matter sharing a color. They
+/// are converted to binary chunks of current matter, see `encode_chunk`, colors looked up from
+/// the map's palette. Images failing to convert are left as is
+fn migrate_v1_to_v2(
+    map_dir: &Path,
+    manifest: MapManifest,
+    matter_definitions: &MatterDefinitions,
+) -> Result<MapManifest> {
+    info!("Migrating map {:?} to format version 2", map_dir);
+    let (remap, _) = manifest.matter_remap(matter_definitions);
+    // First of palette matters sharing a color wins
+    let matter_by_color = manifest
+        .matters
+        .iter()
+        .zip(remap)
+        .rev()
+        .map(|(matter, id)| (matter.color, id))
+        .collect::<HashMap<u32, u32>>();
+    for file in fs::read_dir(map_dir)? {
+        let path = file?.path();
+        let file_name = path.file_name().unwrap().to_string_lossy();
+        if !file_name.starts_with("chunk") || !file_name.ends_with(".png") {
+            continue;
+        }
+        let converted = load_bitmap_image_from_path(path.clone())
+            .and_then(|image| {
+                matter_image_to_grid(&image, &matter_by_color, matter_definitions.empty)
+            })
+            .and_then(|(matter_grid, num_unknown)| {
+                if num_unknown > 0 {
+                    warn!(
+                        "{} cells of {:?} had colors of no matter, they become empty",
+                        num_unknown, path
+                    );
+                }
+                let bin_path = path.with_extension(CHUNK_FILE_EXTENSION);
+                fs::write(bin_path, encode_chunk(&matter_grid))?;
+                fs::remove_file(&path)?;
+                Ok(())
+            });
+        if let Err(e) = converted {
+            warn!("Failed to convert chunk image {:?}: {:#}", path, e);
+        }
+    }
+    // Chunks now refer to current matter
+    let manifest = MapManifest {
+        version: 2,
+        matters: MapManifest::new(matter_definitions, manifest.chunked).matters,
+        ..manifest
+    };
+    manifest.write(map_dir)?;
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{matter::MatterDefinition, utils::BitmapImage, CANVAS_CHUNK_SIZE};
+
+    #[test]
+    fn test_migrate_unversioned_map() {
+        let map_dir = std::env::temp_dir().join("sandbox_test_migrate_unversioned_map");
+        let _ = fs::remove_dir_all(&map_dir);
+        fs::create_dir_all(&map_dir).unwrap();
+        fs::write(map_dir.join("chunk_0_0.png"), b"").unwrap();
+        fs::write(map_dir.join("chunk_1_0.png"), b"").unwrap();
+        BitmapImage::empty(CANVAS_CHUNK_SIZE.x, CANVAS_CHUNK_SIZE.y)
+            .save_png(&map_dir.join("chunk_2_0.png"))
+            .unwrap();
+        let matter_definitions = MatterDefinitions {
+            definitions: vec![MatterDefinition::zero()],
+            empty: 0,
+        };
+
+        let manifest = migrate_map(&map_dir, &matter_definitions).unwrap();
+        assert_eq!(manifest.version, MAP_FORMAT_VERSION);
+        assert!(manifest.chunked);
+        assert!(map_dir.join("objects/objects.json").exists());
+        // Valid chunk images are converted, broken ones left for loading to report
+        assert!(map_dir.join("chunk_2_0.bin").exists());
+        assert!(!map_dir.join("chunk_2_0.png").exists());
+        assert!(map_dir.join("chunk_0_0.png").exists());
+        assert_eq!(MapManifest::read(&map_dir).unwrap(), Some(manifest));
+
+        // Newer maps are refused instead of being loaded wrong
+        MapManifest {
+            version: MAP_FORMAT_VERSION + 1,
+            ..MapManifest::new(&matter_definitions, false)
+        }
+        .write(&map_dir)
+        .unwrap();
+        assert!(migrate_map(&map_dir, &matter_definitions).is_err());
+        fs::remove_dir_all(&map_dir).unwrap();
+    }
+
+    #[test]
+    fn test_matter_remap() {
+        let matter = |id: u32, name: &str, color: u32| MatterDefinition {
+            id,
+            name: name.to_string(),
+            color,
+            ..MatterDefinition::zero()
+        };
+        let matter_definitions = MatterDefinitions {
+            definitions: vec![matter(0, "Empty", 0), matter(1, "Sand", 1), matter(2, "Dust", 1)],
+            empty: 0,
+        };
+        let map_matter = |name: &str, color: u32| MapMatter {
+            name: name.to_string(),
+            color,
+        };
+        // Saved when Dust came before Sand, & with a matter since renamed & one since removed
+        let manifest = MapManifest {
+            matters: vec![
+                map_matter("Empty", 0),
+                map_matter("Dust", 1),
+                map_matter("Sand", 1),
+                map_matter("Renamed", 1),
+                map_matter("Removed", 5),
+            ],
+            ..MapManifest::new(&matter_definitions, false)
+        };
+        let (remap, missing) = manifest.matter_remap(&matter_definitions);
+        assert_eq!(remap, vec![0, 2, 1, 1, 0]);
+        assert_eq!(missing, vec!["Removed".to_string()]);
+        assert_eq!(matter_definitions.color_collisions(), vec![(1, 2)]);
+    }
+}
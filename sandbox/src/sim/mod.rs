@@ -1,12 +1,54 @@
+mod anchors;
+mod annotations;
 mod boundaries;
 mod ca_simulator;
+mod chunk_access;
+mod chunk_index;
+mod chunk_jobs;
+mod detail_upscaler;
+mod explosions;
+mod flood_fill;
+mod gpu_timings;
 mod gpu_utils;
+mod impact_sounds;
+mod map_format;
+mod object_budgets;
+mod object_joints;
+mod ore_veins;
+mod portals;
+mod raycast;
+mod reaction_stats;
+mod region_of_interest;
+mod replay;
+mod shapes;
 mod simulation;
 mod simulation_chunk_manager;
 mod simulation_utils;
+mod trigger_zones;
 
+pub use anchors::*;
+pub use annotations::*;
 pub use ca_simulator::*;
+pub use chunk_access::*;
+pub use chunk_index::*;
+pub use chunk_jobs::*;
+pub use detail_upscaler::*;
+pub use explosions::*;
+pub use flood_fill::*;
+pub use gpu_timings::*;
 pub use gpu_utils::*;
+pub use impact_sounds::*;
+pub use map_format::*;
+pub use object_budgets::*;
+pub use object_joints::*;
+pub use ore_veins::*;
+pub use portals::*;
+pub use raycast::*;
+pub use reaction_stats::*;
+pub use region_of_interest::*;
+pub use replay::*;
+pub use shapes::*;
 pub use simulation::*;
 pub use simulation_chunk_manager::*;
 pub use simulation_utils::*;
+pub use trigger_zones::*;
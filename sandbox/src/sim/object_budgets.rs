@@ -0,0 +1,127 @@
+use cgmath::Vector2;
+use hecs::Entity;
+use serde::{Deserialize, Serialize};
+
+use crate::object::TempPixel;
+
+/// Objects with at most this many alive pixels count as debris, which budgets may remove
+pub const DEBRIS_MAX_PIXELS: usize = 64;
+/// Debris slower than this (world units per second) is resting and can be merged into the grid
+pub const DEBRIS_REST_SPEED: f32 = 0.05;
+
+/// What to do with debris when dynamic objects or their colliders go over budget
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DebrisPolicy {
+    /// Turn resting debris inside sim area back into matter, oldest first. Moving debris is left
+    /// alone until it settles
+    MergeIntoGrid,
+    /// Remove oldest debris wherever it is
+    DespawnOldest,
+}
+
+/// Simulation step on which a dynamic object was created. Objects without it count as oldest
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SpawnStep(pub u64);
+
+/// Simulated time (seconds) since which a dynamic object has been asleep, see
+/// `AppSettings::freeze_resting_objects`. Removed when it wakes up
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AsleepSince(pub f64);
+
+/// Whether an object of `pixels` (in canvas coordinates) rests on terrain, i.e. a cell right
+/// below one of its pixels is terrain & not the object's own
+pub fn rests_on_terrain(pixels: &[TempPixel], is_terrain: impl Fn(Vector2<i32>) -> bool) -> bool {
+    pixels.iter().any(|pixel| {
+        let below = pixel.canvas_pos - Vector2::new(0, 1);
+        is_terrain(below) && !pixels.iter().any(|other| other.canvas_pos == below)
+    })
+}
+
+/// Small dynamic object that can be removed to get back within budgets
+#[derive(Debug, Copy, Clone)]
+pub struct Debris {
+    pub entity: Entity,
+    pub spawn_step: u64,
+    pub num_colliders: usize,
+    /// Resting inside sim area, so it can be merged into the grid
+    pub can_merge: bool,
+}
+
+/// Select debris to remove, oldest first, until object & collider counts are within budgets.
+/// Empty if already within them
+pub fn select_debris_over_budget(
+    mut debris: Vec<Debris>,
+    num_objects: usize,
+    num_colliders: usize,
+    max_objects: usize,
+    max_colliders: usize,
+    policy: DebrisPolicy,
+) -> Vec<Entity> {
+    let mut objects_over = num_objects.saturating_sub(max_objects);
+    let mut colliders_over = num_colliders.saturating_sub(max_colliders);
+    if objects_over == 0 && colliders_over == 0 {
+        return vec![];
+    }
+    debris.sort_unstable_by_key(|d| d.spawn_step);
+    let mut selected = vec![];
+    for d in debris {
+        if objects_over == 0 && colliders_over == 0 {
+            break;
+        }
+        if policy == DebrisPolicy::MergeIntoGrid && !d.can_merge {
+            continue;
+        }
+        objects_over = objects_over.saturating_sub(1);
+        colliders_over = colliders_over.saturating_sub(d.num_colliders);
+        selected.push(d.entity);
+    }
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_debris_over_budget() {
+        let debris = (0..4u32)
+            .map(|i| Debris {
+                entity: Entity::from_bits(i as u64 + (1 << 32)).unwrap(),
+                spawn_step: 10 - i as u64,
+                num_colliders: 2,
+                can_merge: i != 3,
+            })
+            .collect::<Vec<Debris>>();
+        let entity = |i: u32| debris[i as usize].entity;
+        // Within budgets
+        let policy = DebrisPolicy::DespawnOldest;
+        assert!(select_debris_over_budget(debris.clone(), 4, 8, 4, 8, policy).is_empty());
+        // One object over, oldest goes
+        let selected = select_debris_over_budget(debris.clone(), 5, 8, 4, 8, policy);
+        assert_eq!(selected, vec![entity(3)]);
+        // Three colliders over needs two objects
+        let selected = select_debris_over_budget(debris.clone(), 4, 11, 4, 8, policy);
+        assert_eq!(selected, vec![entity(3), entity(2)]);
+        // Merging skips the oldest which is still moving
+        let policy = DebrisPolicy::MergeIntoGrid;
+        let selected = select_debris_over_budget(debris, 5, 8, 4, 8, policy);
+        assert_eq!(selected, vec![entity(2)]);
+    }
+
+    #[test]
+    fn test_rests_on_terrain() {
+        let entity = Entity::from_bits(1 << 32).unwrap();
+        let pixel = |x: i32, y: i32| TempPixel {
+            pixel_index: 0,
+            canvas_pos: Vector2::new(x, y),
+            matter: 0,
+            color: 0,
+            entity,
+        };
+        // Vertical bar whose own pixels are in terrain rows
+        let pixels = vec![pixel(0, 1), pixel(0, 2)];
+        assert!(rests_on_terrain(&pixels, |pos| pos.y <= 0));
+        assert!(!rests_on_terrain(&pixels, |pos| pos.y <= -1));
+        assert!(!rests_on_terrain(&pixels, |pos| pos.y == 1));
+    }
+}
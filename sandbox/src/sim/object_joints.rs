@@ -0,0 +1,135 @@
+use cgmath::{InnerSpace, Vector2};
+use corrode::physics::PhysicsWorld;
+use hecs::Entity;
+use rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Shortest rope joint, so ropes between touching objects don't pull them into each other
+pub const MIN_ROPE_LENGTH: f32 = 0.01;
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ObjectJointKind {
+    /// Objects turn freely around a shared point
+    Revolute,
+    /// Objects' points can't get further apart than rope length
+    Rope,
+}
+
+/// Hinge or rope between two dynamic objects, at points local to the objects
+#[derive(Debug, Copy, Clone)]
+pub struct ObjectJoint {
+    pub entities: [Entity; 2],
+    /// Objects' bodies the joint was made with. Deformed objects get new bodies and need a new
+    /// joint
+    pub bodies: [RigidBodyHandle; 2],
+    pub joint: ImpulseJointHandle,
+    pub kind: ObjectJointKind,
+    /// Joined points relative to object positions, unrotated
+    pub local_anchors: [Vector2<f32>; 2],
+    /// Max distance of the joined points of a rope
+    pub length: f32,
+}
+
+impl ObjectJoint {
+    /// Join `bodies` of `entities` at `local_anchors`
+    pub fn new(
+        physics_world: &mut PhysicsWorld,
+        entities: [Entity; 2],
+        bodies: [RigidBodyHandle; 2],
+        kind: ObjectJointKind,
+        local_anchors: [Vector2<f32>; 2],
+        length: f32,
+    ) -> ObjectJoint {
+        let mut joint = ObjectJoint {
+            entities,
+            bodies,
+            joint: ImpulseJointHandle::invalid(),
+            kind,
+            local_anchors,
+            length: length.max(MIN_ROPE_LENGTH),
+        };
+        joint.insert(physics_world, bodies);
+        joint
+    }
+
+    /// Insert the joint between `bodies`, e.g. after the objects were deformed
+    pub fn insert(&mut self, physics_world: &mut PhysicsWorld, bodies: [RigidBodyHandle; 2]) {
+        let [anchor1, anchor2] = self.local_anchors;
+        let (anchor1, anchor2) = (point![anchor1.x, anchor1.y], point![anchor2.x, anchor2.y]);
+        let joint: GenericJoint = match self.kind {
+            ObjectJointKind::Revolute => RevoluteJointBuilder::new()
+                .local_anchor1(anchor1)
+                .local_anchor2(anchor2)
+                .into(),
+            ObjectJointKind::Rope => {
+                // Limiting coupled linear axes limits the distance of the points
+                let mut joint = GenericJointBuilder::new(JointAxesMask::empty())
+                    .local_anchor1(anchor1)
+                    .local_anchor2(anchor2)
+                    .limits(JointAxis::X, [0.0, self.length])
+                    .build();
+                joint.coupled_axes = JointAxesMask::X | JointAxesMask::Y;
+                joint
+            }
+        };
+        self.joint = physics_world.insert_joint(bodies[0], bodies[1], joint);
+        self.bodies = bodies;
+    }
+
+    pub fn joins(&self, entity: Entity) -> bool {
+        self.entities.contains(&entity)
+    }
+}
+
+/// Point at `world_pos` relative to the body's position, unrotated
+pub fn object_local_anchor(body: &RigidBody, world_pos: Vector2<f32>) -> Vector2<f32> {
+    let local = body.position().inverse_transform_point(&point![world_pos.x, world_pos.y]);
+    Vector2::new(local.x, local.y)
+}
+
+/// World position of a point relative to the body's position
+pub fn object_world_anchor(body: &RigidBody, local_anchor: Vector2<f32>) -> Vector2<f32> {
+    let world = body.position() * point![local_anchor.x, local_anchor.y];
+    Vector2::new(world.x, world.y)
+}
+
+/// Joint saved with map objects, joined objects by their index in `PixelObjectSaveDataArray`
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct ObjectJointSaveData {
+    pub objects: [usize; 2],
+    pub kind: ObjectJointKind,
+    pub local_anchors: [Vector2<f32>; 2],
+    pub length: f32,
+}
+
+/// Rope length to join points at `world_anchors` with, the distance they are from each other
+pub fn rope_length(world_anchors: [Vector2<f32>; 2]) -> f32 {
+    (world_anchors[1] - world_anchors[0]).magnitude()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::PixelObjectSaveDataArray;
+
+    #[test]
+    fn test_joints_save_data() {
+        // Objects saved before joints have none
+        let old = PixelObjectSaveDataArray::deserialize(r#"{"objects":[]}"#).unwrap();
+        assert!(old.joints.is_empty());
+        let saved = PixelObjectSaveDataArray {
+            objects: vec![],
+            joints: vec![ObjectJointSaveData {
+                objects: [1, 0],
+                kind: ObjectJointKind::Rope,
+                local_anchors: [Vector2::new(0.5, 0.0), Vector2::new(0.0, -0.5)],
+                length: rope_length([Vector2::new(0.0, 0.0), Vector2::new(3.0, 4.0)]),
+            }],
+        };
+        let loaded = PixelObjectSaveDataArray::deserialize(&saved.serialize()).unwrap();
+        assert_eq!(loaded.joints[0].objects, [1, 0]);
+        assert_eq!(loaded.joints[0].kind, ObjectJointKind::Rope);
+        assert_eq!(loaded.joints[0].local_anchors[1], Vector2::new(0.0, -0.5));
+        assert_eq!(loaded.joints[0].length, 5.0);
+    }
+}
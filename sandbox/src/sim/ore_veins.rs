@@ -0,0 +1,84 @@
+use cgmath::Vector2;
+
+use crate::utils::value_noise;
+
+/// Generates ore veins through host matter (e.g. rock) along noise contours, e.g. for mining
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OreVeins {
+    pub host: u32,
+    pub ore: u32,
+    /// Share of host area veins appear in, 0.0 - 1.0
+    pub frequency: f32,
+    /// Typical length of veins in cells
+    pub vein_length: f32,
+    /// Width of veins, 0.0 - 1.0
+    pub thickness: f32,
+    /// Fill empty cells with host first, e.g. for a new map to be dug through
+    pub fill_empty: bool,
+    pub seed: u32,
+}
+
+impl OreVeins {
+    /// Matter of cell at `pos` after generation
+    pub fn matter_at(&self, pos: Vector2<i32>, matter: u32, empty: u32) -> u32 {
+        let matter = if self.fill_empty && matter == empty {
+            self.host
+        } else {
+            matter
+        };
+        if matter != self.host {
+            return matter;
+        }
+        let length = self.vein_length.max(1.0);
+        let (x, y) = (pos.x as f32, pos.y as f32);
+        // Veins are clustered to regions a few vein lengths wide
+        let region = value_noise(x / (4.0 * length), y / (4.0 * length), self.seed ^ 0x9e37_79b9);
+        if region >= self.frequency {
+            return matter;
+        }
+        let vein = value_noise(x / length, y / length, self.seed);
+        if (vein - 0.5).abs() < 0.1 * self.thickness {
+            self.ore
+        } else {
+            matter
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ore_veins() {
+        let (empty, rock, ore, sand) = (0, 1, 2, 3);
+        let veins = OreVeins {
+            host: rock,
+            ore,
+            frequency: 0.7,
+            vein_length: 8.0,
+            thickness: 0.5,
+            fill_empty: false,
+            seed: 3,
+        };
+        let cells = (0..128).flat_map(|y| (0..128).map(move |x| Vector2::new(x, y)));
+        let ores = cells
+            .clone()
+            .filter(|pos| veins.matter_at(*pos, rock, empty) == ore)
+            .count();
+        assert!(ores > 0 && ores < 128 * 128 / 4);
+        // Only host is replaced
+        assert!(cells.clone().all(|pos| veins.matter_at(pos, sand, empty) == sand));
+        assert!(cells.clone().all(|pos| veins.matter_at(pos, empty, empty) == empty));
+        let filled = OreVeins {
+            fill_empty: true,
+            ..veins
+        };
+        assert!(cells.clone().all(|pos| filled.matter_at(pos, empty, empty) != empty));
+        let no_veins = OreVeins {
+            frequency: 0.0,
+            ..veins
+        };
+        assert!(cells.clone().all(|pos| no_veins.matter_at(pos, rock, empty) == rock));
+    }
+}
@@ -0,0 +1,138 @@
+use anyhow::*;
+use cgmath::Vector2;
+use serde::{Deserialize, Serialize};
+
+use crate::sim::is_inside_sim_canvas;
+
+/// Max number of active portal pairs. If you touch this, also change shaders...
+pub const MAX_PORTALS: usize = 2;
+
+/// Two horizontal seams (in canvas coordinates) glued together. Matter & objects moving down
+/// through row `a.y` continue from row `b.y`, and moving down through row `b.y + 1` continue from
+/// row `a.y - 1` (and vice versa upwards).
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct PortalPair {
+    pub a: Vector2<i32>,
+    pub b: Vector2<i32>,
+    pub width: i32,
+}
+
+impl PortalPair {
+    fn is_inside_sim_canvas(&self, sim_pos_offset: Vector2<i32>) -> bool {
+        let right = Vector2::new(self.width - 1, 0);
+        let up = Vector2::new(0, 1);
+        [
+            self.a - up,
+            self.a + right,
+            self.b,
+            self.b + right + up,
+        ]
+        .iter()
+        .all(|&p| is_inside_sim_canvas(p, sim_pos_offset))
+    }
+
+    /// Position after moving from `prev` to `current` if the move went through the pair's seams
+    pub fn teleport(&self, prev: Vector2<i32>, current: Vector2<i32>) -> Option<Vector2<i32>> {
+        let Self {
+            a,
+            b,
+            width,
+        } = *self;
+        let in_range = |start: i32| current.x >= start && current.x < start + width;
+        if prev.y >= a.y && current.y < a.y && in_range(a.x) {
+            // Down through a
+            Some(Vector2::new(b.x + current.x - a.x, b.y + 1 - (a.y - current.y)))
+        } else if prev.y < a.y && current.y >= a.y && in_range(a.x) {
+            // Up through a
+            Some(Vector2::new(b.x + current.x - a.x, b.y + current.y - (a.y - 1)))
+        } else if prev.y > b.y && current.y <= b.y && in_range(b.x) {
+            // Down through b
+            Some(Vector2::new(a.x + current.x - b.x, a.y - (b.y + 1 - current.y)))
+        } else if prev.y <= b.y && current.y > b.y && in_range(b.x) {
+            // Up through b
+            Some(Vector2::new(a.x + current.x - b.x, a.y - 1 + current.y - b.y))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Portals {
+    pub pairs: Vec<PortalPair>,
+}
+
+impl Portals {
+    pub fn new() -> Portals {
+        Portals {
+            pairs: vec![],
+        }
+    }
+
+    pub fn serialize(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn deserialize(data: &str) -> Result<Portals> {
+        Ok(serde_json::from_str(data)?)
+    }
+
+    /// Push constant data for simulation kernels. Only the first `MAX_PORTALS` pairs that are
+    /// fully inside simulated area are active, otherwise matter could be lost outside the canvas
+    pub fn shader_data(
+        &self,
+        sim_pos_offset: Vector2<i32>,
+    ) -> ([[i32; 4]; MAX_PORTALS], [i32; MAX_PORTALS]) {
+        let mut portals = [[0; 4]; MAX_PORTALS];
+        let mut widths = [0; MAX_PORTALS];
+        for (i, pair) in self
+            .pairs
+            .iter()
+            .filter(|p| p.width > 0 && p.is_inside_sim_canvas(sim_pos_offset))
+            .take(MAX_PORTALS)
+            .enumerate()
+        {
+            portals[i] = [pair.a.x, pair.a.y, pair.b.x, pair.b.y];
+            widths[i] = pair.width;
+        }
+        (portals, widths)
+    }
+
+    pub fn teleport(&self, prev: Vector2<i32>, current: Vector2<i32>) -> Option<Vector2<i32>> {
+        self.pairs
+            .iter()
+            .filter(|p| p.width > 0)
+            .find_map(|pair| pair.teleport(prev, current))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_portal_teleport_is_symmetric() {
+        let pair = PortalPair {
+            a: Vector2::new(0, 10),
+            b: Vector2::new(100, 50),
+            width: 5,
+        };
+        // Falling through a lands on b's row
+        assert_eq!(
+            pair.teleport(Vector2::new(2, 10), Vector2::new(2, 9)),
+            Some(Vector2::new(102, 50))
+        );
+        // Rising back up through b's row lands on a's row
+        assert_eq!(
+            pair.teleport(Vector2::new(102, 50), Vector2::new(102, 51)),
+            Some(Vector2::new(2, 10))
+        );
+        // Falling onto b from above continues below a
+        assert_eq!(
+            pair.teleport(Vector2::new(104, 51), Vector2::new(104, 50)),
+            Some(Vector2::new(4, 9))
+        );
+        // Outside portal width nothing happens
+        assert_eq!(pair.teleport(Vector2::new(5, 10), Vector2::new(5, 9)), None);
+    }
+}
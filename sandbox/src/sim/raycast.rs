@@ -0,0 +1,96 @@
+use cgmath::{InnerSpace, Vector2};
+
+/// Cell a ray hit
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RayHit {
+    pub canvas_pos: Vector2<i32>,
+    pub matter: u32,
+    /// Distance in cells from ray origin to where the ray entered the cell
+    pub distance: f32,
+}
+
+/// March cells along a ray from canvas `origin` towards `dir`, returning the first cell within
+/// `max_dist` cells whose matter `accept`s. Canvas cell `c` covers `c - 0.5..c + 0.5`, like
+/// `world_pos_to_canvas_pos` rounds. `matter_at` returns None for positions outside simulated
+/// area, which end the ray
+pub fn raycast_cells(
+    origin: Vector2<f32>,
+    dir: Vector2<f32>,
+    max_dist: f32,
+    matter_at: impl Fn(Vector2<i32>) -> Option<u32>,
+    accept: impl Fn(u32) -> bool,
+) -> Option<RayHit> {
+    let length = dir.magnitude();
+    if length == 0.0 || !length.is_finite() {
+        return None;
+    }
+    let dir = dir / length;
+    // Cells as unit squares from their corner
+    let start = origin + Vector2::new(0.5, 0.5);
+    let mut cell = Vector2::new(start.x.floor() as i32, start.y.floor() as i32);
+    let axis = |start: f32, cell: i32, dir: f32| -> (i32, f32, f32) {
+        if dir > 0.0 {
+            (1, (cell as f32 + 1.0 - start) / dir, 1.0 / dir)
+        } else if dir < 0.0 {
+            (-1, (start - cell as f32) / -dir, -1.0 / dir)
+        } else {
+            (0, f32::INFINITY, f32::INFINITY)
+        }
+    };
+    // Step direction, distance to next cell border & distance between borders
+    let (step_x, mut next_x, delta_x) = axis(start.x, cell.x, dir.x);
+    let (step_y, mut next_y, delta_y) = axis(start.y, cell.y, dir.y);
+    let mut distance = 0.0;
+    while distance <= max_dist {
+        let matter = matter_at(cell)?;
+        if accept(matter) {
+            return Some(RayHit {
+                canvas_pos: cell,
+                matter,
+                distance,
+            });
+        }
+        if next_x < next_y {
+            cell.x += step_x;
+            distance = next_x;
+            next_x += delta_x;
+        } else {
+            cell.y += step_y;
+            distance = next_y;
+            next_y += delta_y;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raycast_cells() {
+        // Wall of matter 1 at x = 5, outside simulated area beyond 10 cells
+        let matter_at = |pos: Vector2<i32>| match (pos.x, pos.y) {
+            (x, y) if x.abs() > 10 || y.abs() > 10 => None,
+            (5, _) => Some(1),
+            _ => Some(0),
+        };
+        let cast = |dir: Vector2<f32>, max_dist: f32| {
+            raycast_cells(Vector2::new(0.0, 0.0), dir, max_dist, matter_at, |m| m != 0)
+        };
+        assert_eq!(
+            cast(Vector2::new(1.0, 0.0), 20.0),
+            Some(RayHit {
+                canvas_pos: Vector2::new(5, 0),
+                matter: 1,
+                distance: 4.5,
+            })
+        );
+        let diagonal = cast(Vector2::new(2.0, 2.0), 20.0);
+        assert_eq!(diagonal.map(|hit| hit.canvas_pos.x), Some(5));
+        // Too short, away from the wall & no direction
+        assert_eq!(cast(Vector2::new(1.0, 0.0), 4.0), None);
+        assert_eq!(cast(Vector2::new(-1.0, 0.0), 20.0), None);
+        assert_eq!(cast(Vector2::new(0.0, 0.0), 20.0), None);
+    }
+}
@@ -0,0 +1,159 @@
+use std::cmp::Reverse;
+
+use anyhow::*;
+
+use crate::{
+    matter::{MatterDefinitions, MAX_TRANSITIONS},
+    sim::Simulation,
+    MAX_NUM_MATTERS,
+};
+
+/// Counts per matter: its reactions, then decay & decay emission. Must match
+/// compute_shaders/simulation/includes.glsl
+pub const REACTION_COUNT_STRIDE: usize = MAX_TRANSITIONS as usize + 2;
+const DECAY_COUNT_INDEX: usize = MAX_TRANSITIONS as usize;
+const EMISSION_COUNT_INDEX: usize = MAX_TRANSITIONS as usize + 1;
+/// Counts are read from gpu only every this many steps, as reading waits for the latest step
+const READ_INTERVAL_STEPS: u64 = 30;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReactionKind {
+    /// Index of reaction in matter definition
+    Reaction(usize),
+    Decay,
+    /// Matter emitted to a neighbor when decaying
+    Emission,
+}
+
+/// How many times a reaction of a matter occurred
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ReactionCount {
+    pub matter: u32,
+    pub kind: ReactionKind,
+    /// Matter the cell became (or was emitted)
+    pub becomes: u32,
+    pub count: u64,
+}
+
+/// Cells of a matter created & destroyed by reactions
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MatterBalance {
+    pub matter: u32,
+    pub created: u64,
+    pub destroyed: u64,
+}
+
+/// Reaction counts accumulated from gpu over simulation steps, revealing which reactions dominate
+/// when balancing custom matters
+pub struct ReactionStats {
+    counts: Vec<u64>,
+    /// Simulation steps counts were accumulated over
+    pub steps: u64,
+    last_step: u64,
+}
+
+impl ReactionStats {
+    pub fn new() -> ReactionStats {
+        ReactionStats {
+            counts: vec![0; MAX_NUM_MATTERS as usize * REACTION_COUNT_STRIDE],
+            steps: 0,
+            last_step: 0,
+        }
+    }
+
+    /// Add counts of steps simulated since last update, once every `READ_INTERVAL_STEPS`
+    pub fn update(&mut self, simulation: &Simulation) -> Result<()> {
+        if simulation.step_count >= self.last_step
+            && simulation.step_count - self.last_step < READ_INTERVAL_STEPS
+        {
+            return Ok(());
+        }
+        simulation.take_reaction_counts(&mut self.counts)?;
+        self.steps += simulation.step_count.saturating_sub(self.last_step);
+        self.last_step = simulation.step_count;
+        Ok(())
+    }
+
+    /// Start counting over, also discarding counts accumulated on gpu meanwhile
+    pub fn reset(&mut self, simulation: &Simulation) -> Result<()> {
+        simulation.take_reaction_counts(&mut self.counts)?;
+        self.counts.iter_mut().for_each(|count| *count = 0);
+        self.steps = 0;
+        self.last_step = simulation.step_count;
+        Ok(())
+    }
+
+    /// Reactions that occurred, most frequent first
+    pub fn reactions(&self, matter_definitions: &MatterDefinitions) -> Vec<ReactionCount> {
+        let mut reactions = vec![];
+        for (matter, definition) in matter_definitions.definitions.iter().enumerate() {
+            let counts = &self.counts[matter * REACTION_COUNT_STRIDE..][..REACTION_COUNT_STRIDE];
+            for (index, count) in counts.iter().enumerate().filter(|(_, count)| **count > 0) {
+                let (kind, becomes) = match index {
+                    DECAY_COUNT_INDEX => (ReactionKind::Decay, definition.decay.becomes),
+                    EMISSION_COUNT_INDEX => (ReactionKind::Emission, definition.decay.emits),
+                    _ => (ReactionKind::Reaction(index), definition.reactions[index].becomes),
+                };
+                reactions.push(ReactionCount {
+                    matter: matter as u32,
+                    kind,
+                    becomes,
+                    count: *count,
+                });
+            }
+        }
+        reactions.sort_by_key(|reaction| Reverse(reaction.count));
+        reactions
+    }
+
+    /// Cells created & destroyed per matter, for matters reactions changed
+    pub fn balances(&self, matter_definitions: &MatterDefinitions) -> Vec<MatterBalance> {
+        let mut balances = (0..matter_definitions.definitions.len() as u32)
+            .map(|matter| MatterBalance {
+                matter,
+                created: 0,
+                destroyed: 0,
+            })
+            .collect::<Vec<MatterBalance>>();
+        for reaction in self.reactions(matter_definitions) {
+            if reaction.kind == ReactionKind::Emission {
+                // Emission fills an empty cell, the emitter stays
+                balances[matter_definitions.empty as usize].destroyed += reaction.count;
+            } else if reaction.becomes != reaction.matter {
+                balances[reaction.matter as usize].destroyed += reaction.count;
+            } else {
+                continue;
+            }
+            balances[reaction.becomes as usize].created += reaction.count;
+        }
+        balances.retain(|b| b.created > 0 || b.destroyed > 0);
+        balances
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matter::{
+        default_matter_definitions, MATTER_RADIATION, MATTER_RADIUM, MATTER_URANIUM,
+    };
+
+    #[test]
+    fn test_reaction_balances() {
+        let matter_definitions = default_matter_definitions();
+        let mut stats = ReactionStats::new();
+        let uranium = MATTER_URANIUM as usize * REACTION_COUNT_STRIDE;
+        stats.counts[uranium + DECAY_COUNT_INDEX] = 3;
+        stats.counts[uranium + EMISSION_COUNT_INDEX] = 2;
+        let reactions = stats.reactions(&matter_definitions);
+        assert_eq!(reactions.len(), 2);
+        assert_eq!(reactions[0].kind, ReactionKind::Decay);
+        assert_eq!(reactions[0].becomes, MATTER_RADIUM);
+        let balances = stats.balances(&matter_definitions);
+        let balance = |matter| *balances.iter().find(|b| b.matter == matter).unwrap();
+        assert_eq!(balance(MATTER_URANIUM).destroyed, 3);
+        assert_eq!(balance(MATTER_RADIUM).created, 3);
+        assert_eq!(balance(MATTER_RADIATION).created, 2);
+        assert_eq!(balance(matter_definitions.empty).destroyed, 2);
+    }
+}
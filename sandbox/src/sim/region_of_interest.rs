@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use cgmath::Vector2;
+
+use crate::{HALF_CANVAS, SIM_CANVAS_SIZE};
+
+/// Width & height of a region of interest tile in cells. Must match
+/// compute_shaders/simulation/includes.glsl
+pub const ROI_TILE_SIZE: i32 = 32;
+/// Tile flags of `tile_activity` buffer. Must match compute_shaders/simulation/includes.glsl
+pub const TILE_ACTIVE: u32 = 1;
+pub const TILE_CHANGED: u32 = 2;
+/// Steps a tile is simulated after its matter last changed
+const KEEP_AWAKE_STEPS: u32 = 60;
+
+/// Tiles simulated when simulation is restricted to region of interest: tiles overlapping focus
+/// areas (around camera, cursor & dynamic objects) and tiles whose matter keeps changing. Other
+/// tiles are frozen on gpu. Waking spreads to neighbor tiles, so flows continue outwards
+pub struct RegionOfInterest {
+    /// Steps left awake by tile (canvas pos / `ROI_TILE_SIZE`)
+    awake: HashMap<Vector2<i32>, u32>,
+    /// Origin tile flags were last written with
+    origin: Option<Vector2<i32>>,
+}
+
+impl RegionOfInterest {
+    pub fn new() -> RegionOfInterest {
+        RegionOfInterest {
+            awake: HashMap::new(),
+            origin: None,
+        }
+    }
+
+    /// Tiles covering sim canvas. Tiles are aligned to canvas rather than sim area, thus one
+    /// extra row & column
+    pub fn tiles() -> Vector2<i32> {
+        Vector2::new(
+            SIM_CANVAS_SIZE.x as i32 / ROI_TILE_SIZE + 1,
+            SIM_CANVAS_SIZE.y as i32 / ROI_TILE_SIZE + 1,
+        )
+    }
+
+    /// Canvas pos of the corner of tile 0 for sim area at `sim_pos_offset`
+    pub fn origin(sim_pos_offset: Vector2<i32>) -> Vector2<i32> {
+        let min = sim_pos_offset - *HALF_CANVAS;
+        Vector2::new(
+            min.x.div_euclid(ROI_TILE_SIZE) * ROI_TILE_SIZE,
+            min.y.div_euclid(ROI_TILE_SIZE) * ROI_TILE_SIZE,
+        )
+    }
+
+    /// Wake tiles changed in latest step by their flags, then write flags for the next step.
+    /// `focus` are canvas rectangles (min, max) simulated regardless of changes
+    pub fn update(
+        &mut self,
+        tile_flags: &mut [u32],
+        origin: Vector2<i32>,
+        focus: &[(Vector2<i32>, Vector2<i32>)],
+    ) {
+        let tiles = Self::tiles();
+        let tile_at = |origin: Vector2<i32>, index: usize| {
+            origin / ROI_TILE_SIZE + Vector2::new(index as i32 % tiles.x, index as i32 / tiles.x)
+        };
+        self.awake.retain(|_, steps| {
+            *steps -= 1;
+            *steps > 0
+        });
+        if let Some(prev_origin) = self.origin {
+            for (index, _) in tile_flags
+                .iter()
+                .enumerate()
+                .filter(|(_, flags)| **flags & TILE_CHANGED != 0)
+            {
+                let tile = tile_at(prev_origin, index);
+                for y in -1..=1 {
+                    for x in -1..=1 {
+                        self.awake.insert(tile + Vector2::new(x, y), KEEP_AWAKE_STEPS);
+                    }
+                }
+            }
+        }
+        let focus_tiles = focus
+            .iter()
+            .map(|(min, max)| {
+                (
+                    min.map(|c| c.div_euclid(ROI_TILE_SIZE)),
+                    max.map(|c| c.div_euclid(ROI_TILE_SIZE)),
+                )
+            })
+            .collect::<Vec<_>>();
+        for (index, flags) in tile_flags.iter_mut().enumerate() {
+            let tile = tile_at(origin, index);
+            let is_focused = focus_tiles.iter().any(|(min, max)| {
+                tile.x >= min.x && tile.x <= max.x && tile.y >= min.y && tile.y <= max.y
+            });
+            *flags = if is_focused || self.awake.contains_key(&tile) {
+                TILE_ACTIVE
+            } else {
+                0
+            };
+        }
+        self.origin = Some(origin);
+    }
+
+    /// Simulate all tiles, forgetting awake tiles
+    pub fn clear(&mut self, tile_flags: &mut [u32]) {
+        self.awake.clear();
+        self.origin = None;
+        tile_flags.iter_mut().for_each(|flags| *flags = TILE_ACTIVE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_tiles_stay_awake() {
+        let tiles = RegionOfInterest::tiles();
+        let mut flags = vec![0; (tiles.x * tiles.y) as usize];
+        let mut roi = RegionOfInterest::new();
+        let origin = RegionOfInterest::origin(Vector2::new(0, 0));
+        let focus = [(origin, origin + Vector2::new(1, 1))];
+        roi.update(&mut flags, origin, &focus);
+        assert_eq!(flags[0], TILE_ACTIVE);
+        assert_eq!(flags.iter().filter(|f| **f == TILE_ACTIVE).count(), 1);
+        // Focus moves away, but tile 0 changed & wakes its neighbors
+        flags[0] |= TILE_CHANGED;
+        roi.update(&mut flags, origin, &[]);
+        assert_eq!(flags[0], TILE_ACTIVE);
+        assert_eq!(flags[1], TILE_ACTIVE);
+        assert_eq!(flags[tiles.x as usize + 1], TILE_ACTIVE);
+        assert_eq!(flags[2], 0);
+        for _ in 0..KEEP_AWAKE_STEPS {
+            roi.update(&mut flags, origin, &[]);
+        }
+        assert!(flags.iter().all(|f| *f == 0));
+    }
+}
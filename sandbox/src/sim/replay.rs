@@ -0,0 +1,116 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{data_dirs::DATA_DIRS, utils::data_file_path};
+
+/// Longest replay in steps, 10 minutes at 60 steps per second. Sessions past it stop recording
+/// so step seeds don't grow without bound
+pub const MAX_REPLAY_STEPS: usize = 10 * 60 * 60;
+
+/// Shader seeds of each step since a map was loaded. Loading the map again & stepping with the
+/// recorded seeds replays the session deterministically, as long as nothing is edited meanwhile.
+/// Saved in replays of data directory
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub map_name: String,
+    pub world_seed: u64,
+    /// Seed of each compute chunk per step, see `chunk_step_seed`
+    pub step_seeds: Vec<[f32; 4]>,
+    /// Matter cell counts after the last step, replay is checked against these
+    pub matter_counts: Vec<u64>,
+    /// Steps replayed so far
+    #[serde(skip)]
+    next_step: usize,
+}
+
+impl Replay {
+    pub fn new(
+        map_name: &str,
+        world_seed: u64,
+        step_seeds: Vec<[f32; 4]>,
+        matter_counts: Vec<u64>,
+    ) -> Replay {
+        Replay {
+            map_name: map_name.to_string(),
+            world_seed,
+            step_seeds,
+            matter_counts,
+            next_step: 0,
+        }
+    }
+
+    pub fn load(name: &str) -> Result<Replay> {
+        let data = fs::read_to_string(replay_path(name)?)?;
+        serde_json::from_str(&data).with_context(|| format!("Invalid replay {}", name))
+    }
+
+    /// Save as map name & step count. Returns name of the replay
+    pub fn save(&self) -> Result<String> {
+        let name = format!("{}_{}", self.map_name, self.step_seeds.len());
+        fs::write(replay_path(&name)?, serde_json::to_string(self)?)?;
+        Ok(name)
+    }
+
+    /// Seeds of next replayed step, None once all steps have been replayed
+    pub fn next_seeds(&mut self) -> Option<[f32; 4]> {
+        let seeds = self.step_seeds.get(self.next_step).copied();
+        if seeds.is_some() {
+            self.next_step += 1;
+        }
+        seeds
+    }
+
+    pub fn progress(&self) -> (usize, usize) {
+        (self.next_step, self.step_seeds.len())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next_step == self.step_seeds.len()
+    }
+}
+
+/// Names of saved replays, sorted
+pub fn replay_names() -> Result<Vec<String>> {
+    let mut names = vec![];
+    for entry in fs::read_dir(replays_dir()?)? {
+        let path = entry?.path();
+        if path.extension().map_or(false, |ext| ext == "json") {
+            if let Some(name) = path.file_stem() {
+                names.push(name.to_string_lossy().to_string());
+            }
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+fn replays_dir() -> Result<PathBuf> {
+    let dir_path = DATA_DIRS.data.join("replays");
+    fs::create_dir_all(&dir_path)?;
+    Ok(dir_path)
+}
+
+/// Replay file by name, which can't lead outside replays directory
+fn replay_path(name: &str) -> Result<PathBuf> {
+    data_file_path(&replays_dir()?, name, "json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_steps_recorded_seeds() {
+        let replay = Replay::new("Test", 7, vec![[1.0; 4], [2.0; 4]], vec![10, 5]);
+        let mut replay = serde_json::from_str::<Replay>(&serde_json::to_string(&replay).unwrap())
+            .unwrap();
+        assert_eq!(replay.next_seeds(), Some([1.0; 4]));
+        assert!(!replay.is_finished());
+        assert_eq!(replay.next_seeds(), Some([2.0; 4]));
+        assert!(replay.is_finished());
+        assert_eq!(replay.next_seeds(), None);
+        assert_eq!(replay.progress(), (2, 2));
+    }
+}
@@ -0,0 +1,124 @@
+use cgmath::{MetricSpace, Vector2};
+use serde::{Deserialize, Serialize};
+
+/// Shape of matter spawned with `Simulation::spawn_shape`, in cells relative to its position
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Shape {
+    /// Cells within radius, like round brush
+    Circle { radius: f32 },
+    /// Centered rectangle, like stamps
+    Rect { width: i32, height: i32 },
+    /// Cells whose center is inside the polygon. Vertices are relative to position
+    Polygon(Vec<Vector2<f32>>),
+}
+
+impl Shape {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Shape::Circle {
+                ..
+            } => "Circle",
+            Shape::Rect {
+                ..
+            } => "Rect",
+            Shape::Polygon(_) => "Polygon",
+        }
+    }
+
+    /// Canvas positions of shape's cells at `pos`
+    pub fn cells(&self, pos: Vector2<i32>) -> Vec<Vector2<i32>> {
+        match self {
+            Shape::Circle {
+                radius,
+            } => {
+                let r = *radius as i32;
+                let center = Vector2::new(pos.x as f32, pos.y as f32);
+                (pos.y - r..=pos.y + r)
+                    .flat_map(|y| (pos.x - r..=pos.x + r).map(move |x| Vector2::new(x, y)))
+                    .filter(|cell| {
+                        let cell_f32 = Vector2::new(cell.x as f32, cell.y as f32);
+                        cell_f32.distance(center).round() <= *radius
+                    })
+                    .collect()
+            }
+            Shape::Rect {
+                width,
+                height,
+            } => {
+                let min = pos - Vector2::new(width / 2, height - 1 - height / 2);
+                (min.y..min.y + height)
+                    .flat_map(|y| (min.x..min.x + width).map(move |x| Vector2::new(x, y)))
+                    .collect()
+            }
+            Shape::Polygon(vertices) => {
+                if vertices.len() < 3 {
+                    return vec![];
+                }
+                let (min, max) = vertices
+                    .iter()
+                    .fold((vertices[0], vertices[0]), |(min, max), v| {
+                        (
+                            Vector2::new(min.x.min(v.x), min.y.min(v.y)),
+                            Vector2::new(max.x.max(v.x), max.y.max(v.y)),
+                        )
+                    });
+                let (x_start, x_end) = (min.x.floor() as i32, max.x.ceil() as i32);
+                let (y_start, y_end) = (min.y.floor() as i32, max.y.ceil() as i32);
+                (y_start..=y_end)
+                    .flat_map(|y| (x_start..=x_end).map(move |x| Vector2::new(x, y)))
+                    .filter(|cell| {
+                        is_inside_polygon(vertices, Vector2::new(cell.x as f32, cell.y as f32))
+                    })
+                    .map(|cell| pos + cell)
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Whether point is inside polygon, by even-odd rule
+pub fn is_inside_polygon(vertices: &[Vector2<f32>], point: Vector2<f32>) -> bool {
+    let mut inside = false;
+    let mut j = vertices.len() - 1;
+    for i in 0..vertices.len() {
+        let (a, b) = (vertices[i], vertices[j]);
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x
+        {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shape_cells() {
+        let pos = Vector2::new(10, -5);
+        let rect = Shape::Rect {
+            width: 3,
+            height: 2,
+        };
+        assert_eq!(rect.cells(pos).len(), 6);
+        assert!(rect.cells(pos).contains(&Vector2::new(9, -5)));
+        assert!(rect.cells(pos).contains(&Vector2::new(11, -4)));
+        let circle = Shape::Circle {
+            radius: 0.5,
+        };
+        assert_eq!(circle.cells(pos), vec![pos]);
+        // Triangle covering cells x + y <= 3
+        let triangle = Shape::Polygon(vec![
+            Vector2::new(-0.5, -0.5),
+            Vector2::new(4.0, -0.5),
+            Vector2::new(-0.5, 4.0),
+        ]);
+        let cells = triangle.cells(pos);
+        assert_eq!(cells.len(), 10);
+        assert!(cells.contains(&(pos + Vector2::new(3, 0))));
+        assert!(!cells.contains(&(pos + Vector2::new(3, 3))));
+    }
+}
@@ -1,42 +1,91 @@
-use std::{collections::BTreeMap, env::current_dir, fs, path::PathBuf, sync::Arc};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{mpsc::channel, Arc},
+    time::Instant,
+};
 
 use anyhow::*;
-use cgmath::{MetricSpace, Vector2};
+use cgmath::{InnerSpace, MetricSpace, Vector2};
 use corrode::{
-    api::{remove_physics_entity, EngineApi},
+    api::{physics_entity_at_pos, remove_physics_entity, EngineApi},
     physics::PhysicsWorld,
+    renderer::{Line, LineBatch},
     time::PerformanceTimer,
 };
 use hecs::{Entity, World};
+use rand::Rng;
 use rapier2d::prelude::*;
 use rayon::{
     iter::{IntoParallelIterator, ParallelIterator},
     prelude::IntoParallelRefIterator,
 };
-use vulkano::{device::Queue, format::Format};
+use vulkano::{
+    device::Queue,
+    format::Format,
+    sync::{FenceSignalFuture, GpuFuture},
+};
 
 use crate::{
     app::InputAction,
+    data_dirs::DATA_DIRS,
     map_path,
-    matter::{MatterDefinition, MatterDefinitions, MatterState},
+    matter::{
+        matter_reference_markdown, ImpactSound, MatterCharacteristic, MatterDefinition,
+        MatterDefinitions, MatterPack, MatterState, PackImport,
+    },
+    mods::without_mod_matters,
     object::{
-        collider_from_convex_decomposition, dynamic_pixel_object,
+        boundary_interaction_groups, collider_from_convex_decomposition, dynamic_pixel_object,
         extract_connected_components_from_bitmap, form_contour_vertices,
         form_pixel_data_with_contours_from_image, invisible_sensor_object, invisible_static_object,
+        make_pixel_object_fixed, set_collider_groups, set_pixel_object_collision,
         update_after_physics, Angle, AngularVelocity, DeformedObjectData,
-        DynamicPixelObjectCreationData, InvisibleObject, LinearVelocity, PixelData,
-        PixelObjectSaveDataArray, Position, TempPixel,
+        DynamicPixelObjectCreationData, FixedObject, LinearVelocity, ObjectCollision, PixelData,
+        PixelObjectSaveDataArray, Position, StaticRigidbody, TempPixel,
     },
+    render::ParticleSystem,
     settings::AppSettings,
     sim::{
-        boundaries::PhysicsBoundaries, create_boundary_object_data, get_alive_pixels,
-        is_inside_sim_canvas, sim_canvas_index, sim_chunk_canvas_index, world_pos_to_canvas_pos,
-        CASimulator, SimulationChunkManager,
+        anchor_joint,
+        boundaries::{take_changed_regions, PhysicsBoundaries},
+        can_support_anchor, canvas_pos_to_world_pos, chunk_file_name, chunk_matter_grid,
+        create_boundaries_object_data, explosion_impulse, is_inside_sim_canvas, migrate_map,
+        nearest_matter, nearest_matter_cell, object_local_anchor, raycast_cells, rests_on_terrain,
+        rope_length, scanline_flood_fill, select_debris_over_budget, sim_canvas_index,
+        world_pos_inside_canvas, world_pos_to_canvas_pos, write_alive_pixels, Anchor, Annotations,
+        AsleepSince, BoundaryObjectData, CASimulator, ChunkGrid, ChunkSnapshot, Debris,
+        DebrisPolicy, DetailUpscaler, EntityChunkIndex, Explosions, GpuTimings, ImpactSoundEvent,
+        ObjectJoint, ObjectJointKind, OreVeins, PaintStroke, Portals, RayHit, Replay, Shape,
+        SimulationChunkManager, SoundEvent, SpawnStep, TriggerAction, TriggerZones, WorldChunk,
+        ANCHOR_SEARCH_RADIUS, ANCHOR_SUPPORT_RADIUS, CHUNK_FILE_EXTENSION, DEBRIS_MAX_PIXELS,
+        DEBRIS_REST_SPEED, IMPACT_SAMPLE_RADIUS, MAX_REPLAY_STEPS, MIN_IMPACT_IMPULSE,
+        MIN_SPLASH_SPEED, ROI_TILE_SIZE,
+    },
+    utils::{
+        load_bitmap_image_from_path, matter_definitions_path, rotate_radians, BitmapImage,
+        CanvasMouseState, Color32,
     },
-    utils::{load_image_from_file_bytes, rotate_radians, BitmapImage, CanvasMouseState},
-    CELL_UNIT_SIZE, SIM_CANVAS_SIZE, WORLD_UNIT_SIZE,
+    BITMAP_RATIO, CANVAS_CHUNK_SIZE, CELL_UNIT_SIZE, CHAR_PER_STEP, CONVEYOR_FORCE,
+    EXPLOSION_IMPULSE, EXPLOSION_RADIUS, FAN_FORCE, FAN_REACH, HALF_CANVAS,
+    PORTAL_MAX_OBJECT_PIXELS, SIM_CANVAS_SIZE, WORLD_UNIT_SIZE,
 };
 
+/// Buffers reused between frames on hot paths, so they don't allocate every frame
+#[derive(Default)]
+pub struct SimulationScratch {
+    /// Debug contour lines
+    pub lines: Vec<Line>,
+    /// Uploaded debug lines of boundary objects, valid for one boundary version
+    pub boundary_lines: LineBatch,
+    /// Collisions started during latest physics step
+    collisions: Vec<(ColliderHandle, ColliderHandle)>,
+    /// Reactions emitted for particles during latest step
+    particle_emissions: Vec<u32>,
+}
+
 pub struct Simulation {
     ca_simulator: CASimulator,
     pub boundaries: PhysicsBoundaries,
@@ -45,14 +94,51 @@ pub struct Simulation {
     pub camera_pos: Vector2<f32>,
     pub camera_canvas_pos: Vector2<i32>,
     pub chunk_manager: SimulationChunkManager,
+    /// 2x detail images of chunks, updated before render when enabled
+    pub detail_upscaler: DetailUpscaler,
+    pub particles: ParticleSystem,
+    /// Whether particles were on last step. Emissions pile up on gpu while they're off
+    particles_were_on: bool,
     tmp_object_ids: Vec<Vec<Entity>>,
+    /// Pixel objects by chunk, updated when they move
+    object_chunk_index: EntityChunkIndex,
+    pub scratch: SimulationScratch,
     pub loaded_obj_images: BTreeMap<u32, Arc<BitmapImage>>,
 
     pub matter_definitions: MatterDefinitions,
+    /// Matter whose cells brush & reactions don't overwrite, toggled in editor
+    protected_matters: BTreeSet<u32>,
+    /// Names of matters added by mods, which aren't saved with base matter definitions
+    pub mod_matters: BTreeSet<String>,
+    pub trigger_zones: TriggerZones,
+    pub portals: Portals,
+    pub annotations: Annotations,
+    pub explosions: Explosions,
+    /// Impact & trigger sounds of latest physics step
+    pub sound_events: Vec<SoundEvent>,
+    /// Steps simulated, used to age dynamic objects
+    pub step_count: u64,
+    /// World positions of objects deformed or destroyed by latest finished step
+    pub deformed_object_positions: Vec<Vector2<f32>>,
+    /// Objects anchored to terrain
+    pub anchors: Vec<Anchor>,
+    /// World positions of objects to anchor or release on next `finish_step`
+    anchor_requests: Vec<Vector2<f32>>,
+    /// Hinges & ropes between objects
+    pub object_joints: Vec<ObjectJoint>,
+    /// Ore veins to generate once chunks have loaded, e.g. on a new map
+    pub pending_ore_veins: Option<OreVeins>,
+    /// Shader seeds of steps since map was loaded, see `Replay`. None once there were more than
+    /// `MAX_REPLAY_STEPS`
+    step_seeds: Option<Vec<[f32; 4]>>,
+    /// Replay whose seeds next steps use
+    pub replay: Option<Replay>,
 
+    pub chunk_timer: PerformanceTimer,
     pub obj_write_timer: PerformanceTimer,
     pub obj_read_timer: PerformanceTimer,
     pub ca_timer: PerformanceTimer,
+    pub ca_wait_timer: PerformanceTimer,
     pub boundary_timer: PerformanceTimer,
     pub physics_timer: PerformanceTimer,
 }
@@ -60,13 +146,14 @@ pub struct Simulation {
 impl Simulation {
     pub fn new(
         comp_queue: Arc<Queue>,
+        transfer_queue: Arc<Queue>,
         matter_definitions: MatterDefinitions,
         image_format: Format,
     ) -> Result<Simulation> {
         let mut ca_simulator = CASimulator::new(comp_queue.clone(), matter_definitions.empty)?;
-        ca_simulator.update_matter_data(&matter_definitions)?;
+        ca_simulator.update_matter_data(&matter_definitions, &BTreeSet::new())?;
         let tmp_object_ids: Vec<Vec<Entity>> =
-            vec![vec![]; (*SIM_CANVAS_SIZE * *SIM_CANVAS_SIZE) as usize];
+            vec![vec![]; (SIM_CANVAS_SIZE.x * SIM_CANVAS_SIZE.y) as usize];
 
         Ok(Simulation {
             ca_simulator,
@@ -74,37 +161,153 @@ impl Simulation {
             object_pixel_query: None,
             camera_pos: Vector2::new(0.0, 0.0),
             camera_canvas_pos: Vector2::new(0, 0),
-            chunk_manager: SimulationChunkManager::new(comp_queue, image_format)?,
+            detail_upscaler: DetailUpscaler::new(comp_queue.clone(), image_format)?,
+            particles: ParticleSystem::new(comp_queue.clone(), image_format)?,
+            particles_were_on: false,
+            chunk_manager: SimulationChunkManager::new(comp_queue, transfer_queue, image_format)?,
             tmp_object_ids,
+            object_chunk_index: EntityChunkIndex::new(),
+            scratch: SimulationScratch::default(),
             loaded_obj_images: BTreeMap::new(),
             matter_definitions,
+            protected_matters: BTreeSet::new(),
+            mod_matters: BTreeSet::new(),
+            trigger_zones: TriggerZones::new(),
+            portals: Portals::new(),
+            annotations: Annotations::new(),
+            explosions: Explosions::new(),
+            sound_events: vec![],
+            step_count: 0,
+            deformed_object_positions: vec![],
+            anchors: vec![],
+            anchor_requests: vec![],
+            object_joints: vec![],
+            pending_ore_veins: None,
+            step_seeds: Some(vec![]),
+            replay: None,
+            chunk_timer: PerformanceTimer::new(),
             obj_write_timer: PerformanceTimer::new(),
             obj_read_timer: PerformanceTimer::new(),
             ca_timer: PerformanceTimer::new(),
+            ca_wait_timer: PerformanceTimer::new(),
             boundary_timer: PerformanceTimer::new(),
             physics_timer: PerformanceTimer::new(),
         })
     }
 
+    pub fn world_seed(&self) -> u64 {
+        self.ca_simulator.world_seed()
+    }
+
+    /// Gpu time per pass of CA steps, None if gpu doesn't support timestamps
+    pub fn gpu_timings(&self) -> Option<&GpuTimings> {
+        self.ca_simulator.gpu_timings()
+    }
+
+    /// Seed randomness of the simulation, e.g. by `SimulationSeed` on a new map
+    pub fn set_world_seed(&mut self, world_seed: u64) -> Result<()> {
+        self.ca_simulator.wait_compute()?;
+        self.ca_simulator.set_world_seed(world_seed);
+        self.step_seeds = Some(vec![]);
+        Ok(())
+    }
+
+    /// Replay of steps since `map_name` was loaded. Fails past `MAX_REPLAY_STEPS`
+    pub fn record_replay(&self, map_name: &str) -> Result<Replay> {
+        let step_seeds = self.step_seeds.clone().with_context(|| {
+            format!(
+                "Replays are recorded for {} steps after loading a map, load it again to record",
+                MAX_REPLAY_STEPS
+            )
+        })?;
+        Ok(Replay::new(
+            map_name,
+            self.world_seed(),
+            step_seeds,
+            self.matter_counts()?,
+        ))
+    }
+
+    /// Whether a finished replay reproduced its recorded matter counts. Checked once its last step
+    /// has been read back, after which replay ends
+    pub fn check_replay(&mut self) -> Result<Option<bool>> {
+        match &self.replay {
+            Some(replay) if replay.is_finished() => {
+                let matched = self.matter_counts()? == replay.matter_counts;
+                self.replay = None;
+                Ok(Some(matched))
+            }
+            _ => Ok(None),
+        }
+    }
+
     pub fn reset(&mut self, image_format: Format) -> Result<()> {
+        // Protection is an editing preference, so it's kept across maps
+        let protected_matters = std::mem::take(&mut self.protected_matters);
+        let mod_matters = std::mem::take(&mut self.mod_matters);
         *self = Simulation::new(
             self.chunk_manager.queue.clone(),
+            self.chunk_manager.transfer_queue.clone(),
             self.matter_definitions.clone(),
             image_format,
         )?;
+        self.protected_matters = protected_matters;
+        self.mod_matters = mod_matters;
+        self.update_matter_data()
+    }
+
+    fn update_matter_data(&mut self) -> Result<()> {
+        self.ca_simulator
+            .update_matter_data(&self.matter_definitions, &self.protected_matters)
+    }
+
+    pub fn is_matter_protected(&self, matter: u32) -> bool {
+        self.protected_matters.contains(&matter)
+    }
+
+    /// Protect cells of `matter` from being overwritten by brush & reactions, or stop doing so
+    pub fn set_matter_protected(&mut self, matter: u32, protected: bool) -> Result<()> {
+        if protected {
+            self.protected_matters.insert(matter);
+        } else {
+            self.protected_matters.remove(&matter);
+        }
+        self.update_matter_data()
+    }
+
+    /// Recreate gpu resources on a new device after the previous one was lost. Simulated chunks
+    /// are reloaded from their cpu images, see `SimulationChunkManager::recreate_gpu`
+    pub fn recreate_gpu(
+        &mut self,
+        comp_queue: Arc<Queue>,
+        transfer_queue: Arc<Queue>,
+        image_format: Format,
+    ) -> Result<()> {
+        let world_seed = self.world_seed();
+        self.ca_simulator = CASimulator::new(comp_queue.clone(), self.matter_definitions.empty)?;
+        self.update_matter_data()?;
+        self.ca_simulator.set_world_seed(world_seed);
+        self.detail_upscaler = DetailUpscaler::new(comp_queue.clone(), image_format)?;
+        self.particles = ParticleSystem::new(comp_queue.clone(), image_format)?;
+        self.chunk_manager.recreate_gpu(
+            comp_queue,
+            transfer_queue,
+            image_format,
+            self.camera_canvas_pos,
+            &self.matter_definitions,
+        )?;
+        self.scratch = SimulationScratch::default();
         Ok(())
     }
 
     /// 1. Write objects to CA grid
-    /// 2. Step CA (multiple steps if needed). Updates solid etc. bitmaps
-    /// 3. Remove object pixels from grid
-    /// 4. Form contours for new deformed physics objects
-    /// 5. Step physics simulation
+    /// 2. Submit CA step (multiple steps if needed) to gpu without waiting for it
+    /// Results are read back in `finish_step` at the start of next frame, so gpu compute overlaps
+    /// with rendering of the current frame
     pub fn step(
         &mut self,
         api: &mut EngineApi<InputAction>,
         settings: AppSettings,
-        canvas_mouse_state: &CanvasMouseState,
     ) -> Result<()> {
         // If we intend to move in the world via chunked simulation
         if settings.chunked_simulation {
@@ -115,17 +318,111 @@ impl Simulation {
             Vector2::new(canvas_pos_f32.x as i32, canvas_pos_f32.y as i32)
         };
 
-        self.chunk_manager
-            .update_chunks(self.camera_canvas_pos, &self.matter_definitions)?;
+        self.chunk_timer.start();
+        self.chunk_manager.update_chunks(
+            self.camera_canvas_pos,
+            settings.chunk_streaming,
+            &self.matter_definitions,
+        )?;
+        self.chunk_timer.time_it();
 
         self.obj_write_timer.start();
+        self.enforce_object_budgets(api, &settings)?;
+        if settings.freeze_resting_objects {
+            self.freeze_resting_objects(api, &settings)?;
+        }
         self.write_pixel_objects_to_grid(api)?;
+        // Chunk uploads ran on transfer queue while objects were written
+        self.chunk_manager.wait_transfers()?;
+        if let Some(ore_veins) = self.pending_ore_veins.take() {
+            self.generate_ore_veins(&ore_veins)?;
+        }
+        self.apply_terrain_forces(api)?;
+        self.char_burning_objects(api)?;
+        self.apply_explosions_to_grid()?;
         self.obj_write_timer.time_it();
 
+        if let Some(seeds) = self.replay.as_mut().and_then(|replay| replay.next_seeds()) {
+            self.ca_simulator.set_next_step_seeds(seeds);
+        }
+        let focus = if settings.region_of_interest {
+            self.region_of_interest_focus(api, settings.region_of_interest_radius as i32)
+        } else {
+            vec![]
+        };
         self.ca_timer.start();
-        self.ca_simulator
-            .step(settings, self.camera_canvas_pos, &mut self.chunk_manager)?;
+        self.ca_simulator.step(
+            settings,
+            self.camera_canvas_pos,
+            &mut self.chunk_manager,
+            &self.portals,
+            &focus,
+        )?;
         self.ca_timer.time_it();
+        let is_replay_full =
+            matches!(&self.step_seeds, Some(seeds) if seeds.len() >= MAX_REPLAY_STEPS);
+        if is_replay_full {
+            self.step_seeds = None;
+        }
+        if let Some(step_seeds) = &mut self.step_seeds {
+            step_seeds.push(self.ca_simulator.chunk_seeds());
+        }
+        self.step_count += 1;
+
+        Ok(())
+    }
+
+    /// Canvas rectangles (min, max) always simulated in region of interest mode: around camera,
+    /// cursor & awake dynamic objects
+    fn region_of_interest_focus(
+        &self,
+        api: &mut EngineApi<InputAction>,
+        radius: i32,
+    ) -> Vec<(Vector2<i32>, Vector2<i32>)> {
+        let around = |pos: Vector2<i32>, radius: i32| {
+            (pos - Vector2::new(radius, radius), pos + Vector2::new(radius, radius))
+        };
+        let camera_pos = world_pos_to_canvas_pos(api.main_camera.pos());
+        let mouse_pos = CanvasMouseState::new(&api.main_camera, &api.inputs[0]).mouse_on_canvas;
+        let mut focus = vec![
+            around(camera_pos.cast::<i32>().unwrap(), radius),
+            around(mouse_pos, radius),
+        ];
+        let EngineApi {
+            ecs_world,
+            physics_world,
+            ..
+        } = api;
+        for (_id, (rb, pos, pixel_data)) in
+            ecs_world.query_mut::<(&RigidBodyHandle, &Position, &PixelData)>()
+        {
+            let rigid_body = &physics_world.physics.bodies[*rb];
+            if rigid_body.is_dynamic() && !rigid_body.is_sleeping() {
+                let extent = pixel_data.width.max(pixel_data.height) as i32 / 2;
+                let canvas_pos = world_pos_to_canvas_pos(pos.0).cast::<i32>().unwrap();
+                focus.push(around(canvas_pos, extent + ROI_TILE_SIZE));
+            }
+        }
+        focus
+    }
+
+    /// Wait for the step submitted last frame (if any) and read its results back
+    /// 1. Remove object pixels from grid
+    /// 2. Form contours for new deformed physics objects
+    /// 3. Update physics boundaries from CA bitmaps (a frame late if formed in the background)
+    /// 4. Step physics simulation
+    pub fn finish_step(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        settings: AppSettings,
+        canvas_mouse_state: &CanvasMouseState,
+    ) -> Result<()> {
+        self.ca_wait_timer.start();
+        let has_results = self.ca_simulator.finish_compute()?;
+        if !has_results {
+            return Ok(());
+        }
+        self.ca_wait_timer.time_it();
 
         self.object_pixel_query = self.query_object(canvas_mouse_state.mouse_on_canvas)?;
 
@@ -134,18 +431,517 @@ impl Simulation {
         self.obj_read_timer.time_it();
 
         self.boundary_timer.start();
-        self.update_physics_boundaries(api)?;
+        self.update_physics_boundaries(
+            api,
+            settings.background_boundaries,
+            settings.boundary_collides_with,
+        )?;
         self.boundary_timer.time_it();
 
         self.physics_timer.start();
-        api.physics_world
-            .step(&api.thread_pool, |_collision_event| {});
+        self.read_explosions()?;
+        self.apply_explosion_impulses(api);
+        // Reading emissions waits for gpu, so they're left on gpu while particles are off
+        if settings.particles {
+            self.ca_simulator
+                .take_particle_emissions(&mut self.scratch.particle_emissions)?;
+            // Emissions left while particles were off are stale
+            if self.particles_were_on {
+                self.particles
+                    .queue_emissions(&self.scratch.particle_emissions, &self.matter_definitions);
+            }
+        }
+        self.particles_were_on = settings.particles;
+        self.update_anchors(api)?;
+        self.update_object_joints(api);
+        let positions_before_physics = self.small_object_positions(api);
+        let collisions = RefCell::new(std::mem::take(&mut self.scratch.collisions));
+        collisions.borrow_mut().clear();
+        api.physics_world.step(&api.thread_pool, |collision_event| {
+            if collision_event.started() {
+                collisions
+                    .borrow_mut()
+                    .push((collision_event.collider1(), collision_event.collider2()));
+            }
+        });
+        self.scratch.collisions = collisions.into_inner();
         self.update_dynamic_physics_objects(api)?;
+        self.teleport_objects_through_portals(api, positions_before_physics);
         self.physics_timer.time_it();
 
+        self.sound_events.clear();
+        self.update_impact_sounds(api)?;
+
+        self.update_trigger_zones(api)?;
+
+        Ok(())
+    }
+
+    /// Run CA steps on loaded chunks without objects or physics, waiting for each. Returns average
+    /// milliseconds per step, e.g. to pick settings by gpu throughput
+    pub fn time_ca_steps(&mut self, settings: AppSettings, steps: u32) -> Result<f64> {
+        self.chunk_manager.update_chunks(
+            self.camera_canvas_pos,
+            settings.chunk_streaming,
+            &self.matter_definitions,
+        )?;
+        self.chunk_manager.wait_transfers()?;
+        let start = Instant::now();
+        for _ in 0..steps {
+            self.ca_simulator.step(
+                settings,
+                self.camera_canvas_pos,
+                &mut self.chunk_manager,
+                &self.portals,
+                &[],
+            )?;
+            self.ca_simulator.finish_compute()?;
+        }
+        Ok(start.elapsed().as_secs_f64() * 1000.0 / steps.max(1) as f64)
+    }
+
+    /// Future of the latest CA step, rendering of chunk images must be joined with it
+    pub fn compute_future(&self) -> Option<Arc<FenceSignalFuture<Box<dyn GpuFuture>>>> {
+        self.ca_simulator.compute_future()
+    }
+
+    /// Latest step's time spent in each subsystem, used to tell what was slow on a frame spike
+    pub fn latest_timings(&self) -> [(&'static str, f64); 7] {
+        [
+            ("Chunk loading", self.chunk_timer.latest_ms()),
+            ("Obj write to grid", self.obj_write_timer.latest_ms()),
+            ("CA simulation", self.ca_timer.latest_ms()),
+            ("CA gpu wait", self.ca_wait_timer.latest_ms()),
+            ("Obj deformation", self.obj_read_timer.latest_ms()),
+            ("Boundary creation", self.boundary_timer.latest_ms()),
+            ("Physics", self.physics_timer.latest_ms()),
+        ]
+    }
+
+    /// Map collisions of objects with boundaries started in latest physics step to impact sounds
+    /// by the matter at the contact
+    fn update_impact_sounds(&mut self, api: &mut EngineApi<InputAction>) -> Result<()> {
+        if self.scratch.collisions.is_empty() {
+            return Ok(());
+        }
+        let physics = &api.physics_world.physics;
+        let definitions = &self.matter_definitions.definitions;
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let matters = chunks.read(ChunkGrid::Matter)?;
+        let matter_at = |canvas_pos: Vector2<i32>| matters.get(canvas_pos);
+        for &(handle1, handle2) in self.scratch.collisions.iter() {
+            let (collider1, collider2) =
+                match (physics.colliders.get(handle1), physics.colliders.get(handle2)) {
+                    (Some(c1), Some(c2)) => (c1, c2),
+                    _ => continue,
+                };
+            let body_of = |collider: &Collider| {
+                collider
+                    .parent()
+                    .and_then(|handle| physics.bodies.get(handle))
+            };
+            // Object first, boundary second
+            let (object, boundary) = match (body_of(collider1), body_of(collider2)) {
+                (Some(b1), Some(b2)) if b1.is_dynamic() && !b2.is_dynamic() => {
+                    ((collider1, b1), (collider2, b2))
+                }
+                (Some(b1), Some(b2)) if b2.is_dynamic() && !b1.is_dynamic() => {
+                    ((collider2, b2), (collider1, b1))
+                }
+                _ => continue,
+            };
+            let event = if boundary.0.is_sensor() {
+                // Only liquid boundaries, not trigger zones
+                let is_liquid = Entity::from_bits(boundary.1.user_data as u64)
+                    .map_or(false, |e| {
+                        self.boundaries
+                            .liquid_objects
+                            .iter()
+                            .any(|objects| objects.contains(&e))
+                    });
+                let speed = object.1.linvel().norm();
+                if !is_liquid || speed < MIN_SPLASH_SPEED {
+                    continue;
+                }
+                // Bottom of the part of object in liquid
+                let object_aabb = object.0.compute_aabb();
+                let overlap = match object_aabb.intersection(&boundary.0.compute_aabb()) {
+                    Some(overlap) => overlap,
+                    None => continue,
+                };
+                let world_pos =
+                    Vector2::new((overlap.mins.x + overlap.maxs.x) * 0.5, overlap.mins.y);
+                nearest_matter(
+                    world_pos_to_canvas_pos(world_pos).cast::<i32>().unwrap(),
+                    IMPACT_SAMPLE_RADIUS,
+                    matter_at,
+                    |m| definitions[m as usize].state == MatterState::Liquid,
+                )
+                .map(|matter| (matter, world_pos, speed))
+            } else {
+                let contact_pair = match physics.narrow_phase.contact_pair(handle1, handle2) {
+                    Some(contact_pair) => contact_pair,
+                    None => continue,
+                };
+                let impulse = contact_pair
+                    .manifolds
+                    .iter()
+                    .flat_map(|manifold| manifold.points.iter())
+                    .map(|point| point.data.impulse)
+                    .sum::<f32>();
+                let point = contact_pair
+                    .manifolds
+                    .iter()
+                    .flat_map(|manifold| manifold.data.solver_contacts.iter())
+                    .next();
+                let point = match point {
+                    Some(point) if impulse >= MIN_IMPACT_IMPULSE => point.point,
+                    _ => continue,
+                };
+                let world_pos = Vector2::new(point.x, point.y);
+                nearest_matter(
+                    world_pos_to_canvas_pos(world_pos).cast::<i32>().unwrap(),
+                    IMPACT_SAMPLE_RADIUS,
+                    matter_at,
+                    |m| {
+                        let state = definitions[m as usize].state;
+                        state == MatterState::Solid
+                            || state == MatterState::SolidGravity
+                            || state == MatterState::Powder
+                    },
+                )
+                .map(|matter| (matter, world_pos, impulse))
+            };
+            if let Some((matter, world_pos, strength)) = event {
+                let sound = definitions[matter as usize].impact_sound();
+                if sound != ImpactSound::Silent {
+                    debug!(
+                        "Impact sound {} on {} ({:.2})",
+                        sound.name(),
+                        definitions[matter as usize].name,
+                        strength
+                    );
+                    self.sound_events.push(SoundEvent::Impact(ImpactSoundEvent {
+                        sound,
+                        matter,
+                        world_pos,
+                        strength,
+                    }));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Run enter / exit actions of trigger zones & paint matter from emitting zones
+    fn update_trigger_zones(&mut self, api: &mut EngineApi<InputAction>) -> Result<()> {
+        let events = self
+            .trigger_zones
+            .collect_events(&api.ecs_world, &api.physics_world);
+        for event in events {
+            let zone = &self.trigger_zones.zones[event.zone];
+            let actions = if event.entered {
+                zone.on_enter.clone()
+            } else {
+                zone.on_exit.clone()
+            };
+            let zone_canvas_pos = world_pos_to_canvas_pos(zone.pos).cast::<i32>().unwrap();
+            for action in actions {
+                match action {
+                    TriggerAction::SpawnMatter {
+                        matter,
+                        radius,
+                    } => {
+                        self.queue_paint_round(&[zone_canvas_pos], matter, radius);
+                    }
+                    TriggerAction::SpawnShape {
+                        matter,
+                        shape,
+                    } => {
+                        self.spawn_shape(&shape, matter, zone_canvas_pos)?;
+                    }
+                    TriggerAction::DeleteObjects => {
+                        if api.ecs_world.contains(event.entity) {
+                            remove_physics_entity(
+                                &mut api.ecs_world,
+                                &mut api.physics_world,
+                                event.entity,
+                            );
+                        }
+                    }
+                    TriggerAction::PlaySound(sound) => {
+                        // Sound not named yet in the editor
+                        if sound.is_empty() {
+                            continue;
+                        }
+                        let zone = &self.trigger_zones.zones[event.zone];
+                        debug!("Trigger {} sound {}", zone.name, sound);
+                        self.sound_events.push(SoundEvent::Trigger {
+                            name: sound,
+                            world_pos: zone.pos,
+                        });
+                    }
+                    TriggerAction::ToggleEmitter => {
+                        let zone = &mut self.trigger_zones.zones[event.zone];
+                        zone.is_emitting = !zone.is_emitting;
+                    }
+                }
+            }
+        }
+        let emitters = self
+            .trigger_zones
+            .zones
+            .iter()
+            .filter(|z| z.is_emitting)
+            .map(|z| {
+                let radius = z.half_extents.x.min(z.half_extents.y) / *CELL_UNIT_SIZE;
+                (
+                    world_pos_to_canvas_pos(z.pos).cast::<i32>().unwrap(),
+                    z.emitter_matter,
+                    radius,
+                )
+            })
+            .collect::<Vec<(Vector2<i32>, u32, f32)>>();
+        for (canvas_pos, matter, radius) in emitters {
+            self.queue_paint_round(&[canvas_pos], matter, radius);
+        }
+        Ok(())
+    }
+
+    /// Anchor the dynamic object at `world_pos` to nearby solid terrain, or release it if it's
+    /// anchored already. Happens on next `finish_step`, when the grid can be read
+    pub fn toggle_anchor_at(&mut self, world_pos: Vector2<f32>) {
+        self.anchor_requests.push(world_pos);
+    }
+
+    /// Toggle requested anchors, release anchors whose object is gone or whose supporting terrain
+    /// was destroyed, and joint deformed objects again (their bodies are replaced)
+    fn update_anchors(&mut self, api: &mut EngineApi<InputAction>) -> Result<()> {
+        if self.anchors.is_empty() && self.anchor_requests.is_empty() {
+            return Ok(());
+        }
+        let EngineApi {
+            ecs_world,
+            physics_world,
+            ..
+        } = api;
+        let definitions = &self.matter_definitions.definitions;
+        let camera_canvas_pos = self.camera_canvas_pos;
+        let chunks = self.chunk_manager.sim_chunks(camera_canvas_pos);
+        let matters = chunks.read(ChunkGrid::Matter)?;
+        let matter_at = |canvas_pos: Vector2<i32>| matters.get(canvas_pos);
+        let is_support = |matter: u32| can_support_anchor(&definitions[matter as usize]);
+        let mut requested = self
+            .anchor_requests
+            .drain(..)
+            .filter_map(|world_pos| {
+                physics_entity_at_pos(physics_world, world_pos)
+                    .filter(|(rb, _)| rb.is_dynamic())
+                    .map(|(_, entity)| (entity, world_pos))
+            })
+            .collect::<Vec<(Entity, Vector2<f32>)>>();
+
+        let mut anchors = Vec::with_capacity(self.anchors.len());
+        for mut anchor in self.anchors.drain(..) {
+            let object_body = ecs_world.get::<RigidBodyHandle>(anchor.entity).ok().map(|rb| *rb);
+            let toggled = requested.iter().position(|(e, _)| *e == anchor.entity);
+            // Terrain outside simulated area is unknown, anchors there hold
+            let supported = !is_inside_sim_canvas(anchor.support, camera_canvas_pos)
+                || nearest_matter(anchor.support, ANCHOR_SUPPORT_RADIUS, &matter_at, is_support)
+                    .is_some();
+            let object_body = match object_body {
+                Some(object_body) if toggled.is_none() && supported => object_body,
+                _ => {
+                    if let Some(i) = toggled {
+                        requested.swap_remove(i);
+                    }
+                    physics_world.remove_physics(anchor.anchor_body);
+                    continue;
+                }
+            };
+            if object_body != anchor.object_body {
+                let physics = &mut physics_world.physics;
+                let joint = anchor_joint(
+                    &physics.bodies[object_body],
+                    &physics.bodies[anchor.anchor_body],
+                );
+                anchor.joint = physics.joints.insert(object_body, anchor.anchor_body, joint);
+                anchor.object_body = object_body;
+            }
+            anchors.push(anchor);
+        }
+
+        let physics = &mut physics_world.physics;
+        for (entity, world_pos) in requested {
+            let object_body = match ecs_world.get::<RigidBodyHandle>(entity) {
+                std::result::Result::Ok(rb) => *rb,
+                _ => continue,
+            };
+            let canvas_pos = world_pos_to_canvas_pos(world_pos).cast::<i32>().unwrap();
+            let support = match nearest_matter_cell(
+                canvas_pos,
+                ANCHOR_SEARCH_RADIUS,
+                &matter_at,
+                is_support,
+            ) {
+                Some((support, _)) => support,
+                None => {
+                    info!("No solid terrain near {:?} to anchor to", canvas_pos);
+                    continue;
+                }
+            };
+            let anchor_body = StaticRigidbody::spawn(
+                entity,
+                &mut physics.bodies,
+                &mut physics.colliders,
+                canvas_pos_to_world_pos(support),
+                0.0,
+                vec![],
+            );
+            let joint = anchor_joint(&physics.bodies[object_body], &physics.bodies[anchor_body]);
+            anchors.push(Anchor {
+                entity,
+                object_body,
+                anchor_body,
+                joint: physics.joints.insert(object_body, anchor_body, joint),
+                support,
+            });
+        }
+        self.anchors = anchors;
+        Ok(())
+    }
+
+    /// Join two objects with a hinge or rope at `world_anchors`. Ropes are as long as the
+    /// anchors are apart
+    pub fn join_objects(
+        &mut self,
+        ecs_world: &World,
+        physics_world: &mut PhysicsWorld,
+        entities: [Entity; 2],
+        world_anchors: [Vector2<f32>; 2],
+        kind: ObjectJointKind,
+    ) -> Result<()> {
+        if entities[0] == entities[1] {
+            bail!("Can't join an object to itself");
+        }
+        let bodies = [
+            *ecs_world.get::<RigidBodyHandle>(entities[0])?,
+            *ecs_world.get::<RigidBodyHandle>(entities[1])?,
+        ];
+        let bodies_set = &physics_world.physics.bodies;
+        let local_anchors = [
+            object_local_anchor(&bodies_set[bodies[0]], world_anchors[0]),
+            object_local_anchor(&bodies_set[bodies[1]], world_anchors[1]),
+        ];
+        self.object_joints.push(ObjectJoint::new(
+            physics_world,
+            entities,
+            bodies,
+            kind,
+            local_anchors,
+            rope_length(world_anchors),
+        ));
         Ok(())
     }
 
+    /// Remove joints of `entity`, returns how many were removed
+    pub fn remove_object_joints(
+        &mut self,
+        physics_world: &mut PhysicsWorld,
+        entity: Entity,
+    ) -> usize {
+        let num_joints = self.object_joints.len();
+        self.object_joints.retain(|joint| {
+            if joint.joins(entity) {
+                physics_world.remove_joint(joint.joint);
+            }
+            !joint.joins(entity)
+        });
+        num_joints - self.object_joints.len()
+    }
+
+    /// Drop joints of removed objects and join deformed objects again (their bodies are replaced
+    /// along with their joints)
+    fn update_object_joints(&mut self, api: &mut EngineApi<InputAction>) {
+        let EngineApi {
+            ecs_world,
+            physics_world,
+            ..
+        } = api;
+        let mut object_joints = Vec::with_capacity(self.object_joints.len());
+        for mut joint in self.object_joints.drain(..) {
+            let bodies = match (
+                ecs_world.get::<RigidBodyHandle>(joint.entities[0]),
+                ecs_world.get::<RigidBodyHandle>(joint.entities[1]),
+            ) {
+                (std::result::Result::Ok(rb1), std::result::Result::Ok(rb2)) => [*rb1, *rb2],
+                _ => {
+                    physics_world.remove_joint(joint.joint);
+                    continue;
+                }
+            };
+            // Previous joint was removed with the replaced body
+            if bodies != joint.bodies {
+                joint.insert(physics_world, bodies);
+            }
+            object_joints.push(joint);
+        }
+        self.object_joints = object_joints;
+    }
+
+    /// Positions of objects small enough to fit through portals
+    fn small_object_positions(
+        &self,
+        api: &mut EngineApi<InputAction>,
+    ) -> Vec<(Entity, Vector2<f32>)> {
+        if self.portals.pairs.is_empty() {
+            return vec![];
+        }
+        api.ecs_world
+            .query_mut::<(&PixelData, &Position)>()
+            .into_iter()
+            .filter(|(_id, (pixel_data, _pos))| {
+                (pixel_data.width * pixel_data.height) as usize <= PORTAL_MAX_OBJECT_PIXELS
+            })
+            .map(|(id, (_pixel_data, pos))| (id, pos.0))
+            .collect()
+    }
+
+    /// Move objects that went through a portal seam during physics step to the other side,
+    /// velocities are kept as is
+    fn teleport_objects_through_portals(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        positions_before: Vec<(Entity, Vector2<f32>)>,
+    ) {
+        let EngineApi {
+            ecs_world,
+            physics_world,
+            ..
+        } = api;
+        for (id, prev_pos) in positions_before {
+            let mut query = match ecs_world
+                .query_one::<(&RigidBodyHandle, &mut Position, &PixelData)>(id)
+                .ok()
+            {
+                Some(query) => query,
+                None => continue,
+            };
+            if let Some((rb, pos, pixel_data)) = query.get() {
+                let prev_canvas_pos = world_pos_to_canvas_pos(prev_pos).cast::<i32>().unwrap();
+                let canvas_pos = world_pos_to_canvas_pos(pos.0).cast::<i32>().unwrap();
+                if let Some(new_canvas_pos) = self.portals.teleport(prev_canvas_pos, canvas_pos) {
+                    let offset = new_canvas_pos - canvas_pos;
+                    pos.0 += Vector2::new(offset.x as f32, offset.y as f32) * *CELL_UNIT_SIZE;
+                    physics_world.physics.bodies[*rb]
+                        .set_translation(vector![pos.0.x, pos.0.y], true);
+                    let range = EntityChunkIndex::object_chunk_range(pos.0, pixel_data);
+                    self.object_chunk_index.update(id, range);
+                }
+            }
+        }
+    }
+
     /// Update object ecs data after physics calculation
     fn update_dynamic_physics_objects(&mut self, api: &mut EngineApi<InputAction>) -> Result<()> {
         let EngineApi {
@@ -154,12 +950,13 @@ impl Simulation {
             ..
         } = api;
         let mut remove = vec![];
-        for (id, (rb, pos, lin_vel, angle, ang_vel)) in ecs_world.query_mut::<(
+        for (id, (rb, pos, lin_vel, angle, ang_vel, pixel_data)) in ecs_world.query_mut::<(
             &RigidBodyHandle,
             &mut Position,
             &mut LinearVelocity,
             &mut Angle,
             &mut AngularVelocity,
+            Option<&PixelData>,
         )>() {
             let rigid_body: &mut RigidBody = &mut physics_world.physics.bodies[*rb];
             update_after_physics(
@@ -171,6 +968,9 @@ impl Simulation {
             );
             if pos.0.y < -10.0 * WORLD_UNIT_SIZE {
                 remove.push(id)
+            } else if let Some(pixel_data) = pixel_data {
+                let range = EntityChunkIndex::object_chunk_range(pos.0, pixel_data);
+                self.object_chunk_index.update(id, range);
             }
         }
         // ToDo: Delete dropped objects
@@ -178,15 +978,236 @@ impl Simulation {
             remove_physics_entity(ecs_world, physics_world, e);
             info!("Removed physics entity {} as it dropped too far", e.id());
         }
+        // Objects removed by editor, budgets or deformation
+        self.object_chunk_index.retain(|e| ecs_world.contains(e));
+        Ok(())
+    }
+
+    /// Keep dynamic object & collider counts within budgets by merging resting debris into the
+    /// grid or by despawning oldest debris. Runs before objects are written to the grid
+    fn enforce_object_budgets(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        settings: &AppSettings,
+    ) -> Result<()> {
+        let EngineApi {
+            ecs_world,
+            physics_world,
+            ..
+        } = api;
+        let mut num_objects = 0;
+        let mut num_colliders = 0;
+        let mut debris = vec![];
+        for (id, (pixel_data, rb, pos, lin_vel, spawn_step, fixed)) in ecs_world.query_mut::<(
+            &PixelData,
+            &RigidBodyHandle,
+            &Position,
+            &LinearVelocity,
+            Option<&SpawnStep>,
+            Option<&FixedObject>,
+        )>() {
+            let object_colliders = physics_world.physics.bodies[*rb].colliders().len();
+            num_objects += 1;
+            num_colliders += object_colliders;
+            // Static objects are placed on purpose, never treat them as debris
+            if fixed.is_some()
+                || pixel_data.pixels.iter().filter(|p| p.is_alive).count() > DEBRIS_MAX_PIXELS
+            {
+                continue;
+            }
+            debris.push(Debris {
+                entity: id,
+                spawn_step: spawn_step.map(|s| s.0).unwrap_or(0),
+                num_colliders: object_colliders,
+                can_merge: lin_vel.0.magnitude() < DEBRIS_REST_SPEED
+                    && world_pos_inside_canvas(pos.0, self.camera_pos),
+            });
+        }
+        let selected = select_debris_over_budget(
+            debris,
+            num_objects,
+            num_colliders,
+            settings.max_dynamic_objects as usize,
+            settings.max_object_colliders as usize,
+            settings.debris_policy,
+        );
+        if selected.is_empty() {
+            return Ok(());
+        }
+        if settings.debris_policy == DebrisPolicy::MergeIntoGrid {
+            self.merge_objects_into_grid(ecs_world, &selected)?;
+        }
+        for &id in selected.iter() {
+            remove_physics_entity(ecs_world, physics_world, id);
+        }
+        debug!("Removed {} debris objects over budget", selected.len());
         Ok(())
     }
 
-    pub fn save_matter_definitions(&self) {
-        let matter_definitions_path = current_dir()
-            .unwrap()
-            .join("assets/matter_definitions.json");
-        fs::write(matter_definitions_path, self.matter_definitions.serialize()).unwrap();
-        info!("Saved matter definitions to assets/matter_definitions.json");
+    /// Write alive pixels of objects `ids` into empty cells of the grid as their matter. Objects
+    /// are left for the caller to remove
+    fn merge_objects_into_grid(&mut self, ecs_world: &mut World, ids: &[Entity]) -> Result<()> {
+        // Grid may still be uploading to newly loaded chunks
+        self.chunk_manager.wait_transfers()?;
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let mut grid = chunks.write(ChunkGrid::Matter)?;
+        let mut pixels = vec![];
+        for &id in ids.iter() {
+            let mut query = ecs_world.query_one::<(&PixelData, &Position, &Angle)>(id)?;
+            if let Some((pixel_data, pos, angle)) = query.get() {
+                write_alive_pixels(pixel_data, pos.0, angle.0, id, &mut pixels);
+                for pixel in pixels.iter() {
+                    if grid.get(pixel.canvas_pos) == Some(self.matter_definitions.empty) {
+                        grid.set(pixel.canvas_pos, pixel.matter);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Merge dynamic objects that have been asleep on terrain for `freeze_after_secs` into the
+    /// grid, so settled objects stop costing physics. Anchored & joined objects are kept
+    fn freeze_resting_objects(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        settings: &AppSettings,
+    ) -> Result<()> {
+        let EngineApi {
+            ecs_world,
+            physics_world,
+            time,
+            ..
+        } = api;
+        let now = time.sim_time_secs();
+        let mut woke = vec![];
+        let mut fell_asleep = vec![];
+        let mut candidates = vec![];
+        for (id, (rb, pos, asleep_since, fixed)) in ecs_world.query_mut::<(
+            &RigidBodyHandle,
+            &Position,
+            Option<&AsleepSince>,
+            Option<&FixedObject>,
+        )>() {
+            if fixed.is_some() {
+                continue;
+            }
+            let is_sleeping = physics_world
+                .physics
+                .bodies
+                .get(*rb)
+                .map_or(false, |rb| rb.is_sleeping());
+            match (is_sleeping, asleep_since) {
+                (false, Some(_)) => woke.push(id),
+                (true, None) => fell_asleep.push(id),
+                (true, Some(since)) => {
+                    if now - since.0 >= settings.freeze_after_secs as f64
+                        && world_pos_inside_canvas(pos.0, self.camera_pos)
+                    {
+                        candidates.push(id);
+                    }
+                }
+                (false, None) => (),
+            }
+        }
+        for id in woke {
+            ecs_world.remove_one::<AsleepSince>(id)?;
+        }
+        for id in fell_asleep {
+            ecs_world.insert_one(id, AsleepSince(now))?;
+        }
+        candidates.retain(|&id| {
+            !self.anchors.iter().any(|anchor| anchor.entity == id)
+                && !self
+                    .object_joints
+                    .iter()
+                    .any(|joint| joint.entities.contains(&id))
+        });
+        if candidates.is_empty() {
+            return Ok(());
+        }
+        self.chunk_manager.wait_transfers()?;
+        let resting = {
+            let mut pixels = vec![];
+            // Object pixels were cleared from the grid after last step, so they're rewritten here.
+            // Other objects aren't terrain, an object frozen onto them would float once they move
+            let mut object_cells = HashSet::new();
+            for id in self.objects_in_sim_area() {
+                let mut query = ecs_world.query_one::<(&PixelData, &Position, &Angle)>(id)?;
+                if let Some((pixel_data, pos, angle)) = query.get() {
+                    write_alive_pixels(pixel_data, pos.0, angle.0, id, &mut pixels);
+                    object_cells.extend(pixels.iter().map(|pixel| pixel.canvas_pos));
+                }
+            }
+            let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+            let grid = chunks.read(ChunkGrid::Matter)?;
+            let definitions = &self.matter_definitions.definitions;
+            let is_terrain = |canvas_pos: Vector2<i32>| {
+                !object_cells.contains(&canvas_pos)
+                    && grid
+                        .get(canvas_pos)
+                        .and_then(|matter| definitions.get(matter as usize))
+                        .map_or(false, |matter| {
+                            matches!(
+                                matter.state,
+                                MatterState::Solid | MatterState::SolidGravity | MatterState::Powder
+                            )
+                        })
+            };
+            let mut resting = vec![];
+            for id in candidates {
+                let mut query = ecs_world.query_one::<(&PixelData, &Position, &Angle)>(id)?;
+                if let Some((pixel_data, pos, angle)) = query.get() {
+                    write_alive_pixels(pixel_data, pos.0, angle.0, id, &mut pixels);
+                    if rests_on_terrain(&pixels, &is_terrain) {
+                        resting.push(id);
+                    }
+                }
+            }
+            resting
+        };
+        if resting.is_empty() {
+            return Ok(());
+        }
+        self.merge_objects_into_grid(ecs_world, &resting)?;
+        for &id in resting.iter() {
+            remove_physics_entity(ecs_world, physics_world, id);
+        }
+        debug!("Froze {} resting objects into grid", resting.len());
+        Ok(())
+    }
+
+    /// Save matter definitions to config directory. Returns the file path
+    pub fn save_matter_definitions(&self) -> Result<PathBuf> {
+        let matter_definitions_path = matter_definitions_path();
+        fs::create_dir_all(&DATA_DIRS.config)?;
+        // Mod matters come back from their mods, & not at all once a mod is disabled
+        let base_definitions = without_mod_matters(&self.matter_definitions, &self.mod_matters);
+        fs::write(&matter_definitions_path, base_definitions.serialize())?;
+        Ok(matter_definitions_path)
+    }
+
+    /// Write markdown reference of current matters to data directory. Returns the file path
+    pub fn export_matter_reference(&self) -> Result<PathBuf> {
+        let reference_path = DATA_DIRS.data.join("matter_reference.md");
+        fs::create_dir_all(&DATA_DIRS.data)?;
+        fs::write(&reference_path, matter_reference_markdown(&self.matter_definitions))?;
+        Ok(reference_path)
+    }
+
+    /// Save matters `ids` & the matters they react into as a named matter pack. Returns the file
+    /// path
+    pub fn export_matter_pack(&self, name: &str, ids: &[u32]) -> Result<PathBuf> {
+        self.matter_definitions.export_pack(name, ids).save()
+    }
+
+    /// Add matters of a saved matter pack that don't exist yet
+    pub fn import_matter_pack(&mut self, name: &str) -> Result<PackImport> {
+        let pack = MatterPack::load(name)?;
+        let import = self.matter_definitions.import_pack(&pack)?;
+        info!("Import matter pack {}: added {:?}", name, import.added);
+        self.update_matter_data()?;
+        Ok(import)
     }
 
     pub fn remove_matter_definition(&mut self, id: u32) -> Result<()> {
@@ -201,8 +1222,13 @@ impl Simulation {
         for (i, def) in self.matter_definitions.definitions.iter_mut().enumerate() {
             def.id = i as u32;
         }
-        self.ca_simulator
-            .update_matter_data(&self.matter_definitions)?;
+        self.protected_matters = self
+            .protected_matters
+            .iter()
+            .filter(|&&matter| matter != id)
+            .map(|&matter| if matter > id { matter - 1 } else { matter })
+            .collect();
+        self.update_matter_data()?;
         Ok(())
     }
 
@@ -214,172 +1240,825 @@ impl Simulation {
                 id, matter_definition.name, matter_definition.state
             );
             self.matter_definitions.definitions.push(matter_definition);
-            self.ca_simulator
-                .update_matter_data(&self.matter_definitions)?;
+            self.update_matter_data()?;
         } else {
             info!(
                 "Update matter {}: name: {}, state: {}",
                 id, matter_definition.name, matter_definition.state
             );
-            self.matter_definitions.definitions[id as usize] = matter_definition;
-            self.ca_simulator
-                .update_matter_data(&self.matter_definitions)?;
+            self.matter_definitions.definitions[id as usize] = matter_definition;
+            self.update_matter_data()?;
+        }
+        Ok(())
+    }
+
+    /// Load map from disk. Broken parts (chunks, objects, triggers, portals & annotations) are
+    /// skipped so the rest of the map still loads. Returns descriptions of what was skipped
+    pub fn load_map_from_disk(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        map_name: &str,
+        player_pos: Vector2<i32>,
+    ) -> Result<Vec<String>> {
+        self.ca_simulator.wait_compute()?;
+        // Bugs & particles aren't saved with maps
+        self.ca_simulator.clear_bugs()?;
+        self.particles.clear();
+        let mut skipped = vec![];
+        let map_path = map_path().join(map_name);
+        let manifest = migrate_map(&map_path, &self.matter_definitions)?;
+        // Same seed & steps from load replay the map identically
+        self.set_world_seed(manifest.world_seed)?;
+        // Chunks store ids of the map's palette, which may differ from current definitions
+        let (matter_remap, missing_matters) = manifest.matter_remap(&self.matter_definitions);
+        for name in missing_matters {
+            skipped.push(format!("Matter {} isn't defined, its cells load empty", name));
+        }
+        self.chunk_manager.load_map_from_disk(
+            map_path.clone(),
+            player_pos,
+            &self.matter_definitions,
+            &matter_remap,
+            &mut skipped,
+        )?;
+
+        // Load objects
+        self.loaded_obj_images.clear();
+        if let Err(e) = self.load_objects_from_disk(api, &map_path, &mut skipped) {
+            skipped.push(format!("Objects: {:#}", e));
+        }
+
+        // Load trigger zones (older maps don't have them)
+        let triggers_path = map_path.join("triggers.json");
+        if triggers_path.exists() {
+            match fs::read_to_string(triggers_path)
+                .map_err(Error::from)
+                .and_then(|data| TriggerZones::deserialize(&data))
+            {
+                std::result::Result::Ok(trigger_zones) => {
+                    self.trigger_zones = trigger_zones;
+                    self.trigger_zones
+                        .spawn_all(&mut api.ecs_world, &mut api.physics_world)?;
+                }
+                Err(e) => skipped.push(format!("Trigger zones: {:#}", e)),
+            }
+        }
+        let portals_path = map_path.join("portals.json");
+        if portals_path.exists() {
+            match fs::read_to_string(portals_path)
+                .map_err(Error::from)
+                .and_then(|data| Portals::deserialize(&data))
+            {
+                std::result::Result::Ok(portals) => self.portals = portals,
+                Err(e) => skipped.push(format!("Portals: {:#}", e)),
+            }
+        }
+        let annotations_path = map_path.join("annotations.json");
+        if annotations_path.exists() {
+            match fs::read_to_string(annotations_path)
+                .map_err(Error::from)
+                .and_then(|data| Annotations::deserialize(&data))
+            {
+                std::result::Result::Ok(annotations) => self.annotations = annotations,
+                Err(e) => skipped.push(format!("Annotations: {:#}", e)),
+            }
+        }
+        Ok(skipped)
+    }
+
+    /// Load map's objects, skipping ones whose images fail to load
+    fn load_objects_from_disk(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        map_path: &Path,
+        skipped: &mut Vec<String>,
+    ) -> Result<()> {
+        let obj_dir_path = map_path.join("objects");
+        let obj_save_data_path = obj_dir_path.join("objects.json");
+        let object_save_data_str = fs::read_to_string(obj_save_data_path)?;
+        let object_save_data = PixelObjectSaveDataArray::deserialize(&object_save_data_str)?;
+        // Entities by object index, for joints
+        let mut entities = Vec::with_capacity(object_save_data.objects.len());
+        for object_data in object_save_data.objects.iter() {
+            let img_path = obj_dir_path.join(&format!("{}.png", object_data.id));
+            let obj_img = match load_bitmap_image_from_path(img_path.clone()) {
+                std::result::Result::Ok(image) => Arc::new(image),
+                Err(e) => {
+                    skipped.push(format!("Object {:?}: {:#}", img_path, e));
+                    entities.push(None);
+                    continue;
+                }
+            };
+            let entity = object_data.add_dynamic_pixel_object(
+                &mut api.ecs_world,
+                &mut api.physics_world,
+                self,
+                &obj_img,
+            )?;
+            self.loaded_obj_images.insert(entity.id(), obj_img);
+            entities.push(Some(entity));
+        }
+        for joint_data in object_save_data.joints.iter() {
+            let [a, b] = joint_data.objects;
+            let entity_at = |i: usize| entities.get(i).copied().flatten();
+            let (a, b) = match (entity_at(a), entity_at(b)) {
+                (Some(a), Some(b)) => (a, b),
+                _ => {
+                    skipped.push(format!("Joint of objects {:?}", joint_data.objects));
+                    continue;
+                }
+            };
+            let bodies = [
+                *api.ecs_world.get::<RigidBodyHandle>(a)?,
+                *api.ecs_world.get::<RigidBodyHandle>(b)?,
+            ];
+            self.object_joints.push(ObjectJoint::new(
+                &mut api.physics_world,
+                [a, b],
+                bodies,
+                joint_data.kind,
+                joint_data.local_anchors,
+                joint_data.length,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Snapshots of the map's chunks to be written in the background, see `ChunkSnapshot`
+    pub fn snapshot_map_chunks(
+        &mut self,
+        settings: &AppSettings,
+    ) -> Result<Vec<(Vector2<i32>, ChunkSnapshot)>> {
+        self.ca_simulator.wait_compute()?;
+        self.chunk_manager.snapshot_chunks(settings.chunked_simulation)
+    }
+
+    /// Images of the map's chunks, matter drawn in its color, & their paths in `dir`
+    pub fn map_chunk_images(
+        &mut self,
+        dir: PathBuf,
+        settings: &AppSettings,
+    ) -> Result<Vec<(PathBuf, BitmapImage)>> {
+        self.ca_simulator.wait_compute()?;
+        self.chunk_manager
+            .chunk_images(dir, settings.chunked_simulation, &self.matter_definitions)
+    }
+
+    /// Paint round strokes of matter along the line on gpu at the start of next step. Same as
+    /// `paint_round` without a fill, but cpu cost doesn't depend on radius
+    pub fn queue_paint_round(&mut self, line: &[Vector2<i32>], matter: u32, radius: f32) {
+        self.mark_line_boundaries_changed(line, radius.ceil() as i32);
+        for &center in line {
+            self.ca_simulator.queue_paint(PaintStroke {
+                center,
+                radius,
+                matter,
+                is_square: false,
+            });
+        }
+    }
+
+    /// Square strokes like `paint_square` on gpu, see `queue_paint_round`
+    pub fn queue_paint_square(&mut self, line: &[Vector2<i32>], matter: u32, size: i32) {
+        self.mark_line_boundaries_changed(line, size / 2 + 1);
+        for &center in line {
+            self.ca_simulator.queue_paint(PaintStroke {
+                center,
+                radius: size as f32 / 2.0,
+                matter,
+                is_square: true,
+            });
+        }
+    }
+
+    /// Queue round or square strokes along the line to be painted on gpu like `queue_paint_round`
+    /// & `queue_paint_square`. Cells are only read on cpu, to return the ones strokes will paint
+    /// & their previous matter, e.g. for undo
+    pub fn queue_paint_line(
+        &mut self,
+        line: &[Vector2<i32>],
+        matter: u32,
+        radius: f32,
+        is_square: bool,
+    ) -> Result<Vec<(Vector2<i32>, u32)>> {
+        self.ca_simulator.wait_compute()?;
+        let empty = self.matter_definitions.empty;
+        let size = (radius * 2.0) as i32;
+        let mut painted = HashMap::new();
+        {
+            let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+            let grid = chunks.read(ChunkGrid::Matter)?;
+            // Same cells as the paint kernel covers & changes, see compute_shaders/paint
+            let mut paint_cell = |canvas_pos: Vector2<i32>| {
+                if let Some(previous) = grid.get(canvas_pos) {
+                    let changes = previous != matter
+                        && (previous == empty
+                            || (matter == empty && !self.protected_matters.contains(&previous)));
+                    if changes {
+                        painted.entry(canvas_pos).or_insert(previous);
+                    }
+                }
+            };
+            for &pos in line.iter() {
+                if !is_inside_sim_canvas(pos, self.camera_canvas_pos) {
+                    continue;
+                }
+                if is_square {
+                    for y in pos.y - size / 2..pos.y + size / 2 {
+                        for x in pos.x - size / 2..pos.x + size / 2 {
+                            paint_cell(Vector2::new(x, y));
+                        }
+                    }
+                } else {
+                    let r = radius as i32;
+                    for y in pos.y - r..=pos.y + r {
+                        for x in pos.x - r..=pos.x + r {
+                            let distance = Vector2::new(x as f32, y as f32)
+                                .distance(Vector2::new(pos.x as f32, pos.y as f32));
+                            if distance.round() <= radius {
+                                paint_cell(Vector2::new(x, y));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if is_square {
+            self.queue_paint_square(line, matter, size);
+        } else {
+            self.queue_paint_round(line, matter, radius);
+        }
+        Ok(painted.into_iter().collect())
+    }
+
+    /// Paint matter along the line, only to cells `fills` accepts by cell & brush center. Empty
+    /// matter erases, cells harder than brush `strength` only by their `break_chance` & protected
+    /// cells not at all. Returns painted cells & their previous matter
+    pub fn paint_round(
+        &mut self,
+        line: &[Vector2<i32>],
+        matter: u32,
+        radius: f32,
+        strength: f32,
+        mut fills: impl FnMut(Vector2<i32>, Vector2<i32>) -> bool,
+    ) -> Result<Vec<(Vector2<i32>, u32)>> {
+        self.ca_simulator.wait_compute()?;
+        let empty = self.matter_definitions.empty;
+        let definitions = &self.matter_definitions.definitions;
+        let protected_matters = &self.protected_matters;
+        let mut rng = rand::thread_rng();
+        let mut breaks = |previous: u32| {
+            // Ids past the palette, e.g. of a removed matter, break like empty
+            let chance = definitions
+                .get(previous as usize)
+                .map_or(1.0, |definition| definition.break_chance(strength));
+            matter == empty && !protected_matters.contains(&previous) && rng.gen::<f32>() < chance
+        };
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let mut grid = chunks.write(ChunkGrid::Matter)?;
+        let mut painted = vec![];
+        for &pos in line.iter() {
+            if !is_inside_sim_canvas(pos, self.camera_canvas_pos) {
+                continue;
+            }
+            let y_start = pos.y - radius as i32;
+            let y_end = pos.y + radius as i32;
+            let x_start = pos.x - radius as i32;
+            let x_end = pos.x + radius as i32;
+            for y in y_start..=y_end {
+                for x in x_start..=x_end {
+                    if Vector2::new(x as f32, y as f32)
+                        .distance(Vector2::new(pos.x as f32, pos.y as f32))
+                        .round()
+                        <= radius
+                    {
+                        let canvas_pos = Vector2::new(x, y);
+                        let previous = match grid.get(canvas_pos) {
+                            Some(previous) if fills(canvas_pos, pos) => previous,
+                            _ => continue,
+                        };
+                        if previous != matter && (previous == empty || breaks(previous)) {
+                            grid.set(canvas_pos, matter);
+                            painted.push((canvas_pos, previous));
+                        }
+                    }
+                }
+            }
+        }
+        if !painted.is_empty() {
+            self.mark_line_boundaries_changed(line, radius.ceil() as i32);
+        }
+        Ok(painted)
+    }
+
+    /// Paint squares of matter along the line, see `paint_round`
+    pub fn paint_square(
+        &mut self,
+        line: &[Vector2<i32>],
+        matter: u32,
+        size: i32,
+        strength: f32,
+        mut fills: impl FnMut(Vector2<i32>, Vector2<i32>) -> bool,
+    ) -> Result<Vec<(Vector2<i32>, u32)>> {
+        self.ca_simulator.wait_compute()?;
+        let empty = self.matter_definitions.empty;
+        let definitions = &self.matter_definitions.definitions;
+        let protected_matters = &self.protected_matters;
+        let mut rng = rand::thread_rng();
+        let mut breaks = |previous: u32| {
+            // Ids past the palette, e.g. of a removed matter, break like empty
+            let chance = definitions
+                .get(previous as usize)
+                .map_or(1.0, |definition| definition.break_chance(strength));
+            matter == empty && !protected_matters.contains(&previous) && rng.gen::<f32>() < chance
+        };
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let mut grid = chunks.write(ChunkGrid::Matter)?;
+        let mut painted = vec![];
+        for &pos in line.iter() {
+            if !is_inside_sim_canvas(pos, self.camera_canvas_pos) {
+                continue;
+            }
+            let y_start = pos.y - size / 2;
+            let y_end = pos.y + size / 2;
+            let x_start = pos.x - size / 2;
+            let x_end = pos.x + size / 2;
+            for y in y_start..y_end {
+                for x in x_start..x_end {
+                    let canvas_pos = Vector2::new(x, y);
+                    let previous = match grid.get(canvas_pos) {
+                        Some(previous) if fills(canvas_pos, pos) => previous,
+                        _ => continue,
+                    };
+                    if previous != matter && (previous == empty || breaks(previous)) {
+                        grid.set(canvas_pos, matter);
+                        painted.push((canvas_pos, previous));
+                    }
+                }
+            }
+        }
+        if !painted.is_empty() {
+            self.mark_line_boundaries_changed(line, size / 2 + 1);
+        }
+        Ok(painted)
+    }
+
+    /// Write matter to cells, skipping cells outside simulated area
+    pub fn write_matter_cells(&mut self, cells: &[(Vector2<i32>, u32)]) -> Result<()> {
+        self.ca_simulator.wait_compute()?;
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let mut grid = chunks.write(ChunkGrid::Matter)?;
+        for &(canvas_pos, matter) in cells {
+            grid.set(canvas_pos, matter);
+        }
+        Ok(())
+    }
+
+    /// Set cells from their matter before to after, only where they still have the matter before
+    /// so cells changed since aren't overwritten. Returns the cells that were set
+    pub fn swap_matter_cells(
+        &mut self,
+        cells: &[(Vector2<i32>, u32, u32)],
+    ) -> Result<Vec<(Vector2<i32>, u32, u32)>> {
+        self.ca_simulator.wait_compute()?;
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let mut grid = chunks.write(ChunkGrid::Matter)?;
+        let mut swapped = vec![];
+        for &(canvas_pos, before, after) in cells {
+            if grid.get(canvas_pos) == Some(before) {
+                grid.set(canvas_pos, after);
+                swapped.push((canvas_pos, before, after));
+            }
+        }
+        if !swapped.is_empty() {
+            let changed = swapped.iter().map(|&(pos, ..)| pos).collect::<Vec<_>>();
+            self.mark_line_boundaries_changed(&changed, 0);
+        }
+        Ok(swapped)
+    }
+
+    /// Fill cells with matter, e.g. a dragged rectangle. Only empty cells are filled unless
+    /// `replace`, and protected cells are never replaced. Returns filled cells & their previous
+    /// matter
+    pub fn fill_cells(
+        &mut self,
+        cells: &[Vector2<i32>],
+        matter: u32,
+        replace: bool,
+    ) -> Result<Vec<(Vector2<i32>, u32)>> {
+        self.ca_simulator.wait_compute()?;
+        let empty = self.matter_definitions.empty;
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let mut grid = chunks.write(ChunkGrid::Matter)?;
+        let mut filled = vec![];
+        for &canvas_pos in cells {
+            let previous = match grid.get(canvas_pos) {
+                Some(previous) => previous,
+                None => continue,
+            };
+            if previous == matter
+                || self.protected_matters.contains(&previous)
+                || (previous != empty && matter != empty && !replace)
+            {
+                continue;
+            }
+            grid.set(canvas_pos, matter);
+            filled.push((canvas_pos, previous));
+        }
+        if !filled.is_empty() {
+            self.mark_line_boundaries_changed(cells, 0);
+        }
+        Ok(filled)
+    }
+
+    /// Replace the connected region of matter at `start` with `matter` (bucket fill), within
+    /// simulated area. Protected matter isn't replaced. Returns filled cells & their previous
+    /// matter
+    pub fn flood_fill(
+        &mut self,
+        start: Vector2<i32>,
+        matter: u32,
+    ) -> Result<Vec<(Vector2<i32>, u32)>> {
+        self.ca_simulator.wait_compute()?;
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let mut grid = chunks.write(ChunkGrid::Matter)?;
+        let previous = match grid.get(start) {
+            Some(previous) if !self.protected_matters.contains(&previous) => previous,
+            _ => return Ok(vec![]),
+        };
+        let filled = scanline_flood_fill(
+            &mut grid,
+            start,
+            matter,
+            |grid, pos| grid.get(pos),
+            |grid, pos, matter| {
+                grid.set(pos, matter);
+            },
+        );
+        if !filled.is_empty() {
+            self.mark_line_boundaries_changed(&filled, 0);
+        }
+        Ok(filled.into_iter().map(|pos| (pos, previous)).collect())
+    }
+
+    /// Image of simulated area's matter `factor` times smaller, e.g. for edit history thumbnails.
+    /// Compute must have finished
+    pub fn sim_area_thumbnail(&self, factor: u32) -> Result<BitmapImage> {
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let grid = chunks.read(ChunkGrid::Matter)?;
+        let corner = self.camera_canvas_pos - *HALF_CANVAS;
+        let mut image = BitmapImage::empty(SIM_CANVAS_SIZE.x, SIM_CANVAS_SIZE.y);
+        for y in 0..SIM_CANVAS_SIZE.y {
+            for x in 0..SIM_CANVAS_SIZE.x {
+                // Image rows go from top down
+                let canvas_pos =
+                    corner + Vector2::new(x as i32, (SIM_CANVAS_SIZE.y - 1 - y) as i32);
+                let matter = match grid.get(canvas_pos) {
+                    Some(matter) => matter,
+                    None => continue,
+                };
+                let color = match self.matter_definitions.definitions.get(matter as usize) {
+                    Some(definition) => Color32::from_u32_rgba(definition.color),
+                    None => continue,
+                };
+                let index = ((y * SIM_CANVAS_SIZE.x + x) * 4) as usize;
+                image.data[index..index + 4].copy_from_slice(&color.to_rgba());
+            }
+        }
+        Ok(image.downscaled(factor))
+    }
+
+    /// Write matter of cells like `write_matter_cells`. Returns cells inside simulated area with
+    /// their matter before & after
+    pub fn replace_matter_cells(
+        &mut self,
+        cells: &[(Vector2<i32>, u32)],
+    ) -> Result<Vec<(Vector2<i32>, u32, u32)>> {
+        self.ca_simulator.wait_compute()?;
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let mut grid = chunks.write(ChunkGrid::Matter)?;
+        let mut replaced = vec![];
+        for &(canvas_pos, matter) in cells {
+            if let Some(previous) = grid.set(canvas_pos, matter) {
+                replaced.push((canvas_pos, previous, matter));
+            }
+        }
+        Ok(replaced)
+    }
+
+    /// Write matter to cells of shape at canvas `pos`, replacing what was there. Cells outside
+    /// simulated area are skipped. Returns written cells with their matter before & after
+    pub fn spawn_shape(
+        &mut self,
+        shape: &Shape,
+        matter: u32,
+        pos: Vector2<i32>,
+    ) -> Result<Vec<(Vector2<i32>, u32, u32)>> {
+        let cells = shape
+            .cells(pos)
+            .into_iter()
+            .map(|cell| (cell, matter))
+            .collect::<Vec<_>>();
+        self.replace_matter_cells(&cells)
+    }
+
+    /// Matter ids of canvas rectangle `min..=max`, bottom row first. Cells outside simulated area
+    /// are empty
+    pub fn read_matter_rect(&self, min: Vector2<i32>, max: Vector2<i32>) -> Result<Vec<u32>> {
+        let positions = (min.y..=max.y)
+            .flat_map(|y| (min.x..=max.x).map(move |x| Vector2::new(x, y)))
+            .collect::<Vec<_>>();
+        Ok(self
+            .query_matters(&positions)?
+            .into_iter()
+            .map(|matter| matter.unwrap_or(self.matter_definitions.empty))
+            .collect())
+    }
+
+    /// Matter of canvas rectangle `min..=max` as an image of matter colors, top row first like
+    /// map chunk images. Cells outside simulated area are empty
+    pub fn copy_matter_image(&self, min: Vector2<i32>, max: Vector2<i32>) -> Result<BitmapImage> {
+        self.ca_simulator.wait_compute()?;
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let grid = chunks.read(ChunkGrid::Matter)?;
+        let (width, height) = ((max.x - min.x + 1) as u32, (max.y - min.y + 1) as u32);
+        let mut image = BitmapImage::empty(width, height);
+        let definitions = &self.matter_definitions.definitions;
+        for y in 0..height {
+            for x in 0..width {
+                let canvas_pos = Vector2::new(min.x + x as i32, max.y - y as i32);
+                let matter = grid
+                    .get(canvas_pos)
+                    .unwrap_or(self.matter_definitions.empty);
+                let color = Color32::from_u32_rgba(definitions[matter as usize].color);
+                let index = (y * width + x) as usize * 4;
+                image.data[index..index + 4].copy_from_slice(&color.to_rgba());
+            }
+        }
+        Ok(image)
+    }
+
+    /// Write matter of an image of matter colors centered at `canvas_pos`. Empty cells and colors
+    /// of no matter are skipped, so the image keeps its shape
+    pub fn paste_matter_image(
+        &mut self,
+        image: &BitmapImage,
+        canvas_pos: Vector2<i32>,
+    ) -> Result<()> {
+        self.ca_simulator.wait_compute()?;
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let mut grid = chunks.write(ChunkGrid::Matter)?;
+        let matter_by_color = self
+            .matter_definitions
+            .definitions
+            .iter()
+            .map(|m| (m.color, m.id))
+            .collect::<HashMap<u32, u32>>();
+        let top_left =
+            canvas_pos + Vector2::new(-(image.width as i32) / 2, image.height as i32 / 2);
+        for (index, rgba) in image.data.chunks_exact(4).enumerate() {
+            let color = Color32::from_rgba_bytes(rgba).to_u32_rgba();
+            let matter = match matter_by_color.get(&color) {
+                Some(&matter) if matter != self.matter_definitions.empty => matter,
+                _ => continue,
+            };
+            let (x, y) = (index as u32 % image.width, index as u32 / image.width);
+            grid.set(top_left + Vector2::new(x as i32, -(y as i32)), matter);
+        }
+        Ok(())
+    }
+
+    /// Generate ore veins in chunks being simulated. Returns number of ore cells generated
+    pub fn generate_ore_veins(&mut self, ore_veins: &OreVeins) -> Result<usize> {
+        self.ca_simulator.wait_compute()?;
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let mut grid = chunks.write(ChunkGrid::Matter)?;
+        let empty = self.matter_definitions.empty;
+        let mut num_ore = 0;
+        for chunk_index in 0..4 {
+            let chunk_offset = Vector2::new(
+                (chunk_index % 2) as i32 * SIM_CANVAS_SIZE.x as i32,
+                (chunk_index / 2) as i32 * SIM_CANVAS_SIZE.y as i32,
+            );
+            for (grid_index, matter) in grid.chunk_mut(chunk_index).iter_mut().enumerate() {
+                let pos = chunks.chunk_start()
+                    + chunk_offset
+                    + Vector2::new(
+                        (grid_index % SIM_CANVAS_SIZE.x as usize) as i32,
+                        (grid_index / SIM_CANVAS_SIZE.x as usize) as i32,
+                    );
+                *matter = ore_veins.matter_at(pos, *matter, empty);
+                if *matter == ore_veins.ore {
+                    num_ore += 1;
+                }
+            }
         }
-        Ok(())
+        Ok(num_ore)
     }
 
-    pub fn load_map_from_disk(
+    /// Reload chunk from the map saved on disk, discarding changes to it since. Chunks the map
+    /// wasn't saved with reload empty
+    pub fn reload_chunk_from_disk(
         &mut self,
-        api: &mut EngineApi<InputAction>,
         map_name: &str,
-        player_pos: Vector2<i32>,
+        chunk_pos: Vector2<i32>,
     ) -> Result<()> {
+        self.ca_simulator.wait_compute()?;
         let map_path = map_path().join(map_name);
-        self.chunk_manager.load_map_from_disk(
-            map_path.clone(),
-            player_pos,
-            &self.matter_definitions,
-        )?;
+        let manifest = migrate_map(&map_path, &self.matter_definitions)?;
+        let (matter_remap, _) = manifest.matter_remap(&self.matter_definitions);
+        let chunk_path = map_path.join(chunk_file_name(chunk_pos, CHUNK_FILE_EXTENSION));
+        let data = if chunk_path.exists() {
+            match WorldChunk::load_from_disk(chunk_path, &matter_remap) {
+                (_, Some(e)) => return Err(e),
+                (chunk, None) => chunk.data,
+            }
+        } else {
+            None
+        };
+        let matter_grid = chunk_matter_grid(data.as_deref(), &self.matter_definitions)?;
+        self.chunk_manager.replace_chunk(chunk_pos, &matter_grid)
+    }
 
-        // Load objects
-        self.loaded_obj_images.clear();
-        let obj_dir_path = map_path.join("objects");
-        let obj_save_data_path = obj_dir_path.join("objects.json");
-        let object_save_data_str = fs::read_to_string(obj_save_data_path).unwrap();
-        let object_save_data = PixelObjectSaveDataArray::deserialize(&object_save_data_str);
-        for object_data in object_save_data.objects.iter() {
-            let img_path = obj_dir_path.join(&format!("{}.png", object_data.id));
-            let contents = fs::read(img_path.clone()).unwrap();
-            let obj_img = Arc::new(load_image_from_file_bytes(&contents));
-            let entity = object_data.add_dynamic_pixel_object(
-                &mut api.ecs_world,
-                &mut api.physics_world,
-                self,
-                &obj_img,
-            )?;
-            self.loaded_obj_images.insert(entity.id(), obj_img);
+    /// Regenerate chunk like in a new map with `ore_veins` generated, discarding changes to it
+    pub fn regenerate_chunk(
+        &mut self,
+        chunk_pos: Vector2<i32>,
+        ore_veins: &OreVeins,
+    ) -> Result<()> {
+        self.ca_simulator.wait_compute()?;
+        let empty = self.matter_definitions.empty;
+        let mut matter_grid = chunk_matter_grid(None, &self.matter_definitions)?;
+        let chunk_start = Vector2::new(
+            chunk_pos.x * CANVAS_CHUNK_SIZE.x as i32,
+            chunk_pos.y * CANVAS_CHUNK_SIZE.y as i32,
+        ) - *HALF_CANVAS;
+        for (grid_index, matter) in matter_grid.iter_mut().enumerate() {
+            let pos = chunk_start
+                + Vector2::new(
+                    (grid_index % CANVAS_CHUNK_SIZE.x as usize) as i32,
+                    (grid_index / CANVAS_CHUNK_SIZE.x as usize) as i32,
+                );
+            *matter = ore_veins.matter_at(pos, *matter, empty);
         }
-        Ok(())
+        self.chunk_manager.replace_chunk(chunk_pos, &matter_grid)
     }
 
-    pub fn save_map_to_disk(&mut self, map_path: PathBuf, settings: &AppSettings) -> Result<()> {
-        if settings.chunked_simulation {
-            self.chunk_manager
-                .save_chunks_to_disk(map_path, &self.matter_definitions)
-        } else {
-            self.chunk_manager
-                .save_one_chunk_to_disk(map_path, &self.matter_definitions)
-        }
+    /// Spawn `count` bugs at random positions within `radius` of `canvas_pos`. Returns how many
+    /// were spawned, which is less than `count` when bug limit is reached
+    pub fn spawn_bugs(
+        &mut self,
+        canvas_pos: Vector2<i32>,
+        radius: f32,
+        count: usize,
+    ) -> Result<usize> {
+        let mut rng = rand::thread_rng();
+        let positions = (0..count)
+            .map(|_| {
+                let offset = Vector2::new(
+                    rng.gen_range(-radius..=radius),
+                    rng.gen_range(-radius..=radius),
+                );
+                canvas_pos + offset.cast::<i32>().unwrap()
+            })
+            .filter(|pos| is_inside_sim_canvas(*pos, self.camera_canvas_pos))
+            .collect::<Vec<Vector2<i32>>>();
+        self.ca_simulator.spawn_bugs(&positions)
     }
 
-    pub fn paint_round(&mut self, line: &[Vector2<i32>], matter: u32, radius: f32) -> Result<()> {
-        for &pos in line.iter() {
-            if !is_inside_sim_canvas(pos, self.camera_canvas_pos) {
-                continue;
-            }
-            let (chunk_start, grids) = self.chunk_manager.get_chunks_for_compute();
-            let mut grids = [
-                grids[0].matter_in.write()?,
-                grids[1].matter_in.write()?,
-                grids[2].matter_in.write()?,
-                grids[3].matter_in.write()?,
-            ];
-            let y_start = pos.y - radius as i32;
-            let y_end = pos.y + radius as i32;
-            let x_start = pos.x - radius as i32;
-            let x_end = pos.x + radius as i32;
-            for y in y_start..=y_end {
-                for x in x_start..=x_end {
-                    if Vector2::new(x as f32, y as f32)
-                        .distance(Vector2::new(pos.x as f32, pos.y as f32))
-                        .round()
-                        <= radius
-                    {
-                        let canvas_pos = Vector2::new(x, y);
-                        if is_inside_sim_canvas(canvas_pos, self.camera_canvas_pos) {
-                            let (chunk_index, grid_index) =
-                                sim_chunk_canvas_index(canvas_pos, chunk_start);
-                            if grids[chunk_index][grid_index] == self.matter_definitions.empty
-                                || matter == self.matter_definitions.empty
-                            {
-                                grids[chunk_index][grid_index] = matter;
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    pub fn num_bugs(&self) -> Result<usize> {
+        self.ca_simulator.num_bugs()
+    }
+
+    pub fn clear_bugs(&mut self) -> Result<()> {
+        self.ca_simulator.clear_bugs()
+    }
+
+    pub fn read_bugs(&self) -> Result<Vec<u32>> {
+        self.ca_simulator.read_bugs()
+    }
+
+    pub fn write_bugs(&mut self, bugs: &[u32]) -> Result<()> {
+        self.ca_simulator.write_bugs(bugs)
+    }
+
+    /// CA steps since world seed was set, which seed the next steps
+    pub fn sim_steps(&self) -> usize {
+        self.ca_simulator.sim_steps
+    }
+
+    /// Continue step seeds from `sim_steps`, e.g. when resuming a saved session
+    pub fn resume_sim_steps(&mut self, sim_steps: usize) -> Result<()> {
+        self.ca_simulator.wait_compute()?;
+        self.ca_simulator.sim_steps = sim_steps;
         Ok(())
     }
 
-    pub fn paint_square(&mut self, line: &[Vector2<i32>], matter: u32, size: i32) -> Result<()> {
-        for &pos in line.iter() {
-            if !is_inside_sim_canvas(pos, self.camera_canvas_pos) {
-                continue;
-            }
-            let (chunk_start, grids) = self.chunk_manager.get_chunks_for_compute();
-            let mut grids = [
-                grids[0].matter_in.write()?,
-                grids[1].matter_in.write()?,
-                grids[2].matter_in.write()?,
-                grids[3].matter_in.write()?,
-            ];
-            let y_start = pos.y - size / 2;
-            let y_end = pos.y + size / 2;
-            let x_start = pos.x - size / 2;
-            let x_end = pos.x + size / 2;
-            for y in y_start..y_end {
-                for x in x_start..x_end {
-                    let canvas_pos = Vector2::new(x, y);
-                    if is_inside_sim_canvas(canvas_pos, self.camera_canvas_pos) {
-                        let (chunk_index, grid_index) =
-                            sim_chunk_canvas_index(canvas_pos, chunk_start);
-                        if grids[chunk_index][grid_index] == self.matter_definitions.empty
-                            || matter == self.matter_definitions.empty
-                        {
-                            grids[chunk_index][grid_index] = matter;
-                        }
-                    }
+    /// Number of cells of each matter (indexed by matter id) in chunks being simulated
+    pub fn matter_counts(&self) -> Result<Vec<u64>> {
+        self.ca_simulator.wait_compute()?;
+        let mut counts = vec![0; self.matter_definitions.definitions.len()];
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let grid = chunks.read(ChunkGrid::Matter)?;
+        for chunk_index in 0..4 {
+            for matter in grid.chunk(chunk_index).iter() {
+                if let Some(count) = counts.get_mut(*matter as usize) {
+                    *count += 1;
                 }
             }
         }
-        Ok(())
+        Ok(counts)
+    }
+
+    /// Count reactions on gpu from the next step on, only needed while `ReactionStats` are shown
+    pub fn set_count_reactions(&mut self, count: bool) {
+        self.ca_simulator.count_reactions = count;
+    }
+
+    /// Add reaction counts since last call to `counts`, see `ReactionStats`
+    pub fn take_reaction_counts(&self, counts: &mut [u64]) -> Result<()> {
+        self.ca_simulator.take_reaction_counts(counts)
     }
 
     /// Query cell via GUI, this should be performed on grid_next
     pub fn query_matter(&self, mouse_pos: Vector2<i32>) -> Result<Option<u32>> {
-        if !is_inside_sim_canvas(mouse_pos, self.camera_canvas_pos) {
-            return Ok(None);
+        Ok(self.query_matters(&[mouse_pos])?[0])
+    }
+
+    /// Matter of each cell, None for cells outside simulated area
+    pub fn query_matters(&self, canvas_positions: &[Vector2<i32>]) -> Result<Vec<Option<u32>>> {
+        self.ca_simulator.wait_compute()?;
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let matters = chunks.read(ChunkGrid::Matter)?;
+        Ok(canvas_positions
+            .iter()
+            .map(|pos| matters.get(*pos))
+            .collect())
+    }
+
+    /// First cell that isn't empty and passes `filter` along a ray from canvas `origin` towards
+    /// `dir`, within `max_dist` cells. Objects count as their matter. Ray ends at simulated area
+    /// edge
+    pub fn raycast_matter(
+        &self,
+        origin: Vector2<f32>,
+        dir: Vector2<f32>,
+        max_dist: f32,
+        filter: impl Fn(&MatterDefinition) -> bool,
+    ) -> Result<Option<RayHit>> {
+        self.ca_simulator.wait_compute()?;
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let matters = chunks.read(ChunkGrid::Matter)?;
+        let obj_matters = chunks.read(ChunkGrid::ObjectsMatter)?;
+        let definitions = &self.matter_definitions.definitions;
+        let empty = self.matter_definitions.empty;
+        let matter_at = |canvas_pos: Vector2<i32>| match obj_matters.get(canvas_pos)? {
+            obj_matter if obj_matter != empty => Some(obj_matter),
+            _ => matters.get(canvas_pos),
+        };
+        let accept = |matter: u32| {
+            matter != empty && definitions.get(matter as usize).map_or(false, &filter)
+        };
+        Ok(raycast_cells(origin, dir, max_dist, matter_at, accept))
+    }
+
+    /// Fire a beam from canvas `origin` towards `dir`, passing through liquids, gases & energy
+    /// until it hits a solid or powder within `max_dist` cells. `heat_matter` is queued to empty
+    /// cells around the hit, so its reactions heat or ignite the hit cell
+    pub fn fire_beam(
+        &mut self,
+        origin: Vector2<f32>,
+        dir: Vector2<f32>,
+        max_dist: f32,
+        heat_matter: u32,
+    ) -> Result<Option<RayHit>> {
+        let hit = self.raycast_matter(origin, dir, max_dist, |matter| {
+            matches!(
+                matter.state,
+                MatterState::Solid | MatterState::SolidGravity | MatterState::Powder
+            )
+        })?;
+        if let Some(hit) = &hit {
+            self.queue_paint_round(&[hit.canvas_pos], heat_matter, 1.5);
         }
-        let (chunk_start, chunks) = self.chunk_manager.get_chunks_for_compute();
-        let matters = [
-            chunks[0].matter_in.read()?,
-            chunks[1].matter_in.read()?,
-            chunks[2].matter_in.read()?,
-            chunks[3].matter_in.read()?,
-        ];
-        let (chunk_index, grid_index) = sim_chunk_canvas_index(mouse_pos, chunk_start);
-        Ok(Some(matters[chunk_index][grid_index]))
+        Ok(hit)
     }
 
     fn query_object(&self, mouse_pos: Vector2<i32>) -> Result<Option<(u32, Vec<Entity>)>> {
         if !is_inside_sim_canvas(mouse_pos, self.camera_canvas_pos) {
             return Ok(None);
         }
-        let (chunk_start, chunks) = self.chunk_manager.get_chunks_for_compute();
-        let obj_matters = [
-            chunks[0].objects_matter.read()?,
-            chunks[1].objects_matter.read()?,
-            chunks[2].objects_matter.read()?,
-            chunks[3].objects_matter.read()?,
-        ];
-        let (chunk_index, grid_index) = sim_chunk_canvas_index(mouse_pos, chunk_start);
-        if obj_matters[chunk_index][grid_index] == self.matter_definitions.empty {
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let obj_matter = chunks
+            .read(ChunkGrid::ObjectsMatter)?
+            .get(mouse_pos)
+            .unwrap_or(self.matter_definitions.empty);
+        if obj_matter == self.matter_definitions.empty {
             Ok(None)
         } else {
             let object_ids =
                 self.tmp_object_ids[sim_canvas_index(mouse_pos, self.camera_canvas_pos)].clone();
-            Ok(Some((obj_matters[chunk_index][grid_index], object_ids)))
+            Ok(Some((obj_matter, object_ids)))
         }
     }
 
@@ -387,29 +2066,20 @@ impl Simulation {
         let EngineApi {
             ecs_world, ..
         } = api;
-        let (chunk_start, chunks) = self.chunk_manager.get_chunks_for_compute();
-        let mut obj_matters = [
-            chunks[0].objects_matter.write()?,
-            chunks[1].objects_matter.write()?,
-            chunks[2].objects_matter.write()?,
-            chunks[3].objects_matter.write()?,
-        ];
-        let mut obj_colors = [
-            chunks[0].objects_color.write()?,
-            chunks[1].objects_color.write()?,
-            chunks[2].objects_color.write()?,
-            chunks[3].objects_color.write()?,
-        ];
-        for (id, (pixel_data, temp_canvas_pixels, pos, angle)) in
-            ecs_world.query_mut::<(&PixelData, &mut Vec<TempPixel>, &mut Position, &mut Angle)>()
-        {
-            *temp_canvas_pixels = get_alive_pixels(pixel_data, pos.0, angle.0, id);
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let mut obj_matters = chunks.write(ChunkGrid::ObjectsMatter)?;
+        let mut obj_colors = chunks.write(ChunkGrid::ObjectsColor)?;
+        for id in self.objects_in_sim_area() {
+            let (pixel_data, temp_canvas_pixels, pos, angle) = match ecs_world
+                .query_one_mut::<(&PixelData, &mut Vec<TempPixel>, &Position, &Angle)>(id)
+            {
+                std::result::Result::Ok(object) => object,
+                _ => continue,
+            };
+            write_alive_pixels(pixel_data, pos.0, angle.0, id, temp_canvas_pixels);
             for &tmp_pixel in temp_canvas_pixels.iter() {
-                if is_inside_sim_canvas(tmp_pixel.canvas_pos, self.camera_canvas_pos) {
-                    let (chunk_index, grid_index) =
-                        sim_chunk_canvas_index(tmp_pixel.canvas_pos, chunk_start);
-                    obj_matters[chunk_index][grid_index] = tmp_pixel.matter;
-                    obj_colors[chunk_index][grid_index] = tmp_pixel.color;
+                if obj_matters.set(tmp_pixel.canvas_pos, tmp_pixel.matter).is_some() {
+                    obj_colors.set(tmp_pixel.canvas_pos, tmp_pixel.color);
                     self.tmp_object_ids
                         [sim_canvas_index(tmp_pixel.canvas_pos, self.camera_canvas_pos)]
                     .push(tmp_pixel.entity);
@@ -419,11 +2089,177 @@ impl Simulation {
         Ok(())
     }
 
+    /// Push objects resting on conveyors sideways and objects above fans upwards.
+    /// Uses object temp pixels written this step, so must run after `write_pixel_objects_to_grid`
+    fn apply_terrain_forces(&mut self, api: &mut EngineApi<InputAction>) -> Result<()> {
+        let EngineApi {
+            ecs_world,
+            physics_world,
+            ..
+        } = api;
+        let definitions = &self.matter_definitions.definitions;
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let matters = chunks.read(ChunkGrid::Matter)?;
+        for (_id, (rb, temp_canvas_pixels)) in
+            ecs_world.query_mut::<(&RigidBodyHandle, &Vec<TempPixel>)>()
+        {
+            let mut force = Vector2::new(0.0, 0.0);
+            for tmp_pixel in temp_canvas_pixels.iter() {
+                for i in 1..=FAN_REACH {
+                    let matter = match matters.get(tmp_pixel.canvas_pos - Vector2::new(0, i)) {
+                        Some(matter) => &definitions[matter as usize],
+                        None => break,
+                    };
+                    // Conveyors only affect pixels directly on top of them
+                    if i == 1 {
+                        if matter
+                            .characteristics
+                            .contains(MatterCharacteristic::CONVEYOR_LEFT)
+                        {
+                            force.x -= CONVEYOR_FORCE;
+                        } else if matter
+                            .characteristics
+                            .contains(MatterCharacteristic::CONVEYOR_RIGHT)
+                        {
+                            force.x += CONVEYOR_FORCE;
+                        }
+                    }
+                    if matter.characteristics.contains(MatterCharacteristic::FAN) {
+                        force.y += FAN_FORCE;
+                        break;
+                    }
+                    if matter.state == MatterState::Solid
+                        || matter.state == MatterState::SolidGravity
+                    {
+                        break;
+                    }
+                }
+            }
+            if force.x != 0.0 || force.y != 0.0 {
+                let rigid_body = &mut physics_world.physics.bodies[*rb];
+                rigid_body.add_force(vector![force.x, force.y], true);
+            }
+        }
+        Ok(())
+    }
+
+    /// Scorch object pixels next to burning matter, darkening them until fire destroys them.
+    /// Uses object temp pixels written this step, so must run after `write_pixel_objects_to_grid`
+    fn char_burning_objects(&mut self, api: &mut EngineApi<InputAction>) -> Result<()> {
+        let definitions = &self.matter_definitions.definitions;
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let matters = chunks.read(ChunkGrid::Matter)?;
+        let is_burning = |canvas_pos: Vector2<i32>| {
+            matters.get(canvas_pos).map_or(false, |matter| {
+                definitions[matter as usize]
+                    .characteristics
+                    .contains(MatterCharacteristic::BURNING)
+            })
+        };
+        let neighbors = [(1, 0), (-1, 0), (0, 1), (0, -1)].map(|(x, y)| Vector2::new(x, y));
+        for (_id, (pixel_data, temp_canvas_pixels)) in
+            api.ecs_world.query_mut::<(&mut PixelData, &Vec<TempPixel>)>()
+        {
+            for tmp_pixel in temp_canvas_pixels.iter() {
+                if neighbors
+                    .iter()
+                    .any(|&offset| is_burning(tmp_pixel.canvas_pos + offset))
+                {
+                    let pixel = &mut pixel_data.pixels[tmp_pixel.pixel_index];
+                    pixel.char_level = pixel.char_level.saturating_add(CHAR_PER_STEP);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Read blocks that had exploding matter in the latest CA step. They are applied to objects
+    /// right away & to the grid on next step
+    fn read_explosions(&mut self) -> Result<()> {
+        self.ca_simulator.read_explosions(
+            self.matter_definitions.empty,
+            self.explosions.blocks_mut(),
+        )?;
+        self.explosions.update_from_blocks(self.camera_canvas_pos);
+        Ok(())
+    }
+
+    /// Push dynamic objects away from explosions
+    fn apply_explosion_impulses(&mut self, api: &mut EngineApi<InputAction>) {
+        if self.explosions.explosions.is_empty() {
+            return;
+        }
+        let EngineApi {
+            ecs_world,
+            physics_world,
+            ..
+        } = api;
+        let radius = EXPLOSION_RADIUS * *CELL_UNIT_SIZE;
+        for (_id, (rb, pos)) in ecs_world.query_mut::<(&RigidBodyHandle, &Position)>() {
+            let mut impulse = Vector2::new(0.0, 0.0);
+            for explosion in self.explosions.explosions.iter() {
+                if let Some(i) = explosion_impulse(
+                    canvas_pos_to_world_pos(explosion.canvas_pos),
+                    pos.0,
+                    radius,
+                    EXPLOSION_IMPULSE,
+                ) {
+                    impulse += i;
+                }
+            }
+            if impulse.x != 0.0 || impulse.y != 0.0 {
+                let rigid_body = &mut physics_world.physics.bodies[*rb];
+                rigid_body.apply_impulse(vector![impulse.x, impulse.y], true);
+            }
+        }
+    }
+
+    /// Carve object pixels inside explosions, which deforms (and splits) the objects after CA
+    /// step, and ignite matter that explodes so explosions chain through it.
+    /// Must run after `write_pixel_objects_to_grid`
+    fn apply_explosions_to_grid(&mut self) -> Result<()> {
+        if self.explosions.explosions.is_empty() {
+            return Ok(());
+        }
+        let empty = self.matter_definitions.empty;
+        let definitions = &self.matter_definitions.definitions;
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let mut matters = chunks.write(ChunkGrid::Matter)?;
+        let mut obj_matters = chunks.write(ChunkGrid::ObjectsMatter)?;
+        let radius = EXPLOSION_RADIUS as i32;
+        for explosion in self.explosions.explosions.iter() {
+            for y in -radius..=radius {
+                for x in -radius..=radius {
+                    if x * x + y * y > radius * radius {
+                        continue;
+                    }
+                    let pos = explosion.canvas_pos + Vector2::new(x, y);
+                    let matter = match matters.get(pos) {
+                        Some(matter) => matter,
+                        None => continue,
+                    };
+                    obj_matters.set(pos, empty);
+                    if definitions[matter as usize]
+                        .characteristics
+                        .contains(MatterCharacteristic::EXPLODES)
+                    {
+                        matters.set(pos, explosion.matter);
+                    }
+                }
+            }
+        }
+        self.explosions.clear();
+        Ok(())
+    }
+
     /// 1. Compare temp pixels that were written to canvas before ca simulation now after simulation
     /// 2. If they changed, object is determined to be deformed
     /// 3. Update object...
     pub fn update_objects_from_grid(&mut self, api: &mut EngineApi<InputAction>) -> Result<()> {
         let deformed_objects = self.get_deformed_object_bitmaps(api)?;
+        self.deformed_object_positions.clear();
+        self.deformed_object_positions
+            .extend(deformed_objects.iter().map(|(_, _, _, pos, ..)| pos.0));
         self.clear_object_pixels_from_grid(api)?;
         self.add_deformed_objects_to_world(api, deformed_objects)?;
         Ok(())
@@ -515,14 +2351,19 @@ impl Simulation {
                 ecs_world.despawn(prev_obj)?;
             } else {
                 physics_world.remove_physics(rb);
+                // Pieces of a static object stay static & keep its collision groups
+                let is_fixed = ecs_world.get::<FixedObject>(prev_obj).is_ok();
+                let collision = ecs_world.get::<ObjectCollision>(prev_obj).ok().map(|c| *c);
                 // Create new (first should retain the id)
                 for (count, (pixel_data, pos, lin_vel, angle, ang_vel, colliders)) in
                     add_objects.into_iter().enumerate()
                 {
-                    let id = if count == 0 {
-                        prev_obj
+                    // New pieces are aged from now, first keeps the age of the object it split from
+                    let (id, spawn_step) = if count == 0 {
+                        let spawn_step = ecs_world.get::<SpawnStep>(prev_obj).ok().map(|s| *s);
+                        (prev_obj, spawn_step.unwrap_or(SpawnStep(self.step_count)))
                     } else {
-                        ecs_world.reserve_entity()
+                        (ecs_world.reserve_entity(), SpawnStep(self.step_count))
                     };
                     ecs_world.insert(
                         id,
@@ -537,6 +2378,18 @@ impl Simulation {
                             colliders,
                         ),
                     )?;
+                    ecs_world.insert_one(id, spawn_step)?;
+                    if is_fixed {
+                        make_pixel_object_fixed(ecs_world, &mut physics_world.physics, id)?;
+                    }
+                    if let Some(collision) = collision {
+                        set_pixel_object_collision(
+                            ecs_world,
+                            &mut physics_world.physics,
+                            id,
+                            collision,
+                        )?;
+                    }
                 }
             }
         }
@@ -551,70 +2404,82 @@ impl Simulation {
         let EngineApi {
             ecs_world, ..
         } = api;
-        let (chunk_start, chunks) = self.chunk_manager.get_chunks_for_compute();
-        let obj_matters = [
-            chunks[0].objects_matter.read()?,
-            chunks[1].objects_matter.read()?,
-            chunks[2].objects_matter.read()?,
-            chunks[3].objects_matter.read()?,
-        ];
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let obj_matters = chunks.read(ChunkGrid::ObjectsMatter)?;
         let obj_ids = &self.tmp_object_ids;
-        let mut objects_to_check = vec![];
-        for (id, (rb, pixel_data, temp_canvas_pixels, pos, lin_vel, angle, ang_vel)) in
-            &mut ecs_world.query::<(
-                &RigidBodyHandle,
-                &PixelData,
-                &Vec<TempPixel>,
-                &Position,
-                &LinearVelocity,
-                &Angle,
-                &AngularVelocity,
-            )>()
-        {
-            objects_to_check.push((
-                id,
-                *rb,
-                pixel_data.clone(),
-                temp_canvas_pixels.clone(),
-                *pos,
-                *lin_vel,
-                *angle,
-                *ang_vel,
-            ));
-        }
+        let object_ids = self.objects_in_sim_area();
+        let mut queries = object_ids
+            .iter()
+            .filter_map(|&id| {
+                ecs_world
+                    .query_one::<(
+                        &RigidBodyHandle,
+                        &PixelData,
+                        &Vec<TempPixel>,
+                        &Position,
+                        &LinearVelocity,
+                        &Angle,
+                        &AngularVelocity,
+                    )>(id)
+                    .ok()
+                    .map(|query| (id, query))
+            })
+            .collect::<Vec<_>>();
+        // Objects added after the step was submitted haven't been written to grid yet
+        let objects_to_check = queries
+            .iter_mut()
+            .filter_map(|(id, query)| query.get().map(|object| (*id, object)))
+            .filter(|(_id, (_, _, temp_canvas_pixels, ..))| !temp_canvas_pixels.is_empty())
+            .collect::<Vec<_>>();
         let deformed_objects = objects_to_check
-            .into_par_iter()
+            .par_iter()
             .filter_map(
-                |(id, rb, pixel_data, temp_canvas_pixels, pos, lin_vel, angle, ang_vel)| {
-                    let mut bitmap = vec![0.0; (pixel_data.width * pixel_data.height) as usize];
-                    let mut should_update_object = false;
-                    let mut pixel_count = temp_canvas_pixels.len();
-                    for &tmp_pixel in temp_canvas_pixels.iter() {
-                        // Only look inside canvas, deformation can only take place inside it
-                        if is_inside_sim_canvas(tmp_pixel.canvas_pos, self.camera_canvas_pos) {
-                            let canvas_index =
-                                sim_canvas_index(tmp_pixel.canvas_pos, self.camera_canvas_pos);
-                            let obj_id_in_grid =
-                                obj_ids[canvas_index].iter().position(|&i| i == id);
-                            // If object exists in visible canvas grid, mark bitmap 1.0. Else objet should be updated (deformed)
-                            let (chunk_index, grid_index) =
-                                sim_chunk_canvas_index(tmp_pixel.canvas_pos, chunk_start);
-                            if obj_id_in_grid.is_some()
-                                && obj_matters[chunk_index][grid_index]
-                                    != self.matter_definitions.empty
-                            {
+                |&(id, (rb, pixel_data, temp_canvas_pixels, pos, lin_vel, angle, ang_vel))| {
+                    // Only look inside canvas, deformation can only take place inside it.
+                    // None if outside canvas, else whether object still exists in grid
+                    let is_in_grid = |tmp_pixel: &TempPixel| {
+                        let obj_matter = obj_matters.get(tmp_pixel.canvas_pos)?;
+                        let canvas_index =
+                            sim_canvas_index(tmp_pixel.canvas_pos, self.camera_canvas_pos);
+                        let obj_id_in_grid = obj_ids[canvas_index].iter().any(|&i| i == id);
+                        Some(obj_id_in_grid && obj_matter != self.matter_definitions.empty)
+                    };
+                    let num_lost = temp_canvas_pixels
+                        .iter()
+                        .filter(|p| is_in_grid(p) == Some(false))
+                        .count();
+                    let pixel_count = temp_canvas_pixels.len() - num_lost;
+                    // Too small objects will be removed (3 * 3)
+                    if pixel_count <= 9 {
+                        Some((
+                            id,
+                            *rb,
+                            pixel_data.clone(),
+                            *pos,
+                            *lin_vel,
+                            *angle,
+                            *ang_vel,
+                            vec![],
+                        ))
+                    } else if num_lost > 0 {
+                        // Only deformed objects need a bitmap (and a copy of their data).
+                        // If object exists in visible canvas grid, mark bitmap 1.0
+                        let mut bitmap = vec![0.0; (pixel_data.width * pixel_data.height) as usize];
+                        for tmp_pixel in temp_canvas_pixels.iter() {
+                            if is_in_grid(tmp_pixel) == Some(true) {
                                 bitmap[tmp_pixel.pixel_index] = 1.0;
-                            } else {
-                                pixel_count -= 1;
-                                should_update_object = true;
                             }
                         }
-                    }
-                    // Too small objects will be removed (3 * 3)
-                    if pixel_count <= 9 {
-                        Some((id, rb, pixel_data, pos, lin_vel, angle, ang_vel, vec![]))
-                    } else if should_update_object {
-                        Some((id, rb, pixel_data, pos, lin_vel, angle, ang_vel, bitmap))
+                        Some((
+                            id,
+                            *rb,
+                            pixel_data.clone(),
+                            *pos,
+                            *lin_vel,
+                            *angle,
+                            *ang_vel,
+                            bitmap,
+                        ))
                     } else {
                         None
                     }
@@ -631,28 +2496,19 @@ impl Simulation {
         let EngineApi {
             ecs_world, ..
         } = api;
-        let (chunk_start, chunks) = self.chunk_manager.get_chunks_for_compute();
-        let mut obj_matters = [
-            chunks[0].objects_matter.write()?,
-            chunks[1].objects_matter.write()?,
-            chunks[2].objects_matter.write()?,
-            chunks[3].objects_matter.write()?,
-        ];
-        let mut obj_colors = [
-            chunks[0].objects_color.write()?,
-            chunks[1].objects_color.write()?,
-            chunks[2].objects_color.write()?,
-            chunks[3].objects_color.write()?,
-        ];
-        for (_id, temp_canvas_pixels) in &mut ecs_world.query::<&mut Vec<TempPixel>>() {
+        let chunks = self.chunk_manager.sim_chunks(self.camera_canvas_pos);
+        let mut obj_matters = chunks.write(ChunkGrid::ObjectsMatter)?;
+        let mut obj_colors = chunks.write(ChunkGrid::ObjectsColor)?;
+        for id in self.objects_in_sim_area() {
+            let temp_canvas_pixels = match ecs_world.query_one_mut::<&mut Vec<TempPixel>>(id) {
+                std::result::Result::Ok(temp_canvas_pixels) => temp_canvas_pixels,
+                _ => continue,
+            };
             for &tmp_pixel in temp_canvas_pixels.iter() {
-                if is_inside_sim_canvas(tmp_pixel.canvas_pos, self.camera_canvas_pos) {
-                    let (chunk_index, grid_index) =
-                        sim_chunk_canvas_index(tmp_pixel.canvas_pos, chunk_start);
+                if obj_matters.set(tmp_pixel.canvas_pos, 0x0).is_some() {
+                    obj_colors.set(tmp_pixel.canvas_pos, 0x0);
                     let canvas_index =
                         sim_canvas_index(tmp_pixel.canvas_pos, self.camera_canvas_pos);
-                    obj_matters[chunk_index][grid_index] = 0x0;
-                    obj_colors[chunk_index][grid_index] = 0x0;
                     if let Some(pos) = self.tmp_object_ids[canvas_index]
                         .iter()
                         .position(|x| *x == tmp_pixel.entity)
@@ -666,116 +2522,201 @@ impl Simulation {
         Ok(())
     }
 
-    pub fn update_physics_boundaries(&mut self, api: &mut EngineApi<InputAction>) -> Result<()> {
-        let EngineApi {
-            ecs_world,
-            physics_world,
-            ..
-        } = api;
+    /// Update boundary objects from CA bitmaps. In the background mode colliders of changed
+    /// bitmaps are formed on rayon's thread pool and replace old ones on the next update.
+    /// `collides_with` is the mask of object layers boundaries collide with
+    pub fn update_physics_boundaries(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        background: bool,
+        collides_with: u32,
+    ) -> Result<()> {
+        if collides_with != self.boundaries.collides_with {
+            self.boundaries.collides_with = collides_with;
+            self.update_boundary_collision_groups(api);
+        }
+        // Colliders formed since last update (should be ready by now)
+        if let Some(pending) = self.boundaries.pending_objects.take() {
+            let objects_data = pending.recv()?;
+            self.replace_boundary_objects(api, objects_data)?;
+        }
         self.ca_simulator.update_bitmaps(
             &mut self.boundaries.solid_bitmap,
             &mut self.boundaries.powder_bitmap,
             &mut self.boundaries.liquid_bitmap,
-            &mut self.boundaries.solids_changed,
-            &mut self.boundaries.powders_changed,
-            &mut self.boundaries.liquids_changed,
+            &mut self.boundaries.solid_regions_changed,
+            &mut self.boundaries.powder_regions_changed,
+            &mut self.boundaries.liquid_regions_changed,
         )?;
 
-        let mut changed_bitmaps = vec![];
-        let mut remove_objects = vec![];
-        if self.boundaries.solids_changed {
-            // Remove old objects
-            remove_objects.extend(&self.boundaries.solid_objects);
-            self.boundaries.solid_objects.clear();
-            // Set creation to occur
-            changed_bitmaps.push((&self.boundaries.solid_bitmap, MatterState::Solid));
-            self.boundaries.solids_changed = false;
-        }
-        if self.boundaries.powders_changed {
-            remove_objects.extend(&self.boundaries.powder_objects);
-            self.boundaries.powder_objects.clear();
-            changed_bitmaps.push((&self.boundaries.powder_bitmap, MatterState::Powder));
-            self.boundaries.powders_changed = false;
-        }
-        if self.boundaries.liquids_changed {
-            remove_objects.extend(&self.boundaries.liquid_objects);
-            self.boundaries.liquid_objects.clear();
-            changed_bitmaps.push((&self.boundaries.liquid_bitmap, MatterState::Liquid));
-            self.boundaries.liquids_changed = false;
+        // Regions are positioned relative to where they were built
+        if self.boundaries.built_at != Some(self.camera_pos) {
+            self.boundaries.mark_all_changed();
+            self.boundaries.built_at = Some(self.camera_pos);
+        }
+        let boundaries = &mut self.boundaries;
+        let mut changed_bitmaps: Vec<(&[f64], MatterState, Vec<usize>)> = vec![];
+        for (bitmap, regions_changed, state) in [
+            (
+                &boundaries.solid_bitmap,
+                &mut boundaries.solid_regions_changed,
+                MatterState::Solid,
+            ),
+            (
+                &boundaries.powder_bitmap,
+                &mut boundaries.powder_regions_changed,
+                MatterState::Powder,
+            ),
+            (
+                &boundaries.liquid_bitmap,
+                &mut boundaries.liquid_regions_changed,
+                MatterState::Liquid,
+            ),
+        ] {
+            let regions = take_changed_regions(regions_changed);
+            if !regions.is_empty() {
+                changed_bitmaps.push((bitmap, state, regions));
+            }
+        }
+        if changed_bitmaps.is_empty() {
+            return Ok(());
         }
 
         // Create boundary object data (with par iters) (creates colliders etc...)
-        let add_objects_data = changed_bitmaps
-            .par_iter()
-            .map(|(bitmap, state)| {
-                (
-                    create_boundary_object_data(
-                        self.camera_pos,
-                        bitmap,
-                        *state == MatterState::Liquid,
-                    ),
-                    *state,
-                )
-            })
-            .collect::<Vec<(Vec<(Vector2<f32>, f32, Collider)>, MatterState)>>();
+        let pos_offset = self.camera_pos;
+        if background {
+            // Bitmaps keep being diffed, so the task gets copies of them
+            let bitmaps = changed_bitmaps
+                .into_iter()
+                .map(|(bitmap, state, regions)| (bitmap.to_vec(), state, regions))
+                .collect::<Vec<(Vec<f64>, MatterState, Vec<usize>)>>();
+            let (sender, receiver) = channel();
+            rayon::spawn(move || {
+                let changed_bitmaps = bitmaps
+                    .iter()
+                    .map(|(bitmap, state, regions)| (bitmap.as_slice(), *state, regions.clone()))
+                    .collect::<Vec<(&[f64], MatterState, Vec<usize>)>>();
+                let _ = sender.send(create_boundaries_object_data(pos_offset, &changed_bitmaps));
+            });
+            self.boundaries.pending_objects = Some(receiver);
+            Ok(())
+        } else {
+            let objects_data = create_boundaries_object_data(pos_offset, &changed_bitmaps);
+            self.replace_boundary_objects(api, objects_data)
+        }
+    }
 
-        // remove previous boundary objects
-        for e in remove_objects {
-            let rb = *ecs_world.get::<RigidBodyHandle>(e).unwrap();
-            physics_world.remove_physics(rb);
-            ecs_world.despawn(e)?;
+    /// Mark boundary regions overlapping canvas rectangle `min` - `max` (inclusive) changed, so
+    /// only their colliders are rebuilt after e.g. painting there. Parts outside simulated area
+    /// are ignored
+    pub fn mark_boundaries_changed(&mut self, min: Vector2<i32>, max: Vector2<i32>) {
+        let corner = self.camera_canvas_pos - *HALF_CANVAS;
+        let size = SIM_CANVAS_SIZE.cast::<i32>().unwrap();
+        let (min, max) = (min - corner, max - corner);
+        if max.x < 0 || max.y < 0 || min.x >= size.x || min.y >= size.y {
+            return;
         }
+        let to_bitmap = |pos: Vector2<i32>| pos.map(|v| v.max(0) as usize / *BITMAP_RATIO as usize);
+        self.boundaries.mark_rect_changed(to_bitmap(min), to_bitmap(max));
+    }
 
-        // Create new objects & update boundary data
-        let add_objects = add_objects_data
-            .into_iter()
-            .map(|(obj_data, state)| {
-                obj_data
-                    .into_iter()
-                    .map(|(pos, angle, collider)| {
-                        let id = ecs_world.reserve_entity();
-                        if state == MatterState::Liquid {
-                            (
-                                id,
-                                invisible_sensor_object(
-                                    id,
-                                    &mut physics_world.physics,
-                                    pos,
-                                    angle,
-                                    vec![collider],
-                                ),
-                                state,
-                            )
-                        } else {
-                            (
-                                id,
-                                invisible_static_object(
-                                    id,
-                                    &mut physics_world.physics,
-                                    pos,
-                                    angle,
-                                    vec![collider],
-                                ),
-                                state,
-                            )
-                        }
-                    })
-                    .collect::<Vec<(Entity, InvisibleObject, MatterState)>>()
-            })
-            .flatten()
-            .collect::<Vec<(Entity, InvisibleObject, MatterState)>>();
-        for (entity, o_components, state) in add_objects {
-            match state {
-                MatterState::Liquid => self.boundaries.liquid_objects.push(entity),
-                MatterState::Solid => self.boundaries.solid_objects.push(entity),
-                MatterState::Powder => self.boundaries.powder_objects.push(entity),
-                _ => (),
+    /// Mark boundary regions of cells painted along `line` with brush `radius` changed
+    fn mark_line_boundaries_changed(&mut self, line: &[Vector2<i32>], radius: i32) {
+        let xs = line.iter().map(|pos| pos.x);
+        let ys = line.iter().map(|pos| pos.y);
+        if let (Some(min_x), Some(max_x), Some(min_y), Some(max_y)) =
+            (xs.clone().min(), xs.max(), ys.clone().min(), ys.max())
+        {
+            self.mark_boundaries_changed(
+                Vector2::new(min_x - radius, min_y - radius),
+                Vector2::new(max_x + radius, max_y + radius),
+            );
+        }
+    }
+
+    /// Replace boundary objects of each changed region in `objects_data` with the new ones
+    fn replace_boundary_objects(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        objects_data: Vec<BoundaryObjectData>,
+    ) -> Result<()> {
+        let EngineApi {
+            ecs_world,
+            physics_world,
+            ..
+        } = api;
+        let groups = boundary_interaction_groups(self.boundaries.collides_with);
+        for (obj_data, state, region) in objects_data {
+            let objects = match state {
+                MatterState::Liquid => &mut self.boundaries.liquid_objects[region],
+                MatterState::Solid => &mut self.boundaries.solid_objects[region],
+                MatterState::Powder => &mut self.boundaries.powder_objects[region],
+                _ => continue,
+            };
+            // remove previous boundary objects of the region
+            for e in objects.drain(..) {
+                let rb = *ecs_world.get::<RigidBodyHandle>(e).unwrap();
+                physics_world.remove_physics(rb);
+                ecs_world.despawn(e)?;
+            }
+            // Create new objects
+            for (pos, angle, collider) in obj_data {
+                let id = ecs_world.reserve_entity();
+                let o_components = if state == MatterState::Liquid {
+                    invisible_sensor_object(
+                        id,
+                        &mut physics_world.physics,
+                        pos,
+                        angle,
+                        vec![collider],
+                    )
+                } else {
+                    invisible_static_object(
+                        id,
+                        &mut physics_world.physics,
+                        pos,
+                        angle,
+                        vec![collider],
+                    )
+                };
+                let rb = o_components.0;
+                ecs_world.insert(id, o_components)?;
+                objects.push(id);
+                let physics = &mut physics_world.physics;
+                for handle in physics.bodies[rb].colliders() {
+                    set_collider_groups(&mut physics.colliders[*handle], groups, false);
+                }
             }
-            api.ecs_world.insert(entity, o_components)?;
         }
+        self.boundaries.version += 1;
         Ok(())
     }
 
+    /// Set collision groups of current boundary colliders after boundary layers changed
+    fn update_boundary_collision_groups(&mut self, api: &mut EngineApi<InputAction>) {
+        let EngineApi {
+            ecs_world,
+            physics_world,
+            ..
+        } = api;
+        let groups = boundary_interaction_groups(self.boundaries.collides_with);
+        let physics = &mut physics_world.physics;
+        for e in self
+            .boundaries
+            .solid_objects
+            .iter()
+            .chain(self.boundaries.powder_objects.iter())
+            .chain(self.boundaries.liquid_objects.iter())
+            .flatten()
+        {
+            if let std::result::Result::Ok(rb) = ecs_world.get::<RigidBodyHandle>(*e) {
+                for handle in physics.bodies[*rb].colliders() {
+                    set_collider_groups(&mut physics.colliders[*handle], groups, false);
+                }
+            }
+        }
+    }
+
     pub fn add_dynamic_pixel_object(
         &mut self,
         ecs_world: &mut World,
@@ -807,6 +2748,20 @@ impl Simulation {
                 colliders,
             ),
         )?;
+        ecs_world.insert_one(entity, SpawnStep(self.step_count))?;
+        let range =
+            EntityChunkIndex::object_chunk_range(pos, &*ecs_world.get::<PixelData>(entity)?);
+        self.object_chunk_index.update(entity, range);
         Ok(entity)
     }
+
+    /// Pixel objects overlapping any of the chunks
+    pub fn entities_in_chunks(&self, chunk_positions: &[Vector2<i32>]) -> Vec<Entity> {
+        self.object_chunk_index.entities_in_chunks(chunk_positions)
+    }
+
+    /// Pixel objects that may have pixels in simulated area
+    fn objects_in_sim_area(&self) -> Vec<Entity> {
+        self.entities_in_chunks(&self.chunk_manager.interaction_chunks)
+    }
 }
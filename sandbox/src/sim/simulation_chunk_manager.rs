@@ -1,90 +1,282 @@
 use std::{
-    collections::{HashMap, HashSet, VecDeque},
+    collections::{hash_map::DefaultHasher, HashMap, HashSet, VecDeque},
     fs,
-    path::PathBuf,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
     sync::Arc,
 };
 
 use anyhow::*;
 use cgmath::{InnerSpace, Vector2};
-use corrode::renderer::{create_device_image_with_usage, DeviceImageView};
-use image::{ImageBuffer, Rgba};
+use corrode::renderer::{create_shared_device_image, DeviceImageView};
+use serde::{Deserialize, Serialize};
 use vulkano::{
     buffer::CpuAccessibleBuffer,
     command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, PrimaryCommandBuffer},
     device::Queue,
     format::Format,
     image::ImageUsage,
-    sync::GpuFuture,
+    sync::{FenceSignalFuture, GpuFuture},
 };
 
 use crate::{
     matter::MatterDefinitions,
-    sim::{empty_u32, write_canvas_chunk_to_matter_image, write_matter_image_to_canvas_chunk},
-    utils::{load_bitmap_image_from_path, BitmapImage},
+    sim::{
+        empty_u32, matter_grid_to_image, shared_empty_u32, ChunkJobResult, ChunkJobs, SimChunks,
+    },
+    utils::BitmapImage,
     CANVAS_CHUNK_SIZE, CELL_OFFSETS_NINE, HALF_CANVAS, MAX_GPU_CHUNKS, SIM_CANVAS_SIZE,
 };
 
+/// Chunk upload or clear running on transfer queue
+pub type TransferFuture = Arc<FenceSignalFuture<Box<dyn GpuFuture>>>;
+
+/// Chunks kept above & below camera's chunk in vertical slice streaming
+pub const VERTICAL_SLICE_REACH: i32 = 2;
+/// Camera falling faster than this (cells per step) prefetches chunks below the vertical slice
+pub const FAST_FALL_SPEED: i32 = 4;
+/// Rows of chunks prefetched below the vertical slice when falling fast
+pub const PREFETCH_ROWS: i32 = 2;
+/// Prefetched chunk uploads per step. Spreads prefetching so it doesn't hitch itself
+pub const MAX_PREFETCH_PER_STEP: usize = 1;
+
+/// Gpu memory of a chunk: matter in & out, object matter & color grids and an 8 bit rgba image
+pub fn gpu_chunk_bytes() -> usize {
+    (SIM_CANVAS_SIZE.x * SIM_CANVAS_SIZE.y) as usize * 4 * 5
+}
+
+/// Version of the binary chunk format written by `encode_chunk`
+pub const CHUNK_FORMAT_VERSION: u32 = 1;
+/// Extension of binary chunk files, e.g. `chunk_1_-2.bin`
+pub const CHUNK_FILE_EXTENSION: &str = "bin";
+/// Magic bytes starting binary chunks
+const CHUNK_MAGIC: &[u8; 4] = b"SBCK";
+/// Bytes of magic, version, width & height
+const CHUNK_HEADER_SIZE: usize = 16;
+/// Bytes of a run length & its matter
+const CHUNK_RUN_SIZE: usize = 8;
+
+/// Encode chunk's matter grid losslessly as a header followed by runs of the same matter, each
+/// a little endian u32 run length & matter id. Mostly uniform chunks take only a few bytes
+pub fn encode_chunk(matter_grid: &[u32]) -> Vec<u8> {
+    debug_assert_eq!(matter_grid.len(), (CANVAS_CHUNK_SIZE.x * CANVAS_CHUNK_SIZE.y) as usize);
+    let mut data = Vec::with_capacity(CHUNK_HEADER_SIZE + CHUNK_RUN_SIZE);
+    data.extend_from_slice(CHUNK_MAGIC);
+    for value in [CHUNK_FORMAT_VERSION, CANVAS_CHUNK_SIZE.x, CANVAS_CHUNK_SIZE.y] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    let mut cells = matter_grid.iter().peekable();
+    while let Some(&matter) = cells.next() {
+        let mut length = 1u32;
+        while cells.next_if_eq(&&matter).is_some() {
+            length += 1;
+        }
+        data.extend_from_slice(&length.to_le_bytes());
+        data.extend_from_slice(&matter.to_le_bytes());
+    }
+    data
+}
+
+/// Decode chunk written by `encode_chunk`, checking it's intact & contains only matter ids below
+/// `num_matters`
+pub fn decode_chunk(data: &[u8], num_matters: usize) -> Result<Vec<u32>> {
+    if data.len() < CHUNK_HEADER_SIZE || &data[..4] != CHUNK_MAGIC {
+        bail!("Not a binary chunk");
+    }
+    let read_u32 = |bytes: &[u8]| u32::from_le_bytes(bytes.try_into().unwrap());
+    let version = read_u32(&data[4..8]);
+    if version != CHUNK_FORMAT_VERSION {
+        bail!("Unsupported chunk format version {}", version);
+    }
+    let size = Vector2::new(read_u32(&data[8..12]), read_u32(&data[12..16]));
+    if size != *CANVAS_CHUNK_SIZE {
+        bail!(
+            "Chunk is {}x{}, expected canvas chunk size {}x{}",
+            size.x,
+            size.y,
+            CANVAS_CHUNK_SIZE.x,
+            CANVAS_CHUNK_SIZE.y
+        );
+    }
+    let runs = &data[CHUNK_HEADER_SIZE..];
+    if runs.len() % CHUNK_RUN_SIZE != 0 {
+        bail!("Chunk data is truncated");
+    }
+    let num_cells = (size.x * size.y) as usize;
+    let mut matter_grid = Vec::with_capacity(num_cells);
+    for run in runs.chunks_exact(CHUNK_RUN_SIZE) {
+        let length = read_u32(&run[..4]) as usize;
+        let matter = read_u32(&run[4..]);
+        if matter as usize >= num_matters {
+            bail!("Chunk contains unknown matter {}", matter);
+        }
+        if matter_grid.len() + length > num_cells {
+            bail!("Chunk has more than {} cells", num_cells);
+        }
+        matter_grid.resize(matter_grid.len() + length, matter);
+    }
+    if matter_grid.len() != num_cells {
+        bail!("Chunk has {} of {} cells", matter_grid.len(), num_cells);
+    }
+    Ok(matter_grid)
+}
+
+/// Matter grid of encoded chunk `data`. Chunks without data are empty
+pub fn chunk_matter_grid(
+    data: Option<&[u8]>,
+    matter_definitions: &MatterDefinitions,
+) -> Result<Vec<u32>> {
+    match data {
+        Some(data) => decode_chunk(data, matter_definitions.definitions.len()),
+        None => Ok(vec![
+            matter_definitions.empty;
+            (CANVAS_CHUNK_SIZE.x * CANVAS_CHUNK_SIZE.y) as usize
+        ]),
+    }
+}
+
+/// Which chunks around the camera are kept on gpu in chunked simulation. Simulated area is
+/// the same 2 x 2 chunks in both, this only affects what's loaded ahead of movement
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChunkStreaming {
+    /// 3 x 3 chunks around camera, suits movement in any direction
+    Radial,
+    /// 3 x 5 chunks tall slice around camera & chunks below it prefetched when falling fast.
+    /// Suits deep vertical digging
+    VerticalSlice,
+}
+
+/// Where a world chunk's matter currently is, e.g. for the world map
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChunkLoadState {
+    /// On gpu & simulated around the camera
+    Simulated,
+    /// On gpu, drawn but not simulated
+    Gpu,
+    /// Queued or being decoded for upload to gpu
+    Loading,
+    /// Queued to unload or being encoded after unloading
+    Unloading,
+    /// Encoded on cpu only
+    Cpu,
+}
+
+impl ChunkLoadState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChunkLoadState::Simulated => "Simulated",
+            ChunkLoadState::Gpu => "On gpu",
+            ChunkLoadState::Loading => "Loading",
+            ChunkLoadState::Unloading => "Unloading",
+            ChunkLoadState::Cpu => "On cpu",
+        }
+    }
+}
+
 pub struct WorldChunk {
-    pub image: BitmapImage,
+    /// Matter grid encoded by `encode_chunk`, None for chunks that have never had matter.
+    /// Stale while the chunk is on gpu
+    pub data: Option<Vec<u8>>,
     pub gpu_chunk: Option<GpuChunk>,
 }
 
 impl WorldChunk {
     fn empty() -> WorldChunk {
         WorldChunk {
-            image: BitmapImage::empty(*CANVAS_CHUNK_SIZE, *CANVAS_CHUNK_SIZE),
+            data: None,
             gpu_chunk: None,
         }
     }
 
-    pub fn load_from_disk(image_path: PathBuf) -> WorldChunk {
-        let map_img = match load_bitmap_image_from_path(image_path) {
-            std::result::Result::Ok(loaded_image) => {
-                debug!("Found map image");
-                loaded_image
+    /// Load chunk from a binary chunk file, mapping its matter ids from the map's palette to
+    /// current ones with `matter_remap`. A missing or broken file results in an empty chunk
+    /// and an error describing it, so the rest of the map can still be loaded
+    pub fn load_from_disk(path: PathBuf, matter_remap: &[u32]) -> (WorldChunk, Option<Error>) {
+        let loaded = fs::read(&path).map_err(Error::from).and_then(|data| {
+            let matter_grid = decode_chunk(&data, matter_remap.len())?;
+            let remapped = matter_grid
+                .iter()
+                .map(|&matter| matter_remap[matter as usize])
+                .collect::<Vec<u32>>();
+            if remapped == matter_grid {
+                Ok(data)
+            } else {
+                Ok(encode_chunk(&remapped))
             }
+        });
+        match loaded {
+            std::result::Result::Ok(data) => (
+                WorldChunk {
+                    data: Some(data),
+                    gpu_chunk: None,
+                },
+                None,
+            ),
             Err(e) => {
-                debug!("{}. No image. Loading empty chunk", e.to_string(),);
-                BitmapImage::empty(*CANVAS_CHUNK_SIZE, *CANVAS_CHUNK_SIZE)
+                let e = e.context(format!("Failed to load chunk {:?}", path));
+                warn!("{:#}. Loading empty chunk", e);
+                (WorldChunk::empty(), Some(e))
             }
-        };
-        WorldChunk {
-            image: map_img,
-            gpu_chunk: None,
         }
     }
 
-    /// Adds gpu chunk to use by this world chunk and fills it with the content from Bitmap Image
+    /// Adds gpu chunk to use by this world chunk and uploads its matter grid (decoded from its
+    /// data) to it on transfer queue through `staging`, which must not be in use. Returned future
+    /// must finish before the chunk is used
     pub fn write_to_gpu(
         &mut self,
-        matter_definitions: &MatterDefinitions,
-        chunk: GpuChunk,
-    ) -> Result<()> {
+        matter_grid: &[u32],
+        mut chunk: GpuChunk,
+        transfer_queue: Arc<Queue>,
+        staging: Arc<CpuAccessibleBuffer<[u32]>>,
+    ) -> Result<TransferFuture> {
+        // Chunk may still be cleared from its previous use
+        chunk.wait_transfer()?;
+        staging.write()?.copy_from_slice(matter_grid);
+        let mut builder = AutoCommandBufferBuilder::primary(
+            transfer_queue.device().clone(),
+            transfer_queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        builder
+            .copy_buffer(staging.clone(), chunk.matter_in.clone())?
+            .copy_buffer(staging, chunk.matter_out.clone())?;
+        let command_buffer = builder.build()?;
+        let finished = command_buffer.execute(transfer_queue)?;
+        let future = Arc::new(finished.boxed().then_signal_fence_and_flush()?);
+        chunk.transfer = Some(future.clone());
         self.gpu_chunk = Some(chunk);
-        write_matter_image_to_canvas_chunk(
-            &self.image,
-            matter_definitions,
-            self.gpu_chunk.as_ref().unwrap().get_matter_input(),
-            self.gpu_chunk.as_ref().unwrap().get_matter_output(),
-        )
+        Ok(future)
     }
 
-    /// Writes gpu content to Bitmap Image and returns the gpu chunk removing it from use by this world chunk
+    /// Copies gpu matter grid for encoding to chunk data and returns the gpu chunk removing it
+    /// from use by this world chunk. The chunk is cleared on transfer queue, so it must be waited
+    /// for before reuse
     pub fn unload_from_gpu(
         &mut self,
-        matter_definitions: &MatterDefinitions,
-        queue: Arc<Queue>,
-    ) -> Result<GpuChunk> {
-        self.image = write_canvas_chunk_to_matter_image(
-            matter_definitions,
-            self.gpu_chunk.as_ref().unwrap().get_matter_input(),
-        )?;
-        self.clear_data(queue)?;
-        Ok(self.gpu_chunk.take().unwrap())
+        transfer_queue: Arc<Queue>,
+        zero_buffer: Arc<CpuAccessibleBuffer<[u32]>>,
+    ) -> Result<(GpuChunk, TransferFuture, Vec<u32>)> {
+        let matter_grid = self
+            .gpu_chunk
+            .as_ref()
+            .unwrap()
+            .get_matter_input()
+            .read()?
+            .to_vec();
+        let future = self.clear_data(transfer_queue, zero_buffer)?;
+        let mut chunk = self.gpu_chunk.take().unwrap();
+        chunk.transfer = Some(future.clone());
+        Ok((chunk, future, matter_grid))
     }
 
-    fn clear_data(&self, queue: Arc<Queue>) -> Result<()> {
+    /// Clear objects & image. Matter is overwritten on next upload so it's left as is.
+    /// Image is cleared by copying zeros, because transfer queues can't clear images
+    fn clear_data(
+        &self,
+        queue: Arc<Queue>,
+        zero_buffer: Arc<CpuAccessibleBuffer<[u32]>>,
+    ) -> Result<TransferFuture> {
         let mut builder = AutoCommandBufferBuilder::primary(
             queue.device().clone(),
             queue.family(),
@@ -92,26 +284,32 @@ impl WorldChunk {
         )?;
         let chunk = self.gpu_chunk.as_ref().unwrap();
         builder
-            .clear_color_image(chunk.image.image().clone(), [0.0; 4].into())?
+            .copy_buffer_to_image(zero_buffer, chunk.image.image().clone())?
             .fill_buffer(chunk.objects_matter.clone(), 0)?
-            .fill_buffer(chunk.objects_color.clone(), 0)?
-            .fill_buffer(chunk.matter_in.clone(), 0)?
-            .fill_buffer(chunk.matter_out.clone(), 0)?;
+            .fill_buffer(chunk.objects_color.clone(), 0)?;
         let command_buffer = builder.build()?;
         let finished = command_buffer.execute(queue)?;
-        let _fut = finished.then_signal_fence_and_flush()?;
-        Ok(())
+        Ok(Arc::new(finished.boxed().then_signal_fence_and_flush()?))
     }
 
-    pub fn write_to_cpu(&mut self, matter_definitions: &MatterDefinitions) -> Result<()> {
-        self.image = write_canvas_chunk_to_matter_image(
-            matter_definitions,
-            self.gpu_chunk.as_ref().unwrap().get_matter_input(),
-        )?;
+    pub fn write_to_cpu(&mut self) -> Result<()> {
+        let matter_grid = self.gpu_chunk.as_ref().unwrap().get_matter_input();
+        self.data = Some(encode_chunk(&matter_grid.read()?));
         Ok(())
     }
 }
 
+/// Matter of a chunk at the time it was saved
+pub enum ChunkSnapshot {
+    /// Matter grid of a chunk on gpu, copied to `staging`. Readable once `copied` has finished
+    Gpu {
+        staging: Arc<CpuAccessibleBuffer<[u32]>>,
+        copied: TransferFuture,
+    },
+    /// Encoded data of a chunk that isn't on gpu, None for chunks that have never had matter
+    Cpu(Option<Vec<u8>>),
+}
+
 #[derive(Clone)]
 pub struct GpuChunk {
     pub matter_in: Arc<CpuAccessibleBuffer<[u32]>>,
@@ -119,29 +317,33 @@ pub struct GpuChunk {
     pub objects_matter: Arc<CpuAccessibleBuffer<[u32]>>,
     pub objects_color: Arc<CpuAccessibleBuffer<[u32]>>,
     pub image: DeviceImageView,
+    /// Latest upload or clear on transfer queue
+    pub transfer: Option<TransferFuture>,
 }
 
 impl GpuChunk {
+    /// Matter grids, objects & image are shared by all queue families, as they're written on
+    /// transfer queue & used on compute & graphics
     pub fn new(comp_queue: Arc<Queue>, format: Format) -> Result<GpuChunk> {
-        let matter_in = empty_u32(
+        let matter_in = shared_empty_u32(
             comp_queue.device().clone(),
-            (*SIM_CANVAS_SIZE * *SIM_CANVAS_SIZE) as usize,
+            (SIM_CANVAS_SIZE.x * SIM_CANVAS_SIZE.y) as usize,
         )?;
-        let matter_out = empty_u32(
+        let matter_out = shared_empty_u32(
             comp_queue.device().clone(),
-            (*SIM_CANVAS_SIZE * *SIM_CANVAS_SIZE) as usize,
+            (SIM_CANVAS_SIZE.x * SIM_CANVAS_SIZE.y) as usize,
         )?;
-        let objects_matter = empty_u32(
+        let objects_matter = shared_empty_u32(
             comp_queue.device().clone(),
-            (*SIM_CANVAS_SIZE * *SIM_CANVAS_SIZE) as usize,
+            (SIM_CANVAS_SIZE.x * SIM_CANVAS_SIZE.y) as usize,
         )?;
-        let objects_color = empty_u32(
+        let objects_color = shared_empty_u32(
             comp_queue.device().clone(),
-            (*SIM_CANVAS_SIZE * *SIM_CANVAS_SIZE) as usize,
+            (SIM_CANVAS_SIZE.x * SIM_CANVAS_SIZE.y) as usize,
         )?;
-        let image = create_device_image_with_usage(
-            comp_queue.clone(),
-            [*SIM_CANVAS_SIZE; 2],
+        let image = create_shared_device_image(
+            comp_queue.device().clone(),
+            SIM_CANVAS_SIZE.into(),
             format,
             ImageUsage {
                 sampled: true,
@@ -165,15 +367,41 @@ impl GpuChunk {
             objects_matter,
             objects_color,
             image,
+            transfer: None,
         })
     }
 
-    pub fn get_matter_input(&self) -> Arc<CpuAccessibleBuffer<[u32]>> {
-        self.matter_in.clone()
+    /// Copy matter grid to staging memory on `queue`, so it can be read later without waiting
+    /// for the copy now
+    pub fn snapshot(&self, queue: Arc<Queue>) -> Result<ChunkSnapshot> {
+        let staging = empty_u32(
+            queue.device().clone(),
+            (SIM_CANVAS_SIZE.x * SIM_CANVAS_SIZE.y) as usize,
+        )?;
+        let mut builder = AutoCommandBufferBuilder::primary(
+            queue.device().clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        builder.copy_buffer(self.matter_in.clone(), staging.clone())?;
+        let command_buffer = builder.build()?;
+        let finished = command_buffer.execute(queue)?;
+        let copied = Arc::new(finished.boxed().then_signal_fence_and_flush()?);
+        Ok(ChunkSnapshot::Gpu {
+            staging,
+            copied,
+        })
     }
 
-    pub fn get_matter_output(&self) -> Arc<CpuAccessibleBuffer<[u32]>> {
-        self.matter_out.clone()
+    pub fn wait_transfer(&mut self) -> Result<()> {
+        if let Some(future) = self.transfer.take() {
+            future.wait(None)?;
+        }
+        Ok(())
+    }
+
+    pub fn get_matter_input(&self) -> Arc<CpuAccessibleBuffer<[u32]>> {
+        self.matter_in.clone()
     }
 }
 
@@ -182,6 +410,13 @@ impl GpuChunk {
 /// More like a tech demo part.
 pub struct SimulationChunkManager {
     pub queue: Arc<Queue>,
+    pub transfer_queue: Arc<Queue>,
+    // Chunk uploads & clears that haven't been waited for yet
+    pending_transfers: Vec<TransferFuture>,
+    // Source for clearing chunk images on transfer queue
+    zero_buffer: Arc<CpuAccessibleBuffer<[u32]>>,
+    // Staging buffers of chunk uploads, reused once their uploads have finished
+    upload_staging: Vec<Arc<CpuAccessibleBuffer<[u32]>>>,
     canvas_pos: Vector2<i32>,
     chunk_pos: Vector2<i32>,
     // An infinite amount (create as we go). They will own gpu chunks while they are in use "around player"
@@ -190,36 +425,60 @@ pub struct SimulationChunkManager {
     gpu_chunk_pool: VecDeque<GpuChunk>,
     // A set of canvas coordinates currently using a gpu chunk
     pub chunks_in_use: HashSet<Vector2<i32>>,
+    // Gpu chunks reserved for chunks whose data is being decoded in the background
+    chunks_loading: HashMap<Vector2<i32>, GpuChunk>,
+    // Background chunk data decoding & encoding
+    jobs: ChunkJobs,
     // Chunks that are to be written to by world interaction
     pub interaction_chunks: Vec<Vector2<i32>>,
+    // Streaming mode of latest update
+    streaming: ChunkStreaming,
     // Chunks that determine what we will load as player moves
-    nearest_nine_chunks: HashSet<Vector2<i32>>,
-    prev_nine_chunks: Option<HashSet<Vector2<i32>>>,
+    resident_chunks: HashSet<Vector2<i32>>,
+    prev_resident_chunks: Option<HashSet<Vector2<i32>>>,
     // Chunks that need to be loaded
     chunks_to_load: VecDeque<Vector2<i32>>,
     chunks_to_unload: VecDeque<Vector2<i32>>,
+    // Previews saved with the loaded map & hashes of chunk data they were drawn from
+    saved_previews: HashMap<Vector2<i32>, (PathBuf, u64)>,
 }
 
 impl SimulationChunkManager {
-    pub fn new(comp_queue: Arc<Queue>, format: Format) -> Result<SimulationChunkManager> {
+    pub fn new(
+        comp_queue: Arc<Queue>,
+        transfer_queue: Arc<Queue>,
+        format: Format,
+    ) -> Result<SimulationChunkManager> {
         let chunk_pos = Vector2::new(0, 0);
+        let zero_buffer = empty_u32(
+            transfer_queue.device().clone(),
+            (SIM_CANVAS_SIZE.x * SIM_CANVAS_SIZE.y) as usize,
+        )?;
         let mut manager = SimulationChunkManager {
             queue: comp_queue.clone(),
+            transfer_queue,
+            pending_transfers: vec![],
+            zero_buffer,
+            upload_staging: vec![],
             canvas_pos: Vector2::new(0, 0),
             chunk_pos,
             world_chunks: HashMap::new(),
             gpu_chunk_pool: VecDeque::new(),
             chunks_in_use: HashSet::new(),
+            chunks_loading: HashMap::new(),
+            jobs: ChunkJobs::new(),
             interaction_chunks: vec![
                 Vector2::new(0, 0) + Vector2::new(0, 0),
                 Vector2::new(0, 0) + Vector2::new(0, 1),
                 Vector2::new(0, 0) + Vector2::new(1, 1),
                 Vector2::new(0, 0) + Vector2::new(1, 0),
             ],
-            nearest_nine_chunks: CELL_OFFSETS_NINE.iter().cloned().collect(),
-            prev_nine_chunks: None,
+            streaming: ChunkStreaming::Radial,
+            resident_chunks: CELL_OFFSETS_NINE.iter().cloned().collect(),
+            prev_resident_chunks: None,
             chunks_to_load: VecDeque::new(),
             chunks_to_unload: VecDeque::new(),
+            saved_previews: HashMap::new(),
         };
         // Insert one world chunk
         manager.world_chunks.insert(chunk_pos, WorldChunk::empty());
@@ -243,6 +502,42 @@ impl SimulationChunkManager {
         Ok(manager)
     }
 
+    /// Recreate gpu chunks on a new device, e.g. after the previous one was lost. World chunks are
+    /// kept & chunks around `player_pos` reloaded from their cpu data, so changes to chunks in
+    /// use since they were loaded are lost
+    pub fn recreate_gpu(
+        &mut self,
+        comp_queue: Arc<Queue>,
+        transfer_queue: Arc<Queue>,
+        format: Format,
+        player_pos: Vector2<i32>,
+        matter_definitions: &MatterDefinitions,
+    ) -> Result<()> {
+        // Unloaded chunks' data is still needed, chunks being loaded are reloaded anyway
+        for (chunk_pos, result) in self.jobs.wait_all() {
+            if let ChunkJobResult::Encoded(data) = result? {
+                self.world_chunks.get_mut(&chunk_pos).unwrap().data = Some(data);
+            }
+        }
+        let mut world_chunks = std::mem::take(&mut self.world_chunks);
+        world_chunks
+            .values_mut()
+            .for_each(|chunk| chunk.gpu_chunk = None);
+        let streaming = self.streaming;
+        *self = SimulationChunkManager::new(comp_queue, transfer_queue, format)?;
+        self.world_chunks = world_chunks;
+        self.update_chunks(player_pos, streaming, matter_definitions)
+    }
+
+    /// Block until chunk uploads & clears have finished. Chunks in use can't be accessed on cpu
+    /// or compute before that
+    pub fn wait_transfers(&mut self) -> Result<()> {
+        for future in self.pending_transfers.drain(..) {
+            future.wait(None)?;
+        }
+        Ok(())
+    }
+
     /// Will panic if chunk is not in use...
     fn get_world_gpu_chunk(&self, chunk_pos: &Vector2<i32>) -> GpuChunk {
         // println!("Chunk pos {:?}", chunk_pos);
@@ -264,9 +559,17 @@ impl SimulationChunkManager {
             .unwrap()
     }
 
+    /// Canvas pos of the corner of simulated chunks
+    pub fn sim_chunk_start(&self) -> Vector2<i32> {
+        Vector2::new(
+            self.interaction_chunks[0].x * SIM_CANVAS_SIZE.x as i32,
+            self.interaction_chunks[0].y * SIM_CANVAS_SIZE.y as i32,
+        ) - *HALF_CANVAS
+    }
+
     pub fn get_chunks_for_compute(&self) -> (Vector2<i32>, Vec<GpuChunk>) {
         (
-            self.interaction_chunks[0] * *SIM_CANVAS_SIZE as i32 - *HALF_CANVAS,
+            self.sim_chunk_start(),
             self.interaction_chunks
                 .iter()
                 .map(|pos| self.get_world_gpu_chunk(pos))
@@ -274,6 +577,13 @@ impl SimulationChunkManager {
         )
     }
 
+    /// Borrow the simulated chunks for a phase of cpu access, see `SimChunks`. Compute must have
+    /// finished, e.g. by `CASimulator::wait_compute`
+    pub fn sim_chunks(&self, camera_canvas_pos: Vector2<i32>) -> SimChunks {
+        let (chunk_start, chunks) = self.get_chunks_for_compute();
+        SimChunks::new(chunk_start, camera_canvas_pos, chunks)
+    }
+
     pub fn update_compute_chunks(&mut self, chunks: Vec<GpuChunk>) {
         for (i, c) in chunks.iter().enumerate().take(4) {
             let pos = self.interaction_chunks[i];
@@ -282,6 +592,94 @@ impl SimulationChunkManager {
         }
     }
 
+    /// World chunk at `chunk_pos`, None if it has never been loaded or created
+    pub fn world_chunk(&self, chunk_pos: Vector2<i32>) -> Option<&WorldChunk> {
+        self.world_chunks.get(&chunk_pos)
+    }
+
+    /// Preview image saved with the map for chunk at `chunk_pos`, None if the chunk's data (hashed
+    /// by `chunk_data_hash`) is no longer what the preview was drawn from
+    pub fn saved_preview(&self, chunk_pos: Vector2<i32>, data_hash: u64) -> Option<&Path> {
+        self.saved_previews
+            .get(&chunk_pos)
+            .filter(|(_, saved_hash)| *saved_hash == data_hash)
+            .map(|(path, _)| path.as_path())
+    }
+
+    /// Load state of every known world chunk
+    pub fn chunk_load_states(&self) -> Vec<(Vector2<i32>, ChunkLoadState)> {
+        self.world_chunks
+            .keys()
+            .map(|&chunk_pos| {
+                let state = if self.interaction_chunks.contains(&chunk_pos) {
+                    ChunkLoadState::Simulated
+                } else if self.chunks_loading.contains_key(&chunk_pos)
+                    || self.chunks_to_load.contains(&chunk_pos)
+                {
+                    ChunkLoadState::Loading
+                } else if self.chunks_to_unload.contains(&chunk_pos)
+                    || self.jobs.is_pending(chunk_pos)
+                {
+                    ChunkLoadState::Unloading
+                } else if self.chunks_in_use.contains(&chunk_pos) {
+                    ChunkLoadState::Gpu
+                } else {
+                    ChunkLoadState::Cpu
+                };
+                (chunk_pos, state)
+            })
+            .collect()
+    }
+
+    /// Bytes of encoded chunk data on cpu
+    pub fn cpu_memory_bytes(&self) -> usize {
+        self.world_chunks
+            .values()
+            .filter_map(|chunk| chunk.data.as_ref())
+            .map(|data| data.len())
+            .sum()
+    }
+
+    /// Gpu bytes of chunks in use & loading, and of all allocated gpu chunks
+    pub fn gpu_memory_bytes(&self) -> (usize, usize) {
+        let used = self.chunks_in_use.len() + self.chunks_loading.len();
+        let chunk_bytes = gpu_chunk_bytes();
+        (used * chunk_bytes, MAX_GPU_CHUNKS as usize * chunk_bytes)
+    }
+
+    /// Queue a chunk that's only on cpu to be loaded to a free gpu chunk on next update. It stays
+    /// loaded until its gpu chunk is needed closer to the camera
+    pub fn queue_chunk_load(&mut self, chunk_pos: Vector2<i32>) -> Result<()> {
+        let is_on_cpu = self.world_chunks.contains_key(&chunk_pos)
+            && !self.chunks_in_use.contains(&chunk_pos)
+            && !self.chunks_loading.contains_key(&chunk_pos)
+            && !self.chunks_to_load.contains(&chunk_pos);
+        if !is_on_cpu {
+            bail!("Chunk {:?} isn't unloaded", chunk_pos);
+        }
+        if self.chunks_to_load.len() >= self.gpu_chunk_pool.len() {
+            bail!("No free gpu chunks, unload a chunk first");
+        }
+        self.chunks_to_load.push_back(chunk_pos);
+        Ok(())
+    }
+
+    /// Queue a chunk on gpu to be unloaded on next update. Chunks kept around the camera can't be
+    /// unloaded
+    pub fn queue_chunk_unload(&mut self, chunk_pos: Vector2<i32>) -> Result<()> {
+        if !self.chunks_in_use.contains(&chunk_pos) || self.chunks_to_unload.contains(&chunk_pos)
+        {
+            bail!("Chunk {:?} isn't loaded", chunk_pos);
+        }
+        if self.resident_chunks.contains(&chunk_pos)
+            || self.interaction_chunks.contains(&chunk_pos)
+        {
+            bail!("Chunk {:?} is kept loaded around the camera", chunk_pos);
+        }
+        self.chunks_to_unload.push_back(chunk_pos);
+        Ok(())
+    }
+
     pub fn get_chunks_for_render(&self) -> Vec<(Vector2<i32>, GpuChunk)> {
         self.chunks_in_use
             .iter()
@@ -289,29 +687,44 @@ impl SimulationChunkManager {
             .collect()
     }
 
+    /// Load map's chunks, mapping saved matter ids to current ones with `matter_remap`. Chunks
+    /// that fail to load are left empty and their errors are pushed to `skipped`
     pub fn load_map_from_disk(
         &mut self,
         map_dir: PathBuf,
         player_pos: Vector2<i32>,
         matter_definitions: &MatterDefinitions,
+        matter_remap: &[u32],
+        skipped: &mut Vec<String>,
     ) -> Result<()> {
-        for file in fs::read_dir(&map_dir).unwrap() {
+        // Pending chunks of the previous map must not overwrite loaded ones
+        self.finish_all_jobs()?;
+        for file in fs::read_dir(&map_dir)? {
             let file = file?.file_name();
-            let file_name = file.to_str().unwrap();
-            let file_path = map_dir.join(file_name);
-            if std::fs::metadata(&file_path).unwrap().is_file()
-                && file_name.starts_with("chunk")
-                && file_name.ends_with(".png")
-            {
-                let splits = file_name.split('.').take(1).collect::<Vec<&str>>()[0]
-                    .split('_')
-                    .collect::<Vec<&str>>();
-                let x = splits[1].parse::<i32>().unwrap();
-                let y = splits[2].parse::<i32>().unwrap();
-                self.world_chunks.insert(
-                    Vector2::new(x, y),
-                    WorldChunk::load_from_disk(file_path.clone()),
-                );
+            let file_name = file.to_string_lossy();
+            let file_path = map_dir.join(&*file_name);
+            if !file_path.is_file() || !file_name.starts_with("chunk") {
+                continue;
+            }
+            // Chunk images are converted by map migration, those left over failed to convert
+            if file_name.ends_with(".png") {
+                skipped.push(format!("Chunk image {} wasn't converted", file_name));
+            } else if file_name.ends_with(&format!(".{}", CHUNK_FILE_EXTENSION)) {
+                let chunk_pos = match parse_chunk_file_name(&file_name) {
+                    Some(pos) => pos,
+                    None => {
+                        skipped.push(format!("Invalid chunk file name {}", file_name));
+                        continue;
+                    }
+                };
+                let (chunk, error) = WorldChunk::load_from_disk(file_path, matter_remap);
+                if let Some(e) = error {
+                    skipped.push(format!("{:#}", e));
+                }
+                let preview_path = map_dir.join("previews").join(chunk_file_name(chunk_pos, "png"));
+                let data_hash = chunk_data_hash(chunk.data.as_deref());
+                self.saved_previews.insert(chunk_pos, (preview_path, data_hash));
+                self.world_chunks.insert(chunk_pos, chunk);
             }
         }
 
@@ -326,32 +739,110 @@ impl SimulationChunkManager {
             self.chunks_to_load.push_back(chunk_pos);
         }
 
-        self.update_chunks(player_pos, matter_definitions)?;
+        self.update_chunks(player_pos, self.streaming, matter_definitions)?;
 
         Ok(())
     }
 
+    /// Start unloading & loading queued chunks in the background & finish those whose jobs are
+    /// done. Only simulated chunks are waited for
     fn load_chunks_from_queue(&mut self, matter_definitions: &MatterDefinitions) -> Result<()> {
         while !self.chunks_to_unload.is_empty() {
             let chunk_pos = self.chunks_to_unload.pop_front().unwrap();
-            self.remove_gpu_chunk_from_world_use(chunk_pos, matter_definitions)?;
+            self.remove_gpu_chunk_from_world_use(chunk_pos)?;
         }
-        while !self.chunks_to_load.is_empty() {
-            let chunk_pos = self.chunks_to_load.pop_front().unwrap();
+        // Chunks without a free gpu chunk are queued again for next update
+        for chunk_pos in std::mem::take(&mut self.chunks_to_load) {
             self.add_gpu_chunk_to_world_use(chunk_pos, matter_definitions)?;
         }
+        for (chunk_pos, result) in self.jobs.poll() {
+            self.finish_job(chunk_pos, result)?;
+        }
+        for chunk_pos in self.interaction_chunks.clone() {
+            if !self.chunks_in_use.contains(&chunk_pos) {
+                // Simulated chunks can't wait for unloads of next update
+                if self.gpu_chunk_pool.is_empty() && !self.chunks_loading.contains_key(&chunk_pos)
+                {
+                    self.free_gpu_chunk()?;
+                }
+                self.add_gpu_chunk_to_world_use(chunk_pos, matter_definitions)?;
+                for (finished_pos, result) in self.jobs.wait_for(chunk_pos) {
+                    self.finish_job(finished_pos, result)?;
+                }
+            }
+        }
         Ok(())
     }
 
-    fn remove_gpu_chunk_from_world_use(
+    /// Upload decoded chunk to its reserved gpu chunk, or store encoded data of unloaded chunk
+    fn finish_job(
         &mut self,
         chunk_pos: Vector2<i32>,
-        matter_definitions: &MatterDefinitions,
+        result: Result<ChunkJobResult>,
     ) -> Result<()> {
+        match result {
+            std::result::Result::Ok(ChunkJobResult::Decoded(matter_grid)) => {
+                // Loading was cancelled to free its gpu chunk, see `free_gpu_chunk`
+                let gpu_chunk = match self.chunks_loading.remove(&chunk_pos) {
+                    Some(gpu_chunk) => gpu_chunk,
+                    None => return Ok(()),
+                };
+                let staging = self.upload_staging()?;
+                let world_chunk = self.world_chunks.get_mut(&chunk_pos).unwrap();
+                let future = world_chunk.write_to_gpu(
+                    &matter_grid,
+                    gpu_chunk,
+                    self.transfer_queue.clone(),
+                    staging,
+                )?;
+                self.pending_transfers.push(future);
+                self.chunks_in_use.insert(chunk_pos);
+            }
+            std::result::Result::Ok(ChunkJobResult::Encoded(data)) => {
+                self.world_chunks.get_mut(&chunk_pos).unwrap().data = Some(data)
+            }
+            Err(e) => {
+                // Failed decode leaves its gpu chunk free for others
+                if let Some(gpu_chunk) = self.chunks_loading.remove(&chunk_pos) {
+                    self.gpu_chunk_pool.push_back(gpu_chunk);
+                }
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Staging buffer for a chunk upload. One whose upload has finished is reused, as gpu no
+    /// longer holds it, so only as many exist as uploads have been in flight at once
+    fn upload_staging(&mut self) -> Result<Arc<CpuAccessibleBuffer<[u32]>>> {
+        let free = self.upload_staging.iter().find(|staging| staging.write().is_ok());
+        if let Some(staging) = free {
+            return Ok(staging.clone());
+        }
+        let staging = empty_u32(
+            self.transfer_queue.device().clone(),
+            (SIM_CANVAS_SIZE.x * SIM_CANVAS_SIZE.y) as usize,
+        )?;
+        self.upload_staging.push(staging.clone());
+        Ok(staging)
+    }
+
+    /// Block until all background jobs have finished & apply them, e.g. before saving
+    fn finish_all_jobs(&mut self) -> Result<()> {
+        for (chunk_pos, result) in self.jobs.wait_all() {
+            self.finish_job(chunk_pos, result)?;
+        }
+        Ok(())
+    }
+
+    fn remove_gpu_chunk_from_world_use(&mut self, chunk_pos: Vector2<i32>) -> Result<()> {
         if let Some(world_chunk) = self.world_chunks.get_mut(&chunk_pos) {
-            let gpu_chunk = world_chunk.unload_from_gpu(matter_definitions, self.queue.clone())?;
+            let (gpu_chunk, future, matter_grid) =
+                world_chunk.unload_from_gpu(self.transfer_queue.clone(), self.zero_buffer.clone())?;
             self.chunks_in_use.remove(&chunk_pos);
             self.gpu_chunk_pool.push_back(gpu_chunk);
+            self.pending_transfers.push(future);
+            self.jobs.encode(chunk_pos, matter_grid);
         } else {
             panic!(
                 "World did not contain chunk at {:?} when removing gpu chunk from world use",
@@ -361,120 +852,236 @@ impl SimulationChunkManager {
         Ok(())
     }
 
+    /// Put a gpu chunk in the pool now, unloading the farthest chunk that isn't needed around
+    /// the camera, or else cancelling the farthest chunk still loading (e.g. prefetched) and
+    /// queueing it to load again
+    fn free_gpu_chunk(&mut self) -> Result<()> {
+        let needed = self
+            .resident_chunks
+            .iter()
+            .chain(self.interaction_chunks.iter())
+            .copied()
+            .collect::<HashSet<Vector2<i32>>>();
+        let loading = self.chunks_loading.keys().copied().collect::<Vec<Vector2<i32>>>();
+        match gpu_chunk_to_free(self.chunk_pos, &self.chunks_in_use, &loading, &needed) {
+            Some(FreedGpuChunk::Unload(chunk_pos)) => {
+                self.chunks_to_unload.retain(|pos| *pos != chunk_pos);
+                self.remove_gpu_chunk_from_world_use(chunk_pos)?;
+            }
+            Some(FreedGpuChunk::CancelLoad(chunk_pos)) => {
+                let gpu_chunk = self.chunks_loading.remove(&chunk_pos).unwrap();
+                self.gpu_chunk_pool.push_back(gpu_chunk);
+                self.chunks_to_load.push_back(chunk_pos);
+            }
+            None => bail!("No gpu chunk can be freed for simulated chunks"),
+        }
+        Ok(())
+    }
+
     fn add_gpu_chunk_to_world_use(
         &mut self,
         chunk_pos: Vector2<i32>,
         matter_definitions: &MatterDefinitions,
     ) -> Result<()> {
-        if self.chunks_in_use.contains(&chunk_pos) {
+        if self.chunks_in_use.contains(&chunk_pos) || self.chunks_loading.contains_key(&chunk_pos)
+        {
             return Ok(());
         }
-        let world_chunk = if let Some(world_chunk) = self.world_chunks.get_mut(&chunk_pos) {
-            world_chunk
-        } else {
-            // If world chunk didn't exist at requested chunk pos, we just create it (empty)
-            self.world_chunks.insert(chunk_pos, WorldChunk::empty());
-            self.world_chunks.get_mut(&chunk_pos).unwrap()
+        // Chunk unloaded a moment ago needs its data encoded before it's decoded again
+        if self.jobs.is_pending(chunk_pos) {
+            for (finished_pos, result) in self.jobs.wait_for(chunk_pos) {
+                self.finish_job(finished_pos, result)?;
+            }
+        }
+        // Chunks still loading hold gpu chunks too, so the pool can run out before unloads free
+        // more. Retry on next update once farthest chunks have been unloaded
+        let gpu_chunk = match self.gpu_chunk_pool.pop_front() {
+            Some(gpu_chunk) => gpu_chunk,
+            None => {
+                if self.chunks_to_unload.is_empty() {
+                    self.add_farthest_chunks_for_unloading(1);
+                }
+                self.chunks_to_load.push_back(chunk_pos);
+                return Ok(());
+            }
         };
-        // Write world chunk image to gpu
-        let gpu_chunk = self.gpu_chunk_pool.pop_front().unwrap();
-        world_chunk.write_to_gpu(matter_definitions, gpu_chunk)?;
-        // Tell manager gpu chunk at index is in use
-        self.chunks_in_use.insert(chunk_pos);
+        // If world chunk didn't exist at requested chunk pos, we just create it (empty)
+        let world_chunk = self
+            .world_chunks
+            .entry(chunk_pos)
+            .or_insert_with(WorldChunk::empty);
+        // Decode world chunk data in the background, it's uploaded to gpu once done
+        self.jobs
+            .decode(chunk_pos, world_chunk.data.clone(), matter_definitions.clone());
+        self.chunks_loading.insert(chunk_pos, gpu_chunk);
         Ok(())
     }
 
-    pub fn save_one_chunk_to_disk(
-        &mut self,
-        map_dir: PathBuf,
-        matter_definitions: &MatterDefinitions,
-    ) -> Result<()> {
-        let chunk_pos = Vector2::new(0, 0);
-        self.world_chunks
-            .get_mut(&chunk_pos)
-            .unwrap()
-            .write_to_cpu(matter_definitions)?;
-        let chunk = self.world_chunks.get(&chunk_pos).unwrap();
-        let image = ImageBuffer::<Rgba<u8>, _>::from_raw(
-            *CANVAS_CHUNK_SIZE,
-            *CANVAS_CHUNK_SIZE,
-            &chunk.image.data[..],
-        )
-        .unwrap();
-
-        let filename = format!("chunk_{}_{}.png", chunk_pos.x, chunk_pos.y);
-        let image_path = map_dir.join(filename);
-        image.save(image_path).unwrap();
-
+    /// Replace chunk's matter, discarding changes to it. A chunk on gpu is overwritten in place,
+    /// so compute must not be running
+    pub fn replace_chunk(&mut self, chunk_pos: Vector2<i32>, matter_grid: &[u32]) -> Result<()> {
+        // Pending encode of an unloaded chunk would overwrite the replaced data
+        self.finish_all_jobs()?;
+        self.wait_transfers()?;
+        let world_chunk = self
+            .world_chunks
+            .entry(chunk_pos)
+            .or_insert_with(WorldChunk::empty);
+        world_chunk.data = Some(encode_chunk(matter_grid));
+        if let Some(gpu_chunk) = &world_chunk.gpu_chunk {
+            gpu_chunk.matter_in.write()?.copy_from_slice(matter_grid);
+            gpu_chunk.matter_out.write()?.copy_from_slice(matter_grid);
+        }
         Ok(())
     }
 
-    pub fn save_chunks_to_disk(
-        &mut self,
-        map_dir: PathBuf,
-        matter_definitions: &MatterDefinitions,
-    ) -> Result<()> {
+    /// Make cpu data of chunks saved with the map current & return their positions. Non
+    /// chunked maps save only chunk (0, 0)
+    fn sync_saved_chunks(&mut self, chunked: bool) -> Result<Vec<Vector2<i32>>> {
+        self.finish_all_jobs()?;
+        self.wait_transfers()?;
         for gpu_chunk_pos in self.chunks_in_use.iter() {
-            self.world_chunks
-                .get_mut(gpu_chunk_pos)
-                .unwrap()
-                .write_to_cpu(matter_definitions)?;
+            if chunked || *gpu_chunk_pos == Vector2::new(0, 0) {
+                self.world_chunks
+                    .get_mut(gpu_chunk_pos)
+                    .unwrap()
+                    .write_to_cpu()?;
+            }
         }
-        for (chunk_pos, chunk) in self.world_chunks.iter() {
-            let image = ImageBuffer::<Rgba<u8>, _>::from_raw(
-                *CANVAS_CHUNK_SIZE,
-                *CANVAS_CHUNK_SIZE,
-                &chunk.image.data[..],
-            )
-            .unwrap();
+        Ok(if chunked {
+            self.world_chunks.keys().copied().collect()
+        } else {
+            vec![Vector2::new(0, 0)]
+        })
+    }
 
-            let filename = format!("chunk_{}_{}.png", chunk_pos.x, chunk_pos.y);
-            let image_path = map_dir.join(&filename);
-            image.save(image_path).unwrap();
+    /// Snapshot matter of chunks saved with the map without reading gpu chunks back, see
+    /// `ChunkSnapshot`. Non chunked maps save only chunk (0, 0). Compute must not be running
+    pub fn snapshot_chunks(
+        &mut self,
+        chunked: bool,
+    ) -> Result<Vec<(Vector2<i32>, ChunkSnapshot)>> {
+        // Pending encodes & uploads would change the data being snapshot
+        self.finish_all_jobs()?;
+        self.wait_transfers()?;
+        let chunk_positions = if chunked {
+            self.world_chunks.keys().copied().collect()
+        } else {
+            vec![Vector2::new(0, 0)]
+        };
+        let mut snapshots = vec![];
+        for chunk_pos in chunk_positions {
+            let world_chunk = &self.world_chunks[&chunk_pos];
+            let snapshot = match &world_chunk.gpu_chunk {
+                Some(gpu_chunk) if self.chunks_in_use.contains(&chunk_pos) => {
+                    gpu_chunk.snapshot(self.queue.clone())?
+                }
+                _ => ChunkSnapshot::Cpu(world_chunk.data.clone()),
+            };
+            snapshots.push((chunk_pos, snapshot));
         }
+        Ok(snapshots)
+    }
 
-        Ok(())
+    /// Images of chunks saved with the map, matter drawn in its color, along with the paths
+    /// they should be saved to. Encoding is left to the caller so it can happen off the main
+    /// thread
+    pub fn chunk_images(
+        &mut self,
+        dir: PathBuf,
+        chunked: bool,
+        matter_definitions: &MatterDefinitions,
+    ) -> Result<Vec<(PathBuf, BitmapImage)>> {
+        let mut images = vec![];
+        for chunk_pos in self.sync_saved_chunks(chunked)? {
+            let data = self.world_chunks[&chunk_pos].data.as_deref();
+            let matter_grid = chunk_matter_grid(data, matter_definitions)?;
+            images.push((
+                dir.join(chunk_file_name(chunk_pos, "png")),
+                matter_grid_to_image(matter_definitions, &matter_grid),
+            ));
+        }
+        Ok(images)
     }
 
     pub fn update_chunks(
         &mut self,
         player_pos: Vector2<i32>,
+        streaming: ChunkStreaming,
         matter_definitions: &MatterDefinitions,
     ) -> Result<()> {
+        // Cells moved down since last step
+        let fall_speed = self.canvas_pos.y - player_pos.y;
         self.canvas_pos = player_pos;
+        self.streaming = streaming;
         self.chunk_pos = Vector2::new(
-            (player_pos.x as f32 / (*CANVAS_CHUNK_SIZE) as f32).round() as i32,
-            (player_pos.y as f32 / (*CANVAS_CHUNK_SIZE) as f32).round() as i32,
+            (player_pos.x as f32 / CANVAS_CHUNK_SIZE.x as f32).round() as i32,
+            (player_pos.y as f32 / CANVAS_CHUNK_SIZE.y as f32).round() as i32,
         );
         self.interaction_chunks = self.get_nearest_four_chunks();
-        self.prev_nine_chunks = Some(self.nearest_nine_chunks.clone());
-        self.nearest_nine_chunks = self.get_nearest_nine_chunks();
-        // if 9 chunks changed, we must load more...
+        self.prev_resident_chunks = Some(self.resident_chunks.clone());
+        self.resident_chunks = self.get_resident_chunks(streaming);
+        // if resident chunks changed, we must load more... Prefetched ones are already loaded
         let difference: HashSet<_> = self
-            .nearest_nine_chunks
-            .difference(self.prev_nine_chunks.as_ref().unwrap())
+            .resident_chunks
+            .difference(self.prev_resident_chunks.as_ref().unwrap())
+            .filter(|chunk| {
+                !self.chunks_in_use.contains(chunk) && !self.chunks_loading.contains_key(chunk)
+            })
             .cloned()
             .collect();
         if !difference.is_empty() {
-            // If we ran out of chunks, start unloading chunks farther than one
-            if difference.len() > self.gpu_chunk_pool.len() {
-                self.add_farthest_chunks_for_unloading(
-                    difference.len() - self.gpu_chunk_pool.len(),
-                );
+            // If we ran out of chunks, start unloading chunks farther than one. Chunks queued
+            // from the world map take free chunks too
+            let queued = self
+                .chunks_to_load
+                .iter()
+                .filter(|chunk| !difference.contains(chunk))
+                .count();
+            // Chunks still loading already took theirs from the pool, queued unloads return theirs
+            let needed = difference.len() + queued;
+            let free = self.gpu_chunk_pool.len() + self.chunks_to_unload.len();
+            if needed > free {
+                self.add_farthest_chunks_for_unloading(needed - free);
             }
 
             for chunk in difference {
                 self.chunks_to_load.push_back(chunk);
             }
         }
-        self.load_chunks_from_queue(matter_definitions)
+        self.load_chunks_from_queue(matter_definitions)?;
+        if streaming == ChunkStreaming::VerticalSlice && fall_speed >= FAST_FALL_SPEED {
+            self.prefetch_below_slice(matter_definitions)?;
+        }
+        Ok(())
+    }
+
+    /// Load chunks below the vertical slice ahead of a fast fall, nearest rows first. Only free
+    /// gpu chunks are used, so nothing is unloaded for prefetching
+    fn prefetch_below_slice(&mut self, matter_definitions: &MatterDefinitions) -> Result<()> {
+        let budget = MAX_PREFETCH_PER_STEP.min(self.gpu_chunk_pool.len());
+        let slice_bottom = self.chunk_pos.y - VERTICAL_SLICE_REACH;
+        let chunk_x = self.chunk_pos.x;
+        let to_prefetch = (1..=PREFETCH_ROWS)
+            .flat_map(|row| (-1..=1).map(move |x| Vector2::new(chunk_x + x, slice_bottom - row)))
+            .filter(|chunk| {
+                !self.chunks_in_use.contains(chunk) && !self.chunks_loading.contains_key(chunk)
+            })
+            .take(budget)
+            .collect::<Vec<Vector2<i32>>>();
+        for chunk_pos in to_prefetch {
+            self.add_gpu_chunk_to_world_use(chunk_pos, matter_definitions)?;
+        }
+        Ok(())
     }
 
     fn add_farthest_chunks_for_unloading(&mut self, count: usize) {
+        // Resident chunks are needed now, never unload them
         let mut chunks_in_use = self
             .chunks_in_use
-            .clone()
-            .into_iter()
+            .difference(&self.resident_chunks)
+            .filter(|chunk| !self.chunks_to_unload.contains(chunk))
+            .cloned()
             .collect::<Vec<Vector2<i32>>>();
         // Sort from farthest to closest
         chunks_in_use.sort_unstable_by(|a, b| {
@@ -489,11 +1096,17 @@ impl SimulationChunkManager {
             .extend(chunks_in_use.iter().take(count));
     }
 
-    fn get_nearest_nine_chunks(&self) -> HashSet<Vector2<i32>> {
-        CELL_OFFSETS_NINE
-            .iter()
-            .map(|offset| self.chunk_pos + offset)
-            .collect()
+    fn get_resident_chunks(&self, streaming: ChunkStreaming) -> HashSet<Vector2<i32>> {
+        match streaming {
+            ChunkStreaming::Radial => CELL_OFFSETS_NINE
+                .iter()
+                .map(|offset| self.chunk_pos + offset)
+                .collect(),
+            ChunkStreaming::VerticalSlice => (-VERTICAL_SLICE_REACH..=VERTICAL_SLICE_REACH)
+                .flat_map(|y| (-1..=1).map(move |x| Vector2::new(x, y)))
+                .map(|offset| self.chunk_pos + offset)
+                .collect(),
+        }
     }
 
     ///
@@ -530,7 +1143,10 @@ impl SimulationChunkManager {
         .map(|option| {
             // the distance of this option from player
             let dist = option.iter().fold(0.0f32, |acc, val| {
-                let chunk_pos_center = val.cast::<f32>().unwrap() * *SIM_CANVAS_SIZE as f32;
+                let chunk_pos_center = Vector2::new(
+                    val.x as f32 * SIM_CANVAS_SIZE.x as f32,
+                    val.y as f32 * SIM_CANVAS_SIZE.y as f32,
+                );
                 let diff = chunk_pos_center - self.canvas_pos.cast::<f32>().unwrap();
                 acc + diff.magnitude()
             }) / 4.0f32;
@@ -541,3 +1157,123 @@ impl SimulationChunkManager {
         .0
     }
 }
+
+/// File name of chunk at `chunk_pos`, e.g. `chunk_1_-2.bin`
+pub fn chunk_file_name(chunk_pos: Vector2<i32>, extension: &str) -> String {
+    format!("chunk_{}_{}.{}", chunk_pos.x, chunk_pos.y, extension)
+}
+
+/// Hash of world chunk data, telling whether it has changed
+pub fn chunk_data_hash(data: Option<&[u8]>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Bytes of each binary chunk file in map directory `map_dir`
+pub fn chunk_disk_bytes(map_dir: &Path) -> Result<HashMap<Vector2<i32>, u64>> {
+    let mut disk_bytes = HashMap::new();
+    for file in fs::read_dir(map_dir)? {
+        let file = file?;
+        let file_name = file.file_name().to_string_lossy().to_string();
+        if !file_name.ends_with(&format!(".{}", CHUNK_FILE_EXTENSION)) {
+            continue;
+        }
+        if let Some(chunk_pos) = parse_chunk_file_name(&file_name) {
+            disk_bytes.insert(chunk_pos, file.metadata()?.len());
+        }
+    }
+    Ok(disk_bytes)
+}
+
+/// How a gpu chunk is freed when the pool is empty, see `gpu_chunk_to_free`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum FreedGpuChunk {
+    Unload(Vector2<i32>),
+    CancelLoad(Vector2<i32>),
+}
+
+/// Farthest chunk from `center` whose gpu chunk can be freed: a `loaded` chunk that isn't
+/// `needed` is unloaded, or else a `loading` one that isn't needed is cancelled. None if all are
+/// needed
+fn gpu_chunk_to_free(
+    center: Vector2<i32>,
+    loaded: &HashSet<Vector2<i32>>,
+    loading: &[Vector2<i32>],
+    needed: &HashSet<Vector2<i32>>,
+) -> Option<FreedGpuChunk> {
+    let farthest = |chunks: &mut dyn Iterator<Item = Vector2<i32>>| {
+        chunks
+            .filter(|chunk| !needed.contains(chunk))
+            .max_by_key(|chunk| {
+                let offset = *chunk - center;
+                (offset.x * offset.x + offset.y * offset.y, chunk.x, chunk.y)
+            })
+    };
+    farthest(&mut loaded.iter().copied())
+        .map(FreedGpuChunk::Unload)
+        .or_else(|| farthest(&mut loading.iter().copied()).map(FreedGpuChunk::CancelLoad))
+}
+
+/// Chunk position from file names like `chunk_1_-2.bin`
+fn parse_chunk_file_name(file_name: &str) -> Option<Vector2<i32>> {
+    let stem = file_name.split('.').next()?;
+    let mut splits = stem.split('_').skip(1);
+    let x = splits.next()?.parse::<i32>().ok()?;
+    let y = splits.next()?.parse::<i32>().ok()?;
+    Some(Vector2::new(x, y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matter::{default_matter_definitions, MATTER_SAND};
+
+    #[test]
+    fn test_chunk_binary_round_trip() {
+        let definitions = default_matter_definitions();
+        let num_matters = definitions.definitions.len();
+        let mut grid = chunk_matter_grid(None, &definitions).unwrap();
+        grid[0] = MATTER_SAND;
+        grid[10..20].fill(MATTER_SAND);
+        let data = encode_chunk(&grid);
+        // Header & runs of sand, empty, sand & empty
+        assert_eq!(data.len(), CHUNK_HEADER_SIZE + 4 * CHUNK_RUN_SIZE);
+        assert_eq!(decode_chunk(&data, num_matters).unwrap(), grid);
+
+        let chunk_pos = Vector2::new(1, -2);
+        let file_name = chunk_file_name(chunk_pos, CHUNK_FILE_EXTENSION);
+        assert_eq!(parse_chunk_file_name(&file_name), Some(chunk_pos));
+        // Truncated, from an unknown version or with unknown matter are refused
+        assert!(decode_chunk(&data[..data.len() - 1], num_matters).is_err());
+        let mut newer = data.clone();
+        newer[4] += 1;
+        assert!(decode_chunk(&newer, num_matters).is_err());
+        let mut unknown = data;
+        let matter_start = CHUNK_HEADER_SIZE + 4;
+        unknown[matter_start..matter_start + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(decode_chunk(&unknown, num_matters).is_err());
+    }
+
+    #[test]
+    fn test_gpu_chunk_to_free() {
+        let around = |center: Vector2<i32>| {
+            CELL_OFFSETS_NINE
+                .iter()
+                .map(|offset| center + offset)
+                .collect::<HashSet<Vector2<i32>>>()
+        };
+        // Pool drained by chunks around (0, 0) & two prefetched below them still loading
+        let loaded = around(Vector2::new(0, 0));
+        let loading = [Vector2::new(0, -2), Vector2::new(0, -3)];
+        // Interaction window moves right, chunks on the left are no longer needed
+        let center = Vector2::new(1, 0);
+        let freed = gpu_chunk_to_free(center, &loaded, &loading, &around(center));
+        assert!(matches!(freed, Some(FreedGpuChunk::Unload(chunk)) if chunk.x == -1));
+        // All loaded chunks needed, farthest prefetched one gives up its gpu chunk
+        let needed = around(Vector2::new(0, 0));
+        let freed = gpu_chunk_to_free(Vector2::new(0, 0), &loaded, &loading, &needed);
+        assert_eq!(freed, Some(FreedGpuChunk::CancelLoad(Vector2::new(0, -3))));
+        assert_eq!(gpu_chunk_to_free(Vector2::new(0, 0), &loaded, &[], &needed), None);
+    }
+}
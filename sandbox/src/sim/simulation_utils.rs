@@ -1,22 +1,22 @@
-use std::sync::Arc;
+use std::collections::HashMap;
 
 use anyhow::*;
 use cgmath::Vector2;
 use corrode::renderer::{Camera2D, Line};
 use hecs::Entity;
 use rapier2d::geometry::Collider;
-use vulkano::buffer::CpuAccessibleBuffer;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 use crate::{
-    matter::MatterDefinitions,
+    matter::{MatterDefinitions, MatterState},
     object::{
         collider_from_polylines, collider_sensor_from_polylines, douglas_peucker_simplify,
         form_contour_vertices, PixelData, TempPixel,
     },
-    sim::Simulation,
-    utils::{rotate_radians, u32_rgba_to_u8_rgba, u8_rgba_to_u32_rgba, BitmapImage},
-    BITMAP_PIXEL_TO_CANVAS_RATIO, BITMAP_RATIO, CANVAS_CHUNK_SIZE, HALF_CANVAS, HALF_CELL,
-    SIM_CANVAS_SIZE, WORLD_UNIT_SIZE,
+    sim::{boundaries::region_bitmap, Simulation},
+    utils::{rotate_radians, BitmapImage, Color32},
+    BITMAP_PIXEL_TO_CANVAS_RATIO, CANVAS_CHUNK_SIZE, CELL_UNIT_SIZE, HALF_CANVAS, HALF_CELL,
+    SIM_CANVAS_SIZE, WORLD_CANVAS_SIZE,
 };
 
 /// Convert normalized mouse position to position on the pixel canvas
@@ -29,19 +29,28 @@ pub fn mouse_to_canvas_pos(normalized_mouse: Vector2<f32>, camera: &Camera2D) ->
 /// Convert canvas integer position to world position
 pub fn canvas_pos_to_world_pos(canvas_pos: Vector2<i32>) -> Vector2<f32> {
     let mut world_pos = Vector2::new(canvas_pos.x as f32 + 0.5, canvas_pos.y as f32 + 0.5);
-    world_pos /= *SIM_CANVAS_SIZE as f32 / WORLD_UNIT_SIZE;
+    world_pos *= *CELL_UNIT_SIZE;
     world_pos
 }
 
 pub fn world_pos_to_canvas_pos(world_pos: Vector2<f32>) -> Vector2<f32> {
     let mut canvas_pos = world_pos;
-    canvas_pos *= *SIM_CANVAS_SIZE as f32 / WORLD_UNIT_SIZE;
+    canvas_pos /= *CELL_UNIT_SIZE;
     Vector2::new(canvas_pos.x.round(), canvas_pos.y.round())
 }
 
+/// Chunk containing the canvas position. Chunk (0, 0) is centered at canvas origin
+pub fn canvas_pos_to_chunk_pos(canvas_pos: Vector2<i32>) -> Vector2<i32> {
+    let pos = canvas_pos + *HALF_CANVAS;
+    Vector2::new(
+        pos.x.div_euclid(CANVAS_CHUNK_SIZE.x as i32),
+        pos.y.div_euclid(CANVAS_CHUNK_SIZE.y as i32),
+    )
+}
+
 pub fn is_inside_sim_canvas(canvas_pos: Vector2<i32>, camera_canvas_pos: Vector2<i32>) -> bool {
     let pos = canvas_pos + *HALF_CANVAS - camera_canvas_pos;
-    pos.x >= 0 && pos.x < *SIM_CANVAS_SIZE as i32 && pos.y >= 0 && pos.y < *SIM_CANVAS_SIZE as i32
+    pos.x >= 0 && pos.x < SIM_CANVAS_SIZE.x as i32 && pos.y >= 0 && pos.y < SIM_CANVAS_SIZE.y as i32
 }
 
 pub fn world_pos_inside_canvas(world_pos: Vector2<f32>, camera_world_pos: Vector2<f32>) -> bool {
@@ -59,49 +68,74 @@ pub fn sim_chunk_canvas_index(
     chunk_start: Vector2<i32>,
 ) -> (usize, usize) {
     let diff = canvas_pos - chunk_start;
-    let chunk_diff = diff / *SIM_CANVAS_SIZE as i32;
-    let index = ((diff.y % *SIM_CANVAS_SIZE as i32) * *SIM_CANVAS_SIZE as i32
-        + (diff.x % *SIM_CANVAS_SIZE as i32)) as usize;
+    let size = SIM_CANVAS_SIZE.cast::<i32>().unwrap();
+    let chunk_diff = Vector2::new(diff.x / size.x, diff.y / size.y);
+    let index = ((diff.y % size.y) * size.x + (diff.x % size.x)) as usize;
     let chunk_index = (chunk_diff.y * 2 + chunk_diff.x) as usize;
     (chunk_index, index)
 }
 
 pub fn sim_canvas_index(canvas_pos: Vector2<i32>, camera_canvas_pos: Vector2<i32>) -> usize {
     let pos = canvas_pos + *HALF_CANVAS - camera_canvas_pos;
-    (pos.y * *SIM_CANVAS_SIZE as i32 + pos.x) as usize
+    (pos.y * SIM_CANVAS_SIZE.x as i32 + pos.x) as usize
+}
+
+/// Boundary objects (position, angle & collider) formed from a boundary region of the bitmap of
+/// a matter state, with the state & region index
+pub type BoundaryObjectData = (Vec<(Vector2<f32>, f32, Collider)>, MatterState, usize);
+
+/// Form boundary objects of changed regions of state bitmaps in parallel. Liquids become sensors
+pub(crate) fn create_boundaries_object_data(
+    pos_offset: Vector2<f32>,
+    changed_bitmaps: &[(&[f64], MatterState, Vec<usize>)],
+) -> Vec<BoundaryObjectData> {
+    changed_bitmaps
+        .iter()
+        .flat_map(|(bitmap, state, regions)| {
+            regions.iter().map(move |region| (*bitmap, *state, *region))
+        })
+        .collect::<Vec<(&[f64], MatterState, usize)>>()
+        .par_iter()
+        .map(|(bitmap, state, region)| {
+            let sensor = *state == MatterState::Liquid;
+            (
+                create_boundary_object_data(pos_offset, bitmap, *region, sensor),
+                *state,
+                *region,
+            )
+        })
+        .collect()
 }
 
+/// Boundary objects of a boundary region of `bitmap`, positioned like the bitmap was formed whole
 pub(crate) fn create_boundary_object_data(
     pos_offset: Vector2<f32>,
     bitmap: &[f64],
+    region: usize,
     sensor: bool,
 ) -> Vec<(Vector2<f32>, f32, Collider)> {
-    form_contour_vertices(
-        bitmap,
-        *SIM_CANVAS_SIZE / *BITMAP_RATIO,
-        *SIM_CANVAS_SIZE / *BITMAP_RATIO,
-        *BITMAP_PIXEL_TO_CANVAS_RATIO,
-    )
-    .iter()
-    .filter_map(|c| {
-        let contour = douglas_peucker_simplify(c.to_vec(), 0.0001);
-        if contour.len() < 3 {
-            return None;
-        }
-        let collider = if sensor {
-            collider_sensor_from_polylines(&contour)
-        } else {
-            collider_from_polylines(&contour)
-        };
-        let pos = pos_offset;
-        let angle = 0.0;
-        Some((pos, angle, collider))
-    })
-    .collect()
+    let (region_bitmap, width, height, center_offset) = region_bitmap(bitmap, region);
+    let pos = pos_offset + (center_offset * *BITMAP_PIXEL_TO_CANVAS_RATIO).cast::<f32>().unwrap();
+    form_contour_vertices(&region_bitmap, width, height, *BITMAP_PIXEL_TO_CANVAS_RATIO)
+        .iter()
+        .filter_map(|c| {
+            let contour = douglas_peucker_simplify(c.to_vec(), 0.0001);
+            if contour.len() < 3 {
+                return None;
+            }
+            let collider = if sensor {
+                collider_sensor_from_polylines(&contour)
+            } else {
+                collider_from_polylines(&contour)
+            };
+            let angle = 0.0;
+            Some((pos, angle, collider))
+        })
+        .collect()
 }
 
-pub fn get_collider_lines(collider: &Collider, color: [f32; 4]) -> Vec<Line> {
-    let mut lines = vec![];
+/// Push collider's outline to `lines`, which can be reused between frames
+pub fn push_collider_lines(collider: &Collider, color: [f32; 4], lines: &mut Vec<Line>) {
     if let Some(comp) = collider.shape().as_compound() {
         comp.shapes().iter().for_each(|s| {
             if let Some(poly) = s.1.as_convex_polygon() {
@@ -192,8 +226,6 @@ pub fn get_collider_lines(collider: &Collider, color: [f32; 4]) -> Vec<Line> {
             ));
         }
     }
-
-    lines
 }
 
 /// https://datagenetics.com/blog/august32013/index.html
@@ -226,108 +258,118 @@ fn shear(angle: f32, pos: Vector2<i32>) -> Vector2<i32> {
     Vector2::new(x as i32, y as i32)
 }
 
-pub fn get_alive_pixels(
+/// Write object's alive pixels (in canvas coordinates) to `temp_pixels`, reusing its allocation
+pub fn write_alive_pixels(
     pixel_data: &PixelData,
     pos: Vector2<f32>,
     angle: f32,
     entity: Entity,
-) -> Vec<TempPixel> {
+    temp_pixels: &mut Vec<TempPixel>,
+) {
     let pixels = &pixel_data.pixels;
     let w = pixel_data.width as i32;
     let h = pixel_data.height as i32;
     let obj_canvas_pos = world_pos_to_canvas_pos(pos);
     let half_w = (((w as f32 + 1.0) / 2.0) - 1.0).round() as i32;
     let half_h = (((h as f32 + 1.0) / 2.0) - 1.0).round() as i32;
-    (0..(h * w))
-        .filter_map(|pixel_index| {
-            let x = pixel_index % w;
-            let y = pixel_index / w;
-            if pixels[pixel_index as usize].is_alive {
-                let pixel_pos_relative_to_center = Vector2::new(x - half_w, y - half_h);
-                let new_pos = shear(angle, pixel_pos_relative_to_center);
-                let canvas_pos = new_pos + obj_canvas_pos.cast::<i32>().unwrap();
-                let pixel = pixel_data.pixels[pixel_index as usize];
-                let rgba_index = pixel.color_index * 4;
-                let r = pixel_data.image.data[rgba_index];
-                let g = pixel_data.image.data[rgba_index + 1];
-                let b = pixel_data.image.data[rgba_index + 2];
-                let a = pixel_data.image.data[rgba_index + 3];
-                Some(TempPixel {
-                    pixel_index: pixel_index as usize,
-                    canvas_pos,
-                    matter: pixel.matter,
-                    color: u8_rgba_to_u32_rgba(a, b, g, r),
-                    entity,
-                })
-            } else {
-                None
-            }
-        })
-        .collect()
+    temp_pixels.clear();
+    temp_pixels.extend((0..(h * w)).filter_map(|pixel_index| {
+        let x = pixel_index % w;
+        let y = pixel_index / w;
+        if pixels[pixel_index as usize].is_alive {
+            let pixel_pos_relative_to_center = Vector2::new(x - half_w, y - half_h);
+            let new_pos = shear(angle, pixel_pos_relative_to_center);
+            let canvas_pos = new_pos + obj_canvas_pos.cast::<i32>().unwrap();
+            let pixel = pixel_data.pixels[pixel_index as usize];
+            let rgba_index = pixel.color_index * 4;
+            let brightness = pixel.char_brightness();
+            let channel = |i: usize| (pixel_data.image.data[i] as f32 * brightness) as u8;
+            let r = channel(rgba_index);
+            let g = channel(rgba_index + 1);
+            let b = channel(rgba_index + 2);
+            let a = pixel_data.image.data[rgba_index + 3];
+            Some(TempPixel {
+                pixel_index: pixel_index as usize,
+                canvas_pos,
+                matter: pixel.matter,
+                color: Color32::from_rgba(r, g, b, a).to_u32_abgr(),
+                entity,
+            })
+        } else {
+            None
+        }
+    }));
 }
 
-pub fn write_matter_image_to_canvas_chunk(
+/// Matter grid of a chunk image, in canvas order (y flipped), with matter of each color from
+/// `matter_by_color`. Returns the grid & number of cells whose color had no matter, which become
+/// `empty`
+pub fn matter_image_to_grid(
     matter_image: &BitmapImage,
-    matter_definitions: &MatterDefinitions,
-    chunk_in: Arc<CpuAccessibleBuffer<[u32]>>,
-    chunk_out: Arc<CpuAccessibleBuffer<[u32]>>,
-) -> Result<()> {
-    let mut matter_grid_in = chunk_in.write()?;
-    let mut matter_grid_out = chunk_out.write()?;
+    matter_by_color: &HashMap<u32, u32>,
+    empty: u32,
+) -> Result<(Vec<u32>, usize)> {
+    if matter_image.width != CANVAS_CHUNK_SIZE.x || matter_image.height != CANVAS_CHUNK_SIZE.y {
+        bail!(
+            "Chunk image is {}x{}, expected canvas chunk size {}x{}",
+            matter_image.width,
+            matter_image.height,
+            CANVAS_CHUNK_SIZE.x,
+            CANVAS_CHUNK_SIZE.y
+        );
+    }
+    let mut matter_grid = vec![empty; matter_image.data.len() / 4];
+    let mut num_unknown = 0;
     for y in 0..matter_image.height as usize {
         for x in 0..matter_image.width as usize {
             let index = y * matter_image.width as usize + x;
-            // ToDo: Matter definitions could be a hash map or something to speed up "find"
-            let matter = if let Some(m) = matter_definitions.definitions.iter().find(|m| {
-                let r = matter_image.data[index * 4];
-                let g = matter_image.data[index * 4 + 1];
-                let b = matter_image.data[index * 4 + 2];
-                let a = matter_image.data[index * 4 + 3];
-                let color = u8_rgba_to_u32_rgba(r, g, b, a);
-                m.color == color
-            }) {
-                m.id
-            } else {
-                matter_definitions.empty
-            };
+            let color = Color32::from_rgba_bytes(&matter_image.data[index * 4..]).to_u32_rgba();
             let flipped_y_index =
-                ((*CANVAS_CHUNK_SIZE) as usize - y - 1) * (*CANVAS_CHUNK_SIZE) as usize + x;
-            matter_grid_in[flipped_y_index] = matter;
-            matter_grid_out[flipped_y_index] = matter;
+                (CANVAS_CHUNK_SIZE.y as usize - y - 1) * CANVAS_CHUNK_SIZE.x as usize + x;
+            match matter_by_color.get(&color) {
+                Some(&matter) => matter_grid[flipped_y_index] = matter,
+                None => num_unknown += 1,
+            }
         }
     }
-    Ok(())
+    Ok((matter_grid, num_unknown))
 }
 
-pub fn write_canvas_chunk_to_matter_image(
+/// Chunk image of a matter grid, see `matter_image_to_grid`
+pub fn matter_grid_to_image(
     matter_definitions: &MatterDefinitions,
-    chunk: Arc<CpuAccessibleBuffer<[u32]>>,
-) -> Result<BitmapImage> {
-    let matter_grid = chunk.read()?;
-    let mut image = BitmapImage::empty(*CANVAS_CHUNK_SIZE, *CANVAS_CHUNK_SIZE);
-    for y in 0..(*CANVAS_CHUNK_SIZE) as usize {
-        for x in 0..(*CANVAS_CHUNK_SIZE) as usize {
-            let index = y * (*CANVAS_CHUNK_SIZE) as usize + x;
+    matter_grid: &[u32],
+) -> BitmapImage {
+    let mut image = BitmapImage::empty(CANVAS_CHUNK_SIZE.x, CANVAS_CHUNK_SIZE.y);
+    for y in 0..CANVAS_CHUNK_SIZE.y as usize {
+        for x in 0..CANVAS_CHUNK_SIZE.x as usize {
+            let index = y * CANVAS_CHUNK_SIZE.x as usize + x;
             let flipped_y_index =
-                ((*CANVAS_CHUNK_SIZE) as usize - 1 - y) * (*CANVAS_CHUNK_SIZE) as usize + x;
+                (CANVAS_CHUNK_SIZE.y as usize - 1 - y) * CANVAS_CHUNK_SIZE.x as usize + x;
             let matter = matter_grid[flipped_y_index];
-            let color = u32_rgba_to_u8_rgba(matter_definitions.definitions[matter as usize].color);
-            image.data[index * 4] = color[0];
-            image.data[index * 4 + 1] = color[1];
-            image.data[index * 4 + 2] = color[2];
-            image.data[index * 4 + 3] = color[3];
+            let color = matter_definitions.definitions[matter as usize].color;
+            let color = Color32::from_u32_rgba(color);
+            image.data[index * 4..index * 4 + 4].copy_from_slice(&color.to_rgba());
         }
     }
-    Ok(image)
+    image
 }
 
 pub fn log_world_performance(simulation: &Simulation) {
     println!("  World functions:");
+    println!(
+        "  Chunk load: {:.3}",
+        simulation.chunk_timer.time_average_ms()
+    );
     println!(
         "  Obj write: {:.3}",
         simulation.obj_write_timer.time_average_ms()
     );
     println!("  CA sim: {:.3}", simulation.ca_timer.time_average_ms());
+    println!(
+        "  CA gpu wait: {:.3}",
+        simulation.ca_wait_timer.time_average_ms()
+    );
     println!(
         "  Obj deform: {:.3}",
         simulation.obj_read_timer.time_average_ms()
@@ -340,44 +382,39 @@ pub fn log_world_performance(simulation: &Simulation) {
         "  Physics step: {:.3}",
         simulation.physics_timer.time_average_ms()
     );
+    if let Some(gpu_timings) = simulation.gpu_timings() {
+        println!("  CA gpu passes: {:.3}", gpu_timings.total_average_ms());
+        for (pass, ms) in gpu_timings.averages_ms() {
+            println!("    {}: {:.3}", pass.name(), ms);
+        }
+    }
 }
 
 pub fn chunk_lines(chunk: Vector2<i32>, chunk_color: [f32; 4]) -> Vec<Line> {
+    let half = 0.5 * *WORLD_CANVAS_SIZE;
+    let center = Vector2::new(
+        chunk.x as f32 * WORLD_CANVAS_SIZE.x,
+        chunk.y as f32 * WORLD_CANVAS_SIZE.y,
+    ) - *HALF_CELL;
     vec![
         Line(
-            0.5 * Vector2::new(-WORLD_UNIT_SIZE, WORLD_UNIT_SIZE)
-                + chunk.cast::<f32>().unwrap() * WORLD_UNIT_SIZE
-                - *HALF_CELL,
-            0.5 * Vector2::new(WORLD_UNIT_SIZE, WORLD_UNIT_SIZE)
-                + chunk.cast::<f32>().unwrap() * WORLD_UNIT_SIZE
-                - *HALF_CELL,
+            Vector2::new(-half.x, half.y) + center,
+            Vector2::new(half.x, half.y) + center,
             chunk_color,
         ),
         Line(
-            0.5 * Vector2::new(-WORLD_UNIT_SIZE, -WORLD_UNIT_SIZE)
-                + chunk.cast::<f32>().unwrap() * WORLD_UNIT_SIZE
-                - *HALF_CELL,
-            0.5 * Vector2::new(WORLD_UNIT_SIZE, -WORLD_UNIT_SIZE)
-                + chunk.cast::<f32>().unwrap() * WORLD_UNIT_SIZE
-                - *HALF_CELL,
+            Vector2::new(-half.x, -half.y) + center,
+            Vector2::new(half.x, -half.y) + center,
             chunk_color,
         ),
         Line(
-            0.5 * Vector2::new(-WORLD_UNIT_SIZE, -WORLD_UNIT_SIZE)
-                + chunk.cast::<f32>().unwrap() * WORLD_UNIT_SIZE
-                - *HALF_CELL,
-            0.5 * Vector2::new(-WORLD_UNIT_SIZE, WORLD_UNIT_SIZE)
-                + chunk.cast::<f32>().unwrap() * WORLD_UNIT_SIZE
-                - *HALF_CELL,
+            Vector2::new(-half.x, -half.y) + center,
+            Vector2::new(-half.x, half.y) + center,
             chunk_color,
         ),
         Line(
-            0.5 * Vector2::new(WORLD_UNIT_SIZE, -WORLD_UNIT_SIZE)
-                + chunk.cast::<f32>().unwrap() * WORLD_UNIT_SIZE
-                - *HALF_CELL,
-            0.5 * Vector2::new(WORLD_UNIT_SIZE, WORLD_UNIT_SIZE)
-                + chunk.cast::<f32>().unwrap() * WORLD_UNIT_SIZE
-                - *HALF_CELL,
+            Vector2::new(half.x, -half.y) + center,
+            Vector2::new(half.x, half.y) + center,
             chunk_color,
         ),
     ]
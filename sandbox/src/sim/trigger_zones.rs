@@ -0,0 +1,244 @@
+use anyhow::*;
+use cgmath::Vector2;
+use corrode::physics::PhysicsWorld;
+use hecs::{Entity, World};
+use rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    object::{collider_sensor_from_cuboid, invisible_sensor_object},
+    sim::Shape,
+};
+
+/// Actions a trigger zone can run when a dynamic object enters or exits it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum TriggerAction {
+    /// Paint matter (radius in cells) at the center of the zone
+    SpawnMatter { matter: u32, radius: f32 },
+    /// Replace cells of shape at the center of the zone with matter
+    SpawnShape { matter: u32, shape: Shape },
+    /// Delete the object that caused the trigger
+    DeleteObjects,
+    /// Play a named sound
+    PlaySound(String),
+    /// Toggle the zone's matter emitter on / off
+    ToggleEmitter,
+}
+
+impl TriggerAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TriggerAction::SpawnMatter {
+                ..
+            } => "Spawn matter",
+            TriggerAction::SpawnShape {
+                ..
+            } => "Spawn shape",
+            TriggerAction::DeleteObjects => "Delete objects",
+            TriggerAction::PlaySound(_) => "Play sound",
+            TriggerAction::ToggleEmitter => "Toggle emitter",
+        }
+    }
+}
+
+/// A rectangular sensor region in world space
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TriggerZone {
+    pub name: String,
+    pub pos: Vector2<f32>,
+    pub half_extents: Vector2<f32>,
+    pub on_enter: Vec<TriggerAction>,
+    pub on_exit: Vec<TriggerAction>,
+    /// Matter painted each step at the zone center while emitting
+    pub emitter_matter: u32,
+    pub is_emitting: bool,
+    #[serde(skip)]
+    pub entity: Option<Entity>,
+    #[serde(skip)]
+    inside: Vec<Entity>,
+}
+
+impl TriggerZone {
+    pub fn new(name: &str, pos: Vector2<f32>, emitter_matter: u32) -> TriggerZone {
+        TriggerZone {
+            name: name.to_string(),
+            pos,
+            half_extents: Vector2::new(0.5, 0.5),
+            on_enter: vec![],
+            on_exit: vec![],
+            emitter_matter,
+            is_emitting: false,
+            entity: None,
+            inside: vec![],
+        }
+    }
+}
+
+/// Event produced when a dynamic object enters (or exits) a zone
+#[derive(Debug, Copy, Clone)]
+pub struct TriggerEvent {
+    pub zone: usize,
+    pub entity: Entity,
+    pub entered: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct TriggerZones {
+    pub zones: Vec<TriggerZone>,
+}
+
+impl TriggerZones {
+    pub fn new() -> TriggerZones {
+        TriggerZones {
+            zones: vec![],
+        }
+    }
+
+    pub fn serialize(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn deserialize(data: &str) -> Result<TriggerZones> {
+        Ok(serde_json::from_str(data)?)
+    }
+
+    pub fn add_zone(
+        &mut self,
+        ecs_world: &mut World,
+        physics_world: &mut PhysicsWorld,
+        zone: TriggerZone,
+    ) -> Result<()> {
+        self.zones.push(zone);
+        self.spawn_zone(ecs_world, physics_world, self.zones.len() - 1)
+    }
+
+    pub fn remove_zone(
+        &mut self,
+        ecs_world: &mut World,
+        physics_world: &mut PhysicsWorld,
+        index: usize,
+    ) -> Result<()> {
+        self.despawn_zone(ecs_world, physics_world, index)?;
+        self.zones.remove(index);
+        Ok(())
+    }
+
+    /// Spawn sensors for all zones, e.g. after a map was loaded
+    pub fn spawn_all(
+        &mut self,
+        ecs_world: &mut World,
+        physics_world: &mut PhysicsWorld,
+    ) -> Result<()> {
+        for index in 0..self.zones.len() {
+            self.spawn_zone(ecs_world, physics_world, index)?;
+        }
+        Ok(())
+    }
+
+    /// Recreate zone's sensor after its position or size was edited
+    pub fn respawn_zone(
+        &mut self,
+        ecs_world: &mut World,
+        physics_world: &mut PhysicsWorld,
+        index: usize,
+    ) -> Result<()> {
+        self.despawn_zone(ecs_world, physics_world, index)?;
+        self.spawn_zone(ecs_world, physics_world, index)
+    }
+
+    fn spawn_zone(
+        &mut self,
+        ecs_world: &mut World,
+        physics_world: &mut PhysicsWorld,
+        index: usize,
+    ) -> Result<()> {
+        let zone = &mut self.zones[index];
+        let id = ecs_world.reserve_entity();
+        ecs_world.insert(
+            id,
+            invisible_sensor_object(id, &mut physics_world.physics, zone.pos, 0.0, vec![
+                collider_sensor_from_cuboid(zone.half_extents),
+            ]),
+        )?;
+        zone.entity = Some(id);
+        zone.inside.clear();
+        Ok(())
+    }
+
+    fn despawn_zone(
+        &mut self,
+        ecs_world: &mut World,
+        physics_world: &mut PhysicsWorld,
+        index: usize,
+    ) -> Result<()> {
+        if let Some(entity) = self.zones[index].entity.take() {
+            if let Some(rb) = ecs_world.get::<RigidBodyHandle>(entity).ok().map(|rb| *rb) {
+                physics_world.remove_physics(rb);
+            }
+            ecs_world.despawn(entity)?;
+        }
+        Ok(())
+    }
+
+    /// Compare dynamic bodies inside each zone against last step, returning enter & exit events
+    pub fn collect_events(
+        &mut self,
+        ecs_world: &World,
+        physics_world: &PhysicsWorld,
+    ) -> Vec<TriggerEvent> {
+        let mut events = vec![];
+        let bodies = &physics_world.physics.bodies;
+        let colliders = &physics_world.physics.colliders;
+        let narrow_phase = &physics_world.physics.narrow_phase;
+        for (zone_index, zone) in self.zones.iter_mut().enumerate() {
+            let entity = match zone.entity {
+                Some(e) => e,
+                None => continue,
+            };
+            let rb = match ecs_world.get::<RigidBodyHandle>(entity).ok() {
+                Some(rb) => *rb,
+                None => continue,
+            };
+            let mut inside_now = vec![];
+            for &zone_collider in bodies[rb].colliders() {
+                for (c1, c2, intersecting) in narrow_phase.intersections_with(zone_collider) {
+                    if !intersecting {
+                        continue;
+                    }
+                    let other = if c1 == zone_collider { c2 } else { c1 };
+                    if let Some(parent) = colliders[other].parent() {
+                        let body = &bodies[parent];
+                        if !body.is_dynamic() {
+                            continue;
+                        }
+                        if let Some(other_entity) = Entity::from_bits(body.user_data as u64) {
+                            if !inside_now.contains(&other_entity) {
+                                inside_now.push(other_entity);
+                            }
+                        }
+                    }
+                }
+            }
+            for &e in inside_now.iter() {
+                if !zone.inside.contains(&e) {
+                    events.push(TriggerEvent {
+                        zone: zone_index,
+                        entity: e,
+                        entered: true,
+                    });
+                }
+            }
+            for &e in zone.inside.iter() {
+                if !inside_now.contains(&e) {
+                    events.push(TriggerEvent {
+                        zone: zone_index,
+                        entity: e,
+                        entered: false,
+                    });
+                }
+            }
+            zone.inside = inside_now;
+        }
+        events
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::*;
+
+use crate::{data_dirs::DATA_DIRS, sim::Simulation};
+
+/// Per step statistics written to a csv file for offline analysis of performance or of matter
+/// balance. One row is written per simulation step
+pub struct StatsLog {
+    writer: Option<BufWriter<File>>,
+    /// Matter names the header was written with. A new file is started if they change
+    matter_names: Vec<String>,
+    step: u64,
+}
+
+impl StatsLog {
+    pub fn new() -> StatsLog {
+        StatsLog {
+            writer: None,
+            matter_names: vec![],
+            step: 0,
+        }
+    }
+
+    pub fn is_logging(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    /// Start logging to a new file in `stats` of data directory. Returns the file path
+    pub fn start(&mut self, simulation: &Simulation) -> Result<PathBuf> {
+        self.stop()?;
+        let dir = DATA_DIRS.data.join("stats");
+        fs::create_dir_all(&dir)?;
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let path = dir.join(format!("stats_{}.csv", timestamp));
+        let mut writer = BufWriter::new(
+            File::create(&path).with_context(|| format!("Failed to create {:?}", path))?,
+        );
+        self.matter_names = simulation
+            .matter_definitions
+            .definitions
+            .iter()
+            .map(|m| m.name.clone())
+            .collect();
+        let mut header = vec![
+            "step".to_string(),
+            "time_secs".to_string(),
+            "entities".to_string(),
+            "step_ms".to_string(),
+        ];
+        header.extend(
+            simulation
+                .latest_timings()
+                .iter()
+                .map(|(name, _)| format!("{}_ms", name)),
+        );
+        header.extend(self.matter_names.iter().cloned());
+        writeln!(writer, "{}", csv_row(&header))?;
+        self.writer = Some(writer);
+        self.step = 0;
+        info!("Logging stats to {:?}", path);
+        Ok(path)
+    }
+
+    pub fn stop(&mut self) -> Result<()> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write a row of latest step's stats. Returns path of a new file if one had to be started
+    /// because matter definitions changed
+    pub fn log_step(
+        &mut self,
+        simulation: &Simulation,
+        time_secs: f64,
+        num_entities: u32,
+        step_ms: f64,
+    ) -> Result<Option<PathBuf>> {
+        if self.writer.is_none() {
+            return Ok(None);
+        }
+        let matters_changed = simulation.matter_definitions.definitions.len()
+            != self.matter_names.len()
+            || simulation
+                .matter_definitions
+                .definitions
+                .iter()
+                .zip(self.matter_names.iter())
+                .any(|(m, name)| m.name != *name);
+        let new_path = if matters_changed {
+            Some(self.start(simulation)?)
+        } else {
+            None
+        };
+        let mut row = vec![
+            self.step.to_string(),
+            format!("{:.3}", time_secs),
+            num_entities.to_string(),
+            format!("{:.3}", step_ms),
+        ];
+        row.extend(
+            simulation
+                .latest_timings()
+                .iter()
+                .map(|(_, ms)| format!("{:.3}", ms)),
+        );
+        row.extend(simulation.matter_counts()?.iter().map(|c| c.to_string()));
+        writeln!(self.writer.as_mut().unwrap(), "{}", csv_row(&row))?;
+        self.step += 1;
+        Ok(new_path)
+    }
+}
+
+/// Join fields to a csv row, quoting ones that contain separators or quotes
+fn csv_row(fields: &[String]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            if field.contains(|c| c == ',' || c == '"' || c == '\n') {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_row_quotes_fields() {
+        let fields = ["step".to_string(), "Sand, wet".to_string(), "\"Goo\"".to_string()];
+        assert_eq!(csv_row(&fields), "step,\"Sand, wet\",\"\"\"Goo\"\"\"");
+    }
+}
@@ -0,0 +1,133 @@
+use anyhow::*;
+
+use crate::sim::Simulation;
+
+/// Event to stop running a paused simulation at, for debugging specific interactions
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StopCondition {
+    /// Cell counts of some matter changed, e.g. due to a reaction
+    Reaction,
+    ObjectDeforms,
+    Explosion,
+}
+
+pub const ALL_STOP_CONDITIONS: [StopCondition; 3] = [
+    StopCondition::Reaction,
+    StopCondition::ObjectDeforms,
+    StopCondition::Explosion,
+];
+
+impl StopCondition {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StopCondition::Reaction => "Any reaction",
+            StopCondition::ObjectDeforms => "Object deforms",
+            StopCondition::Explosion => "Explosion",
+        }
+    }
+}
+
+/// Steps taken while simulation is paused: a number of steps per step input, or steps until a
+/// condition is met
+pub struct StepControl {
+    /// Steps taken per step input
+    pub steps_per_input: u32,
+    pub stop_condition: StopCondition,
+    steps_left: u32,
+    running_until: Option<StopCondition>,
+    /// Simulation step count when conditions were last checked
+    checked_step: u64,
+    /// Matter counts after last checked step, reactions show up as changes in them
+    matter_counts: Vec<u64>,
+}
+
+impl StepControl {
+    pub fn new() -> StepControl {
+        StepControl {
+            steps_per_input: 1,
+            stop_condition: StopCondition::Reaction,
+            steps_left: 0,
+            running_until: None,
+            checked_step: 0,
+            matter_counts: vec![],
+        }
+    }
+
+    pub fn step_n(&mut self) {
+        self.steps_left += self.steps_per_input;
+    }
+
+    pub fn run_until(&mut self, simulation: &Simulation) -> Result<()> {
+        if self.stop_condition == StopCondition::Reaction {
+            self.matter_counts = simulation.matter_counts()?;
+        }
+        self.checked_step = simulation.step_count;
+        self.running_until = Some(self.stop_condition);
+        Ok(())
+    }
+
+    pub fn running_until(&self) -> Option<StopCondition> {
+        self.running_until
+    }
+
+    pub fn steps_left(&self) -> u32 {
+        self.steps_left
+    }
+
+    pub fn stop(&mut self) {
+        self.steps_left = 0;
+        self.running_until = None;
+    }
+
+    /// Whether a step should be taken now while paused
+    pub fn take_step(&mut self) -> bool {
+        if self.steps_left > 0 {
+            self.steps_left -= 1;
+            true
+        } else {
+            self.running_until.is_some()
+        }
+    }
+
+    /// Check stop condition once results of a new step have been read back. Returns the condition
+    /// if it was met and running stopped
+    pub fn check_stop(&mut self, simulation: &Simulation) -> Result<Option<StopCondition>> {
+        let condition = match self.running_until {
+            Some(condition) if simulation.step_count != self.checked_step => condition,
+            _ => return Ok(None),
+        };
+        self.checked_step = simulation.step_count;
+        let is_met = match condition {
+            StopCondition::Reaction => {
+                let counts = simulation.matter_counts()?;
+                let changed = counts != self.matter_counts;
+                self.matter_counts = counts;
+                changed
+            }
+            StopCondition::ObjectDeforms => !simulation.deformed_object_positions.is_empty(),
+            StopCondition::Explosion => !simulation.explosions.explosions.is_empty(),
+        };
+        if is_met {
+            self.stop();
+            return Ok(Some(condition));
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_n() {
+        let mut step_control = StepControl::new();
+        assert!(!step_control.take_step());
+        step_control.steps_per_input = 3;
+        step_control.step_n();
+        assert_eq!((0..5).filter(|_| step_control.take_step()).count(), 3);
+        step_control.step_n();
+        step_control.stop();
+        assert!(!step_control.take_step());
+    }
+}
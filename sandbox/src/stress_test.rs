@@ -0,0 +1,139 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::*;
+use cgmath::Vector2;
+use corrode::api::{remove_physics_entity, EngineApi};
+use hecs::Entity;
+use rand::Rng;
+
+use crate::{
+    app::InputAction,
+    matter::{MATTER_SAND, MATTER_WOOD},
+    sim::{canvas_pos_to_world_pos, Simulation},
+    utils::BitmapImage,
+    HALF_CANVAS, SIM_CANVAS_SIZE,
+};
+
+/// Objects spawned are kept this many cells away from simulated area's edges
+const SPAWN_MARGIN: i32 = 32;
+
+/// Load generator for reproducing performance ceilings: spawns random objects up to a population
+/// cap & paints random cells every frame, while performance is read out alongside
+pub struct StressTest {
+    pub objects_per_spawn: u32,
+    /// Stress test objects alive at most, spawning stops at the cap
+    pub max_objects: usize,
+    pub cells_per_frame: u32,
+    pub cell_matter: u32,
+    pub object_matter: u32,
+    pub is_painting: bool,
+    /// Objects spawned by the stress test that may still exist
+    spawned: Vec<Entity>,
+}
+
+impl StressTest {
+    pub fn new() -> StressTest {
+        StressTest {
+            objects_per_spawn: 50,
+            max_objects: 500,
+            cells_per_frame: 1000,
+            cell_matter: MATTER_SAND,
+            object_matter: MATTER_WOOD,
+            is_painting: false,
+            spawned: vec![],
+        }
+    }
+
+    /// Stress test objects alive
+    pub fn num_objects(&self) -> usize {
+        self.spawned.len()
+    }
+
+    /// Spawn objects of random images at random positions of simulated area, up to the cap.
+    /// Returns the number spawned
+    pub fn spawn_objects(
+        &mut self,
+        api: &mut EngineApi<InputAction>,
+        simulation: &mut Simulation,
+        images: &BTreeMap<String, Arc<BitmapImage>>,
+    ) -> Result<usize> {
+        let images = images.values().collect::<Vec<&Arc<BitmapImage>>>();
+        if images.is_empty() {
+            bail!("No object images to spawn");
+        }
+        let count = (self.objects_per_spawn as usize)
+            .min(self.max_objects.saturating_sub(self.spawned.len()));
+        let mut rng = rand::thread_rng();
+        for _ in 0..count {
+            let image = images[rng.gen_range(0..images.len())];
+            let canvas_pos = random_canvas_pos(simulation.camera_canvas_pos, SPAWN_MARGIN);
+            let entity = simulation.add_dynamic_pixel_object(
+                &mut api.ecs_world,
+                &mut api.physics_world,
+                image,
+                self.object_matter,
+                canvas_pos_to_world_pos(canvas_pos),
+                Vector2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)),
+                rng.gen_range(0.0..std::f32::consts::TAU),
+                0.0,
+            )?;
+            self.spawned.push(entity);
+        }
+        Ok(count)
+    }
+
+    /// Remove stress test objects that still exist
+    pub fn remove_objects(&mut self, api: &mut EngineApi<InputAction>) {
+        for entity in self.spawned.drain(..) {
+            if api.ecs_world.contains(entity) {
+                remove_physics_entity(&mut api.ecs_world, &mut api.physics_world, entity);
+            }
+        }
+    }
+
+    /// Forget destroyed objects & paint random cells while painting. Call once a frame after
+    /// the previous step has finished
+    pub fn update(
+        &mut self,
+        api: &EngineApi<InputAction>,
+        simulation: &mut Simulation,
+    ) -> Result<()> {
+        self.spawned.retain(|&entity| api.ecs_world.contains(entity));
+        if !self.is_painting {
+            return Ok(());
+        }
+        let cells = (0..self.cells_per_frame)
+            .map(|_| (random_canvas_pos(simulation.camera_canvas_pos, 0), self.cell_matter))
+            .collect::<Vec<(Vector2<i32>, u32)>>();
+        simulation.write_matter_cells(&cells)
+    }
+}
+
+/// Random canvas position inside simulated area, at least `margin` cells from its edges
+fn random_canvas_pos(camera_canvas_pos: Vector2<i32>, margin: i32) -> Vector2<i32> {
+    let mut rng = rand::thread_rng();
+    let size = SIM_CANVAS_SIZE.cast::<i32>().unwrap();
+    let margin = margin.min(size.x / 2 - 1).min(size.y / 2 - 1);
+    camera_canvas_pos - *HALF_CANVAS
+        + Vector2::new(
+            rng.gen_range(margin..size.x - margin),
+            rng.gen_range(margin..size.y - margin),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::is_inside_sim_canvas;
+
+    #[test]
+    fn test_random_canvas_pos_is_simulated() {
+        let camera_canvas_pos = Vector2::new(-300, 1200);
+        for margin in [0, SPAWN_MARGIN, 10_000] {
+            for _ in 0..100 {
+                let pos = random_canvas_pos(camera_canvas_pos, margin);
+                assert!(is_inside_sim_canvas(pos, camera_canvas_pos));
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::*;
+use egui::{Align2, Color32, CtxRef};
+
+/// Seconds a toast stays visible
+const TOAST_DURATION_SECS: f64 = 3.0;
+/// Errors and links stay longer, so there's time to read or click them
+const LONG_TOAST_DURATION_SECS: f64 = 8.0;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ToastKind {
+    Info,
+    Error,
+}
+
+/// Short lived notification shown in the corner
+struct Toast {
+    message: String,
+    kind: ToastKind,
+    link: Option<String>,
+    duration: f64,
+    /// Time (secs) the toast disappears at, set when first shown
+    until: Option<f64>,
+}
+
+/// Transient notifications giving feedback of editor and save / load actions
+pub struct Toasts {
+    toasts: Vec<Toast>,
+}
+
+impl Toasts {
+    pub fn new() -> Toasts {
+        Toasts {
+            toasts: vec![],
+        }
+    }
+
+    fn push(&mut self, message: String, kind: ToastKind, link: Option<String>, duration: f64) {
+        self.toasts.push(Toast {
+            message,
+            kind,
+            link,
+            duration,
+            until: None,
+        });
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        info!("{}", message);
+        self.push(message, ToastKind::Info, None, TOAST_DURATION_SECS);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        error!("{}", message);
+        self.push(message, ToastKind::Error, None, LONG_TOAST_DURATION_SECS);
+    }
+
+    pub fn link(&mut self, message: impl Into<String>, link: impl Into<String>) {
+        let message = message.into();
+        info!("{}", message);
+        self.push(
+            message,
+            ToastKind::Info,
+            Some(link.into()),
+            LONG_TOAST_DURATION_SECS,
+        );
+    }
+
+    /// Toast `success` if the action succeeded, otherwise its error
+    pub fn report<T>(&mut self, result: Result<T>, success: impl Into<String>) -> Option<T> {
+        match result {
+            std::result::Result::Ok(value) => {
+                self.info(success);
+                Some(value)
+            }
+            Err(e) => {
+                self.error(format!("{:#}", e));
+                None
+            }
+        }
+    }
+
+    /// Toast the error if the action failed
+    pub fn report_error<T>(&mut self, result: Result<T>) -> Option<T> {
+        match result {
+            std::result::Result::Ok(value) => Some(value),
+            Err(e) => {
+                self.error(format!("{:#}", e));
+                None
+            }
+        }
+    }
+
+    pub fn show(&mut self, ctx: &CtxRef, time_secs: f64) {
+        for toast in self.toasts.iter_mut() {
+            toast.until.get_or_insert(time_secs + toast.duration);
+        }
+        self.toasts
+            .retain(|toast| toast.until.map_or(true, |until| until > time_secs));
+        if self.toasts.is_empty() {
+            return;
+        }
+        egui::Area::new("Toasts")
+            .anchor(Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+            .show(ctx, |ui| {
+                for toast in self.toasts.iter() {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        match toast.kind {
+                            ToastKind::Info => ui.label(&toast.message),
+                            ToastKind::Error => ui.colored_label(Color32::RED, &toast.message),
+                        };
+                        if let Some(link) = &toast.link {
+                            ui.hyperlink(link);
+                        }
+                    });
+                }
+            });
+    }
+}
@@ -1,12 +1,24 @@
 use core::fmt;
-use std::{collections::BTreeSet, env::current_dir, fs, hash::Hash, path::PathBuf};
+use std::{
+    collections::BTreeSet,
+    fs,
+    hash::Hash,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
 
 use anyhow::*;
 use cgmath::Vector2;
 use corrode::{input_system::InputSystem, renderer::Camera2D};
-use image::{GenericImageView, RgbaImage};
+use image::{ColorType, ImageFormat, RgbaImage};
 
-use crate::{map_path, matter::MatterDefinitions, sim::world_pos_to_canvas_pos};
+use crate::{
+    data_dirs::DATA_DIRS, map_path, matter::MatterDefinitions, sim::world_pos_to_canvas_pos,
+};
 
 /// 32 bit bitmap image
 #[derive(Debug, Clone)]
@@ -24,6 +36,200 @@ impl BitmapImage {
             height,
         }
     }
+
+    pub fn from_rgba_image(image: RgbaImage) -> BitmapImage {
+        let (width, height) = image.dimensions();
+        BitmapImage {
+            data: image.into_raw(),
+            width,
+            height,
+        }
+    }
+
+    /// Image `factor` times smaller, each pixel averaging a block of pixels. Alpha weighs colors,
+    /// so empty cells don't darken the edges of matter
+    pub fn downscaled(&self, factor: u32) -> BitmapImage {
+        let factor = factor.max(1);
+        let mut image = BitmapImage::empty(
+            (self.width / factor).max(1),
+            (self.height / factor).max(1),
+        );
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let mut sum = [0u32; 4];
+                let mut count = 0;
+                for sy in (y * factor)..((y + 1) * factor).min(self.height) {
+                    for sx in (x * factor)..((x + 1) * factor).min(self.width) {
+                        let i = ((sy * self.width + sx) * 4) as usize;
+                        let alpha = self.data[i + 3] as u32;
+                        for (s, c) in sum.iter_mut().zip(&self.data[i..i + 3]) {
+                            *s += *c as u32 * alpha;
+                        }
+                        sum[3] += alpha;
+                        count += 1;
+                    }
+                }
+                let i = ((y * image.width + x) * 4) as usize;
+                let alpha_sum = sum[3];
+                if alpha_sum > 0 {
+                    for (c, s) in image.data[i..i + 3].iter_mut().zip(sum.iter()) {
+                        *c = (s / alpha_sum) as u8;
+                    }
+                    image.data[i + 3] = (alpha_sum / count) as u8;
+                }
+            }
+        }
+        image
+    }
+
+    /// Encode as png. Written to a temporary file first so a partially written image is never
+    /// read back
+    pub fn save_png(&self, path: &Path) -> Result<()> {
+        let tmp_path = path.with_extension("png.tmp");
+        image::save_buffer_with_format(
+            &tmp_path,
+            &self.data,
+            self.width,
+            self.height,
+            ColorType::Rgba8,
+            ImageFormat::Png,
+        )?;
+        fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+}
+
+/// Write file via a temporary file, so a partially written file is never read back
+pub fn write_file_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    fs::write(&tmp_path, data)?;
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+/// Write of one file in the background, given the path it writes to
+pub type FileWrite = Box<dyn FnOnce(&Path) -> Result<()> + Send>;
+
+/// Images written in the background as one unit, e.g. a map save
+struct ImageWriteJob {
+    id: usize,
+    name: String,
+    total: usize,
+    remaining: Arc<AtomicUsize>,
+    errors: Arc<Mutex<Vec<String>>>,
+}
+
+/// Result of a finished background image write job
+pub struct ImageWriteResult {
+    pub job: usize,
+    pub name: String,
+    pub errors: Vec<String>,
+}
+
+/// Encodes and writes png images & other map files on rayon's thread pool so saving doesn't
+/// freeze the app
+#[derive(Default)]
+pub struct ImageWriter {
+    jobs: Vec<ImageWriteJob>,
+    next_job_id: usize,
+}
+
+impl ImageWriter {
+    pub fn new() -> ImageWriter {
+        ImageWriter {
+            jobs: vec![],
+            next_job_id: 0,
+        }
+    }
+
+    /// Start a job of `total` writes, which are added with `spawn` as they become ready. Its
+    /// result is returned by `finished_jobs` once all are on disk
+    pub fn start(&mut self, name: &str, total: usize) -> usize {
+        let id = self.next_job_id;
+        self.next_job_id += 1;
+        self.jobs.push(ImageWriteJob {
+            id,
+            name: name.to_string(),
+            total,
+            remaining: Arc::new(AtomicUsize::new(total)),
+            errors: Arc::new(Mutex::new(vec![])),
+        });
+        id
+    }
+
+    /// Run `write` of `job` to `path` on rayon's thread pool
+    pub fn spawn(&self, job: usize, path: PathBuf, write: FileWrite) {
+        let job = match self.jobs.iter().find(|j| j.id == job) {
+            Some(job) => job,
+            None => return,
+        };
+        let remaining = job.remaining.clone();
+        let errors = job.errors.clone();
+        rayon::spawn(move || {
+            if let Err(e) = write(&path) {
+                errors.lock().unwrap().push(format!("{:?}: {}", path, e));
+            }
+            remaining.fetch_sub(1, Ordering::AcqRel);
+        });
+    }
+
+    /// Start writing `images` & raw `files` as one job, see `start`
+    pub fn write(
+        &mut self,
+        name: &str,
+        images: Vec<(PathBuf, BitmapImage)>,
+        files: Vec<(PathBuf, Vec<u8>)>,
+    ) {
+        let job = self.start(name, images.len() + files.len());
+        for (path, image) in images {
+            self.spawn(job, path, Box::new(move |path| image.save_png(path)));
+        }
+        for (path, data) in files {
+            self.spawn(job, path, Box::new(move |path| write_file_atomic(path, &data)));
+        }
+    }
+
+    pub fn is_writing(&self) -> bool {
+        self.jobs
+            .iter()
+            .any(|job| job.remaining.load(Ordering::Acquire) > 0)
+    }
+
+    /// Block until all pending images are on disk, e.g. before loading a map
+    pub fn wait(&self) {
+        while self.is_writing() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Name, written & total files of jobs still writing
+    pub fn progress(&self) -> Vec<(String, usize, usize)> {
+        self.jobs
+            .iter()
+            .filter_map(|job| {
+                let remaining = job.remaining.load(Ordering::Acquire);
+                (remaining > 0).then(|| (job.name.clone(), job.total - remaining, job.total))
+            })
+            .collect()
+    }
+
+    /// Remove and return jobs whose images have all been written
+    pub fn finished_jobs(&mut self) -> Vec<ImageWriteResult> {
+        let mut finished = vec![];
+        self.jobs.retain(|job| {
+            if job.remaining.load(Ordering::Acquire) > 0 {
+                return true;
+            }
+            finished.push(ImageWriteResult {
+                job: job.id,
+                name: job.name.clone(),
+                errors: std::mem::take(&mut *job.errors.lock().unwrap()),
+            });
+            false
+        });
+        finished
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -81,90 +287,209 @@ pub fn rotate_radians(v: Vector2<f32>, rad: f32) -> Vector2<f32> {
     Vector2::new(ca * v.x - sa * v.y, sa * v.x + ca * v.y)
 }
 
-/// Loads an image as rgba array from file_bytes (whole file in memory as bytes)`
-pub fn load_image_from_file_bytes(file_bytes: &[u8]) -> BitmapImage {
-    let img = image::load_from_memory(file_bytes).expect("Failed to load image from bytes");
-    let rgba = if let Some(rgba) = img.as_rgba8() {
-        rgba.to_owned().to_vec()
-    } else {
-        // Convert rgb to rgba
-        let rgb = img.as_rgb8().unwrap().to_owned();
-        let mut raw_data = vec![];
-        for val in rgb.chunks(3) {
-            raw_data.push(val[0]);
-            raw_data.push(val[1]);
-            raw_data.push(val[2]);
-            raw_data.push(255);
-        }
-        let new_rgba = RgbaImage::from_raw(rgb.width(), rgb.height(), raw_data).unwrap();
-        new_rgba.to_vec()
-    };
-    let (width, height) = img.dimensions();
-    BitmapImage {
-        data: rgba,
-        width,
-        height,
+/// Random value of a cell in 0.0 - 1.0, same for same cell & seed
+pub fn cell_hash(x: i32, y: i32, seed: u32) -> f32 {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343) ^ (y as u32).wrapping_mul(0xd816_3841);
+    h = (h ^ seed ^ (h >> 13)).wrapping_mul(0x5bd1_e995);
+    h ^= h >> 15;
+    (h >> 8) as f32 / (1 << 24) as f32
+}
+
+/// Smoothly interpolated `cell_hash` lattice, 0.0 - 1.0
+pub fn value_noise(x: f32, y: f32, seed: u32) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (tx, ty) = (smooth(x - x0), smooth(y - y0));
+    let (x0, y0) = (x0 as i32, y0 as i32);
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let bottom = lerp(cell_hash(x0, y0, seed), cell_hash(x0 + 1, y0, seed), tx);
+    let top = lerp(cell_hash(x0, y0 + 1, seed), cell_hash(x0 + 1, y0 + 1, seed), tx);
+    lerp(bottom, top, ty)
+}
+
+/// Loads an image as rgba array from file_bytes (whole file in memory as bytes). Rgb & rgba images
+/// of 8 or 16 bits per channel are supported, 16 bit channels are scaled down to 8 bits
+pub fn load_image_from_file_bytes(file_bytes: &[u8]) -> Result<BitmapImage> {
+    let img = image::load_from_memory(file_bytes).context("Failed to load image from bytes")?;
+    match img.color() {
+        ColorType::Rgb8 | ColorType::Rgba8 | ColorType::Rgb16 | ColorType::Rgba16 => (),
+        color => bail!("Unsupported image format {:?}, expected rgb or rgba", color),
     }
+    let rgba = img.to_rgba8();
+    Ok(BitmapImage {
+        width: rgba.width(),
+        height: rgba.height(),
+        data: rgba.into_raw(),
+    })
 }
 
 pub fn load_bitmap_image_from_path(path: PathBuf) -> Result<BitmapImage> {
     let contents = fs::read(path)?;
-    let map_img = load_image_from_file_bytes(&contents);
-    Ok(map_img)
+    load_image_from_file_bytes(&contents)
 }
 
-pub fn u8_rgba_to_u32_rgba(r: u8, g: u8, b: u8, a: u8) -> u32 {
-    ((r as u32) << 24) | ((g as u32) << 16) | ((b as u32) << 8) | (a as u32)
-}
+/// 8 bit rgba color. Packed to u32 in either channel order: matter colors are RGBA, red in the
+/// highest byte like `#rrggbbaa`, and gpu buffers are ABGR, red in the lowest byte, so shaders
+/// unpack them with unpackUnorm4x8
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Color32(pub [u8; 4]);
 
-pub fn u32_rgba_to_u32_abgr(num: u32) -> u32 {
-    let r = num & 255;
-    let g = (num >> 8) & 255;
-    let b = (num >> 16) & 255;
-    let a = (num >> 24) & 255;
-    (r << 24) | (g << 16) | (b << 8) | a
-}
+impl Color32 {
+    pub const fn from_rgba(r: u8, g: u8, b: u8, a: u8) -> Color32 {
+        Color32([r, g, b, a])
+    }
 
-pub fn u32_rgba_to_u8_rgba(num: u32) -> [u8; 4] {
-    let r = num & 255;
-    let g = (num >> 8) & 255;
-    let b = (num >> 16) & 255;
-    let a = (num >> 24) & 255;
-    [a as u8, b as u8, g as u8, r as u8]
-}
+    /// Color of the first 4 bytes of rgba image data
+    pub fn from_rgba_bytes(bytes: &[u8]) -> Color32 {
+        Color32([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
 
-pub fn u32_rgba_to_f32_rgba(num: u32) -> [f32; 4] {
-    let color_u8 = u32_rgba_to_u8_rgba(num);
-    [
-        color_u8[0] as f32 / 255.0,
-        color_u8[1] as f32 / 255.0,
-        color_u8[2] as f32 / 255.0,
-        color_u8[3] as f32 / 255.0,
-    ]
+    pub const fn from_u32_rgba(num: u32) -> Color32 {
+        Color32(num.to_be_bytes())
+    }
+
+    #[allow(unused)]
+    pub const fn from_u32_abgr(num: u32) -> Color32 {
+        Color32(num.to_le_bytes())
+    }
+
+    pub const fn to_u32_rgba(self) -> u32 {
+        u32::from_be_bytes(self.0)
+    }
+
+    pub const fn to_u32_abgr(self) -> u32 {
+        u32::from_le_bytes(self.0)
+    }
+
+    pub const fn to_rgba(self) -> [u8; 4] {
+        self.0
+    }
+
+    pub fn to_f32_rgba(self) -> [f32; 4] {
+        self.0.map(|channel| channel as f32 / 255.0)
+    }
 }
 
 pub fn get_map_directory_names() -> Result<BTreeSet<String>> {
     let mut file_names = BTreeSet::new();
     let dir_path = map_path();
-    fs::create_dir_all(dir_path.clone()).unwrap();
-    for file in fs::read_dir(dir_path.clone()).unwrap() {
-        let file = file.unwrap().file_name();
-        let file_name = file.to_str().unwrap();
-        let file_path = dir_path.join(file_name);
-        if std::fs::metadata(file_path).unwrap().is_dir() {
+    fs::create_dir_all(dir_path.clone())?;
+    for file in fs::read_dir(dir_path.clone())? {
+        let file = file?.file_name();
+        let file_name = file.to_string_lossy();
+        let file_path = dir_path.join(file_name.as_ref());
+        // Hidden directories are map saves being written or replaced
+        if file_path.is_dir() && !file_name.starts_with('.') {
             file_names.insert(file_name.to_string());
         }
     }
     Ok(file_names)
 }
 
-pub fn read_matter_definitions_file() -> Option<MatterDefinitions> {
-    let matter_definitions_path = current_dir()
-        .unwrap()
-        .join("assets/matter_definitions.json");
-    if let std::result::Result::Ok(data) = fs::read_to_string(matter_definitions_path) {
-        Some(MatterDefinitions::deserialize(&data))
+/// File `name.ext` in `dir`. Names are typed by the user, so ones that could lead outside `dir`
+/// are rejected
+pub fn data_file_path(dir: &Path, name: &str, ext: &str) -> Result<PathBuf> {
+    if name.trim().is_empty() || name.contains(|c| c == '/' || c == '\\' || c == '.') {
+        bail!("Invalid name {:?}", name);
+    }
+    Ok(dir.join(format!("{}.{}", name, ext)))
+}
+
+/// Matter definitions file, in config directory
+pub fn matter_definitions_path() -> PathBuf {
+    DATA_DIRS.config.join("matter_definitions.json")
+}
+
+/// Matter definitions saved by the user, if any. Errors if the file exists but is broken
+pub fn read_matter_definitions_file() -> Result<Option<MatterDefinitions>> {
+    let matter_definitions_path = matter_definitions_path();
+    if let std::result::Result::Ok(data) = fs::read_to_string(&matter_definitions_path) {
+        let matter_definitions = MatterDefinitions::deserialize(&data)
+            .with_context(|| format!("Invalid {:?}", matter_definitions_path))?;
+        Ok(Some(matter_definitions))
     } else {
-        None
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, ImageBuffer, ImageOutputFormat, Rgba};
+
+    use super::*;
+
+    #[test]
+    fn test_downscaled() {
+        // Left half red, right half empty
+        let mut image = BitmapImage::empty(4, 2);
+        for y in 0..2 {
+            for x in 0..2 {
+                let i = (y * 4 + x) * 4;
+                image.data[i..i + 4].copy_from_slice(&[255, 0, 0, 255]);
+            }
+        }
+        let preview = image.downscaled(2);
+        assert_eq!((preview.width, preview.height), (2, 1));
+        assert_eq!(preview.data, vec![255, 0, 0, 255, 0, 0, 0, 0]);
+        // Half empty block keeps its color, only alpha is halved
+        let preview = image.downscaled(4);
+        assert_eq!(preview.data, vec![255, 0, 0, 127]);
+    }
+
+    #[test]
+    fn test_color32_channel_order() {
+        let color = Color32::from_rgba(0x11, 0x22, 0x33, 0x44);
+        assert_eq!(color.to_u32_rgba(), 0x11223344);
+        assert_eq!(color.to_u32_abgr(), 0x44332211);
+        assert_eq!(Color32::from_u32_rgba(0x11223344), color);
+        assert_eq!(Color32::from_u32_abgr(0x44332211), color);
+        assert_eq!(Color32::from_rgba_bytes(&[0x11, 0x22, 0x33, 0x44, 0x55]), color);
+    }
+
+    #[test]
+    fn test_data_file_path_stays_in_dir() {
+        let dir = Path::new("data");
+        assert_eq!(
+            data_file_path(dir, "a b", "json").unwrap(),
+            dir.join("a b.json")
+        );
+        for name in ["", " ", "..", "../a", "a/b", "a\\b", "a.json"] {
+            assert!(data_file_path(dir, name, "json").is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn test_load_16_bit_png() {
+        let mut image = ImageBuffer::<Rgba<u16>, Vec<u16>>::new(2, 1);
+        image.put_pixel(0, 0, Rgba([u16::MAX, 0, 0, u16::MAX]));
+        image.put_pixel(1, 0, Rgba([0, 0x8080, 0, u16::MAX]));
+        let mut bytes = vec![];
+        DynamicImage::ImageRgba16(image)
+            .write_to(&mut bytes, ImageOutputFormat::Png)
+            .unwrap();
+        let loaded = load_image_from_file_bytes(&bytes).unwrap();
+        assert_eq!((loaded.width, loaded.height), (2, 1));
+        assert_eq!(loaded.data, vec![255, 0, 0, 255, 0, 128, 0, 255]);
+    }
+
+    #[test]
+    fn test_image_writer_late_writes() {
+        let dir = std::env::temp_dir().join(format!("image_writer_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut writer = ImageWriter::new();
+        let job = writer.start("map", 2);
+        writer.spawn(job, dir.join("a"), Box::new(|path| write_file_atomic(path, &[1])));
+        while writer.progress()[0].1 < 1 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        // Job isn't finished until its last write has been added & written
+        assert_eq!(writer.progress(), vec![("map".to_string(), 1, 2)]);
+        assert!(writer.finished_jobs().is_empty());
+        writer.spawn(job, dir.join("b"), Box::new(|_| bail!("Broken")));
+        writer.wait();
+        let finished = writer.finished_jobs();
+        assert_eq!(finished[0].errors.len(), 1);
+        assert_eq!(fs::read(dir.join("a")).unwrap(), vec![1]);
+        fs::remove_dir_all(dir).unwrap();
     }
 }
@@ -0,0 +1,95 @@
+use std::sync::mpsc::{channel, Receiver};
+
+use anyhow::*;
+use serde::Deserialize;
+
+/// Version of this build, shown in the info window for bug reports
+pub const BUILD_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Static file uploaded with each release. Only this file is requested, nothing is sent about
+/// the user or their machine
+const LATEST_RELEASE_URL: &str =
+    "https://github.com/hakolao/sandbox/releases/latest/download/latest.json";
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ReleaseInfo {
+    pub version: String,
+    pub url: String,
+}
+
+/// Opt-in check for a newer release, run once in the background
+pub struct VersionCheck {
+    is_started: bool,
+    receiver: Option<Receiver<Result<ReleaseInfo>>>,
+}
+
+impl VersionCheck {
+    pub fn new() -> VersionCheck {
+        VersionCheck {
+            is_started: false,
+            receiver: None,
+        }
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.is_started
+    }
+
+    pub fn start(&mut self) {
+        self.is_started = true;
+        let (sender, receiver) = channel();
+        std::thread::spawn(move || {
+            let _ = sender.send(fetch_latest_release());
+        });
+        self.receiver = Some(receiver);
+    }
+
+    /// Returns latest release once the check has finished, if it's newer than this build
+    pub fn poll(&mut self) -> Option<ReleaseInfo> {
+        let result = self.receiver.as_ref()?.try_recv().ok()?;
+        self.receiver = None;
+        let release = match result {
+            std::result::Result::Ok(release) => release,
+            Err(e) => {
+                warn!("Version check failed: {}", e);
+                return None;
+            }
+        };
+        if is_newer_version(&release.version, BUILD_VERSION) {
+            info!("New version {} available", release.version);
+            Some(release)
+        } else {
+            None
+        }
+    }
+}
+
+fn fetch_latest_release() -> Result<ReleaseInfo> {
+    let body = ureq::get(LATEST_RELEASE_URL).call()?.into_string()?;
+    Ok(serde_json::from_str(&body)?)
+}
+
+/// Compare dot separated numeric versions, e.g. "0.2.0" > "0.1.9"
+pub fn is_newer_version(latest: &str, current: &str) -> bool {
+    let parse = |version: &str| {
+        version
+            .trim_start_matches('v')
+            .split('.')
+            .map(|part| part.parse::<u32>().unwrap_or(0))
+            .collect::<Vec<u32>>()
+    };
+    parse(latest) > parse(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_newer_version() {
+        assert!(is_newer_version("0.2.0", "0.1.9"));
+        assert!(is_newer_version("v0.1.10", "0.1.9"));
+        assert!(!is_newer_version("0.1.0", "0.1.0"));
+        assert!(!is_newer_version("0.0.9", "0.1.0"));
+    }
+}
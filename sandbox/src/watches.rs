@@ -0,0 +1,103 @@
+use anyhow::*;
+use cgmath::Vector2;
+use corrode::api::{physics_entity_at_pos, EngineApi};
+use hecs::Entity;
+
+use crate::{
+    app::InputAction,
+    object::{Angle, AngularVelocity, LinearVelocity, Position},
+    sim::{canvas_pos_to_world_pos, Simulation},
+};
+
+/// Canvas cell or entity pinned to the watch window
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Watch {
+    Cell(Vector2<i32>),
+    Entity(Entity),
+}
+
+/// Live value of a watch
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WatchValue {
+    /// Matter of cell, None outside simulated area
+    Cell(Option<u32>),
+    Entity {
+        pos: Vector2<f32>,
+        vel: Vector2<f32>,
+        angle: f32,
+        ang_vel: f32,
+    },
+    /// Entity was destroyed, e.g. deformed into new objects
+    Removed,
+}
+
+/// Cells & entities whose values are shown each frame without hovering them
+pub struct Watches {
+    pub watches: Vec<Watch>,
+    /// Values of watches, updated each frame
+    pub values: Vec<WatchValue>,
+}
+
+impl Watches {
+    pub fn new() -> Watches {
+        Watches {
+            watches: vec![],
+            values: vec![],
+        }
+    }
+
+    /// Pin dynamic object at canvas position, or the cell if there's none
+    pub fn pin_at(&mut self, api: &EngineApi<InputAction>, canvas_pos: Vector2<i32>) {
+        let world_pos = canvas_pos_to_world_pos(canvas_pos);
+        let watch = match physics_entity_at_pos(&api.physics_world, world_pos) {
+            Some((rb, entity)) if rb.is_dynamic() => Watch::Entity(entity),
+            _ => Watch::Cell(canvas_pos),
+        };
+        if !self.watches.contains(&watch) {
+            self.watches.push(watch);
+        }
+    }
+
+    pub fn update(&mut self, api: &EngineApi<InputAction>, simulation: &Simulation) -> Result<()> {
+        self.values.clear();
+        if self.watches.is_empty() {
+            return Ok(());
+        }
+        let cells = self
+            .watches
+            .iter()
+            .filter_map(|watch| match watch {
+                Watch::Cell(pos) => Some(*pos),
+                Watch::Entity(_) => None,
+            })
+            .collect::<Vec<Vector2<i32>>>();
+        let mut cell_matters = simulation.query_matters(&cells)?.into_iter();
+        for watch in self.watches.iter() {
+            let value = match watch {
+                Watch::Cell(_) => WatchValue::Cell(cell_matters.next().unwrap()),
+                Watch::Entity(entity) => api
+                    .ecs_world
+                    .query_one::<(&Position, &LinearVelocity, &Angle, &AngularVelocity)>(*entity)
+                    .ok()
+                    .and_then(|mut query| {
+                        query.get().map(|(pos, vel, angle, ang_vel)| WatchValue::Entity {
+                            pos: pos.0,
+                            vel: vel.0,
+                            angle: angle.0,
+                            ang_vel: ang_vel.0,
+                        })
+                    })
+                    .unwrap_or(WatchValue::Removed),
+            };
+            self.values.push(value);
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        self.watches.remove(index);
+        if index < self.values.len() {
+            self.values.remove(index);
+        }
+    }
+}